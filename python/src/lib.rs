@@ -0,0 +1,172 @@
+//! Python module `kira_nuclearqc`: scores an in-memory count matrix, such as
+//! `adata.X` from Scanpy, without going through MTX files.
+
+use kira_nuclearqc::model::thresholds::{ConfidenceModel, NamedProfile};
+use kira_nuclearqc::panels::PanelScoreMode;
+use kira_nuclearqc::pipeline::stage2_normalize::NormalizeMode;
+use kira_nuclearqc::pipeline::stage7_report::{format_flags, regime_name};
+use kira_nuclearqc::simd::SumMode;
+use kira_nuclearqc::{CsrCounts, RunConfig, RunError, run_counts};
+use numpy::{AllowTypeChange, IntoPyArray, PyArrayLike1};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+/// Scores cells of a cells × genes CSR matrix (`indptr`, `indices`, `data`
+/// of a `scipy.sparse.csr_matrix`) of raw counts.
+///
+/// `options` takes the CLI settings under their flag names:
+/// `strict_nuclear`, `profile`, `confidence_model`, `normalize`, `normalize_mode`,
+/// `pearson_theta`, `panel_score`, `sum_mode`, `cea_ribo_adjust`,
+/// `key_panels`, `only_panels`, `disable_panels` and `panels_path`. Returns
+/// a dict of per-cell numpy arrays in barcode order, keyed by the matching
+/// `nuclearqc.tsv` columns.
+#[pyfunction]
+#[pyo3(signature = (indptr, indices, data, gene_symbols, barcodes, options = None))]
+fn score_counts<'py>(
+    py: Python<'py>,
+    indptr: PyArrayLike1<'py, i64, AllowTypeChange>,
+    indices: PyArrayLike1<'py, i64, AllowTypeChange>,
+    data: PyArrayLike1<'py, f64, AllowTypeChange>,
+    gene_symbols: Vec<String>,
+    barcodes: Vec<String>,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let config = config_from_options(options)?;
+    let counts = CsrCounts {
+        indptr: indptr.as_slice()?,
+        indices: indices.as_slice()?,
+        data: data.as_slice()?,
+        gene_symbols: &gene_symbols,
+        barcodes: &barcodes,
+    };
+    let outputs = run_counts(config, counts).map_err(run_error)?;
+
+    let result = PyDict::new(py);
+    result.set_item("barcode", PyList::new(py, &outputs.barcodes)?)?;
+    let axes = outputs.axes;
+    for (name, values) in [
+        ("a1_tbi", axes.tbi),
+        ("a2_rci", axes.rci),
+        ("a3_pds", axes.pds),
+        ("a4_trs", axes.trs),
+        ("a5_nsai", axes.nsai),
+        ("a6_iaa", axes.iaa),
+        ("a7_dfa", axes.dfa),
+        ("a8_cea", axes.cea),
+        ("rss", axes.rss),
+        ("drbi", axes.drbi),
+        ("cci", axes.cci),
+        ("trci", axes.trci),
+    ] {
+        result.set_item(name, values.into_pyarray(py))?;
+    }
+    let scores = outputs.scores;
+    result.set_item("c1_nps", scores.nps.into_pyarray(py))?;
+    result.set_item("c2_ci", scores.ci.into_pyarray(py))?;
+    result.set_item("c3_rls", scores.rls.into_pyarray(py))?;
+    result.set_item("confidence", scores.confidence.into_pyarray(py))?;
+    let regimes = outputs
+        .classifications
+        .iter()
+        .map(|c| regime_name(c.regime))
+        .collect::<Vec<_>>();
+    let flags = outputs
+        .classifications
+        .iter()
+        .map(|c| format_flags(&c.flags))
+        .collect::<Vec<_>>();
+    result.set_item("regime", PyList::new(py, regimes)?)?;
+    result.set_item("flags", PyList::new(py, flags)?)?;
+    Ok(result)
+}
+
+fn config_from_options(options: Option<&Bound<'_, PyDict>>) -> PyResult<RunConfig> {
+    let mut config = RunConfig::new("", "");
+    config.write_reports = false;
+    let mut normalize = None;
+    let mut normalize_mode = None;
+    if let Some(options) = options {
+        for (key, value) in options.iter() {
+            let key = key.extract::<String>()?;
+            match key.as_str() {
+                "strict_nuclear" => {
+                    if value.extract::<bool>()? {
+                        config.profile = NamedProfile::Default;
+                    }
+                }
+                "profile" => {
+                    let profile = value.extract::<String>()?;
+                    config.profile = NamedProfile::parse(&profile).ok_or_else(|| {
+                        PyValueError::new_err("invalid profile (use default|immune|snrna|tumor)")
+                    })?;
+                }
+                "confidence_model" => {
+                    let model = value.extract::<String>()?;
+                    config.confidence_model =
+                        Some(ConfidenceModel::parse(&model).ok_or_else(|| {
+                            PyValueError::new_err(
+                                "invalid confidence_model (use additive|multiplicative|logistic)",
+                            )
+                        })?);
+                }
+                "normalize" => normalize = Some(value.extract::<bool>()?),
+                "normalize_mode" => {
+                    let mode = value.extract::<String>()?;
+                    normalize_mode = Some(NormalizeMode::parse(&mode).ok_or_else(|| {
+                        PyValueError::new_err("invalid normalize_mode (use log|pearson)")
+                    })?);
+                }
+                "pearson_theta" => {
+                    config.pearson_theta = Some(value.extract::<f32>()?)
+                        .filter(|t| t.is_finite() && *t > 0.0)
+                        .ok_or_else(|| {
+                            PyValueError::new_err("pearson_theta must be a positive number")
+                        })?;
+                }
+                "panel_score" => {
+                    config.panel_score = match value.extract::<String>()?.as_str() {
+                        "sum" => PanelScoreMode::Sum,
+                        "mean" => PanelScoreMode::Mean,
+                        _ => {
+                            return Err(PyValueError::new_err(
+                                "invalid panel_score (use mean|sum)",
+                            ));
+                        }
+                    };
+                }
+                "sum_mode" => {
+                    config.sum_mode =
+                        SumMode::parse(&value.extract::<String>()?).ok_or_else(|| {
+                            PyValueError::new_err("invalid sum_mode (use blocked|pairwise)")
+                        })?;
+                }
+                "cea_ribo_adjust" => config.cea_ribo_adjust = value.extract::<bool>()?,
+                "key_panels" => config.key_panels = Some(value.extract::<Vec<String>>()?),
+                "only_panels" => config.only_panels = Some(value.extract::<Vec<String>>()?),
+                "disable_panels" => config.disable_panels = value.extract::<Vec<String>>()?,
+                "panels_path" => config.panels_path = Some(value.extract::<String>()?.into()),
+                _ => return Err(PyValueError::new_err(format!("unknown option: {key}"))),
+            }
+        }
+    }
+    config
+        .resolve_normalization(normalize, normalize_mode)
+        .map_err(PyValueError::new_err)?;
+    config.validate().map_err(PyValueError::new_err)?;
+    Ok(config)
+}
+
+fn run_error(err: RunError) -> PyErr {
+    match err {
+        RunError::Input(_) | RunError::Config(_) => PyValueError::new_err(err.to_string()),
+        _ => PyRuntimeError::new_err(err.to_string()),
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "kira_nuclearqc")]
+fn kira_nuclearqc_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(score_counts, m)?)?;
+    Ok(())
+}
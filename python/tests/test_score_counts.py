@@ -0,0 +1,88 @@
+"""score_counts must agree with the CLI run on the same counts."""
+
+import csv
+import os
+import subprocess
+from pathlib import Path
+
+import numpy as np
+import pytest
+
+import kira_nuclearqc
+
+GENES = [
+    "ACTB", "GAPDH", "MKI67", "TOP2A", "PCNA", "MCM2", "TYMS", "CDK1", "BIRC5", "CCNB2",
+    "JUN", "FOS", "MYC", "EGR1", "TP53", "CDKN1A", "MT-CO1", "MT-ND1", "RPL3", "RPS6",
+]
+N_CELLS = 6
+REPO = Path(__file__).resolve().parents[2]
+CLI = Path(os.environ.get("KIRA_NUCLEARQC_BIN", REPO / "target" / "release" / "kira-nuclearqc"))
+FLOAT_COLUMNS = [
+    "confidence", "a1_tbi", "a2_rci", "a3_pds", "a4_trs", "a5_nsai", "a6_iaa", "a7_dfa",
+    "a8_cea", "c1_nps", "c2_ci", "c3_rls", "rss", "drbi", "cci", "trci",
+]
+
+
+def toy_counts():
+    dense = np.array(
+        [
+            [(gene * 7 + cell * 3) % 5 * (cell + 1) for gene in range(len(GENES))]
+            for cell in range(N_CELLS)
+        ],
+        dtype=np.int64,
+    )
+    barcodes = [f"CELL{cell}-1" for cell in range(N_CELLS)]
+    return dense, barcodes
+
+
+def write_mtx(dense, barcodes, path):
+    path.mkdir(parents=True)
+    with open(path / "features.tsv", "w") as f:
+        for i, gene in enumerate(GENES):
+            f.write(f"G{i}\t{gene}\tGene Expression\n")
+    with open(path / "barcodes.tsv", "w") as f:
+        f.writelines(f"{b}\n" for b in barcodes)
+    cells, genes = np.nonzero(dense)
+    with open(path / "matrix.mtx", "w") as f:
+        f.write("%%MatrixMarket matrix coordinate integer general\n")
+        f.write(f"{len(GENES)} {N_CELLS} {len(cells)}\n")
+        for cell, gene in zip(cells, genes):
+            f.write(f"{gene + 1} {cell + 1} {dense[cell, gene]}\n")
+
+
+def to_csr(dense):
+    indptr, indices, data = [0], [], []
+    for row in dense:
+        nz = np.nonzero(row)[0]
+        indices.extend(nz)
+        data.extend(row[nz])
+        indptr.append(len(indices))
+    return np.array(indptr), np.array(indices), np.array(data, dtype=np.float64)
+
+
+@pytest.mark.skipif(not CLI.exists(), reason="build the CLI with cargo build --release")
+@pytest.mark.parametrize("flags,options", [([], {}), (["--normalize-mode", "pearson"], {"normalize_mode": "pearson"})])
+def test_score_counts_matches_cli(tmp_path, flags, options):
+    dense, barcodes = toy_counts()
+    write_mtx(dense, barcodes, tmp_path / "input")
+    subprocess.run(
+        [str(CLI), "run", "--input", str(tmp_path / "input"), "--out", str(tmp_path / "out"), *flags],
+        check=True,
+    )
+    with open(tmp_path / "out" / "nuclearqc.tsv") as f:
+        expected = list(csv.DictReader(f, delimiter="\t"))
+
+    result = kira_nuclearqc.score_counts(*to_csr(dense), GENES, barcodes, options)
+
+    assert result["barcode"] == barcodes
+    assert result["regime"] == [row["regime"] for row in expected]
+    assert result["flags"] == [row["flags"] for row in expected]
+    for column in FLOAT_COLUMNS:
+        cli = np.array([float(row[column]) for row in expected])
+        np.testing.assert_allclose(result[column], cli, atol=1e-6, err_msg=column)
+
+
+def test_unknown_option_is_rejected():
+    dense, barcodes = toy_counts()
+    with pytest.raises(ValueError, match="unknown option"):
+        kira_nuclearqc.score_counts(*to_csr(dense), GENES, barcodes, {"bogus": 1})
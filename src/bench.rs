@@ -0,0 +1,205 @@
+//! Seeded synthetic datasets shaped like 10x counts, for the `bench` command
+//! and for test fixtures.
+//!
+//! Genes are the builtin panel genes first, then mitochondrial and ribosomal
+//! genes, then filler symbols; each cell draws its genes with a skew towards
+//! the front of that list so the panels are detected as in real data. The
+//! same [`SyntheticSpec`] gives the same counts on every platform.
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::panels::defs::builtin_panels;
+use crate::run::{CsrCounts, DEFAULT_SEED};
+
+/// Size and seed of a synthetic dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntheticSpec {
+    pub n_cells: usize,
+    pub n_genes: usize,
+    /// Mean stored counts per cell; cells vary between half and one and a
+    /// half times this, capped at `n_genes`.
+    pub nnz_per_cell: usize,
+    pub seed: u64,
+}
+
+impl Default for SyntheticSpec {
+    fn default() -> Self {
+        Self {
+            n_cells: 5_000,
+            n_genes: 20_000,
+            nnz_per_cell: 1_500,
+            seed: DEFAULT_SEED,
+        }
+    }
+}
+
+/// Cells × genes counts in CSR layout with their features and barcodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticData {
+    pub gene_ids: Vec<String>,
+    pub gene_symbols: Vec<String>,
+    pub barcodes: Vec<String>,
+    /// `n_cells + 1` offsets into `indices` and `data`.
+    pub indptr: Vec<i64>,
+    /// Gene index of each stored count, ascending within a cell.
+    pub indices: Vec<i64>,
+    pub data: Vec<f64>,
+}
+
+const MITO_GENES: &[&str] = &[
+    "MT-ND1", "MT-ND2", "MT-CO1", "MT-CO2", "MT-ATP8", "MT-ATP6", "MT-CO3", "MT-ND3", "MT-ND4L",
+    "MT-ND4", "MT-ND5", "MT-ND6", "MT-CYB",
+];
+
+const RIBO_GENES: &[&str] = &[
+    "RPL3", "RPL4", "RPL5", "RPL7", "RPL10", "RPL11", "RPL13", "RPL19", "RPL32", "RPS2", "RPS3",
+    "RPS6", "RPS8", "RPS12", "RPS18", "RPS27A",
+];
+
+impl SyntheticData {
+    pub fn generate(spec: &SyntheticSpec) -> Self {
+        let gene_symbols = synthetic_symbols(spec.n_genes);
+        let n_genes = gene_symbols.len();
+        let gene_ids = (1..=n_genes).map(|i| format!("ENSG{i:011}")).collect();
+        let barcodes = (0..spec.n_cells).map(synthetic_barcode).collect();
+
+        let mut rng = SplitMix64(spec.seed);
+        let mut indptr = Vec::with_capacity(spec.n_cells + 1);
+        let mut indices = Vec::with_capacity(spec.n_cells * spec.nnz_per_cell);
+        let mut data = Vec::with_capacity(spec.n_cells * spec.nnz_per_cell);
+        // `taken[g] == cell + 1` marks genes already drawn for this cell.
+        let mut taken = vec![0usize; n_genes];
+        let mut cell_genes = Vec::new();
+        indptr.push(0);
+        for cell in 0..spec.n_cells {
+            let spread = rng.below(spec.nnz_per_cell + 1);
+            let nnz = (spec.nnz_per_cell / 2 + spread).min(n_genes);
+            cell_genes.clear();
+            for _ in 0..nnz {
+                // Squaring skews draws towards the panel genes at the front.
+                let u = rng.unit();
+                let mut gene = ((u * u * n_genes as f64) as usize).min(n_genes - 1);
+                while taken[gene] == cell + 1 {
+                    gene = (gene + 1) % n_genes;
+                }
+                taken[gene] = cell + 1;
+                cell_genes.push(gene);
+            }
+            cell_genes.sort_unstable();
+            for &gene in &cell_genes {
+                // Geometric counts (p = 1/2) from integer bits, so no libm
+                // call can differ between platforms.
+                let count = 1.0 + rng.next_u64().trailing_zeros() as f64;
+                indices.push(gene as i64);
+                data.push(count);
+            }
+            indptr.push(indices.len() as i64);
+        }
+
+        Self {
+            gene_ids,
+            gene_symbols,
+            barcodes,
+            indptr,
+            indices,
+            data,
+        }
+    }
+
+    /// The counts as [`run_counts`](crate::run_counts) input.
+    pub fn counts(&self) -> CsrCounts<'_> {
+        CsrCounts {
+            indptr: &self.indptr,
+            indices: &self.indices,
+            data: &self.data,
+            gene_symbols: &self.gene_symbols,
+            barcodes: &self.barcodes,
+        }
+    }
+
+    /// Writes `matrix.mtx`, `features.tsv` and `barcodes.tsv` into `dir` as
+    /// an uncompressed 10x directory, creating it if needed.
+    pub fn write_tenx(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut features = BufWriter::new(File::create(dir.join("features.tsv"))?);
+        for (id, symbol) in self.gene_ids.iter().zip(&self.gene_symbols) {
+            writeln!(features, "{id}\t{symbol}\tGene Expression")?;
+        }
+        features.flush()?;
+
+        let mut barcodes = BufWriter::new(File::create(dir.join("barcodes.tsv"))?);
+        for barcode in &self.barcodes {
+            writeln!(barcodes, "{barcode}")?;
+        }
+        barcodes.flush()?;
+
+        let mut matrix = BufWriter::new(File::create(dir.join("matrix.mtx"))?);
+        writeln!(matrix, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(
+            matrix,
+            "{} {} {}",
+            self.gene_symbols.len(),
+            self.barcodes.len(),
+            self.data.len()
+        )?;
+        for cell in 0..self.barcodes.len() {
+            let range = self.indptr[cell] as usize..self.indptr[cell + 1] as usize;
+            for (&gene, &count) in self.indices[range.clone()].iter().zip(&self.data[range]) {
+                writeln!(matrix, "{} {} {}", gene + 1, cell + 1, count as u64)?;
+            }
+        }
+        matrix.flush()
+    }
+}
+
+/// `n_genes` unique symbols: builtin panel genes in panel order, then
+/// mitochondrial and ribosomal genes, then `SYNTH<n>` filler.
+fn synthetic_symbols(n_genes: usize) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    let panel_genes = builtin_panels().into_iter().flat_map(|panel| panel.genes);
+    let named = panel_genes
+        .chain(MITO_GENES.iter().map(|g| g.to_string()))
+        .chain(RIBO_GENES.iter().map(|g| g.to_string()))
+        .filter(|symbol| seen.insert(symbol.clone()));
+    let filler = (1..).map(|i| format!("SYNTH{i}"));
+    named.chain(filler).take(n_genes).collect()
+}
+
+/// 16-base barcode spelling `cell` in base 4, with the 10x `-1` suffix.
+fn synthetic_barcode(cell: usize) -> String {
+    let mut barcode: String = (0..16)
+        .rev()
+        .map(|digit| b"ACGT"[(cell >> (2 * digit)) & 3] as char)
+        .collect();
+    barcode.push_str("-1");
+    barcode
+}
+
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1) from the top 53 bits.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/src_inline/bench.rs"]
+mod tests;
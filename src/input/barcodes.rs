@@ -1,9 +1,11 @@
+use std::io::BufRead;
 use std::path::Path;
 
 use kira_scio::api::{Reader, ReaderOptions};
 use kira_scio::detect::DetectedFormat;
 
 use crate::input::InputError;
+use crate::input::cache::open_maybe_gz;
 
 pub fn parse_barcodes(path: &Path) -> Result<Vec<String>, InputError> {
     let md = Reader::with_options(
@@ -22,3 +24,20 @@ pub fn parse_barcodes(path: &Path) -> Result<Vec<String>, InputError> {
 
     Ok(md.barcodes)
 }
+
+/// Reads barcodes directly (first column per line), without kira-scio discovery.
+pub fn parse_barcodes_tsv(path: &Path) -> Result<Vec<String>, InputError> {
+    let reader = open_maybe_gz(path)?;
+    let mut barcodes = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let barcode = line.split('\t').next().unwrap_or("").trim();
+        if !barcode.is_empty() {
+            barcodes.push(barcode.to_string());
+        }
+    }
+    if barcodes.is_empty() {
+        return Err(InputError::Parse("barcodes file is empty".to_string()));
+    }
+    Ok(barcodes)
+}
@@ -1,8 +1,13 @@
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use memmap2::Mmap;
+
 use crate::input::InputError;
 
 pub fn open_maybe_gz(path: &Path) -> Result<Box<dyn BufRead>, InputError> {
@@ -53,85 +58,282 @@ pub struct CacheMeta {
     pub log1p: bool,
 }
 
+/// Per-cell normalized columns as produced by Stage 2, before flattening.
 #[derive(Debug, Clone)]
 pub struct CachedNormalizedData {
-    pub libsizes: Vec<f32>,
-    pub nnz: Vec<u32>,
+    pub libsizes: Vec<f64>,
     pub columns: Vec<Vec<(u32, f32)>>,
 }
 
+/// Normalized matrix in the v2 cache payload layout: `col_ptr` (u64,
+/// `n_cells + 1`), `libsizes` (f64, `n_cells`), then `gene_id` (u32) and
+/// `value` (f32) for every nonzero, little-endian and back to back. Backed by
+/// the mapped cache file when it is uncompressed, so cells are read straight
+/// from the page cache.
+pub struct NormalizedCsc {
+    bytes: CacheBytes,
+    offset: usize,
+    n_cells: usize,
+    nnz: usize,
+}
+
+enum CacheBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl NormalizedCsc {
+    pub fn from_data(data: &CachedNormalizedData) -> Self {
+        let n_cells = data.columns.len();
+        let nnz = data.columns.iter().map(Vec::len).sum::<usize>();
+        let mut bytes = Vec::with_capacity(payload_len(n_cells, nnz));
+        let mut ptr = 0u64;
+        bytes.extend_from_slice(&ptr.to_le_bytes());
+        for col in &data.columns {
+            ptr += col.len() as u64;
+            bytes.extend_from_slice(&ptr.to_le_bytes());
+        }
+        for &lib in &data.libsizes {
+            bytes.extend_from_slice(&lib.to_le_bytes());
+        }
+        for &(gene_id, _) in data.columns.iter().flatten() {
+            bytes.extend_from_slice(&gene_id.to_le_bytes());
+        }
+        for &(_, value) in data.columns.iter().flatten() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Self {
+            bytes: CacheBytes::Owned(bytes),
+            offset: 0,
+            n_cells,
+            nnz,
+        }
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.n_cells
+    }
+
+    pub fn libsize(&self, cell: usize) -> f64 {
+        let at = self.libsizes_at() + cell * 8;
+        f64::from_le_bytes(self.payload()[at..at + 8].try_into().unwrap())
+    }
+
+    pub fn nnz(&self, cell: usize) -> u32 {
+        (self.col_ptr(cell + 1) - self.col_ptr(cell)) as u32
+    }
+
+    pub fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        let (start, end) = (self.col_ptr(cell), self.col_ptr(cell + 1));
+        let payload = self.payload();
+        let gene_ids = &payload[self.gene_ids_at() + start * 4..self.gene_ids_at() + end * 4];
+        let values = &payload[self.values_at() + start * 4..self.values_at() + end * 4];
+        for (gene_id, value) in gene_ids.chunks_exact(4).zip(values.chunks_exact(4)) {
+            f(
+                u32::from_le_bytes(gene_id.try_into().unwrap()),
+                f32::from_le_bytes(value.try_into().unwrap()),
+            );
+        }
+    }
+
+    fn payload(&self) -> &[u8] {
+        let bytes = match &self.bytes {
+            CacheBytes::Mapped(map) => &map[..],
+            CacheBytes::Owned(vec) => &vec[..],
+        };
+        &bytes[self.offset..self.offset + payload_len(self.n_cells, self.nnz)]
+    }
+
+    fn col_ptr(&self, idx: usize) -> usize {
+        let at = idx * 8;
+        u64::from_le_bytes(self.payload()[at..at + 8].try_into().unwrap()) as usize
+    }
+
+    fn libsizes_at(&self) -> usize {
+        (self.n_cells + 1) * 8
+    }
+
+    fn gene_ids_at(&self) -> usize {
+        self.libsizes_at() + self.n_cells * 8
+    }
+
+    fn values_at(&self) -> usize {
+        self.gene_ids_at() + self.nnz * 4
+    }
+
+    /// Rejects column pointers that do not start at 0, decrease, end past
+    /// `nnz`, or give a cell more entries than there are genes.
+    fn check_col_ptr(&self, n_genes: usize) -> bool {
+        if self.col_ptr(0) != 0 || self.col_ptr(self.n_cells) != self.nnz {
+            return false;
+        }
+        (0..self.n_cells).all(|cell| {
+            let (start, end) = (self.col_ptr(cell), self.col_ptr(cell + 1));
+            start <= end && end - start <= n_genes
+        })
+    }
+}
+
+fn payload_len(n_cells: usize, nnz: usize) -> usize {
+    (n_cells + 1) * 8 + n_cells * 8 + nnz * 8
+}
+
+/// Payload encoding, stored in the first byte left reserved in v1 headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheCodec {
+    None,
+    Deflate,
+}
+
+impl CacheCodec {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheCodec::None => "none",
+            CacheCodec::Deflate => "deflate",
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            CacheCodec::None => 0,
+            CacheCodec::Deflate => 1,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(CacheCodec::None),
+            1 => Some(CacheCodec::Deflate),
+            _ => None,
+        }
+    }
+}
+
 const CACHE_MAGIC: &[u8; 8] = b"KIRAQC2\0";
-const CACHE_VERSION: u32 = 1;
+const CACHE_VERSION: u32 = 2;
+/// v1 files store per-cell `(gene_id, value)` records and no payload hash;
+/// they are still read, unverified, via a copy.
+const CACHE_VERSION_V1: u32 = 1;
+/// v2 header size; a multiple of 8 so the mapped `col_ptr` array is aligned.
+const HEADER_LEN: usize = 80;
 
 pub fn cache_path_default(mtx_path: &Path) -> PathBuf {
     let dir = mtx_path.parent().unwrap_or_else(|| Path::new("."));
     dir.join("kira_nuclearqc.normcache")
 }
 
+/// Writes a v2 cache to a sibling temp file and renames it into place, so a
+/// crash never leaves a partial cache at `path`. The payload hash covers the
+/// uncompressed payload.
 pub fn write_normalized_cache(
     path: &Path,
     meta: &CacheMeta,
-    data: &CachedNormalizedData,
+    csc: &NormalizedCsc,
+    codec: CacheCodec,
 ) -> Result<(), InputError> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    let mut file = File::create(path)?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let payload = csc.payload();
+    let mut file = BufWriter::new(File::create(&tmp_path)?);
     file.write_all(CACHE_MAGIC)?;
     write_u32(&mut file, CACHE_VERSION)?;
     write_f32(&mut file, meta.scale)?;
     write_u8(&mut file, if meta.log1p { 1 } else { 0 })?;
-    file.write_all(&[0u8; 3])?;
+    write_u8(&mut file, codec.code())?;
+    file.write_all(&[0u8; 2])?;
     write_u32(&mut file, meta.n_cells)?;
     write_u32(&mut file, meta.n_genes)?;
     write_u64(&mut file, meta.hash_mtx)?;
     write_u64(&mut file, meta.hash_features)?;
     write_u64(&mut file, meta.hash_barcodes)?;
     write_u64(&mut file, meta.hash_gene_index)?;
+    write_u64(&mut file, hash_bytes(payload))?;
+    write_u64(&mut file, csc.nnz as u64)?;
+    file.write_all(&[0u8; 4])?;
 
-    for &lib in &data.libsizes {
-        write_f32(&mut file, lib)?;
-    }
-    for &n in &data.nnz {
-        write_u32(&mut file, n)?;
-    }
-
-    for col in &data.columns {
-        for (gene_id, value) in col {
-            write_u32(&mut file, *gene_id)?;
-            write_f32(&mut file, *value)?;
+    match codec {
+        CacheCodec::None => file.write_all(payload)?,
+        CacheCodec::Deflate => {
+            let mut encoder = DeflateEncoder::new(&mut file, Compression::default());
+            encoder.write_all(payload)?;
+            encoder.finish()?;
         }
     }
+
+    let file = file
+        .into_inner()
+        .map_err(|e| InputError::Io(e.into_error()))?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp_path, path)?;
     Ok(())
 }
 
+/// Returns `Ok(None)` on any mismatch: stale inputs, an unknown version or
+/// codec, a truncated or undecodable file, or a payload hash that does not
+/// verify. The codec is read from the header. Uncompressed v2 caches are
+/// memory-mapped; v1 caches and deflate payloads are copied into memory.
 pub fn read_normalized_cache(
     path: &Path,
     meta: &CacheMeta,
-) -> Result<Option<CachedNormalizedData>, InputError> {
+) -> Result<Option<NormalizedCsc>, InputError> {
     if !path.exists() {
         return Ok(None);
     }
-    let mut file = File::open(path)?;
+    match read_cache_file(path, meta) {
+        Err(InputError::Io(e))
+            if matches!(
+                e.kind(),
+                ErrorKind::UnexpectedEof | ErrorKind::InvalidData | ErrorKind::InvalidInput
+            ) =>
+        {
+            crate::warn!(
+                "normalized cache {} is truncated or corrupt; recomputing",
+                path.display()
+            );
+            Ok(None)
+        }
+        other => other,
+    }
+}
+
+fn read_cache_file(path: &Path, meta: &CacheMeta) -> Result<Option<NormalizedCsc>, InputError> {
+    let mut file = BufReader::new(File::open(path)?);
     let mut magic = [0u8; 8];
     file.read_exact(&mut magic)?;
     if &magic != CACHE_MAGIC {
         return Ok(None);
     }
     let version = read_u32(&mut file)?;
-    if version != CACHE_VERSION {
+    if !matches!(version, CACHE_VERSION | CACHE_VERSION_V1) {
         return Ok(None);
     }
     let scale = read_f32(&mut file)?;
     let log1p = read_u8(&mut file)? != 0;
-    let mut _reserved = [0u8; 3];
+    let codec = read_u8(&mut file)?;
+    let mut _reserved = [0u8; 2];
     file.read_exact(&mut _reserved)?;
+    let codec = match (version, CacheCodec::from_code(codec)) {
+        (CACHE_VERSION_V1, _) => CacheCodec::None,
+        (_, Some(codec)) => codec,
+        (_, None) => return Ok(None),
+    };
     let n_cells = read_u32(&mut file)?;
     let n_genes = read_u32(&mut file)?;
     let hash_mtx = read_u64(&mut file)?;
     let hash_features = read_u64(&mut file)?;
     let hash_barcodes = read_u64(&mut file)?;
     let hash_gene_index = read_u64(&mut file)?;
+    let payload_hash = if version == CACHE_VERSION_V1 {
+        None
+    } else {
+        Some(read_u64(&mut file)?)
+    };
 
     if scale != meta.scale
         || log1p != meta.log1p
@@ -145,31 +347,98 @@ pub fn read_normalized_cache(
         return Ok(None);
     }
 
-    let mut libsizes = vec![0f32; n_cells as usize];
+    let csc = match payload_hash {
+        Some(expected) => {
+            let nnz = read_u64(&mut file)? as usize;
+            let mut _pad = [0u8; 4];
+            file.read_exact(&mut _pad)?;
+            let n_cells = n_cells as usize;
+            if nnz > n_cells * n_genes as usize {
+                return Err(InputError::Io(ErrorKind::InvalidData.into()));
+            }
+            let len = payload_len(n_cells, nnz);
+            let (bytes, offset) = match codec {
+                CacheCodec::None => {
+                    // SAFETY: we assume no other process truncates or rewrites
+                    // the cache while mapped. `write_normalized_cache` replaces
+                    // it by rename, which leaves this mapping's file intact;
+                    // the length is checked below.
+                    let map = unsafe { Mmap::map(file.get_ref())? };
+                    if map.len() != HEADER_LEN + len {
+                        return Err(InputError::Io(ErrorKind::UnexpectedEof.into()));
+                    }
+                    (CacheBytes::Mapped(map), HEADER_LEN)
+                }
+                CacheCodec::Deflate => {
+                    let mut buf = Vec::new();
+                    DeflateDecoder::new(&mut file)
+                        .take(len as u64 + 1)
+                        .read_to_end(&mut buf)?;
+                    if buf.len() != len {
+                        return Err(InputError::Io(ErrorKind::UnexpectedEof.into()));
+                    }
+                    (CacheBytes::Owned(buf), 0)
+                }
+            };
+            let csc = NormalizedCsc {
+                bytes,
+                offset,
+                n_cells,
+                nnz,
+            };
+            if hash_bytes(csc.payload()) != expected {
+                crate::warn!(
+                    "normalized cache {} failed its checksum; recomputing",
+                    path.display()
+                );
+                return Ok(None);
+            }
+            csc
+        }
+        None => {
+            crate::warn!(
+                "normalized cache {} predates payload checksums; reading it unverified",
+                path.display()
+            );
+            NormalizedCsc::from_data(&read_v1_payload(&mut file, n_cells, n_genes)?)
+        }
+    };
+
+    if !csc.check_col_ptr(n_genes as usize) {
+        return Ok(None);
+    }
+    Ok(Some(csc))
+}
+
+/// v1 payload: f32 libsizes, per-cell nnz, then `(gene_id, value)` records.
+fn read_v1_payload(
+    mut payload: impl Read,
+    n_cells: u32,
+    n_genes: u32,
+) -> Result<CachedNormalizedData, InputError> {
+    let mut libsizes = vec![0f64; n_cells as usize];
     for item in &mut libsizes {
-        *item = read_f32(&mut file)?;
+        *item = f64::from(read_f32(&mut payload)?);
     }
     let mut nnz = vec![0u32; n_cells as usize];
     for item in &mut nnz {
-        *item = read_u32(&mut file)?;
+        *item = read_u32(&mut payload)?;
+    }
+    if nnz.iter().any(|&count| count > n_genes) {
+        return Err(InputError::Io(ErrorKind::InvalidData.into()));
     }
 
     let mut columns = Vec::with_capacity(n_cells as usize);
     for &count in &nnz {
         let mut col = Vec::with_capacity(count as usize);
         for _ in 0..count {
-            let gene_id = read_u32(&mut file)?;
-            let value = read_f32(&mut file)?;
+            let gene_id = read_u32(&mut payload)?;
+            let value = read_f32(&mut payload)?;
             col.push((gene_id, value));
         }
         columns.push(col);
     }
-
-    Ok(Some(CachedNormalizedData {
-        libsizes,
-        nnz,
-        columns,
-    }))
+    Ok(CachedNormalizedData { libsizes, columns })
 }
 
 fn write_u8<W: Write>(w: &mut W, v: u8) -> Result<(), InputError> {
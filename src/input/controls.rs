@@ -0,0 +1,173 @@
+//! Spike-in and control features: ERCC spike-ins, multiplexing capture oligos
+//! and any other prefix given with `--control-prefixes`. They are left out of
+//! the gene index, so they never reach libsize, panels or entropy, and are
+//! counted per cell only for `pct_control`.
+
+use crate::input::mtx::{CscMatrix, sum_feature_counts};
+use crate::input::{GeneIndex, InputBundle, InputError, InputSourceKind};
+
+/// Symbol prefixes always treated as controls.
+pub const DEFAULT_CONTROL_PREFIXES: &[&str] = &["ERCC-"];
+
+/// Case-insensitive prefix match against any of `prefixes`.
+pub fn is_control_symbol(symbol: &str, prefixes: &[String]) -> bool {
+    prefixes.iter().any(|prefix| {
+        symbol
+            .as_bytes()
+            .get(..prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(prefix.as_bytes()))
+    })
+}
+
+/// [`DEFAULT_CONTROL_PREFIXES`] followed by `extra`.
+pub fn control_prefixes(extra: &[String]) -> Vec<String> {
+    DEFAULT_CONTROL_PREFIXES
+        .iter()
+        .map(|p| p.to_string())
+        .chain(extra.iter().cloned())
+        .collect()
+}
+
+/// A gene index with its control genes removed.
+#[derive(Debug, Clone)]
+pub struct ControlSplit {
+    pub gene_index: GeneIndex,
+    /// New id of each gene id of the original index; `None` for controls.
+    /// Kept genes stay in order, so gene-sorted columns stay sorted.
+    pub kept_gene_id: Vec<Option<u32>>,
+    /// Per feature, whether it maps to a control gene.
+    pub control_by_feature: Vec<bool>,
+}
+
+/// `None` when no gene matches `prefixes`.
+pub fn split_controls(gene_index: &GeneIndex, prefixes: &[String]) -> Option<ControlSplit> {
+    let mut kept_gene_id = Vec::with_capacity(gene_index.symbols_by_gene_id.len());
+    let mut symbols_by_gene_id = Vec::with_capacity(gene_index.symbols_by_gene_id.len());
+    for symbol in &gene_index.symbols_by_gene_id {
+        if is_control_symbol(symbol, prefixes) {
+            kept_gene_id.push(None);
+        } else {
+            kept_gene_id.push(Some(symbols_by_gene_id.len() as u32));
+            symbols_by_gene_id.push(symbol.clone());
+        }
+    }
+    if symbols_by_gene_id.len() == gene_index.symbols_by_gene_id.len() {
+        return None;
+    }
+    let gene_id_by_feature = gene_index
+        .gene_id_by_feature
+        .iter()
+        .map(|gene_id| gene_id.and_then(|g| kept_gene_id[g]).map(|g| g as usize))
+        .collect();
+    let control_by_feature = gene_index
+        .gene_id_by_feature
+        .iter()
+        .map(|gene_id| gene_id.is_some_and(|g| kept_gene_id[g].is_none()))
+        .collect();
+    Some(ControlSplit {
+        gene_index: GeneIndex {
+            gene_id_by_feature,
+            symbols_by_gene_id,
+        },
+        kept_gene_id,
+        control_by_feature,
+    })
+}
+
+/// Whether any gene of `gene_index` matches `prefixes`.
+pub fn has_controls(gene_index: &GeneIndex, prefixes: &[String]) -> bool {
+    gene_index
+        .symbols_by_gene_id
+        .iter()
+        .any(|symbol| is_control_symbol(symbol, prefixes))
+}
+
+/// Drops control features from `bundle` and from `csc`, the counts already
+/// read for it, and records their per-cell counts in
+/// `bundle.control_counts`. Without `csc` a shared bin's counts are summed
+/// in memory, while an MTX's are left to the read that streams it, through
+/// `bundle.control_by_feature`.
+pub fn exclude_controls(
+    bundle: &mut InputBundle,
+    csc: Option<CscMatrix>,
+    prefixes: &[String],
+) -> Result<Option<CscMatrix>, InputError> {
+    let Some(split) = split_controls(&bundle.gene_index, prefixes) else {
+        return Ok(csc);
+    };
+    let (csc, control_counts) = match (csc, &bundle.organelle, bundle.source) {
+        (Some(csc), _, _) => {
+            let (csc, control_counts) = csc.split_genes(&split.kept_gene_id);
+            (Some(csc), Some(control_counts))
+        }
+        (None, Some(bin), InputSourceKind::OrganelleBin) => {
+            let control_counts = (0..bin.csc.n_cells)
+                .map(|cell| {
+                    let (rows, counts) = bin.csc.column(cell);
+                    rows.iter()
+                        .zip(counts)
+                        .filter(|(feature, _)| split.control_by_feature[**feature as usize])
+                        .map(|(_, &count)| count as f64)
+                        .sum()
+                })
+                .collect();
+            (None, Some(control_counts))
+        }
+        (None, _, _) => {
+            bundle.control_by_feature = Some(split.control_by_feature.clone());
+            (None, None)
+        }
+    };
+    let n_control_features = split.control_by_feature.iter().filter(|&&c| c).count();
+    crate::info!(
+        "excluding {n_control_features} control features (prefixes: {}) from scoring",
+        prefixes.join(",")
+    );
+    bundle.gene_index = split.gene_index;
+    bundle.n_genes_indexed = bundle.gene_index.symbols_by_gene_id.len();
+    bundle.n_control_features = n_control_features;
+    bundle.control_counts = control_counts;
+    Ok(csc)
+}
+
+/// Sums the control counts still pending in `bundle.control_by_feature` in
+/// a pass of their own over the MTX, for reads that cannot sum them on the
+/// way.
+pub fn read_control_counts(bundle: &mut InputBundle) -> Result<(), InputError> {
+    let Some(control_by_feature) = bundle.control_by_feature.take() else {
+        return Ok(());
+    };
+    bundle.control_counts = Some(sum_feature_counts(
+        &bundle.mtx_path,
+        bundle.n_features_raw,
+        bundle.n_cells,
+        bundle.transposed,
+        bundle.strict_input,
+        &control_by_feature,
+    )?);
+    Ok(())
+}
+
+/// Share of each cell's counts on control features, out of its libsize plus
+/// those counts; 0 without controls.
+pub fn control_fraction(control_counts: Option<&[f64]>, libsize: &[f64]) -> Vec<f32> {
+    match control_counts {
+        Some(control) => control
+            .iter()
+            .zip(libsize)
+            .map(|(&control, &lib)| {
+                let total = control + lib;
+                if total > 0.0 {
+                    (control / total) as f32
+                } else {
+                    0.0
+                }
+            })
+            .collect(),
+        None => vec![0.0; libsize.len()],
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/controls.rs"]
+mod tests;
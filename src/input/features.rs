@@ -1,9 +1,11 @@
+use std::io::BufRead;
 use std::path::Path;
 
 use kira_scio::api::{Reader, ReaderOptions};
 use kira_scio::detect::DetectedFormat;
 
-use crate::input::InputError;
+use crate::input::cache::open_maybe_gz;
+use crate::input::{InputError, is_comment_line, strip_bom};
 
 #[derive(Debug, Clone)]
 pub struct Feature {
@@ -13,6 +15,23 @@ pub struct Feature {
     pub feature_type: Option<String>,
 }
 
+/// Zero-based columns of the feature id and gene symbol in a features table
+/// (`--feature-id-col`, `--feature-symbol-col`). 10x writes the id first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureColumns {
+    pub id: usize,
+    pub symbol: usize,
+}
+
+impl Default for FeatureColumns {
+    fn default() -> Self {
+        Self { id: 0, symbol: 1 }
+    }
+}
+
+/// Reads a 10x features/genes table through kira-scio, which would read a
+/// UTF-8 BOM into the first id and a `#` comment as a feature: tables with
+/// either ([`has_bom_or_comments`]) go through [`parse_features_tsv`].
 pub fn parse_features(path: &Path) -> Result<Vec<Feature>, InputError> {
     let md = Reader::with_options(
         path,
@@ -46,6 +65,81 @@ pub fn parse_features(path: &Path) -> Result<Vec<Feature>, InputError> {
     Ok(features)
 }
 
+/// Reads a 10x features/genes table directly. Unlike `parse_features`, this does
+/// not go through kira-scio discovery, so no matrix needs to be present.
+///
+/// A single-column line is both id and symbol; otherwise `columns` picks
+/// them, and the column after both is the feature type. Blank lines, `#`
+/// comments and a leading UTF-8 BOM are skipped.
+pub fn parse_features_tsv(
+    path: &Path,
+    columns: FeatureColumns,
+) -> Result<Vec<Feature>, InputError> {
+    let reader = open_maybe_gz(path)?;
+    let mut features = Vec::new();
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = if line_no == 0 {
+            strip_bom(&line)
+        } else {
+            &line
+        };
+        if line.trim().is_empty() || is_comment_line(line) {
+            continue;
+        }
+        let cols = line.split('\t').map(str::trim).collect::<Vec<_>>();
+        let column = |idx: usize| {
+            if cols.len() == 1 {
+                Some(cols[0].to_string())
+            } else {
+                cols.get(idx).map(|v| v.to_string())
+            }
+        };
+        let (Some(id), Some(symbol)) = (column(columns.id), column(columns.symbol)) else {
+            return Err(InputError::Parse(format!(
+                "{}:{}: expected the feature id in column {} and the symbol in column {}, found {} columns",
+                path.display(),
+                line_no + 1,
+                columns.id,
+                columns.symbol,
+                cols.len()
+            )));
+        };
+        if id.is_empty() {
+            return Err(InputError::Parse(format!(
+                "{}:{}: empty feature id",
+                path.display(),
+                line_no + 1
+            )));
+        }
+        let feature_type = cols
+            .get(columns.id.max(columns.symbol) + 1)
+            .map(|t| t.to_string());
+        features.push(Feature {
+            id,
+            symbol_norm: normalize_symbol(&symbol),
+            symbol_raw: symbol,
+            feature_type,
+        });
+    }
+    if features.is_empty() {
+        return Err(InputError::Parse("features file is empty".to_string()));
+    }
+    Ok(features)
+}
+
+/// Whether the table starts with a UTF-8 BOM or has `#` comment lines, which
+/// only the direct reader skips.
+pub fn has_bom_or_comments(path: &Path) -> Result<bool, InputError> {
+    for (line_no, line) in open_maybe_gz(path)?.lines().enumerate() {
+        let line = line?;
+        if (line_no == 0 && line.starts_with('\u{feff}')) || is_comment_line(&line) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 pub fn normalize_symbol(raw: &str) -> String {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
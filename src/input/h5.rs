@@ -0,0 +1,1003 @@
+//! Read-only HDF5 access for `.loom` input, covering what h5py and loompy
+//! write: superblocks v0-v3, v1 and v2 object headers, symbol-table and
+//! compact groups, compact, contiguous and v1-B-tree chunked layouts with
+//! the deflate, shuffle and Fletcher-32 filters, and integer, float and
+//! string datatypes. Dense link storage, v4 chunk indexes and other filters
+//! are reported as unsupported.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use memmap2::Mmap;
+
+use crate::input::InputError;
+
+const SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+/// An unset address: all bytes `0xff`.
+const UNDEFINED: u64 = u64::MAX;
+/// Deepest B-tree or group nesting followed, against cyclic files.
+const MAX_DEPTH: usize = 64;
+
+const MSG_DATASPACE: u16 = 0x0001;
+const MSG_LINK_INFO: u16 = 0x0002;
+const MSG_DATATYPE: u16 = 0x0003;
+const MSG_LINK: u16 = 0x0006;
+const MSG_LAYOUT: u16 = 0x0008;
+const MSG_FILTERS: u16 = 0x000b;
+const MSG_CONTINUATION: u16 = 0x0010;
+const MSG_SYMBOL_TABLE: u16 = 0x0011;
+
+const FILTER_DEFLATE: u16 = 1;
+const FILTER_SHUFFLE: u16 = 2;
+const FILTER_FLETCHER32: u16 = 3;
+
+// Errors below the public API are plain messages; `H5File` prefixes them
+// with the file path.
+type H5Result<T> = Result<T, String>;
+
+/// An open HDF5 file.
+pub struct H5File {
+    path: PathBuf,
+    map: Mmap,
+    size_offsets: usize,
+    size_lengths: usize,
+    base: u64,
+    root: u64,
+}
+
+/// A dataset's shape, element type and storage, read from its object header.
+pub struct Dataset<'a> {
+    file: &'a H5File,
+    shape: Vec<u64>,
+    dtype: Datatype,
+    layout: Layout,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Datatype {
+    Int {
+        size: usize,
+        signed: bool,
+        big_endian: bool,
+    },
+    Float {
+        size: usize,
+        big_endian: bool,
+    },
+    FixedString {
+        size: usize,
+        space_padded: bool,
+    },
+    /// Length, global heap collection address and object index per element.
+    VarString,
+}
+
+enum Layout {
+    Compact(Vec<u8>),
+    Contiguous {
+        address: u64,
+    },
+    /// A v1 B-tree of chunks of `chunk` elements per dimension.
+    Chunked {
+        btree: u64,
+        chunk: Vec<u64>,
+    },
+}
+
+struct Filter {
+    id: u16,
+    client: Vec<u32>,
+}
+
+struct Message<'a> {
+    kind: u16,
+    data: &'a [u8],
+}
+
+// One stored chunk: its first element, stored size, filter mask and address.
+struct Chunk {
+    origin: Vec<u64>,
+    size: u64,
+    filter_mask: u32,
+    address: u64,
+}
+
+// Little-endian reads over a slice of the file, with addresses and lengths
+// in the file's sizes.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    size_offsets: usize,
+    size_lengths: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> H5Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or("truncated HDF5 structure")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> H5Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn uint(&mut self, n: usize) -> H5Result<u64> {
+        Ok(uint_le(self.take(n)?))
+    }
+
+    fn u8(&mut self) -> H5Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> H5Result<u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> H5Result<u32> {
+        Ok(self.uint(4)? as u32)
+    }
+
+    fn offset(&mut self) -> H5Result<u64> {
+        let bytes = self.take(self.size_offsets)?;
+        Ok(match bytes.iter().all(|&b| b == 0xff) {
+            true => UNDEFINED,
+            false => uint_le(bytes),
+        })
+    }
+
+    fn length(&mut self) -> H5Result<u64> {
+        self.uint(self.size_lengths)
+    }
+
+    fn expect(&mut self, signature: &[u8]) -> H5Result<()> {
+        match self.take(signature.len())? == signature {
+            true => Ok(()),
+            false => Err(format!(
+                "expected HDF5 {} block",
+                String::from_utf8_lossy(signature)
+            )),
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+}
+
+fn uint_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, &b| (acc << 8) | u64::from(b))
+}
+
+fn uint_be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b))
+}
+
+impl H5File {
+    pub fn open(path: &Path) -> Result<Self, InputError> {
+        let file = File::open(path)?;
+        // SAFETY: the file is read-only input; we assume no process truncates
+        // or rewrites it while mapped. Every read is bounds-checked.
+        let map = unsafe { Mmap::map(&file)? };
+        let mut h5 = H5File {
+            path: path.to_path_buf(),
+            map,
+            size_offsets: 8,
+            size_lengths: 8,
+            base: 0,
+            root: UNDEFINED,
+        };
+        h5.read_superblock().map_err(|msg| h5.error(msg))?;
+        Ok(h5)
+    }
+
+    /// The dataset at `path` (`/`-separated from the root group), or `None`
+    /// when no object has that path.
+    pub fn dataset(&self, path: &str) -> Result<Option<Dataset<'_>>, InputError> {
+        self.find(path)
+            .and_then(|address| address.map(|a| self.read_dataset(a)).transpose())
+            .map_err(|msg| self.error(format!("{path}: {msg}")))
+    }
+
+    /// Names in the group at `path`, sorted; empty when there is no such group.
+    pub fn members(&self, path: &str) -> Result<Vec<String>, InputError> {
+        let names = match self.find(path) {
+            Ok(Some(address)) => self.links(address, 0).map(|links| {
+                let mut names = links.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+                names.sort();
+                names
+            }),
+            Ok(None) => Ok(Vec::new()),
+            Err(msg) => Err(msg),
+        };
+        names.map_err(|msg| self.error(format!("{path}: {msg}")))
+    }
+
+    fn error(&self, msg: String) -> InputError {
+        InputError::Parse(format!("{}: {msg}", self.path.display()))
+    }
+
+    fn read_superblock(&mut self) -> H5Result<()> {
+        let start = (0..)
+            .map(|k| if k == 0 { 0 } else { 512usize << (k - 1) })
+            .take_while(|&at| at + SIGNATURE.len() <= self.map.len())
+            .find(|&at| &self.map[at..at + SIGNATURE.len()] == SIGNATURE)
+            .ok_or("not an HDF5 file")?;
+        let mut c = Cursor {
+            data: &self.map[start..],
+            pos: SIGNATURE.len(),
+            size_offsets: 8,
+            size_lengths: 8,
+        };
+        let version = c.u8()?;
+        match version {
+            0 | 1 => {
+                c.skip(4)?;
+                c.size_offsets = c.u8()? as usize;
+                c.size_lengths = c.u8()? as usize;
+                c.skip(9)?;
+                if version == 1 {
+                    c.skip(4)?;
+                }
+            }
+            2 | 3 => {
+                c.size_offsets = c.u8()? as usize;
+                c.size_lengths = c.u8()? as usize;
+                c.skip(1)?;
+            }
+            v => return Err(format!("unsupported HDF5 superblock version {v}")),
+        }
+        if ![2, 4, 8].contains(&c.size_offsets) || ![2, 4, 8].contains(&c.size_lengths) {
+            return Err("unsupported HDF5 address size".to_string());
+        }
+        self.base = c.offset()?;
+        if version < 2 {
+            // Free-space, end-of-file and driver addresses, then the root
+            // group's symbol table entry, whose link name offset comes first.
+            c.offset()?;
+            c.offset()?;
+            c.offset()?;
+            c.offset()?;
+        } else {
+            // Superblock extension and end-of-file addresses.
+            c.offset()?;
+            c.offset()?;
+        }
+        self.root = c.offset()?;
+        self.size_offsets = c.size_offsets;
+        self.size_lengths = c.size_lengths;
+        if self.base == UNDEFINED {
+            self.base = 0;
+        }
+        Ok(())
+    }
+
+    fn cursor_over<'a>(&self, data: &'a [u8]) -> Cursor<'a> {
+        Cursor {
+            data,
+            pos: 0,
+            size_offsets: self.size_offsets,
+            size_lengths: self.size_lengths,
+        }
+    }
+
+    // The file from `address` on.
+    fn cursor(&self, address: u64) -> H5Result<Cursor<'_>> {
+        let start = self.position(address)?;
+        Ok(self.cursor_over(&self.map[start..]))
+    }
+
+    fn bytes(&self, address: u64, len: u64) -> H5Result<&[u8]> {
+        let start = self.position(address)?;
+        usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .filter(|&end| end <= self.map.len())
+            .map(|end| &self.map[start..end])
+            .ok_or_else(|| "HDF5 block runs past the end of the file".to_string())
+    }
+
+    fn position(&self, address: u64) -> H5Result<usize> {
+        address
+            .checked_add(self.base)
+            .and_then(|at| usize::try_from(at).ok())
+            .filter(|&at| address != UNDEFINED && at < self.map.len())
+            .ok_or_else(|| "HDF5 address out of range".to_string())
+    }
+
+    fn find(&self, path: &str) -> H5Result<Option<u64>> {
+        let mut address = self.root;
+        for (depth, name) in path.split('/').filter(|p| !p.is_empty()).enumerate() {
+            match self
+                .links(address, depth)?
+                .into_iter()
+                .find(|(n, _)| n == name)
+            {
+                Some((_, child)) => address = child,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(address))
+    }
+
+    fn messages(&self, address: u64) -> H5Result<Vec<Message<'_>>> {
+        let mut c = self.cursor(address)?;
+        let mut blocks = Vec::new();
+        let v2 = c.data.starts_with(b"OHDR");
+        let flags = if v2 {
+            c.skip(4)?;
+            if c.u8()? != 2 {
+                return Err("unsupported HDF5 object header version".to_string());
+            }
+            let flags = c.u8()?;
+            if flags & 0x20 != 0 {
+                c.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                c.skip(4)?;
+            }
+            let size = c.uint(1 << (flags & 0x03))?;
+            blocks.push((address + c.pos as u64, size));
+            flags
+        } else {
+            if c.u8()? != 1 {
+                return Err("unsupported HDF5 object header version".to_string());
+            }
+            c.skip(7)?;
+            let size = c.u32()?;
+            blocks.push((address + 16, u64::from(size)));
+            0
+        };
+
+        let mut messages = Vec::new();
+        let mut next = 0;
+        while let Some(&(start, len)) = blocks.get(next) {
+            next += 1;
+            if blocks.len() > 1024 {
+                return Err("too many HDF5 header continuations".to_string());
+            }
+            let mut c = self.cursor_over(self.bytes(start, len)?);
+            let header_len = match v2 {
+                true => 4 + if flags & 0x04 != 0 { 2 } else { 0 },
+                false => 8,
+            };
+            while c.remaining() >= header_len {
+                let (kind, size) = if v2 {
+                    let kind = u16::from(c.u8()?);
+                    let size = c.u16()? as usize;
+                    c.skip(header_len - 3)?;
+                    (kind, size)
+                } else {
+                    let kind = c.u16()?;
+                    let size = c.u16()? as usize;
+                    c.skip(4)?;
+                    (kind, size)
+                };
+                let data = c.take(size)?;
+                if kind == MSG_CONTINUATION {
+                    let mut d = self.cursor_over(data);
+                    let (at, len) = (d.offset()?, d.length()?);
+                    // v2 continuation blocks open with `OCHK` and end in a
+                    // checksum.
+                    blocks.push(match v2 {
+                        true => (at + 4, len.saturating_sub(8)),
+                        false => (at, len),
+                    });
+                }
+                messages.push(Message { kind, data });
+            }
+        }
+        Ok(messages)
+    }
+
+    // The hard links of the group at `address`, by name.
+    fn links(&self, address: u64, depth: usize) -> H5Result<Vec<(String, u64)>> {
+        if depth > MAX_DEPTH {
+            return Err("HDF5 groups nest too deeply".to_string());
+        }
+        let mut links = Vec::new();
+        for message in self.messages(address)? {
+            let mut c = self.cursor_over(message.data);
+            match message.kind {
+                MSG_SYMBOL_TABLE => {
+                    let btree = c.offset()?;
+                    let heap = self.local_heap(c.offset()?)?;
+                    self.group_btree(btree, heap, &mut links, 0)?;
+                }
+                MSG_LINK => {
+                    c.skip(1)?;
+                    let flags = c.u8()?;
+                    let link_type = if flags & 0x08 != 0 { c.u8()? } else { 0 };
+                    if flags & 0x04 != 0 {
+                        c.skip(8)?;
+                    }
+                    if flags & 0x10 != 0 {
+                        c.skip(1)?;
+                    }
+                    let name_len = c.uint(1 << (flags & 0x03))? as usize;
+                    let name = String::from_utf8_lossy(c.take(name_len)?).into_owned();
+                    // Soft and external links are not followed.
+                    if link_type == 0 {
+                        links.push((name, c.offset()?));
+                    }
+                }
+                MSG_LINK_INFO => {
+                    c.skip(1)?;
+                    if c.u8()? & 0x01 != 0 {
+                        c.skip(8)?;
+                    }
+                    if c.offset()? != UNDEFINED {
+                        return Err("dense HDF5 link storage is not supported".to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(links)
+    }
+
+    // The data segment of a local heap.
+    fn local_heap(&self, address: u64) -> H5Result<&[u8]> {
+        let mut c = self.cursor(address)?;
+        c.expect(b"HEAP")?;
+        c.skip(4)?;
+        let size = c.length()?;
+        c.length()?;
+        self.bytes(c.offset()?, size)
+    }
+
+    fn group_btree(
+        &self,
+        address: u64,
+        heap: &[u8],
+        links: &mut Vec<(String, u64)>,
+        depth: usize,
+    ) -> H5Result<()> {
+        if depth > MAX_DEPTH {
+            return Err("HDF5 B-tree nests too deeply".to_string());
+        }
+        let mut c = self.cursor(address)?;
+        c.expect(b"TREE")?;
+        if c.u8()? != 0 {
+            return Err("expected an HDF5 group B-tree".to_string());
+        }
+        let level = c.u8()?;
+        let entries = c.u16()?;
+        c.offset()?;
+        c.offset()?;
+        for _ in 0..entries {
+            c.length()?;
+            let child = c.offset()?;
+            if level > 0 {
+                self.group_btree(child, heap, links, depth + 1)?;
+            } else {
+                self.symbol_node(child, heap, links)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn symbol_node(
+        &self,
+        address: u64,
+        heap: &[u8],
+        links: &mut Vec<(String, u64)>,
+    ) -> H5Result<()> {
+        let mut c = self.cursor(address)?;
+        c.expect(b"SNOD")?;
+        c.skip(2)?;
+        let n_symbols = c.u16()?;
+        for _ in 0..n_symbols {
+            let name_offset = c.offset()?;
+            let header = c.offset()?;
+            c.skip(24)?;
+            let name = usize::try_from(name_offset)
+                .ok()
+                .and_then(|at| heap.get(at..))
+                .map(|s| s.split(|&b| b == 0).next().unwrap_or_default())
+                .ok_or("HDF5 link name out of range")?;
+            links.push((String::from_utf8_lossy(name).into_owned(), header));
+        }
+        Ok(())
+    }
+
+    fn read_dataset(&self, address: u64) -> H5Result<Dataset<'_>> {
+        let (mut shape, mut dtype, mut layout) = (None, None, None);
+        let mut filters = Vec::new();
+        for message in self.messages(address)? {
+            let mut c = self.cursor_over(message.data);
+            match message.kind {
+                MSG_DATASPACE => shape = Some(parse_dataspace(&mut c)?),
+                MSG_DATATYPE => dtype = Some(parse_datatype(&mut c)?),
+                MSG_LAYOUT => layout = Some(parse_layout(&mut c)?),
+                MSG_FILTERS => filters = parse_filters(&mut c)?,
+                _ => {}
+            }
+        }
+        let (Some(shape), Some(dtype), Some(layout)) = (shape, dtype, layout) else {
+            return Err("not a dataset".to_string());
+        };
+        Ok(Dataset {
+            file: self,
+            shape,
+            dtype,
+            layout,
+            filters,
+        })
+    }
+
+    fn chunk_btree(
+        &self,
+        address: u64,
+        rank: usize,
+        chunks: &mut Vec<Chunk>,
+        depth: usize,
+    ) -> H5Result<()> {
+        if depth > MAX_DEPTH {
+            return Err("HDF5 B-tree nests too deeply".to_string());
+        }
+        let mut c = self.cursor(address)?;
+        c.expect(b"TREE")?;
+        if c.u8()? != 1 {
+            return Err("expected an HDF5 chunk B-tree".to_string());
+        }
+        let level = c.u8()?;
+        let entries = c.u16()?;
+        c.offset()?;
+        c.offset()?;
+        for _ in 0..entries {
+            let size = u64::from(c.u32()?);
+            let filter_mask = c.u32()?;
+            let origin = (0..=rank)
+                .map(|_| c.uint(8))
+                .collect::<H5Result<Vec<_>>>()?;
+            let child = c.offset()?;
+            if level > 0 {
+                self.chunk_btree(child, rank, chunks, depth + 1)?;
+            } else {
+                chunks.push(Chunk {
+                    origin: origin[..rank].to_vec(),
+                    size,
+                    filter_mask,
+                    address: child,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // The objects of a global heap collection, by index.
+    fn global_heap(&self, address: u64) -> H5Result<Vec<(u32, Vec<u8>)>> {
+        let mut c = self.cursor(address)?;
+        c.expect(b"GCOL")?;
+        c.skip(4)?;
+        let size = c.length()?;
+        let mut c = self.cursor_over(self.bytes(address, size)?);
+        c.skip(8 + self.size_lengths)?;
+        let mut objects = Vec::new();
+        while c.remaining() >= 8 + self.size_lengths {
+            let index = c.u16()?;
+            c.skip(6)?;
+            let len = c.length()?;
+            if index == 0 {
+                break;
+            }
+            let len = usize::try_from(len).map_err(|_| "HDF5 heap object too large")?;
+            objects.push((u32::from(index), c.take(len)?.to_vec()));
+            c.skip(len.next_multiple_of(8) - len)?;
+        }
+        Ok(objects)
+    }
+}
+
+fn parse_dataspace(c: &mut Cursor<'_>) -> H5Result<Vec<u64>> {
+    let version = c.u8()?;
+    let rank = c.u8()?;
+    c.skip(1)?;
+    c.skip(if version == 1 { 5 } else { 1 })?;
+    (0..rank).map(|_| c.length()).collect()
+}
+
+fn parse_datatype(c: &mut Cursor<'_>) -> H5Result<Datatype> {
+    let class = c.u8()? & 0x0f;
+    let bits = c.take(3)?;
+    let size = c.u32()? as usize;
+    let big_endian = bits[0] & 0x01 != 0;
+    match class {
+        0 if [1, 2, 4, 8].contains(&size) => Ok(Datatype::Int {
+            size,
+            signed: bits[0] & 0x08 != 0,
+            big_endian,
+        }),
+        1 if [4, 8].contains(&size) => Ok(Datatype::Float { size, big_endian }),
+        3 => Ok(Datatype::FixedString {
+            size,
+            space_padded: bits[0] & 0x0f == 2,
+        }),
+        9 if bits[0] & 0x0f == 1 => Ok(Datatype::VarString),
+        _ => Err(format!(
+            "unsupported HDF5 datatype (class {class}, {size} bytes)"
+        )),
+    }
+}
+
+fn parse_layout(c: &mut Cursor<'_>) -> H5Result<Layout> {
+    let version = c.u8()?;
+    let (class, address, dims) = match version {
+        1 | 2 => {
+            let rank = c.u8()?;
+            let class = c.u8()?;
+            c.skip(5)?;
+            let address = if class == 0 { UNDEFINED } else { c.offset()? };
+            let dims = (0..rank)
+                .map(|_| c.u32().map(u64::from))
+                .collect::<H5Result<Vec<_>>>()?;
+            if class == 0 {
+                let size = c.u32()? as usize;
+                return Ok(Layout::Compact(c.take(size)?.to_vec()));
+            }
+            (class, address, dims)
+        }
+        3 => match c.u8()? {
+            0 => {
+                let size = c.u16()? as usize;
+                return Ok(Layout::Compact(c.take(size)?.to_vec()));
+            }
+            1 => (1, c.offset()?, Vec::new()),
+            2 => {
+                let rank = c.u8()?;
+                let address = c.offset()?;
+                let dims = (0..rank)
+                    .map(|_| c.u32().map(u64::from))
+                    .collect::<H5Result<Vec<_>>>()?;
+                (2, address, dims)
+            }
+            class => return Err(format!("unsupported HDF5 layout class {class}")),
+        },
+        v => return Err(format!("unsupported HDF5 data layout version {v}")),
+    };
+    match class {
+        1 => Ok(Layout::Contiguous { address }),
+        2 if dims.len() >= 2 && dims.iter().all(|&d| d > 0) => Ok(Layout::Chunked {
+            btree: address,
+            // The last dimension is the element size.
+            chunk: dims[..dims.len() - 1].to_vec(),
+        }),
+        _ => Err(format!("unsupported HDF5 layout class {class}")),
+    }
+}
+
+fn parse_filters(c: &mut Cursor<'_>) -> H5Result<Vec<Filter>> {
+    let version = c.u8()?;
+    let n_filters = c.u8()?;
+    if version == 1 {
+        c.skip(6)?;
+    }
+    let mut filters = Vec::with_capacity(n_filters as usize);
+    for _ in 0..n_filters {
+        let id = c.u16()?;
+        let name_len = match version == 1 || id >= 256 {
+            true => c.u16()? as usize,
+            false => 0,
+        };
+        c.skip(2)?;
+        let n_values = c.u16()? as usize;
+        c.skip(name_len)?;
+        let client = (0..n_values)
+            .map(|_| c.u32())
+            .collect::<H5Result<Vec<_>>>()?;
+        if version == 1 && n_values % 2 == 1 {
+            c.skip(4)?;
+        }
+        filters.push(Filter { id, client });
+    }
+    Ok(filters)
+}
+
+impl Dataset<'_> {
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    /// Elements of a 1-D (or scalar) dataset as text: strings as stored
+    /// (UTF-8, invalid bytes replaced), numbers in their shortest decimal form.
+    pub fn read_strings(&self) -> Result<Vec<String>, InputError> {
+        self.strings()
+            .map_err(|msg| self.file.error(format!("dataset: {msg}")))
+    }
+
+    /// Calls `f(row, col, value)` for every nonzero element of a 2-D numeric
+    /// dataset, in storage order.
+    pub fn for_each_nonzero(&self, mut f: impl FnMut(u64, u64, f64)) -> Result<(), InputError> {
+        let n_cols = match self.shape[..] {
+            [_, n_cols] if !self.is_string() => n_cols,
+            _ => {
+                return Err(self.file.error(format!(
+                    "expected a 2-D numeric dataset, found shape {:?}",
+                    self.shape
+                )));
+            }
+        };
+        self.for_each_element(|index, bytes| {
+            let value = self.number(bytes);
+            if value != 0.0 {
+                f(index / n_cols, index % n_cols, value);
+            }
+            Ok(())
+        })
+        .map_err(|msg| self.file.error(format!("matrix: {msg}")))
+    }
+
+    fn is_string(&self) -> bool {
+        matches!(
+            self.dtype,
+            Datatype::FixedString { .. } | Datatype::VarString
+        )
+    }
+
+    fn element_size(&self) -> usize {
+        match self.dtype {
+            Datatype::Int { size, .. }
+            | Datatype::Float { size, .. }
+            | Datatype::FixedString { size, .. } => size,
+            Datatype::VarString => 4 + self.file.size_offsets + 4,
+        }
+    }
+
+    fn n_elements(&self) -> H5Result<u64> {
+        self.shape
+            .iter()
+            .try_fold(1u64, |n, &d| n.checked_mul(d))
+            .ok_or_else(|| "dataset too large".to_string())
+    }
+
+    fn strings(&self) -> H5Result<Vec<String>> {
+        if self.shape.len() > 1 {
+            return Err(format!(
+                "expected a 1-D dataset, found shape {:?}",
+                self.shape
+            ));
+        }
+        let n = usize::try_from(self.n_elements()?).map_err(|_| "dataset too large")?;
+        let mut out = vec![String::new(); n];
+        let mut heaps = HashMap::new();
+        self.for_each_element(|index, bytes| {
+            out[index as usize] = self.text(bytes, &mut heaps)?;
+            Ok(())
+        })?;
+        Ok(out)
+    }
+
+    fn number(&self, bytes: &[u8]) -> f64 {
+        match self.dtype {
+            Datatype::Int {
+                size,
+                signed,
+                big_endian,
+            } => {
+                let raw = if big_endian {
+                    uint_be(bytes)
+                } else {
+                    uint_le(bytes)
+                };
+                match signed {
+                    true => {
+                        let shift = 64 - 8 * size as u32;
+                        ((raw << shift) as i64 >> shift) as f64
+                    }
+                    false => raw as f64,
+                }
+            }
+            Datatype::Float { size, big_endian } => {
+                let raw = if big_endian {
+                    uint_be(bytes)
+                } else {
+                    uint_le(bytes)
+                };
+                match size {
+                    4 => f64::from(f32::from_bits(raw as u32)),
+                    _ => f64::from_bits(raw),
+                }
+            }
+            Datatype::FixedString { .. } | Datatype::VarString => f64::NAN,
+        }
+    }
+
+    fn text(
+        &self,
+        bytes: &[u8],
+        heaps: &mut HashMap<u64, Vec<(u32, Vec<u8>)>>,
+    ) -> H5Result<String> {
+        match self.dtype {
+            Datatype::FixedString { space_padded, .. } => {
+                let s = bytes.split(|&b| b == 0).next().unwrap_or_default();
+                let s = String::from_utf8_lossy(s);
+                Ok(match space_padded {
+                    true => s.trim_end_matches(' ').to_string(),
+                    false => s.into_owned(),
+                })
+            }
+            Datatype::VarString => {
+                let mut c = self.file.cursor_over(bytes);
+                let len = c.u32()? as usize;
+                let collection = c.offset()?;
+                let index = c.u32()?;
+                if len == 0 || collection == UNDEFINED {
+                    return Ok(String::new());
+                }
+                let objects = match heaps.entry(collection) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(self.file.global_heap(collection)?),
+                };
+                let object = objects
+                    .iter()
+                    .find(|(i, _)| *i == index)
+                    .and_then(|(_, data)| data.get(..len))
+                    .ok_or("missing HDF5 global heap object")?;
+                Ok(String::from_utf8_lossy(object).into_owned())
+            }
+            Datatype::Int { signed: true, .. } => Ok((self.number(bytes) as i64).to_string()),
+            Datatype::Int { signed: false, .. } => Ok((self.number(bytes) as u64).to_string()),
+            Datatype::Float { size: 4, .. } => Ok((self.number(bytes) as f32).to_string()),
+            Datatype::Float { .. } => Ok(self.number(bytes).to_string()),
+        }
+    }
+
+    // Calls `f(index, bytes)` for each element inside the dataset extent,
+    // with `index` its row-major position.
+    fn for_each_element(&self, mut f: impl FnMut(u64, &[u8]) -> H5Result<()>) -> H5Result<()> {
+        let size = self.element_size();
+        let n = self.n_elements()?;
+        match &self.layout {
+            Layout::Compact(data) => for_each_stored(data, n, size, &mut f),
+            Layout::Contiguous { address } if *address == UNDEFINED => {
+                // Never written: every element is the zero fill value.
+                let zeros = vec![0u8; size];
+                (0..n).try_for_each(|index| f(index, &zeros))
+            }
+            Layout::Contiguous { address } => {
+                let len = n.checked_mul(size as u64).ok_or("dataset too large")?;
+                for_each_stored(self.file.bytes(*address, len)?, n, size, &mut f)
+            }
+            Layout::Chunked { btree, chunk } => {
+                if chunk.len() != self.shape.len() {
+                    return Err("chunk rank does not match the dataset".to_string());
+                }
+                let mut chunks = Vec::new();
+                if *btree != UNDEFINED {
+                    self.file.chunk_btree(*btree, chunk.len(), &mut chunks, 0)?;
+                }
+                let chunk_elements = chunk.iter().product::<u64>();
+                let chunk_len =
+                    usize::try_from(chunk_elements * size as u64).map_err(|_| "chunk too large")?;
+                for stored in chunks {
+                    let raw = self.file.bytes(stored.address, stored.size)?;
+                    let data = self.unfilter(raw.to_vec(), stored.filter_mask)?;
+                    if data.len() < chunk_len {
+                        return Err("HDF5 chunk is shorter than its shape".to_string());
+                    }
+                    self.for_each_in_chunk(&stored.origin, chunk, &data, &mut f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Elements of one chunk that fall inside the dataset, in row-major
+    // order within the chunk.
+    fn for_each_in_chunk(
+        &self,
+        origin: &[u64],
+        chunk: &[u64],
+        data: &[u8],
+        f: &mut impl FnMut(u64, &[u8]) -> H5Result<()>,
+    ) -> H5Result<()> {
+        let size = self.element_size();
+        let rank = chunk.len();
+        let mut local = vec![0u64; rank];
+        for bytes in data
+            .chunks_exact(size)
+            .take(chunk.iter().product::<u64>() as usize)
+        {
+            let mut index = 0u64;
+            let mut inside = true;
+            for d in 0..rank {
+                let coord = origin[d] + local[d];
+                inside &= coord < self.shape[d];
+                index = index * self.shape[d] + coord;
+            }
+            if inside {
+                f(index, bytes)?;
+            }
+            for d in (0..rank).rev() {
+                local[d] += 1;
+                if local[d] < chunk[d] {
+                    break;
+                }
+                local[d] = 0;
+            }
+        }
+        Ok(())
+    }
+
+    // Undoes the filter pipeline, last filter first, skipping the filters
+    // set in `mask`.
+    fn unfilter(&self, mut data: Vec<u8>, mask: u32) -> H5Result<Vec<u8>> {
+        for (i, filter) in self.filters.iter().enumerate().rev() {
+            if i < 32 && mask & (1 << i) != 0 {
+                continue;
+            }
+            data = match filter.id {
+                FILTER_DEFLATE => {
+                    let mut out = Vec::new();
+                    ZlibDecoder::new(&data[..])
+                        .read_to_end(&mut out)
+                        .map_err(|e| format!("corrupt deflate chunk: {e}"))?;
+                    out
+                }
+                FILTER_SHUFFLE => {
+                    let size = filter
+                        .client
+                        .first()
+                        .map_or(self.element_size(), |&s| s as usize);
+                    unshuffle(&data, size)
+                }
+                FILTER_FLETCHER32 => {
+                    data.truncate(data.len().saturating_sub(4));
+                    data
+                }
+                id => return Err(format!("unsupported HDF5 filter {id}")),
+            };
+        }
+        Ok(data)
+    }
+}
+
+// The first `n` elements of `data`, stored contiguously in row-major order.
+fn for_each_stored(
+    data: &[u8],
+    n: u64,
+    size: usize,
+    f: &mut impl FnMut(u64, &[u8]) -> H5Result<()>,
+) -> H5Result<()> {
+    let len = usize::try_from(n)
+        .ok()
+        .and_then(|n| n.checked_mul(size))
+        .filter(|&len| len <= data.len())
+        .ok_or("dataset storage is shorter than its shape")?;
+    for (index, bytes) in data[..len].chunks_exact(size).enumerate() {
+        f(index as u64, bytes)?;
+    }
+    Ok(())
+}
+
+fn unshuffle(data: &[u8], size: usize) -> Vec<u8> {
+    if size <= 1 {
+        return data.to_vec();
+    }
+    let n = data.len() / size;
+    let mut out = data.to_vec();
+    for byte in 0..size {
+        for i in 0..n {
+            out[i * size + byte] = data[byte * n + i];
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/h5.rs"]
+pub(crate) mod tests;
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use crate::input::h5::H5File;
+use crate::input::meta::{CellMeta, load_meta, merge_meta};
+use crate::input::mtx::{CscMatrix, csc_from_cell_rows};
+use crate::input::{InputBundle, InputError, InputSourceKind, bundle_from_symbols};
+
+const GENES: &str = "row_attrs/Gene";
+const CELL_IDS: &str = "col_attrs/CellID";
+
+/// Reads a `.loom` file: genes from `row_attrs/Gene`, barcodes from
+/// `col_attrs/CellID` and counts from `matrix`, which Loom stores genes ×
+/// cells. A cells × genes `matrix` is read transposed, as an AnnData export
+/// may write it. Every other 1-D `col_attrs` entry becomes a metadata column,
+/// with `meta_path` joined onto them by barcode. The counts are loaded here,
+/// since Stage 2 only streams MTX files.
+pub fn load_input_loom(
+    path: &Path,
+    meta_path: Option<&Path>,
+    strict_input: bool,
+) -> Result<(InputBundle, CscMatrix), InputError> {
+    let file = H5File::open(path)?;
+    let genes = read_attr(&file, path, GENES)?;
+    let barcodes = read_attr(&file, path, CELL_IDS)?;
+    let matrix = file
+        .dataset("matrix")?
+        .ok_or_else(|| InputError::MissingInput(format!("{}: no matrix", path.display())))?;
+
+    let (n_genes, n_cells) = (genes.len() as u64, barcodes.len() as u64);
+    let transposed = match matrix.shape() {
+        [rows, cols] if (*rows, *cols) == (n_genes, n_cells) => false,
+        [rows, cols] if (*rows, *cols) == (n_cells, n_genes) => {
+            crate::info!(
+                "{}: matrix is cells × genes; reading it transposed",
+                path.display()
+            );
+            true
+        }
+        shape => {
+            return Err(InputError::InvalidInput(format!(
+                "{}: matrix shape {shape:?} does not match {n_genes} genes in {GENES} and {n_cells} cells in {CELL_IDS}",
+                path.display()
+            )));
+        }
+    };
+
+    let mut per_cell: Vec<Vec<(i64, f64)>> = vec![Vec::new(); barcodes.len()];
+    matrix.for_each_nonzero(|row, col, value| {
+        let (gene, cell) = if transposed { (col, row) } else { (row, col) };
+        per_cell[cell as usize].push((gene as i64, value));
+    })?;
+    let mut indptr = Vec::with_capacity(per_cell.len() + 1);
+    indptr.push(0i64);
+    let (mut indices, mut data) = (Vec::new(), Vec::new());
+    for cell in per_cell {
+        for (gene, value) in cell {
+            indices.push(gene);
+            data.push(value);
+        }
+        indptr.push(indices.len() as i64);
+    }
+
+    let mut bundle = bundle_from_symbols(&genes, barcodes);
+    let csc = csc_from_cell_rows(&indptr, &indices, &data, &bundle.gene_index, strict_input)?;
+
+    let col_attrs = read_col_attrs(&file, path, bundle.n_cells)?;
+    bundle.meta = match (col_attrs, meta_path) {
+        (Some(mut meta), Some(meta_path)) => {
+            merge_meta(&mut meta, meta_path, &bundle.barcodes)?;
+            Some(meta)
+        }
+        (Some(meta), None) => Some(meta),
+        (None, Some(meta_path)) => Some(load_meta(meta_path, &bundle.barcodes)?),
+        (None, None) => None,
+    };
+    bundle.mtx_path = path.to_path_buf();
+    bundle.features_path = path.to_path_buf();
+    bundle.barcodes_path = path.to_path_buf();
+    bundle.source = InputSourceKind::Loom;
+    bundle.strict_input = strict_input;
+    Ok((bundle, csc))
+}
+
+fn read_attr(file: &H5File, path: &Path, name: &str) -> Result<Vec<String>, InputError> {
+    file.dataset(name)?
+        .ok_or_else(|| InputError::MissingInput(format!("{}: no {name}", path.display())))?
+        .read_strings()
+}
+
+// The 1-D `col_attrs` other than `CellID`, one column each in name order;
+// `None` when there are none. Entries of another length are skipped.
+fn read_col_attrs(
+    file: &H5File,
+    path: &Path,
+    n_cells: usize,
+) -> Result<Option<CellMeta>, InputError> {
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    for name in file.members("col_attrs")? {
+        if name == "CellID" {
+            continue;
+        }
+        let Some(attr) = file.dataset(&format!("col_attrs/{name}"))? else {
+            continue;
+        };
+        if attr.shape() != [n_cells as u64] {
+            crate::warn!(
+                "{}: skipping col_attrs/{name} with shape {:?}",
+                path.display(),
+                attr.shape()
+            );
+            continue;
+        }
+        columns.push(name);
+        values.push(attr.read_strings()?);
+    }
+    if columns.is_empty() {
+        return Ok(None);
+    }
+    let rows = (0..n_cells)
+        .map(|cell| values.iter().map(|column| column[cell].clone()).collect())
+        .collect();
+    Ok(Some(CellMeta { columns, rows }))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/input/loom.rs"]
+mod tests;
@@ -2,8 +2,8 @@ use std::collections::HashMap;
 use std::io::BufRead;
 use std::path::Path;
 
-use crate::input::InputError;
 use crate::input::cache::open_maybe_gz;
+use crate::input::{InputError, is_comment_line, strip_bom};
 
 #[derive(Debug, Clone)]
 pub struct CellMeta {
@@ -11,98 +11,261 @@ pub struct CellMeta {
     pub rows: Vec<Vec<String>>,
 }
 
+/// Joins a tab-separated table (optionally gzipped) onto `barcodes` through
+/// its `barcode` column, or its first column without one. Blank lines, `#`
+/// comments and a leading UTF-8 BOM are skipped.
 pub fn load_meta(path: &Path, barcodes: &[String]) -> Result<CellMeta, InputError> {
-    let mut reader = open_maybe_gz(path)?;
-    let mut buf = String::new();
+    Ok(join_by_barcode(&Table::read(path)?, barcodes))
+}
 
-    buf.clear();
-    let read = reader.read_line(&mut buf)?;
-    if read == 0 {
-        return Err(InputError::Parse("meta file is empty".to_string()));
+/// Attaches metadata rows to cells by position (`--meta-by-order`): row `i`
+/// belongs to barcode `i`, whatever its barcode field says. A `barcode`
+/// column is dropped as in [`load_meta`]; without one every column is kept.
+/// Fails unless there is exactly one row per cell.
+pub fn load_meta_by_order(path: &Path, n_cells: usize) -> Result<CellMeta, InputError> {
+    let table = Table::read(path)?;
+    if table.rows.len() != n_cells {
+        return Err(InputError::InvalidInput(format!(
+            "--meta-by-order: {} has {} rows, expected one per barcode ({})",
+            path.display(),
+            table.rows.len(),
+            n_cells
+        )));
     }
-    let header_line = buf.trim_end();
-    let header_cols: Vec<String> = header_line
-        .split('\t')
-        .map(|s| s.trim().to_string())
-        .collect();
-    if header_cols.is_empty() {
-        return Err(InputError::Parse("meta file header is empty".to_string()));
+    let barcode_col = table.position(is_barcode_column);
+    Ok(CellMeta {
+        columns: table.columns(barcode_col),
+        rows: table
+            .rows
+            .iter()
+            .map(|(_, fields)| table.values(fields, barcode_col))
+            .collect(),
+    })
+}
+
+/// Cluster per barcode from a `--clusters` table (header line, tab-separated,
+/// optionally gzipped), joined as in [`load_meta`]. The first column other
+/// than the barcode holds the cluster; cells missing from the table get an
+/// empty label.
+pub fn load_clusters(path: &Path, barcodes: &[String]) -> Result<Vec<String>, InputError> {
+    let table = load_meta(path, barcodes)?;
+    if table.columns.is_empty() {
+        return Err(InputError::InvalidInput(format!(
+            "--clusters {}: expected a barcode column and a cluster column",
+            path.display()
+        )));
     }
+    Ok(table
+        .rows
+        .into_iter()
+        .map(|row| row.into_iter().next().unwrap_or_default())
+        .collect())
+}
 
-    let mut barcode_col = 0usize;
-    for (idx, name) in header_cols.iter().enumerate() {
-        let lower = name.to_ascii_lowercase();
-        if lower == "barcode" || lower == "barcodes" {
-            barcode_col = idx;
-            break;
+// Lines of a metadata table with their 1-based line numbers, leaving out
+// blank lines and `#` comments; a UTF-8 BOM on the first line is dropped. A
+// `#` line with a tab before any other line is a commented header
+// (`#barcode\tsample`) and is kept without its `#`.
+fn table_lines(
+    path: &Path,
+) -> Result<impl Iterator<Item = Result<(usize, String), InputError>>, InputError> {
+    let lines = open_maybe_gz(path)?.lines().enumerate();
+    let mut header_seen = false;
+    Ok(lines.filter_map(move |(idx, line)| match line {
+        Ok(line) => {
+            let line = if idx == 0 { strip_bom(&line) } else { &line };
+            if line.trim().is_empty() {
+                return None;
+            }
+            let line = match is_comment_line(line) {
+                true if header_seen || !line.contains('\t') => return None,
+                true => line.trim_start().trim_start_matches('#'),
+                false => line,
+            };
+            header_seen = true;
+            Some(Ok((idx + 1, line.to_string())))
         }
+        Err(err) => Some(Err(err.into())),
+    }))
+}
+
+fn is_barcode_column(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower == "barcode" || lower == "barcodes"
+}
+
+// A metadata table as read by `table_lines`: the header names and, per data
+// line, its line number and fields, all trimmed.
+struct Table {
+    header: Vec<String>,
+    rows: Vec<(usize, Vec<String>)>,
+}
+
+impl Table {
+    fn read(path: &Path) -> Result<Self, InputError> {
+        let split = |line: &str| {
+            line.trim_end()
+                .split('\t')
+                .map(|s| s.trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut lines = table_lines(path)?;
+        let (_, header_line) = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| InputError::Parse("meta file is empty".to_string()))?;
+        let rows = lines
+            .map(|line| line.map(|(line_no, line)| (line_no, split(&line))))
+            .collect::<Result<_, _>>()?;
+        Ok(Table {
+            header: split(&header_line),
+            rows,
+        })
     }
 
-    let mut columns = Vec::new();
-    for (idx, name) in header_cols.iter().enumerate() {
-        if idx != barcode_col {
-            columns.push(name.to_string());
-        }
+    fn position(&self, matches: impl Fn(&str) -> bool) -> Option<usize> {
+        self.header.iter().position(|name| matches(name))
     }
 
-    let mut map: HashMap<String, Vec<String>> = HashMap::new();
-    let mut line_no = 1usize;
+    // Header names other than the `key` column.
+    fn columns(&self, key: Option<usize>) -> Vec<String> {
+        self.header
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| Some(*idx) != key)
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
 
-    loop {
-        buf.clear();
-        let read = reader.read_line(&mut buf)?;
-        if read == 0 {
-            break;
-        }
-        line_no += 1;
-        let line = buf.trim_end();
-        if line.is_empty() {
-            continue;
-        }
-        let fields: Vec<&str> = line.split('\t').collect();
-        if fields.is_empty() {
-            continue;
-        }
-        if barcode_col >= fields.len() {
-            crate::warn!(
-                "meta line has no barcode column; skipping (line {})",
-                line_no
-            );
-            continue;
-        }
-        let barcode = fields[barcode_col].trim().to_string();
-        if barcode.is_empty() {
-            crate::warn!("meta line has empty barcode; skipping (line {})", line_no);
-            continue;
-        }
-        if map.contains_key(&barcode) {
-            crate::warn!(
-                "duplicate barcode in metadata; keeping first (line {}, barcode {})",
-                line_no,
-                barcode
-            );
-            continue;
-        }
+    // A row's fields under `columns(key)`; short rows are padded with empty
+    // values.
+    fn values(&self, fields: &[String], key: Option<usize>) -> Vec<String> {
+        (0..self.header.len())
+            .filter(|&idx| Some(idx) != key)
+            .map(|idx| fields.get(idx).cloned().unwrap_or_default())
+            .collect()
+    }
 
-        let mut row = Vec::with_capacity(columns.len());
-        for (idx, _name) in header_cols.iter().enumerate() {
-            if idx == barcode_col {
+    // Rows by their `key` column, named `key_name` in warnings; rows without
+    // a key are skipped and the first of duplicate keys wins.
+    fn rows_by_key(&self, key: usize, key_name: &str) -> HashMap<&str, Vec<String>> {
+        let mut map = HashMap::new();
+        for (line_no, fields) in &self.rows {
+            let Some(value) = fields.get(key) else {
+                crate::warn!(
+                    "meta line has no {} column; skipping (line {})",
+                    key_name,
+                    line_no
+                );
+                continue;
+            };
+            if value.is_empty() {
+                crate::warn!(
+                    "meta line has empty {}; skipping (line {})",
+                    key_name,
+                    line_no
+                );
                 continue;
             }
-            let value = fields.get(idx).map(|s| s.trim()).unwrap_or("");
-            row.push(value.to_string());
+            if map.contains_key(value.as_str()) {
+                crate::warn!(
+                    "duplicate {} in metadata; keeping first (line {}, {} {})",
+                    key_name,
+                    line_no,
+                    key_name,
+                    value
+                );
+                continue;
+            }
+            map.insert(value.as_str(), self.values(fields, Some(key)));
         }
-        map.insert(barcode, row);
+        map
     }
+}
 
-    let mut rows = Vec::with_capacity(barcodes.len());
-    for bc in barcodes {
-        if let Some(row) = map.get(bc) {
-            rows.push(row.clone());
-        } else {
-            rows.push(vec![String::new(); columns.len()]);
-        }
+fn join_by_barcode(table: &Table, barcodes: &[String]) -> CellMeta {
+    let barcode_col = table.position(is_barcode_column).unwrap_or(0);
+    let columns = table.columns(Some(barcode_col));
+    let map = table.rows_by_key(barcode_col, "barcode");
+    let rows = barcodes
+        .iter()
+        .map(|bc| match map.get(bc.as_str()) {
+            Some(values) => values.clone(),
+            None => vec![String::new(); columns.len()],
+        })
+        .collect();
+    CellMeta { columns, rows }
+}
+
+/// Joins a further `--meta` file onto `meta`, appending its columns.
+///
+/// A file whose header has a `sample` column but no `barcode` column is keyed
+/// by sample: each row is broadcast to the cells whose `sample` in `meta`
+/// matches. Any other file joins by barcode as in [`load_meta`]. Fails if a
+/// column name (case-insensitive) is already in `meta`.
+pub fn merge_meta(meta: &mut CellMeta, path: &Path, barcodes: &[String]) -> Result<(), InputError> {
+    let table = Table::read(path)?;
+    let sample_col = match table.position(is_barcode_column) {
+        Some(_) => None,
+        None => table.position(is_sample_column),
+    };
+    let other = match sample_col {
+        Some(sample_col) => join_by_sample(&table, sample_col, meta, path)?,
+        None => join_by_barcode(&table, barcodes),
+    };
+
+    let collisions = other
+        .columns
+        .iter()
+        .filter(|name| meta.columns.iter().any(|c| c.eq_ignore_ascii_case(name)))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !collisions.is_empty() {
+        return Err(InputError::InvalidInput(format!(
+            "--meta {}: columns already set by an earlier --meta: {}",
+            path.display(),
+            collisions.join(", ")
+        )));
     }
 
+    meta.columns.extend(other.columns);
+    for (row, extra) in meta.rows.iter_mut().zip(other.rows) {
+        row.extend(extra);
+    }
+    Ok(())
+}
+
+fn is_sample_column(name: &str) -> bool {
+    name.eq_ignore_ascii_case("sample")
+}
+
+// Rows of a sample-keyed table, broadcast to the cells of `cells` through
+// their `sample` column.
+fn join_by_sample(
+    table: &Table,
+    sample_col: usize,
+    cells: &CellMeta,
+    path: &Path,
+) -> Result<CellMeta, InputError> {
+    let cell_sample = cells
+        .columns
+        .iter()
+        .position(|name| is_sample_column(name))
+        .ok_or_else(|| {
+            InputError::InvalidInput(format!(
+                "--meta {} is keyed by sample, but no earlier --meta has a sample column",
+                path.display()
+            ))
+        })?;
+    let columns = table.columns(Some(sample_col));
+    let map = table.rows_by_key(sample_col, "sample");
+    let rows = cells
+        .rows
+        .iter()
+        .map(|row| match map.get(row[cell_sample].as_str()) {
+            Some(values) => values.clone(),
+            None => vec![String::new(); columns.len()],
+        })
+        .collect();
     Ok(CellMeta { columns, rows })
 }
@@ -3,24 +3,47 @@ use std::path::{Path, PathBuf};
 
 pub mod barcodes;
 pub mod cache;
+pub mod controls;
 pub mod features;
+#[cfg(feature = "h5")]
+pub mod h5;
+#[cfg(feature = "h5")]
+pub mod loom;
 pub mod meta;
 pub mod mtx;
 pub mod organelle_bin;
 
-use barcodes::parse_barcodes;
-use features::{Feature, parse_features};
+use barcodes::{parse_barcodes, parse_barcodes_tsv};
+use features::{Feature, FeatureColumns, has_bom_or_comments, parse_features, parse_features_tsv};
 use meta::{CellMeta, load_meta};
-use mtx::find_matrix_path;
+use mtx::{MtxStorage, find_matrix_path, read_mtx_header};
 use organelle_bin::{OrganelleBin, read_organelle_bin};
 
+#[cfg(feature = "h5")]
+pub use loom::load_input_loom;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Species {
     Human,
     Mouse,
+    /// Both human and mouse genes, as in a combined (barnyard) reference.
+    Mixed,
     Unknown,
 }
 
+/// Species of the reference with the evidence behind it ([`call_species`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeciesCall {
+    pub species: Species,
+    /// Fraction of features carrying human-specific evidence.
+    pub human_score: f32,
+    /// Fraction of features carrying mouse-specific evidence.
+    pub mouse_score: f32,
+    /// In [0, 1]: how one-sided the evidence is for `Human`/`Mouse`, how even
+    /// for `Mixed`; 0 for `Unknown`.
+    pub confidence: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct GeneIndex {
     pub gene_id_by_feature: Vec<Option<usize>>,
@@ -36,18 +59,50 @@ pub struct InputBundle {
     pub n_features_raw: usize,
     pub n_genes_indexed: usize,
     pub species: Species,
+    /// Evidence behind `species`.
+    pub species_call: SpeciesCall,
     pub gene_index: GeneIndex,
     pub barcodes: Vec<String>,
     pub meta: Option<CellMeta>,
     pub source: InputSourceKind,
     pub organelle: Option<OrganelleBin>,
     pub shared_bin_path: Option<PathBuf>,
+    /// Matrix stores cells as rows (`--assume-transposed`).
+    pub transposed: bool,
+    /// Negative matrix values are an error rather than clamped to zero
+    /// (`--strict-input`).
+    pub strict_input: bool,
+    /// Threads parsing a coordinate `matrix.mtx` (`--threads`).
+    pub parse_threads: usize,
+    /// Cells in the input when `--max-cells` kept only `n_cells` of them.
+    pub sampled_from: Option<usize>,
+    /// Features left out of `gene_index` as spike-ins or controls.
+    pub n_control_features: usize,
+    /// Per-cell counts on those features; `None` when there are none or
+    /// they are still to be summed from the MTX.
+    pub control_counts: Option<Vec<f64>>,
+    /// Per feature, whether it is a control whose counts are still to be
+    /// summed from the MTX.
+    pub control_by_feature: Option<Vec<bool>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputSourceKind {
     TenX,
     OrganelleBin,
+    /// Counts handed over by a caller (`run_counts`); no files behind it.
+    InMemory,
+    /// A `.loom` file, read whole when loading (`--input-format loom`).
+    Loom,
+}
+
+/// Layout of `--input` (`--input-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A 10x directory, or the shared cache next to it.
+    TenX,
+    /// A `.loom` file; needs the `h5` feature.
+    Loom,
 }
 
 #[derive(Debug)]
@@ -77,14 +132,47 @@ impl From<std::io::Error> for InputError {
     }
 }
 
+/// Drops the UTF-8 byte order mark that Excel writes at the start of exported
+/// text, which would otherwise stick to the first column name or feature id.
+pub fn strip_bom(line: &str) -> &str {
+    line.strip_prefix('\u{feff}').unwrap_or(line)
+}
+
+/// A `#` comment line in a features or metadata table.
+pub fn is_comment_line(line: &str) -> bool {
+    line.trim_start().starts_with('#')
+}
+
 pub fn load_input(input_dir: &Path, meta_path: Option<&Path>) -> Result<InputBundle, InputError> {
-    load_input_tenx(input_dir, meta_path)
+    load_input_tenx(input_dir, meta_path, TenxOptions::default())
 }
 
+/// How [`load_input_tenx`] reads a 10x directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TenxOptions {
+    /// The matrix is cells × genes (`--assume-transposed`).
+    pub assume_transposed: bool,
+    /// Read features and barcodes directly, as for layouts kira-scio rejects,
+    /// since its metadata reader parses the whole matrix; `--chunk-cells`
+    /// streams the matrix itself later.
+    pub stream_matrix: bool,
+    /// Non-default columns are read directly too, as kira-scio cannot take
+    /// them.
+    pub feature_columns: FeatureColumns,
+}
+
+/// A features file with a BOM or comment lines is also read directly, since
+/// kira-scio would count those lines as features.
 pub fn load_input_tenx(
     input_dir: &Path,
     meta_path: Option<&Path>,
+    options: TenxOptions,
 ) -> Result<InputBundle, InputError> {
+    let TenxOptions {
+        assume_transposed,
+        stream_matrix,
+        feature_columns,
+    } = options;
     let mtx_path = find_matrix_path(input_dir)?;
     let features_path = find_features_path(input_dir)?;
     let barcodes_path = find_barcodes_path(input_dir)?;
@@ -96,15 +184,63 @@ pub fn load_input_tenx(
         barcodes_path.display()
     );
 
-    let features = parse_features(&features_path)?;
+    let dense = read_mtx_header(&mtx_path).is_ok_and(|h| h.storage == MtxStorage::Array);
+    let direct = assume_transposed
+        || dense
+        || stream_matrix
+        || feature_columns != FeatureColumns::default()
+        || has_bom_or_comments(&features_path)?;
+    let (features, barcodes) = if direct {
+        // kira-scio only reads genes-as-rows coordinate matrices, so other
+        // layouts are read directly.
+        let features = parse_features_tsv(&features_path, feature_columns)?;
+        let barcodes = parse_barcodes_tsv(&barcodes_path)?;
+        let header = read_mtx_header(&mtx_path)?;
+        if assume_transposed && (header.n_rows != barcodes.len() || header.n_cols != features.len())
+        {
+            return Err(InputError::InvalidInput(format!(
+                "--assume-transposed: matrix is {}x{}, expected {} barcodes x {} features",
+                header.n_rows,
+                header.n_cols,
+                barcodes.len(),
+                features.len()
+            )));
+        }
+        if !assume_transposed
+            && (header.n_rows != features.len() || header.n_cols != barcodes.len())
+        {
+            return Err(InputError::InvalidInput(format!(
+                "matrix is {}x{}, expected {} features x {} barcodes",
+                header.n_rows,
+                header.n_cols,
+                features.len(),
+                barcodes.len()
+            )));
+        }
+        (features, barcodes)
+    } else {
+        let parsed = parse_features(&features_path)
+            .and_then(|features| Ok((features, parse_barcodes(&barcodes_path)?)));
+        match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                explain_dimension_mismatch(
+                    &mtx_path,
+                    &features_path,
+                    &barcodes_path,
+                    feature_columns,
+                )?;
+                return Err(err);
+            }
+        }
+    };
     let n_features_raw = features.len();
 
     let gene_index = build_gene_index(&features);
     let n_genes_indexed = gene_index.symbols_by_gene_id.len();
 
-    let species = detect_species(&features);
+    let species_call = call_species(&features);
 
-    let barcodes = parse_barcodes(&barcodes_path)?;
     let n_cells = barcodes.len();
 
     let meta = if let Some(path) = meta_path {
@@ -120,22 +256,73 @@ pub fn load_input_tenx(
         n_cells,
         n_features_raw,
         n_genes_indexed,
-        species,
+        species: species_call.species,
+        species_call,
         gene_index,
         barcodes,
         meta,
         source: InputSourceKind::TenX,
         organelle: None,
         shared_bin_path: None,
+        transposed: assume_transposed,
+        strict_input: false,
+        parse_threads: 1,
+        sampled_from: None,
+        n_control_features: 0,
+        control_counts: None,
+        control_by_feature: None,
     })
 }
 
+/// Turns a dimension mismatch into a clear error: a transposed matrix when its
+/// rows match the barcodes and its columns match the features, otherwise the
+/// shape against the table lengths. Returns `Ok` when the files cannot be read
+/// or agree, so the caller reports its original error.
+fn explain_dimension_mismatch(
+    mtx_path: &Path,
+    features_path: &Path,
+    barcodes_path: &Path,
+    feature_columns: FeatureColumns,
+) -> Result<(), InputError> {
+    let (Ok(header), Ok(features), Ok(barcodes)) = (
+        read_mtx_header(mtx_path),
+        parse_features_tsv(features_path, feature_columns),
+        parse_barcodes_tsv(barcodes_path),
+    ) else {
+        return Ok(());
+    };
+    if header.n_rows == barcodes.len()
+        && header.n_cols == features.len()
+        && header.n_rows != header.n_cols
+    {
+        return Err(InputError::InvalidInput(format!(
+            "matrix appears transposed: {} rows match the {} barcodes and {} columns match the {} features; \
+             10x matrices store genes as rows. Re-export the matrix or pass --assume-transposed",
+            header.n_rows,
+            barcodes.len(),
+            header.n_cols,
+            features.len()
+        )));
+    }
+    if header.n_rows != features.len() || header.n_cols != barcodes.len() {
+        return Err(InputError::InvalidInput(format!(
+            "matrix is {}x{}, expected {} features x {} barcodes",
+            header.n_rows,
+            header.n_cols,
+            features.len(),
+            barcodes.len()
+        )));
+    }
+    Ok(())
+}
+
 pub fn load_input_organelle(
     input_dir: &Path,
     meta_path: Option<&Path>,
     bin_path: &Path,
 ) -> Result<InputBundle, InputError> {
     let bin = read_organelle_bin(bin_path)?;
+    check_organelle_dimensions(&bin)?;
     let gene_symbols = bin.genes.clone();
     let barcodes = bin.barcodes.clone();
 
@@ -143,7 +330,7 @@ pub fn load_input_organelle(
     let n_features_raw = features.len();
     let gene_index = build_gene_index(&features);
     let n_genes_indexed = gene_index.symbols_by_gene_id.len();
-    let species = detect_species(&features);
+    let species_call = call_species(&features);
     let n_cells = barcodes.len();
 
     let meta = if let Some(path) = meta_path {
@@ -159,36 +346,181 @@ pub fn load_input_organelle(
         n_cells,
         n_features_raw,
         n_genes_indexed,
-        species,
+        species: species_call.species,
+        species_call,
         gene_index,
         barcodes,
         meta,
         source: InputSourceKind::OrganelleBin,
         organelle: Some(bin),
         shared_bin_path: Some(bin_path.to_path_buf()),
+        transposed: false,
+        strict_input: false,
+        parse_threads: 1,
+        sampled_from: None,
+        n_control_features: 0,
+        control_counts: None,
+        control_by_feature: None,
+    })
+}
+
+/// Checks the gene and barcode tables against the matrix shape, so a malformed
+/// bin fails here rather than out of bounds in a later stage. Row indices are
+/// bounds-checked by the shared reader.
+fn check_organelle_dimensions(bin: &OrganelleBin) -> Result<(), InputError> {
+    let csc = &bin.csc;
+    if bin.genes.len() != csc.n_genes || bin.barcodes.len() != csc.n_cells {
+        return Err(InputError::InvalidInput(format!(
+            "kira-organelle.bin: matrix is {}x{}, expected {} genes x {} barcodes",
+            csc.n_genes,
+            csc.n_cells,
+            bin.genes.len(),
+            bin.barcodes.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Bundle for counts held in memory: gene index and species come from the
+/// symbols, and the paths are empty.
+pub fn bundle_from_symbols(gene_symbols: &[String], barcodes: Vec<String>) -> InputBundle {
+    let features = build_features_from_symbols(gene_symbols);
+    let gene_index = build_gene_index(&features);
+    let species_call = call_species(&features);
+    InputBundle {
+        mtx_path: PathBuf::new(),
+        features_path: PathBuf::new(),
+        barcodes_path: PathBuf::new(),
+        n_cells: barcodes.len(),
+        n_features_raw: features.len(),
+        n_genes_indexed: gene_index.symbols_by_gene_id.len(),
+        species: species_call.species,
+        species_call,
+        gene_index,
+        barcodes,
+        meta: None,
+        source: InputSourceKind::InMemory,
+        organelle: None,
+        shared_bin_path: None,
+        transposed: false,
+        strict_input: false,
+        parse_threads: 1,
+        sampled_from: None,
+        n_control_features: 0,
+        control_counts: None,
+        control_by_feature: None,
+    }
+}
+
+/// Gene index and species from the features file alone, for panel inspection.
+pub fn load_gene_index(
+    input_dir: &Path,
+    feature_columns: FeatureColumns,
+) -> Result<(GeneIndex, Species), InputError> {
+    let features_path = find_features_path(input_dir)?;
+    let features = parse_features_tsv(&features_path, feature_columns)?;
+    Ok((build_gene_index(&features), detect_species(&features)))
+}
+
+#[derive(Debug, Clone)]
+pub struct InputValidation {
+    pub source: InputSourceKind,
+    pub n_cells: usize,
+    pub n_features: usize,
+    pub n_genes_indexed: usize,
+    pub nnz: usize,
+    pub species: Species,
+    pub barcodes: Vec<String>,
+    /// Metadata joined from `meta_path`.
+    pub meta: Option<CellMeta>,
+}
+
+/// Discovers and parses inputs up to the matrix header, checking that the
+/// dimensions agree, without reading matrix entries.
+pub fn validate_input(
+    input_dir: &Path,
+    meta_path: Option<&Path>,
+    bin_path: Option<&Path>,
+    options: TenxOptions,
+) -> Result<InputValidation, InputError> {
+    let (bundle, nnz) = match bin_path {
+        Some(path) => {
+            let bundle = load_input_organelle(input_dir, meta_path, path)?;
+            let nnz = bundle.organelle.as_ref().map_or(0, |bin| bin.csc.nnz);
+            (bundle, nnz)
+        }
+        None => {
+            let bundle = load_input_tenx(
+                input_dir,
+                meta_path,
+                TenxOptions {
+                    stream_matrix: false,
+                    ..options
+                },
+            )?;
+            let mut header = read_mtx_header(&bundle.mtx_path)?;
+            if bundle.transposed {
+                std::mem::swap(&mut header.n_rows, &mut header.n_cols);
+            }
+            if header.n_rows != bundle.n_features_raw {
+                return Err(InputError::InvalidInput(format!(
+                    "matrix row count {} does not match features {}",
+                    header.n_rows, bundle.n_features_raw
+                )));
+            }
+            if header.n_cols != bundle.n_cells {
+                return Err(InputError::InvalidInput(format!(
+                    "matrix column count {} does not match barcodes {}",
+                    header.n_cols, bundle.n_cells
+                )));
+            }
+            (bundle, header.nnz)
+        }
+    };
+    Ok(InputValidation {
+        source: bundle.source,
+        n_cells: bundle.n_cells,
+        n_features: bundle.n_features_raw,
+        n_genes_indexed: bundle.n_genes_indexed,
+        nnz,
+        species: bundle.species,
+        barcodes: bundle.barcodes,
+        meta: bundle.meta,
     })
 }
 
+/// Collapses features onto one gene per symbol. Symbols of a combined human
+/// and mouse reference are indexed without their reference prefix, so panels
+/// map, and a human gene shares its id with the mouse gene of the same name;
+/// only repeats of a full symbol count as duplicates.
 pub fn build_gene_index(features: &[Feature]) -> GeneIndex {
     let mut symbols_by_gene_id: Vec<String> = Vec::new();
     let mut symbol_to_gene_id: HashMap<String, usize> = HashMap::new();
     let mut gene_id_by_feature: Vec<Option<usize>> = Vec::with_capacity(features.len());
     let mut duplicate_events: Vec<(usize, String)> = Vec::new();
+    let combined = is_combined_reference(features);
+    let mut seen_symbols = std::collections::HashSet::new();
 
     for (idx, feature) in features.iter().enumerate() {
         if feature.symbol_norm.is_empty() {
             gene_id_by_feature.push(None);
             continue;
         }
-        if let Some(existing) = symbol_to_gene_id.get(feature.symbol_norm.as_str()) {
-            let _ = existing;
-            duplicate_events.push((idx, feature.symbol_norm.clone()));
-            gene_id_by_feature.push(Some(*existing));
+        let symbol = match combined {
+            true => strip_reference_prefix(&feature.symbol_norm),
+            false => &feature.symbol_norm,
+        };
+        let repeated = !seen_symbols.insert(feature.symbol_norm.as_str());
+        if let Some(&existing) = symbol_to_gene_id.get(symbol) {
+            if repeated {
+                duplicate_events.push((idx, feature.symbol_norm.clone()));
+            }
+            gene_id_by_feature.push(Some(existing));
             continue;
         }
         let gene_id = symbols_by_gene_id.len();
-        symbols_by_gene_id.push(feature.symbol_norm.clone());
-        symbol_to_gene_id.insert(feature.symbol_norm.clone(), gene_id);
+        symbols_by_gene_id.push(symbol.to_string());
+        symbol_to_gene_id.insert(symbol.to_string(), gene_id);
         gene_id_by_feature.push(Some(gene_id));
     }
 
@@ -227,40 +559,167 @@ pub fn build_gene_index(features: &[Feature]) -> GeneIndex {
     }
 }
 
+/// Species of the reference from the features alone; [`call_species`] also
+/// reports the evidence behind it.
 pub fn detect_species(features: &[Feature]) -> Species {
-    const HUMAN_SYMBOLS: &[&str] = &[
-        "HLA-A", "HLA-B", "HLA-C", "HLA-DRA", "HLA-DRB1", "HLA-DPA1", "HLA-DPB1", "HLA-E", "HLA-F",
-        "HLA-G",
-    ];
-    const MOUSE_SYMBOLS: &[&str] = &[
-        "H2-K1", "H2-D1", "H2-AB1", "H2-AA", "H2-EB1", "H2-EA", "H2-Q7", "H2-Q10", "H2-T23",
-        "H2-M2",
-    ];
+    call_species(features).species
+}
+
+/// Weighs human against mouse evidence over every feature. Each feature casts
+/// at most one vote, from the first signal it carries: an Ensembl id prefix
+/// (`ENSG`/`ENSMUSG`), a combined-reference symbol prefix (`GRCh38_`,
+/// `mm10___`), a species-specific symbol (HLA vs H2 genes, `Trp53`, `Gm`/`Rik`
+/// and `-ps` pseudogene names), or the symbol casing (`GAPDH` vs `Gapdh`).
+/// Casing only counts when some symbol in the file has lowercase letters, as
+/// upper-cased references carry no casing signal.
+pub fn call_species(features: &[Feature]) -> SpeciesCall {
+    // Votes needed for a species to count as present at all.
+    const MIN_MATCHES: usize = 3;
+    // Share of the votes the minority species needs for a `Mixed` call.
+    const MIXED_MIN_SHARE: f32 = 0.2;
 
+    let use_casing = features
+        .iter()
+        .any(|f| f.symbol_raw.bytes().any(|b| b.is_ascii_lowercase()));
     let mut human = 0usize;
     let mut mouse = 0usize;
+    let mut n_features = 0usize;
     for feature in features {
-        let s = feature.symbol_norm.as_str();
-        if s.is_empty() {
+        if feature.symbol_norm.is_empty() {
             continue;
         }
-        if HUMAN_SYMBOLS.iter().any(|&x| x == s) {
-            human += 1;
+        n_features += 1;
+        match species_vote(feature, use_casing) {
+            Some(Species::Human) => human += 1,
+            Some(Species::Mouse) => mouse += 1,
+            _ => {}
+        }
+    }
+
+    let votes = human + mouse;
+    let (species, confidence) = if votes == 0 {
+        (Species::Unknown, 0.0)
+    } else {
+        let minority_share = human.min(mouse) as f32 / votes as f32;
+        if human.min(mouse) >= MIN_MATCHES && minority_share >= MIXED_MIN_SHARE {
+            // 1 for an even split, falling towards the `Mixed` boundary.
+            (Species::Mixed, 2.0 * minority_share)
+        } else if human.max(mouse) >= MIN_MATCHES {
+            let species = if human > mouse {
+                Species::Human
+            } else {
+                Species::Mouse
+            };
+            (species, 1.0 - 2.0 * minority_share)
+        } else {
+            (Species::Unknown, 0.0)
         }
-        if MOUSE_SYMBOLS.iter().any(|&x| x == s) {
-            mouse += 1;
+    };
+    let score = |n: usize| {
+        if n_features == 0 {
+            0.0
+        } else {
+            n as f32 / n_features as f32
         }
+    };
+    SpeciesCall {
+        species,
+        human_score: score(human),
+        mouse_score: score(mouse),
+        confidence,
     }
+}
 
-    const MIN_MATCHES: usize = 3;
-    const MIN_DELTA: usize = 2;
+// Reference prefixes of combined-reference symbols, upper-cased as in
+// `symbol_norm`: Cell Ranger writes `GRCh38_GAPDH` and `mm10___Gapdh`.
+const HUMAN_REFERENCE_PREFIXES: &[&str] = &["GRCH38_", "GRCH38-", "HG19_", "HG19-"];
+const MOUSE_REFERENCE_PREFIXES: &[&str] = &["MM10_", "MM10-", "GRCM39_", "GRCM39-", "MM39_"];
+
+/// Whether the features come from a combined human and mouse (barnyard)
+/// reference: some symbols carry a human and some a mouse reference prefix.
+fn is_combined_reference(features: &[Feature]) -> bool {
+    let any_prefixed = |prefixes: &[&str]| {
+        features
+            .iter()
+            .any(|f| prefixes.iter().any(|p| f.symbol_norm.starts_with(p)))
+    };
+    any_prefixed(HUMAN_REFERENCE_PREFIXES) && any_prefixed(MOUSE_REFERENCE_PREFIXES)
+}
+
+/// `symbol` without its reference prefix and the separators after it
+/// (`MM10___GAPDH` to `GAPDH`); unprefixed symbols are returned as they are.
+fn strip_reference_prefix(symbol: &str) -> &str {
+    HUMAN_REFERENCE_PREFIXES
+        .iter()
+        .chain(MOUSE_REFERENCE_PREFIXES)
+        .find_map(|p| symbol.strip_prefix(p))
+        .map(|rest| rest.trim_start_matches(['_', '-']))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(symbol)
+}
+
+fn species_vote(feature: &Feature, use_casing: bool) -> Option<Species> {
+    const HUMAN_SYMBOLS: &[&str] = &["TP53", "TP53BP1", "RPS4Y1", "RPS4Y2"];
+    const MOUSE_SYMBOLS: &[&str] = &["TRP53", "TRP53BP1", "EIF2S3Y"];
+
+    let id = feature.id.as_str();
+    if id.starts_with("ENSMUSG") {
+        return Some(Species::Mouse);
+    }
+    if id.starts_with("ENSG") {
+        return Some(Species::Human);
+    }
 
-    if human >= MIN_MATCHES && human >= mouse + MIN_DELTA {
-        Species::Human
-    } else if mouse >= MIN_MATCHES && mouse >= human + MIN_DELTA {
-        Species::Mouse
+    let s = feature.symbol_norm.as_str();
+    if HUMAN_REFERENCE_PREFIXES.iter().any(|p| s.starts_with(p)) {
+        return Some(Species::Human);
+    }
+    if MOUSE_REFERENCE_PREFIXES.iter().any(|p| s.starts_with(p)) {
+        return Some(Species::Mouse);
+    }
+
+    if s.starts_with("HLA-") || HUMAN_SYMBOLS.contains(&s) {
+        return Some(Species::Human);
+    }
+    let gm = s
+        .strip_prefix("GM")
+        .is_some_and(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()));
+    let pseudogene = s
+        .rsplit_once("-PS")
+        .is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    if s.starts_with("H2-") || gm || s.ends_with("RIK") || pseudogene || MOUSE_SYMBOLS.contains(&s)
+    {
+        return Some(Species::Mouse);
+    }
+
+    if use_casing {
+        return symbol_casing(&feature.symbol_raw);
+    }
+    None
+}
+
+// `GAPDH` and `MT-CO1` are human style, `Gapdh` and `mt-Co1` mouse style;
+// human `C1orf112` style names are lowercase only in `orf`.
+fn symbol_casing(raw: &str) -> Option<Species> {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix("mt-") {
+        return rest
+            .bytes()
+            .any(|b| b.is_ascii_lowercase())
+            .then_some(Species::Mouse);
+    }
+    let mut letters = raw.bytes().filter(u8::is_ascii_alphabetic);
+    let first = letters.next()?;
+    let rest = letters.collect::<Vec<_>>();
+    if !first.is_ascii_uppercase() || rest.is_empty() {
+        return None;
+    }
+    if rest.iter().all(u8::is_ascii_uppercase) {
+        Some(Species::Human)
+    } else if rest.iter().all(u8::is_ascii_lowercase) && !raw.contains("orf") {
+        Some(Species::Mouse)
     } else {
-        Species::Unknown
+        None
     }
 }
 
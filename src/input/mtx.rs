@@ -1,87 +1,771 @@
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Write};
 use std::path::{Path, PathBuf};
 
-use kira_scio::api::{Reader, ReaderOptions};
-use kira_scio::detect::DetectedFormat;
+use flate2::read::MultiGzDecoder;
 
-use crate::input::{GeneIndex, InputError};
+use kira_scio::ErrorCode;
+
+use crate::input::{GeneIndex, InputBundle, InputError};
 
 pub fn find_matrix_path(input_dir: &Path) -> Result<PathBuf, InputError> {
-    let ds = kira_scio::discover(input_dir).map_err(|e| InputError::InvalidInput(e.message))?;
+    let ds = kira_scio::discover(input_dir).map_err(|e| match e.code {
+        ErrorCode::MissingFile => InputError::MissingInput(e.message),
+        _ => InputError::InvalidInput(e.message),
+    })?;
     Ok(ds.matrix)
 }
 
+/// Storage variant from the `%%MatrixMarket` banner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtxStorage {
+    Coordinate,
+    /// Dense column-major values with a two-field size line.
+    Array,
+}
+
+/// Field type from the `%%MatrixMarket` banner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtxField {
+    Integer,
+    Real,
+    /// Entries without a value, each counting as 1.
+    Pattern,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtxHeader {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    /// Stored entries; `n_rows * n_cols` for `array` storage.
+    pub nnz: usize,
+    pub storage: MtxStorage,
+    pub field: MtxField,
+}
+
+fn open_mtx(path: &Path) -> Result<Box<dyn BufRead>, InputError> {
+    let file = File::open(path)?;
+    Ok(if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// Consumes the banner, comments and size line, leaving `lines` at the first entry.
+fn parse_mtx_header(
+    path: &Path,
+    lines: &mut impl Iterator<Item = std::io::Result<String>>,
+) -> Result<MtxHeader, InputError> {
+    let banner = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| InputError::Parse(format!("{} is empty", path.display())))?;
+    if !banner.starts_with("%%MatrixMarket") {
+        return Err(InputError::Parse(format!(
+            "{} is missing the %%MatrixMarket banner",
+            path.display()
+        )));
+    }
+    let banner_fields = banner
+        .split_whitespace()
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>();
+    let storage = match banner_fields.get(2).map(String::as_str) {
+        Some("array") => MtxStorage::Array,
+        _ => MtxStorage::Coordinate,
+    };
+    let field = match banner_fields.get(3).map(String::as_str) {
+        Some("integer") => MtxField::Integer,
+        Some("real" | "double") => MtxField::Real,
+        Some("pattern") if storage == MtxStorage::Coordinate => MtxField::Pattern,
+        _ => {
+            return Err(InputError::Parse(format!(
+                "{} has an unsupported MTX field type: {}",
+                path.display(),
+                banner.trim()
+            )));
+        }
+    };
+    let n_dims = match storage {
+        MtxStorage::Coordinate => 3,
+        MtxStorage::Array => 2,
+    };
+    for line in lines {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('%') {
+            continue;
+        }
+        let dims = trimmed
+            .split_whitespace()
+            .map(|v| v.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InputError::Parse(format!("invalid MTX size line: {trimmed}")))?;
+        if dims.len() != n_dims {
+            return Err(InputError::Parse(format!(
+                "invalid MTX size line: {trimmed}"
+            )));
+        }
+        return Ok(MtxHeader {
+            n_rows: dims[0],
+            n_cols: dims[1],
+            nnz: match storage {
+                MtxStorage::Coordinate => dims[2],
+                MtxStorage::Array => dims[0] * dims[1],
+            },
+            storage,
+            field,
+        });
+    }
+    Err(InputError::Parse(format!(
+        "{} has no MTX size line",
+        path.display()
+    )))
+}
+
+/// Reads the MatrixMarket banner and size line without touching the entries.
+pub fn read_mtx_header(path: &Path) -> Result<MtxHeader, InputError> {
+    parse_mtx_header(path, &mut open_mtx(path)?.lines())
+}
+
+/// Negative values met while reading a matrix, as a corrected matrix may hold;
+/// summed as counts they would shrink library sizes, down to negative totals
+/// and NaN normalization. They are clamped to zero with one warning per
+/// matrix, or rejected under `--strict-input`.
+struct NegativeCounts {
+    strict: bool,
+    n_clamped: usize,
+    first: Option<String>,
+}
+
+impl NegativeCounts {
+    fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            n_clamped: 0,
+            first: None,
+        }
+    }
+
+    /// `val_f`, or zero when negative; `entry` describes where it was read.
+    fn check(&mut self, val_f: f64, entry: impl FnOnce() -> String) -> Result<f64, InputError> {
+        if val_f >= 0.0 {
+            return Ok(val_f);
+        }
+        if self.strict {
+            return Err(InputError::InvalidInput(format!(
+                "negative count in {} (--strict-input)",
+                entry()
+            )));
+        }
+        self.n_clamped += 1;
+        self.first.get_or_insert_with(entry);
+        Ok(0.0)
+    }
+
+    /// Folds in the values met by a later part of the same matrix.
+    fn merge(&mut self, later: NegativeCounts) {
+        self.n_clamped += later.n_clamped;
+        if self.first.is_none() {
+            self.first = later.first;
+        }
+    }
+
+    fn warn(&self) {
+        if let Some(first) = &self.first {
+            crate::warn!(
+                "clamped {} negative matrix values to 0, first in {}; use --strict-input to reject them",
+                self.n_clamped,
+                first
+            );
+        }
+    }
+}
+
+/// Mapped gene ids and summed counts per cell, gene-sorted within each column.
 #[derive(Debug, Clone)]
 pub struct CscMatrix {
     pub n_rows: usize,
     pub n_cols: usize,
-    pub cols: Vec<Vec<(u32, i64)>>,
+    pub col_ptr: Vec<u64>,
+    pub gene_ids: Vec<u32>,
+    pub counts: Vec<i64>,
 }
 
+impl CscMatrix {
+    /// Gene ids and counts stored for `cell`.
+    pub fn column(&self, cell: usize) -> (&[u32], &[i64]) {
+        let start = self.col_ptr[cell] as usize;
+        let end = self.col_ptr[cell + 1] as usize;
+        (&self.gene_ids[start..end], &self.counts[start..end])
+    }
+
+    /// The matrix with gene `g` renumbered to `gene_ids[g]`, dropping genes
+    /// mapped to `None`, and the per-cell sums of the dropped counts. The
+    /// renumbering must keep gene order.
+    pub fn split_genes(&self, gene_ids: &[Option<u32>]) -> (CscMatrix, Vec<f64>) {
+        let mut col_ptr = Vec::with_capacity(self.n_cols + 1);
+        let mut kept_ids = Vec::with_capacity(self.gene_ids.len());
+        let mut kept_counts = Vec::with_capacity(self.counts.len());
+        let mut dropped = vec![0f64; self.n_cols];
+        col_ptr.push(0u64);
+        for (cell, dropped) in dropped.iter_mut().enumerate() {
+            let (genes, values) = self.column(cell);
+            for (&gene, &count) in genes.iter().zip(values) {
+                match gene_ids[gene as usize] {
+                    Some(gene) => {
+                        kept_ids.push(gene);
+                        kept_counts.push(count);
+                    }
+                    None => *dropped += count as f64,
+                }
+            }
+            col_ptr.push(kept_ids.len() as u64);
+        }
+        let csc = CscMatrix {
+            n_rows: self.n_rows,
+            n_cols: self.n_cols,
+            col_ptr,
+            gene_ids: kept_ids,
+            counts: kept_counts,
+        };
+        (csc, dropped)
+    }
+
+    /// The matrix restricted to `cells`, in the given order.
+    pub fn select_columns(&self, cells: &[usize]) -> CscMatrix {
+        let mut col_ptr = Vec::with_capacity(cells.len() + 1);
+        let mut gene_ids = Vec::new();
+        let mut counts = Vec::new();
+        col_ptr.push(0u64);
+        for &cell in cells {
+            let (genes, values) = self.column(cell);
+            gene_ids.extend_from_slice(genes);
+            counts.extend_from_slice(values);
+            col_ptr.push(gene_ids.len() as u64);
+        }
+        CscMatrix {
+            n_rows: self.n_rows,
+            n_cols: cells.len(),
+            col_ptr,
+            gene_ids,
+            counts,
+        }
+    }
+}
+
+/// Builds the matrix from a cells × features CSR layout (scipy/AnnData `X`),
+/// with the same negative value handling, zero skipping, truncation and
+/// duplicate summing as the MTX readers.
+pub fn csc_from_cell_rows(
+    indptr: &[i64],
+    indices: &[i64],
+    data: &[f64],
+    gene_index: &GeneIndex,
+    strict_input: bool,
+) -> Result<CscMatrix, InputError> {
+    let n_features = gene_index.gene_id_by_feature.len();
+    if indptr.first() != Some(&0)
+        || indptr.windows(2).any(|w| w[0] > w[1])
+        || indptr.last().copied() != Some(indices.len() as i64)
+        || indices.len() != data.len()
+    {
+        return Err(InputError::InvalidInput(format!(
+            "malformed CSR matrix: indptr of length {} over {} indices and {} values",
+            indptr.len(),
+            indices.len(),
+            data.len()
+        )));
+    }
+    let mut negatives = NegativeCounts::new(strict_input);
+    let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); indptr.len() - 1];
+    for (cell, window) in indptr.windows(2).enumerate() {
+        for idx in window[0] as usize..window[1] as usize {
+            let feature = indices[idx];
+            if feature < 0 || feature as usize >= n_features {
+                return Err(InputError::InvalidInput(format!(
+                    "CSR feature index {feature} out of range for {n_features} features"
+                )));
+            }
+            let val_f = negatives.check(data[idx], || {
+                format!("CSR entry cell {cell}, feature {feature}: {}", data[idx])
+            })?;
+            if val_f == 0.0 {
+                continue;
+            }
+            if let Some(gene_id) = gene_index.gene_id_by_feature[feature as usize] {
+                *per_col[cell].entry(gene_id as u32).or_insert(0) += val_f as i64;
+            }
+        }
+    }
+    negatives.warn();
+    Ok(collect_columns(n_features, per_col))
+}
+
+/// Reads the whole matrix as mapped, summed counts per cell. With
+/// `threads > 1`, a genes × cells coordinate matrix is parsed by
+/// [`read_mtx_csc_parallel`]; both readers go through
+/// [`parse_coordinate_entry`] and [`check_entry_count`], so they agree on
+/// every file.
 pub fn read_mtx_csc(
     path: &Path,
     n_features_raw: usize,
     n_cells: usize,
     gene_index: &GeneIndex,
+    transposed: bool,
+    strict_input: bool,
+    threads: usize,
 ) -> Result<CscMatrix, InputError> {
-    let matrix = Reader::with_options(
+    let storage = read_mtx_header(path)?.storage;
+    if threads > 1 && !transposed && storage == MtxStorage::Coordinate {
+        return read_mtx_csc_parallel(
+            path,
+            n_features_raw,
+            n_cells,
+            gene_index,
+            strict_input,
+            threads,
+        );
+    }
+    read_mtx_csc_serial(
         path,
-        ReaderOptions {
-            force_format: Some(DetectedFormat::Mtx10x),
-            strict: true,
-        },
+        n_features_raw,
+        n_cells,
+        gene_index,
+        transposed,
+        strict_input,
     )
-    .read_matrix()
-    .map_err(|e| InputError::Parse(e.message))?;
+}
+
+/// Parses the matrix line by line on the calling thread, in any layout: dense
+/// `array` storage and cells-as-rows matrices (`--assume-transposed`, where
+/// entry `(i, j)` is cell `i`, feature `j`) included.
+fn read_mtx_csc_serial(
+    path: &Path,
+    n_features_raw: usize,
+    n_cells: usize,
+    gene_index: &GeneIndex,
+    transposed: bool,
+    strict_input: bool,
+) -> Result<CscMatrix, InputError> {
+    let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); n_cells];
+    for_each_mtx_entry(
+        path,
+        n_features_raw,
+        n_cells,
+        transposed,
+        strict_input,
+        |feature, cell, val_f| {
+            if val_f == 0.0 {
+                return;
+            }
+            if let Some(gene_id) = gene_index.gene_id_by_feature.get(feature).and_then(|v| *v) {
+                *per_col[cell].entry(gene_id as u32).or_insert(0) += val_f as i64;
+            }
+        },
+    )?;
+    Ok(collect_columns(n_features_raw, per_col))
+}
+
+/// Per-cell sums of the entries on features flagged in `features`, streamed
+/// without building the matrix.
+pub fn sum_feature_counts(
+    path: &Path,
+    n_features_raw: usize,
+    n_cells: usize,
+    transposed: bool,
+    strict_input: bool,
+    features: &[bool],
+) -> Result<Vec<f64>, InputError> {
+    let mut sums = vec![0f64; n_cells];
+    for_each_mtx_entry(
+        path,
+        n_features_raw,
+        n_cells,
+        transposed,
+        strict_input,
+        |feature, cell, val_f| {
+            if features.get(feature).copied().unwrap_or(false) {
+                sums[cell] += (val_f as i64) as f64;
+            }
+        },
+    )?;
+    Ok(sums)
+}
 
-    if matrix.n_genes != n_features_raw {
+/// Streams every entry as 0-based `(feature, cell, value)` after checking the
+/// size line against the bundle, with negative values clamped to zero (or
+/// rejected under `strict_input`); `array` storage yields its zeros as well.
+fn for_each_mtx_entry(
+    path: &Path,
+    n_features_raw: usize,
+    n_cells: usize,
+    transposed: bool,
+    strict_input: bool,
+    mut f: impl FnMut(usize, usize, f64),
+) -> Result<(), InputError> {
+    let mut lines = open_mtx(path)?.lines();
+    let header = parse_mtx_header(path, &mut lines)?;
+    check_mtx_dims(&header, n_features_raw, n_cells, transposed)?;
+
+    // `row` and `col` are 0-based matrix positions.
+    let mut add = |row: usize, col: usize, val_f: f64| {
+        if transposed {
+            f(col, row, val_f);
+        } else {
+            f(row, col, val_f);
+        }
+    };
+
+    let mut negatives = NegativeCounts::new(strict_input);
+    let mut n_values = 0usize;
+    for line in lines {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('%') {
+            continue;
+        }
+        let invalid = || InputError::Parse(format!("invalid MTX entry: {trimmed}"));
+        let entry = || format!("MTX entry: {trimmed}");
+        match header.storage {
+            MtxStorage::Coordinate => {
+                let (row, col, val_f) = parse_coordinate_entry(trimmed, &header, &mut negatives)?;
+                add(row, col, val_f);
+                n_values += 1;
+            }
+            MtxStorage::Array => {
+                for v in trimmed.split_whitespace() {
+                    if n_values == header.nnz {
+                        return Err(InputError::Parse(format!(
+                            "{} has more than the {} values its size line declares",
+                            path.display(),
+                            header.nnz
+                        )));
+                    }
+                    let val_f = negatives.check(parse_mtx_value(v).ok_or_else(invalid)?, entry)?;
+                    add(n_values % header.n_rows, n_values / header.n_rows, val_f);
+                    n_values += 1;
+                }
+            }
+        }
+    }
+    check_entry_count(path, &header, n_values)?;
+    negatives.warn();
+    Ok(())
+}
+
+/// Fails unless the matrix held as many entries (values, for `array`
+/// storage) as its size line declares, as a truncated file would not.
+fn check_entry_count(path: &Path, header: &MtxHeader, n_entries: usize) -> Result<(), InputError> {
+    if n_entries != header.nnz {
+        return Err(InputError::Parse(format!(
+            "{} has {} entries, its size line declares {}",
+            path.display(),
+            n_entries,
+            header.nnz
+        )));
+    }
+    Ok(())
+}
+
+fn check_mtx_dims(
+    header: &MtxHeader,
+    n_features_raw: usize,
+    n_cells: usize,
+    transposed: bool,
+) -> Result<(), InputError> {
+    if transposed && (header.n_rows != n_cells || header.n_cols != n_features_raw) {
         return Err(InputError::InvalidInput(format!(
-            "matrix row count {} does not match features {}",
-            matrix.n_genes, n_features_raw
+            "transposed matrix is {}x{}, expected {} barcodes x {} features",
+            header.n_rows, header.n_cols, n_cells, n_features_raw
         )));
     }
-    if matrix.n_cells != n_cells {
+    if !transposed && (header.n_rows != n_features_raw || header.n_cols != n_cells) {
         return Err(InputError::InvalidInput(format!(
-            "matrix column count {} does not match barcodes {}",
-            matrix.n_cells, n_cells
+            "matrix is {}x{}, expected {} features x {} barcodes",
+            header.n_rows, header.n_cols, n_features_raw, n_cells
         )));
     }
+    Ok(())
+}
 
-    let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); matrix.n_cells];
+/// 0-based `(row, col, value)` of a trimmed `coordinate` entry line: three
+/// fields, or two in a `pattern` matrix, whose entries read as 1.
+fn parse_coordinate_entry(
+    trimmed: &str,
+    header: &MtxHeader,
+    negatives: &mut NegativeCounts,
+) -> Result<(usize, usize, f64), InputError> {
+    let invalid = || InputError::Parse(format!("invalid MTX entry: {trimmed}"));
+    let parts = trimmed.split_whitespace().collect::<Vec<_>>();
+    let (row, col, value) = match (header.field, parts.as_slice()) {
+        (MtxField::Pattern, &[row, col]) => (row, col, None),
+        (MtxField::Integer | MtxField::Real, &[row, col, value]) => (row, col, Some(value)),
+        _ => return Err(invalid()),
+    };
+    let row = row.parse::<usize>().map_err(|_| invalid())?;
+    let col = col.parse::<usize>().map_err(|_| invalid())?;
+    let val_f = match value {
+        Some(v) => negatives.check(parse_mtx_value(v).ok_or_else(invalid)?, || {
+            format!("MTX entry: {trimmed}")
+        })?,
+        None => 1.0,
+    };
+    if row == 0 || row > header.n_rows || col == 0 || col > header.n_cols {
+        return Err(invalid());
+    }
+    Ok((row - 1, col - 1, val_f))
+}
 
-    for (col_idx, window) in matrix.col_ptr.windows(2).enumerate() {
-        let start = window[0];
-        let end = window[1];
-        for idx in start..end {
-            let feature_idx = matrix.row_idx[idx];
-            let val_f = matrix.values[idx];
+// A finite entry value; NaN and infinities are not counts.
+fn parse_mtx_value(v: &str) -> Option<f64> {
+    v.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Parses a genes × cells `coordinate` matrix on `threads` threads. The
+/// decompressed body is read into memory and split at line boundaries into
+/// one chunk per thread; each thread sums its entries into its own per-cell
+/// maps, which are then merged in file order. Counts are summed as integers,
+/// so the result, duplicate entries included, is the serial one.
+fn read_mtx_csc_parallel(
+    path: &Path,
+    n_features_raw: usize,
+    n_cells: usize,
+    gene_index: &GeneIndex,
+    strict_input: bool,
+    threads: usize,
+) -> Result<CscMatrix, InputError> {
+    let mut body = Vec::new();
+    open_mtx(path)?.read_to_end(&mut body)?;
+    let mut cursor = Cursor::new(&body[..]);
+    let header = parse_mtx_header(path, &mut (&mut cursor).lines())?;
+    check_mtx_dims(&header, n_features_raw, n_cells, false)?;
+    let entries = &body[cursor.position() as usize..];
+
+    let mut chunks = Vec::with_capacity(threads);
+    let mut start = 0;
+    for i in 1..=threads {
+        let mut end = (entries.len() * i / threads).max(start);
+        if i < threads {
+            end = entries[end..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(entries.len(), |at| end + at + 1);
+        }
+        chunks.push(&entries[start..end]);
+        start = end;
+    }
+
+    let parse_chunk = |chunk: &[u8]| {
+        let text = std::str::from_utf8(chunk)
+            .map_err(|_| InputError::Parse(format!("{} is not valid UTF-8", path.display())))?;
+        let mut negatives = NegativeCounts::new(strict_input);
+        let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); n_cells];
+        let mut n_entries = 0usize;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('%') {
+                continue;
+            }
+            let (feature, cell, val_f) = parse_coordinate_entry(trimmed, &header, &mut negatives)?;
+            n_entries += 1;
             if val_f == 0.0 {
                 continue;
             }
-            let val = val_f as i64;
-            if let Some(gene_id) = gene_index
+            if let Some(gene_id) = gene_index.gene_id_by_feature.get(feature).and_then(|v| *v) {
+                *per_col[cell].entry(gene_id as u32).or_insert(0) += val_f as i64;
+            }
+        }
+        Ok::<_, InputError>((per_col, negatives, n_entries))
+    };
+    let results = std::thread::scope(|scope| {
+        let handles = chunks
+            .iter()
+            .map(|chunk| scope.spawn(|| parse_chunk(chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("MTX parser thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    // Chunks are in file order, so the first error is the one a serial read
+    // would report.
+    let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); n_cells];
+    let mut negatives = NegativeCounts::new(strict_input);
+    let mut n_entries = 0usize;
+    for result in results {
+        let (chunk_cols, chunk_negatives, chunk_entries) = result?;
+        for (merged, col) in per_col.iter_mut().zip(chunk_cols) {
+            for (gene_id, count) in col {
+                *merged.entry(gene_id).or_insert(0) += count;
+            }
+        }
+        negatives.merge(chunk_negatives);
+        n_entries += chunk_entries;
+    }
+    check_entry_count(path, &header, n_entries)?;
+    negatives.warn();
+    Ok(collect_columns(n_features_raw, per_col))
+}
+
+/// Bytes per spilled `(cell, gene_id, count)` record.
+const SPILL_RECORD_BYTES: usize = 16;
+
+/// Streams the matrix once into `chunk_paths.len()` files of `chunk_cells`
+/// consecutive cells each, as little-endian `(cell within chunk, gene_id,
+/// count)` records of mapped, non-zero entries. Values go through `as i64`
+/// exactly as in [`read_mtx_csc`], so a chunk read back with
+/// [`read_mtx_chunk`] holds the same columns. Returns the per-cell counts of
+/// the controls in `bundle.control_by_feature`, summed on the way.
+pub fn spill_mtx_chunks(
+    bundle: &InputBundle,
+    chunk_cells: usize,
+    chunk_paths: &[PathBuf],
+) -> Result<Option<Vec<f64>>, InputError> {
+    let mut writers = chunk_paths
+        .iter()
+        .map(|p| File::create(p).map(BufWriter::new))
+        .collect::<Result<Vec<_>, _>>()?;
+    let controls = bundle.control_by_feature.as_deref();
+    let mut control_counts = controls.map(|_| vec![0f64; bundle.n_cells]);
+    let mut failed = None;
+    for_each_mtx_entry(
+        &bundle.mtx_path,
+        bundle.n_features_raw,
+        bundle.n_cells,
+        bundle.transposed,
+        bundle.strict_input,
+        |feature, cell, val_f| {
+            if val_f == 0.0 || failed.is_some() {
+                return;
+            }
+            let Some(gene_id) = bundle
+                .gene_index
                 .gene_id_by_feature
-                .get(feature_idx)
+                .get(feature)
                 .and_then(|v| *v)
-            {
-                let entry = per_col[col_idx].entry(gene_id as u32).or_insert(0);
-                *entry += val;
+            else {
+                if let (Some(controls), Some(sums)) = (controls, control_counts.as_mut())
+                    && controls.get(feature).copied().unwrap_or(false)
+                {
+                    sums[cell] += (val_f as i64) as f64;
+                }
+                return;
+            };
+            let mut record = [0u8; SPILL_RECORD_BYTES];
+            record[..4].copy_from_slice(&((cell % chunk_cells) as u32).to_le_bytes());
+            record[4..8].copy_from_slice(&(gene_id as u32).to_le_bytes());
+            record[8..].copy_from_slice(&(val_f as i64).to_le_bytes());
+            if let Err(e) = writers[cell / chunk_cells].write_all(&record) {
+                failed = Some(e);
             }
+        },
+    )?;
+    if let Some(e) = failed {
+        return Err(e.into());
+    }
+    for writer in &mut writers {
+        writer.flush()?;
+    }
+    Ok(control_counts)
+}
+
+/// Streams a real-valued coordinate MTX column by column. Entries go to a
+/// side file first, since the size line needs the final entry count;
+/// [`MtxWriter::finish`] writes the header and appends them.
+pub struct MtxWriter {
+    path: PathBuf,
+    body_path: PathBuf,
+    body: BufWriter<File>,
+    n_rows: usize,
+    n_cols: usize,
+    nnz: u64,
+}
+
+impl MtxWriter {
+    pub fn create(path: &Path, n_rows: usize, n_cols: usize) -> Result<Self, InputError> {
+        let mut body_path = path.as_os_str().to_owned();
+        body_path.push(".body");
+        let body_path = PathBuf::from(body_path);
+        Ok(Self {
+            path: path.to_path_buf(),
+            body: BufWriter::new(File::create(&body_path)?),
+            body_path,
+            n_rows,
+            n_cols,
+            nnz: 0,
+        })
+    }
+
+    /// Adds one entry; `row` and `col` are 0-based. `value` is written in
+    /// its shortest round-trip form.
+    pub fn push(&mut self, row: usize, col: usize, value: f32) -> Result<(), InputError> {
+        writeln!(self.body, "{} {} {}", row + 1, col + 1, value)?;
+        self.nnz += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), InputError> {
+        self.body.flush()?;
+        drop(self.body);
+        let mut out = BufWriter::new(File::create(&self.path)?);
+        writeln!(out, "%%MatrixMarket matrix coordinate real general")?;
+        writeln!(out, "{} {} {}", self.n_rows, self.n_cols, self.nnz)?;
+        std::io::copy(&mut File::open(&self.body_path)?, &mut out)?;
+        out.flush()?;
+        std::fs::remove_file(&self.body_path)?;
+        Ok(())
+    }
+}
+
+/// Reads one file written by [`spill_mtx_chunks`] into a `n_cells`-column matrix.
+pub fn read_mtx_chunk(
+    path: &Path,
+    n_features_raw: usize,
+    n_cells: usize,
+) -> Result<CscMatrix, InputError> {
+    let mut per_col: Vec<BTreeMap<u32, i64>> = vec![BTreeMap::new(); n_cells];
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut record = [0u8; SPILL_RECORD_BYTES];
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
         }
+        let cell = u32::from_le_bytes(record[..4].try_into().unwrap()) as usize;
+        let gene_id = u32::from_le_bytes(record[4..8].try_into().unwrap());
+        let count = i64::from_le_bytes(record[8..].try_into().unwrap());
+        let column = per_col.get_mut(cell).ok_or_else(|| {
+            InputError::Parse(format!("{}: cell {cell} outside chunk", path.display()))
+        })?;
+        *column.entry(gene_id).or_insert(0) += count;
     }
+    Ok(collect_columns(n_features_raw, per_col))
+}
 
-    let mut cols_vec: Vec<Vec<(u32, i64)>> = Vec::with_capacity(matrix.n_cells);
+pub(crate) fn collect_columns(n_rows: usize, per_col: Vec<BTreeMap<u32, i64>>) -> CscMatrix {
+    let n_cols = per_col.len();
+    let nnz = per_col.iter().map(BTreeMap::len).sum::<usize>();
+    let mut col_ptr = Vec::with_capacity(n_cols + 1);
+    let mut gene_ids = Vec::with_capacity(nnz);
+    let mut counts = Vec::with_capacity(nnz);
+    col_ptr.push(0u64);
     for map in per_col {
-        let mut col_vec = Vec::with_capacity(map.len());
-        for (gene, v) in map {
-            col_vec.push((gene, v));
+        for (gene_id, count) in map {
+            gene_ids.push(gene_id);
+            counts.push(count);
         }
-        cols_vec.push(col_vec);
+        col_ptr.push(gene_ids.len() as u64);
+    }
+    CscMatrix {
+        n_rows,
+        n_cols,
+        col_ptr,
+        gene_ids,
+        counts,
     }
-
-    Ok(CscMatrix {
-        n_rows: matrix.n_genes,
-        n_cols: matrix.n_cells,
-        cols: cols_vec,
-    })
 }
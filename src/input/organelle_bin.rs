@@ -1,6 +1,8 @@
 use std::fs::File;
 use std::path::Path;
+use std::sync::Arc;
 
+use kira_shared_sc_cache::SharedCacheMmap;
 use memmap2::Mmap;
 
 use crate::input::InputError;
@@ -24,14 +26,36 @@ pub struct HeaderV1 {
     pub data_crc64: u64,
 }
 
+/// CSC counts borrowed straight from the mapped bin; section bounds, alignment
+/// and `col_ptr` monotonicity are checked once by `mmap_shared_cache`.
 #[derive(Debug, Clone)]
 pub struct CscView {
     pub n_genes: usize,
     pub n_cells: usize,
     pub nnz: usize,
-    pub col_ptr: Vec<u64>,
-    pub row_idx: Vec<u32>,
-    pub values: Vec<u32>,
+    mapped: Arc<SharedCacheMmap>,
+}
+
+impl CscView {
+    pub fn col_ptr(&self) -> &[u64] {
+        self.mapped.col_ptr()
+    }
+
+    pub fn row_idx(&self) -> &[u32] {
+        self.mapped.row_idx()
+    }
+
+    pub fn values(&self) -> &[u32] {
+        self.mapped.values_u32()
+    }
+
+    /// Feature rows and raw counts stored for `cell`.
+    pub fn column(&self, cell: usize) -> (&[u32], &[u32]) {
+        let col_ptr = self.col_ptr();
+        let start = col_ptr[cell] as usize;
+        let end = col_ptr[cell + 1] as usize;
+        (&self.row_idx()[start..end], &self.values()[start..end])
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -43,34 +67,96 @@ pub struct OrganelleBin {
 }
 
 pub fn read_organelle_bin(path: &Path) -> Result<OrganelleBin, InputError> {
-    let shared = kira_shared_sc_cache::read_shared_cache_owned(path).map_err(map_err)?;
-
     let file = File::open(path)?;
+    // SAFETY: the bin is read-only input; we assume no process truncates or
+    // rewrites it while mapped. Section bounds are checked against its length.
     let mmap = unsafe { Mmap::map(&file)? };
     if mmap.len() < 256 {
         return Err(InputError::InvalidInput(
             "kira-organelle.bin too small".to_string(),
         ));
     }
-
     let header = parse_header(&mmap[..256])?;
+    // The shared reader casts header offsets to `usize` and adds them
+    // unchecked; reject anything that would truncate or wrap first.
+    validate_sections(&header, mmap.len())?;
+
+    let mut shared = kira_shared_sc_cache::mmap_shared_cache(path).map_err(map_err)?;
+    let genes = std::mem::take(&mut shared.genes);
+    let barcodes = std::mem::take(&mut shared.barcodes);
     let csc = CscView {
-        n_genes: shared.n_genes as usize,
-        n_cells: shared.n_cells as usize,
-        nnz: shared.nnz as usize,
-        col_ptr: shared.col_ptr,
-        row_idx: shared.row_idx,
-        values: shared.values_u32,
+        n_genes: shared.n_genes,
+        n_cells: shared.n_cells,
+        nnz: shared.nnz,
+        mapped: Arc::new(shared),
     };
 
     Ok(OrganelleBin {
         header,
-        genes: shared.genes,
-        barcodes: shared.barcodes,
+        genes,
+        barcodes,
         csc,
     })
 }
 
+/// Checks that every section ends inside the file, with all offset and size
+/// arithmetic done in checked `u64` and every value fitting a `usize`.
+fn validate_sections(header: &HeaderV1, file_len: usize) -> Result<(), InputError> {
+    for (name, value) in [
+        ("n_genes", header.n_genes),
+        ("n_cells", header.n_cells),
+        ("nnz", header.nnz),
+    ] {
+        to_usize(name, value)?;
+    }
+    let col_ptr_bytes = header
+        .n_cells
+        .checked_add(1)
+        .and_then(|n| n.checked_mul(8))
+        .ok_or_else(|| overflow("col_ptr"))?;
+    let nnz_bytes = header
+        .nnz
+        .checked_mul(4)
+        .ok_or_else(|| overflow("row_idx"))?;
+    for (name, offset, bytes) in [
+        (
+            "genes_table",
+            header.genes_table_offset,
+            header.genes_table_bytes,
+        ),
+        (
+            "barcodes_table",
+            header.barcodes_table_offset,
+            header.barcodes_table_bytes,
+        ),
+        ("col_ptr", header.col_ptr_offset, col_ptr_bytes),
+        ("row_idx", header.row_idx_offset, nnz_bytes),
+        ("values_u32", header.values_u32_offset, nnz_bytes),
+    ] {
+        let end = offset.checked_add(bytes).ok_or_else(|| overflow(name))?;
+        if to_usize(name, end)? > file_len {
+            return Err(InputError::InvalidInput(format!(
+                "kira-organelle.bin section {name} ends at byte {end}, past the end of the file ({file_len} bytes)"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn to_usize(name: &str, value: u64) -> Result<usize, InputError> {
+    usize::try_from(value).map_err(|_| {
+        InputError::InvalidInput(format!(
+            "kira-organelle.bin {name} ({value}) does not fit in memory on this platform"
+        ))
+    })
+}
+
+fn overflow(name: &str) -> InputError {
+    InputError::InvalidInput(format!(
+        "kira-organelle.bin section {name} overflows its offset arithmetic"
+    ))
+}
+
 fn parse_header(bytes: &[u8]) -> Result<HeaderV1, InputError> {
     if &bytes[0..4] != b"KORG" {
         return Err(InputError::InvalidInput(
@@ -123,7 +209,7 @@ fn map_err(err: kira_shared_sc_cache::SharedCacheError) -> InputError {
     match err {
         kira_shared_sc_cache::SharedCacheError::Io { source, .. } => InputError::Io(source),
         kira_shared_sc_cache::SharedCacheError::Format { message, .. } => {
-            InputError::InvalidInput(message)
+            InputError::InvalidInput(format!("kira-organelle.bin: {message}"))
         }
     }
 }
@@ -0,0 +1,19 @@
+//! Nuclear state and transcriptional plasticity scoring for 10x scRNA-seq.
+//!
+//! [`run_pipeline`] runs one dataset end to end, as the `kira-nuclearqc`
+//! binary does; the stage modules are public for callers that need a single
+//! stage.
+
+pub mod bench;
+pub mod input;
+pub mod memory;
+pub mod metrics;
+pub mod model;
+pub mod panels;
+pub mod pipeline;
+pub mod report;
+mod run;
+pub mod simd;
+pub mod tracing;
+
+pub use run::{CsrCounts, DEFAULT_SEED, RunConfig, RunError, RunOutputs, run_counts, run_pipeline};
@@ -1,309 +1,277 @@
-mod input;
-mod metrics;
-mod model;
-mod panels;
-mod pipeline;
-mod report;
-mod simd;
-mod tracing;
-
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
-use crate::input::{load_input_organelle, load_input_tenx, resolve_shared_bin};
-use crate::model::thresholds::{NuclearScoringMode, ThresholdProfile};
-use crate::pipeline::stage2_normalize::{Stage2Params, build_expr_accessor};
-use crate::pipeline::stage3_panels::run_stage3;
-use crate::pipeline::stage4_axes::run_stage4;
-use crate::pipeline::stage5_scores::{Stage5Inputs, run_stage5};
-use crate::pipeline::stage6_classify::{Stage6Inputs, run_stage6};
-use crate::pipeline::stage7_report::{
-    PipelineContext, ReportMode, RunMode, Stage7Input, write_reports,
+use kira_nuclearqc::bench::{SyntheticData, SyntheticSpec};
+use kira_nuclearqc::input::cache::CacheCodec;
+use kira_nuclearqc::input::features::FeatureColumns;
+use kira_nuclearqc::input::mtx::find_matrix_path;
+use kira_nuclearqc::input::{
+    InputError, InputFormat, InputSourceKind, detect_prefix, load_gene_index, resolve_shared_bin,
+    validate_input,
+};
+use kira_nuclearqc::model::axes::EntropyUnit;
+use kira_nuclearqc::model::scores::CustomComposite;
+use kira_nuclearqc::model::thresholds::{
+    ConfidenceModel, NamedProfile, RelativeWithin, ThresholdProfile,
+};
+use kira_nuclearqc::panels::loader::{DEFAULT_MAX_RULE_GENES, MinMappable};
+use kira_nuclearqc::panels::{self, PanelScoreMode};
+use kira_nuclearqc::pipeline::stage2_normalize::{
+    DEFAULT_PEARSON_THETA, NormalizeMode, Stage2Error,
 };
-use crate::report::p90;
+use kira_nuclearqc::pipeline::stage7_report::{ReportMode, RunMode};
+use kira_nuclearqc::report::QcGates;
+use kira_nuclearqc::report::batch::{BatchSample, render_batch_json, render_batch_tsv};
+use kira_nuclearqc::simd::{self, SumMode};
+use kira_nuclearqc::tracing::{LogLevel, set_log_level};
+use kira_nuclearqc::{DEFAULT_SEED, RunConfig, RunError, run_counts, run_pipeline};
 
 fn main() {
-    println!("SIMD backend: {}", simd::backend_name());
     if let Err(err) = run() {
-        eprintln!("{err}");
-        std::process::exit(1);
+        eprintln!("{}", err.message);
+        std::process::exit(err.code);
     }
 }
 
-fn run() -> Result<(), String> {
-    let args = std::env::args().skip(1).collect::<Vec<_>>();
-    let config = parse_args(&args)?;
-
-    let out_dir = resolve_output_dir(&config.out_dir, config.run_mode);
-
-    let (bundle, input_source, shared_bin) = if let Some(cache_path) = config.cache_path.as_ref() {
-        if !cache_path.exists() {
-            return Err(format!(
-                "shared cache path does not exist: {}",
-                cache_path.display()
-            ));
-        }
-        match load_input_organelle(&config.input_dir, config.meta_path.as_deref(), cache_path) {
-            Ok(bundle) => (
-                bundle,
-                cache_path.display().to_string(),
-                Some(cache_path.display().to_string()),
-            ),
-            Err(err) => {
-                crate::warn!(
-                    "failed reading shared cache {}: {}; falling back to 10x MTX reading",
-                    cache_path.display(),
-                    err
-                );
-                (
-                    load_input_tenx(&config.input_dir, config.meta_path.as_deref())
-                        .map_err(|e| e.to_string())?,
-                    "10x".to_string(),
-                    None,
-                )
-            }
-        }
-    } else {
-        match config.run_mode {
-            RunMode::Standalone => (
-                load_input_tenx(&config.input_dir, config.meta_path.as_deref())
-                    .map_err(|e| e.to_string())?,
-                "10x".to_string(),
-                None,
-            ),
-            RunMode::Pipeline => {
-                let resolution =
-                    resolve_shared_bin(&config.input_dir).map_err(|e| e.to_string())?;
-                if resolution.exists {
-                    match load_input_organelle(
-                        &config.input_dir,
-                        config.meta_path.as_deref(),
-                        &resolution.path,
-                    ) {
-                        Ok(bundle) => (
-                            bundle,
-                            "kira-organelle.bin".to_string(),
-                            Some(resolution.name),
-                        ),
-                        Err(err) => {
-                            crate::warn!(
-                                "failed reading shared cache {}: {}; falling back to 10x MTX reading (slower).",
-                                resolution.path.display(),
-                                err
-                            );
-                            (
-                                load_input_tenx(&config.input_dir, config.meta_path.as_deref())
-                                    .map_err(|e| e.to_string())?,
-                                "10x".to_string(),
-                                None,
-                            )
-                        }
-                    }
-                } else {
-                    crate::warn!(
-                        "--run-mode pipeline requested but shared cache file {} was not found; falling back to 10x MTX reading (slower).",
-                        resolution.name
-                    );
-                    (
-                        load_input_tenx(&config.input_dir, config.meta_path.as_deref())
-                            .map_err(|e| e.to_string())?,
-                        "10x".to_string(),
-                        None,
-                    )
-                }
-            }
-        }
-    };
-
-    let stage2 = Stage2Params {
-        normalize: config.normalize,
-        cache_normalized: config.cache_normalized,
-        cache_path: None,
-    };
-    let accessor = build_expr_accessor(&bundle, &stage2).map_err(|e| e.to_string())?;
-
-    let stage3 = run_stage3(&bundle, accessor.as_ref()).map_err(|e| e.to_string())?;
-    let thresholds = match config.scoring_mode {
-        NuclearScoringMode::ImmuneAware => ThresholdProfile::immune_v1(),
-        NuclearScoringMode::StrictBulk => ThresholdProfile::default_v1(),
-    };
-    let stage4 = run_stage4(
-        accessor.as_ref(),
-        &bundle.gene_index,
-        bundle.species,
-        &stage3.panels,
-        &stage3.scores,
-        &thresholds,
-    );
-    log_scoring_mode(config.scoring_mode, &stage3, &stage4);
-
-    let key_panel_coverage_median = compute_key_panel_coverage(&stage3.panels, &stage3.scores);
-    let ambient_rna_risk = vec![false; bundle.n_cells];
-    let axis_p90 = [
-        p90(&stage4.axes.iaa),
-        p90(&stage4.axes.dfa),
-        p90(&stage4.axes.nsai),
-    ];
-
-    let (program_sum, sum_tf, proliferation_share, key_panels_missing, panel_nonzero_fraction) =
-        compute_panel_signals(&stage3.panels, &stage3.scores, &stage3.audits);
-
-    let stage5 = run_stage5(&Stage5Inputs {
-        axes: &stage4.axes,
-        drivers: &stage4.drivers,
-        thresholds: &thresholds,
-        n_genes_mappable: Some(bundle.n_genes_indexed as u32),
-        key_panel_coverage_median: Some(&key_panel_coverage_median),
-        ambient_rna_risk: Some(&ambient_rna_risk),
-        key_panels_missing: Some(&key_panels_missing),
-        panel_nonzero_fraction: Some(&panel_nonzero_fraction),
-        axis_p90: Some(axis_p90),
-        scoring_mode: config.scoring_mode,
-        include_ddr: true,
-    });
+/// A parsed `run` command line: the settings of each run plus what only the
+/// CLI acts on.
+#[derive(Debug, Clone)]
+struct CliArgs {
+    config: RunConfig,
+    /// Every `--input`, split into one run per dataset.
+    input_dirs: Vec<PathBuf>,
+    /// `--batch`: per-dataset outputs even for a single input.
+    batch: bool,
+    /// Check the input and exit.
+    validate_only: bool,
+    /// Print the panel audit and exit.
+    print_panels: bool,
+    log_level: LogLevel,
+}
 
-    let stage6 = run_stage6(&Stage6Inputs {
-        tbi: &stage4.axes.tbi,
-        rci: &stage4.axes.rci,
-        pds: &stage4.axes.pds,
-        trs: &stage4.axes.trs,
-        nsai: &stage4.axes.nsai,
-        iaa: &stage4.axes.iaa,
-        dfa: &stage4.axes.dfa,
-        cea: &stage4.axes.cea,
-        rss: &stage4.axes.rss,
-        drbi: &stage4.axes.drbi,
-        cci: &stage4.axes.cci,
-        trci: &stage4.axes.trci,
-        scores: &stage5.scores,
-        drivers: &stage4.drivers,
-        thresholds: &thresholds,
-        scoring_mode: config.scoring_mode,
-        key_panel_coverage_median: Some(&key_panel_coverage_median),
-        key_panels_missing: Some(&key_panels_missing),
-        sum_tf_panels: Some(&sum_tf),
-        ambient_rna_risk: Some(&ambient_rna_risk),
-        proliferation_program_share: Some(&proliferation_share),
-        program_sum: Some(&program_sum),
-    });
+/// A failed invocation: the message for stderr and the process exit code.
+#[derive(Debug)]
+struct CliError {
+    code: i32,
+    message: String,
+}
 
-    let (sample, condition, species_per_cell, cluster_labels) = extract_meta(&bundle);
+/// Exit code for errors other than input errors.
+const EXIT_FAILURE: i32 = 1;
 
-    let mut libsize_vec = Vec::with_capacity(bundle.n_cells);
-    let mut nnz_vec = Vec::with_capacity(bundle.n_cells);
-    for cell in 0..bundle.n_cells {
-        libsize_vec.push(accessor.libsize(cell));
-        nnz_vec.push(accessor.nnz(cell));
+/// Exit code for an input error: 2 missing input, 3 invalid input, 4 parse
+/// error, 5 IO error.
+fn input_exit_code(err: &InputError) -> i32 {
+    match err {
+        InputError::MissingInput(_) => 2,
+        InputError::InvalidInput(_) => 3,
+        InputError::Parse(_) => 4,
+        InputError::Io(_) => 5,
     }
-    let expressed_vec = stage4
-        .drivers
-        .iter()
-        .map(|d| d.expressed_genes)
-        .collect::<Vec<_>>();
-
-    let input = Stage7Input {
-        barcodes: &bundle.barcodes,
-        sample: sample.as_deref(),
-        condition: condition.as_deref(),
-        species_per_cell: species_per_cell.as_deref(),
-        cluster_labels: cluster_labels.as_deref(),
-        species_global: format!("{:?}", bundle.species),
-
-        libsize: &libsize_vec,
-        nnz: &nnz_vec,
-        expressed_genes: &expressed_vec,
-
-        axes_tbi: &stage4.axes.tbi,
-        axes_rci: &stage4.axes.rci,
-        axes_pds: &stage4.axes.pds,
-        axes_trs: &stage4.axes.trs,
-        axes_nsai: &stage4.axes.nsai,
-        axes_iaa: &stage4.axes.iaa,
-        axes_dfa: &stage4.axes.dfa,
-        axes_cea: &stage4.axes.cea,
-        ddr_rss: &stage4.axes.rss,
-        ddr_drbi: &stage4.axes.drbi,
-        ddr_cci: &stage4.axes.cci,
-        ddr_trci: &stage4.axes.trci,
-        genome_stability: &stage4.genome_stability,
-        genome_stability_norm: &stage4.genome_stability_norm,
-        genome_stability_panel_version: stage4.genome_stability_panel_version,
-        genome_stability_panel_audits: &stage4.genome_stability_panel_audits,
-
-        scores: &stage5.scores,
-        drivers: &stage5.drivers,
-        activation_mode: format!("{:?}", thresholds.activation_mode),
+}
 
-        classifications: &stage6,
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError {
+            code: EXIT_FAILURE,
+            message,
+        }
+    }
+}
 
-        panel_set: &stage3.panels,
-        panel_audits: &stage3.audits,
-        panel_scores: &stage3.scores,
+impl From<InputError> for CliError {
+    fn from(err: InputError) -> Self {
+        CliError {
+            code: input_exit_code(&err),
+            message: err.to_string(),
+        }
+    }
+}
 
-        tool_name: "kira-nuclearqc".to_string(),
-        tool_version: env!("CARGO_PKG_VERSION").to_string(),
-        git_hash: read_git_hash(&PathBuf::from(".")),
-        simd_backend: simd::backend_name().to_string(),
+impl From<RunError> for CliError {
+    fn from(err: RunError) -> Self {
+        let code = match &err {
+            RunError::Input(e) | RunError::Stage2(Stage2Error::Input(e)) => input_exit_code(e),
+            _ => EXIT_FAILURE,
+        };
+        CliError {
+            code,
+            message: err.to_string(),
+        }
+    }
+}
 
-        n_genes_raw: bundle.n_features_raw,
-        n_genes_mappable: bundle.n_genes_indexed,
+fn run() -> Result<(), CliError> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|cmd| cmd == "bench") {
+        println!("SIMD backend: {}", simd::backend_name());
+        let bench = parse_bench_args(&args[1..])?;
+        set_log_level(LogLevel::Warn);
+        return run_bench(&bench);
+    }
+    let cli = parse_args(&args)?;
+    // With --cell-stdout, stdout carries nothing but the TSV.
+    if cli.config.cell_stdout {
+        eprintln!("SIMD backend: {}", simd::backend_name());
+    } else {
+        println!("SIMD backend: {}", simd::backend_name());
+    }
+    set_log_level(cli.log_level);
+    run_datasets(&cli)
+}
 
-        normalize: config.normalize,
-        scale: 10_000.0,
-        log1p: config.normalize,
-        confidence_breakdown: Some(&stage5.scores.confidence_breakdown),
-        scoring_mode: match config.scoring_mode {
-            NuclearScoringMode::ImmuneAware => "immune-aware (default)".to_string(),
-            NuclearScoringMode::StrictBulk => "strict (bulk-oriented)".to_string(),
-        },
-        pipeline_context: if config.run_mode == RunMode::Pipeline {
-            Some(PipelineContext {
-                input_dir: config.input_dir.display().to_string(),
-                input_source,
-                shared_bin,
-                run_mode: "pipeline".to_string(),
-            })
+fn run_datasets(cli: &CliArgs) -> Result<(), CliError> {
+    let multi = is_multi_dataset(cli);
+    let mut samples = Vec::new();
+    for dataset in dataset_configs(cli)? {
+        if multi {
+            kira_nuclearqc::info!(
+                "dataset {} -> {}",
+                dataset.input_dir.display(),
+                dataset.out_dir.display()
+            );
+        }
+        if cli.print_panels {
+            print!(
+                "{}",
+                panel_audit_tsv(
+                    &dataset.input_dir,
+                    dataset.panels_path.as_deref(),
+                    dataset.panel_rule_max_genes,
+                    dataset.feature_columns,
+                )?
+            );
+        } else if cli.validate_only {
+            validate_only(&dataset)?;
+        } else if multi {
+            // One failing dataset must not abort the others.
+            let name = dataset_name(&dataset);
+            let input_dir = dataset.input_dir.clone();
+            let outcome = run_pipeline(dataset)
+                .map(|outputs| outputs.summary)
+                .map_err(|e| e.to_string());
+            if let Err(err) = &outcome {
+                kira_nuclearqc::error!("dataset {name} failed: {err}");
+            }
+            samples.push(BatchSample {
+                name,
+                input_dir,
+                outcome,
+            });
         } else {
-            None
-        },
-    };
-
-    write_reports(&input, &out_dir, config.report_mode).map_err(|e| e.to_string())?;
-
+            run_pipeline(dataset)?;
+        }
+    }
+    if samples.is_empty() {
+        return Ok(());
+    }
+    write_batch_summary(&cli.config.out_dir, &samples)?;
+    let n_failed = samples.iter().filter(|s| s.outcome.is_err()).count();
+    if n_failed > 0 {
+        return Err(format!("{n_failed} of {} datasets failed", samples.len()).into());
+    }
     Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct RunConfig {
-    input_dir: PathBuf,
-    out_dir: PathBuf,
-    cache_path: Option<PathBuf>,
-    report_mode: ReportMode,
-    meta_path: Option<PathBuf>,
-    normalize: bool,
-    cache_normalized: bool,
-    scoring_mode: NuclearScoringMode,
-    run_mode: RunMode,
+fn dataset_name(dataset: &RunConfig) -> String {
+    dataset
+        .out_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn write_batch_summary(out_dir: &Path, samples: &[BatchSample]) -> Result<(), String> {
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| format!("failed creating {}: {e}", out_dir.display()))?;
+    for (name, body) in [
+        ("batch_summary.tsv", render_batch_tsv(samples)),
+        ("batch_summary.json", render_batch_json(samples)),
+    ] {
+        let path = out_dir.join(name);
+        std::fs::write(&path, body)
+            .map_err(|e| format!("failed writing {}: {e}", path.display()))?;
+    }
+    Ok(())
 }
 
-fn parse_args(args: &[String]) -> Result<RunConfig, String> {
+fn parse_args(args: &[String]) -> Result<CliArgs, String> {
     if args.is_empty() {
         return Err("missing command".to_string());
     }
+    let invocation = args.to_vec();
     let mut args = args.to_vec();
     let cmd = args.remove(0);
     if cmd != "run" {
         return Err("unsupported command".to_string());
     }
 
-    let mut input_dir: Option<PathBuf> = None;
+    let mut input_dirs: Vec<PathBuf> = Vec::new();
+    let mut batch = false;
     let mut out_dir: Option<PathBuf> = None;
     let mut report_mode = ReportMode::Cell;
     let mut cache_path: Option<PathBuf> = None;
     let mut meta_path: Option<PathBuf> = None;
-    let mut normalize = false;
+    let mut extra_meta_paths = Vec::new();
+    let mut meta_by_order = false;
+    let mut clusters_path: Option<PathBuf> = None;
+    let mut contrast: Option<String> = None;
+    let mut contrast_reference: Option<String> = None;
+    let mut normalize: Option<bool> = None;
+    let mut normalize_mode: Option<NormalizeMode> = None;
+    let mut pearson_theta = DEFAULT_PEARSON_THETA;
     let mut cache_normalized = false;
-    let mut scoring_mode = NuclearScoringMode::ImmuneAware;
+    let mut cache_codec = CacheCodec::None;
+    let mut profile = NamedProfile::Immune;
+    let mut confidence_model = None;
+    let mut custom_composite = None;
     let mut run_mode = RunMode::Standalone;
+    let mut input_format = InputFormat::TenX;
+    let mut cea_ribo_adjust = false;
+    let mut sum_mode = SumMode::Blocked;
+    let mut validate_only = false;
+    let mut legacy_quantiles = false;
+    let mut key_panels: Option<Vec<String>> = None;
+    let mut explain_panel: Option<String> = None;
+    let mut panels_path: Option<PathBuf> = None;
+    let mut only_panels: Option<Vec<String>> = None;
+    let mut disable_panels: Vec<String> = Vec::new();
+    let mut min_mappable = MinMappable::default();
+    let mut strict_mappable = false;
+    let mut qc_gates = QcGates::default();
+    let mut panel_rule_max_genes = DEFAULT_MAX_RULE_GENES;
+    let mut print_panels = false;
+    let mut assume_transposed = false;
+    let mut feature_columns = FeatureColumns::default();
+    let mut strict_input = false;
+    let mut relative_within = RelativeWithin::Global;
+    let mut relative_min_group_cells = ThresholdProfile::default_v1().relative_min_group_cells;
+    let mut panel_score = PanelScoreMode::Sum;
+    let mut winsorize_panels: Option<f32> = None;
+    let mut mito_max: Option<f32> = None;
+    let mut ribo_max: Option<f32> = None;
+    let mut control_prefixes: Vec<String> = Vec::new();
+    let mut tail_thresholds: Option<[f32; 3]> = None;
+    let mut log_level = LogLevel::Info;
+    let mut fail_on_warn = false;
+    let mut emit_drivers = false;
+    let mut entropy_unit = EntropyUnit::Nats;
+    let mut confidence_breakdown = false;
+    let mut emit_step_json = false;
+    let mut emit_obs_csv = false;
+    let mut emit_program_shares = false;
+    let mut emit_axis_correlations = false;
+    let mut emit_long = false;
+    let mut cell_stdout = false;
+    let mut explain_regimes = false;
+    let mut axis_percentiles = false;
+    let mut emit_panel_detection = false;
+    let mut emit_normalized_mtx = false;
+    let mut output_prefix = String::new();
+    let mut chunk_cells: Option<usize> = None;
+    let mut threads = 1usize;
+    let mut max_cells: Option<usize> = None;
+    let mut seed = DEFAULT_SEED;
 
     let mut i = 0usize;
     while i < args.len() {
@@ -313,7 +281,22 @@ fn parse_args(args: &[String]) -> Result<RunConfig, String> {
                 if i >= args.len() {
                     return Err("missing value for --input".to_string());
                 }
-                input_dir = Some(PathBuf::from(&args[i]));
+                input_dirs.push(PathBuf::from(&args[i]));
+            }
+            "--inputs-file" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --inputs-file".to_string());
+                }
+                input_dirs.extend(read_inputs_file(Path::new(&args[i]))?);
+            }
+            "--batch" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --batch".to_string());
+                }
+                input_dirs.extend(batch_input_dirs(Path::new(&args[i]))?);
+                batch = true;
             }
             "--out" => {
                 i += 1;
@@ -322,6 +305,18 @@ fn parse_args(args: &[String]) -> Result<RunConfig, String> {
                 }
                 out_dir = Some(PathBuf::from(&args[i]));
             }
+            "--output-prefix" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --output-prefix".to_string());
+                }
+                if args[i].contains(['/', '\\']) {
+                    return Err(
+                        "invalid --output-prefix (must not contain path separators)".to_string()
+                    );
+                }
+                output_prefix = args[i].clone();
+            }
             "--cache" => {
                 i += 1;
                 if i >= args.len() {
@@ -337,7 +332,8 @@ fn parse_args(args: &[String]) -> Result<RunConfig, String> {
                 report_mode = match args[i].as_str() {
                     "cell" => ReportMode::Cell,
                     "sample" => ReportMode::Sample,
-                    _ => return Err("invalid --mode (use cell|sample)".to_string()),
+                    "cluster" => ReportMode::Cluster,
+                    _ => return Err("invalid --mode (use cell|sample|cluster)".to_string()),
                 };
             }
             "--meta" => {
@@ -345,16 +341,465 @@ fn parse_args(args: &[String]) -> Result<RunConfig, String> {
                 if i >= args.len() {
                     return Err("missing value for --meta".to_string());
                 }
-                meta_path = Some(PathBuf::from(&args[i]));
+                if meta_path.is_none() {
+                    meta_path = Some(PathBuf::from(&args[i]));
+                } else {
+                    extra_meta_paths.push(PathBuf::from(&args[i]));
+                }
+            }
+            "--meta-by-order" => {
+                meta_by_order = true;
+            }
+            "--clusters" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --clusters".to_string());
+                }
+                clusters_path = Some(PathBuf::from(&args[i]));
             }
             "--normalize" => {
-                normalize = true;
+                if normalize == Some(false) {
+                    return Err("--normalize cannot be combined with --raw".to_string());
+                }
+                normalize = Some(true);
+            }
+            "--raw" => {
+                if normalize == Some(true) {
+                    return Err("--normalize cannot be combined with --raw".to_string());
+                }
+                normalize = Some(false);
+            }
+            "--normalize-mode" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --normalize-mode".to_string());
+                }
+                normalize_mode = Some(
+                    NormalizeMode::parse(&args[i])
+                        .ok_or_else(|| "invalid --normalize-mode (use log|pearson)".to_string())?,
+                );
+            }
+            "--pearson-theta" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --pearson-theta".to_string());
+                }
+                pearson_theta = args[i]
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|t| t.is_finite() && *t > 0.0)
+                    .ok_or_else(|| "invalid --pearson-theta (use a positive number)".to_string())?;
+            }
+            "--qc-max-low-confidence" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --qc-max-low-confidence".to_string());
+                }
+                qc_gates.max_low_confidence_fraction = args[i]
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|f| (0.0..=1.0).contains(f))
+                    .ok_or_else(|| {
+                        "invalid --qc-max-low-confidence (use a fraction in [0, 1])".to_string()
+                    })?;
+            }
+            "--qc-min-cells" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --qc-min-cells".to_string());
+                }
+                qc_gates.min_cells = args[i].parse::<usize>().map_err(|_| {
+                    "invalid --qc-min-cells (use a non-negative integer)".to_string()
+                })?;
             }
             "--cache-normalized" => {
                 cache_normalized = true;
             }
+            "--seed" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --seed".to_string());
+                }
+                seed = args[i]
+                    .parse::<u64>()
+                    .map_err(|_| "invalid --seed (use an unsigned 64-bit integer)".to_string())?;
+            }
+            "--max-cells" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --max-cells".to_string());
+                }
+                max_cells = Some(
+                    args[i]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| {
+                            "invalid --max-cells (use a positive integer)".to_string()
+                        })?,
+                );
+            }
+            "--threads" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --threads".to_string());
+                }
+                threads = args[i]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| "invalid --threads (use a positive integer)".to_string())?;
+            }
+            "--chunk-cells" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --chunk-cells".to_string());
+                }
+                chunk_cells = Some(
+                    args[i]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| {
+                            "invalid --chunk-cells (use a positive integer)".to_string()
+                        })?,
+                );
+            }
+            "--cache-codec" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --cache-codec".to_string());
+                }
+                cache_codec = match args[i].as_str() {
+                    "none" => CacheCodec::None,
+                    "deflate" => CacheCodec::Deflate,
+                    _ => return Err("invalid --cache-codec (use none|deflate)".to_string()),
+                };
+            }
             "--strict-nuclear" => {
-                scoring_mode = NuclearScoringMode::StrictBulk;
+                profile = NamedProfile::Default;
+            }
+            "--profile" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --profile".to_string());
+                }
+                profile = NamedProfile::parse(&args[i]).ok_or_else(|| {
+                    "invalid --profile (use default|immune|snrna|tumor)".to_string()
+                })?;
+            }
+            "--confidence-model" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --confidence-model".to_string());
+                }
+                confidence_model = Some(ConfidenceModel::parse(&args[i]).ok_or_else(|| {
+                    "invalid --confidence-model (use additive|multiplicative|logistic)".to_string()
+                })?);
+            }
+            "--composite" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --composite".to_string());
+                }
+                custom_composite = Some(
+                    CustomComposite::parse(&args[i])
+                        .map_err(|err| format!("invalid --composite: {err}"))?,
+                );
+            }
+            "--emit-drivers" => {
+                emit_drivers = true;
+            }
+            "--entropy-unit" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --entropy-unit".to_string());
+                }
+                entropy_unit = EntropyUnit::parse(&args[i])
+                    .ok_or_else(|| "invalid --entropy-unit (use nats|bits)".to_string())?;
+            }
+            "--confidence-breakdown" => {
+                confidence_breakdown = true;
+            }
+            "--emit-step-json" => {
+                emit_step_json = true;
+            }
+            "--emit-obs-csv" => {
+                emit_obs_csv = true;
+            }
+            "--emit-program-shares" => {
+                emit_program_shares = true;
+            }
+            "--emit-axis-correlations" => {
+                emit_axis_correlations = true;
+            }
+            "--emit-long" => {
+                emit_long = true;
+            }
+            "--cell-stdout" => {
+                cell_stdout = true;
+            }
+            "--explain-regimes" => {
+                explain_regimes = true;
+            }
+            "--axis-percentiles" => {
+                axis_percentiles = true;
+            }
+            "--emit-panel-detection" => {
+                emit_panel_detection = true;
+            }
+            "--emit-normalized-mtx" => {
+                emit_normalized_mtx = true;
+            }
+            "--cea-ribo-adjust" => {
+                cea_ribo_adjust = true;
+            }
+            "--sum-mode" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --sum-mode".to_string());
+                }
+                sum_mode = SumMode::parse(&args[i])
+                    .ok_or_else(|| "invalid --sum-mode (use blocked|pairwise)".to_string())?;
+            }
+            "--relative-within" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --relative-within".to_string());
+                }
+                relative_within = match args[i].as_str() {
+                    "global" => RelativeWithin::Global,
+                    "sample" => RelativeWithin::Sample,
+                    _ => return Err("invalid --relative-within (use global|sample)".to_string()),
+                };
+            }
+            "--relative-min-cells" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --relative-min-cells".to_string());
+                }
+                relative_min_group_cells = args[i]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| {
+                        "invalid --relative-min-cells (use a positive integer)".to_string()
+                    })?;
+            }
+            "--panel-score" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --panel-score".to_string());
+                }
+                panel_score = match args[i].as_str() {
+                    "sum" => PanelScoreMode::Sum,
+                    "mean" => PanelScoreMode::Mean,
+                    _ => return Err("invalid --panel-score (use mean|sum)".to_string()),
+                };
+            }
+            "--winsorize-panels" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --winsorize-panels".to_string());
+                }
+                winsorize_panels = Some(
+                    args[i]
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|q| *q > 0.0 && *q <= 1.0)
+                        .ok_or_else(|| {
+                            "invalid --winsorize-panels (use a quantile in (0, 1])".to_string()
+                        })?,
+                );
+            }
+            "--mito-max" | "--ribo-max" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    return Err(format!("missing value for {flag}"));
+                }
+                let max = args[i]
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|v| (0.0..=1.0).contains(v))
+                    .ok_or_else(|| format!("invalid {flag} (use a fraction in [0, 1])"))?;
+                if flag == "--mito-max" {
+                    mito_max = Some(max);
+                } else {
+                    ribo_max = Some(max);
+                }
+            }
+            "--control-prefixes" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --control-prefixes".to_string());
+                }
+                control_prefixes = args[i]
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
+            "--tail-thresholds" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --tail-thresholds".to_string());
+                }
+                let values = args[i]
+                    .split(',')
+                    .map(|v| v.trim().parse::<f32>().ok())
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|v| v.iter().all(|x| (0.0..=1.0).contains(x)));
+                tail_thresholds = match values.as_deref() {
+                    Some(&[trs, nps, rls]) => Some([trs, nps, rls]),
+                    _ => {
+                        return Err(
+                            "invalid --tail-thresholds (use TRS,NPS,RLS in [0, 1])".to_string()
+                        );
+                    }
+                };
+            }
+            "--validate-only" | "--dry-run" => {
+                validate_only = true;
+            }
+            "--key-panels" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --key-panels".to_string());
+                }
+                key_panels = Some(
+                    args[i]
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                );
+            }
+            "--contrast" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --contrast".to_string());
+                }
+                contrast = Some(args[i].clone());
+            }
+            "--contrast-reference" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --contrast-reference".to_string());
+                }
+                contrast_reference = Some(args[i].clone());
+            }
+            "--explain-panel" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --explain-panel".to_string());
+                }
+                explain_panel = Some(args[i].clone());
+            }
+            "--only-panels" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --only-panels".to_string());
+                }
+                only_panels = Some(
+                    args[i]
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                );
+            }
+            "--disable-panels" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --disable-panels".to_string());
+                }
+                disable_panels.extend(
+                    args[i]
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty()),
+                );
+            }
+            "--min-mappable-fraction" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --min-mappable-fraction".to_string());
+                }
+                min_mappable = MinMappable::parse(&args[i])
+                    .map_err(|err| format!("invalid --min-mappable-fraction: {err}"))?;
+            }
+            "--panel-rule-max-genes" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --panel-rule-max-genes".to_string());
+                }
+                panel_rule_max_genes = args[i]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| {
+                        "invalid --panel-rule-max-genes (use a positive integer)".to_string()
+                    })?;
+            }
+            "--strict-mappable" => {
+                strict_mappable = true;
+            }
+            "--panels" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --panels".to_string());
+                }
+                panels_path = Some(PathBuf::from(&args[i]));
+            }
+            "--assume-transposed" => {
+                assume_transposed = true;
+            }
+            "--feature-id-col" | "--feature-symbol-col" => {
+                let flag = args[i].as_str();
+                i += 1;
+                if i >= args.len() {
+                    return Err(format!("missing value for {flag}"));
+                }
+                let idx = args[i]
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid {flag} (use a zero-based column index)"))?;
+                if flag == "--feature-id-col" {
+                    feature_columns.id = idx;
+                } else {
+                    feature_columns.symbol = idx;
+                }
+            }
+            "--strict-input" => {
+                strict_input = true;
+            }
+            "--print-panels" => {
+                print_panels = true;
+            }
+            "--log-level" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --log-level".to_string());
+                }
+                log_level = LogLevel::parse(&args[i])
+                    .ok_or_else(|| "invalid --log-level (use error|warn|info|debug)".to_string())?;
+            }
+            "--fail-on-warn" => {
+                fail_on_warn = true;
+            }
+            "--legacy-quantiles" => {
+                legacy_quantiles = true;
+            }
+            "--input-format" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --input-format".to_string());
+                }
+                input_format = match args[i].as_str() {
+                    "10x" => InputFormat::TenX,
+                    "loom" => InputFormat::Loom,
+                    _ => return Err("invalid --input-format (use 10x|loom)".to_string()),
+                };
             }
             "--run-mode" => {
                 i += 1;
@@ -374,225 +819,364 @@ fn parse_args(args: &[String]) -> Result<RunConfig, String> {
         i += 1;
     }
 
-    Ok(RunConfig {
-        input_dir: input_dir.ok_or_else(|| "missing --input".to_string())?,
-        out_dir: out_dir.ok_or_else(|| "missing --out".to_string())?,
+    let input_dir = input_dirs
+        .first()
+        .cloned()
+        .ok_or_else(|| "missing --input".to_string())?;
+    if (batch || input_dirs.len() > 1) && cache_path.is_some() {
+        return Err("--cache cannot be combined with multiple inputs".to_string());
+    }
+    if cell_stdout && (batch || input_dirs.len() > 1) {
+        return Err("--cell-stdout cannot be combined with multiple inputs".to_string());
+    }
+    if input_format == InputFormat::Loom {
+        // These read a 10x directory.
+        for (set, flag) in [
+            (batch, "--batch"),
+            (print_panels, "--print-panels"),
+            (validate_only, "--validate-only"),
+        ] {
+            if set {
+                return Err(format!(
+                    "{flag} cannot be combined with --input-format loom"
+                ));
+            }
+        }
+    }
+
+    let mut config = RunConfig {
+        args: invocation,
+        input_dir,
+        input_format,
+        out_dir: match out_dir {
+            Some(dir) => dir,
+            None if validate_only || print_panels => PathBuf::new(),
+            None => return Err("missing --out".to_string()),
+        },
         cache_path,
         report_mode,
         meta_path,
-        normalize,
+        extra_meta_paths,
+        meta_by_order,
+        clusters_path,
+        contrast,
+        contrast_reference,
+        // Both set by `resolve_normalization` below.
+        normalize: false,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta,
         cache_normalized,
-        scoring_mode,
+        cache_codec,
+        profile,
+        confidence_model,
+        custom_composite,
         run_mode,
+        cea_ribo_adjust,
+        sum_mode,
+        relative_within,
+        relative_min_group_cells,
+        panel_score,
+        winsorize_panels,
+        mito_max,
+        ribo_max,
+        control_prefixes,
+        tail_thresholds,
+        legacy_quantiles,
+        key_panels,
+        panels_path,
+        only_panels,
+        disable_panels,
+        min_mappable,
+        qc_gates,
+        strict_mappable,
+        panel_rule_max_genes,
+        assume_transposed,
+        feature_columns,
+        strict_input,
+        max_cells,
+        threads,
+        fail_on_warn,
+        emit_drivers,
+        entropy_unit,
+        confidence_breakdown,
+        emit_step_json,
+        emit_obs_csv,
+        emit_program_shares,
+        emit_axis_correlations,
+        emit_long,
+        cell_stdout,
+        explain_regimes,
+        explain_panel,
+        axis_percentiles,
+        emit_panel_detection,
+        emit_normalized_mtx,
+        output_prefix,
+        chunk_cells,
+        seed,
+        write_reports: true,
+    };
+    config.resolve_normalization(normalize, normalize_mode)?;
+    config.validate()?;
+    Ok(CliArgs {
+        config,
+        input_dirs,
+        batch,
+        validate_only,
+        print_panels,
+        log_level,
     })
 }
 
-fn resolve_output_dir(base: &Path, run_mode: RunMode) -> PathBuf {
-    match run_mode {
-        RunMode::Standalone => base.to_path_buf(),
-        RunMode::Pipeline => base.join("kira-nuclearqc"),
-    }
+/// Options of the `bench` command.
+#[derive(Debug, Clone, PartialEq)]
+struct BenchConfig {
+    spec: SyntheticSpec,
+    threads: usize,
+    /// Write the synthetic 10x files here instead of running the benchmark.
+    emit: Option<PathBuf>,
+    /// Keep the reports here; a temporary directory otherwise.
+    out_dir: Option<PathBuf>,
 }
 
-fn extract_meta(
-    bundle: &input::InputBundle,
-) -> (
-    Option<Vec<String>>,
-    Option<Vec<String>>,
-    Option<Vec<String>>,
-    Option<Vec<String>>,
-) {
-    let mut sample: Option<Vec<String>> = None;
-    let mut condition: Option<Vec<String>> = None;
-    let mut species: Option<Vec<String>> = None;
-    let mut cluster: Option<Vec<String>> = None;
-    if let Some(meta) = &bundle.meta {
-        let mut sample_idx = None;
-        let mut condition_idx = None;
-        let mut species_idx = None;
-        let mut cluster_idx = None;
-        for (i, name) in meta.columns.iter().enumerate() {
-            let lower = name.to_ascii_lowercase();
-            if lower == "sample" {
-                sample_idx = Some(i);
-            } else if lower == "condition" {
-                condition_idx = Some(i);
-            } else if lower == "species" {
-                species_idx = Some(i);
-            } else if lower == "cluster"
-                || lower == "clusters"
-                || lower == "seurat_clusters"
-                || lower == "leiden"
-                || lower == "louvain"
-            {
-                cluster_idx = Some(i);
+fn parse_bench_args(args: &[String]) -> Result<BenchConfig, String> {
+    let mut config = BenchConfig {
+        spec: SyntheticSpec::default(),
+        threads: 1,
+        emit: None,
+        out_dir: None,
+    };
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            flag @ ("--cells" | "--genes" | "--nnz-per-cell" | "--threads") => {
+                i += 1;
+                if i >= args.len() {
+                    return Err(format!("missing value for {flag}"));
+                }
+                let n = args[i]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("invalid {flag} (use a positive integer)"))?;
+                match flag {
+                    "--cells" => config.spec.n_cells = n,
+                    "--genes" => config.spec.n_genes = n,
+                    "--nnz-per-cell" => config.spec.nnz_per_cell = n,
+                    _ => config.threads = n,
+                }
+            }
+            "--seed" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --seed".to_string());
+                }
+                config.spec.seed = args[i]
+                    .parse::<u64>()
+                    .map_err(|_| "invalid --seed (use an unsigned integer)".to_string())?;
+            }
+            "--emit" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --emit".to_string());
+                }
+                config.emit = Some(PathBuf::from(&args[i]));
+            }
+            "--out" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("missing value for --out".to_string());
+                }
+                config.out_dir = Some(PathBuf::from(&args[i]));
+            }
+            other => {
+                return Err(format!("unknown argument: {}", other));
             }
         }
-        if let Some(idx) = sample_idx {
-            sample = Some(
-                meta.rows
-                    .iter()
-                    .map(|r| r.get(idx).cloned().unwrap_or_default())
-                    .collect(),
-            );
-        }
-        if let Some(idx) = condition_idx {
-            condition = Some(
-                meta.rows
-                    .iter()
-                    .map(|r| r.get(idx).cloned().unwrap_or_default())
-                    .collect(),
-            );
-        }
-        if let Some(idx) = species_idx {
-            species = Some(
-                meta.rows
-                    .iter()
-                    .map(|r| r.get(idx).cloned().unwrap_or_default())
-                    .collect(),
-            );
-        }
-        if let Some(idx) = cluster_idx {
-            cluster = Some(
-                meta.rows
-                    .iter()
-                    .map(|r| r.get(idx).cloned().unwrap_or_default())
-                    .collect(),
-            );
-        }
-    }
-    (sample, condition, species, cluster)
-}
-
-fn compute_key_panel_coverage(
-    panel_set: &panels::PanelSet,
-    scores: &panels::PanelScores,
-) -> Vec<f32> {
-    let n_cells = scores.panel_coverage.len();
-    let n_panels = panel_set.panels.len();
-    let mut out = Vec::with_capacity(n_cells);
-    for cell in 0..n_cells {
-        if n_panels == 0 {
-            out.push(0.0);
-            continue;
-        }
-        let mut values = Vec::with_capacity(n_panels);
-        for p in 0..n_panels {
-            values.push(scores.panel_coverage[cell][p]);
-        }
-        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        let idx = values.len() / 2;
-        out.push(values[idx]);
+        i += 1;
     }
-    out
+    Ok(config)
 }
 
-fn compute_panel_signals(
-    panel_set: &panels::PanelSet,
-    scores: &panels::PanelScores,
-    audits: &[panels::PanelAudit],
-) -> (Vec<f32>, Vec<f32>, Vec<f32>, Vec<bool>, Vec<f32>) {
-    let n_cells = scores.panel_sum.len();
-    let mut program_sum = vec![0.0f32; n_cells];
-    let mut tf_sum = vec![0.0f32; n_cells];
-    let mut proliferation_sum = vec![0.0f32; n_cells];
-    let mut nonzero_frac = vec![0.0f32; n_cells];
+/// Generates the synthetic dataset and either writes it out (`--emit`) or
+/// runs Stages 2-7 on it in memory, printing per-stage wall-clock times.
+fn run_bench(bench: &BenchConfig) -> Result<(), CliError> {
+    let spec = &bench.spec;
+    let started = std::time::Instant::now();
+    let data = SyntheticData::generate(spec);
+    let generate_seconds = started.elapsed().as_secs_f64();
+    println!(
+        "bench: {} cells, {} genes, {} nnz, seed {}",
+        spec.n_cells,
+        data.gene_symbols.len(),
+        data.data.len(),
+        spec.seed
+    );
 
-    let mut key_panels_missing = false;
-    for audit in audits {
-        if audit.panel_size_mappable == 0 {
-            key_panels_missing = true;
-        }
+    if let Some(dir) = &bench.emit {
+        data.write_tenx(dir)
+            .map_err(|e| format!("failed writing {}: {e}", dir.display()))?;
+        println!("wrote 10x files to {}", dir.display());
+        return Ok(());
     }
 
-    for (idx, panel) in panel_set.panels.iter().enumerate() {
-        for cell in 0..n_cells {
-            let v = scores.panel_sum[cell][idx];
-            match panel.group {
-                panels::defs::PanelGroup::Program => program_sum[cell] += v,
-                panels::defs::PanelGroup::Tf | panels::defs::PanelGroup::Chromatin => {
-                    tf_sum[cell] += v
-                }
-                panels::defs::PanelGroup::Proliferation => proliferation_sum[cell] += v,
-                _ => {}
-            }
-        }
+    let out_dir = bench.out_dir.clone().unwrap_or_else(|| {
+        std::env::temp_dir().join(format!("kira_nuclearqc_bench_{}", std::process::id()))
+    });
+    let mut config = RunConfig::new(PathBuf::new(), &out_dir);
+    config.threads = bench.threads;
+    let started = std::time::Instant::now();
+    let outputs = run_counts(config, data.counts());
+    let total_seconds = started.elapsed().as_secs_f64();
+    if bench.out_dir.is_none() {
+        let _ = std::fs::remove_dir_all(&out_dir);
     }
+    let summary = outputs?.summary;
 
-    for cell in 0..n_cells {
-        let mut detected = 0u32;
-        let mut total = 0u32;
-        for (idx, panel) in panel_set.panels.iter().enumerate() {
-            detected += scores.panel_detected[cell][idx];
-            total += panel.genes.len() as u32;
+    // Stage 7 writes the reports after the summary's laps were taken.
+    let laps = &summary.stage_seconds;
+    let stage7 = total_seconds - laps.iter().map(|(_, s)| s).sum::<f64>();
+    println!("threads   {}", bench.threads);
+    println!("generate  {generate_seconds:.3}s");
+    for (stage, seconds) in laps.iter().copied().chain([("stage7", stage7.max(0.0))]) {
+        println!("{stage:<9} {seconds:.3}s");
+    }
+    println!("total     {total_seconds:.3}s");
+    println!(
+        "cells/s   {:.1}",
+        if total_seconds > 0.0 {
+            spec.n_cells as f64 / total_seconds
+        } else {
+            0.0
         }
-        if total > 0 {
-            nonzero_frac[cell] = detected as f32 / total as f32;
+    );
+    Ok(())
+}
+
+/// One directory per non-empty line; `#` starts a comment.
+fn read_inputs_file(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed reading --inputs-file {}: {e}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Subdirectories of `root` holding a 10x MTX or an organelle bin, in name
+/// order.
+fn batch_input_dirs(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(root)
+        .map_err(|e| format!("failed reading --batch {}: {e}", root.display()))?;
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("failed reading --batch {}: {e}", root.display()))?
+            .path();
+        if path.is_dir() {
+            dirs.push(path);
         }
     }
-
-    let mut proliferation_share = vec![0.0f32; n_cells];
-    for cell in 0..n_cells {
-        let denom = program_sum[cell];
-        if denom > 0.0 {
-            proliferation_share[cell] = proliferation_sum[cell] / denom;
+    dirs.sort();
+    dirs.retain(|dir| {
+        let found = find_matrix_path(dir).is_ok()
+            || resolve_shared_bin(dir).is_ok_and(|resolution| resolution.exists);
+        if !found {
+            kira_nuclearqc::info!("--batch: skipping {} (no input found)", dir.display());
         }
+        found
+    });
+    if dirs.is_empty() {
+        return Err(format!("no inputs found under --batch {}", root.display()));
     }
-
-    (
-        program_sum,
-        tf_sum,
-        proliferation_share,
-        vec![key_panels_missing; n_cells],
-        nonzero_frac,
-    )
+    Ok(dirs)
 }
 
-fn read_git_hash(repo_root: &Path) -> Option<String> {
-    let head = repo_root.join(".git/HEAD");
-    let content = std::fs::read_to_string(head).ok()?;
-    if let Some(ref_line) = content.strip_prefix("ref: ") {
-        let ref_path = repo_root.join(".git").join(ref_line.trim());
-        return std::fs::read_to_string(ref_path)
-            .ok()
-            .map(|s| s.trim().to_string());
-    }
-    Some(content.trim().to_string())
+fn is_multi_dataset(cli: &CliArgs) -> bool {
+    cli.batch || cli.input_dirs.len() > 1
 }
 
-fn log_scoring_mode(
-    mode: NuclearScoringMode,
-    stage3: &pipeline::stage3_panels::Stage3Output,
-    stage4: &pipeline::stage4_axes::Stage4Output,
-) {
-    match mode {
-        NuclearScoringMode::ImmuneAware => {
-            eprintln!("INFO  Immune-aware nuclear scoring enabled (default)");
-            if immune_like_detected(stage3, stage4) {
-                eprintln!("INFO  Immune-like scRNA detected; relative nuclear scoring in effect");
-            }
+/// Splits a multi-input run into one config per dataset, each writing into
+/// `--out/<name>` where `name` is the detected file prefix or the directory
+/// name, or a `.loom` file's stem.
+fn dataset_configs(cli: &CliArgs) -> Result<Vec<RunConfig>, CliError> {
+    let config = &cli.config;
+    if !is_multi_dataset(cli) {
+        return Ok(vec![config.clone()]);
+    }
+    let mut seen = BTreeSet::new();
+    let mut out = Vec::with_capacity(cli.input_dirs.len());
+    for dir in &cli.input_dirs {
+        let name = match config.input_format {
+            InputFormat::TenX => match detect_prefix(dir)? {
+                Some(prefix) => Some(prefix),
+                None => dir.file_name().map(|n| n.to_string_lossy().into_owned()),
+            },
+            InputFormat::Loom => dir.file_stem().map(|n| n.to_string_lossy().into_owned()),
         }
-        NuclearScoringMode::StrictBulk => {
-            eprintln!(
-                "WARN  Strict nuclear mode enabled (--strict-nuclear); immune dynamics may be underdetected"
+        .ok_or_else(|| format!("cannot name dataset for input {}", dir.display()))?;
+        if !seen.insert(name.clone()) {
+            return Err(
+                format!("duplicate dataset name {name} for input {}", dir.display()).into(),
             );
         }
+        let mut dataset = config.clone();
+        dataset.input_dir = dir.clone();
+        dataset.out_dir = config.out_dir.join(name);
+        out.push(dataset);
     }
+    Ok(out)
+}
+
+fn panel_audit_tsv(
+    input_dir: &Path,
+    panels_path: Option<&Path>,
+    max_rule_genes: usize,
+    feature_columns: FeatureColumns,
+) -> Result<String, CliError> {
+    let panel_defs = panels::loader::resolve_panel_defs(panels_path).map_err(|e| e.to_string())?;
+    let (gene_index, species) = load_gene_index(input_dir, feature_columns)?;
+    kira_nuclearqc::info!("species detected: {:?}", species);
+    let (_, audits) =
+        panels::loader::load_panels(species, &gene_index, &panel_defs, max_rule_genes)
+            .map_err(|e| e.to_string())?;
+    Ok(panels::render_panel_audits_tsv(&audits))
 }
 
-fn immune_like_detected(
-    stage3: &pipeline::stage3_panels::Stage3Output,
-    stage4: &pipeline::stage4_axes::Stage4Output,
-) -> bool {
-    let has_immune_panels = stage3
-        .panels
-        .panels
-        .iter()
-        .any(|p| p.id == "immune_activation" || p.id == "clonal_engagement");
-    let p90_iaa = p90(&stage4.axes.iaa);
-    let p90_dfa = p90(&stage4.axes.dfa);
-    let p90_cea = p90(&stage4.axes.cea);
-    has_immune_panels && (p90_iaa > 0.5 || p90_dfa > 0.5 || p90_cea > 0.5)
+fn validate_only(config: &RunConfig) -> Result<(), CliError> {
+    let bin_path = match (&config.cache_path, config.run_mode) {
+        (Some(path), _) => Some(path.clone()),
+        (None, RunMode::Pipeline) => {
+            // As in a run, custom feature columns read the 10x files.
+            let resolution = resolve_shared_bin(&config.input_dir)?;
+            (resolution.exists && config.feature_columns == FeatureColumns::default())
+                .then_some(resolution.path)
+        }
+        (None, RunMode::Standalone) => None,
+    };
+    let mut report = validate_input(
+        &config.input_dir,
+        config.keyed_meta_path(),
+        bin_path.as_deref(),
+        config.tenx_options(),
+    )?;
+    config.attach_meta(&mut report.meta, &report.barcodes)?;
+    println!(
+        "input OK: source={}, n_cells={}, n_features={}, n_genes_indexed={}, nnz={}, species={:?}",
+        match report.source {
+            InputSourceKind::TenX => "10x",
+            InputSourceKind::OrganelleBin => "kira-organelle.bin",
+            InputSourceKind::InMemory => "in-memory",
+            InputSourceKind::Loom => "loom",
+        },
+        report.n_cells,
+        report.n_features,
+        report.n_genes_indexed,
+        report.nnz,
+        report.species
+    );
+    Ok(())
 }
 
 #[cfg(test)]
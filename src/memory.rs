@@ -0,0 +1,101 @@
+//! Best-effort process memory figures for sizing jobs: the peak resident set
+//! as reported by the OS, and an estimate from the input dimensions.
+
+/// Peak resident set size of this process in bytes, or `None` where the
+/// platform does not expose it.
+pub fn peak_rss_bytes() -> Option<u64> {
+    platform::peak_rss_bytes()
+}
+
+/// Rough peak resident set size in bytes for a dataset of `n_cells` cells,
+/// `n_genes` features and `nnz` stored counts, before running it.
+///
+/// Covers the sparse matrix as loaded and normalized, per-cell metric
+/// vectors and a fixed base for panels and code; it tracks the default
+/// in-memory run, not `--chunk-cells`.
+pub fn estimate_peak_rss_bytes(n_cells: usize, n_genes: usize, nnz: u64) -> u64 {
+    // Estimates, not a measured fit: each nonzero is held several times over
+    // (parsed triplets, raw and normalized sparse copies, per-gene indices),
+    // cells and genes carry a few dozen metric and index slots, and the base
+    // covers code and panels. Compare with `peak_rss_bytes` for a given input.
+    const BASE_BYTES: u64 = 8 << 20;
+    const BYTES_PER_NNZ: u64 = 52;
+    const BYTES_PER_CELL: u64 = 320;
+    const BYTES_PER_GENE: u64 = 128;
+    BASE_BYTES
+        + nnz * BYTES_PER_NNZ
+        + n_cells as u64 * BYTES_PER_CELL
+        + n_genes as u64 * BYTES_PER_GENE
+}
+
+// `VmHWM:   123456 kB` from `/proc/self/status`, in bytes.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_hwm(status: &str) -> Option<u64> {
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let mut fields = line["VmHWM:".len()..].split_whitespace();
+    let value = fields.next()?.parse::<u64>().ok()?;
+    match fields.next() {
+        Some("kB") => value.checked_mul(1024),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn peak_rss_bytes() -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        super::parse_vm_hwm(&status)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    // `mach_task_basic_info` from <mach/task_info.h>.
+    #[repr(C)]
+    #[derive(Default)]
+    struct MachTaskBasicInfo {
+        virtual_size: u64,
+        resident_size: u64,
+        resident_size_max: u64,
+        user_time: [i32; 2],
+        system_time: [i32; 2],
+        policy: i32,
+        suspend_count: i32,
+    }
+
+    const MACH_TASK_BASIC_INFO: i32 = 20;
+    const KERN_SUCCESS: i32 = 0;
+
+    unsafe extern "C" {
+        static mach_task_self_: u32;
+        fn task_info(task: u32, flavor: i32, info: *mut i32, count: *mut u32) -> i32;
+    }
+
+    pub fn peak_rss_bytes() -> Option<u64> {
+        let mut info = MachTaskBasicInfo::default();
+        let mut count =
+            (std::mem::size_of::<MachTaskBasicInfo>() / std::mem::size_of::<u32>()) as u32;
+        // SAFETY: `info` is a live `mach_task_basic_info` and `count` holds
+        // its size in `natural_t` units, as `task_info` requires.
+        let status = unsafe {
+            task_info(
+                mach_task_self_,
+                MACH_TASK_BASIC_INFO,
+                (&mut info as *mut MachTaskBasicInfo).cast(),
+                &mut count,
+            )
+        };
+        (status == KERN_SUCCESS).then_some(info.resident_size_max)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod platform {
+    pub fn peak_rss_bytes() -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/src_inline/memory.rs"]
+mod tests;
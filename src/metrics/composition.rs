@@ -0,0 +1,64 @@
+use crate::input::GeneIndex;
+use crate::pipeline::stage2_normalize::ExprAccessor;
+
+// Symbols are upper-cased by `normalize_symbol`, so mouse `mt-` genes match as well.
+const MITO_PREFIX: &str = "MT-";
+const RIBO_PREFIXES: &[&str] = &["RPL", "RPS"];
+// RPS6KA*/RPS6KB*/RPS6KC*/RPS6KL* are kinases, not ribosomal proteins.
+const RIBO_EXCLUDE_PREFIXES: &[&str] = &["RPS6K"];
+
+pub fn is_mito_symbol(symbol: &str) -> bool {
+    symbol.starts_with(MITO_PREFIX)
+}
+
+/// Cytosolic ribosomal protein genes; mitochondrial `MRPL`/`MRPS` never match.
+pub fn is_ribo_symbol(symbol: &str) -> bool {
+    RIBO_PREFIXES.iter().any(|p| symbol.starts_with(p))
+        && !RIBO_EXCLUDE_PREFIXES.iter().any(|p| symbol.starts_with(p))
+}
+
+pub fn gene_mask(gene_index: &GeneIndex, predicate: impl Fn(&str) -> bool) -> Vec<bool> {
+    gene_index
+        .symbols_by_gene_id
+        .iter()
+        .map(|s| predicate(s.as_str()))
+        .collect()
+}
+
+/// Per-cell fraction of raw counts falling on genes selected by `mask`.
+pub fn count_fraction(accessor: &dyn ExprAccessor, mask: &[bool]) -> Vec<f32> {
+    let n_cells = accessor.n_cells();
+    let mut out = vec![0.0f32; n_cells];
+    if !mask.iter().any(|&m| m) {
+        return out;
+    }
+    for (cell, slot) in out.iter_mut().enumerate() {
+        let mut hit = 0f64;
+        let mut total = 0f64;
+        accessor.for_cell_counts(cell, &mut |gene_id, count| {
+            let count = count as f64;
+            total += count;
+            if mask.get(gene_id as usize).copied().unwrap_or(false) {
+                hit += count;
+            }
+        });
+        if total > 0.0 {
+            *slot = (hit / total) as f32;
+        }
+    }
+    out
+}
+
+pub fn mito_fraction(accessor: &dyn ExprAccessor, gene_index: &GeneIndex) -> Vec<f32> {
+    let mask = gene_mask(gene_index, is_mito_symbol);
+    count_fraction(accessor, &mask)
+}
+
+pub fn ribo_fraction(accessor: &dyn ExprAccessor, gene_index: &GeneIndex) -> Vec<f32> {
+    let mask = gene_mask(gene_index, is_ribo_symbol);
+    count_fraction(accessor, &mask)
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/metrics/composition.rs"]
+mod tests;
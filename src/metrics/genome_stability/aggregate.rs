@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 
 use super::scores::{GenomePanelAudit, GenomeStabilityCellScores, RobustNormStat};
+use crate::report::{QuantileMethod, quantile_sorted};
 
 #[derive(Debug, Clone)]
 pub struct GenomeStabilityThresholds {
@@ -69,8 +70,9 @@ pub fn summarize_genome_stability(
     cells: &GenomeStabilityCellScores,
     robust_norm: &[RobustNormStat],
     cluster_labels: Option<&[String]>,
+    method: QuantileMethod,
 ) -> GenomeStabilitySummary {
-    let cluster_stats = compute_cluster_stats(cells, cluster_labels);
+    let cluster_stats = compute_cluster_stats(cells, cluster_labels, method);
 
     let top_clusters_by_rss = top_clusters(&cluster_stats, |cluster| {
         metric_value(&cluster.metrics, "rss", |m| m.median)
@@ -97,11 +99,11 @@ pub fn summarize_genome_stability(
         global_stats: GenomeGlobalStats {
             robust_norm: robust_norm.to_vec(),
             distributions: vec![
-                distribution("rss", &cells.rss),
-                distribution("ddr", &cells.ddr),
-                distribution("rb", &cells.rb),
-                distribution("cds", &cells.cds),
-                distribution("sas", &cells.sas),
+                distribution("rss", &cells.rss, method),
+                distribution("ddr", &cells.ddr, method),
+                distribution("rb", &cells.rb, method),
+                distribution("cds", &cells.cds, method),
+                distribution("sas", &cells.sas, method),
             ],
             flag_fractions: vec![
                 GenomeFlagFraction {
@@ -148,6 +150,7 @@ pub fn summarize_genome_stability(
 fn compute_cluster_stats(
     cells: &GenomeStabilityCellScores,
     cluster_labels: Option<&[String]>,
+    method: QuantileMethod,
 ) -> Vec<ClusterGenomeStats> {
     let Some(labels) = cluster_labels else {
         return Vec::new();
@@ -192,11 +195,11 @@ fn compute_cluster_stats(
             cluster_id,
             n_cells: idxs.len(),
             metrics: vec![
-                distribution_idx("rss", &cells.rss, &idxs),
-                distribution_idx("ddr", &cells.ddr, &idxs),
-                distribution_idx("rb", &cells.rb, &idxs),
-                distribution_idx("cds", &cells.cds, &idxs),
-                distribution_idx("sas", &cells.sas, &idxs),
+                distribution_idx("rss", &cells.rss, &idxs, method),
+                distribution_idx("ddr", &cells.ddr, &idxs, method),
+                distribution_idx("rb", &cells.rb, &idxs, method),
+                distribution_idx("cds", &cells.cds, &idxs, method),
+                distribution_idx("sas", &cells.sas, &idxs, method),
             ],
             flag_fractions: vec![
                 GenomeFlagFraction {
@@ -257,19 +260,24 @@ fn top_clusters(
     ranked.into_iter().take(3).collect()
 }
 
-fn distribution(name: &'static str, values: &[f32]) -> GenomeMetricDistribution {
+fn distribution(
+    name: &'static str,
+    values: &[f32],
+    method: QuantileMethod,
+) -> GenomeMetricDistribution {
     let finite = values
         .iter()
         .copied()
         .filter(|v| v.is_finite())
         .collect::<Vec<_>>();
-    quantile_distribution(name, &finite)
+    quantile_distribution(name, &finite, method)
 }
 
 fn distribution_idx(
     name: &'static str,
     values: &[f32],
     idxs: &[usize],
+    method: QuantileMethod,
 ) -> GenomeMetricDistribution {
     let mut filtered = Vec::with_capacity(idxs.len());
     for &idx in idxs {
@@ -278,10 +286,14 @@ fn distribution_idx(
             filtered.push(value);
         }
     }
-    quantile_distribution(name, &filtered)
+    quantile_distribution(name, &filtered, method)
 }
 
-fn quantile_distribution(name: &'static str, values: &[f32]) -> GenomeMetricDistribution {
+fn quantile_distribution(
+    name: &'static str,
+    values: &[f32],
+    method: QuantileMethod,
+) -> GenomeMetricDistribution {
     if values.is_empty() {
         return GenomeMetricDistribution {
             name,
@@ -295,18 +307,10 @@ fn quantile_distribution(name: &'static str, values: &[f32]) -> GenomeMetricDist
 
     GenomeMetricDistribution {
         name,
-        median: quantile_sorted(&sorted, 0.5),
-        p10: quantile_sorted(&sorted, 0.10),
-        p90: quantile_sorted(&sorted, 0.90),
-    }
-}
-
-fn quantile_sorted(sorted: &[f32], q: f32) -> f32 {
-    if sorted.is_empty() {
-        return 0.0;
+        median: quantile_sorted(&sorted, 0.5, method),
+        p10: quantile_sorted(&sorted, 0.10, method),
+        p90: quantile_sorted(&sorted, 0.90, method),
     }
-    let idx = ((sorted.len() - 1) as f32 * q).ceil() as usize;
-    sorted[idx]
 }
 
 fn bool_fraction(values: &[bool]) -> f32 {
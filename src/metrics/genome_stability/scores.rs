@@ -64,11 +64,34 @@ struct ResolvedPanel {
     panel_size_defined: usize,
 }
 
-pub fn compute_genome_stability(
+/// Per-cell trimmed panel means, the only part of the computation that reads
+/// expression; the rest normalizes them across cells.
+#[derive(Debug, Clone, Default)]
+pub struct GenomeCores {
+    pub replication_core: Vec<f32>,
+    pub ddr_core: Vec<f32>,
+    pub hr_core: Vec<f32>,
+    pub nhej_core: Vec<f32>,
+    pub sphase_core: Vec<f32>,
+    pub senescence_core: Vec<f32>,
+}
+
+impl GenomeCores {
+    pub fn append(&mut self, mut other: GenomeCores) {
+        self.replication_core.append(&mut other.replication_core);
+        self.ddr_core.append(&mut other.ddr_core);
+        self.hr_core.append(&mut other.hr_core);
+        self.nhej_core.append(&mut other.nhej_core);
+        self.sphase_core.append(&mut other.sphase_core);
+        self.senescence_core.append(&mut other.senescence_core);
+    }
+}
+
+pub fn compute_genome_cores(
     accessor: &dyn ExprAccessor,
     gene_index: &GeneIndex,
     species: Species,
-) -> GenomeStabilityComputation {
+) -> GenomeCores {
     let resolved = resolve_panels(gene_index, species);
     let n_cells = accessor.n_cells();
 
@@ -120,6 +143,32 @@ pub fn compute_genome_stability(
         }
     }
 
+    GenomeCores {
+        replication_core,
+        ddr_core,
+        hr_core,
+        nhej_core,
+        sphase_core,
+        senescence_core,
+    }
+}
+
+pub fn finish_genome_stability(
+    cores: GenomeCores,
+    gene_index: &GeneIndex,
+    species: Species,
+) -> GenomeStabilityComputation {
+    let resolved = resolve_panels(gene_index, species);
+    let GenomeCores {
+        replication_core,
+        ddr_core,
+        hr_core,
+        nhej_core,
+        sphase_core,
+        senescence_core,
+    } = cores;
+    let n_cells = replication_core.len();
+
     let (z_replication_core, replication_stat) =
         robust_zscore(&replication_core, "replication_core");
     let (z_ddr_core, ddr_stat) = robust_zscore(&ddr_core, "ddr_core");
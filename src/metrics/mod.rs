@@ -1 +1,2 @@
+pub mod composition;
 pub mod genome_stability;
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Axes {
     pub tbi: Vec<f32>,
     pub rci: Vec<f32>,
@@ -17,6 +18,7 @@ pub struct Axes {
 #[derive(Debug, Clone, Default)]
 pub struct AxisDrivers {
     pub expressed_genes: u32,
+    pub min_nonzero_expr: f32,
     pub gene_entropy: f32,
     pub panel_entropy: f32,
     pub max_program_share: f32,
@@ -29,13 +31,49 @@ pub struct AxisDrivers {
     pub axis_variance: f32,
 }
 
+/// Unit of the raw entropies reported as driver columns (`--entropy-unit`).
+/// Stage 4 and the thresholds work in nats; normalized axes are unitless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntropyUnit {
+    #[default]
+    Nats,
+    Bits,
+}
+
+impl EntropyUnit {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "nats" => Some(EntropyUnit::Nats),
+            "bits" => Some(EntropyUnit::Bits),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntropyUnit::Nats => "nats",
+            EntropyUnit::Bits => "bits",
+        }
+    }
+
+    /// `nats` expressed in this unit.
+    pub fn from_nats(self, nats: f32) -> f32 {
+        match self {
+            EntropyUnit::Nats => nats,
+            EntropyUnit::Bits => nats / std::f32::consts::LN_2,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct AxisFlags {
     pub low_tf_signal: bool,
 }
 
+/// Clamps `x` to [0, 1]; NaN maps to 0 so that no axis or score leaves the
+/// range on degenerate input.
 pub fn clip01(x: f32) -> f32 {
-    if x < 0.0 {
+    if x.is_nan() || x < 0.0 {
         0.0
     } else if x > 1.0 {
         1.0
@@ -43,3 +81,7 @@ pub fn clip01(x: f32) -> f32 {
         x
     }
 }
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/axes.rs"]
+mod tests;
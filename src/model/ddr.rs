@@ -57,6 +57,7 @@ pub fn compute_ddr_metrics(
     }
 }
 
+/// Maps [-1, 1] onto [0, 1], with NaN at 0.
 pub fn rescale_to_0_1(x: f32) -> f32 {
     let v = (x + 1.0) * 0.5;
     clip01(v)
@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Flag {
     LowExprGenes,
     LowPanelCoverage,
@@ -7,6 +8,8 @@ pub enum Flag {
     HighStressBias,
     LowTfSignal,
     AmbientRnaRisk,
+    HighMitoFraction,
+    HighRiboFraction,
     CellCycleConfounder,
     LowConfidence,
     ModelLimitation,
@@ -27,6 +30,8 @@ pub fn flag_order() -> &'static [Flag] {
         Flag::HighStressBias,
         Flag::LowTfSignal,
         Flag::AmbientRnaRisk,
+        Flag::HighMitoFraction,
+        Flag::HighRiboFraction,
         Flag::CellCycleConfounder,
         Flag::LowConfidence,
         Flag::HighReplicationStress,
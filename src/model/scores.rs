@@ -1,4 +1,7 @@
+use crate::model::axes::{Axes, clip01};
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CompositeScores {
     pub nps: Vec<f32>,
     pub ci: Vec<f32>,
@@ -6,3 +9,119 @@ pub struct CompositeScores {
     pub confidence: Vec<f32>,
     pub confidence_breakdown: Vec<[f32; 4]>,
 }
+
+/// Axis short names accepted by `--composite`, in `Axes` field order.
+pub const COMPOSITE_AXES: [&str; 12] = [
+    "tbi", "rci", "pds", "trs", "nsai", "iaa", "dfa", "cea", "rss", "drbi", "cci", "trci",
+];
+
+/// User-defined linear composite over axes (`--composite`), reported as
+/// `c4_custom` and clipped to [0, 1] like the built-in composites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomComposite {
+    /// `(weight, axis)` pairs, axes named as in [`COMPOSITE_AXES`].
+    pub terms: Vec<(f32, &'static str)>,
+}
+
+impl CustomComposite {
+    /// Parses `weight*axis` terms joined by `+` or `-`, such as
+    /// `0.4*tbi+0.3*rci-0.2*pds`. A bare axis has weight 1 and whitespace is
+    /// ignored.
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        if expr.is_empty() {
+            return Err("empty composite".to_string());
+        }
+
+        let mut terms = Vec::new();
+        let mut rest = expr.as_str();
+        while !rest.is_empty() {
+            let (sign, body) = match rest.as_bytes()[0] {
+                b'-' => (-1.0, &rest[1..]),
+                b'+' => (1.0, &rest[1..]),
+                _ if terms.is_empty() => (1.0, rest),
+                _ => unreachable!("terms are split at signs"),
+            };
+            let end = body.find(['+', '-']).unwrap_or(body.len());
+            let term = &body[..end];
+            rest = &body[end..];
+            if term.is_empty() {
+                return Err(format!("missing term in composite '{expr}'"));
+            }
+
+            let (weight, axis) = match term.split_once('*') {
+                Some((weight, axis)) => {
+                    let weight = weight
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|w| w.is_finite())
+                        .ok_or_else(|| format!("invalid weight '{weight}' in term '{term}'"))?;
+                    (weight, axis)
+                }
+                None => (1.0, term),
+            };
+            let axis = COMPOSITE_AXES
+                .iter()
+                .find(|name| **name == axis)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown axis '{axis}' in term '{term}' (use {})",
+                        COMPOSITE_AXES.join("|")
+                    )
+                })?;
+            terms.push((sign * weight, *axis));
+        }
+        Ok(CustomComposite { terms })
+    }
+
+    /// Canonical form of the expression, as recorded in `summary.json`.
+    pub fn expr(&self) -> String {
+        let mut out = String::new();
+        for (i, (weight, axis)) in self.terms.iter().enumerate() {
+            if *weight < 0.0 {
+                out.push('-');
+            } else if i > 0 {
+                out.push('+');
+            }
+            out.push_str(&format!("{}*{axis}", weight.abs()));
+        }
+        out
+    }
+
+    /// Per-cell weighted sum of the axes, clipped to [0, 1].
+    pub fn evaluate(&self, axes: &Axes) -> Vec<f32> {
+        let n_cells = axes.tbi.len();
+        let mut out = vec![0.0f32; n_cells];
+        for &(weight, axis) in &self.terms {
+            for (acc, &value) in out.iter_mut().zip(axis_values(axes, axis)) {
+                *acc += weight * value;
+            }
+        }
+        out.into_iter().map(clip01).collect()
+    }
+}
+
+fn axis_values<'a>(axes: &'a Axes, axis: &str) -> &'a [f32] {
+    match axis {
+        "tbi" => &axes.tbi,
+        "rci" => &axes.rci,
+        "pds" => &axes.pds,
+        "trs" => &axes.trs,
+        "nsai" => &axes.nsai,
+        "iaa" => &axes.iaa,
+        "dfa" => &axes.dfa,
+        "cea" => &axes.cea,
+        "rss" => &axes.rss,
+        "drbi" => &axes.drbi,
+        "cci" => &axes.cci,
+        "trci" => &axes.trci,
+        _ => unreachable!("axis names come from COMPOSITE_AXES"),
+    }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/scores.rs"]
+mod tests;
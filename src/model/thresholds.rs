@@ -1,3 +1,7 @@
+use crate::panels::{DEFAULT_KEY_PANELS, PanelScoreMode};
+use crate::report::QuantileMethod;
+use crate::simd::SumMode;
+
 #[derive(Debug, Clone)]
 pub struct ThresholdProfile {
     pub expr_min: f32,
@@ -17,6 +21,67 @@ pub struct ThresholdProfile {
     pub rel_p70: f32,
     pub rel_p85: f32,
     pub confidence_low: f32,
+    pub confidence_w_coverage: f32,
+    pub confidence_w_expr_support: f32,
+    pub confidence_w_axis_structure: f32,
+    pub confidence_w_consistency: f32,
+    pub confidence_coverage_scale: f32,
+    pub confidence_axis_variance_scale: f32,
+    /// Additive confidence model: cells with key panels present and an axis
+    /// structure score of at least `confidence_structure_min` keep at least
+    /// `confidence_floor`.
+    pub confidence_floor: f32,
+    pub confidence_structure_min: f32,
+    /// Median key panel coverage below which a cell is flagged
+    /// `LowPanelCoverage`.
+    pub panel_coverage_min: f32,
+    /// Mitochondrial and ribosomal count fractions above which a cell is
+    /// flagged `HighMitoFraction` / `HighRiboFraction`.
+    pub mito_frac_max: f32,
+    pub ribo_frac_max: f32,
+    /// Proliferation program share above which a cycling cell is flagged
+    /// `CellCycleConfounder`.
+    pub cell_cycle_share_min: f32,
+    /// Regime cutoffs used by Stage 6, checked in regime precedence order.
+    pub collapsed_tbi_max: f32,
+    pub collapsed_entropy_max: f32,
+    pub rigid_trs_min: f32,
+    pub rigid_nsai_min: f32,
+    pub rigid_rci_max: f32,
+    pub committed_trs_min: f32,
+    pub committed_pds_min: f32,
+    pub committed_tbi_max: f32,
+    pub committed_nsai_max: f32,
+    pub stress_nsai_min: f32,
+    pub stress_rci_min: f32,
+    pub stress_tbi_min: f32,
+    pub stress_pds_max: f32,
+    pub plastic_nps_min: f32,
+    pub plastic_trs_max: f32,
+    pub plastic_pds_max: f32,
+    pub transient_nps_min: f32,
+    pub transient_iaa_min: f32,
+    pub transient_dfa_min: f32,
+    pub transient_trs_max: f32,
+    pub transient_pds_max: f32,
+    pub cea_ribo_adjust: bool,
+    pub sum_mode: SumMode,
+    /// Estimator behind the relative anchors, panel winsorizing and every
+    /// summary quantile (`--legacy-quantiles`).
+    pub quantile_method: QuantileMethod,
+    pub relative_within: RelativeWithin,
+    pub relative_min_group_cells: usize,
+    pub key_panels: Vec<String>,
+    pub panel_score: PanelScoreMode,
+    /// Clamp each panel's values at this quantile over cells before Stage 4
+    /// (`--winsorize-panels`); reported panel scores stay raw.
+    pub winsorize_panels: Option<f32>,
+    /// Summary tail cutoffs: the share of cells with `a4_trs` at or above
+    /// `tail_trs_min`, `c1_nps` at or above `tail_nps_min` and `c3_rls` at or
+    /// below `tail_rls_max`.
+    pub tail_trs_min: f32,
+    pub tail_nps_min: f32,
+    pub tail_rls_max: f32,
     pub scoring_mode: NuclearScoringMode,
 }
 
@@ -27,12 +92,153 @@ pub enum AxisActivationMode {
     Hybrid,
 }
 
+/// Cell population used for the p70/p85 anchors of relative axis activation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeWithin {
+    Global,
+    Sample,
+}
+
+impl RelativeWithin {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RelativeWithin::Global => "global",
+            RelativeWithin::Sample => "sample",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NuclearScoringMode {
     ImmuneAware,
     StrictBulk,
 }
 
+/// Threshold preset chosen with `--profile`; `--strict-nuclear` is `Default`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedProfile {
+    /// `default_v1`, strict bulk-oriented scoring.
+    Default,
+    /// `immune_v1`, the CLI default.
+    Immune,
+    /// `snrna_v1`.
+    Snrna,
+    /// `tumor_v1`.
+    Tumor,
+}
+
+impl NamedProfile {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "default" => Some(NamedProfile::Default),
+            "immune" => Some(NamedProfile::Immune),
+            "snrna" => Some(NamedProfile::Snrna),
+            "tumor" => Some(NamedProfile::Tumor),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NamedProfile::Default => "default",
+            NamedProfile::Immune => "immune",
+            NamedProfile::Snrna => "snrna",
+            NamedProfile::Tumor => "tumor",
+        }
+    }
+
+    /// Name of the `ThresholdProfile` constructor, e.g. `snrna_v1`.
+    pub fn versioned_name(self) -> &'static str {
+        match self {
+            NamedProfile::Default => "default_v1",
+            NamedProfile::Immune => "immune_v1",
+            NamedProfile::Snrna => "snrna_v1",
+            NamedProfile::Tumor => "tumor_v1",
+        }
+    }
+
+    pub fn thresholds(self) -> ThresholdProfile {
+        match self {
+            NamedProfile::Default => ThresholdProfile::default_v1(),
+            NamedProfile::Immune => ThresholdProfile::immune_v1(),
+            NamedProfile::Snrna => ThresholdProfile::snrna_v1(),
+            NamedProfile::Tumor => ThresholdProfile::tumor_v1(),
+        }
+    }
+
+    pub fn scoring_mode(self) -> NuclearScoringMode {
+        self.thresholds().scoring_mode
+    }
+}
+
+/// How Stage 5 turns the confidence components into one confidence value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfidenceModel {
+    /// Weighted sum of the four components with a floor for structured cells
+    /// (`confidence_floor`); the immune-aware default.
+    AdditiveImmune,
+    /// Product of coverage, expression and ambient terms; the strict default.
+    LegacyMultiplicative,
+    /// Logistic of the weighted component sum, centered at 0.5.
+    Logistic,
+}
+
+impl ConfidenceModel {
+    /// The model each scoring mode uses unless `--confidence-model` is given.
+    pub fn for_scoring_mode(mode: NuclearScoringMode) -> Self {
+        match mode {
+            NuclearScoringMode::ImmuneAware => ConfidenceModel::AdditiveImmune,
+            NuclearScoringMode::StrictBulk => ConfidenceModel::LegacyMultiplicative,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "additive" => Some(ConfidenceModel::AdditiveImmune),
+            "multiplicative" => Some(ConfidenceModel::LegacyMultiplicative),
+            "logistic" => Some(ConfidenceModel::Logistic),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfidenceModel::AdditiveImmune => "additive",
+            ConfidenceModel::LegacyMultiplicative => "multiplicative",
+            ConfidenceModel::Logistic => "logistic",
+        }
+    }
+
+    /// Name shown in the text report.
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfidenceModel::AdditiveImmune => "immune-calibrated additive",
+            ConfidenceModel::LegacyMultiplicative => "legacy multiplicative",
+            ConfidenceModel::Logistic => "logistic",
+        }
+    }
+
+    /// `nuclearqc.tsv` columns for the per-cell confidence breakdown
+    /// (`--confidence-breakdown`). The multiplicative model records its
+    /// weighted coverage, expression and ambient terms; its fourth slot is
+    /// unused.
+    pub fn breakdown_columns(self) -> &'static [&'static str] {
+        match self {
+            ConfidenceModel::AdditiveImmune | ConfidenceModel::Logistic => &[
+                "conf_panel_coverage",
+                "conf_expr_support",
+                "conf_axis_structure",
+                "conf_consistency",
+            ],
+            ConfidenceModel::LegacyMultiplicative => &[
+                "conf_key_coverage_term",
+                "conf_expr_fraction_term",
+                "conf_ambient_term",
+            ],
+        }
+    }
+}
+
 impl ThresholdProfile {
     pub fn default_v1() -> Self {
         Self {
@@ -53,6 +259,50 @@ impl ThresholdProfile {
             rel_p70: 0.70,
             rel_p85: 0.85,
             confidence_low: 0.4,
+            confidence_w_coverage: 0.30,
+            confidence_w_expr_support: 0.25,
+            confidence_w_axis_structure: 0.25,
+            confidence_w_consistency: 0.20,
+            confidence_coverage_scale: 0.6,
+            confidence_axis_variance_scale: 0.05,
+            confidence_floor: 0.2,
+            confidence_structure_min: 0.2,
+            panel_coverage_min: 0.4,
+            mito_frac_max: 0.2,
+            ribo_frac_max: 0.5,
+            cell_cycle_share_min: 0.5,
+            collapsed_tbi_max: 0.15,
+            collapsed_entropy_max: 0.10,
+            rigid_trs_min: 0.75,
+            rigid_nsai_min: 0.55,
+            rigid_rci_max: 0.35,
+            committed_trs_min: 0.70,
+            committed_pds_min: 0.60,
+            committed_tbi_max: 0.45,
+            committed_nsai_max: 0.55,
+            stress_nsai_min: 0.65,
+            stress_rci_min: 0.35,
+            stress_tbi_min: 0.35,
+            stress_pds_max: 0.60,
+            plastic_nps_min: 0.60,
+            plastic_trs_max: 0.45,
+            plastic_pds_max: 0.50,
+            transient_nps_min: 0.45,
+            transient_iaa_min: 0.35,
+            transient_dfa_min: 0.35,
+            transient_trs_max: 0.55,
+            transient_pds_max: 0.65,
+            cea_ribo_adjust: false,
+            sum_mode: SumMode::Blocked,
+            quantile_method: QuantileMethod::Linear,
+            relative_within: RelativeWithin::Global,
+            relative_min_group_cells: 20,
+            key_panels: DEFAULT_KEY_PANELS.iter().map(|s| s.to_string()).collect(),
+            panel_score: PanelScoreMode::Sum,
+            winsorize_panels: None,
+            tail_trs_min: 0.75,
+            tail_nps_min: 0.60,
+            tail_rls_max: 0.35,
             scoring_mode: NuclearScoringMode::StrictBulk,
         }
     }
@@ -66,4 +316,96 @@ impl ThresholdProfile {
         base.scoring_mode = NuclearScoringMode::ImmuneAware;
         base
     }
+
+    /// Single-nucleus RNA: nuclei carry fewer and shorter transcripts, so
+    /// expression support counts for less, fewer genes are expected and key
+    /// panels are only partly covered even in good nuclei.
+    pub fn snrna_v1() -> Self {
+        let mut base = Self::immune_v1();
+        base.min_expr_genes = 3;
+        base.frac_rescale_max = 0.40;
+        base.confidence_w_coverage = 0.35;
+        base.confidence_w_expr_support = 0.15;
+        base.confidence_w_axis_structure = 0.30;
+        base.confidence_w_consistency = 0.20;
+        base.panel_coverage_min = 0.15;
+        base
+    }
+
+    /// Tumor samples: proliferation programs are expected, so only a
+    /// dominant proliferation share flags a cell cycle confounder.
+    pub fn tumor_v1() -> Self {
+        let mut base = Self::immune_v1();
+        base.cell_cycle_share_min = 0.7;
+        base
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [
+            ("confidence_w_coverage", self.confidence_w_coverage),
+            ("confidence_w_expr_support", self.confidence_w_expr_support),
+            (
+                "confidence_w_axis_structure",
+                self.confidence_w_axis_structure,
+            ),
+            ("confidence_w_consistency", self.confidence_w_consistency),
+        ];
+        for (name, w) in weights {
+            if !w.is_finite() || w < 0.0 {
+                return Err(format!("{name} must be a non-negative number, got {w}"));
+            }
+        }
+        if weights.iter().map(|(_, w)| *w).sum::<f32>() <= 0.0 {
+            return Err("confidence weights must not all be zero".to_string());
+        }
+        for (name, scale) in [
+            ("confidence_coverage_scale", self.confidence_coverage_scale),
+            (
+                "confidence_axis_variance_scale",
+                self.confidence_axis_variance_scale,
+            ),
+        ] {
+            if !scale.is_finite() || scale <= 0.0 {
+                return Err(format!("{name} must be positive, got {scale}"));
+            }
+        }
+        if let Some(q) = self.winsorize_panels
+            && !(q > 0.0 && q <= 1.0)
+        {
+            return Err(format!("winsorize_panels must be in (0, 1], got {q}"));
+        }
+        for (name, cutoff) in [
+            ("tail_trs_min", self.tail_trs_min),
+            ("tail_nps_min", self.tail_nps_min),
+            ("tail_rls_max", self.tail_rls_max),
+            ("confidence_floor", self.confidence_floor),
+            ("confidence_structure_min", self.confidence_structure_min),
+            ("panel_coverage_min", self.panel_coverage_min),
+        ] {
+            if !(0.0..=1.0).contains(&cutoff) {
+                return Err(format!("{name} must be in [0, 1], got {cutoff}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Confidence component weights normalized to sum to 1, in breakdown order
+    /// (panel coverage, expression support, axis structure, consistency).
+    pub fn confidence_weights(&self) -> [f32; 4] {
+        let raw = [
+            self.confidence_w_coverage,
+            self.confidence_w_expr_support,
+            self.confidence_w_axis_structure,
+            self.confidence_w_consistency,
+        ];
+        let sum = raw.iter().sum::<f32>();
+        if sum <= 0.0 {
+            return raw;
+        }
+        raw.map(|w| w / sum)
+    }
 }
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/model/thresholds.rs"]
+mod tests;
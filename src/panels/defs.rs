@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PanelGroup {
     Housekeeping,
@@ -10,12 +12,110 @@ pub enum PanelGroup {
     Confounder,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl PanelGroup {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "housekeeping" => Some(PanelGroup::Housekeeping),
+            "tf" => Some(PanelGroup::Tf),
+            "chromatin" => Some(PanelGroup::Chromatin),
+            "stress" => Some(PanelGroup::Stress),
+            "developmental" => Some(PanelGroup::Developmental),
+            "proliferation" => Some(PanelGroup::Proliferation),
+            "program" => Some(PanelGroup::Program),
+            "confounder" => Some(PanelGroup::Confounder),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PanelGroup::Housekeeping => "housekeeping",
+            PanelGroup::Tf => "tf",
+            PanelGroup::Chromatin => "chromatin",
+            PanelGroup::Stress => "stress",
+            PanelGroup::Developmental => "developmental",
+            PanelGroup::Proliferation => "proliferation",
+            PanelGroup::Program => "program",
+            PanelGroup::Confounder => "confounder",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct PanelDef {
-    pub id: &'static str,
-    pub name: &'static str,
+    pub id: String,
+    pub name: String,
     pub group: PanelGroup,
-    pub genes: &'static [&'static str],
+    pub genes: Vec<String>,
+    /// Per-gene weights aligned with `genes`; empty means every gene weighs 1.0.
+    pub weights: Vec<f32>,
+    /// Membership rules expanded against the reference when panels load;
+    /// matched genes weigh 1.0.
+    pub rules: Vec<GeneRule>,
+}
+
+impl PanelDef {
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+}
+
+/// Panel membership by symbol pattern (`prefix:MT-`, `regex:^HIST1H`),
+/// matched against the upper-cased reference symbols.
+#[derive(Debug, Clone)]
+pub enum GeneRule {
+    Prefix(String),
+    Regex(regex::Regex),
+}
+
+impl GeneRule {
+    /// `prefix:<text>` or `regex:<pattern>`; `None` for anything else.
+    /// Prefixes are upper-cased and patterns match ignoring case, since the
+    /// reference symbols are upper-cased.
+    pub fn parse(entry: &str) -> Option<Result<Self, String>> {
+        if let Some(prefix) = entry.strip_prefix("prefix:") {
+            let prefix = prefix.trim();
+            return Some(if prefix.is_empty() {
+                Err("empty prefix rule".to_string())
+            } else {
+                Ok(GeneRule::Prefix(prefix.to_ascii_uppercase()))
+            });
+        }
+        let pattern = entry.strip_prefix("regex:")?.trim();
+        Some(
+            regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(GeneRule::Regex)
+                .map_err(|err| format!("invalid regex {pattern}: {err}")),
+        )
+    }
+
+    pub fn matches(&self, symbol: &str) -> bool {
+        match self {
+            GeneRule::Prefix(prefix) => symbol.starts_with(prefix.as_str()),
+            GeneRule::Regex(re) => re.is_match(symbol),
+        }
+    }
+}
+
+impl fmt::Display for GeneRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneRule::Prefix(prefix) => write!(f, "prefix:{prefix}"),
+            GeneRule::Regex(re) => write!(f, "regex:{}", re.as_str()),
+        }
+    }
+}
+
+pub const CELL_CYCLE_S_PANEL: &str = "cell_cycle_s";
+pub const CELL_CYCLE_G2M_PANEL: &str = "cell_cycle_g2m";
+
+struct BuiltinPanel {
+    id: &'static str,
+    name: &'static str,
+    group: PanelGroup,
+    genes: &'static [&'static str],
 }
 
 const HOUSEKEEPING_CORE: &[&str] = &["ACTB", "GAPDH", "RPLP0", "B2M"];
@@ -23,6 +123,21 @@ const TF_BASIC: &[&str] = &["POU5F1", "SOX2", "NANOG", "MYC"];
 const STRESS_RESPONSE: &[&str] = &["FOS", "JUN", "ATF3", "HSP90AA1"];
 const CHROMATIN_CORE: &[&str] = &["SMARCA4", "SMARCB1", "EZH2", "ARID1A"];
 const PROLIFERATION_CORE: &[&str] = &["MKI67", "TOP2A", "PCNA", "MCM2"];
+// Tirosh et al. 2016 S and G2/M markers as shipped with Seurat (`cc.genes.updated.2019`).
+const CELL_CYCLE_S: &[&str] = &[
+    "MCM5", "PCNA", "TYMS", "FEN1", "MCM2", "MCM4", "RRM1", "UNG", "GINS2", "MCM6", "CDCA7", "DTL",
+    "PRIM1", "UHRF1", "CENPU", "HELLS", "RFC2", "RPA2", "NASP", "RAD51AP1", "GMNN", "WDR76",
+    "SLBP", "CCNE2", "UBR7", "POLD3", "MSH2", "ATAD2", "RAD51", "RRM2", "CDC45", "CDC6", "EXO1",
+    "TIPIN", "DSCC1", "BLM", "CASP8AP2", "USP1", "CLSPN", "POLA1", "CHAF1B", "BRIP1", "E2F8",
+];
+const CELL_CYCLE_G2M: &[&str] = &[
+    "HMGB2", "CDK1", "NUSAP1", "UBE2C", "BIRC5", "TPX2", "TOP2A", "NDC80", "CKS2", "NUF2", "CKS1B",
+    "MKI67", "TMPO", "CENPF", "TACC3", "PIMREG", "SMC4", "CCNB2", "CKAP2L", "CKAP2", "AURKB",
+    "BUB1", "KIF11", "ANP32E", "TUBB4B", "GTSE1", "KIF20B", "HJURP", "CDCA3", "JPT1", "CDC20",
+    "TTK", "CDC25C", "KIF2C", "RANGAP1", "NCAPD2", "DLGAP5", "CDCA2", "CDCA8", "ECT2", "KIF23",
+    "HMMR", "AURKA", "PSRC1", "ANLN", "LBR", "CKAP5", "CENPE", "CTCF", "NEK2", "G2E3", "GAS2L3",
+    "CBX5", "CENPA",
+];
 const DEVELOPMENTAL_CORE: &[&str] = &["SOX9", "PAX6", "GATA3", "TBX5"];
 const IMMUNE_ACTIVATION: &[&str] = &["CD69", "CD83", "HLA-DRA", "HLA-DRB1", "CD74"];
 const DIFFERENTIATION_FLUX: &[&str] = &["BCL6", "IRF4", "MYC"];
@@ -45,98 +160,110 @@ const REPLICATION_FORK_STABILITY: &[&str] = &[
 ];
 const CHECKPOINT_ACTIVATION: &[&str] = &["ATM", "ATR", "CHEK1", "CHEK2", "TP53", "CDKN1A"];
 
-const BUILTIN_PANELS: &[PanelDef] = &[
-    PanelDef {
+const BUILTIN_PANELS: &[BuiltinPanel] = &[
+    BuiltinPanel {
         id: "housekeeping_core",
         name: "Housekeeping Core",
         group: PanelGroup::Housekeeping,
         genes: HOUSEKEEPING_CORE,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "tf_basic",
         name: "TF Basic",
         group: PanelGroup::Tf,
         genes: TF_BASIC,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "chromatin_core",
         name: "Chromatin Core",
         group: PanelGroup::Chromatin,
         genes: CHROMATIN_CORE,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "stress_response",
         name: "Stress Response",
         group: PanelGroup::Stress,
         genes: STRESS_RESPONSE,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "developmental_core",
         name: "Developmental Core",
         group: PanelGroup::Developmental,
         genes: DEVELOPMENTAL_CORE,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "proliferation_core",
         name: "Proliferation Core",
         group: PanelGroup::Proliferation,
         genes: PROLIFERATION_CORE,
     },
-    PanelDef {
+    BuiltinPanel {
+        id: CELL_CYCLE_S_PANEL,
+        name: "Cell Cycle S Phase",
+        group: PanelGroup::Confounder,
+        genes: CELL_CYCLE_S,
+    },
+    BuiltinPanel {
+        id: CELL_CYCLE_G2M_PANEL,
+        name: "Cell Cycle G2M Phase",
+        group: PanelGroup::Confounder,
+        genes: CELL_CYCLE_G2M,
+    },
+    BuiltinPanel {
         id: "immune_activation",
         name: "Immune Activation",
         group: PanelGroup::Program,
         genes: IMMUNE_ACTIVATION,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "differentiation_flux",
         name: "Differentiation Flux",
         group: PanelGroup::Program,
         genes: DIFFERENTIATION_FLUX,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "clonal_engagement",
         name: "Clonal Engagement",
         group: PanelGroup::Program,
         genes: CLONAL_ENGAGEMENT,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "replication_stress_genes",
         name: "Replication Stress",
         group: PanelGroup::Confounder,
         genes: REPLICATION_STRESS_GENES,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "dna_repair_hr",
         name: "DNA Repair HR",
         group: PanelGroup::Confounder,
         genes: DNA_REPAIR_HR,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "dna_repair_nhej",
         name: "DNA Repair NHEJ",
         group: PanelGroup::Confounder,
         genes: DNA_REPAIR_NHEJ,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "chromatin_compaction",
         name: "Chromatin Compaction",
         group: PanelGroup::Confounder,
         genes: CHROMATIN_COMPACTION,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "chromatin_open_state",
         name: "Chromatin Open State",
         group: PanelGroup::Confounder,
         genes: CHROMATIN_OPEN_STATE,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "replication_fork_stability",
         name: "Replication Fork Stability",
         group: PanelGroup::Confounder,
         genes: REPLICATION_FORK_STABILITY,
     },
-    PanelDef {
+    BuiltinPanel {
         id: "checkpoint_activation",
         name: "Checkpoint Activation",
         group: PanelGroup::Confounder,
@@ -144,6 +271,16 @@ const BUILTIN_PANELS: &[PanelDef] = &[
     },
 ];
 
-pub fn builtin_panels() -> &'static [PanelDef] {
+pub fn builtin_panels() -> Vec<PanelDef> {
     BUILTIN_PANELS
+        .iter()
+        .map(|p| PanelDef {
+            id: p.id.to_string(),
+            name: p.name.to_string(),
+            group: p.group,
+            genes: p.genes.iter().map(|g| g.to_string()).collect(),
+            weights: Vec::new(),
+            rules: Vec::new(),
+        })
+        .collect()
 }
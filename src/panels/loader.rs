@@ -1,54 +1,460 @@
-use crate::input::{GeneIndex, Species};
-use crate::panels::defs::{PanelDef, builtin_panels};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::input::{GeneIndex, InputError, Species};
+use crate::panels::defs::{GeneRule, PanelDef, PanelGroup, builtin_panels};
 use crate::panels::mapping::{build_symbol_map, map_symbol};
 use crate::panels::{Panel, PanelAudit, PanelSet};
 
-pub fn load_panels(species: Species, gene_index: &GeneIndex) -> (PanelSet, Vec<PanelAudit>) {
-    let defs = builtin_panels();
+/// `--panel-rule-max-genes` when not given.
+pub const DEFAULT_MAX_RULE_GENES: usize = 500;
+
+/// Maps `defs` onto the reference, expanding membership rules; a rule
+/// matching more than `max_rule_genes` genes is an error.
+pub fn load_panels(
+    species: Species,
+    gene_index: &GeneIndex,
+    defs: &[PanelDef],
+    max_rule_genes: usize,
+) -> Result<(PanelSet, Vec<PanelAudit>), InputError> {
     let symbol_map = build_symbol_map(gene_index);
 
     let mut panels = Vec::with_capacity(defs.len());
     let mut audits = Vec::with_capacity(defs.len());
 
     for def in defs {
-        let (panel, audit) = map_panel(def, species, &symbol_map);
+        let (panel, audit) = map_panel(def, species, &symbol_map, gene_index, max_rule_genes)?;
         panels.push(panel);
         audits.push(audit);
     }
 
-    (PanelSet { panels }, audits)
+    let panel_set = PanelSet { panels };
+    for overlap in group_overlaps(&panel_set) {
+        if overlap.fraction() > PANEL_OVERLAP_WARN_FRACTION {
+            crate::warn!(
+                "{} of {} genes in the {} panel group are shared between panels ({}); group sums count them more than once",
+                overlap.shared.len(),
+                overlap.n_genes,
+                overlap.group.as_str(),
+                overlap.panel_ids.join(", ")
+            );
+            crate::debug!(
+                "shared {} genes: {}",
+                overlap.group.as_str(),
+                overlap
+                    .shared
+                    .iter()
+                    .map(|&g| gene_index.symbols_by_gene_id[g as usize].as_str())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            );
+        }
+    }
+
+    Ok((panel_set, audits))
+}
+
+/// Mapping audits alone, without building the panel set or warning.
+pub fn audit_panels(
+    species: Species,
+    gene_index: &GeneIndex,
+    defs: &[PanelDef],
+    max_rule_genes: usize,
+) -> Result<Vec<PanelAudit>, InputError> {
+    let symbol_map = build_symbol_map(gene_index);
+    defs.iter()
+        .map(|def| Ok(map_panel(def, species, &symbol_map, gene_index, max_rule_genes)?.1))
+        .collect()
+}
+
+/// Minimum share of a panel's defined genes that must map to the reference
+/// (`--min-mappable-fraction`): a global value with per-group overrides.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MinMappable {
+    pub global: f32,
+    pub groups: Vec<(PanelGroup, f32)>,
+}
+
+impl MinMappable {
+    /// Comma-separated entries, each a bare fraction (global) or
+    /// `group=fraction`, e.g. `0.5,program=0.3`. Fractions lie in [0, 1].
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut out = MinMappable::default();
+        for entry in s.split(',').map(str::trim) {
+            let (group, raw) = match entry.split_once('=') {
+                Some((group, raw)) => {
+                    let group = PanelGroup::parse(group.trim())
+                        .ok_or_else(|| format!("unknown panel group {}", group.trim()))?;
+                    (Some(group), raw.trim())
+                }
+                None => (None, entry),
+            };
+            let fraction = raw
+                .parse::<f32>()
+                .ok()
+                .filter(|f| (0.0..=1.0).contains(f))
+                .ok_or_else(|| format!("invalid fraction '{raw}' (expected 0..=1)"))?;
+            match group {
+                Some(group) => {
+                    out.groups.retain(|(g, _)| *g != group);
+                    out.groups.push((group, fraction));
+                }
+                None => out.global = fraction,
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn for_group(&self, group: PanelGroup) -> f32 {
+        self.groups
+            .iter()
+            .find(|(g, _)| *g == group)
+            .map_or(self.global, |&(_, fraction)| fraction)
+    }
+}
+
+/// A panel whose mappable share fell below its group's minimum.
+#[derive(Debug, Clone)]
+pub struct LowMappabilityPanel {
+    pub panel_id: String,
+    pub group: PanelGroup,
+    pub mappable_fraction: f32,
+    pub min_fraction: f32,
+    pub missing_genes: Vec<String>,
+}
+
+/// Panels of `defs` (aligned with `audits`) mapping less than their minimum
+/// share of genes, in definition order.
+pub fn low_mappability_panels(
+    defs: &[PanelDef],
+    audits: &[PanelAudit],
+    min: &MinMappable,
+) -> Vec<LowMappabilityPanel> {
+    defs.iter()
+        .zip(audits)
+        .filter_map(|(def, audit)| {
+            let mappable_fraction = if audit.panel_size_defined == 0 {
+                0.0
+            } else {
+                audit.panel_size_mappable as f32 / audit.panel_size_defined as f32
+            };
+            let min_fraction = min.for_group(def.group);
+            (mappable_fraction < min_fraction).then(|| LowMappabilityPanel {
+                panel_id: def.id.clone(),
+                group: def.group,
+                mappable_fraction,
+                min_fraction,
+                missing_genes: audit.missing_genes.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Share of a group's distinct genes above which `load_panels` warns.
+pub const PANEL_OVERLAP_WARN_FRACTION: f32 = 0.1;
+
+/// Genes mapped into more than one panel of the same group.
+#[derive(Debug, Clone)]
+pub struct GroupOverlap {
+    pub group: PanelGroup,
+    /// Ids of the panels holding at least one shared gene.
+    pub panel_ids: Vec<String>,
+    /// Shared gene ids, ascending.
+    pub shared: Vec<u32>,
+    /// Distinct genes across all panels of the group.
+    pub n_genes: usize,
+}
+
+impl GroupOverlap {
+    pub fn fraction(&self) -> f32 {
+        if self.n_genes == 0 {
+            0.0
+        } else {
+            self.shared.len() as f32 / self.n_genes as f32
+        }
+    }
+}
+
+/// One entry per group with shared genes, in first-panel order. Confounder
+/// panels are read individually rather than summed by group, so they are
+/// skipped.
+pub fn group_overlaps(panel_set: &PanelSet) -> Vec<GroupOverlap> {
+    let mut groups: Vec<PanelGroup> = Vec::new();
+    for panel in &panel_set.panels {
+        if panel.group != PanelGroup::Confounder && !groups.contains(&panel.group) {
+            groups.push(panel.group);
+        }
+    }
+
+    let mut out = Vec::new();
+    for group in groups {
+        let members = panel_set
+            .panels
+            .iter()
+            .filter(|p| p.group == group)
+            .collect::<Vec<_>>();
+        let mut owners: BTreeMap<u32, usize> = BTreeMap::new();
+        for panel in &members {
+            let mut genes = panel.genes.clone();
+            genes.sort_unstable();
+            genes.dedup();
+            for gene in genes {
+                *owners.entry(gene).or_default() += 1;
+            }
+        }
+        let shared = owners
+            .iter()
+            .filter(|&(_, &n)| n > 1)
+            .map(|(&g, _)| g)
+            .collect::<Vec<_>>();
+        if shared.is_empty() {
+            continue;
+        }
+        let panel_ids = members
+            .iter()
+            .filter(|p| p.genes.iter().any(|g| shared.binary_search(g).is_ok()))
+            .map(|p| p.id.clone())
+            .collect();
+        out.push(GroupOverlap {
+            group,
+            panel_ids,
+            shared,
+            n_genes: owners.len(),
+        });
+    }
+    out
+}
+
+/// Built-in panels, with custom panels from `path` replacing built-ins of the
+/// same id and appending the rest in file order.
+pub fn resolve_panel_defs(path: Option<&Path>) -> Result<Vec<PanelDef>, InputError> {
+    let mut defs = builtin_panels();
+    let Some(path) = path else {
+        return Ok(defs);
+    };
+    for custom in read_panel_file(path)? {
+        match defs.iter_mut().find(|d| d.id == custom.id) {
+            Some(slot) => *slot = custom,
+            None => defs.push(custom),
+        }
+    }
+    Ok(defs)
+}
+
+/// Keeps only the panels in `only` (when set) and drops those in `disable`,
+/// preserving definition order. Unknown ids are an error.
+pub fn select_panel_defs(
+    defs: Vec<PanelDef>,
+    only: Option<&[String]>,
+    disable: &[String],
+) -> Result<Vec<PanelDef>, InputError> {
+    for (flag, ids) in [
+        ("--only-panels", only.unwrap_or(&[])),
+        ("--disable-panels", disable),
+    ] {
+        if let Some(unknown) = ids.iter().find(|id| !defs.iter().any(|d| d.id == **id)) {
+            return Err(InputError::InvalidInput(format!(
+                "unknown panel in {flag}: {unknown}"
+            )));
+        }
+    }
+    Ok(defs
+        .into_iter()
+        .filter(|d| only.is_none_or(|ids| ids.contains(&d.id)) && !disable.contains(&d.id))
+        .collect())
+}
+
+/// Custom panel file: one panel per line, `panel_id<TAB>group<TAB>genes`,
+/// where genes are comma-separated `SYMBOL`, `SYMBOL:weight` or membership
+/// rules `prefix:<text>` and `regex:<pattern>`; commas inside `{}` belong
+/// to the pattern (`{2,3}`). Blank lines and lines starting with `#` are
+/// ignored.
+pub fn read_panel_file(path: &Path) -> Result<Vec<PanelDef>, InputError> {
+    let text = std::fs::read_to_string(path)?;
+    parse_panel_file(&text)
+}
+
+pub fn parse_panel_file(text: &str) -> Result<Vec<PanelDef>, InputError> {
+    let mut defs: Vec<PanelDef> = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() != 3 {
+            return Err(InputError::Parse(format!(
+                "panel file line {line_no}: expected 3 tab-separated columns"
+            )));
+        }
+        let id = cols[0].trim();
+        if id.is_empty() {
+            return Err(InputError::Parse(format!(
+                "panel file line {line_no}: empty panel id"
+            )));
+        }
+        if defs.iter().any(|d| d.id == id) {
+            return Err(InputError::Parse(format!(
+                "panel file line {line_no}: duplicate panel id {id}"
+            )));
+        }
+        let group = PanelGroup::parse(cols[1].trim()).ok_or_else(|| {
+            InputError::Parse(format!(
+                "panel file line {line_no}: unknown group {}",
+                cols[1].trim()
+            ))
+        })?;
+
+        let mut genes = Vec::new();
+        let mut weights = Vec::new();
+        let mut rules = Vec::new();
+        for entry in split_gene_entries(cols[2])
+            .into_iter()
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            if let Some(rule) = GeneRule::parse(entry) {
+                rules.push(rule.map_err(|err| {
+                    InputError::Parse(format!("panel file line {line_no}: {err}"))
+                })?);
+                continue;
+            }
+            let (symbol, weight) = match entry.split_once(':') {
+                Some((symbol, raw)) => {
+                    let weight = raw
+                        .trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|w| w.is_finite() && *w > 0.0)
+                        .ok_or_else(|| {
+                            InputError::Parse(format!(
+                                "panel file line {line_no}: invalid weight for {}",
+                                symbol.trim()
+                            ))
+                        })?;
+                    (symbol.trim(), weight)
+                }
+                None => (entry, 1.0),
+            };
+            genes.push(symbol.to_string());
+            weights.push(weight);
+        }
+        if genes.is_empty() && rules.is_empty() {
+            return Err(InputError::Parse(format!(
+                "panel file line {line_no}: panel {id} has no genes"
+            )));
+        }
+        if weights.iter().all(|&w| w == 1.0) {
+            weights.clear();
+        }
+
+        defs.push(PanelDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            group,
+            genes,
+            weights,
+            rules,
+        });
+    }
+    Ok(defs)
+}
+
+// Splits on commas outside `{}`, so regex repetitions stay whole.
+fn split_gene_entries(genes: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, c) in genes.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                entries.push(&genes[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&genes[start..]);
+    entries
 }
 
 fn map_panel(
     def: &PanelDef,
     species: Species,
     symbol_map: &std::collections::BTreeMap<String, u32>,
-) -> (Panel, PanelAudit) {
+    gene_index: &GeneIndex,
+    max_rule_genes: usize,
+) -> Result<(Panel, PanelAudit), InputError> {
     let mut genes = Vec::new();
+    let mut weights = Vec::new();
     let mut missing = Vec::new();
 
-    for &symbol in def.genes {
+    for (idx, symbol) in def.genes.iter().enumerate() {
         if let Some(gene_id) = map_symbol(species, symbol, symbol_map) {
             genes.push(gene_id);
+            if def.is_weighted() {
+                weights.push(def.weights[idx]);
+            }
         } else {
             missing.push(symbol.to_string());
         }
     }
 
+    // Rule matches follow the listed genes in gene id order, skipping genes
+    // already in the panel. A rule counts as one defined entry, mappable
+    // when it matches any gene.
+    let mut expanded = Vec::new();
+    let mut rules_matched = 0;
+    for rule in &def.rules {
+        let matched = gene_index
+            .symbols_by_gene_id
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| rule.matches(symbol))
+            .map(|(gene_id, _)| gene_id as u32)
+            .collect::<Vec<_>>();
+        if matched.len() > max_rule_genes {
+            return Err(InputError::InvalidInput(format!(
+                "panel {}: rule {rule} matches {} genes, more than --panel-rule-max-genes {max_rule_genes}",
+                def.id,
+                matched.len()
+            )));
+        }
+        if matched.is_empty() {
+            missing.push(rule.to_string());
+        } else {
+            rules_matched += 1;
+        }
+        expanded.extend(matched);
+    }
+    expanded.sort_unstable();
+    expanded.dedup();
+    expanded.retain(|gene_id| !genes.contains(gene_id));
+    if def.is_weighted() {
+        weights.extend(std::iter::repeat_n(1.0, expanded.len()));
+    }
+    let n_listed_mapped = genes.len();
+    genes.extend(expanded);
+
     let audit = PanelAudit {
-        panel_id: def.id.to_string(),
-        panel_size_defined: def.genes.len(),
-        panel_size_mappable: genes.len(),
+        panel_id: def.id.clone(),
+        panel_size_defined: def.genes.len() + def.rules.len(),
+        panel_size_mappable: n_listed_mapped + rules_matched,
         missing_genes: missing.clone(),
+        rules: def.rules.iter().map(|rule| rule.to_string()).collect(),
     };
 
     let panel = Panel {
-        id: def.id,
-        name: def.name,
+        id: def.id.clone(),
+        name: def.name.clone(),
         group: def.group,
         genes,
+        weights,
         missing,
     };
 
-    (panel, audit)
+    Ok((panel, audit))
 }
@@ -4,12 +4,16 @@ pub mod mapping;
 
 pub use defs::PanelGroup;
 
+use crate::report::{QuantileMethod, median};
+
 #[derive(Debug, Clone)]
 pub struct Panel {
-    pub id: &'static str,
-    pub name: &'static str,
+    pub id: String,
+    pub name: String,
     pub group: PanelGroup,
     pub genes: Vec<u32>,
+    /// Weights aligned with `genes`; empty for unweighted panels.
+    pub weights: Vec<f32>,
     pub missing: Vec<String>,
 }
 
@@ -18,19 +22,201 @@ pub struct PanelSet {
     pub panels: Vec<Panel>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PanelScores {
     pub panel_sum: Vec<Vec<f32>>,
+    /// `panel_sum` divided by the panel's mappable size; 0 for unmappable panels.
+    pub panel_mean: Vec<Vec<f32>>,
+    /// Raw count sums per panel, independent of `--normalize`.
+    pub panel_raw_sum: Vec<Vec<f32>>,
     pub panel_detected: Vec<Vec<u32>>,
     pub panel_coverage: Vec<Vec<f32>>,
 }
 
+/// Which per-panel value feeds axis metrics (`--panel-score`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelScoreMode {
+    Sum,
+    Mean,
+}
+
+impl PanelScoreMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PanelScoreMode::Sum => "sum",
+            PanelScoreMode::Mean => "mean",
+        }
+    }
+}
+
+impl Panel {
+    pub fn weight(&self, idx: usize) -> f32 {
+        self.weights.get(idx).copied().unwrap_or(1.0)
+    }
+
+    pub fn is_weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+}
+
+impl PanelScores {
+    /// Appends the cells of `other`, scored against the same panel set.
+    pub fn append(&mut self, mut other: PanelScores) {
+        self.panel_sum.append(&mut other.panel_sum);
+        self.panel_mean.append(&mut other.panel_mean);
+        self.panel_raw_sum.append(&mut other.panel_raw_sum);
+        self.panel_detected.append(&mut other.panel_detected);
+        self.panel_coverage.append(&mut other.panel_coverage);
+    }
+
+    pub fn values(&self, mode: PanelScoreMode) -> &[Vec<f32>] {
+        match mode {
+            PanelScoreMode::Sum => &self.panel_sum,
+            PanelScoreMode::Mean => &self.panel_mean,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PanelAudit {
     pub panel_id: String,
+    /// Listed genes plus membership rules, independent of the reference.
     pub panel_size_defined: usize,
+    /// Listed genes found in the reference plus rules matching any gene.
     pub panel_size_mappable: usize,
+    /// Listed genes and rules that matched nothing.
     pub missing_genes: Vec<String>,
+    /// Membership rules as written (`prefix:MT-`).
+    pub rules: Vec<String>,
+}
+
+/// One TSV row per panel audit; missing genes are comma-separated.
+pub fn render_panel_audits_tsv(audits: &[PanelAudit]) -> String {
+    let mut out =
+        String::from("panel_id\tpanel_size_defined\tpanel_size_mappable\tmissing_genes\n");
+    for audit in audits {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            audit.panel_id,
+            audit.panel_size_defined,
+            audit.panel_size_mappable,
+            audit.missing_genes.join(",")
+        ));
+    }
+    out
+}
+
+/// Cell-cycle phase called from the S and G2M panels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellCyclePhase {
+    G1,
+    S,
+    G2M,
+}
+
+impl CellCyclePhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CellCyclePhase::G1 => "G1",
+            CellCyclePhase::S => "S",
+            CellCyclePhase::G2M => "G2M",
+        }
+    }
+}
+
+/// Detected genes a phase panel needs before it can win a cell.
+const CELL_CYCLE_MIN_DETECTED: u32 = 2;
+
+/// Per-cell phase: the S or G2M panel with the larger size-normalized score
+/// wins if it has at least `CELL_CYCLE_MIN_DETECTED` detected genes; ties and
+/// cells without enough phase signal are `G1`. Every cell is `G1` when either
+/// panel is absent from the set.
+pub fn compute_cell_cycle_phase(panel_set: &PanelSet, scores: &PanelScores) -> Vec<CellCyclePhase> {
+    let n_cells = scores.panel_mean.len();
+    let find = |id: &str| panel_set.panels.iter().position(|p| p.id == id);
+    let (Some(s_idx), Some(g2m_idx)) = (
+        find(defs::CELL_CYCLE_S_PANEL),
+        find(defs::CELL_CYCLE_G2M_PANEL),
+    ) else {
+        return vec![CellCyclePhase::G1; n_cells];
+    };
+    (0..n_cells)
+        .map(|cell| {
+            let s = scores.panel_mean[cell][s_idx];
+            let g2m = scores.panel_mean[cell][g2m_idx];
+            let (phase, idx) = if s > g2m {
+                (CellCyclePhase::S, s_idx)
+            } else if g2m > s {
+                (CellCyclePhase::G2M, g2m_idx)
+            } else {
+                return CellCyclePhase::G1;
+            };
+            if scores.panel_detected[cell][idx] >= CELL_CYCLE_MIN_DETECTED {
+                phase
+            } else {
+                CellCyclePhase::G1
+            }
+        })
+        .collect()
+}
+
+/// Panels whose absence makes a cell's panel coverage unreliable.
+pub const DEFAULT_KEY_PANELS: &[&str] = &[
+    "housekeeping_core",
+    "tf_basic",
+    "chromatin_core",
+    "immune_activation",
+];
+
+/// Per-cell MissingKeyPanels state. A key panel with no mappable genes marks
+/// every cell (reference-level gap); otherwise a cell is marked when any key
+/// panel has no detected gene in that cell. Unknown ids are ignored.
+pub fn compute_key_panels_missing(
+    panel_set: &PanelSet,
+    scores: &PanelScores,
+    key_panels: &[String],
+) -> Vec<bool> {
+    let key_idx = panel_set
+        .panels
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| key_panels.contains(&p.id))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    let n_cells = scores.panel_detected.len();
+    if key_idx
+        .iter()
+        .any(|&idx| panel_set.panels[idx].genes.is_empty())
+    {
+        return vec![true; n_cells];
+    }
+    scores
+        .panel_detected
+        .iter()
+        .map(|row| key_idx.iter().any(|&idx| row[idx] == 0))
+        .collect()
+}
+
+/// Per-cell median of panel coverage across all panels; with linear
+/// quantiles even panel counts average the two central coverages.
+pub fn compute_key_panel_coverage(
+    panel_set: &PanelSet,
+    scores: &PanelScores,
+    method: QuantileMethod,
+) -> Vec<f32> {
+    let n_panels = panel_set.panels.len();
+    scores
+        .panel_coverage
+        .iter()
+        .map(|row| {
+            if n_panels == 0 {
+                0.0
+            } else {
+                median(&row[..n_panels.min(row.len())], method)
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use crate::input::InputBundle;
+use crate::metrics::composition::{mito_fraction, ribo_fraction};
+use crate::model::thresholds::ThresholdProfile;
+use crate::panels::{PanelAudit, PanelScores, PanelSet};
+use crate::pipeline::stage2_normalize::{
+    ExprAccessor, NormalizedExport, Stage2Error, Stage2Params, build_expr_chunks,
+};
+use crate::pipeline::stage3_panels::{
+    PanelContributions, Stage3Output, explain_panel_genes, find_panel, score_panels,
+};
+use crate::pipeline::stage4_axes::Stage4Cells;
+
+/// Spill directory under the output directory for `--chunk-cells` runs on an
+/// MTX; removed once the chunks have been scanned.
+pub const CHUNK_SPILL_DIR: &str = ".nuclearqc_chunks";
+
+/// Per-cell vectors read from expression outside Stage 3, compact enough to
+/// hold for every cell once the accessor itself is gone.
+#[derive(Debug, Clone, Default)]
+pub struct CellScan {
+    pub stage4: Stage4Cells,
+    pub pct_mito: Vec<f32>,
+    pub pct_ribo: Vec<f32>,
+    pub libsize: Vec<f64>,
+    pub nnz: Vec<u32>,
+    /// Control counts the chunked read summed for `InputBundle::control_counts`.
+    pub control_counts: Option<Vec<f64>>,
+}
+
+impl CellScan {
+    pub fn scan(
+        accessor: &dyn ExprAccessor,
+        bundle: &InputBundle,
+        thresholds: &ThresholdProfile,
+    ) -> Self {
+        Self {
+            stage4: Stage4Cells::scan(accessor, &bundle.gene_index, bundle.species, thresholds),
+            pct_mito: mito_fraction(accessor, &bundle.gene_index),
+            pct_ribo: ribo_fraction(accessor, &bundle.gene_index),
+            libsize: (0..accessor.n_cells())
+                .map(|cell| accessor.libsize(cell))
+                .collect(),
+            nnz: (0..accessor.n_cells())
+                .map(|cell| accessor.nnz(cell))
+                .collect(),
+            control_counts: None,
+        }
+    }
+
+    fn append(&mut self, mut other: CellScan) {
+        self.stage4.append(other.stage4);
+        self.pct_mito.append(&mut other.pct_mito);
+        self.pct_ribo.append(&mut other.pct_ribo);
+        self.libsize.append(&mut other.libsize);
+        self.nnz.append(&mut other.nnz);
+    }
+}
+
+/// Stage 3 and the expression-reading half of Stage 4 over `chunk_cells`
+/// cells at a time (`--chunk-cells`). Every per-cell quantity depends on that
+/// cell alone, so the concatenated result is bitwise identical to a single
+/// in-memory pass; only cross-cell calibration runs afterwards, on these
+/// vectors. `panels` is the output of `load_panels`.
+pub fn run_chunked(
+    bundle: &InputBundle,
+    params: &Stage2Params,
+    chunk_cells: usize,
+    spill_dir: &Path,
+    panels: (PanelSet, Vec<PanelAudit>),
+    explain_panel: Option<&str>,
+    thresholds: &ThresholdProfile,
+    mut export: Option<&mut NormalizedExport>,
+) -> Result<(Stage3Output, CellScan), Stage2Error> {
+    let (panels, audits) = panels;
+    let mut chunks = build_expr_chunks(bundle, params, chunk_cells, spill_dir)?;
+    let n_chunks = chunks.n_chunks();
+
+    let explained = find_panel(&panels, explain_panel);
+    let mut contributions = explained.map(|panel| PanelContributions {
+        panel_id: panel.id.clone(),
+        top_genes: Vec::new(),
+    });
+    let mut scores = PanelScores::default();
+    let mut scan = CellScan::default();
+    for chunk in 0..n_chunks {
+        crate::debug!("scanning chunk {}/{}", chunk + 1, n_chunks);
+        let accessor = chunks.chunk(chunk)?;
+        if let Some(export) = export.as_deref_mut() {
+            export.append(accessor.as_ref())?;
+        }
+        scores.append(score_panels(accessor.as_ref(), &panels));
+        if let (Some(all), Some(panel)) = (contributions.as_mut(), explained) {
+            all.append(explain_panel_genes(accessor.as_ref(), panel));
+        }
+        scan.append(CellScan::scan(accessor.as_ref(), bundle, thresholds));
+    }
+    scan.control_counts = chunks.control_counts();
+
+    Ok((
+        Stage3Output {
+            panels,
+            scores,
+            audits,
+            contributions,
+        },
+        scan,
+    ))
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/pipeline/cell_scan.rs"]
+mod tests;
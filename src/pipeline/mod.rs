@@ -1,3 +1,4 @@
+pub mod cell_scan;
 pub mod stage2_normalize;
 pub mod stage3_panels;
 pub mod stage4_axes;
@@ -1,10 +1,13 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use crate::input::cache::{
-    CacheMeta, CachedNormalizedData, cache_path_default, hash_bytes, hash_file,
-    read_normalized_cache, write_normalized_cache,
+    CacheCodec, CacheMeta, CachedNormalizedData, NormalizedCsc, cache_path_default, hash_bytes,
+    hash_file, read_normalized_cache, write_normalized_cache,
+};
+use crate::input::mtx::{
+    CscMatrix, MtxWriter, collect_columns, read_mtx_chunk, read_mtx_csc, spill_mtx_chunks,
 };
-use crate::input::mtx::{CscMatrix, read_mtx_csc};
 use crate::input::organelle_bin::OrganelleBin;
 use crate::input::{GeneIndex, InputBundle, InputError, InputSourceKind};
 
@@ -35,13 +38,15 @@ pub trait ExprAccessor {
     fn n_cells(&self) -> usize;
     fn n_genes(&self) -> usize;
     fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32));
-    fn libsize(&self, cell: usize) -> f32;
+    /// Visits the raw counts of a cell, independent of any normalization.
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32));
+    fn libsize(&self, cell: usize) -> f64;
     fn nnz(&self, cell: usize) -> u32;
 }
 
 pub struct RawCountsAccessor {
-    cols: Vec<Vec<(u32, i64)>>,
-    libsizes: Vec<f32>,
+    csc: CscMatrix,
+    libsizes: Vec<f64>,
     nnz: Vec<u32>,
     n_genes: usize,
     normalize: bool,
@@ -50,7 +55,7 @@ pub struct RawCountsAccessor {
 
 impl ExprAccessor for RawCountsAccessor {
     fn n_cells(&self) -> usize {
-        self.cols.len()
+        self.csc.n_cols
     }
 
     fn n_genes(&self) -> usize {
@@ -58,8 +63,9 @@ impl ExprAccessor for RawCountsAccessor {
     }
 
     fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
-        let lib = self.libsizes[cell] as f64;
-        for &(gene_id, count) in &self.cols[cell] {
+        let lib = self.libsizes[cell];
+        let (gene_ids, counts) = self.csc.column(cell);
+        for (&gene_id, &count) in gene_ids.iter().zip(counts) {
             let value = if self.normalize {
                 if lib == 0.0 {
                     0.0
@@ -74,7 +80,14 @@ impl ExprAccessor for RawCountsAccessor {
         }
     }
 
-    fn libsize(&self, cell: usize) -> f32 {
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        let (gene_ids, counts) = self.csc.column(cell);
+        for (&gene_id, &count) in gene_ids.iter().zip(counts) {
+            f(gene_id, count as f32);
+        }
+    }
+
+    fn libsize(&self, cell: usize) -> f64 {
         self.libsizes[cell]
     }
 
@@ -83,17 +96,18 @@ impl ExprAccessor for RawCountsAccessor {
     }
 }
 
+/// Serves normalized values straight from the cache layout; mapped from disk
+/// on a cache hit.
 pub struct CachedNormalizedAccessor {
-    cols: Vec<Vec<(u32, f32)>>,
-    libsizes: Vec<f32>,
-    nnz: Vec<u32>,
+    csc: NormalizedCsc,
     n_genes: usize,
+    scale: f32,
 }
 
 pub struct OrganelleCountsAccessor {
     bin: OrganelleBin,
     gene_index: GeneIndex,
-    libsizes: Vec<f32>,
+    libsizes: Vec<f64>,
     nnz: Vec<u32>,
     normalize: bool,
     scale: f32,
@@ -110,13 +124,11 @@ impl ExprAccessor for OrganelleCountsAccessor {
     }
 
     fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
-        let start = self.bin.csc.col_ptr[cell] as usize;
-        let end = self.bin.csc.col_ptr[cell + 1] as usize;
-        let lib = self.libsizes[cell] as f64;
-        for idx in start..end {
-            let feature = self.bin.csc.row_idx[idx] as usize;
-            if let Some(gene_id) = self.gene_index.gene_id_by_feature[feature] {
-                let count = self.bin.csc.values[idx] as f64;
+        let (rows, counts) = self.bin.csc.column(cell);
+        let lib = self.libsizes[cell];
+        for (&feature, &count) in rows.iter().zip(counts) {
+            if let Some(gene_id) = self.gene_index.gene_id_by_feature[feature as usize] {
+                let count = count as f64;
                 let value = if self.normalize {
                     if lib == 0.0 {
                         0.0
@@ -132,7 +144,16 @@ impl ExprAccessor for OrganelleCountsAccessor {
         }
     }
 
-    fn libsize(&self, cell: usize) -> f32 {
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        let (rows, counts) = self.bin.csc.column(cell);
+        for (&feature, &count) in rows.iter().zip(counts) {
+            if let Some(gene_id) = self.gene_index.gene_id_by_feature[feature as usize] {
+                f(gene_id as u32, count as f32);
+            }
+        }
+    }
+
+    fn libsize(&self, cell: usize) -> f64 {
         self.libsizes[cell]
     }
 
@@ -143,7 +164,7 @@ impl ExprAccessor for OrganelleCountsAccessor {
 
 impl ExprAccessor for CachedNormalizedAccessor {
     fn n_cells(&self) -> usize {
-        self.cols.len()
+        self.csc.n_cells()
     }
 
     fn n_genes(&self) -> usize {
@@ -151,31 +172,240 @@ impl ExprAccessor for CachedNormalizedAccessor {
     }
 
     fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
-        for &(gene_id, value) in &self.cols[cell] {
-            f(gene_id, value);
+        self.csc.for_cell(cell, f);
+    }
+
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        // Invert ln(1 + count / lib * scale); exact up to f32 rounding.
+        let lib = self.csc.libsize(cell);
+        let scale = self.scale as f64;
+        self.csc.for_cell(cell, &mut |gene_id, value| {
+            let count = (value as f64).exp_m1() * lib / scale;
+            f(gene_id, count as f32);
+        });
+    }
+
+    fn libsize(&self, cell: usize) -> f64 {
+        self.csc.libsize(cell)
+    }
+
+    fn nnz(&self, cell: usize) -> u32 {
+        self.csc.nnz(cell)
+    }
+}
+
+/// Analytic Pearson residuals of raw counts under a negative binomial model
+/// with a shared overdispersion `theta` (sctransform-like, without the
+/// per-gene regression).
+///
+/// `mu = libsize * gene_total / total`, `r = (x - mu) / sqrt(mu + mu^2 / theta)`,
+/// clipped to `[-clip, clip]`. Only stored (detected) entries are visited;
+/// the negative residuals of undetected genes are left implicit, as the
+/// downstream panel and entropy metrics work on sparse cell vectors.
+pub struct PearsonResidualAccessor {
+    counts: Box<dyn ExprAccessor>,
+    gene_fraction: Vec<f64>,
+    theta: f64,
+    clip: f64,
+}
+
+impl PearsonResidualAccessor {
+    pub fn new(counts: Box<dyn ExprAccessor>, theta: f32) -> Self {
+        let mut gene_total = vec![0f64; counts.n_genes()];
+        add_gene_totals(counts.as_ref(), &mut gene_total);
+        let n_cells = counts.n_cells();
+        Self::with_gene_totals(counts, gene_total, n_cells, theta)
+    }
+
+    /// Residuals for a subset of cells, with gene totals and the clip taken
+    /// from the `n_cells_total` cells of the full matrix.
+    pub fn with_gene_totals(
+        counts: Box<dyn ExprAccessor>,
+        gene_total: Vec<f64>,
+        n_cells_total: usize,
+        theta: f32,
+    ) -> Self {
+        let total: f64 = gene_total.iter().sum();
+        let gene_fraction = if total > 0.0 {
+            gene_total.iter().map(|t| t / total).collect()
+        } else {
+            gene_total
+        };
+        Self {
+            counts,
+            gene_fraction,
+            theta: theta as f64,
+            clip: (n_cells_total as f64).sqrt(),
         }
     }
 
-    fn libsize(&self, cell: usize) -> f32 {
-        self.libsizes[cell]
+    fn residual(&self, gene_id: u32, count: f32, lib: f64) -> f32 {
+        let mu = lib * self.gene_fraction[gene_id as usize];
+        if mu <= 0.0 {
+            return 0.0;
+        }
+        let r = (count as f64 - mu) / (mu + mu * mu / self.theta).sqrt();
+        r.clamp(-self.clip, self.clip) as f32
+    }
+}
+
+impl ExprAccessor for PearsonResidualAccessor {
+    fn n_cells(&self) -> usize {
+        self.counts.n_cells()
+    }
+
+    fn n_genes(&self) -> usize {
+        self.counts.n_genes()
+    }
+
+    fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        let lib = self.counts.libsize(cell);
+        self.counts.for_cell_counts(cell, &mut |gene_id, count| {
+            f(gene_id, self.residual(gene_id, count, lib));
+        });
+    }
+
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        self.counts.for_cell_counts(cell, f);
+    }
+
+    fn libsize(&self, cell: usize) -> f64 {
+        self.counts.libsize(cell)
     }
 
     fn nnz(&self, cell: usize) -> u32 {
-        self.nnz[cell]
+        self.counts.nnz(cell)
     }
 }
 
+fn add_gene_totals(counts: &dyn ExprAccessor, gene_total: &mut [f64]) {
+    for cell in 0..counts.n_cells() {
+        counts.for_cell_counts(cell, &mut |gene_id, count| {
+            gene_total[gene_id as usize] += count as f64;
+        });
+    }
+}
+
+/// `--emit-normalized-mtx`: the values Stage 3 sees, written as a 10x-style
+/// directory (`matrix.mtx`, `features.tsv`, `barcodes.tsv`) over the
+/// collapsed gene index. Cells are appended one accessor at a time, so a
+/// chunked run never holds more than one chunk.
+pub struct NormalizedExport {
+    mtx: MtxWriter,
+    next_cell: usize,
+}
+
+/// Subdirectory of the output directory written by [`NormalizedExport`].
+pub const NORMALIZED_EXPORT_DIR: &str = "normalized";
+
+impl NormalizedExport {
+    pub fn create(
+        dir: &Path,
+        gene_index: &GeneIndex,
+        barcodes: &[String],
+    ) -> Result<Self, InputError> {
+        std::fs::create_dir_all(dir)?;
+        let mut features = String::new();
+        for symbol in &gene_index.symbols_by_gene_id {
+            features.push_str(symbol);
+            features.push('\t');
+            features.push_str(symbol);
+            features.push_str("\tGene Expression\n");
+        }
+        std::fs::write(dir.join("features.tsv"), features)?;
+        let mut barcode_lines = barcodes.join("\n");
+        barcode_lines.push('\n');
+        std::fs::write(dir.join("barcodes.tsv"), barcode_lines)?;
+        Ok(Self {
+            mtx: MtxWriter::create(
+                &dir.join("matrix.mtx"),
+                gene_index.symbols_by_gene_id.len(),
+                barcodes.len(),
+            )?,
+            next_cell: 0,
+        })
+    }
+
+    /// Writes every cell of `accessor`, following the cells already written.
+    pub fn append(&mut self, accessor: &dyn ExprAccessor) -> Result<(), InputError> {
+        for cell in 0..accessor.n_cells() {
+            let col = self.next_cell + cell;
+            let mut result = Ok(());
+            accessor.for_cell(cell, &mut |gene_id, value| {
+                if result.is_ok() {
+                    result = self.mtx.push(gene_id as usize, col, value);
+                }
+            });
+            result?;
+        }
+        self.next_cell += accessor.n_cells();
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), InputError> {
+        self.mtx.finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizeMode {
+    /// `ln(1 + count / libsize * 10000)`.
+    LogCp10k,
+    /// See [`PearsonResidualAccessor`].
+    PearsonResiduals,
+}
+
+impl NormalizeMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "log" => Some(NormalizeMode::LogCp10k),
+            "pearson" => Some(NormalizeMode::PearsonResiduals),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NormalizeMode::LogCp10k => "log",
+            NormalizeMode::PearsonResiduals => "pearson",
+        }
+    }
+}
+
+pub const DEFAULT_PEARSON_THETA: f32 = 100.0;
+
 #[derive(Debug, Clone)]
 pub struct Stage2Params {
     pub normalize: bool,
+    /// Only consulted when `normalize` is set.
+    pub normalize_mode: NormalizeMode,
+    pub pearson_theta: f32,
     pub cache_normalized: bool,
     pub cache_path: Option<PathBuf>,
+    pub cache_codec: CacheCodec,
 }
 
 pub fn build_expr_accessor(
     bundle: &InputBundle,
     params: &Stage2Params,
 ) -> Result<Box<dyn ExprAccessor>, Stage2Error> {
+    if params.normalize && params.normalize_mode == NormalizeMode::PearsonResiduals {
+        // Residuals are recomputed from counts on every run; the normalized
+        // cache only holds log-normalized values.
+        let counts = build_expr_accessor(
+            bundle,
+            &Stage2Params {
+                normalize: false,
+                cache_normalized: false,
+                ..params.clone()
+            },
+        )?;
+        return Ok(Box::new(PearsonResidualAccessor::new(
+            counts,
+            params.pearson_theta,
+        )));
+    }
+
     let scale = 10_000f32;
     let normalize = params.normalize;
 
@@ -194,30 +424,23 @@ pub fn build_expr_accessor(
                 .clone()
                 .unwrap_or_else(|| cache_path_default(bundle.shared_bin_path.as_deref().unwrap()));
 
-            if let Some(cached) = read_normalized_cache(&cache_path, &meta)? {
+            if let Some(csc) = read_normalized_cache(&cache_path, &meta)? {
                 let accessor = CachedNormalizedAccessor {
-                    cols: cached.columns,
-                    libsizes: cached.libsizes,
-                    nnz: cached.nnz,
+                    csc,
                     n_genes,
+                    scale,
                 };
                 return Ok(Box::new(accessor));
             }
 
-            let (libsizes, nnz, normalized_cols) =
-                normalize_organelle(&bin, &bundle.gene_index, scale);
-            let data = CachedNormalizedData {
-                libsizes: libsizes.clone(),
-                nnz: nnz.clone(),
-                columns: normalized_cols.clone(),
-            };
-            write_normalized_cache(&cache_path, &meta, &data)?;
+            let csc =
+                NormalizedCsc::from_data(&normalize_organelle(&bin, &bundle.gene_index, scale));
+            write_normalized_cache(&cache_path, &meta, &csc, params.cache_codec)?;
 
             let accessor = CachedNormalizedAccessor {
-                cols: normalized_cols,
-                libsizes,
-                nnz,
+                csc,
                 n_genes,
+                scale,
             };
             return Ok(Box::new(accessor));
         }
@@ -235,98 +458,348 @@ pub fn build_expr_accessor(
         return Ok(Box::new(accessor));
     }
 
-    let csc = read_mtx_csc(
-        &bundle.mtx_path,
-        bundle.n_features_raw,
-        bundle.n_cells,
-        &bundle.gene_index,
-    )?;
-
     let n_genes = bundle.gene_index.symbols_by_gene_id.len();
 
     if normalize && params.cache_normalized {
+        // Hashing streams the inputs once, far cheaper than parsing the MTX,
+        // so the cache is checked before `read_mtx_csc`.
         let meta = build_cache_meta(bundle, scale, true)?;
         let cache_path = params
             .cache_path
             .clone()
             .unwrap_or_else(|| cache_path_default(&bundle.mtx_path));
 
-        if let Some(cached) = read_normalized_cache(&cache_path, &meta)? {
+        if let Some(csc) = read_normalized_cache(&cache_path, &meta)? {
             let accessor = CachedNormalizedAccessor {
-                cols: cached.columns,
-                libsizes: cached.libsizes,
-                nnz: cached.nnz,
+                csc,
                 n_genes,
+                scale,
             };
             return Ok(Box::new(accessor));
         }
 
-        let (libsizes, nnz, normalized_cols) = normalize_csc(&csc, scale);
-        let data = CachedNormalizedData {
-            libsizes: libsizes.clone(),
-            nnz: nnz.clone(),
-            columns: normalized_cols.clone(),
-        };
-        write_normalized_cache(&cache_path, &meta, &data)?;
+        let csc = NormalizedCsc::from_data(&normalize_csc(&read_bundle_counts(bundle)?, scale));
+        write_normalized_cache(&cache_path, &meta, &csc, params.cache_codec)?;
 
         let accessor = CachedNormalizedAccessor {
-            cols: normalized_cols,
-            libsizes,
-            nnz,
+            csc,
             n_genes,
+            scale,
         };
         return Ok(Box::new(accessor));
     }
 
-    let (libsizes, nnz) = compute_stats(&csc);
+    let csc = read_bundle_counts(bundle)?;
+    Ok(Box::new(raw_counts_accessor(
+        csc, n_genes, normalize, scale,
+    )))
+}
 
-    let accessor = RawCountsAccessor {
-        cols: csc.cols,
+/// Stage 2 over counts already in memory, following `params` except for the
+/// normalized cache, which needs an input file to key on.
+pub fn counts_accessor(
+    csc: CscMatrix,
+    n_genes: usize,
+    params: &Stage2Params,
+) -> Box<dyn ExprAccessor> {
+    if params.normalize && params.normalize_mode == NormalizeMode::PearsonResiduals {
+        let counts = raw_counts_accessor(csc, n_genes, false, 10_000.0);
+        return Box::new(PearsonResidualAccessor::new(
+            Box::new(counts),
+            params.pearson_theta,
+        ));
+    }
+    Box::new(raw_counts_accessor(
+        csc,
+        n_genes,
+        params.normalize,
+        10_000.0,
+    ))
+}
+
+fn raw_counts_accessor(
+    csc: CscMatrix,
+    n_genes: usize,
+    normalize: bool,
+    scale: f32,
+) -> RawCountsAccessor {
+    let (libsizes, nnz) = compute_stats(&csc);
+    RawCountsAccessor {
+        csc,
         libsizes,
         nnz,
         n_genes,
         normalize,
         scale,
+    }
+}
+
+/// Most chunk files `--chunk-cells` keeps open while spilling an MTX.
+pub const MAX_SPILL_CHUNKS: usize = 512;
+
+/// Cells per spilled chunk: `requested`, raised as far as needed to split
+/// `n_cells` into at most [`MAX_SPILL_CHUNKS`] chunks.
+pub fn spill_chunk_cells(n_cells: usize, requested: usize) -> usize {
+    requested.max(n_cells.div_ceil(MAX_SPILL_CHUNKS))
+}
+
+/// Expression served in contiguous chunks of cells (`--chunk-cells`).
+///
+/// A 10x MTX is streamed once into one spill file per chunk, and each chunk
+/// is parsed back only when requested, so at most one chunk of counts is in
+/// memory. An organelle bin is already mapped from disk, so its chunks are
+/// views of the full accessor.
+pub enum ExprChunks {
+    Mapped {
+        accessor: Box<dyn ExprAccessor>,
+        chunk_cells: usize,
+    },
+    Spilled(MtxSpill),
+}
+
+pub struct MtxSpill {
+    dir: PathBuf,
+    chunk_paths: Vec<PathBuf>,
+    chunk_cells: usize,
+    n_cells: usize,
+    n_features_raw: usize,
+    n_genes: usize,
+    params: Stage2Params,
+    /// Per-gene count totals over all cells, for Pearson residuals.
+    gene_total: Option<Vec<f64>>,
+    /// Per-cell control counts summed while spilling.
+    control_counts: Option<Vec<f64>>,
+}
+
+impl Drop for MtxSpill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl MtxSpill {
+    fn load(&self, chunk: usize) -> Result<Box<dyn ExprAccessor>, Stage2Error> {
+        let start = chunk * self.chunk_cells;
+        let len = self.chunk_cells.min(self.n_cells - start);
+        let csc = read_mtx_chunk(&self.chunk_paths[chunk], self.n_features_raw, len)?;
+        match &self.gene_total {
+            Some(gene_total) => Ok(Box::new(PearsonResidualAccessor::with_gene_totals(
+                Box::new(raw_counts_accessor(csc, self.n_genes, false, 10_000.0)),
+                gene_total.clone(),
+                self.n_cells,
+                self.params.pearson_theta,
+            ))),
+            None => Ok(Box::new(raw_counts_accessor(
+                csc,
+                self.n_genes,
+                self.params.normalize,
+                10_000.0,
+            ))),
+        }
+    }
+}
+
+impl ExprChunks {
+    pub fn n_chunks(&self) -> usize {
+        match self {
+            ExprChunks::Mapped {
+                accessor,
+                chunk_cells,
+            } => accessor.n_cells().div_ceil(*chunk_cells),
+            ExprChunks::Spilled(spill) => spill.chunk_paths.len(),
+        }
+    }
+
+    /// Per-cell counts of the controls pending in the bundle, when the
+    /// chunks were spilled from an MTX.
+    pub fn control_counts(&mut self) -> Option<Vec<f64>> {
+        match self {
+            ExprChunks::Mapped { .. } => None,
+            ExprChunks::Spilled(spill) => spill.control_counts.take(),
+        }
+    }
+
+    /// Accessor over chunk `chunk`, with cells numbered from zero.
+    pub fn chunk(&self, chunk: usize) -> Result<Box<dyn ExprAccessor + '_>, Stage2Error> {
+        match self {
+            ExprChunks::Mapped {
+                accessor,
+                chunk_cells,
+            } => {
+                let start = chunk * chunk_cells;
+                Ok(Box::new(CellRangeAccessor {
+                    inner: accessor.as_ref(),
+                    start,
+                    len: (*chunk_cells).min(accessor.n_cells() - start),
+                }))
+            }
+            ExprChunks::Spilled(spill) => spill.load(chunk),
+        }
+    }
+}
+
+/// Cells `start..start + len` of another accessor.
+struct CellRangeAccessor<'a> {
+    inner: &'a dyn ExprAccessor,
+    start: usize,
+    len: usize,
+}
+
+impl ExprAccessor for CellRangeAccessor<'_> {
+    fn n_cells(&self) -> usize {
+        self.len
+    }
+
+    fn n_genes(&self) -> usize {
+        self.inner.n_genes()
+    }
+
+    fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        self.inner.for_cell(self.start + cell, f);
+    }
+
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        self.inner.for_cell_counts(self.start + cell, f);
+    }
+
+    fn libsize(&self, cell: usize) -> f64 {
+        self.inner.libsize(self.start + cell)
+    }
+
+    fn nnz(&self, cell: usize) -> u32 {
+        self.inner.nnz(self.start + cell)
+    }
+}
+
+/// Splits the bundle's expression into chunks of `chunk_cells` cells,
+/// spilling an MTX under `spill_dir` (removed when the chunks are dropped).
+pub fn build_expr_chunks(
+    bundle: &InputBundle,
+    params: &Stage2Params,
+    chunk_cells: usize,
+    spill_dir: &Path,
+) -> Result<ExprChunks, Stage2Error> {
+    if bundle.source == InputSourceKind::OrganelleBin {
+        return Ok(ExprChunks::Mapped {
+            accessor: build_expr_accessor(bundle, params)?,
+            chunk_cells,
+        });
+    }
+
+    let requested = chunk_cells;
+    let chunk_cells = spill_chunk_cells(bundle.n_cells, requested);
+    if chunk_cells > requested {
+        crate::warn!(
+            "--chunk-cells {requested} would split {} cells into more than {MAX_SPILL_CHUNKS} spill files; using chunks of {chunk_cells} cells",
+            bundle.n_cells
+        );
+    }
+    let n_chunks = bundle.n_cells.div_ceil(chunk_cells);
+    if spill_dir.exists() {
+        std::fs::remove_dir_all(spill_dir).map_err(InputError::Io)?;
+    }
+    std::fs::create_dir_all(spill_dir).map_err(InputError::Io)?;
+    let mut spill = MtxSpill {
+        dir: spill_dir.to_path_buf(),
+        chunk_paths: (0..n_chunks)
+            .map(|i| spill_dir.join(format!("chunk_{i:05}.bin")))
+            .collect(),
+        chunk_cells,
+        n_cells: bundle.n_cells,
+        n_features_raw: bundle.n_features_raw,
+        n_genes: bundle.gene_index.symbols_by_gene_id.len(),
+        params: params.clone(),
+        gene_total: None,
+        control_counts: None,
     };
-    Ok(Box::new(accessor))
+    spill.control_counts = spill_mtx_chunks(bundle, chunk_cells, &spill.chunk_paths)?;
+
+    if params.normalize && params.normalize_mode == NormalizeMode::PearsonResiduals {
+        let mut gene_total = vec![0f64; spill.n_genes];
+        for chunk in 0..n_chunks {
+            add_gene_totals(spill.load(chunk)?.as_ref(), &mut gene_total);
+        }
+        spill.gene_total = Some(gene_total);
+    }
+    Ok(ExprChunks::Spilled(spill))
 }
 
-fn compute_stats(csc: &CscMatrix) -> (Vec<f32>, Vec<u32>) {
+/// Raw counts of every cell, mapped and summed per gene as
+/// [`build_expr_accessor`] reads them.
+pub fn read_bundle_counts(bundle: &InputBundle) -> Result<CscMatrix, InputError> {
+    read_mtx_csc(
+        &bundle.mtx_path,
+        bundle.n_features_raw,
+        bundle.n_cells,
+        &bundle.gene_index,
+        bundle.transposed,
+        bundle.strict_input,
+        bundle.parse_threads,
+    )
+}
+
+/// Raw counts of `cells` (input indices), mapped and summed per gene as
+/// [`build_expr_accessor`] reads them, for `--max-cells`.
+pub fn read_cell_counts(bundle: &InputBundle, cells: &[usize]) -> Result<CscMatrix, InputError> {
+    let bin = match (&bundle.organelle, bundle.source) {
+        (Some(bin), InputSourceKind::OrganelleBin) => bin,
+        _ => return Ok(read_bundle_counts(bundle)?.select_columns(cells)),
+    };
+    let per_col = cells
+        .iter()
+        .map(|&cell| {
+            let (rows, counts) = bin.csc.column(cell);
+            let mut column = BTreeMap::new();
+            for (&feature, &value) in rows.iter().zip(counts) {
+                if value == 0 {
+                    continue;
+                }
+                if let Some(gene_id) = bundle.gene_index.gene_id_by_feature[feature as usize] {
+                    *column.entry(gene_id as u32).or_insert(0i64) += value as i64;
+                }
+            }
+            column
+        })
+        .collect();
+    Ok(collect_columns(bundle.n_features_raw, per_col))
+}
+
+fn compute_stats(csc: &CscMatrix) -> (Vec<f64>, Vec<u32>) {
     let mut libsizes = Vec::with_capacity(csc.n_cols);
     let mut nnz = Vec::with_capacity(csc.n_cols);
-    for col in &csc.cols {
+    for cell in 0..csc.n_cols {
+        let (_, counts) = csc.column(cell);
         let mut sum = 0f64;
-        for &(_, v) in col {
+        for &v in counts {
             sum += v as f64;
         }
-        libsizes.push(sum as f32);
-        nnz.push(col.len() as u32);
+        libsizes.push(sum);
+        nnz.push(counts.len() as u32);
     }
     (libsizes, nnz)
 }
 
-fn normalize_csc(csc: &CscMatrix, scale: f32) -> (Vec<f32>, Vec<u32>, Vec<Vec<(u32, f32)>>) {
+fn normalize_csc(csc: &CscMatrix, scale: f32) -> CachedNormalizedData {
     let mut libsizes = Vec::with_capacity(csc.n_cols);
-    let mut nnz = Vec::with_capacity(csc.n_cols);
     let mut out_cols: Vec<Vec<(u32, f32)>> = Vec::with_capacity(csc.n_cols);
 
-    for col in &csc.cols {
+    for cell in 0..csc.n_cols {
+        let (gene_ids, counts) = csc.column(cell);
         let mut sum = 0f64;
-        for &(_, v) in col {
+        for &v in counts {
             sum += v as f64;
         }
         let lib = sum;
-        libsizes.push(lib as f32);
-        nnz.push(col.len() as u32);
+        libsizes.push(lib);
 
-        let mut out_col = Vec::with_capacity(col.len());
+        let mut out_col = Vec::with_capacity(counts.len());
         if lib == 0.0 {
-            for &(gene, _) in col {
+            for &gene in gene_ids {
                 out_col.push((gene, 0.0));
             }
         } else {
             let denom = lib;
-            for &(gene, v) in col {
+            for (&gene, &v) in gene_ids.iter().zip(counts) {
                 let scaled = (v as f64) / denom * (scale as f64);
                 let val = scaled.ln_1p() as f32;
                 out_col.push((gene, val));
@@ -335,7 +808,10 @@ fn normalize_csc(csc: &CscMatrix, scale: f32) -> (Vec<f32>, Vec<u32>, Vec<Vec<(u
         out_cols.push(out_col);
     }
 
-    (libsizes, nnz, out_cols)
+    CachedNormalizedData {
+        libsizes,
+        columns: out_cols,
+    }
 }
 
 fn build_cache_meta(
@@ -385,23 +861,21 @@ fn build_cache_meta_organelle(
     })
 }
 
-fn compute_stats_organelle(bin: &OrganelleBin, gene_index: &GeneIndex) -> (Vec<f32>, Vec<u32>) {
+fn compute_stats_organelle(bin: &OrganelleBin, gene_index: &GeneIndex) -> (Vec<f64>, Vec<u32>) {
     let n_cells = bin.csc.n_cells;
-    let mut libsizes = vec![0f32; n_cells];
+    let mut libsizes = vec![0f64; n_cells];
     let mut nnz = vec![0u32; n_cells];
     for cell in 0..n_cells {
-        let start = bin.csc.col_ptr[cell] as usize;
-        let end = bin.csc.col_ptr[cell + 1] as usize;
+        let (rows, counts) = bin.csc.column(cell);
         let mut sum = 0f64;
         let mut count = 0u32;
-        for idx in start..end {
-            let feature = bin.csc.row_idx[idx] as usize;
-            if gene_index.gene_id_by_feature[feature].is_some() {
-                sum += bin.csc.values[idx] as f64;
+        for (&feature, &value) in rows.iter().zip(counts) {
+            if gene_index.gene_id_by_feature[feature as usize].is_some() {
+                sum += value as f64;
                 count += 1;
             }
         }
-        libsizes[cell] = sum as f32;
+        libsizes[cell] = sum;
         nnz[cell] = count;
     }
     (libsizes, nnz)
@@ -411,30 +885,26 @@ fn normalize_organelle(
     bin: &OrganelleBin,
     gene_index: &GeneIndex,
     scale: f32,
-) -> (Vec<f32>, Vec<u32>, Vec<Vec<(u32, f32)>>) {
+) -> CachedNormalizedData {
     let n_cells = bin.csc.n_cells;
-    let mut libsizes = vec![0f32; n_cells];
-    let mut nnz = vec![0u32; n_cells];
+    let mut libsizes = Vec::with_capacity(n_cells);
     let mut out_cols = Vec::with_capacity(n_cells);
 
     for cell in 0..n_cells {
-        let start = bin.csc.col_ptr[cell] as usize;
-        let end = bin.csc.col_ptr[cell + 1] as usize;
+        let (rows, counts) = bin.csc.column(cell);
         let mut sum = 0f64;
-        for idx in start..end {
-            let feature = bin.csc.row_idx[idx] as usize;
-            if gene_index.gene_id_by_feature[feature].is_some() {
-                sum += bin.csc.values[idx] as f64;
+        for (&feature, &value) in rows.iter().zip(counts) {
+            if gene_index.gene_id_by_feature[feature as usize].is_some() {
+                sum += value as f64;
             }
         }
         let lib = sum;
-        libsizes[cell] = lib as f32;
+        libsizes.push(lib);
 
         let mut out_col = Vec::new();
-        for idx in start..end {
-            let feature = bin.csc.row_idx[idx] as usize;
-            if let Some(gene_id) = gene_index.gene_id_by_feature[feature] {
-                let count = bin.csc.values[idx] as f64;
+        for (&feature, &count) in rows.iter().zip(counts) {
+            if let Some(gene_id) = gene_index.gene_id_by_feature[feature as usize] {
+                let count = count as f64;
                 let val = if lib == 0.0 {
                     0.0
                 } else {
@@ -444,11 +914,13 @@ fn normalize_organelle(
                 out_col.push((gene_id as u32, val));
             }
         }
-        nnz[cell] = out_col.len() as u32;
         out_cols.push(out_col);
     }
 
-    (libsizes, nnz, out_cols)
+    CachedNormalizedData {
+        libsizes,
+        columns: out_cols,
+    }
 }
 
 fn hash_gene_index(index: &GeneIndex) -> u64 {
@@ -1,38 +1,116 @@
 use crate::input::{InputBundle, InputError};
+use crate::panels::defs::PanelDef;
 use crate::panels::loader::load_panels;
-use crate::panels::{PanelAudit, PanelScores, PanelSet};
+use crate::panels::{Panel, PanelAudit, PanelScores, PanelSet};
 use crate::pipeline::stage2_normalize::ExprAccessor;
 
+/// Genes kept per cell by `--explain-panel`.
+pub const EXPLAIN_TOP_GENES: usize = 5;
+
 #[derive(Debug)]
 pub struct Stage3Output {
     pub panels: PanelSet,
     pub scores: PanelScores,
     pub audits: Vec<PanelAudit>,
+    /// Gene contributions to the `--explain-panel` panel; `None` when not
+    /// requested or the panel is not in `panels`.
+    pub contributions: Option<PanelContributions>,
+}
+
+/// The genes behind one panel's sum, per cell (`--explain-panel`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PanelContributions {
+    pub panel_id: String,
+    /// Up to [`EXPLAIN_TOP_GENES`] `(gene id, contribution)` pairs per cell,
+    /// largest first, ties in panel order. A contribution is the normalized
+    /// value times the gene weight, as added to `panel_sum`.
+    pub top_genes: Vec<Vec<(u32, f32)>>,
+}
+
+impl PanelContributions {
+    pub fn append(&mut self, mut other: PanelContributions) {
+        self.top_genes.append(&mut other.top_genes);
+    }
 }
 
 pub fn run_stage3(
     bundle: &InputBundle,
     accessor: &dyn ExprAccessor,
+    panel_defs: &[PanelDef],
+    max_rule_genes: usize,
+    explain_panel: Option<&str>,
 ) -> Result<Stage3Output, InputError> {
-    let (panel_set, audits) = load_panels(bundle.species, &bundle.gene_index);
+    let (panel_set, audits) = load_panels(
+        bundle.species,
+        &bundle.gene_index,
+        panel_defs,
+        max_rule_genes,
+    )?;
     let scores = score_panels(accessor, &panel_set);
+    let contributions =
+        find_panel(&panel_set, explain_panel).map(|panel| explain_panel_genes(accessor, panel));
     Ok(Stage3Output {
         panels: panel_set,
         scores,
         audits,
+        contributions,
     })
 }
 
+pub fn find_panel<'a>(panel_set: &'a PanelSet, id: Option<&str>) -> Option<&'a Panel> {
+    let id = id?;
+    panel_set.panels.iter().find(|panel| panel.id == id)
+}
+
+/// Top contributing genes of `panel` in every cell of `accessor`. Genes with
+/// a zero value are left out.
+pub fn explain_panel_genes(accessor: &dyn ExprAccessor, panel: &Panel) -> PanelContributions {
+    let mut gene_pos: Vec<Option<usize>> = vec![None; accessor.n_genes()];
+    for (pos, &gene_id) in panel.genes.iter().enumerate() {
+        if let Some(slot) = gene_pos.get_mut(gene_id as usize) {
+            *slot = Some(pos);
+        }
+    }
+
+    let mut top_genes = Vec::with_capacity(accessor.n_cells());
+    let mut terms = Vec::new();
+    for cell in 0..accessor.n_cells() {
+        terms.clear();
+        accessor.for_cell(cell, &mut |gene_id, value| {
+            if value == 0.0 {
+                return;
+            }
+            if let Some(pos) = gene_pos[gene_id as usize] {
+                terms.push((pos, value * panel.weight(pos)));
+            }
+        });
+        terms.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        top_genes.push(
+            terms
+                .iter()
+                .take(EXPLAIN_TOP_GENES)
+                .map(|&(pos, value)| (panel.genes[pos], value))
+                .collect(),
+        );
+    }
+
+    PanelContributions {
+        panel_id: panel.id.clone(),
+        top_genes,
+    }
+}
+
 pub fn score_panels(accessor: &dyn ExprAccessor, panel_set: &PanelSet) -> PanelScores {
     let n_cells = accessor.n_cells();
     let n_panels = panel_set.panels.len();
 
-    let mut gene_to_panels: Vec<Vec<usize>> = vec![Vec::new(); accessor.n_genes()];
+    // (panel index, gene weight) per gene; weights only scale `panel_sum`.
+    let mut gene_to_panels: Vec<Vec<(usize, f64)>> = vec![Vec::new(); accessor.n_genes()];
     for (panel_idx, panel) in panel_set.panels.iter().enumerate() {
-        for &gene_id in &panel.genes {
+        for (gene_pos, &gene_id) in panel.genes.iter().enumerate() {
             let idx = gene_id as usize;
             if idx < gene_to_panels.len() {
-                gene_to_panels[idx].push(panel_idx);
+                gene_to_panels[idx].push((panel_idx, panel.weight(gene_pos) as f64));
             }
         }
     }
@@ -40,6 +118,8 @@ pub fn score_panels(accessor: &dyn ExprAccessor, panel_set: &PanelSet) -> PanelS
     let panel_sizes: Vec<usize> = panel_set.panels.iter().map(|p| p.genes.len()).collect();
 
     let mut panel_sum = Vec::with_capacity(n_cells);
+    let mut panel_mean = Vec::with_capacity(n_cells);
+    let mut panel_raw_sum = Vec::with_capacity(n_cells);
     let mut panel_detected = Vec::with_capacity(n_cells);
     let mut panel_coverage = Vec::with_capacity(n_cells);
 
@@ -55,33 +135,50 @@ pub fn score_panels(accessor: &dyn ExprAccessor, panel_set: &PanelSet) -> PanelS
             if panels.is_empty() {
                 return;
             }
-            for &p in panels {
-                sums[p] += value as f64;
+            for &(p, weight) in panels {
+                sums[p] += value as f64 * weight;
                 if value > 0.0 {
                     detected[p] += 1;
                 }
             }
         });
 
+        let mut raw_sums = vec![0f64; n_panels];
+        accessor.for_cell_counts(cell, &mut |gene_id, count| {
+            if count == 0.0 {
+                return;
+            }
+            for &(p, _) in &gene_to_panels[gene_id as usize] {
+                raw_sums[p] += count as f64;
+            }
+        });
+
         let mut sums_f32 = Vec::with_capacity(n_panels);
+        let mut means = Vec::with_capacity(n_panels);
         let mut coverage = Vec::with_capacity(n_panels);
         for p in 0..n_panels {
             sums_f32.push(sums[p] as f32);
             let size = panel_sizes[p];
             if size == 0 {
+                means.push(0.0);
                 coverage.push(0.0);
             } else {
+                means.push((sums[p] / size as f64) as f32);
                 coverage.push(detected[p] as f32 / size as f32);
             }
         }
 
         panel_sum.push(sums_f32);
+        panel_mean.push(means);
+        panel_raw_sum.push(raw_sums.iter().map(|&v| v as f32).collect());
         panel_detected.push(detected);
         panel_coverage.push(coverage);
     }
 
     PanelScores {
         panel_sum,
+        panel_mean,
+        panel_raw_sum,
         panel_detected,
         panel_coverage,
     }
@@ -1,14 +1,19 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
 use crate::input::{GeneIndex, Species};
 use crate::metrics::genome_stability::scores::{
-    GenomePanelAudit, GenomeStabilityCellScores, RobustNormStat, compute_genome_stability,
+    GenomeCores, GenomePanelAudit, GenomeStabilityCellScores, RobustNormStat, compute_genome_cores,
+    finish_genome_stability,
 };
 use crate::model::axes::{Axes, AxisDrivers, AxisFlags, clip01};
 use crate::model::ddr::{DdrMetrics, compute_ddr_metrics};
-use crate::model::thresholds::{AxisActivationMode, ThresholdProfile};
+use crate::model::thresholds::{AxisActivationMode, RelativeWithin, ThresholdProfile};
 use crate::panels::defs::PanelGroup;
 use crate::panels::{PanelScores, PanelSet};
 use crate::pipeline::stage2_normalize::ExprAccessor;
-use crate::simd;
+use crate::report::{QuantileMethod, quantile_sorted};
+use crate::simd::{self, SumMode};
 
 #[derive(Debug)]
 pub struct Stage4Output {
@@ -20,18 +25,129 @@ pub struct Stage4Output {
     pub genome_stability_norm: Vec<RobustNormStat>,
     pub genome_stability_panel_version: &'static str,
     pub genome_stability_panel_audits: Vec<GenomePanelAudit>,
+    pub cea_ribo_slope: Option<f32>,
+    /// Per-panel upper clamps applied under `winsorize_panels`, in panel
+    /// order.
+    pub panel_caps: Option<Vec<f32>>,
+}
+
+/// Optional per-cell inputs that adjust axis activation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stage4Covariates<'a> {
+    pub ribo_fraction: Option<&'a [f32]>,
+    pub sample_labels: Option<&'a [String]>,
+}
+
+/// Gene-level quantities of one cell, read from its expression vector.
+#[derive(Debug, Clone, Copy)]
+pub struct CellExpression {
+    pub expressed_genes: u32,
+    pub frac_norm: f32,
+    pub gene_entropy: f32,
+    pub gene_entropy_norm: f32,
+    pub min_nonzero_expr: f32,
+}
+
+/// Everything Stage 4 reads from the expression accessor. Cells are
+/// independent, so this can be scanned chunk by chunk and appended.
+#[derive(Debug, Clone, Default)]
+pub struct Stage4Cells {
+    pub expression: Vec<CellExpression>,
+    pub genome_cores: GenomeCores,
+}
+
+impl Stage4Cells {
+    pub fn scan(
+        accessor: &dyn ExprAccessor,
+        gene_index: &GeneIndex,
+        species: Species,
+        thresholds: &ThresholdProfile,
+    ) -> Self {
+        let n_cells = accessor.n_cells();
+        let n_genes_mappable = accessor.n_genes() as f32;
+        let mut value_buf: Vec<f32> = Vec::new();
+        let mut expression = Vec::with_capacity(n_cells);
+        for cell in 0..n_cells {
+            value_buf.clear();
+            let nnz = accessor.nnz(cell) as usize;
+            if value_buf.capacity() < nnz {
+                value_buf.reserve(nnz - value_buf.capacity());
+            }
+
+            let mut expressed_genes = 0u32;
+            accessor.for_cell(cell, &mut |_gene_id, value| {
+                if value > 0.0 {
+                    value_buf.push(value);
+                }
+                if value > thresholds.expr_min {
+                    expressed_genes += 1;
+                }
+            });
+
+            let frac = if n_genes_mappable > 0.0 {
+                expressed_genes as f32 / n_genes_mappable
+            } else {
+                0.0
+            };
+            let frac_norm = rescale01(
+                frac,
+                thresholds.frac_rescale_min,
+                thresholds.frac_rescale_max,
+            );
+
+            let (gene_entropy, gene_entropy_norm) =
+                entropy_norm_from_values(&value_buf, thresholds.sum_mode);
+            expression.push(CellExpression {
+                expressed_genes,
+                frac_norm,
+                gene_entropy,
+                gene_entropy_norm,
+                min_nonzero_expr: simd::min_f32(&value_buf),
+            });
+        }
+        Self {
+            expression,
+            genome_cores: compute_genome_cores(accessor, gene_index, species),
+        }
+    }
+
+    pub fn append(&mut self, mut other: Stage4Cells) {
+        self.expression.append(&mut other.expression);
+        self.genome_cores.append(other.genome_cores);
+    }
 }
 
+/// Cells per unit of work when Stage 4 runs on several threads.
+const STAGE4_CHUNK_CELLS: usize = 1024;
+
+/// With `threads > 1` the per-cell axes are scored on that many threads, in
+/// fixed chunks of [`STAGE4_CHUNK_CELLS`] cells; each cell depends only on
+/// its own inputs and on anchors computed beforehand, so the output is
+/// bitwise the serial one.
 pub fn run_stage4(
-    accessor: &dyn ExprAccessor,
+    cells: Stage4Cells,
     gene_index: &GeneIndex,
     species: Species,
     panel_set: &PanelSet,
     panel_scores: &PanelScores,
     thresholds: &ThresholdProfile,
+    covariates: &Stage4Covariates,
+    threads: usize,
 ) -> Stage4Output {
-    let n_cells = accessor.n_cells();
-    let n_panels = panel_set.panels.len();
+    let n_cells = cells.expression.len();
+    let winsorized;
+    let (panel_values, panel_caps) = match thresholds.winsorize_panels {
+        Some(quantile) => {
+            let (values, caps) = winsorize_panel_values(
+                panel_scores.values(thresholds.panel_score),
+                quantile,
+                thresholds.quantile_method,
+            );
+            winsorized = values;
+            (winsorized.as_slice(), Some(caps))
+        }
+        None => (panel_scores.values(thresholds.panel_score), None),
+    };
 
     let mut program_panels = Vec::new();
     let mut tf_panels = Vec::new();
@@ -67,9 +183,6 @@ pub fn run_stage4(
     let mut drivers = vec![AxisDrivers::default(); n_cells];
     let mut flags = vec![AxisFlags::default(); n_cells];
 
-    let mut value_buf: Vec<f32> = Vec::new();
-    let mut program_buf: Vec<f32> = Vec::with_capacity(program_panels.len());
-    let mut tf_buf: Vec<f32> = Vec::with_capacity(tf_panels.len() + chromatin_panels.len());
     let mut iaa_raw = vec![0.0f32; n_cells];
     let mut dfa_raw = vec![0.0f32; n_cells];
     let mut cea_raw = vec![0.0f32; n_cells];
@@ -96,40 +209,57 @@ pub fn run_stage4(
 
     for cell in 0..n_cells {
         if let Some(p) = iaa_panel {
-            iaa_raw[cell] = panel_scores.panel_sum[cell][p];
+            iaa_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = dfa_panel {
-            dfa_raw[cell] = panel_scores.panel_sum[cell][p];
+            dfa_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = cea_panel {
-            cea_raw[cell] = panel_scores.panel_sum[cell][p];
+            cea_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = replication_stress_panel {
-            replication_stress_raw[cell] = panel_scores.panel_sum[cell][p];
+            replication_stress_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = checkpoint_activation_panel {
-            checkpoint_activation_raw[cell] = panel_scores.panel_sum[cell][p];
+            checkpoint_activation_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = replication_fork_stability_panel {
-            replication_fork_stability_raw[cell] = panel_scores.panel_sum[cell][p];
+            replication_fork_stability_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = dna_repair_hr_panel {
-            hr_raw[cell] = panel_scores.panel_sum[cell][p];
+            hr_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = dna_repair_nhej_panel {
-            nhej_raw[cell] = panel_scores.panel_sum[cell][p];
+            nhej_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = chromatin_compaction_panel {
-            chromatin_compaction_raw[cell] = panel_scores.panel_sum[cell][p];
+            chromatin_compaction_raw[cell] = panel_values[cell][p];
         }
         if let Some(p) = chromatin_open_state_panel {
-            chromatin_open_raw[cell] = panel_scores.panel_sum[cell][p];
+            chromatin_open_raw[cell] = panel_values[cell][p];
         }
     }
 
-    let iaa_rel = compute_relative_scores(&iaa_raw, thresholds);
-    let dfa_rel = compute_relative_scores(&dfa_raw, thresholds);
-    let cea_rel = compute_relative_scores(&cea_raw, thresholds);
+    let cea_ribo_slope = match covariates.ribo_fraction {
+        Some(ribo) if thresholds.cea_ribo_adjust => {
+            let slope = ribo_regression_slope(&cea_raw, ribo);
+            for (raw, &r) in cea_raw.iter_mut().zip(ribo) {
+                *raw = (*raw - slope * r).max(0.0);
+            }
+            Some(slope)
+        }
+        _ => None,
+    };
+
+    let groups = match (thresholds.relative_within, covariates.sample_labels) {
+        (RelativeWithin::Sample, Some(labels)) => {
+            relative_groups(labels, thresholds.relative_min_group_cells)
+        }
+        _ => Vec::new(),
+    };
+    let iaa_rel = compute_relative_scores_grouped(&iaa_raw, thresholds, &groups);
+    let dfa_rel = compute_relative_scores_grouped(&dfa_raw, thresholds, &groups);
+    let cea_rel = compute_relative_scores_grouped(&cea_raw, thresholds, &groups);
     let replication_stress_norm = compute_relative_scores(&replication_stress_raw, thresholds);
     let checkpoint_activation_norm =
         compute_relative_scores(&checkpoint_activation_raw, thresholds);
@@ -140,105 +270,43 @@ pub fn run_stage4(
     let chromatin_compaction_norm = compute_relative_scores(&chromatin_compaction_raw, thresholds);
     let chromatin_open_norm = compute_relative_scores(&chromatin_open_raw, thresholds);
 
-    for cell in 0..n_cells {
-        value_buf.clear();
-        let nnz = accessor.nnz(cell) as usize;
-        if value_buf.capacity() < nnz {
-            value_buf.reserve(nnz - value_buf.capacity());
+    let scorer = CellAxisScorer {
+        expression: &cells.expression,
+        panel_values,
+        program_panels: &program_panels,
+        tf_panels: &tf_panels,
+        chromatin_panels: &chromatin_panels,
+        stress_panels: &stress_panels,
+        dev_panels: &dev_panels,
+        thresholds,
+        raw: [&iaa_raw, &dfa_raw, &cea_raw],
+        rel: [&iaa_rel, &dfa_rel, &cea_rel],
+    };
+    let chunks = AxisSlices::new(&mut axes, &mut drivers, &mut flags).chunks(STAGE4_CHUNK_CELLS);
+    if threads <= 1 || chunks.len() <= 1 {
+        for (start, chunk) in chunks {
+            scorer.score_chunk(start, chunk);
         }
-
-        let mut expressed_genes = 0u32;
-        accessor.for_cell(cell, &mut |_gene_id, value| {
-            if value > 0.0 {
-                value_buf.push(value);
-            }
-            if value > thresholds.expr_min {
-                expressed_genes += 1;
+    } else {
+        // Chunks hold disjoint cells, so which thread takes one does not
+        // change what is written.
+        let queue = Mutex::new(chunks.into_iter());
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue.lock().expect("Stage 4 queue poisoned").next();
+                        let Some((start, chunk)) = next else {
+                            break;
+                        };
+                        scorer.score_chunk(start, chunk);
+                    }
+                });
             }
         });
+    }
 
-        let n_genes_mappable = accessor.n_genes() as f32;
-        let frac = if n_genes_mappable > 0.0 {
-            expressed_genes as f32 / n_genes_mappable
-        } else {
-            0.0
-        };
-        let frac_norm = rescale01(
-            frac,
-            thresholds.frac_rescale_min,
-            thresholds.frac_rescale_max,
-        );
-
-        let (gene_entropy, gene_entropy_norm) = entropy_norm_from_values(&value_buf);
-
-        program_buf.clear();
-        for &idx in &program_panels {
-            program_buf.push(panel_scores.panel_sum[cell][idx]);
-        }
-        let (panel_entropy_norm, panel_entropy) = panel_entropy_program(&program_buf);
-
-        let tbi = thresholds.tbi_w1 * frac_norm
-            + thresholds.tbi_w2 * gene_entropy_norm
-            + thresholds.tbi_w3 * panel_entropy_norm;
-
-        tf_buf.clear();
-        for &idx in tf_panels.iter().chain(chromatin_panels.iter()) {
-            tf_buf.push(panel_scores.panel_sum[cell][idx]);
-        }
-        let (rci, tf_entropy, low_tf) = rci_score(&tf_buf, thresholds.tf_min_sum);
-
-        let (pds, max_share) = pds_score(&program_buf, thresholds.program_min_sum);
-
-        let trs = clip01(
-            thresholds.trs_a * (1.0 - tbi)
-                + thresholds.trs_b * (1.0 - rci)
-                + thresholds.trs_c * pds,
-        );
-
-        let (nsai, stress_ratio, dev_ratio) = nsai_score(
-            cell,
-            panel_scores,
-            &stress_panels,
-            &dev_panels,
-            &program_panels,
-            thresholds.program_min_sum,
-            thresholds.stress_boost,
-        );
-
-        let iaa = activate_axis(iaa_raw[cell], iaa_rel[cell], thresholds);
-        let dfa = activate_axis(dfa_raw[cell], dfa_rel[cell], thresholds);
-        let cea = activate_axis(cea_raw[cell], cea_rel[cell], thresholds);
-
-        axes.tbi[cell] = clip01(tbi);
-        axes.rci[cell] = clip01(rci);
-        axes.pds[cell] = clip01(pds);
-        axes.trs[cell] = trs;
-        axes.nsai[cell] = nsai;
-        axes.iaa[cell] = iaa;
-        axes.dfa[cell] = dfa;
-        axes.cea[cell] = cea;
-
-        drivers[cell] = AxisDrivers {
-            expressed_genes,
-            gene_entropy,
-            panel_entropy,
-            max_program_share: max_share,
-            tf_entropy,
-            stress_ratio,
-            dev_ratio,
-            iaa_raw: iaa_raw[cell],
-            dfa_raw: dfa_raw[cell],
-            cea_raw: cea_raw[cell],
-            axis_variance: 0.0,
-        };
-        flags[cell] = AxisFlags {
-            low_tf_signal: low_tf,
-        };
-
-        let _ = n_panels; // silence unused variable warning if panels unused in build.
-    }
-
-    let ddr = compute_ddr_metrics(
+    let mut ddr = compute_ddr_metrics(
         &replication_stress_norm,
         &checkpoint_activation_norm,
         &replication_fork_stability_norm,
@@ -248,7 +316,26 @@ pub fn run_stage4(
         &chromatin_open_norm,
         &axes.tbi,
     );
-    let genome_stability = compute_genome_stability(accessor, gene_index, species);
+    // A DDR axis missing one of its panels (`--disable-panels`) reads 0
+    // rather than a baseline set by the panels that remain.
+    let present = |panels: &[Option<usize>]| panels.iter().all(Option::is_some);
+    if !present(&[
+        replication_stress_panel,
+        checkpoint_activation_panel,
+        replication_fork_stability_panel,
+    ]) {
+        ddr.rss.fill(0.0);
+    }
+    if !present(&[dna_repair_hr_panel, dna_repair_nhej_panel]) {
+        ddr.drbi.fill(0.0);
+    }
+    if !present(&[chromatin_compaction_panel, chromatin_open_state_panel]) {
+        ddr.cci.fill(0.0);
+    }
+    if !present(&[replication_stress_panel, replication_fork_stability_panel]) {
+        ddr.trci.fill(0.0);
+    }
+    let genome_stability = finish_genome_stability(cells.genome_cores, gene_index, species);
 
     for cell in 0..n_cells {
         axes.rss[cell] = ddr.rss[cell];
@@ -282,9 +369,262 @@ pub fn run_stage4(
         genome_stability_norm: genome_stability.norm_stats,
         genome_stability_panel_version: genome_stability.panel_version,
         genome_stability_panel_audits: genome_stability.panel_audits,
+        cea_ribo_slope,
+        panel_caps,
+    }
+}
+
+/// Per-cell axis outputs of `run_stage4` for a contiguous run of cells.
+struct AxisSlices<'a> {
+    tbi: &'a mut [f32],
+    rci: &'a mut [f32],
+    pds: &'a mut [f32],
+    trs: &'a mut [f32],
+    nsai: &'a mut [f32],
+    iaa: &'a mut [f32],
+    dfa: &'a mut [f32],
+    cea: &'a mut [f32],
+    drivers: &'a mut [AxisDrivers],
+    flags: &'a mut [AxisFlags],
+}
+
+impl<'a> AxisSlices<'a> {
+    fn new(axes: &'a mut Axes, drivers: &'a mut [AxisDrivers], flags: &'a mut [AxisFlags]) -> Self {
+        Self {
+            tbi: &mut axes.tbi,
+            rci: &mut axes.rci,
+            pds: &mut axes.pds,
+            trs: &mut axes.trs,
+            nsai: &mut axes.nsai,
+            iaa: &mut axes.iaa,
+            dfa: &mut axes.dfa,
+            cea: &mut axes.cea,
+            drivers,
+            flags,
+        }
+    }
+
+    fn split_at(self, mid: usize) -> (Self, Self) {
+        let (tbi, tbi_rest) = self.tbi.split_at_mut(mid);
+        let (rci, rci_rest) = self.rci.split_at_mut(mid);
+        let (pds, pds_rest) = self.pds.split_at_mut(mid);
+        let (trs, trs_rest) = self.trs.split_at_mut(mid);
+        let (nsai, nsai_rest) = self.nsai.split_at_mut(mid);
+        let (iaa, iaa_rest) = self.iaa.split_at_mut(mid);
+        let (dfa, dfa_rest) = self.dfa.split_at_mut(mid);
+        let (cea, cea_rest) = self.cea.split_at_mut(mid);
+        let (drivers, drivers_rest) = self.drivers.split_at_mut(mid);
+        let (flags, flags_rest) = self.flags.split_at_mut(mid);
+        (
+            Self {
+                tbi,
+                rci,
+                pds,
+                trs,
+                nsai,
+                iaa,
+                dfa,
+                cea,
+                drivers,
+                flags,
+            },
+            Self {
+                tbi: tbi_rest,
+                rci: rci_rest,
+                pds: pds_rest,
+                trs: trs_rest,
+                nsai: nsai_rest,
+                iaa: iaa_rest,
+                dfa: dfa_rest,
+                cea: cea_rest,
+                drivers: drivers_rest,
+                flags: flags_rest,
+            },
+        )
+    }
+
+    /// Consecutive runs of `size` cells with the index of their first cell.
+    fn chunks(self, size: usize) -> Vec<(usize, Self)> {
+        let mut out = Vec::new();
+        let mut start = 0;
+        let mut rest = self;
+        while rest.drivers.len() > size {
+            let (chunk, tail) = rest.split_at(size);
+            out.push((start, chunk));
+            start += size;
+            rest = tail;
+        }
+        if !rest.drivers.is_empty() {
+            out.push((start, rest));
+        }
+        out
+    }
+}
+
+/// Read-only inputs of the per-cell Stage 4 loop, shared by all threads.
+struct CellAxisScorer<'a> {
+    expression: &'a [CellExpression],
+    panel_values: &'a [Vec<f32>],
+    program_panels: &'a [usize],
+    tf_panels: &'a [usize],
+    chromatin_panels: &'a [usize],
+    stress_panels: &'a [usize],
+    dev_panels: &'a [usize],
+    thresholds: &'a ThresholdProfile,
+    /// IAA, DFA and CEA raw panel values and their relative scores.
+    raw: [&'a [f32]; 3],
+    rel: [&'a [f32]; 3],
+}
+
+impl CellAxisScorer<'_> {
+    fn score_chunk(&self, start: usize, out: AxisSlices<'_>) {
+        let thresholds = self.thresholds;
+        let panel_values = self.panel_values;
+        let [iaa_raw, dfa_raw, cea_raw] = self.raw;
+        let [iaa_rel, dfa_rel, cea_rel] = self.rel;
+        let mut program_buf: Vec<f32> = Vec::with_capacity(self.program_panels.len());
+        let mut tf_buf: Vec<f32> =
+            Vec::with_capacity(self.tf_panels.len() + self.chromatin_panels.len());
+
+        for i in 0..out.drivers.len() {
+            let cell = start + i;
+            let CellExpression {
+                expressed_genes,
+                frac_norm,
+                gene_entropy,
+                gene_entropy_norm,
+                min_nonzero_expr,
+            } = self.expression[cell];
+
+            // Shares and entropies are taken over positive panel values, as
+            // in `nsai_score`; Pearson residual means can be negative.
+            program_buf.clear();
+            for &idx in self.program_panels {
+                program_buf.push(panel_values[cell][idx].max(0.0));
+            }
+            let (panel_entropy_norm, panel_entropy) =
+                panel_entropy_program(&program_buf, thresholds.sum_mode);
+
+            let tbi = thresholds.tbi_w1 * frac_norm
+                + thresholds.tbi_w2 * gene_entropy_norm
+                + thresholds.tbi_w3 * panel_entropy_norm;
+
+            tf_buf.clear();
+            for &idx in self.tf_panels.iter().chain(self.chromatin_panels) {
+                tf_buf.push(panel_values[cell][idx].max(0.0));
+            }
+            let (rci, tf_entropy, low_tf) =
+                rci_score(&tf_buf, thresholds.tf_min_sum, thresholds.sum_mode);
+
+            let (pds, max_share) = pds_score(
+                &program_buf,
+                thresholds.program_min_sum,
+                thresholds.sum_mode,
+            );
+
+            let trs = clip01(
+                thresholds.trs_a * (1.0 - tbi)
+                    + thresholds.trs_b * (1.0 - rci)
+                    + thresholds.trs_c * pds,
+            );
+
+            let (nsai, stress_ratio, dev_ratio) = nsai_score(
+                cell,
+                panel_values,
+                self.stress_panels,
+                self.dev_panels,
+                self.program_panels,
+                thresholds.program_min_sum,
+                thresholds.stress_boost,
+            );
+
+            let iaa = activate_axis(iaa_raw[cell], iaa_rel[cell], thresholds);
+            let dfa = activate_axis(dfa_raw[cell], dfa_rel[cell], thresholds);
+            let cea = activate_axis(cea_raw[cell], cea_rel[cell], thresholds);
+
+            out.tbi[i] = clip01(tbi);
+            out.rci[i] = clip01(rci);
+            out.pds[i] = clip01(pds);
+            out.trs[i] = trs;
+            out.nsai[i] = nsai;
+            out.iaa[i] = iaa;
+            out.dfa[i] = dfa;
+            out.cea[i] = cea;
+
+            out.drivers[i] = AxisDrivers {
+                expressed_genes,
+                min_nonzero_expr,
+                gene_entropy,
+                panel_entropy,
+                max_program_share: max_share,
+                tf_entropy,
+                stress_ratio,
+                dev_ratio,
+                iaa_raw: iaa_raw[cell],
+                dfa_raw: dfa_raw[cell],
+                cea_raw: cea_raw[cell],
+                axis_variance: 0.0,
+            };
+            out.flags[i] = AxisFlags {
+                low_tf_signal: low_tf,
+            };
+        }
     }
 }
 
+/// Clamps each panel's per-cell values at their `quantile` over all cells, so
+/// a few extreme cells cannot stretch the axis inputs. Returns the clamped
+/// values and the cap of each panel.
+fn winsorize_panel_values(
+    values: &[Vec<f32>],
+    quantile: f32,
+    method: QuantileMethod,
+) -> (Vec<Vec<f32>>, Vec<f32>) {
+    let n_panels = values.first().map_or(0, Vec::len);
+    let mut column = Vec::with_capacity(values.len());
+    let caps = (0..n_panels)
+        .map(|panel| {
+            column.clear();
+            column.extend(values.iter().map(|row| row[panel]));
+            column.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            quantile_sorted(&column, quantile, method)
+        })
+        .collect::<Vec<_>>();
+    let clamped = values
+        .iter()
+        .map(|row| row.iter().zip(&caps).map(|(&v, &cap)| v.min(cap)).collect())
+        .collect();
+    (clamped, caps)
+}
+
+/// Least-squares slope of `values` on `ribo`, clamped at zero so the
+/// adjustment only ever removes ribosome-explained signal.
+fn ribo_regression_slope(values: &[f32], ribo: &[f32]) -> f32 {
+    let n = values.len().min(ribo.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let mut mean_x = 0f64;
+    let mut mean_y = 0f64;
+    for (&y, &x) in values.iter().zip(ribo) {
+        mean_x += x as f64;
+        mean_y += y as f64;
+    }
+    mean_x /= n as f64;
+    mean_y /= n as f64;
+    let mut cov = 0f64;
+    let mut var = 0f64;
+    for (&y, &x) in values.iter().zip(ribo) {
+        let dx = x as f64 - mean_x;
+        cov += dx * (y as f64 - mean_y);
+        var += dx * dx;
+    }
+    if var <= 0.0 {
+        return 0.0;
+    }
+    (cov / var).max(0.0) as f32
+}
+
 fn find_panel(panel_set: &PanelSet, id: &str) -> Option<usize> {
     panel_set.panels.iter().position(|p| p.id == id)
 }
@@ -295,9 +635,9 @@ fn compute_relative_scores(values: &[f32], thresholds: &ThresholdProfile) -> Vec
     }
     let mut sorted = values.to_vec();
     sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    let n = sorted.len();
-    let p70 = sorted[((n - 1) as f32 * thresholds.rel_p70).ceil() as usize];
-    let p85 = sorted[((n - 1) as f32 * thresholds.rel_p85).ceil() as usize];
+    let method = thresholds.quantile_method;
+    let p70 = quantile_sorted(&sorted, thresholds.rel_p70, method);
+    let p85 = quantile_sorted(&sorted, thresholds.rel_p85, method);
     let mut out = Vec::with_capacity(values.len());
     for &v in values {
         if p85 <= p70 {
@@ -310,6 +650,39 @@ fn compute_relative_scores(values: &[f32], thresholds: &ThresholdProfile) -> Vec
     out
 }
 
+/// Cell indices per sample label, in label order, keeping only groups large
+/// enough to anchor their own percentiles.
+fn relative_groups(labels: &[String], min_cells: usize) -> Vec<Vec<usize>> {
+    let mut by_label: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (cell, label) in labels.iter().enumerate() {
+        by_label.entry(label.as_str()).or_default().push(cell);
+    }
+    by_label
+        .into_values()
+        .filter(|cells| cells.len() >= min_cells.max(1))
+        .collect()
+}
+
+/// Relative scores anchored within each group; cells outside every group keep
+/// the pooled anchors.
+fn compute_relative_scores_grouped(
+    values: &[f32],
+    thresholds: &ThresholdProfile,
+    groups: &[Vec<usize>],
+) -> Vec<f32> {
+    let mut out = compute_relative_scores(values, thresholds);
+    let mut group_values = Vec::new();
+    for cells in groups {
+        group_values.clear();
+        group_values.extend(cells.iter().map(|&cell| values[cell]));
+        let rel = compute_relative_scores(&group_values, thresholds);
+        for (&cell, r) in cells.iter().zip(rel) {
+            out[cell] = r;
+        }
+    }
+    out
+}
+
 fn activate_axis(raw: f32, rel: f32, thresholds: &ThresholdProfile) -> f32 {
     match thresholds.activation_mode {
         AxisActivationMode::Absolute => clip01(raw),
@@ -347,19 +720,21 @@ fn axis_variance(
     }
     (var / vals.len() as f64) as f32
 }
+/// `x` rescaled from [min, max] to [0, 1]; an empty or non-finite range
+/// gives 0, as does a NaN `x`.
 fn rescale01(x: f32, min: f32, max: f32) -> f32 {
-    if max <= min {
+    if !(min.is_finite() && max.is_finite()) || max <= min {
         return 0.0;
     }
     let v = (x - min) / (max - min);
     clip01(v)
 }
 
-fn entropy_norm_from_values(values: &[f32]) -> (f32, f32) {
+fn entropy_norm_from_values(values: &[f32], sum_mode: SumMode) -> (f32, f32) {
     if values.is_empty() {
         return (0.0, 0.0);
     }
-    let h = simd::entropy_f32(values) as f64;
+    let h = simd::entropy_f32(values, sum_mode) as f64;
     let n = values.len();
     let h_norm = if n >= 2 {
         let denom = (n as f64).ln();
@@ -370,17 +745,11 @@ fn entropy_norm_from_values(values: &[f32]) -> (f32, f32) {
     (h as f32, h_norm as f32)
 }
 
-fn panel_entropy_program(values: &[f32]) -> (f32, f32) {
+fn panel_entropy_program(values: &[f32], sum_mode: SumMode) -> (f32, f32) {
     if values.is_empty() {
         return (0.0, 0.0);
     }
-    let sum = simd::sum_f32_f64(values);
-    let mut nonzero = 0usize;
-    for &v in values {
-        if v > 0.0 {
-            nonzero += 1;
-        }
-    }
+    let (sum, _, nonzero) = simd::stats_f32(values, sum_mode);
     if sum <= 0.0 || nonzero < 2 {
         return (0.0, 0.0);
     }
@@ -395,15 +764,9 @@ fn panel_entropy_program(values: &[f32]) -> (f32, f32) {
     (h_norm as f32, h as f32)
 }
 
-fn rci_score(values: &[f32], tf_min_sum: f32) -> (f32, f32, bool) {
-    let sum = simd::sum_f32_f64(values);
-    let max = simd::max_f32(values) as f64;
-    let mut nonzero = 0usize;
-    for &v in values {
-        if v > 0.0 {
-            nonzero += 1;
-        }
-    }
+fn rci_score(values: &[f32], tf_min_sum: f32, sum_mode: SumMode) -> (f32, f32, bool) {
+    let (sum, max, nonzero) = simd::stats_f32(values, sum_mode);
+    let max = max as f64;
 
     if sum < tf_min_sum as f64 {
         return (0.0, 0.0, true);
@@ -429,8 +792,12 @@ fn rci_score(values: &[f32], tf_min_sum: f32) -> (f32, f32, bool) {
     (rci as f32, entropy_norm as f32, false)
 }
 
-fn pds_score(values: &[f32], program_min_sum: f32) -> (f32, f32) {
-    let sum = simd::sum_f32_f64(values);
+fn pds_score(values: &[f32], program_min_sum: f32, sum_mode: SumMode) -> (f32, f32) {
+    let sum = simd::sum_f32_f64_mode(values, sum_mode);
+    if sum < program_min_sum as f64 || sum == 0.0 {
+        return (0.0, 0.0);
+    }
+
     let mut top1 = 0f64;
     let mut top2 = 0f64;
     let mut top3 = 0f64;
@@ -451,10 +818,6 @@ fn pds_score(values: &[f32], program_min_sum: f32) -> (f32, f32) {
         }
     }
 
-    if sum < program_min_sum as f64 || sum == 0.0 {
-        return (0.0, 0.0);
-    }
-
     let max_share = top1 / sum;
     let top3_share = (top1 + top2 + top3) / sum;
     let pds = 0.7 * max_share + 0.3 * top3_share;
@@ -463,7 +826,7 @@ fn pds_score(values: &[f32], program_min_sum: f32) -> (f32, f32) {
 
 fn nsai_score(
     cell: usize,
-    panel_scores: &PanelScores,
+    panel_values: &[Vec<f32>],
     stress_panels: &[usize],
     dev_panels: &[usize],
     program_panels: &[usize],
@@ -472,7 +835,7 @@ fn nsai_score(
 ) -> (f32, f32, f32) {
     let mut program_sum = 0f64;
     for &idx in program_panels {
-        let v = panel_scores.panel_sum[cell][idx] as f64;
+        let v = panel_values[cell][idx] as f64;
         if v > 0.0 {
             program_sum += v;
         }
@@ -483,7 +846,7 @@ fn nsai_score(
 
     let mut stress_sum = 0f64;
     for &idx in stress_panels {
-        let v = panel_scores.panel_sum[cell][idx] as f64;
+        let v = panel_values[cell][idx] as f64;
         if v > 0.0 {
             stress_sum += v;
         }
@@ -491,7 +854,7 @@ fn nsai_score(
 
     let mut dev_sum = 0f64;
     for &idx in dev_panels {
-        let v = panel_scores.panel_sum[cell][idx] as f64;
+        let v = panel_values[cell][idx] as f64;
         if v > 0.0 {
             dev_sum += v;
         }
@@ -1,12 +1,14 @@
 use crate::model::axes::{Axes, AxisDrivers, clip01};
 use crate::model::drivers::ScoreDrivers;
-use crate::model::scores::CompositeScores;
-use crate::model::thresholds::{NuclearScoringMode, ThresholdProfile};
+use crate::model::scores::{CompositeScores, CustomComposite};
+use crate::model::thresholds::{ConfidenceModel, NuclearScoringMode, ThresholdProfile};
 
 #[derive(Debug)]
 pub struct Stage5Output {
     pub scores: CompositeScores,
     pub drivers: ScoreDrivers,
+    /// `--composite` values per cell, when one was given.
+    pub custom: Option<Vec<f32>>,
 }
 
 #[derive(Debug, Clone)]
@@ -19,11 +21,18 @@ pub struct Stage5Inputs<'a> {
     pub ambient_rna_risk: Option<&'a [bool]>,
     pub key_panels_missing: Option<&'a [bool]>,
     pub panel_nonzero_fraction: Option<&'a [f32]>,
+    pub mito_fraction: Option<&'a [f32]>,
     pub axis_p90: Option<[f32; 3]>,
     pub scoring_mode: NuclearScoringMode,
+    pub confidence_model: ConfidenceModel,
     pub include_ddr: bool,
+    pub custom_composite: Option<&'a CustomComposite>,
 }
 
+// Logistic confidence: steepness and midpoint on the weighted component sum.
+const LOGISTIC_SLOPE: f32 = 10.0;
+const LOGISTIC_MIDPOINT: f32 = 0.5;
+
 pub fn run_stage5(inputs: &Stage5Inputs<'_>) -> Stage5Output {
     let n_cells = inputs.axes.tbi.len();
     let mut scores = CompositeScores {
@@ -49,9 +58,10 @@ pub fn run_stage5(inputs: &Stage5Inputs<'_>) -> Stage5Output {
 
         let nps = clip01(0.45 * tbi + 0.35 * rci - 0.20 * pds - 0.20 * trs);
         let mut ci = clip01(0.55 * trs + 0.45 * pds - 0.15 * tbi);
-        let (confidence, breakdown) = match inputs.scoring_mode {
-            NuclearScoringMode::StrictBulk => compute_confidence_legacy(inputs, cell),
-            NuclearScoringMode::ImmuneAware => compute_confidence(inputs, cell),
+        let (confidence, breakdown) = match inputs.confidence_model {
+            ConfidenceModel::AdditiveImmune => compute_confidence(inputs, cell),
+            ConfidenceModel::LegacyMultiplicative => compute_confidence_legacy(inputs, cell),
+            ConfidenceModel::Logistic => compute_confidence_logistic(inputs, cell),
         };
         let mut rls = match inputs.scoring_mode {
             NuclearScoringMode::StrictBulk => compute_rls_legacy(inputs, cell, confidence),
@@ -102,10 +112,56 @@ pub fn run_stage5(inputs: &Stage5Inputs<'_>) -> Stage5Output {
     Stage5Output {
         scores,
         drivers: drivers_out,
+        custom: inputs.custom_composite.map(|c| c.evaluate(inputs.axes)),
     }
 }
 
 fn compute_confidence(inputs: &Stage5Inputs<'_>, cell: usize) -> (f32, [f32; 4]) {
+    let breakdown = confidence_components(inputs, cell);
+    let missing_key = inputs
+        .key_panels_missing
+        .and_then(|v| v.get(cell).copied())
+        .unwrap_or(false);
+    let axis_structure_score = breakdown[2];
+
+    let w = inputs.thresholds.confidence_weights();
+    let conf = clip01(
+        w[0] * breakdown[0] + w[1] * breakdown[1] + w[2] * breakdown[2] + w[3] * breakdown[3],
+    );
+
+    let conf = if lacks_evidence(inputs, axis_structure_score) {
+        0.0
+    } else if !missing_key && axis_structure_score >= inputs.thresholds.confidence_structure_min {
+        conf.max(inputs.thresholds.confidence_floor)
+    } else {
+        conf
+    };
+
+    (conf, breakdown)
+}
+
+fn compute_confidence_logistic(inputs: &Stage5Inputs<'_>, cell: usize) -> (f32, [f32; 4]) {
+    let breakdown = confidence_components(inputs, cell);
+    if lacks_evidence(inputs, breakdown[2]) {
+        return (0.0, breakdown);
+    }
+    let w = inputs.thresholds.confidence_weights();
+    let sum = w[0] * breakdown[0] + w[1] * breakdown[1] + w[2] * breakdown[2] + w[3] * breakdown[3];
+    let conf = 1.0 / (1.0 + (-LOGISTIC_SLOPE * (sum - LOGISTIC_MIDPOINT)).exp());
+    (clip01(conf), breakdown)
+}
+
+// No panel evidence and no axis structure: confidence is zero in every model
+// built on the components.
+fn lacks_evidence(inputs: &Stage5Inputs<'_>, axis_structure_score: f32) -> bool {
+    inputs.key_panel_coverage_median.is_none()
+        && inputs.panel_nonzero_fraction.is_none()
+        && axis_structure_score == 0.0
+}
+
+// Panel coverage, expression support, axis structure and consistency, each
+// in [0, 1].
+fn confidence_components(inputs: &Stage5Inputs<'_>, cell: usize) -> [f32; 4] {
     let key_cov = inputs
         .key_panel_coverage_median
         .and_then(|v| v.get(cell).copied())
@@ -129,6 +185,12 @@ fn compute_confidence(inputs: &Stage5Inputs<'_>, cell: usize) -> (f32, [f32; 4])
         .and_then(|v| v.get(cell).copied())
         .unwrap_or(expr_frac);
 
+    let high_mito = inputs
+        .mito_fraction
+        .and_then(|v| v.get(cell).copied())
+        .map(|v| v > inputs.thresholds.mito_frac_max)
+        .unwrap_or(false);
+
     let _ambient_risk = inputs
         .ambient_rna_risk
         .and_then(|v| v.get(cell).copied())
@@ -137,39 +199,24 @@ fn compute_confidence(inputs: &Stage5Inputs<'_>, cell: usize) -> (f32, [f32; 4])
     let panel_coverage_score = if missing_key {
         0.0
     } else {
-        clip01(key_cov / 0.6)
+        clip01(key_cov / inputs.thresholds.confidence_coverage_scale)
     };
-    let expression_support_score = clip01(panel_nonzero.sqrt());
-    let axis_structure_score = clip01(inputs.drivers[cell].axis_variance / 0.05);
-    let consistency_score = consistency_score(inputs, cell);
-
-    let conf = clip01(
-        0.30 * panel_coverage_score
-            + 0.25 * expression_support_score
-            + 0.25 * axis_structure_score
-            + 0.20 * consistency_score,
+    let mut expression_support_score = clip01(panel_nonzero.sqrt());
+    if high_mito {
+        // Mito-dominated libraries overstate nuclear expression support.
+        expression_support_score *= 0.9;
+    }
+    let axis_structure_score = clip01(
+        inputs.drivers[cell].axis_variance / inputs.thresholds.confidence_axis_variance_scale,
     );
+    let consistency_score = consistency_score(inputs, cell);
 
-    let conf = if inputs.key_panel_coverage_median.is_none()
-        && inputs.panel_nonzero_fraction.is_none()
-        && axis_structure_score == 0.0
-    {
-        0.0
-    } else if !missing_key && axis_structure_score >= 0.2 {
-        conf.max(0.2)
-    } else {
-        conf
-    };
-
-    (
-        conf,
-        [
-            panel_coverage_score,
-            expression_support_score,
-            axis_structure_score,
-            consistency_score,
-        ],
-    )
+    [
+        panel_coverage_score,
+        expression_support_score,
+        axis_structure_score,
+        consistency_score,
+    ]
 }
 
 fn compute_confidence_legacy(inputs: &Stage5Inputs<'_>, cell: usize) -> (f32, [f32; 4]) {
@@ -3,11 +3,146 @@ use crate::model::flags::{Flag, flag_order};
 use crate::model::regimes::NuclearRegime;
 use crate::model::scores::CompositeScores;
 use crate::model::thresholds::{AxisActivationMode, NuclearScoringMode, ThresholdProfile};
+use crate::panels::CellCyclePhase;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Classification {
     pub regime: NuclearRegime,
     pub flags: Vec<Flag>,
+    /// For `Unclassified` cells, the regime they came closest to and the
+    /// condition that kept them out.
+    pub near_miss: Option<NearMiss>,
+}
+
+impl Classification {
+    pub fn silence_reason(&self) -> Option<SilenceReason> {
+        if self.flags.contains(&Flag::ModelLimitation) {
+            Some(SilenceReason::ModelLimitation)
+        } else if self.flags.contains(&Flag::BiologicalSilence) {
+            Some(SilenceReason::BiologicalSilence)
+        } else {
+            None
+        }
+    }
+}
+
+/// Comparison a regime rule requires of a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Cmp {
+    Ge,
+    Le,
+    Lt,
+}
+
+impl Cmp {
+    fn holds(self, value: f32, cutoff: f32) -> bool {
+        match self {
+            Cmp::Ge => value >= cutoff,
+            Cmp::Le => value <= cutoff,
+            Cmp::Lt => value < cutoff,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Cmp::Ge => ">=",
+            Cmp::Le => "<=",
+            Cmp::Lt => "<",
+        }
+    }
+
+    /// The comparison that holds when this one fails.
+    pub fn negated_str(self) -> &'static str {
+        match self {
+            Cmp::Ge => "<",
+            Cmp::Le => ">",
+            Cmp::Lt => ">=",
+        }
+    }
+}
+
+/// One rule condition: `value` of `metric` compared with `cutoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Condition {
+    pub metric: &'static str,
+    pub cmp: Cmp,
+    pub value: f32,
+    pub cutoff: f32,
+}
+
+impl Condition {
+    fn new(metric: &'static str, value: f32, cmp: Cmp, cutoff: f32) -> Self {
+        Condition {
+            metric,
+            cmp,
+            value,
+            cutoff,
+        }
+    }
+
+    fn holds(&self) -> bool {
+        self.cmp.holds(self.value, self.cutoff)
+    }
+
+    /// Distance to the cutoff; 0 at the cutoff itself.
+    fn gap(&self) -> f32 {
+        (self.value - self.cutoff).abs()
+    }
+
+    /// The rule as written, e.g. `rci>=0.35`; the same for every cell.
+    pub fn rule(&self) -> String {
+        format!("{}{}{}", self.metric, self.cmp.as_str(), self.cutoff)
+    }
+
+    /// Why the condition failed for this cell, e.g. `rci=0.30<0.35`.
+    pub fn failure(&self) -> String {
+        format!(
+            "{}={:.2}{}{}",
+            self.metric,
+            self.value,
+            self.cmp.negated_str(),
+            self.cutoff
+        )
+    }
+}
+
+/// Closest regime for a cell that fell through every rule in
+/// `classify_cell`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NearMiss {
+    pub regime: NuclearRegime,
+    /// Conditions of `regime` the cell failed.
+    pub failed: usize,
+    /// The failed condition furthest from its cutoff.
+    pub blocked_by: Condition,
+}
+
+/// Whether a quiet cell is explained by the model (relative activation or
+/// any immune-aware axis firing) or is genuinely silent at good confidence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SilenceReason {
+    ModelLimitation,
+    BiologicalSilence,
+}
+
+impl SilenceReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SilenceReason::ModelLimitation => "model_limitation",
+            SilenceReason::BiologicalSilence => "biological_silence",
+        }
+    }
+
+    pub fn flag(self) -> Flag {
+        match self {
+            SilenceReason::ModelLimitation => Flag::ModelLimitation,
+            SilenceReason::BiologicalSilence => Flag::BiologicalSilence,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,7 +167,11 @@ pub struct Stage6Inputs<'a> {
     pub key_panels_missing: Option<&'a [bool]>,
     pub sum_tf_panels: Option<&'a [f32]>,
     pub ambient_rna_risk: Option<&'a [bool]>,
+    pub mito_fraction: Option<&'a [f32]>,
+    pub ribo_fraction: Option<&'a [f32]>,
     pub proliferation_program_share: Option<&'a [f32]>,
+    /// When present, `CellCycleConfounder` also requires an S or G2M call.
+    pub cell_cycle_phase: Option<&'a [CellCyclePhase]>,
     pub program_sum: Option<&'a [f32]>,
 }
 
@@ -43,7 +182,16 @@ pub fn run_stage6(inputs: &Stage6Inputs<'_>) -> Vec<Classification> {
     for cell in 0..n_cells {
         let regime = classify_cell(inputs, cell);
         let flags = collect_flags(inputs, cell);
-        out.push(Classification { regime, flags });
+        let near_miss = if regime == NuclearRegime::Unclassified {
+            near_miss(inputs, cell)
+        } else {
+            None
+        };
+        out.push(Classification {
+            regime,
+            flags,
+            near_miss,
+        });
     }
 
     out
@@ -58,43 +206,140 @@ fn classify_cell(inputs: &Stage6Inputs<'_>, cell: usize) -> NuclearRegime {
         .unwrap_or(0.0);
 
     let tbi = inputs.tbi[cell];
-    let rci = inputs.rci[cell];
-    let pds = inputs.pds[cell];
-    let trs = inputs.trs[cell];
-    let nsai = inputs.nsai[cell];
+    let t = inputs.thresholds;
 
-    if expressed_genes < inputs.thresholds.min_expr_genes
-        || (tbi < 0.15 && gene_entropy < 0.10 && program_sum < inputs.thresholds.program_min_sum)
+    if expressed_genes < t.min_expr_genes
+        || (tbi < t.collapsed_tbi_max
+            && gene_entropy < t.collapsed_entropy_max
+            && program_sum < t.program_min_sum)
     {
         return NuclearRegime::TranscriptionallyCollapsed;
     }
 
-    if trs >= 0.75 && nsai >= 0.55 && rci <= 0.35 {
-        return NuclearRegime::RigidDegenerative;
-    }
-
-    if trs >= 0.70 && pds >= 0.60 && tbi <= 0.45 && nsai < 0.55 {
-        return NuclearRegime::CommittedState;
-    }
+    regime_rules(inputs, cell)
+        .into_iter()
+        .find(|(_, groups)| {
+            groups
+                .iter()
+                .all(|group| group.iter().any(Condition::holds))
+        })
+        .map_or(NuclearRegime::Unclassified, |(regime, _)| regime)
+}
 
-    if nsai >= 0.65 && rci >= 0.35 && (tbi >= 0.35 || pds <= 0.60) {
-        return NuclearRegime::StressAdaptive;
-    }
+/// Conditions of each regime past the collapse check, in precedence order;
+/// `classify_cell` assigns the first regime whose conditions all hold. Each
+/// inner slice is satisfied when any one of its conditions holds.
+fn regime_rules(
+    inputs: &Stage6Inputs<'_>,
+    cell: usize,
+) -> Vec<(NuclearRegime, Vec<Vec<Condition>>)> {
+    let tbi = inputs.tbi[cell];
+    let rci = inputs.rci[cell];
+    let pds = inputs.pds[cell];
+    let trs = inputs.trs[cell];
+    let nsai = inputs.nsai[cell];
+    let nps = inputs.scores.nps[cell];
+    let t = inputs.thresholds;
+    let c = Condition::new;
 
-    if inputs.scores.nps[cell] >= 0.60 && trs <= 0.45 && pds <= 0.50 {
-        return NuclearRegime::PlasticAdaptive;
+    let mut rules = vec![
+        (
+            NuclearRegime::RigidDegenerative,
+            vec![
+                vec![c("trs", trs, Cmp::Ge, t.rigid_trs_min)],
+                vec![c("nsai", nsai, Cmp::Ge, t.rigid_nsai_min)],
+                vec![c("rci", rci, Cmp::Le, t.rigid_rci_max)],
+            ],
+        ),
+        (
+            NuclearRegime::CommittedState,
+            vec![
+                vec![c("trs", trs, Cmp::Ge, t.committed_trs_min)],
+                vec![c("pds", pds, Cmp::Ge, t.committed_pds_min)],
+                vec![c("tbi", tbi, Cmp::Le, t.committed_tbi_max)],
+                vec![c("nsai", nsai, Cmp::Lt, t.committed_nsai_max)],
+            ],
+        ),
+        (
+            NuclearRegime::StressAdaptive,
+            vec![
+                vec![c("nsai", nsai, Cmp::Ge, t.stress_nsai_min)],
+                vec![c("rci", rci, Cmp::Ge, t.stress_rci_min)],
+                vec![
+                    c("tbi", tbi, Cmp::Ge, t.stress_tbi_min),
+                    c("pds", pds, Cmp::Le, t.stress_pds_max),
+                ],
+            ],
+        ),
+        (
+            NuclearRegime::PlasticAdaptive,
+            vec![
+                vec![c("nps", nps, Cmp::Ge, t.plastic_nps_min)],
+                vec![c("trs", trs, Cmp::Le, t.plastic_trs_max)],
+                vec![c("pds", pds, Cmp::Le, t.plastic_pds_max)],
+            ],
+        ),
+    ];
+    if inputs.scoring_mode == NuclearScoringMode::ImmuneAware {
+        rules.push((
+            NuclearRegime::TransientAdaptive,
+            vec![
+                vec![
+                    c("nps", nps, Cmp::Ge, t.transient_nps_min),
+                    c("iaa", inputs.iaa[cell], Cmp::Ge, t.transient_iaa_min),
+                    c("dfa", inputs.dfa[cell], Cmp::Ge, t.transient_dfa_min),
+                ],
+                vec![c("trs", trs, Cmp::Le, t.transient_trs_max)],
+                vec![c("pds", pds, Cmp::Le, t.transient_pds_max)],
+            ],
+        ));
     }
+    rules
+}
 
-    if inputs.scoring_mode == NuclearScoringMode::ImmuneAware {
-        if (inputs.scores.nps[cell] >= 0.45 || inputs.iaa[cell] >= 0.35 || inputs.dfa[cell] >= 0.35)
-            && trs <= 0.55
-            && pds <= 0.65
-        {
-            return NuclearRegime::TransientAdaptive;
+/// The regime whose rule the cell misses by the fewest conditions, then by
+/// the smallest summed distance to the cutoffs, then by precedence. An
+/// either-or condition fails by its nearest alternative. Collapse is not a
+/// candidate: it marks cells too sparse to score, not a missed state.
+pub fn near_miss(inputs: &Stage6Inputs<'_>, cell: usize) -> Option<NearMiss> {
+    let mut best: Option<(NearMiss, f32)> = None;
+    for (regime, groups) in regime_rules(inputs, cell) {
+        let mut failed = 0usize;
+        let mut total_gap = 0.0f32;
+        let mut blocked_by: Option<Condition> = None;
+        for group in &groups {
+            if group.iter().any(Condition::holds) {
+                continue;
+            }
+            let nearest = group
+                .iter()
+                .copied()
+                .min_by(|a, b| a.gap().total_cmp(&b.gap()))
+                .expect("rule groups are not empty");
+            failed += 1;
+            total_gap += nearest.gap();
+            if blocked_by.is_none_or(|b| nearest.gap() > b.gap()) {
+                blocked_by = Some(nearest);
+            }
+        }
+        let Some(blocked_by) = blocked_by else {
+            continue;
+        };
+        let closer = best.as_ref().is_none_or(|(b, gap)| {
+            (failed, total_gap).partial_cmp(&(b.failed, *gap)) == Some(std::cmp::Ordering::Less)
+        });
+        if closer {
+            best = Some((
+                NearMiss {
+                    regime,
+                    failed,
+                    blocked_by,
+                },
+                total_gap,
+            ));
         }
     }
-
-    NuclearRegime::Unclassified
+    best.map(|(near_miss, _)| near_miss)
 }
 
 fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
@@ -117,6 +362,14 @@ fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
         .ambient_rna_risk
         .and_then(|v| v.get(cell).copied())
         .unwrap_or(false);
+    let mito_fraction = inputs
+        .mito_fraction
+        .and_then(|v| v.get(cell).copied())
+        .unwrap_or(0.0);
+    let ribo_fraction = inputs
+        .ribo_fraction
+        .and_then(|v| v.get(cell).copied())
+        .unwrap_or(0.0);
     let proliferation_share = inputs
         .proliferation_program_share
         .and_then(|v| v.get(cell).copied())
@@ -135,7 +388,7 @@ fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
     if expressed_genes < inputs.thresholds.min_expr_genes {
         flags.push(Flag::LowExprGenes);
     }
-    if key_cov < 0.4 {
+    if key_cov < inputs.thresholds.panel_coverage_min {
         flags.push(Flag::LowPanelCoverage);
     }
     if missing_key {
@@ -153,7 +406,17 @@ fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
     if ambient {
         flags.push(Flag::AmbientRnaRisk);
     }
-    if proliferation_share > 0.5 {
+    if mito_fraction > inputs.thresholds.mito_frac_max {
+        flags.push(Flag::HighMitoFraction);
+    }
+    if ribo_fraction > inputs.thresholds.ribo_frac_max {
+        flags.push(Flag::HighRiboFraction);
+    }
+    let cycling = inputs
+        .cell_cycle_phase
+        .and_then(|v| v.get(cell).copied())
+        .is_none_or(|phase| phase != CellCyclePhase::G1);
+    if proliferation_share > inputs.thresholds.cell_cycle_share_min && cycling {
         flags.push(Flag::CellCycleConfounder);
     }
     if confidence < inputs.thresholds.confidence_low
@@ -178,14 +441,8 @@ fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
         flags.push(Flag::HighTrConflict);
     }
 
-    let model_limitation = inputs.thresholds.activation_mode != AxisActivationMode::Absolute
-        || inputs.iaa[cell] > 0.0
-        || inputs.dfa[cell] > 0.0
-        || inputs.cea[cell] > 0.0;
-    if model_limitation {
-        flags.push(Flag::ModelLimitation);
-    } else if confidence >= inputs.thresholds.confidence_low {
-        flags.push(Flag::BiologicalSilence);
+    if let Some(reason) = silence_reason(inputs, cell) {
+        flags.push(reason.flag());
     }
 
     // stable ordering
@@ -198,6 +455,20 @@ fn collect_flags(inputs: &Stage6Inputs<'_>, cell: usize) -> Vec<Flag> {
     ordered
 }
 
+pub fn silence_reason(inputs: &Stage6Inputs<'_>, cell: usize) -> Option<SilenceReason> {
+    let model_limitation = inputs.thresholds.activation_mode != AxisActivationMode::Absolute
+        || inputs.iaa[cell] > 0.0
+        || inputs.dfa[cell] > 0.0
+        || inputs.cea[cell] > 0.0;
+    if model_limitation {
+        Some(SilenceReason::ModelLimitation)
+    } else if inputs.scores.confidence[cell] >= inputs.thresholds.confidence_low {
+        Some(SilenceReason::BiologicalSilence)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage6_classify.rs"]
 mod tests;
@@ -5,40 +5,92 @@ use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
+use crate::input::{GeneIndex, SpeciesCall};
 use crate::metrics::genome_stability::aggregate::summarize_genome_stability;
 use crate::metrics::genome_stability::scores::{
     GenomePanelAudit, GenomeStabilityCellScores, RobustNormStat,
 };
+use crate::model::axes::{AxisDrivers, EntropyUnit};
 use crate::model::drivers::ScoreDrivers;
 use crate::model::flags::{Flag, flag_order};
 use crate::model::regimes::NuclearRegime;
 use crate::model::scores::CompositeScores;
-use crate::panels::{PanelAudit, PanelScores, PanelSet};
-use crate::report::json::render_summary_json;
+use crate::model::thresholds::ThresholdProfile;
+use crate::panels::loader::LowMappabilityPanel;
+use crate::panels::{CellCyclePhase, PanelAudit, PanelScoreMode, PanelScores, PanelSet};
+use crate::pipeline::stage3_panels::PanelContributions;
+use crate::report::contrast::{ContrastDesign, ContrastSummary, contrast_metrics};
+use crate::report::json::{SUMMARY_SCHEMA_VERSION, render_performance_json, render_summary_json};
+use crate::report::metadata::MetaColumnSummary;
 use crate::report::text::render_report_text;
 use crate::report::{
-    NamedStats, RegimeStat, ReportContext, SummaryData, bool_fraction, format_f32_6, median, p10,
-    p90, p99,
+    BlockingStat, ClusterStat, NamedStats, QcGates, QuantileMethod, RegimeStat, ReportContext,
+    SummaryData, bool_fraction, format_f32_6, median, p90, percentile_ranks, quantile_sorted,
+    quantiles,
 };
+use crate::simd::mean_var_f32;
+use crate::tracing::WarningLog;
 
 #[derive(Debug, Clone, Copy)]
 pub enum ReportMode {
     Cell,
     Sample,
+    /// One row per cluster label; needs `--clusters` or a cluster column in
+    /// the metadata.
+    Cluster,
 }
 
+/// Group of the cells without a cluster label in `--mode cluster`.
+pub const UNASSIGNED_CLUSTER: &str = "(none)";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RunMode {
     Standalone,
     Pipeline,
 }
 
+impl RunMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunMode::Standalone => "standalone",
+            RunMode::Pipeline => "pipeline",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PipelineContext {
     pub input_dir: String,
     pub input_source: String,
     pub shared_bin: Option<String>,
     pub run_mode: String,
+    /// Write `pipeline_step.json` outside pipeline mode (`--emit-step-json`).
+    pub emit_step_json: bool,
+    /// Files read, with their FNV-1a 64 hash in hex; only hashed under
+    /// `--emit-step-json`.
+    pub input_hashes: Vec<(String, String)>,
+}
+
+/// Parsed invocation written to `provenance.json` for exact reproduction.
+#[derive(Debug, Clone)]
+pub struct RunProvenance {
+    pub args: Vec<String>,
+    pub input_dir: String,
+    pub out_dir: String,
+    pub meta_path: Option<String>,
+    pub cache_path: Option<String>,
+    pub panels_path: Option<String>,
+    pub run_mode: String,
+    pub report_mode: String,
+    pub scoring_mode: String,
+    /// `additive`, `multiplicative`, or `logistic`.
+    pub confidence_model: String,
+    pub thresholds_profile: String,
+    /// `log`, `pearson`, or `raw`.
+    pub normalize_mode: String,
+    pub cache_normalized: bool,
+    pub cache_codec: String,
+    pub assume_transposed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -48,11 +100,22 @@ pub struct Stage7Input<'a> {
     pub condition: Option<&'a [String]>,
     pub species_per_cell: Option<&'a [String]>,
     pub cluster_labels: Option<&'a [String]>,
+    /// Levels compared by `--contrast`.
+    pub contrast: Option<&'a ContrastDesign>,
     pub species_global: String,
+    pub species_call: SpeciesCall,
+    /// Cells in the input before `--max-cells` subsampling, if it applied.
+    pub sampled_from: Option<usize>,
 
-    pub libsize: &'a [f32],
+    pub libsize: &'a [f64],
     pub nnz: &'a [u32],
     pub expressed_genes: &'a [u32],
+    pub pct_mito: &'a [f32],
+    pub pct_ribo: &'a [f32],
+    /// Share of counts on spike-in and control features.
+    pub pct_control: &'a [f32],
+    pub cell_cycle_phase: &'a [CellCyclePhase],
+    pub min_nonzero_expr: &'a [f32],
 
     pub axes_tbi: &'a [f32],
     pub axes_rci: &'a [f32],
@@ -73,28 +136,88 @@ pub struct Stage7Input<'a> {
 
     pub scores: &'a CompositeScores,
     pub drivers: &'a ScoreDrivers,
+    pub thresholds: &'a ThresholdProfile,
+    pub cea_ribo_slope: Option<f32>,
+    /// Stage 4 panel clamps under `thresholds.winsorize_panels`, in panel
+    /// order.
+    pub panel_caps: Option<&'a [f32]>,
     pub activation_mode: String,
     pub scoring_mode: String,
+    /// Stage 5 confidence model, as named in the text report.
+    pub confidence_model: String,
+    /// `--profile` name (`default`, `immune`, `snrna`, `tumor`).
+    pub profile: String,
     pub pipeline_context: Option<PipelineContext>,
+    pub provenance: Option<RunProvenance>,
 
     pub classifications: &'a [crate::pipeline::stage6_classify::Classification],
 
     pub panel_set: &'a PanelSet,
     pub panel_audits: &'a [PanelAudit],
     pub panel_scores: &'a PanelScores,
+    /// Panels dropped before Stage 3 by `--min-mappable-fraction`.
+    pub excluded_panels: &'a [LowMappabilityPanel],
+    /// Typed summaries of the `--meta` columns, in column order.
+    pub metadata_columns: &'a [MetaColumnSummary],
+    pub qc_gates: QcGates,
 
     pub tool_name: String,
     pub tool_version: String,
     pub git_hash: Option<String>,
     pub simd_backend: String,
+    /// `--seed`, recorded for reproducibility.
+    pub seed: u64,
+    /// Wall-clock seconds per stage up to Stage 6, in run order.
+    pub stage_seconds: Vec<(&'static str, f64)>,
+    /// Peak resident set size in bytes after Stage 6, if the OS reports it.
+    pub peak_rss_bytes: Option<u64>,
 
     pub n_genes_raw: usize,
     pub n_genes_mappable: usize,
+    pub n_control_features: usize,
 
     pub normalize: bool,
     pub scale: f32,
     pub log1p: bool,
     pub confidence_breakdown: Option<&'a [[f32; 4]]>,
+    /// Names of the `confidence_breakdown` components to append per cell,
+    /// from
+    /// [`crate::model::thresholds::ConfidenceModel::breakdown_columns`] (`--confidence-breakdown`).
+    pub confidence_breakdown_columns: Option<&'static [&'static str]>,
+    /// `--composite` in canonical form and its per-cell values, written as
+    /// `c4_custom`.
+    pub custom_composite: Option<(String, &'a [f32])>,
+    /// Stage 4 driver quantities, appended as diagnostic columns when set
+    /// (`--emit-drivers`).
+    pub axis_drivers: Option<&'a [AxisDrivers]>,
+    /// Unit of the entropy driver columns (`--entropy-unit`).
+    pub entropy_unit: EntropyUnit,
+    /// Prepended to every artifact file name (`--output-prefix`).
+    pub output_prefix: String,
+    /// Also write `nuclearqc_obs.csv` (`--emit-obs-csv`).
+    pub emit_obs_csv: bool,
+    /// Also write `program_shares.tsv` (`--emit-program-shares`).
+    pub emit_program_shares: bool,
+    /// Append a `det_<panel>` detected-gene count per key panel
+    /// (`--emit-panel-detection`).
+    pub emit_panel_detection: bool,
+    /// Also write `axis_correlations.tsv` (`--emit-axis-correlations`).
+    pub emit_axis_correlations: bool,
+    /// Also write `metrics_long.tsv`, axes and composites in long form
+    /// (`--emit-long`).
+    pub emit_long: bool,
+    /// Add each cell's percentile rank within the run for axes A1-A8
+    /// (`--axis-percentiles`).
+    pub axis_percentiles: bool,
+    /// Top genes of the `--explain-panel` panel per cell, written to
+    /// `panel_<id>_contributions.tsv` under their `GeneIndex` symbols.
+    pub panel_contributions: Option<(&'a PanelContributions, &'a GeneIndex)>,
+    /// Append the nearest regime and blocking condition of `Unclassified`
+    /// cells (`--explain-regimes`).
+    pub explain_regimes: bool,
+    /// Write the cell-mode `nuclearqc.tsv` to stdout instead of `out_dir`
+    /// (`--cell-stdout`).
+    pub cell_stdout: bool,
 }
 
 pub fn write_reports(
@@ -103,41 +226,132 @@ pub fn write_reports(
     mode: ReportMode,
 ) -> std::io::Result<()> {
     fs::create_dir_all(out_dir)?;
+    let artifact = |name: &str| format!("{}{name}", input.output_prefix);
 
-    let nuclearqc_path = out_dir.join("nuclearqc.tsv");
+    let nuclearqc_path = out_dir.join(artifact("nuclearqc.tsv"));
     match mode {
-        ReportMode::Cell => write_cell_tsv(input, &nuclearqc_path)?,
-        ReportMode::Sample => write_sample_tsv(input, &nuclearqc_path)?,
+        ReportMode::Cell if input.cell_stdout => {
+            write_cell_tsv(input, BufWriter::new(std::io::stdout().lock()))?
+        }
+        ReportMode::Cell => write_cell_tsv(input, BufWriter::new(File::create(&nuclearqc_path)?))?,
+        ReportMode::Sample => write_group_tsv(
+            input,
+            &nuclearqc_path,
+            "sample",
+            group_cells(input.sample, input.barcodes.len(), ""),
+        )?,
+        ReportMode::Cluster => write_group_tsv(
+            input,
+            &nuclearqc_path,
+            "cluster",
+            group_cells(
+                input.cluster_labels,
+                input.barcodes.len(),
+                UNASSIGNED_CLUSTER,
+            ),
+        )?,
+    }
+
+    if input.emit_obs_csv {
+        write_obs_csv(input, &out_dir.join(artifact("nuclearqc_obs.csv")))?;
+    }
+
+    if input.emit_program_shares {
+        write_program_shares(input, &out_dir.join(artifact("program_shares.tsv")))?;
+    }
+
+    if input.emit_axis_correlations {
+        write_axis_correlations(input, &out_dir.join(artifact("axis_correlations.tsv")))?;
     }
 
-    let summary_path = out_dir.join("summary.json");
+    if input.emit_long {
+        write_metrics_long(input, &out_dir.join(artifact("metrics_long.tsv")))?;
+    }
+
+    if let Some((contributions, gene_index)) = input.panel_contributions {
+        write_panel_contributions(
+            input.barcodes,
+            contributions,
+            gene_index,
+            &out_dir.join(artifact(&format!(
+                "panel_{}_contributions.tsv",
+                contributions.panel_id
+            ))),
+        )?;
+    }
+
+    let summary_path = out_dir.join(artifact("summary.json"));
     let summary = build_summary(input, mode);
     let json = render_summary_json(&summary);
     write_text(&summary_path, &json)?;
+    write_text(
+        &out_dir.join(artifact("performance.json")),
+        &render_performance_json(&summary),
+    )?;
 
-    let report_path = out_dir.join("report.txt");
+    let report_path = out_dir.join(artifact("report.txt"));
     let report_ctx = build_report_context(input, &summary);
     let report = render_report_text(&report_ctx);
     write_text(&report_path, &report)?;
 
-    let panels_path = out_dir.join("panels_report.tsv");
+    let panels_path = out_dir.join(artifact("panels_report.tsv"));
     write_panels_report(input, &panels_path)?;
 
-    if let Some(ctx) = &input.pipeline_context {
-        if ctx.run_mode != "pipeline" {
-            return Ok(());
-        }
-        let pipeline_path = out_dir.join("pipeline_step.json");
-        let json = render_pipeline_step_json(&summary);
-        write_text(&pipeline_path, &json)?;
+    if let Some(provenance) = &input.provenance {
+        let provenance_path = out_dir.join(artifact("provenance.json"));
+        write_text(&provenance_path, &render_provenance_json(input, provenance))?;
     }
 
     Ok(())
 }
 
-fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
-    let mut w = BufWriter::new(File::create(path)?);
-    let header = [
+/// Writes `pipeline_step.json` when the run has a pipeline context that asks
+/// for it. Called after [`write_reports`] so `warnings` covers Stage 7 too;
+/// `failed` records a run rejected by `--fail-on-warn`.
+pub fn write_pipeline_step(
+    input: &Stage7Input<'_>,
+    summary: &SummaryData,
+    warnings: &WarningLog,
+    failed: bool,
+    out_dir: &Path,
+) -> std::io::Result<()> {
+    let Some(ctx) = &input.pipeline_context else {
+        return Ok(());
+    };
+    if ctx.run_mode != "pipeline" && !ctx.emit_step_json {
+        return Ok(());
+    }
+    let artifact = |name: &str| format!("{}{name}", input.output_prefix);
+    // `--cell-stdout` streams the cell table instead of writing it.
+    let cell_metrics = match input.cell_stdout {
+        true => "stdout".to_string(),
+        false => artifact("nuclearqc.tsv"),
+    };
+    let pipeline_path = out_dir.join(artifact("pipeline_step.json"));
+    let json = render_pipeline_step_json(
+        summary,
+        ctx,
+        warnings.total(),
+        failed,
+        &artifact,
+        &cell_metrics,
+    );
+    write_text(&pipeline_path, &json)
+}
+
+const AXIS_DRIVER_COLUMNS: [&str; 5] = [
+    "axis_variance",
+    "gene_entropy",
+    "panel_entropy",
+    "tf_entropy",
+    "max_program_share",
+];
+
+/// `--explain-regimes` columns, empty for classified cells.
+const EXPLAIN_REGIME_COLUMNS: [&str; 3] = ["nearest_regime", "unmet_conditions", "blocked_by"];
+
+fn write_cell_tsv(input: &Stage7Input<'_>, mut w: impl Write) -> std::io::Result<()> {
+    let mut columns = vec![
         "barcode",
         "sample",
         "condition",
@@ -145,6 +359,10 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
         "libsize",
         "nnz",
         "expressed_genes",
+        "pct_mito",
+        "pct_ribo",
+        "pct_control",
+        "min_nonzero_expr",
         "confidence",
         "a1_tbi",
         "a2_rci",
@@ -159,6 +377,7 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
         "c3_rls",
         "regime",
         "flags",
+        "silence_reason",
         "drivers_nps",
         "drivers_ci",
         "drivers_rls",
@@ -184,8 +403,47 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
         "checkpoint_addicted",
         "senescent_like",
         "genomic_instability_risk",
-    ]
-    .join("\t");
+        "cc_phase",
+    ];
+    let percentiles = axis_percentiles(input);
+    let pct_at = columns.iter().position(|c| *c == "a8_cea").unwrap() + 1;
+    if percentiles.is_some() {
+        columns.splice(pct_at..pct_at, AXIS_PERCENTILE_COLUMNS);
+    }
+    let mut header = columns.join("\t");
+    if input.custom_composite.is_some() {
+        header.push_str("\tc4_custom");
+    }
+    let breakdown = input
+        .confidence_breakdown
+        .zip(input.confidence_breakdown_columns);
+    if let Some((_, names)) = breakdown {
+        for name in names {
+            header.push('\t');
+            header.push_str(name);
+        }
+    }
+    if input.axis_drivers.is_some() {
+        for name in AXIS_DRIVER_COLUMNS {
+            header.push('\t');
+            header.push_str(name);
+        }
+    }
+    let detection_panels = if input.emit_panel_detection {
+        key_panel_indices(input.panel_set, &input.thresholds.key_panels)
+    } else {
+        Vec::new()
+    };
+    for &idx in &detection_panels {
+        header.push_str("\tdet_");
+        header.push_str(&input.panel_set.panels[idx].id);
+    }
+    if input.explain_regimes {
+        for name in EXPLAIN_REGIME_COLUMNS {
+            header.push('\t');
+            header.push_str(name);
+        }
+    }
     writeln!(w, "{}", header)?;
 
     let program_panels = program_panel_indices(input.panel_set);
@@ -216,23 +474,36 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
             .unwrap_or_else(|| input.species_global.clone());
 
         let flags = format_flags(&input.classifications[cell].flags);
+        let silence_reason = input.classifications[cell]
+            .silence_reason()
+            .map(|r| r.as_str())
+            .unwrap_or_default();
         let regime = regime_name(input.classifications[cell].regime);
 
         let drivers_nps = format_drivers(&input.drivers.nps[cell]);
         let drivers_ci = format_drivers(&input.drivers.ci[cell]);
         let drivers_rls = format_drivers(&input.drivers.rls[cell]);
 
-        let (top_panel, top_share) =
-            top_program_panel(cell, &program_panels, input.panel_set, input.panel_scores);
+        let (top_panel, top_share) = top_program_panel(
+            cell,
+            &program_panels,
+            input.panel_set,
+            input.panel_scores,
+            input.thresholds.panel_score,
+        );
 
-        let row = vec![
+        let mut row = vec![
             barcode.to_string(),
             sample,
             condition,
             species,
-            format_f32_6(input.libsize[cell]),
+            format!("{:.6}", input.libsize[cell]),
             input.nnz[cell].to_string(),
             input.expressed_genes[cell].to_string(),
+            format_f32_6(input.pct_mito[cell]),
+            format_f32_6(input.pct_ribo[cell]),
+            format_f32_6(input.pct_control[cell]),
+            format_f32_6(input.min_nonzero_expr[cell]),
             format_f32_6(input.scores.confidence[cell]),
             format_f32_6(input.axes_tbi[cell]),
             format_f32_6(input.axes_rci[cell]),
@@ -247,6 +518,7 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
             format_f32_6(input.scores.rls[cell]),
             regime.to_string(),
             flags,
+            silence_reason.to_string(),
             drivers_nps,
             drivers_ci,
             drivers_rls,
@@ -272,31 +544,292 @@ fn write_cell_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
             input.genome_stability.checkpoint_addicted[cell].to_string(),
             input.genome_stability.senescent_like[cell].to_string(),
             input.genome_stability.genomic_instability_risk[cell].to_string(),
-        ]
-        .join("\t");
-        writeln!(w, "{}", row)?;
+            input.cell_cycle_phase[cell].as_str().to_string(),
+        ];
+        if let Some(percentiles) = &percentiles {
+            row.splice(
+                pct_at..pct_at,
+                percentiles.iter().map(|ranks| format_f32_6(ranks[cell])),
+            );
+        }
+        if let Some((_, values)) = &input.custom_composite {
+            row.push(format_f32_6(values[cell]));
+        }
+        if let Some((values, names)) = breakdown {
+            row.extend(values[cell][..names.len()].iter().map(|&v| format_f32_6(v)));
+        }
+        if let Some(axis_drivers) = input.axis_drivers {
+            let d = &axis_drivers[cell];
+            let unit = input.entropy_unit;
+            row.extend(
+                [
+                    d.axis_variance,
+                    unit.from_nats(d.gene_entropy),
+                    unit.from_nats(d.panel_entropy),
+                    unit.from_nats(d.tf_entropy),
+                    d.max_program_share,
+                ]
+                .map(format_f32_6),
+            );
+        }
+        let detected = &input.panel_scores.panel_detected[cell];
+        row.extend(
+            detection_panels
+                .iter()
+                .map(|&idx| detected[idx].to_string()),
+        );
+        if input.explain_regimes {
+            match &input.classifications[cell].near_miss {
+                Some(near_miss) => row.extend([
+                    regime_name(near_miss.regime).to_string(),
+                    near_miss.failed.to_string(),
+                    near_miss.blocked_by.failure(),
+                ]),
+                None => row.extend([String::new(), String::new(), String::new()]),
+            }
+        }
+        writeln!(w, "{}", row.join("\t"))?;
+    }
+
+    w.flush()
+}
+
+/// Per-cell CSV for `adata.obs.join`: rows in input barcode order, `index`
+/// holding the barcode, no driver columns.
+fn write_obs_csv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(
+        w,
+        "index,confidence,a1_tbi,a2_rci,a3_pds,a4_trs,a5_nsai,a6_iaa,a7_dfa,a8_cea,\
+         c1_nps,c2_ci,c3_rls,rss,drbi,cci,trci,regime,flags"
+    )?;
+    for (cell, barcode) in input.barcodes.iter().enumerate() {
+        let values = [
+            input.scores.confidence[cell],
+            input.axes_tbi[cell],
+            input.axes_rci[cell],
+            input.axes_pds[cell],
+            input.axes_trs[cell],
+            input.axes_nsai[cell],
+            input.axes_iaa[cell],
+            input.axes_dfa[cell],
+            input.axes_cea[cell],
+            input.scores.nps[cell],
+            input.scores.ci[cell],
+            input.scores.rls[cell],
+            input.ddr_rss[cell],
+            input.ddr_drbi[cell],
+            input.ddr_cci[cell],
+            input.ddr_trci[cell],
+        ];
+        let mut row = vec![csv_field(barcode)];
+        row.extend(values.map(format_f32_6));
+        row.push(regime_name(input.classifications[cell].regime).to_string());
+        row.push(csv_field(&format_flags(&input.classifications[cell].flags)));
+        writeln!(w, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// Each program panel's share of the cell's program total, one column per
+/// panel in panel-set order; rows in input barcode order.
+fn write_program_shares(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+    let program_panels = program_panel_indices(input.panel_set);
+    let mut w = BufWriter::new(File::create(path)?);
+    let mut header = vec!["barcode"];
+    header.extend(
+        program_panels
+            .iter()
+            .map(|&idx| input.panel_set.panels[idx].id.as_str()),
+    );
+    writeln!(w, "{}", header.join("\t"))?;
+    for (cell, barcode) in input.barcodes.iter().enumerate() {
+        let shares = program_shares(
+            cell,
+            &program_panels,
+            input.panel_scores,
+            input.thresholds.panel_score,
+        )
+        .unwrap_or_else(|| vec![0.0; program_panels.len()]);
+        let mut row = vec![barcode.to_string()];
+        row.extend(shares.into_iter().map(format_f32_6));
+        writeln!(w, "{}", row.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// One `barcode, gene_symbol, value` row per cell and contributing gene,
+/// cells in `nuclearqc.tsv` order and genes largest first.
+fn write_panel_contributions(
+    barcodes: &[String],
+    contributions: &PanelContributions,
+    gene_index: &GeneIndex,
+    path: &Path,
+) -> std::io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "barcode\tgene_symbol\tvalue")?;
+    for (barcode, genes) in barcodes.iter().zip(&contributions.top_genes) {
+        for &(gene_id, value) in genes {
+            let symbol = gene_index
+                .symbols_by_gene_id
+                .get(gene_id as usize)
+                .map_or("", String::as_str);
+            writeln!(w, "{barcode}\t{symbol}\t{}", format_f32_6(value))?;
+        }
+    }
+    w.flush()
+}
+
+/// One `barcode, metric_name, value` row per cell and metric, cells in
+/// `nuclearqc.tsv` order and metrics in [`long_metrics`] order.
+fn write_metrics_long(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+    let metrics = long_metrics(input);
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "barcode\tmetric_name\tvalue")?;
+    for (cell, barcode) in input.barcodes.iter().enumerate() {
+        for (name, values) in &metrics {
+            writeln!(w, "{barcode}\t{name}\t{}", format_f32_6(values[cell]))?;
+        }
     }
+    w.flush()
+}
+
+/// Axes and composites under their `nuclearqc.tsv` column names, in column
+/// order; `c4_custom` comes last when `--composite` is set.
+fn long_metrics<'a>(input: &Stage7Input<'a>) -> Vec<(&'static str, &'a [f32])> {
+    let mut metrics = vec![
+        ("a1_tbi", input.axes_tbi),
+        ("a2_rci", input.axes_rci),
+        ("a3_pds", input.axes_pds),
+        ("a4_trs", input.axes_trs),
+        ("a5_nsai", input.axes_nsai),
+        ("a6_iaa", input.axes_iaa),
+        ("a7_dfa", input.axes_dfa),
+        ("a8_cea", input.axes_cea),
+        ("c1_nps", input.scores.nps.as_slice()),
+        ("c2_ci", input.scores.ci.as_slice()),
+        ("c3_rls", input.scores.rls.as_slice()),
+        ("rss", input.ddr_rss),
+        ("drbi", input.ddr_drbi),
+        ("cci", input.ddr_cci),
+        ("trci", input.ddr_trci),
+    ];
+    if let Some((_, values)) = input.custom_composite {
+        metrics.push(("c4_custom", values));
+    }
+    metrics
+}
 
+/// The twelve axes by short name, `tbi` through `trci`.
+fn axis_columns<'a>(input: &Stage7Input<'a>) -> [(&'static str, &'a [f32]); 12] {
+    [
+        ("tbi", input.axes_tbi),
+        ("rci", input.axes_rci),
+        ("pds", input.axes_pds),
+        ("trs", input.axes_trs),
+        ("nsai", input.axes_nsai),
+        ("iaa", input.axes_iaa),
+        ("dfa", input.axes_dfa),
+        ("cea", input.axes_cea),
+        ("rss", input.ddr_rss),
+        ("drbi", input.ddr_drbi),
+        ("cci", input.ddr_cci),
+        ("trci", input.ddr_trci),
+    ]
+}
+
+/// Pearson correlation of every pair of axes across cells, as a symmetric
+/// matrix in [`axis_columns`] order. A pair involving a constant axis is 0.
+fn axis_correlations(input: &Stage7Input<'_>) -> Vec<Vec<f32>> {
+    let sum_mode = input.thresholds.sum_mode;
+    let axes = axis_columns(input);
+    let moments = axes
+        .iter()
+        .map(|(_, values)| mean_var_f32(values, sum_mode))
+        .collect::<Vec<_>>();
+    let mut out = vec![vec![0.0f32; axes.len()]; axes.len()];
+    for i in 0..axes.len() {
+        for j in i..axes.len() {
+            let ((mean_i, var_i), (mean_j, var_j)) = (moments[i], moments[j]);
+            if var_i <= 0.0 || var_j <= 0.0 {
+                continue;
+            }
+            let mut cov = 0.0f64;
+            for (&x, &y) in axes[i].1.iter().zip(axes[j].1) {
+                cov += (x as f64 - mean_i) * (y as f64 - mean_j);
+            }
+            cov /= axes[i].1.len() as f64;
+            let r = (cov / (var_i * var_j).sqrt()).clamp(-1.0, 1.0) as f32;
+            out[i][j] = r;
+            out[j][i] = r;
+        }
+    }
+    out
+}
+
+fn write_axis_correlations(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+    let names = axis_columns(input).map(|(name, _)| name);
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "axis\t{}", names.join("\t"))?;
+    for (name, row) in names.iter().zip(axis_correlations(input)) {
+        let mut fields = vec![name.to_string()];
+        fields.extend(row.into_iter().map(format_f32_6));
+        writeln!(w, "{}", fields.join("\t"))?;
+    }
     Ok(())
 }
 
-fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Cells by group label in label order; cells without a label go to
+// `unlabeled`.
+fn group_cells(
+    labels: Option<&[String]>,
+    n_cells: usize,
+    unlabeled: &str,
+) -> BTreeMap<String, Vec<usize>> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for cell in 0..n_cells {
+        let key = labels
+            .and_then(|v| v.get(cell))
+            .map(String::as_str)
+            .filter(|label| !label.is_empty())
+            .unwrap_or(unlabeled);
+        groups.entry(key.to_string()).or_default().push(cell);
+    }
+    groups
+}
+
+// One row of aggregates per group, keyed by the `key_name` column.
+fn write_group_tsv(
+    input: &Stage7Input<'_>,
+    path: &Path,
+    key_name: &str,
+    groups: BTreeMap<String, Vec<usize>>,
+) -> std::io::Result<()> {
     let mut w = BufWriter::new(File::create(path)?);
 
     let regime_names = regime_names();
 
     let mut header = String::new();
-    header.push_str("sample\tn_cells\t");
+    header.push_str(key_name);
+    header.push_str("\tn_cells\t");
     for name in [
         "a1_tbi", "a2_rci", "a3_pds", "a4_trs", "a5_nsai", "a6_iaa", "a7_dfa", "a8_cea", "c1_nps",
         "c2_ci", "c3_rls", "rss", "drbi", "cci", "trci",
     ] {
-        header.push_str(name);
-        header.push_str("_median\t");
-        header.push_str(name);
-        header.push_str("_p90\t");
-        header.push_str(name);
-        header.push_str("_p99\t");
+        for suffix in SAMPLE_STAT_SUFFIXES {
+            header.push_str(name);
+            header.push('_');
+            header.push_str(suffix);
+            header.push('\t');
+        }
     }
     header.push_str("regime_majority\t");
     for name in regime_names {
@@ -304,22 +837,23 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
         header.push_str(name);
         header.push('\t');
     }
-    header.push_str("trs_ge_0_75\tnps_ge_0_60\trls_le_0_35");
+    for name in tail_column_names(input.thresholds) {
+        header.push_str(&name);
+        header.push('\t');
+    }
+    header.push_str("low_confidence_fraction\tqc_pass");
+    let percentiles = axis_percentiles(input);
+    if percentiles.is_some() {
+        for name in AXIS_PERCENTILE_COLUMNS {
+            header.push('\t');
+            header.push_str(name);
+            header.push_str("_median");
+        }
+    }
 
     writeln!(w, "{}", header)?;
 
-    let mut sample_map: BTreeMap<String, Vec<usize>> = BTreeMap::new();
-    let n_cells = input.barcodes.len();
-    for cell in 0..n_cells {
-        let key = input
-            .sample
-            .and_then(|v| v.get(cell))
-            .cloned()
-            .unwrap_or_default();
-        sample_map.entry(key).or_default().push(cell);
-    }
-
-    for (sample, idxs) in sample_map {
+    for (group, idxs) in groups {
         let n = idxs.len();
         let mut a1 = Vec::with_capacity(n);
         let mut a2 = Vec::with_capacity(n);
@@ -340,6 +874,7 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
         let mut trs_tail = 0usize;
         let mut nps_tail = 0usize;
         let mut rls_tail = 0usize;
+        let mut low_confidence = 0usize;
 
         let mut regime_counts: BTreeMap<&'static str, usize> = BTreeMap::new();
 
@@ -360,16 +895,23 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
             d3.push(input.ddr_cci[cell]);
             d4.push(input.ddr_trci[cell]);
 
-            if input.axes_trs[cell] >= 0.75 {
+            if input.axes_trs[cell] >= input.thresholds.tail_trs_min {
                 trs_tail += 1;
             }
-            if input.scores.nps[cell] >= 0.60 {
+            if input.scores.nps[cell] >= input.thresholds.tail_nps_min {
                 nps_tail += 1;
             }
-            if input.scores.rls[cell] <= 0.35 {
+            if input.scores.rls[cell] <= input.thresholds.tail_rls_max {
                 rls_tail += 1;
             }
 
+            if input.classifications[cell]
+                .flags
+                .contains(&Flag::LowConfidence)
+            {
+                low_confidence += 1;
+            }
+
             let r = regime_name(input.classifications[cell].regime);
             *regime_counts.entry(r).or_insert(0) += 1;
         }
@@ -377,34 +919,18 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
         let majority = majority_regime(&regime_counts, regime_names);
 
         let mut line = String::new();
-        line.push_str(&sample);
+        line.push_str(&group);
         line.push('\t');
         line.push_str(&n.to_string());
         line.push('\t');
 
         for v in [
-            stats(&a1),
-            stats(&a2),
-            stats(&a3),
-            stats(&a4),
-            stats(&a5),
-            stats(&a6),
-            stats(&a7),
-            stats(&a8),
-            stats(&c1),
-            stats(&c2),
-            stats(&c3),
-            stats(&d1),
-            stats(&d2),
-            stats(&d3),
-            stats(&d4),
+            &a1, &a2, &a3, &a4, &a5, &a6, &a7, &a8, &c1, &c2, &c3, &d1, &d2, &d3, &d4,
         ] {
-            line.push_str(&format_f32_6(v.0));
-            line.push('\t');
-            line.push_str(&format_f32_6(v.1));
-            line.push('\t');
-            line.push_str(&format_f32_6(v.2));
-            line.push('\t');
+            for x in stats(v, input.thresholds) {
+                line.push_str(&format_f32_6(x));
+                line.push('\t');
+            }
         }
 
         line.push_str(majority);
@@ -421,6 +947,21 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
         line.push_str(&format_f32_6(nps_tail as f32 / n as f32));
         line.push('\t');
         line.push_str(&format_f32_6(rls_tail as f32 / n as f32));
+        line.push('\t');
+        let low_confidence_fraction = low_confidence as f32 / n as f32;
+        line.push_str(&format_f32_6(low_confidence_fraction));
+        line.push('\t');
+        line.push_str(&input.qc_gates.pass(n, low_confidence_fraction).to_string());
+        if let Some(percentiles) = &percentiles {
+            for ranks in percentiles {
+                let group = idxs.iter().map(|&cell| ranks[cell]).collect::<Vec<_>>();
+                line.push('\t');
+                line.push_str(&format_f32_6(median(
+                    &group,
+                    input.thresholds.quantile_method,
+                )));
+            }
+        }
 
         writeln!(w, "{}", line)?;
     }
@@ -429,10 +970,11 @@ fn write_sample_tsv(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()>
 }
 
 fn write_panels_report(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<()> {
+    let method = input.thresholds.quantile_method;
     let mut w = BufWriter::new(File::create(path)?);
     writeln!(
         w,
-        "panel_id\tpanel_name\tpanel_group\tpanel_size_defined\tpanel_size_mappable\tmissing_genes\tcoverage_median\tcoverage_p10\tsum_median\tsum_p90\tsum_p99"
+        "panel_id\tpanel_name\tpanel_group\tpanel_size_defined\tpanel_size_mappable\tmissing_genes\tcoverage_median\tcoverage_p10\tsum_median\tsum_p90\tsum_p99\traw_sum_median\tweighted\trules\tspecies\tmappable_fraction"
     )?;
 
     let n_cells = input.barcodes.len();
@@ -448,9 +990,11 @@ fn write_panels_report(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<
 
         let mut coverage = Vec::with_capacity(n_cells);
         let mut sums = Vec::with_capacity(n_cells);
+        let mut raw_sums = Vec::with_capacity(n_cells);
         for cell in 0..n_cells {
             coverage.push(input.panel_scores.panel_coverage[cell][panel_idx]);
             sums.push(input.panel_scores.panel_sum[cell][panel_idx]);
+            raw_sums.push(input.panel_scores.panel_raw_sum[cell][panel_idx]);
         }
 
         let missing = audit
@@ -459,33 +1003,53 @@ fn write_panels_report(input: &Stage7Input<'_>, path: &Path) -> std::io::Result<
             .unwrap_or_default();
         let size_defined = audit.as_ref().map(|a| a.panel_size_defined).unwrap_or(0);
         let size_mappable = audit.as_ref().map(|a| a.panel_size_mappable).unwrap_or(0);
+        let mappable_fraction = if size_defined > 0 {
+            size_mappable as f32 / size_defined as f32
+        } else {
+            0.0
+        };
+        let rules = audit
+            .as_ref()
+            .map(|a| a.rules.join(","))
+            .unwrap_or_default();
 
+        let [coverage_median, coverage_p10] = quantiles(&coverage, &[0.5, 0.10], method);
+        let [sum_median, sum_p90, sum_p99] = quantiles(&sums, &[0.5, 0.90, 0.99], method);
         writeln!(
             w,
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
             panel.id,
             panel.name,
-            panel_group_name(panel.group),
+            panel.group.as_str(),
             size_defined,
             size_mappable,
             missing,
-            format_f32_6(median(&coverage)),
-            format_f32_6(p10(&coverage)),
-            format_f32_6(median(&sums)),
-            format_f32_6(p90(&sums)),
-            format_f32_6(p99(&sums)),
+            format_f32_6(coverage_median),
+            format_f32_6(coverage_p10),
+            format_f32_6(sum_median),
+            format_f32_6(sum_p90),
+            format_f32_6(sum_p99),
+            format_f32_6(median(&raw_sums, method)),
+            panel.is_weighted(),
+            rules,
+            input.species_global,
+            format_f32_6(mappable_fraction),
         )?;
     }
 
     Ok(())
 }
 
-fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
+/// The data behind `summary.json`.
+pub fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
     let n_cells = input.barcodes.len();
+    let method = input.thresholds.quantile_method;
 
-    let confidence = input.scores.confidence.to_vec();
-    let confidence_median = median(&confidence);
-    let confidence_p10 = p10(&confidence);
+    let [confidence_median, confidence_p10] =
+        quantiles(&input.scores.confidence, &[0.5, 0.10], method);
+    let [pct_mito_median, pct_mito_p90] = quantiles(input.pct_mito, &[0.5, 0.90], method);
+    let [pct_ribo_median, pct_ribo_p90] = quantiles(input.pct_ribo, &[0.5, 0.90], method);
+    let [pct_control_median, pct_control_p90] = quantiles(input.pct_control, &[0.5, 0.90], method);
 
     let low_conf = input
         .classifications
@@ -497,28 +1061,42 @@ fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
         .iter()
         .map(|c| c.flags.contains(&Flag::LowExprGenes))
         .collect::<Vec<_>>();
+    let high_mito = input
+        .classifications
+        .iter()
+        .map(|c| c.flags.contains(&Flag::HighMitoFraction))
+        .collect::<Vec<_>>();
+    let high_ribo = input
+        .classifications
+        .iter()
+        .map(|c| c.flags.contains(&Flag::HighRiboFraction))
+        .collect::<Vec<_>>();
 
     let axes = vec![
-        named_stats("a1_tbi", input.axes_tbi),
-        named_stats("a2_rci", input.axes_rci),
-        named_stats("a3_pds", input.axes_pds),
-        named_stats("a4_trs", input.axes_trs),
-        named_stats("a5_nsai", input.axes_nsai),
-        named_stats("a6_iaa", input.axes_iaa),
-        named_stats("a7_dfa", input.axes_dfa),
-        named_stats("a8_cea", input.axes_cea),
+        named_stats("a1_tbi", input.axes_tbi, method),
+        named_stats("a2_rci", input.axes_rci, method),
+        named_stats("a3_pds", input.axes_pds, method),
+        named_stats("a4_trs", input.axes_trs, method),
+        named_stats("a5_nsai", input.axes_nsai, method),
+        named_stats("a6_iaa", input.axes_iaa, method),
+        named_stats("a7_dfa", input.axes_dfa, method),
+        named_stats("a8_cea", input.axes_cea, method),
     ];
-    let composites = vec![
-        named_stats("c1_nps", &input.scores.nps),
-        named_stats("c2_ci", &input.scores.ci),
-        named_stats("c3_rls", &input.scores.rls),
+    let mut composites = vec![
+        named_stats("c1_nps", &input.scores.nps, method),
+        named_stats("c2_ci", &input.scores.ci, method),
+        named_stats("c3_rls", &input.scores.rls, method),
     ];
+    if let Some((_, values)) = &input.custom_composite {
+        composites.push(named_stats("c4_custom", values, method));
+    }
 
     let regimes = regime_stats(input.classifications, n_cells);
 
-    let trs_ge_0_75 = fraction_threshold(input.axes_trs, |v| v >= 0.75);
-    let nps_ge_0_60 = fraction_threshold(&input.scores.nps, |v| v >= 0.60);
-    let rls_le_0_35 = fraction_threshold(&input.scores.rls, |v| v <= 0.35);
+    let thresholds = input.thresholds;
+    let trs_tail_fraction = fraction_threshold(input.axes_trs, |v| v >= thresholds.tail_trs_min);
+    let nps_tail_fraction = fraction_threshold(&input.scores.nps, |v| v >= thresholds.tail_nps_min);
+    let rls_tail_fraction = fraction_threshold(&input.scores.rls, |v| v <= thresholds.tail_rls_max);
 
     let missing_genes_by_panel = input
         .panel_audits
@@ -532,6 +1110,7 @@ fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
         input.genome_stability,
         input.genome_stability_norm,
         input.cluster_labels,
+        method,
     );
 
     SummaryData {
@@ -539,6 +1118,14 @@ fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
         tool_version: input.tool_version.clone(),
         git_hash: input.git_hash.clone(),
         simd_backend: input.simd_backend.clone(),
+        seed: input.seed,
+        stage_seconds: input.stage_seconds.clone(),
+        peak_rss_bytes: input.peak_rss_bytes,
+        estimated_peak_rss_bytes: crate::memory::estimate_peak_rss_bytes(
+            input.barcodes.len(),
+            input.n_genes_raw,
+            input.nnz.iter().map(|&n| n as u64).sum(),
+        ),
         run_mode: input
             .pipeline_context
             .as_ref()
@@ -547,12 +1134,16 @@ fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
         resolution: match mode {
             ReportMode::Cell => "cell".to_string(),
             ReportMode::Sample => "sample".to_string(),
+            ReportMode::Cluster => "cluster".to_string(),
         },
 
         n_cells,
+        sampled_from: input.sampled_from,
         n_genes_raw: input.n_genes_raw,
+        n_control_features: input.n_control_features,
         n_genes_mappable: input.n_genes_mappable,
         species: input.species_global.clone(),
+        species_call: input.species_call,
 
         normalize: input.normalize,
         scale: input.scale,
@@ -560,34 +1151,151 @@ fn build_summary(input: &Stage7Input<'_>, mode: ReportMode) -> SummaryData {
         axis_activation_mode: input.activation_mode.clone(),
         confidence_breakdown: input
             .confidence_breakdown
-            .map(|v| confidence_breakdown_median(v)),
+            .map(|v| confidence_breakdown_median(v, method)),
         scoring_mode: input.scoring_mode.clone(),
+        profile: input.profile.clone(),
 
         confidence_median,
         confidence_p10,
         low_confidence_fraction: bool_fraction(&low_conf),
         low_expr_fraction: bool_fraction(&low_expr),
+        pct_mito_median,
+        pct_mito_p90,
+        high_mito_fraction: bool_fraction(&high_mito),
+        pct_ribo_median,
+        pct_ribo_p90,
+        high_ribo_fraction: bool_fraction(&high_ribo),
+        pct_control_median,
+        pct_control_p90,
+
+        expr_min: input.thresholds.expr_min,
+        min_expr_genes: input.thresholds.min_expr_genes,
+        tf_min_sum: input.thresholds.tf_min_sum,
+        program_min_sum: input.thresholds.program_min_sum,
+        rel_p70: input.thresholds.rel_p70,
+        rel_p85: input.thresholds.rel_p85,
+        confidence_low: input.thresholds.confidence_low,
+        trs_weights: [
+            input.thresholds.trs_a,
+            input.thresholds.trs_b,
+            input.thresholds.trs_c,
+        ],
+        mito_frac_max: input.thresholds.mito_frac_max,
+        ribo_frac_max: input.thresholds.ribo_frac_max,
+        panel_coverage_min: input.thresholds.panel_coverage_min,
+        confidence_weights: input.thresholds.confidence_weights(),
+        confidence_coverage_scale: input.thresholds.confidence_coverage_scale,
+        confidence_axis_variance_scale: input.thresholds.confidence_axis_variance_scale,
+        cea_ribo_adjust: input.thresholds.cea_ribo_adjust,
+        cea_ribo_slope: input.cea_ribo_slope,
+        sum_mode: input.thresholds.sum_mode,
+        relative_within: input.thresholds.relative_within,
+        relative_min_group_cells: input.thresholds.relative_min_group_cells,
+        quantile_method: method,
+        key_panels: input.thresholds.key_panels.clone(),
+        panel_score: input.thresholds.panel_score,
+        entropy_unit: input.entropy_unit,
+        winsorize_panels: input.thresholds.winsorize_panels.zip(input.panel_caps).map(
+            |(quantile, caps)| {
+                let caps = input
+                    .panel_set
+                    .panels
+                    .iter()
+                    .zip(caps)
+                    .map(|(panel, &cap)| (panel.id.clone(), cap))
+                    .collect();
+                (quantile, caps)
+            },
+        ),
+        qc_gates: input.qc_gates,
 
         axes,
         ddr_metrics: vec![
-            named_stats("rss", input.ddr_rss),
-            named_stats("drbi", input.ddr_drbi),
-            named_stats("cci", input.ddr_cci),
-            named_stats("trci", input.ddr_trci),
+            named_stats("rss", input.ddr_rss, method),
+            named_stats("drbi", input.ddr_drbi, method),
+            named_stats("cci", input.ddr_cci, method),
+            named_stats("trci", input.ddr_trci, method),
         ],
         composites,
+        custom_composite: input
+            .custom_composite
+            .as_ref()
+            .map(|(expr, _)| expr.clone()),
         regimes,
 
-        trs_ge_0_75,
-        nps_ge_0_60,
-        rls_le_0_35,
+        trs_tail_min: thresholds.tail_trs_min,
+        trs_tail_fraction,
+        nps_tail_min: thresholds.tail_nps_min,
+        nps_tail_fraction,
+        rls_tail_max: thresholds.tail_rls_max,
+        rls_tail_fraction,
 
         missing_genes_by_panel,
+        excluded_panels: input.excluded_panels.to_vec(),
+        metadata_columns: input.metadata_columns.to_vec(),
+        clusters: cluster_stats(input),
+        contrasts: input.contrast.map(|design| {
+            let mut metrics = vec![
+                ("a1_tbi", input.axes_tbi),
+                ("a2_rci", input.axes_rci),
+                ("a3_pds", input.axes_pds),
+                ("a4_trs", input.axes_trs),
+                ("a5_nsai", input.axes_nsai),
+                ("a6_iaa", input.axes_iaa),
+                ("a7_dfa", input.axes_dfa),
+                ("a8_cea", input.axes_cea),
+                ("c1_nps", &input.scores.nps[..]),
+                ("c2_ci", &input.scores.ci[..]),
+                ("c3_rls", &input.scores.rls[..]),
+            ];
+            if let Some((_, values)) = &input.custom_composite {
+                metrics.push(("c4_custom", values));
+            }
+            ContrastSummary {
+                column: design.column.clone(),
+                reference: design.reference.clone(),
+                results: contrast_metrics(design, &metrics, method),
+            }
+        }),
+        unclassified_blockers: unclassified_blockers(input.classifications),
         rls_contributors_top,
         genome_stability,
     }
 }
 
+fn cluster_stats(input: &Stage7Input<'_>) -> Vec<ClusterStat> {
+    let Some(labels) = input.cluster_labels else {
+        return Vec::new();
+    };
+    let n_cells = input.barcodes.len();
+    group_cells(Some(labels), n_cells, UNASSIGNED_CLUSTER)
+        .into_iter()
+        .map(|(cluster, idxs)| {
+            let mut regime_counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+            for &cell in &idxs {
+                *regime_counts
+                    .entry(regime_name(input.classifications[cell].regime))
+                    .or_insert(0) += 1;
+            }
+            let group_median = |values: &[f32]| {
+                median(
+                    &idxs.iter().map(|&cell| values[cell]).collect::<Vec<_>>(),
+                    input.thresholds.quantile_method,
+                )
+            };
+            ClusterStat {
+                n_cells: idxs.len(),
+                fraction: idxs.len() as f32 / n_cells as f32,
+                dominant_regime: majority_regime(&regime_counts, regime_names()),
+                nps_median: group_median(&input.scores.nps),
+                ci_median: group_median(&input.scores.ci),
+                rls_median: group_median(&input.scores.rls),
+                cluster,
+            }
+        })
+        .collect()
+}
+
 fn top_rls_contributors(input: &Stage7Input<'_>) -> Vec<String> {
     let mut counts: BTreeMap<String, usize> = BTreeMap::new();
     for drivers in &input.drivers.rls {
@@ -603,7 +1311,7 @@ fn top_rls_contributors(input: &Stage7Input<'_>) -> Vec<String> {
     items.into_iter().take(3).map(|(n, _)| n).collect()
 }
 
-fn confidence_breakdown_median(values: &[[f32; 4]]) -> [f32; 4] {
+fn confidence_breakdown_median(values: &[[f32; 4]], method: QuantileMethod) -> [f32; 4] {
     if values.is_empty() {
         return [0.0, 0.0, 0.0, 0.0];
     }
@@ -611,10 +1319,16 @@ fn confidence_breakdown_median(values: &[[f32; 4]]) -> [f32; 4] {
     let b = values.iter().map(|v| v[1]).collect::<Vec<_>>();
     let c = values.iter().map(|v| v[2]).collect::<Vec<_>>();
     let d = values.iter().map(|v| v[3]).collect::<Vec<_>>();
-    [median(&a), median(&b), median(&c), median(&d)]
+    [
+        median(&a, method),
+        median(&b, method),
+        median(&c, method),
+        median(&d, method),
+    ]
 }
 
 fn build_report_context(input: &Stage7Input<'_>, summary: &SummaryData) -> ReportContext {
+    let method = input.thresholds.quantile_method;
     let ambient = input
         .classifications
         .iter()
@@ -629,10 +1343,10 @@ fn build_report_context(input: &Stage7Input<'_>, summary: &SummaryData) -> Repor
     ReportContext {
         n_cells: input.barcodes.len(),
         regimes: summary.regimes.clone(),
-        nps_median: median(&input.scores.nps),
-        ci_median: median(&input.scores.ci),
-        nsai_median: median(input.axes_nsai),
-        rls_median: median(&input.scores.rls),
+        nps_median: median(&input.scores.nps, method),
+        ci_median: median(&input.scores.ci, method),
+        nsai_median: median(input.axes_nsai, method),
+        rls_median: median(&input.scores.rls, method),
         low_confidence_fraction: summary.low_confidence_fraction,
         low_expr_fraction: summary.low_expr_fraction,
         ambient_rna_fraction: bool_fraction(&ambient),
@@ -640,34 +1354,43 @@ fn build_report_context(input: &Stage7Input<'_>, summary: &SummaryData) -> Repor
         immune_note: input.activation_mode != "Absolute",
         confidence_breakdown: summary.confidence_breakdown,
         rls_contributors_top: summary.rls_contributors_top.clone(),
-        rls_tail_fraction: summary.rls_le_0_35,
+        rls_tail_fraction: summary.rls_tail_fraction,
         immune_tail_note: immune_tail_note(input),
+        unclassified_blockers: summary.unclassified_blockers.clone(),
+        contrasts: summary.contrasts.clone(),
         scoring_mode: summary.scoring_mode.clone(),
         axis_activation_mode: summary.axis_activation_mode.clone(),
-        confidence_model: if summary.scoring_mode.contains("strict") {
-            "legacy multiplicative".to_string()
-        } else {
-            "immune-calibrated additive".to_string()
-        },
+        confidence_model: input.confidence_model.clone(),
+        profile: input.profile.clone(),
     }
 }
 
-fn render_pipeline_step_json(summary: &SummaryData) -> String {
+/// Version of the `pipeline_step.json` layout; bumped when keys are added.
+const PIPELINE_STEP_SCHEMA_VERSION: u32 = 3;
+
+fn render_pipeline_step_json(
+    summary: &SummaryData,
+    ctx: &PipelineContext,
+    warnings: usize,
+    failed: bool,
+    artifact: &dyn Fn(&str) -> String,
+    cell_metrics: &str,
+) -> String {
     let mut out = String::new();
     out.push('{');
     push_kv_str(&mut out, "tool", "kira-nuclearqc");
     out.push(',');
-    push_kv_str(&mut out, "mode", "pipeline");
+    push_kv_str(&mut out, "mode", &summary.run_mode);
     out.push(',');
 
     out.push_str("\"artifacts\":{");
-    push_kv_str(&mut out, "summary", "summary.json");
+    push_kv_str(&mut out, "summary", &artifact("summary.json"));
     out.push(',');
-    push_kv_str(&mut out, "primary_metrics", "nuclearqc.tsv");
+    push_kv_str(&mut out, "primary_metrics", cell_metrics);
     out.push_str("},");
 
     out.push_str("\"cell_metrics\":{");
-    push_kv_str(&mut out, "file", "nuclearqc.tsv");
+    push_kv_str(&mut out, "file", cell_metrics);
     out.push(',');
     push_kv_str(&mut out, "regime_column", "regime");
     out.push(',');
@@ -705,7 +1428,154 @@ fn render_pipeline_step_json(summary: &SummaryData) -> String {
         "low_confidence_fraction",
         summary.low_confidence_fraction as f64,
     );
-    out.push_str("}");
+    out.push_str("},");
+
+    out.push_str(&format!(
+        "\"schema_version\":{PIPELINE_STEP_SCHEMA_VERSION},\"summary_schema_version\":{SUMMARY_SCHEMA_VERSION},"
+    ));
+    push_kv_str(
+        &mut out,
+        "status",
+        match (failed, warnings) {
+            (true, _) => "failed_on_warnings",
+            (false, 0) => "ok",
+            (false, _) => "ok_with_warnings",
+        },
+    );
+    out.push(',');
+    out.push_str(&format!("\"warnings\":{warnings},"));
+    push_kv_str(&mut out, "tool_version", &summary.tool_version);
+    out.push(',');
+    push_kv_opt_str(&mut out, "git_hash", summary.git_hash.as_deref());
+    out.push(',');
+    out.push_str("\"input\":{");
+    push_kv_str(&mut out, "dir", &ctx.input_dir);
+    out.push(',');
+    push_kv_str(&mut out, "source", &ctx.input_source);
+    out.push(',');
+    push_kv_opt_str(&mut out, "shared_bin", ctx.shared_bin.as_deref());
+    out.push(',');
+    out.push_str("\"hashes\":[");
+    for (i, (path, hash)) in ctx.input_hashes.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "path", path);
+        out.push(',');
+        push_kv_str(&mut out, "fnv1a64", hash);
+        out.push('}');
+    }
+    out.push_str("]},");
+    out.push_str("\"timings_seconds\":{");
+    for (i, (stage, seconds)) in summary.stage_seconds.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_kv_num(&mut out, stage, *seconds);
+    }
+    out.push('}');
+
+    out.push('}');
+    out
+}
+
+fn render_provenance_json(input: &Stage7Input<'_>, provenance: &RunProvenance) -> String {
+    let thresholds = input.thresholds;
+    let mut out = String::new();
+    out.push('{');
+
+    out.push_str("\"tool\":{");
+    push_kv_str(&mut out, "name", &input.tool_name);
+    out.push(',');
+    push_kv_str(&mut out, "version", &input.tool_version);
+    out.push(',');
+    push_kv_opt_str(&mut out, "git_hash", input.git_hash.as_deref());
+    out.push(',');
+    push_kv_str(&mut out, "simd_backend", &input.simd_backend);
+    out.push_str("},");
+
+    out.push_str("\"args\":[");
+    for (i, arg) in provenance.args.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_str_val(&mut out, arg);
+    }
+    out.push_str("],");
+
+    out.push_str("\"config\":{");
+    push_kv_str(&mut out, "input_dir", &provenance.input_dir);
+    out.push(',');
+    push_kv_str(&mut out, "out_dir", &provenance.out_dir);
+    out.push(',');
+    push_kv_opt_str(&mut out, "meta", provenance.meta_path.as_deref());
+    out.push(',');
+    push_kv_opt_str(&mut out, "cache", provenance.cache_path.as_deref());
+    out.push(',');
+    push_kv_opt_str(&mut out, "panels", provenance.panels_path.as_deref());
+    out.push(',');
+    push_kv_str(&mut out, "run_mode", &provenance.run_mode);
+    out.push(',');
+    push_kv_str(&mut out, "report_mode", &provenance.report_mode);
+    out.push(',');
+    push_kv_str(&mut out, "scoring_mode", &provenance.scoring_mode);
+    out.push(',');
+    push_kv_str(&mut out, "confidence_model", &provenance.confidence_model);
+    out.push(',');
+    push_kv_str(
+        &mut out,
+        "thresholds_profile",
+        &provenance.thresholds_profile,
+    );
+    out.push(',');
+    push_kv_bool(&mut out, "normalize", input.normalize);
+    out.push(',');
+    push_kv_str(&mut out, "normalize_mode", &provenance.normalize_mode);
+    out.push(',');
+    push_kv_num(&mut out, "scale", input.scale as f64);
+    out.push(',');
+    push_kv_bool(&mut out, "log1p", input.log1p);
+    out.push(',');
+    push_kv_bool(&mut out, "cache_normalized", provenance.cache_normalized);
+    out.push(',');
+    push_kv_str(&mut out, "cache_codec", &provenance.cache_codec);
+    out.push(',');
+    push_kv_bool(&mut out, "assume_transposed", provenance.assume_transposed);
+    out.push(',');
+    push_kv_bool(&mut out, "cea_ribo_adjust", thresholds.cea_ribo_adjust);
+    out.push(',');
+    push_kv_str(&mut out, "sum_mode", thresholds.sum_mode.as_str());
+    out.push(',');
+    push_kv_str(
+        &mut out,
+        "relative_within",
+        thresholds.relative_within.as_str(),
+    );
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "relative_min_group_cells",
+        thresholds.relative_min_group_cells as f64,
+    );
+    out.push(',');
+    push_kv_str(&mut out, "panel_score", thresholds.panel_score.as_str());
+    out.push(',');
+    push_kv_str(
+        &mut out,
+        "quantile_method",
+        thresholds.quantile_method.as_str(),
+    );
+    out.push(',');
+    out.push_str(&format!("\"seed\":{},", input.seed));
+    out.push_str("\"key_panels\":[");
+    for (i, id) in thresholds.key_panels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_str_val(&mut out, id);
+    }
+    out.push_str("]}");
 
     out.push('}');
     out
@@ -747,6 +1617,24 @@ fn push_kv_num(out: &mut String, key: &str, value: f64) {
     let _ = std::fmt::Write::write_fmt(out, format_args!("{}", format_f32_6(value as f32)));
 }
 
+fn push_kv_bool(out: &mut String, key: &str, value: bool) {
+    out.push('"');
+    out.push_str(key);
+    out.push_str("\":");
+    out.push_str(if value { "true" } else { "false" });
+}
+
+fn push_kv_opt_str(out: &mut String, key: &str, value: Option<&str>) {
+    match value {
+        Some(v) => push_kv_str(out, key, v),
+        None => {
+            out.push('"');
+            out.push_str(key);
+            out.push_str("\":null");
+        }
+    }
+}
+
 fn push_str_val(out: &mut String, value: &str) {
     out.push('"');
     out.push_str(&escape_json(value));
@@ -769,9 +1657,10 @@ fn escape_json(input: &str) -> String {
 }
 
 fn immune_tail_note(input: &Stage7Input<'_>) -> bool {
-    let p90_iaa = p90(input.axes_iaa);
-    let p90_dfa = p90(input.axes_dfa);
-    let p90_cea = p90(input.axes_cea);
+    let method = input.thresholds.quantile_method;
+    let p90_iaa = p90(input.axes_iaa, method);
+    let p90_dfa = p90(input.axes_dfa, method);
+    let p90_cea = p90(input.axes_cea, method);
     p90_iaa >= 0.8 || p90_dfa >= 0.8 || p90_cea >= 0.8
 }
 
@@ -781,15 +1670,37 @@ fn write_text(path: &Path, contents: &str) -> std::io::Result<()> {
     Ok(())
 }
 
-fn named_stats(name: &'static str, values: &[f32]) -> NamedStats {
+fn named_stats(name: &'static str, values: &[f32], method: QuantileMethod) -> NamedStats {
+    let [median, p90, p99] = quantiles(values, &[0.5, 0.90, 0.99], method);
     NamedStats {
         name,
-        median: median(values),
-        p90: p90(values),
-        p99: p99(values),
+        median,
+        p90,
+        p99,
     }
 }
 
+/// `nuclearqc.tsv` sample-mode tail columns named after the configured
+/// cutoffs, e.g. `trs_ge_0_75`, `nps_ge_0_60`, `rls_le_0_35` by default.
+fn tail_column_names(thresholds: &ThresholdProfile) -> [String; 3] {
+    [
+        format!("trs_ge_{}", cutoff_label(thresholds.tail_trs_min)),
+        format!("nps_ge_{}", cutoff_label(thresholds.tail_nps_min)),
+        format!("rls_le_{}", cutoff_label(thresholds.tail_rls_max)),
+    ]
+}
+
+// Two decimals unless that would round the cutoff, with `.` as `_`.
+fn cutoff_label(value: f32) -> String {
+    let short = format!("{value:.2}");
+    let label = if short.parse::<f32>() == Ok(value) {
+        short
+    } else {
+        value.to_string()
+    };
+    label.replace('.', "_")
+}
+
 fn fraction_threshold(values: &[f32], predicate: impl Fn(f32) -> bool) -> f32 {
     if values.is_empty() {
         return 0.0;
@@ -803,7 +1714,8 @@ fn fraction_threshold(values: &[f32], predicate: impl Fn(f32) -> bool) -> f32 {
     count as f32 / values.len() as f32
 }
 
-fn format_flags(flags: &[Flag]) -> String {
+/// Flags in `flag_order`, comma-separated, as in the `flags` column.
+pub fn format_flags(flags: &[Flag]) -> String {
     let order = flag_order();
     let idx = Cell::new(0usize);
     let wrote_any = Cell::new(false);
@@ -844,22 +1756,29 @@ fn format_drivers(drivers: &[(String, f32)]) -> String {
     .to_string()
 }
 
+// Compares panels by `mode` (`--panel-score`), so with `mean` large panels do
+// not win on size alone. Ties go to the lexicographically smallest panel id,
+// so the pick does not depend on the order panels were defined in.
 fn top_program_panel(
     cell: usize,
     program_panels: &[usize],
     panel_set: &PanelSet,
     panel_scores: &PanelScores,
+    mode: PanelScoreMode,
 ) -> (String, f32) {
     if program_panels.is_empty() {
         return (String::new(), 0.0);
     }
+    let values = &panel_scores.values(mode)[cell];
     let mut sum = 0f64;
     let mut max = -1f64;
-    let mut max_idx = None;
+    let mut max_idx: Option<usize> = None;
     for &idx in program_panels {
-        let v = panel_scores.panel_sum[cell][idx] as f64;
+        let v = values[idx] as f64;
         sum += v;
-        if v > max {
+        let wins_tie = v == max
+            && max_idx.is_some_and(|best| panel_set.panels[idx].id < panel_set.panels[best].id);
+        if v > max || wins_tie {
             max = v;
             max_idx = Some(idx);
         }
@@ -868,10 +1787,34 @@ fn top_program_panel(
         return (String::new(), 0.0);
     }
     let idx = max_idx.unwrap();
-    let share = (panel_scores.panel_sum[cell][idx] as f64 / sum) as f32;
+    let share = (values[idx] as f64 / sum) as f32;
     (panel_set.panels[idx].id.to_string(), share)
 }
 
+// Shares of the cell's program total under `mode`, in `program_panels`
+// order; `None` when the total is not positive.
+fn program_shares(
+    cell: usize,
+    program_panels: &[usize],
+    panel_scores: &PanelScores,
+    mode: PanelScoreMode,
+) -> Option<Vec<f32>> {
+    let values = &panel_scores.values(mode)[cell];
+    let sum = program_panels
+        .iter()
+        .map(|&idx| values[idx] as f64)
+        .sum::<f64>();
+    if sum <= 0.0 {
+        return None;
+    }
+    Some(
+        program_panels
+            .iter()
+            .map(|&idx| (values[idx] as f64 / sum) as f32)
+            .collect(),
+    )
+}
+
 fn program_panel_indices(panel_set: &PanelSet) -> Vec<usize> {
     let mut out = Vec::new();
     for (idx, panel) in panel_set.panels.iter().enumerate() {
@@ -882,10 +1825,74 @@ fn program_panel_indices(panel_set: &PanelSet) -> Vec<usize> {
     out
 }
 
-fn stats(values: &[f32]) -> (f32, f32, f32) {
-    (median(values), p90(values), p99(values))
+/// Key panels present in the set, ordered by panel id so the column set does
+/// not depend on `--key-panels` order. Unknown ids are skipped.
+fn key_panel_indices(panel_set: &PanelSet, key_panels: &[String]) -> Vec<usize> {
+    let mut out = panel_set
+        .panels
+        .iter()
+        .enumerate()
+        .filter(|(_, panel)| key_panels.contains(&panel.id))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    out.sort_by(|&a, &b| panel_set.panels[a].id.cmp(&panel_set.panels[b].id));
+    out
+}
+
+// `--axis-percentiles` column names, one per axis A1-A8.
+const AXIS_PERCENTILE_COLUMNS: [&str; 8] = [
+    "a1_tbi_pct",
+    "a2_rci_pct",
+    "a3_pds_pct",
+    "a4_trs_pct",
+    "a5_nsai_pct",
+    "a6_iaa_pct",
+    "a7_dfa_pct",
+    "a8_cea_pct",
+];
+
+/// Percentile ranks of axes A1-A8 over all reported cells, in
+/// [`AXIS_PERCENTILE_COLUMNS`] order, when `--axis-percentiles` is set.
+fn axis_percentiles(input: &Stage7Input<'_>) -> Option<[Vec<f32>; 8]> {
+    input.axis_percentiles.then(|| {
+        [
+            input.axes_tbi,
+            input.axes_rci,
+            input.axes_pds,
+            input.axes_trs,
+            input.axes_nsai,
+            input.axes_iaa,
+            input.axes_dfa,
+            input.axes_cea,
+        ]
+        .map(percentile_ranks)
+    })
+}
+
+// Column suffixes of each axis in sample mode, in `stats` order.
+const SAMPLE_STAT_SUFFIXES: [&str; 8] =
+    ["median", "p90", "p99", "mean", "std", "p25", "p75", "iqr"];
+
+fn stats(values: &[f32], thresholds: &ThresholdProfile) -> [f32; 8] {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let q = |p: f32| quantile_sorted(&sorted, p, thresholds.quantile_method);
+    let (mean, var) = mean_var_f32(values, thresholds.sum_mode);
+    let (p25, p75) = (q(0.25), q(0.75));
+    [
+        q(0.5),
+        q(0.90),
+        q(0.99),
+        mean as f32,
+        var.sqrt() as f32,
+        p25,
+        p75,
+        p75 - p25,
+    ]
 }
 
+// Ties go to the regime listed first in `order` (the fixed `regime_names`
+// order), never to map iteration order.
 fn majority_regime<'a>(counts: &BTreeMap<&'a str, usize>, order: &'a [&'a str]) -> &'a str {
     let mut best = ("Unclassified", 0usize);
     for name in order {
@@ -924,7 +1931,40 @@ fn regime_stats(
     out
 }
 
-fn regime_names() -> &'static [&'static str] {
+/// Blocking conditions kept in `summary.json` and `report.txt`.
+const UNCLASSIFIED_TOP_BLOCKERS: usize = 5;
+
+fn unclassified_blockers(
+    classifications: &[crate::pipeline::stage6_classify::Classification],
+) -> Vec<BlockingStat> {
+    let mut counts: BTreeMap<(&'static str, String), usize> = BTreeMap::new();
+    let mut n_unclassified = 0usize;
+    for c in classifications {
+        if c.regime != NuclearRegime::Unclassified {
+            continue;
+        }
+        n_unclassified += 1;
+        if let Some(near_miss) = &c.near_miss {
+            let key = (regime_name(near_miss.regime), near_miss.blocked_by.rule());
+            *counts.entry(key).or_insert(0) += 1;
+        }
+    }
+    let mut out = counts
+        .into_iter()
+        .map(|((regime, condition), count)| BlockingStat {
+            regime,
+            condition,
+            count,
+            fraction: count as f32 / n_unclassified as f32,
+        })
+        .collect::<Vec<_>>();
+    // Stable sort keeps the (regime, condition) order among equal counts.
+    out.sort_by_key(|b| std::cmp::Reverse(b.count));
+    out.truncate(UNCLASSIFIED_TOP_BLOCKERS);
+    out
+}
+
+pub fn regime_names() -> &'static [&'static str] {
     &[
         "PlasticAdaptive",
         "StressAdaptive",
@@ -936,7 +1976,7 @@ fn regime_names() -> &'static [&'static str] {
     ]
 }
 
-fn regime_name(r: NuclearRegime) -> &'static str {
+pub fn regime_name(r: NuclearRegime) -> &'static str {
     match r {
         NuclearRegime::PlasticAdaptive => "PlasticAdaptive",
         NuclearRegime::StressAdaptive => "StressAdaptive",
@@ -957,6 +1997,8 @@ fn flag_name(flag: Flag) -> &'static str {
         Flag::HighStressBias => "HIGH_STRESS_BIAS",
         Flag::LowTfSignal => "LOW_TF_SIGNAL",
         Flag::AmbientRnaRisk => "AMBIENT_RNA_RISK",
+        Flag::HighMitoFraction => "HIGH_MITO_FRACTION",
+        Flag::HighRiboFraction => "HIGH_RIBO_FRACTION",
         Flag::CellCycleConfounder => "CELL_CYCLE_CONFOUNDER",
         Flag::LowConfidence => "LOW_CONFIDENCE",
         Flag::HighReplicationStress => "HIGH_REPLICATION_STRESS",
@@ -969,20 +2011,6 @@ fn flag_name(flag: Flag) -> &'static str {
     }
 }
 
-fn panel_group_name(group: crate::panels::defs::PanelGroup) -> &'static str {
-    use crate::panels::defs::PanelGroup;
-    match group {
-        PanelGroup::Housekeeping => "housekeeping",
-        PanelGroup::Tf => "tf",
-        PanelGroup::Chromatin => "chromatin",
-        PanelGroup::Stress => "stress",
-        PanelGroup::Developmental => "developmental",
-        PanelGroup::Proliferation => "proliferation",
-        PanelGroup::Program => "program",
-        PanelGroup::Confounder => "confounder",
-    }
-}
-
 #[cfg(test)]
 #[path = "../../tests/src_inline/pipeline/stage7_report.rs"]
 mod tests;
@@ -0,0 +1,160 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::pipeline::stage7_report::regime_names;
+use crate::report::json::{push_kv_num, push_kv_str, push_str_key};
+use crate::report::{NamedStats, SummaryData, format_f32_6};
+
+/// One dataset of a multi-input run.
+#[derive(Debug, Clone)]
+pub struct BatchSample {
+    pub name: String,
+    pub input_dir: PathBuf,
+    /// The dataset's summary, or why its run failed.
+    pub outcome: Result<SummaryData, String>,
+}
+
+const COMPOSITES: [&str; 3] = ["c1_nps", "c2_ci", "c3_rls"];
+
+const QC_COLUMNS: [&str; 4] = [
+    "confidence_median",
+    "low_confidence_fraction",
+    "low_expr_fraction",
+    "high_mito_fraction",
+];
+
+fn qc_values(summary: &SummaryData) -> [f32; 4] {
+    [
+        summary.confidence_median,
+        summary.low_confidence_fraction,
+        summary.low_expr_fraction,
+        summary.high_mito_fraction,
+    ]
+}
+
+fn composite_median(stats: &[NamedStats], name: &str) -> f32 {
+    stats
+        .iter()
+        .find(|s| s.name == name)
+        .map_or(0.0, |s| s.median)
+}
+
+fn regime_fraction(summary: &SummaryData, name: &str) -> f32 {
+    summary
+        .regimes
+        .iter()
+        .find(|r| r.name == name)
+        .map_or(0.0, |r| r.fraction)
+}
+
+/// `batch_summary.tsv`: one row per dataset in input order; failed datasets
+/// carry their error and leave the numeric columns empty.
+pub fn render_batch_tsv(samples: &[BatchSample]) -> String {
+    let mut out = String::from("sample\tinput\tstatus\terror\tn_cells");
+    for name in regime_names() {
+        let _ = write!(out, "\tregime_{name}");
+    }
+    for name in COMPOSITES {
+        let _ = write!(out, "\t{name}_median");
+    }
+    for name in QC_COLUMNS {
+        let _ = write!(out, "\t{name}");
+    }
+    out.push('\n');
+
+    let n_numeric = 1 + regime_names().len() + COMPOSITES.len() + QC_COLUMNS.len();
+    for sample in samples {
+        let _ = write!(out, "{}\t{}", sample.name, sample.input_dir.display());
+        match &sample.outcome {
+            Ok(summary) => {
+                let _ = write!(out, "\tok\t\t{}", summary.n_cells);
+                for name in regime_names() {
+                    out.push('\t');
+                    out.push_str(&format_f32_6(regime_fraction(summary, name)));
+                }
+                for name in COMPOSITES {
+                    out.push('\t');
+                    out.push_str(&format_f32_6(composite_median(&summary.composites, name)));
+                }
+                for value in qc_values(summary) {
+                    out.push('\t');
+                    out.push_str(&format_f32_6(value));
+                }
+            }
+            Err(err) => {
+                let err = err.replace(['\t', '\n', '\r'], " ");
+                let _ = write!(out, "\terror\t{err}");
+                for _ in 0..n_numeric {
+                    out.push('\t');
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `batch_summary.json`: the same per-dataset aggregates as the TSV.
+pub fn render_batch_json(samples: &[BatchSample]) -> String {
+    let mut out = String::from("{");
+    push_kv_str(&mut out, "tool", "kira-nuclearqc");
+    out.push(',');
+    push_str_key(&mut out, "n_samples");
+    let _ = write!(out, ":{},", samples.len());
+    push_str_key(&mut out, "n_failed");
+    let n_failed = samples.iter().filter(|s| s.outcome.is_err()).count();
+    let _ = write!(out, ":{n_failed},");
+    out.push_str("\"samples\":[");
+    for (i, sample) in samples.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "sample", &sample.name);
+        out.push(',');
+        push_kv_str(&mut out, "input", &sample.input_dir.display().to_string());
+        out.push(',');
+        match &sample.outcome {
+            Ok(summary) => {
+                push_kv_str(&mut out, "status", "ok");
+                out.push(',');
+                push_str_key(&mut out, "n_cells");
+                let _ = write!(out, ":{},", summary.n_cells);
+                out.push_str("\"regimes\":{");
+                for (j, name) in regime_names().iter().enumerate() {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    push_kv_num(&mut out, name, regime_fraction(summary, name) as f64);
+                }
+                out.push_str("},\"composites\":{");
+                for (j, name) in COMPOSITES.iter().enumerate() {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    push_kv_num(
+                        &mut out,
+                        &format!("{name}_median"),
+                        composite_median(&summary.composites, name) as f64,
+                    );
+                }
+                out.push_str("},\"qc\":{");
+                for (j, (name, value)) in QC_COLUMNS.iter().zip(qc_values(summary)).enumerate() {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    push_kv_num(&mut out, name, value as f64);
+                }
+                out.push('}');
+            }
+            Err(err) => {
+                push_kv_str(&mut out, "status", "error");
+                out.push(',');
+                push_kv_str(&mut out, "error", err);
+            }
+        }
+        out.push('}');
+    }
+    out.push_str("]}");
+    out
+}
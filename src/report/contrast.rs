@@ -0,0 +1,278 @@
+//! Per-cell contrasts between the levels of a metadata column (`--contrast`):
+//! difference in medians, Cliff's delta and a two-sided Mann-Whitney U test
+//! for each axis and composite, so a treated/control design can be read off
+//! `summary.json` without a trip through R.
+//!
+//! Cells are the observations; cells from the same sample are not
+//! independent, so p-values are a screening aid, not a replicate-level test.
+
+use std::collections::BTreeSet;
+
+use crate::input::meta::CellMeta;
+use crate::report::{QuantileMethod, median};
+
+/// Both groups up to this many cells and no ties: the p-value comes from the
+/// exact null distribution of U instead of the normal approximation.
+pub const EXACT_MAX_GROUP_CELLS: usize = 20;
+
+/// Cells split by the levels of one metadata column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastDesign {
+    pub column: String,
+    pub reference: String,
+    /// Levels compared against `reference`, in sorted order.
+    pub levels: Vec<String>,
+    /// Per cell, its index in `levels`, `levels.len()` for the reference, or
+    /// `None` when the value is empty or `NA`.
+    pub cell_level: Vec<Option<usize>>,
+}
+
+impl ContrastDesign {
+    /// Reads `column` (case-insensitive) from `meta`. With two levels the
+    /// reference defaults to the first in sorted order; with more it must be
+    /// given.
+    pub fn new(meta: &CellMeta, column: &str, reference: Option<&str>) -> Result<Self, String> {
+        let idx = meta
+            .columns
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column))
+            .ok_or_else(|| format!("--contrast {column}: no such metadata column"))?;
+        let values = meta
+            .rows
+            .iter()
+            .map(|row| row.get(idx).map(|v| v.trim()).unwrap_or(""))
+            .map(|v| (!v.is_empty() && v != "NA").then_some(v))
+            .collect::<Vec<_>>();
+        let mut levels = values
+            .iter()
+            .flatten()
+            .map(|v| v.to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if levels.len() < 2 {
+            return Err(format!(
+                "--contrast {column}: needs at least two levels, found {}",
+                levels.len()
+            ));
+        }
+        let reference = match reference {
+            Some(reference) => reference.to_string(),
+            None if levels.len() == 2 => levels[0].clone(),
+            None => {
+                return Err(format!(
+                    "--contrast {column} has {} levels; choose one with --contrast-reference",
+                    levels.len()
+                ));
+            }
+        };
+        let ref_pos = levels
+            .iter()
+            .position(|level| *level == reference)
+            .ok_or_else(|| {
+                format!(
+                    "--contrast-reference {reference} is not a level of {column} (levels: {})",
+                    levels.join(", ")
+                )
+            })?;
+        levels.remove(ref_pos);
+        let cell_level = values
+            .iter()
+            .map(|value| {
+                value.map(|v| {
+                    levels
+                        .iter()
+                        .position(|level| level == v)
+                        .unwrap_or(levels.len())
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            column: meta.columns[idx].clone(),
+            reference,
+            levels,
+            cell_level,
+        })
+    }
+}
+
+/// The `contrasts` block of `summary.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastSummary {
+    pub column: String,
+    pub reference: String,
+    pub results: Vec<MetricContrast>,
+}
+
+/// One metric compared between one level and the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricContrast {
+    pub metric: &'static str,
+    pub level: String,
+    pub n_level: usize,
+    pub n_reference: usize,
+    /// Median of the level minus median of the reference.
+    pub median_diff: f32,
+    pub test: MannWhitney,
+}
+
+/// Two-sided Mann-Whitney U test of `a` against `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitney {
+    /// Pairs with the `a` value larger, ties counting one half.
+    pub u: f64,
+    /// `P(a > b) - P(a < b)` over all pairs, equal to the rank-biserial
+    /// correlation; 0 when a group is empty.
+    pub cliffs_delta: f64,
+    pub p_value: f64,
+    /// Whether `p_value` is exact rather than from the normal approximation.
+    pub exact: bool,
+}
+
+/// Every metric for every level against the reference, levels in design
+/// order and metrics in the given order within each level.
+pub fn contrast_metrics(
+    design: &ContrastDesign,
+    metrics: &[(&'static str, &[f32])],
+    method: QuantileMethod,
+) -> Vec<MetricContrast> {
+    let reference = design.levels.len();
+    let group_values = |values: &[f32], level: usize| {
+        design
+            .cell_level
+            .iter()
+            .zip(values)
+            .filter(|(cell_level, _)| **cell_level == Some(level))
+            .map(|(_, &v)| v)
+            .collect::<Vec<_>>()
+    };
+    let mut out = Vec::with_capacity(design.levels.len() * metrics.len());
+    for (level, name) in design.levels.iter().enumerate() {
+        for &(metric, values) in metrics {
+            let a = group_values(values, level);
+            let b = group_values(values, reference);
+            out.push(MetricContrast {
+                metric,
+                level: name.clone(),
+                n_level: a.len(),
+                n_reference: b.len(),
+                median_diff: median(&a, method) - median(&b, method),
+                test: mann_whitney(&a, &b),
+            });
+        }
+    }
+    out
+}
+
+/// Ranks the pooled values with midranks for ties. The p-value is exact when
+/// both groups have at most [`EXACT_MAX_GROUP_CELLS`] cells and no value is
+/// tied; otherwise it uses the normal approximation with tie-corrected
+/// variance and a continuity correction.
+pub fn mann_whitney(a: &[f32], b: &[f32]) -> MannWhitney {
+    let (n1, n2) = (a.len(), b.len());
+    if n1 == 0 || n2 == 0 {
+        return MannWhitney {
+            u: 0.0,
+            cliffs_delta: 0.0,
+            p_value: 1.0,
+            exact: false,
+        };
+    }
+
+    let mut pooled = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect::<Vec<_>>();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let n = pooled.len();
+    let mut rank_sum_a = 0.0f64;
+    // Sum of t^3 - t over tie groups of size t.
+    let mut tie_term = 0.0f64;
+    let mut start = 0;
+    while start < n {
+        let mut end = start + 1;
+        while end < n && pooled[end].0 == pooled[start].0 {
+            end += 1;
+        }
+        let rank = (start + 1 + end) as f64 / 2.0;
+        rank_sum_a += rank * pooled[start..end].iter().filter(|x| x.1).count() as f64;
+        let t = (end - start) as f64;
+        tie_term += t * t * t - t;
+        start = end;
+    }
+
+    let (n1f, n2f) = (n1 as f64, n2 as f64);
+    let u = rank_sum_a - n1f * (n1f + 1.0) / 2.0;
+    let pairs = n1f * n2f;
+    let cliffs_delta = 2.0 * u / pairs - 1.0;
+    let u_max = u.max(pairs - u);
+
+    let exact = tie_term == 0.0 && n1 <= EXACT_MAX_GROUP_CELLS && n2 <= EXACT_MAX_GROUP_CELLS;
+    let p_value = if exact {
+        let counts = u_distribution(n1, n2);
+        let total = counts.iter().sum::<u64>() as f64;
+        let upper = counts[u_max as usize..].iter().sum::<u64>() as f64;
+        (2.0 * upper / total).min(1.0)
+    } else {
+        let nf = n as f64;
+        let variance = pairs / 12.0 * ((nf + 1.0) - tie_term / (nf * (nf - 1.0)));
+        if variance <= 0.0 {
+            1.0
+        } else {
+            let z = ((u_max - pairs / 2.0 - 0.5) / variance.sqrt()).max(0.0);
+            erfc(z / std::f64::consts::SQRT_2).min(1.0)
+        }
+    };
+
+    MannWhitney {
+        u,
+        cliffs_delta,
+        p_value,
+        exact,
+    }
+}
+
+// Number of orderings of `n1` and `n2` untied values giving each U in
+// 0..=n1*n2, from f(m, n, u) = f(m - 1, n, u - n) + f(m, n - 1, u).
+fn u_distribution(n1: usize, n2: usize) -> Vec<u64> {
+    // `prev[n]` holds f(m - 1, n, .) while row m is built.
+    let mut prev = (0..=n2).map(|_| vec![1u64]).collect::<Vec<_>>();
+    for m in 1..=n1 {
+        let mut row: Vec<Vec<u64>> = vec![vec![1u64]];
+        for n in 1..=n2 {
+            let mut counts = vec![0u64; m * n + 1];
+            for (u, &c) in prev[n].iter().enumerate() {
+                counts[u + n] += c;
+            }
+            for (u, &c) in row[n - 1].iter().enumerate() {
+                counts[u] += c;
+            }
+            row.push(counts);
+        }
+        prev = row;
+    }
+    prev.swap_remove(n2)
+}
+
+// Complementary error function (Numerical Recipes `erfcc`), relative error
+// below 1.2e-7, which is ample for a reported p-value.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let r = t * poly.exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/contrast.rs"]
+mod tests;
@@ -1,14 +1,19 @@
 use std::fmt::Write;
 
+use crate::report::metadata::MetaColumnKind;
 use crate::report::{SummaryData, format_f32_6};
 
+/// Version of the `summary.json` layout, written as `schema_version`; bumped
+/// whenever keys are added, renamed or removed.
+pub const SUMMARY_SCHEMA_VERSION: u32 = 8;
+
 pub fn render_summary_json(data: &SummaryData) -> String {
     let mut out = String::new();
     out.push('{');
 
     // Aggregator contract keys.
     push_kv_str(&mut out, "tool", "kira-nuclearqc");
-    out.push(',');
+    out.push_str(&format!(",\"schema_version\":{SUMMARY_SCHEMA_VERSION},"));
     out.push_str("\"input\":{");
     push_kv_str(&mut out, "mode", &data.run_mode);
     out.push(',');
@@ -16,14 +21,45 @@ pub fn render_summary_json(data: &SummaryData) -> String {
     out.push(',');
     push_kv_num(&mut out, "n_cells", data.n_cells as f64);
     out.push(',');
+    push_kv_bool(&mut out, "sampled", data.sampled_from.is_some());
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "n_cells_total",
+        data.sampled_from.unwrap_or(data.n_cells) as f64,
+    );
+    out.push(',');
     push_kv_num(&mut out, "n_genes_raw", data.n_genes_raw as f64);
     out.push(',');
     push_kv_num(&mut out, "n_genes_mappable", data.n_genes_mappable as f64);
     out.push(',');
+    push_kv_num(
+        &mut out,
+        "n_control_features",
+        data.n_control_features as f64,
+    );
+    out.push(',');
     push_kv_str(&mut out, "species", &data.species);
     out.push(',');
+    out.push_str("\"species_call\":{");
+    push_kv_num(
+        &mut out,
+        "human_score",
+        data.species_call.human_score as f64,
+    );
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "mouse_score",
+        data.species_call.mouse_score as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "confidence", data.species_call.confidence as f64);
+    out.push_str("},");
     push_kv_str(&mut out, "scoring_mode", &data.scoring_mode);
     out.push(',');
+    push_kv_str(&mut out, "profile", &data.profile);
+    out.push(',');
     out.push_str("\"normalization\":{");
     push_kv_bool(&mut out, "normalize", data.normalize);
     out.push(',');
@@ -74,16 +110,59 @@ pub fn render_summary_json(data: &SummaryData) -> String {
         "rls_median",
         stat_median(&data.composites, "c3_rls") as f64,
     );
+    if let Some(expr) = &data.custom_composite {
+        out.push(',');
+        push_kv_str(&mut out, "custom_expr", expr);
+        out.push(',');
+        push_kv_num(
+            &mut out,
+            "custom_median",
+            stat_median(&data.composites, "c4_custom") as f64,
+        );
+    }
     out.push_str("},");
 
     out.push_str("\"tails\":{");
     push_kv_num(&mut out, "trs_p90", stat_p90(&data.axes, "a4_trs") as f64);
     out.push(',');
-    push_kv_num(&mut out, "trs_ge_0_75", data.trs_ge_0_75 as f64);
-    out.push(',');
-    push_kv_num(&mut out, "nps_ge_0_60", data.nps_ge_0_60 as f64);
-    out.push(',');
-    push_kv_num(&mut out, "rls_le_0_35", data.rls_le_0_35 as f64);
+    push_kv_num(&mut out, "trs_ge_threshold", data.trs_tail_min as f64);
+    out.push(',');
+    push_kv_num(&mut out, "trs_tail_fraction", data.trs_tail_fraction as f64);
+    out.push(',');
+    push_kv_num(&mut out, "nps_ge_threshold", data.nps_tail_min as f64);
+    out.push(',');
+    push_kv_num(&mut out, "nps_tail_fraction", data.nps_tail_fraction as f64);
+    out.push(',');
+    push_kv_num(&mut out, "rls_le_threshold", data.rls_tail_max as f64);
+    out.push(',');
+    push_kv_num(&mut out, "rls_tail_fraction", data.rls_tail_fraction as f64);
+    // Deprecated aliases of the `*_tail_fraction` keys, kept for one release
+    // and written only at the cutoff their name states.
+    for (key, cutoff, legacy, fraction) in [
+        (
+            "trs_ge_0_75",
+            data.trs_tail_min,
+            0.75,
+            data.trs_tail_fraction,
+        ),
+        (
+            "nps_ge_0_60",
+            data.nps_tail_min,
+            0.60,
+            data.nps_tail_fraction,
+        ),
+        (
+            "rls_le_0_35",
+            data.rls_tail_max,
+            0.35,
+            data.rls_tail_fraction,
+        ),
+    ] {
+        if cutoff == legacy {
+            out.push(',');
+            push_kv_num(&mut out, key, fraction as f64);
+        }
+    }
     out.push_str("},");
 
     out.push_str("\"qc\":{");
@@ -102,6 +181,163 @@ pub fn render_summary_json(data: &SummaryData) -> String {
         "low_expr_genes_fraction",
         data.low_expr_fraction as f64,
     );
+    out.push(',');
+    push_kv_num(&mut out, "pct_mito_median", data.pct_mito_median as f64);
+    out.push(',');
+    push_kv_num(&mut out, "pct_mito_p90", data.pct_mito_p90 as f64);
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "high_mito_fraction",
+        data.high_mito_fraction as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "pct_ribo_median", data.pct_ribo_median as f64);
+    out.push(',');
+    push_kv_num(&mut out, "pct_ribo_p90", data.pct_ribo_p90 as f64);
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "high_ribo_fraction",
+        data.high_ribo_fraction as f64,
+    );
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "pct_control_median",
+        data.pct_control_median as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "pct_control_p90", data.pct_control_p90 as f64);
+    out.push_str("},");
+
+    out.push_str("\"thresholds\":{");
+    push_kv_num(&mut out, "expr_min", data.expr_min as f64);
+    out.push(',');
+    push_kv_num(&mut out, "min_expr_genes", data.min_expr_genes as f64);
+    out.push(',');
+    push_kv_num(&mut out, "tf_min_sum", data.tf_min_sum as f64);
+    out.push(',');
+    push_kv_num(&mut out, "program_min_sum", data.program_min_sum as f64);
+    out.push(',');
+    push_kv_num(&mut out, "rel_p70", data.rel_p70 as f64);
+    out.push(',');
+    push_kv_num(&mut out, "rel_p85", data.rel_p85 as f64);
+    out.push(',');
+    push_kv_num(&mut out, "confidence_low", data.confidence_low as f64);
+    out.push(',');
+    out.push_str("\"trs_weights\":{");
+    push_kv_num(&mut out, "a", data.trs_weights[0] as f64);
+    out.push(',');
+    push_kv_num(&mut out, "b", data.trs_weights[1] as f64);
+    out.push(',');
+    push_kv_num(&mut out, "c", data.trs_weights[2] as f64);
+    out.push_str("},");
+    push_kv_num(&mut out, "mito_frac_max", data.mito_frac_max as f64);
+    out.push(',');
+    push_kv_num(&mut out, "ribo_frac_max", data.ribo_frac_max as f64);
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "panel_coverage_min",
+        data.panel_coverage_min as f64,
+    );
+    out.push(',');
+    out.push_str("\"confidence_weights\":{");
+    push_kv_num(
+        &mut out,
+        "panel_coverage",
+        data.confidence_weights[0] as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "expr_support", data.confidence_weights[1] as f64);
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "axis_structure",
+        data.confidence_weights[2] as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "consistency", data.confidence_weights[3] as f64);
+    out.push_str("},");
+    push_kv_num(
+        &mut out,
+        "confidence_coverage_scale",
+        data.confidence_coverage_scale as f64,
+    );
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "confidence_axis_variance_scale",
+        data.confidence_axis_variance_scale as f64,
+    );
+    out.push(',');
+    push_kv_str(
+        &mut out,
+        "cea_ribo_adjust",
+        if data.cea_ribo_adjust {
+            "regress"
+        } else {
+            "off"
+        },
+    );
+    out.push(',');
+    out.push_str("\"cea_ribo_slope\":");
+    match data.cea_ribo_slope {
+        Some(v) => out.push_str(&format_f32_6(v)),
+        None => out.push_str("null"),
+    }
+    out.push(',');
+    push_kv_str(&mut out, "sum_mode", data.sum_mode.as_str());
+    out.push(',');
+    push_kv_str(&mut out, "relative_within", data.relative_within.as_str());
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "relative_min_group_cells",
+        data.relative_min_group_cells as f64,
+    );
+    out.push(',');
+    push_kv_str(&mut out, "quantile_method", data.quantile_method.as_str());
+    out.push(',');
+    out.push_str("\"key_panels\":[");
+    for (i, id) in data.key_panels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_str_val(&mut out, id);
+    }
+    out.push(']');
+    out.push(',');
+    out.push_str("\"qc_gates\":{");
+    push_kv_num(
+        &mut out,
+        "max_low_confidence_fraction",
+        data.qc_gates.max_low_confidence_fraction as f64,
+    );
+    out.push(',');
+    push_kv_num(&mut out, "min_cells", data.qc_gates.min_cells as f64);
+    out.push_str("},");
+    push_kv_str(&mut out, "panel_score", data.panel_score.as_str());
+    out.push(',');
+    push_kv_str(&mut out, "entropy_unit", data.entropy_unit.as_str());
+    out.push_str(",\"winsorize_panels\":");
+    match &data.winsorize_panels {
+        Some((quantile, caps)) => {
+            out.push('{');
+            push_kv_num(&mut out, "quantile", *quantile as f64);
+            out.push_str(",\"caps\":{");
+            for (i, (panel_id, cap)) in caps.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_kv_num(&mut out, panel_id, *cap as f64);
+            }
+            out.push_str("}}");
+        }
+        None => out.push_str("null"),
+    }
+    let _ = write!(out, ",\"seed\":{}", data.seed);
     out.push_str("},");
 
     // Existing extended metadata and distributions.
@@ -213,6 +449,153 @@ pub fn render_summary_json(data: &SummaryData) -> String {
     }
     out.push_str("]}");
     out.push(',');
+    out.push_str("\"diagnostics\":{\"excluded_panels\":[");
+    for (i, panel) in data.excluded_panels.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "panel_id", &panel.panel_id);
+        out.push(',');
+        push_kv_str(&mut out, "group", panel.group.as_str());
+        out.push(',');
+        push_kv_num(
+            &mut out,
+            "mappable_fraction",
+            panel.mappable_fraction as f64,
+        );
+        out.push(',');
+        push_kv_num(&mut out, "min_mappable_fraction", panel.min_fraction as f64);
+        out.push(',');
+        out.push_str("\"missing_genes\":[");
+        for (j, g) in panel.missing_genes.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            push_str_val(&mut out, g);
+        }
+        out.push_str("]}");
+    }
+    out.push_str("],\"unclassified_blockers\":[");
+    for (i, blocker) in data.unclassified_blockers.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "regime", blocker.regime);
+        out.push(',');
+        push_kv_str(&mut out, "condition", &blocker.condition);
+        out.push(',');
+        push_kv_num(&mut out, "count", blocker.count as f64);
+        out.push(',');
+        push_kv_num(&mut out, "fraction", blocker.fraction as f64);
+        out.push('}');
+    }
+    out.push_str("]}");
+    out.push(',');
+    out.push_str("\"metadata_columns\":[");
+    for (i, column) in data.metadata_columns.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "name", &column.name);
+        out.push(',');
+        push_kv_str(&mut out, "type", column.kind.as_str());
+        out.push(',');
+        push_kv_num(&mut out, "missing_fraction", column.missing_fraction as f64);
+        out.push(',');
+        push_kv_num(&mut out, "cardinality", column.cardinality as f64);
+        match &column.kind {
+            MetaColumnKind::Numeric { min, median, max } => {
+                out.push(',');
+                push_kv_num(&mut out, "min", *min as f64);
+                out.push(',');
+                push_kv_num(&mut out, "median", *median as f64);
+                out.push(',');
+                push_kv_num(&mut out, "max", *max as f64);
+            }
+            MetaColumnKind::Categorical { top } => {
+                out.push_str(",\"top\":[");
+                for (j, (value, count)) in top.iter().enumerate() {
+                    if j > 0 {
+                        out.push(',');
+                    }
+                    out.push('{');
+                    push_kv_str(&mut out, "value", value);
+                    out.push(',');
+                    push_kv_num(&mut out, "count", *count as f64);
+                    out.push('}');
+                }
+                out.push(']');
+            }
+        }
+        out.push('}');
+    }
+    out.push_str("],");
+    out.push_str("\"clusters\":[");
+    for (i, cluster) in data.clusters.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('{');
+        push_kv_str(&mut out, "cluster", &cluster.cluster);
+        out.push(',');
+        push_kv_num(&mut out, "n_cells", cluster.n_cells as f64);
+        out.push(',');
+        push_kv_num(&mut out, "fraction", cluster.fraction as f64);
+        out.push(',');
+        push_kv_str(&mut out, "dominant_regime", cluster.dominant_regime);
+        out.push(',');
+        push_kv_num(&mut out, "c1_nps_median", cluster.nps_median as f64);
+        out.push(',');
+        push_kv_num(&mut out, "c2_ci_median", cluster.ci_median as f64);
+        out.push(',');
+        push_kv_num(&mut out, "c3_rls_median", cluster.rls_median as f64);
+        out.push('}');
+    }
+    out.push_str("],");
+    out.push_str("\"contrasts\":");
+    match &data.contrasts {
+        Some(contrasts) => {
+            out.push('{');
+            push_kv_str(&mut out, "column", &contrasts.column);
+            out.push(',');
+            push_kv_str(&mut out, "reference", &contrasts.reference);
+            out.push_str(",\"results\":[");
+            for (i, c) in contrasts.results.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('{');
+                push_kv_str(&mut out, "level", &c.level);
+                out.push(',');
+                push_kv_str(&mut out, "metric", c.metric);
+                out.push(',');
+                push_kv_num(&mut out, "n_cells", c.n_level as f64);
+                out.push(',');
+                push_kv_num(&mut out, "n_reference", c.n_reference as f64);
+                out.push(',');
+                push_kv_num(&mut out, "median_diff", c.median_diff as f64);
+                out.push(',');
+                push_kv_num(&mut out, "cliffs_delta", c.test.cliffs_delta);
+                out.push(',');
+                push_kv_num(&mut out, "u", c.test.u);
+                out.push(',');
+                push_kv_num(&mut out, "p_value", c.test.p_value);
+                out.push(',');
+                push_kv_str(
+                    &mut out,
+                    "p_method",
+                    if c.test.exact { "exact" } else { "normal" },
+                );
+                out.push('}');
+            }
+            out.push_str("]}");
+        }
+        None => out.push_str("null"),
+    }
+    out.push(',');
     out.push_str("\"genome_stability\":{");
     push_kv_str(
         &mut out,
@@ -383,6 +766,49 @@ pub fn render_summary_json(data: &SummaryData) -> String {
     out
 }
 
+/// `performance.json`: wall-clock laps, throughput and peak RSS. Kept out of
+/// `summary.json` so that file stays byte-identical across reruns.
+pub fn render_performance_json(data: &SummaryData) -> String {
+    let mut out = String::new();
+    out.push('{');
+    push_kv_str(&mut out, "tool", &data.tool_name);
+    out.push(',');
+    out.push_str("\"stage_seconds\":{");
+    for (i, (stage, seconds)) in data.stage_seconds.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_kv_num(&mut out, stage, *seconds);
+    }
+    out.push_str("},");
+    let total_seconds = data.stage_seconds.iter().map(|(_, s)| s).sum::<f64>();
+    push_kv_num(&mut out, "total_seconds", total_seconds);
+    out.push(',');
+    push_kv_num(
+        &mut out,
+        "cells_per_second",
+        if total_seconds > 0.0 {
+            data.n_cells as f64 / total_seconds
+        } else {
+            0.0
+        },
+    );
+    out.push_str(",\"peak_rss_bytes\":");
+    match data.peak_rss_bytes {
+        Some(bytes) => {
+            let _ = write!(out, "{bytes}");
+        }
+        None => out.push_str("null"),
+    }
+    let _ = write!(
+        out,
+        ",\"estimated_peak_rss_bytes\":{}",
+        data.estimated_peak_rss_bytes
+    );
+    out.push('}');
+    out
+}
+
 fn stat_median(stats: &[crate::report::NamedStats], name: &str) -> f32 {
     for s in stats {
         if s.name == name {
@@ -422,13 +848,13 @@ fn stat_p99(stats: &[crate::report::NamedStats], name: &str) -> f32 {
     0.0
 }
 
-fn push_kv_str(out: &mut String, key: &str, value: &str) {
+pub(super) fn push_kv_str(out: &mut String, key: &str, value: &str) {
     push_str_key(out, key);
     out.push(':');
     push_str_val(out, value);
 }
 
-fn push_kv_num(out: &mut String, key: &str, value: f64) {
+pub(super) fn push_kv_num(out: &mut String, key: &str, value: f64) {
     push_str_key(out, key);
     out.push(':');
     let _ = write!(out, "{}", format_f32_6(value as f32));
@@ -440,13 +866,13 @@ fn push_kv_bool(out: &mut String, key: &str, value: bool) {
     out.push_str(if value { "true" } else { "false" });
 }
 
-fn push_str_key(out: &mut String, key: &str) {
+pub(super) fn push_str_key(out: &mut String, key: &str) {
     out.push('"');
     out.push_str(&escape_json(key));
     out.push('"');
 }
 
-fn push_str_val(out: &mut String, value: &str) {
+pub(super) fn push_str_val(out: &mut String, value: &str) {
     out.push('"');
     out.push_str(&escape_json(value));
     out.push('"');
@@ -0,0 +1,125 @@
+//! Per-column summaries of the cell metadata for `summary.json`, to catch a
+//! swapped or truncated `--meta` file at a glance. Values stay strings in
+//! [`CellMeta`]; this pass only decides how to summarize each column.
+
+use std::collections::BTreeMap;
+
+use crate::input::meta::CellMeta;
+use crate::report::{QuantileMethod, median};
+
+/// Most frequent values listed for a categorical column.
+pub const TOP_CATEGORIES: usize = 5;
+
+/// Grouping by a metadata column with more distinct values than this is
+/// almost always the wrong column.
+pub const MAX_GROUPS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaColumnSummary {
+    pub name: String,
+    /// Share of cells whose value is empty or `NA`.
+    pub missing_fraction: f32,
+    /// Distinct present values.
+    pub cardinality: usize,
+    pub kind: MetaColumnKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaColumnKind {
+    /// Every present value parses as a finite number.
+    Numeric { min: f32, median: f32, max: f32 },
+    /// Up to [`TOP_CATEGORIES`] `(value, count)` pairs, most frequent first,
+    /// ties by value.
+    Categorical { top: Vec<(String, usize)> },
+}
+
+impl MetaColumnKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetaColumnKind::Numeric { .. } => "numeric",
+            MetaColumnKind::Categorical { .. } => "categorical",
+        }
+    }
+}
+
+/// One summary per column, in column order.
+pub fn summarize_meta(meta: &CellMeta, method: QuantileMethod) -> Vec<MetaColumnSummary> {
+    meta.columns
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            summarize_column(
+                name,
+                meta.rows
+                    .iter()
+                    .map(|row| row.get(idx).map(String::as_str).unwrap_or("")),
+                method,
+            )
+        })
+        .collect()
+}
+
+/// Numeric only if all present values parse; a single text value makes the
+/// whole column categorical. A column with no present value is categorical.
+pub fn summarize_column<'a>(
+    name: &str,
+    values: impl Iterator<Item = &'a str>,
+    method: QuantileMethod,
+) -> MetaColumnSummary {
+    let mut n_cells = 0usize;
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for value in values {
+        n_cells += 1;
+        let value = value.trim();
+        if !is_missing(value) {
+            *counts.entry(value).or_default() += 1;
+        }
+    }
+    let n_present = counts.values().sum::<usize>();
+    let missing_fraction = if n_cells > 0 {
+        (n_cells - n_present) as f32 / n_cells as f32
+    } else {
+        0.0
+    };
+
+    let numbers = counts
+        .iter()
+        .map(|(value, &count)| {
+            let number = value.parse::<f32>().ok().filter(|v| v.is_finite())?;
+            Some(std::iter::repeat_n(number, count))
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|_| n_present > 0)
+        .map(|runs| runs.into_iter().flatten().collect::<Vec<_>>());
+    let kind = match numbers {
+        Some(numbers) => MetaColumnKind::Numeric {
+            min: numbers.iter().copied().fold(f32::INFINITY, f32::min),
+            median: median(&numbers, method),
+            max: numbers.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        },
+        None => {
+            let mut top = counts
+                .iter()
+                .map(|(value, &count)| (value.to_string(), count))
+                .collect::<Vec<_>>();
+            top.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+            top.truncate(TOP_CATEGORIES);
+            MetaColumnKind::Categorical { top }
+        }
+    };
+
+    MetaColumnSummary {
+        name: name.to_string(),
+        missing_fraction,
+        cardinality: counts.len(),
+        kind,
+    }
+}
+
+fn is_missing(value: &str) -> bool {
+    value.is_empty() || value == "NA"
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/report/metadata.rs"]
+mod tests;
@@ -1,6 +1,17 @@
+use crate::input::SpeciesCall;
 use crate::metrics::genome_stability::aggregate::GenomeStabilitySummary;
+use crate::model::axes::EntropyUnit;
+use crate::model::thresholds::RelativeWithin;
+use crate::panels::PanelScoreMode;
+use crate::panels::loader::LowMappabilityPanel;
+use crate::report::contrast::ContrastSummary;
+use crate::report::metadata::MetaColumnSummary;
+use crate::simd::SumMode;
 
+pub mod batch;
+pub mod contrast;
 pub mod json;
+pub mod metadata;
 pub mod text;
 
 #[derive(Debug, Clone)]
@@ -18,19 +29,81 @@ pub struct RegimeStat {
     pub fraction: f32,
 }
 
+/// One cluster of `--clusters` (or the metadata cluster column); cells
+/// without a cluster form the [`UNASSIGNED_CLUSTER`] group.
+///
+/// [`UNASSIGNED_CLUSTER`]: crate::pipeline::stage7_report::UNASSIGNED_CLUSTER
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterStat {
+    pub cluster: String,
+    pub n_cells: usize,
+    pub fraction: f32,
+    pub dominant_regime: &'static str,
+    pub nps_median: f32,
+    pub ci_median: f32,
+    pub rls_median: f32,
+}
+
+/// How many `Unclassified` cells came closest to `regime` and failed
+/// `condition` (a rule such as `rci>=0.35`) by the widest margin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingStat {
+    pub regime: &'static str,
+    pub condition: String,
+    pub count: usize,
+    /// Share of the `Unclassified` cells.
+    pub fraction: f32,
+}
+
+/// Gates of the per-sample `qc_pass` verdict in sample-resolution
+/// `nuclearqc.tsv` (`--qc-max-low-confidence`, `--qc-min-cells`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QcGates {
+    /// A sample fails at or above this fraction of `LOW_CONFIDENCE` cells.
+    pub max_low_confidence_fraction: f32,
+    /// A sample with fewer cells fails.
+    pub min_cells: usize,
+}
+
+impl Default for QcGates {
+    fn default() -> Self {
+        Self {
+            max_low_confidence_fraction: 0.2,
+            min_cells: 50,
+        }
+    }
+}
+
+impl QcGates {
+    pub fn pass(&self, n_cells: usize, low_confidence_fraction: f32) -> bool {
+        n_cells >= self.min_cells && low_confidence_fraction < self.max_low_confidence_fraction
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SummaryData {
     pub tool_name: String,
     pub tool_version: String,
     pub git_hash: Option<String>,
     pub simd_backend: String,
+    pub seed: u64,
+    /// Wall-clock seconds per stage up to Stage 6, in run order.
+    pub stage_seconds: Vec<(&'static str, f64)>,
+    /// Peak resident set size in bytes; `None` where the OS does not report it.
+    pub peak_rss_bytes: Option<u64>,
+    /// Peak resident set size predicted from the input dimensions.
+    pub estimated_peak_rss_bytes: u64,
     pub run_mode: String,
     pub resolution: String,
 
     pub n_cells: usize,
+    /// Cells in the input before `--max-cells` subsampling, if it applied.
+    pub sampled_from: Option<usize>,
     pub n_genes_raw: usize,
+    pub n_control_features: usize,
     pub n_genes_mappable: usize,
     pub species: String,
+    pub species_call: SpeciesCall,
 
     pub normalize: bool,
     pub scale: f32,
@@ -38,23 +111,83 @@ pub struct SummaryData {
     pub axis_activation_mode: String,
     pub confidence_breakdown: Option<[f32; 4]>,
     pub scoring_mode: String,
+    /// `--profile` name.
+    pub profile: String,
 
     pub confidence_median: f32,
     pub confidence_p10: f32,
     pub low_confidence_fraction: f32,
     pub low_expr_fraction: f32,
+    pub pct_mito_median: f32,
+    pub pct_mito_p90: f32,
+    pub high_mito_fraction: f32,
+    pub pct_ribo_median: f32,
+    pub pct_ribo_p90: f32,
+    pub high_ribo_fraction: f32,
+    pub pct_control_median: f32,
+    pub pct_control_p90: f32,
+
+    pub expr_min: f32,
+    pub min_expr_genes: u32,
+    pub tf_min_sum: f32,
+    pub program_min_sum: f32,
+    pub rel_p70: f32,
+    pub rel_p85: f32,
+    pub confidence_low: f32,
+    /// TRS weights `a`, `b`, `c`.
+    pub trs_weights: [f32; 3],
+    pub mito_frac_max: f32,
+    pub ribo_frac_max: f32,
+    pub panel_coverage_min: f32,
+    pub confidence_weights: [f32; 4],
+    pub confidence_coverage_scale: f32,
+    pub confidence_axis_variance_scale: f32,
+    pub cea_ribo_adjust: bool,
+    pub cea_ribo_slope: Option<f32>,
+    pub sum_mode: SumMode,
+    pub relative_within: RelativeWithin,
+    pub relative_min_group_cells: usize,
+    pub quantile_method: QuantileMethod,
+    pub key_panels: Vec<String>,
+    pub panel_score: PanelScoreMode,
+    /// Unit of the raw entropy driver columns.
+    pub entropy_unit: EntropyUnit,
+    /// `--winsorize-panels` quantile and the resulting `(panel_id, cap)`
+    /// clamps, in panel order.
+    pub winsorize_panels: Option<(f32, Vec<(String, f32)>)>,
+    pub qc_gates: QcGates,
 
     pub axes: Vec<NamedStats>,
     pub ddr_metrics: Vec<NamedStats>,
     pub composites: Vec<NamedStats>,
+    /// `--composite` in canonical form; its stats are `c4_custom` in
+    /// `composites`.
+    pub custom_composite: Option<String>,
 
     pub regimes: Vec<RegimeStat>,
 
-    pub trs_ge_0_75: f32,
-    pub nps_ge_0_60: f32,
-    pub rls_le_0_35: f32,
+    /// Tail cutoffs from the threshold profile and the share of cells past
+    /// each.
+    pub trs_tail_min: f32,
+    pub trs_tail_fraction: f32,
+    pub nps_tail_min: f32,
+    pub nps_tail_fraction: f32,
+    pub rls_tail_max: f32,
+    pub rls_tail_fraction: f32,
 
     pub missing_genes_by_panel: Vec<(String, Vec<String>)>,
+    /// Panels left out of scoring by `--min-mappable-fraction`.
+    pub excluded_panels: Vec<LowMappabilityPanel>,
+    /// Most common nearest regime and blocking condition among
+    /// `Unclassified` cells, most frequent first.
+    pub unclassified_blockers: Vec<BlockingStat>,
+    /// Typed summaries of the cell metadata columns; empty without `--meta`.
+    pub metadata_columns: Vec<MetaColumnSummary>,
+    /// Per-cluster regime and composite medians, in cluster order; empty
+    /// without cluster labels.
+    pub clusters: Vec<ClusterStat>,
+    /// `--contrast` results; `None` without it.
+    pub contrasts: Option<ContrastSummary>,
     pub rls_contributors_top: Vec<String>,
     pub genome_stability: GenomeStabilitySummary,
 }
@@ -76,40 +209,117 @@ pub struct ReportContext {
     pub rls_contributors_top: Vec<String>,
     pub rls_tail_fraction: f32,
     pub immune_tail_note: bool,
+    pub unclassified_blockers: Vec<BlockingStat>,
+    pub contrasts: Option<ContrastSummary>,
     pub scoring_mode: String,
     pub axis_activation_mode: String,
     pub confidence_model: String,
+    pub profile: String,
 }
 
 pub fn format_f32_6(v: f32) -> String {
     format!("{:.6}", v)
 }
 
-pub fn quantile_indexed(values: &[f32], p: f32) -> f32 {
-    if values.is_empty() {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantileMethod {
+    /// Linear interpolation between order statistics (type 7, numpy default).
+    Linear,
+    /// Order statistic at `ceil((n-1)*p)`, kept for `--legacy-quantiles`.
+    LegacyCeil,
+}
+
+impl QuantileMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuantileMethod::Linear => "linear",
+            QuantileMethod::LegacyCeil => "legacy_ceil",
+        }
+    }
+}
+
+pub fn quantile_sorted(sorted: &[f32], p: f32, method: QuantileMethod) -> f32 {
+    if sorted.is_empty() {
         return 0.0;
     }
+    let n = sorted.len();
+    match method {
+        QuantileMethod::LegacyCeil => {
+            let idx = ((n - 1) as f32 * p).ceil() as usize;
+            sorted[idx.min(n - 1)]
+        }
+        QuantileMethod::Linear => {
+            let h = (n - 1) as f64 * (p as f64).clamp(0.0, 1.0);
+            let lo = h.floor() as usize;
+            let frac = h - lo as f64;
+            if frac == 0.0 || lo + 1 >= n {
+                return sorted[lo.min(n - 1)];
+            }
+            let a = sorted[lo] as f64;
+            let b = sorted[lo + 1] as f64;
+            (a + (b - a) * frac) as f32
+        }
+    }
+}
+
+pub fn quantile_indexed(values: &[f32], p: f32, method: QuantileMethod) -> f32 {
+    let [q] = quantiles(values, &[p], method);
+    q
+}
+
+/// Quantiles `probs` of `values` from a single sorted copy, each equal to
+/// `quantile_indexed(values, p, method)`; use it when a vector needs more
+/// than one.
+pub fn quantiles<const N: usize>(
+    values: &[f32],
+    probs: &[f32; N],
+    method: QuantileMethod,
+) -> [f32; N] {
+    if values.is_empty() {
+        return [0.0; N];
+    }
     let mut sorted = values.to_vec();
     sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    let n = sorted.len();
-    let idx = ((n - 1) as f32 * p).ceil() as usize;
-    sorted[idx]
+    probs.map(|p| quantile_sorted(&sorted, p, method))
 }
 
-pub fn median(values: &[f32]) -> f32 {
-    quantile_indexed(values, 0.5)
+pub fn median(values: &[f32], method: QuantileMethod) -> f32 {
+    quantile_indexed(values, 0.5, method)
 }
 
-pub fn p10(values: &[f32]) -> f32 {
-    quantile_indexed(values, 0.10)
+pub fn p10(values: &[f32], method: QuantileMethod) -> f32 {
+    quantile_indexed(values, 0.10, method)
 }
 
-pub fn p90(values: &[f32]) -> f32 {
-    quantile_indexed(values, 0.90)
+pub fn p90(values: &[f32], method: QuantileMethod) -> f32 {
+    quantile_indexed(values, 0.90, method)
 }
 
-pub fn p99(values: &[f32]) -> f32 {
-    quantile_indexed(values, 0.99)
+pub fn p99(values: &[f32], method: QuantileMethod) -> f32 {
+    quantile_indexed(values, 0.99, method)
+}
+
+/// Percentile rank of each value within `values`: its 1-based rank over the
+/// number of values, with tied values sharing the mean of their ranks.
+pub fn percentile_ranks(values: &[f32]) -> Vec<f32> {
+    let n = values.len();
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]).then(a.cmp(&b)));
+    let mut ranks = vec![0.0f32; n];
+    let mut start = 0;
+    while start < n {
+        let mut end = start + 1;
+        while end < n && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // Ranks start + 1 ..= end average to their midpoint.
+        let rank = (start + 1 + end) as f64 / 2.0;
+        for &idx in &order[start..end] {
+            ranks[idx] = (rank / n as f64) as f32;
+        }
+        start = end;
+    }
+    ranks
 }
 
 pub fn bool_fraction(values: &[bool]) -> f32 {
@@ -8,6 +8,7 @@ pub fn render_report_text(ctx: &ReportContext) -> String {
 
     out.push_str("1. Overall nuclear state\n");
     out.push_str(&format!("Nuclear scoring mode: {}\n", ctx.scoring_mode));
+    out.push_str(&format!("Threshold profile: {}\n", ctx.profile));
     out.push_str(&format!(
         "Axis activation mode: {}\n",
         ctx.axis_activation_mode
@@ -71,6 +72,29 @@ pub fn render_report_text(ctx: &ReportContext) -> String {
     if ctx.immune_tail_note {
         out.push_str("High IAA/DFA/CEA tails indicate immune activation subpopulation; consider cell-type gating for GC B cells\n");
     }
+    let unclassified = ctx
+        .regimes
+        .iter()
+        .find(|r| r.name == "Unclassified")
+        .filter(|r| r.count > 0);
+    if let Some(r) = unclassified
+        && !ctx.unclassified_blockers.is_empty()
+    {
+        out.push_str(&format!(
+            "Unclassified cells: {} ({}); nearest regime and its most common unmet condition:\n",
+            r.count,
+            format_f32_6(r.fraction)
+        ));
+        for blocker in &ctx.unclassified_blockers {
+            out.push_str(&format!(
+                "  {} needs {}: {} cells ({})\n",
+                blocker.regime,
+                blocker.condition,
+                blocker.count,
+                format_f32_6(blocker.fraction)
+            ));
+        }
+    }
     if let Some([pc, es, ax, cs]) = ctx.confidence_breakdown {
         out.push_str(&format!(
             "Confidence breakdown (median): panel_coverage={}, expr_support={}, axis_structure={}, consistency={}\n",
@@ -81,6 +105,24 @@ pub fn render_report_text(ctx: &ReportContext) -> String {
         ));
     }
 
+    if let Some(contrasts) = &ctx.contrasts {
+        out.push_str(&format!(
+            "\n6. Contrasts by {} (reference {})\n",
+            contrasts.column, contrasts.reference
+        ));
+        out.push_str("level\tmetric\tmedian_diff\tcliffs_delta\tp_value\n");
+        for c in &contrasts.results {
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{:.6}\t{:.6}\n",
+                c.level,
+                c.metric,
+                format_f32_6(c.median_diff),
+                c.test.cliffs_delta,
+                c.test.p_value
+            ));
+        }
+    }
+
     out
 }
 
@@ -0,0 +1,1550 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::input::cache::CacheCodec;
+use crate::input::controls::{
+    control_fraction, control_prefixes, exclude_controls, has_controls, read_control_counts,
+};
+use crate::input::features::FeatureColumns;
+use crate::input::meta::{CellMeta, load_clusters, load_meta_by_order, merge_meta};
+use crate::input::mtx::{CscMatrix, csc_from_cell_rows};
+use crate::input::{
+    InputBundle, InputError, InputFormat, InputSourceKind, Species, TenxOptions,
+    bundle_from_symbols, load_input_organelle, load_input_tenx, resolve_shared_bin,
+};
+use crate::model::axes::{Axes, EntropyUnit};
+use crate::model::scores::{CompositeScores, CustomComposite};
+use crate::model::thresholds::{
+    ConfidenceModel, NamedProfile, NuclearScoringMode, RelativeWithin, ThresholdProfile,
+};
+use crate::panels::defs::PanelDef;
+use crate::panels::loader::{LowMappabilityPanel, MinMappable};
+use crate::panels::{self, PanelAudit, PanelScoreMode};
+use crate::pipeline::cell_scan::{CHUNK_SPILL_DIR, CellScan, run_chunked};
+use crate::pipeline::stage2_normalize::{
+    DEFAULT_PEARSON_THETA, ExprAccessor, NORMALIZED_EXPORT_DIR, NormalizeMode, NormalizedExport,
+    Stage2Error, Stage2Params, build_expr_accessor, counts_accessor, read_bundle_counts,
+    read_cell_counts,
+};
+use crate::pipeline::stage3_panels::run_stage3;
+use crate::pipeline::stage4_axes::{Stage4Covariates, run_stage4};
+use crate::pipeline::stage5_scores::{Stage5Inputs, run_stage5};
+use crate::pipeline::stage6_classify::{Classification, Stage6Inputs, run_stage6};
+use crate::pipeline::stage7_report::{
+    PipelineContext, ReportMode, RunMode, RunProvenance, Stage7Input, build_summary,
+    write_pipeline_step, write_reports,
+};
+use crate::report::contrast::ContrastDesign;
+use crate::report::metadata::{MAX_GROUPS, summarize_meta};
+use crate::report::{QcGates, QuantileMethod, SummaryData, p90};
+use crate::simd::{self, SumMode};
+use crate::tracing::{WarningLog, WarningScope};
+use crate::{input, pipeline};
+
+/// `--seed` when not given.
+pub const DEFAULT_SEED: u64 = 0;
+
+/// Settings of one run. The CLI fills it from argv; library callers start
+/// from [`RunConfig::new`] and override fields.
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    /// Command line as given, after the program name.
+    pub args: Vec<String>,
+    pub input_dir: PathBuf,
+    /// `--input-format`: with `Loom`, `input_dir` names a `.loom` file, read
+    /// whole, so the chunking and cache settings do not apply.
+    pub input_format: InputFormat,
+    pub out_dir: PathBuf,
+    pub cache_path: Option<PathBuf>,
+    pub report_mode: ReportMode,
+    pub meta_path: Option<PathBuf>,
+    /// Second and later `--meta` files, joined onto the first in order: by
+    /// barcode, or by sample when a file has a `sample` column and no
+    /// `barcode` column. Column names must not repeat across files.
+    pub extra_meta_paths: Vec<PathBuf>,
+    /// `--meta-by-order`: attach metadata rows to barcodes by position
+    /// instead of by barcode.
+    pub meta_by_order: bool,
+    /// `--clusters`: barcode-to-cluster table for `--mode cluster`; replaces
+    /// a cluster column from the metadata.
+    pub clusters_path: Option<PathBuf>,
+    /// `--contrast`: metadata column whose levels are compared per axis and
+    /// composite in `summary.json` and `report.txt`.
+    pub contrast: Option<String>,
+    /// `--contrast-reference`: level the others are compared against;
+    /// required with more than two levels.
+    pub contrast_reference: Option<String>,
+    pub normalize: bool,
+    pub normalize_mode: NormalizeMode,
+    pub pearson_theta: f32,
+    pub cache_normalized: bool,
+    pub cache_codec: CacheCodec,
+    /// `--profile`; sets the thresholds and, through them, the scoring mode.
+    pub profile: NamedProfile,
+    /// `--confidence-model`; `None` uses the scoring mode's own model.
+    pub confidence_model: Option<ConfidenceModel>,
+    /// `--composite`: extra linear composite over axes, as `c4_custom`.
+    pub custom_composite: Option<CustomComposite>,
+    pub run_mode: RunMode,
+    pub cea_ribo_adjust: bool,
+    pub sum_mode: SumMode,
+    pub relative_within: RelativeWithin,
+    pub relative_min_group_cells: usize,
+    pub panel_score: PanelScoreMode,
+    /// `--winsorize-panels`: clamp panel values at this quantile over cells
+    /// before Stage 4.
+    pub winsorize_panels: Option<f32>,
+    /// `--mito-max` / `--ribo-max`: count fractions above which a cell is
+    /// flagged, replacing the profile's 0.2/0.5.
+    pub mito_max: Option<f32>,
+    pub ribo_max: Option<f32>,
+    /// `--control-prefixes`: symbol prefixes of control features, on top of
+    /// `ERCC-`.
+    pub control_prefixes: Vec<String>,
+    /// `--tail-thresholds`: summary tail cutoffs for `a4_trs`, `c1_nps` and
+    /// `c3_rls`, replacing the profile's 0.75/0.60/0.35.
+    pub tail_thresholds: Option<[f32; 3]>,
+    /// `--legacy-quantiles`: sets the profile's `quantile_method` to
+    /// [`QuantileMethod::LegacyCeil`] for this run.
+    pub legacy_quantiles: bool,
+    pub key_panels: Option<Vec<String>>,
+    pub panels_path: Option<PathBuf>,
+    /// Run only these panels (`--only-panels`).
+    pub only_panels: Option<Vec<String>>,
+    /// Drop these panels (`--disable-panels`).
+    pub disable_panels: Vec<String>,
+    /// `--min-mappable-fraction`: panels mapping a smaller share of their
+    /// genes are left out of scoring.
+    pub min_mappable: MinMappable,
+    /// `qc_pass` gates for sample-resolution reports.
+    pub qc_gates: QcGates,
+    /// `--strict-mappable`: fail instead of excluding low-mappability panels.
+    pub strict_mappable: bool,
+    /// `--panel-rule-max-genes`: most genes one `prefix:`/`regex:` panel rule
+    /// may match.
+    pub panel_rule_max_genes: usize,
+    pub assume_transposed: bool,
+    /// Feature id and symbol columns of a 10x features table
+    /// (`--feature-id-col`, `--feature-symbol-col`).
+    pub feature_columns: FeatureColumns,
+    /// `--strict-input`: reject negative matrix values instead of clamping
+    /// them to zero.
+    pub strict_input: bool,
+    /// `--max-cells`: score an evenly strided subset of at most this many
+    /// cells, for quick previews.
+    pub max_cells: Option<usize>,
+    /// `--threads`: threads parsing a coordinate `matrix.mtx` and scoring
+    /// Stage 4 axes; 1 runs both serially.
+    pub threads: usize,
+    /// `--fail-on-warn`: fail the run, after its reports are written, when
+    /// anything was warned about.
+    pub fail_on_warn: bool,
+    pub emit_drivers: bool,
+    /// Unit of the entropy driver columns (`--entropy-unit`).
+    pub entropy_unit: EntropyUnit,
+    /// Append the per-cell confidence components to `nuclearqc.tsv`
+    /// (`--confidence-breakdown`).
+    pub confidence_breakdown: bool,
+    /// Write `pipeline_step.json` in standalone mode too.
+    pub emit_step_json: bool,
+    /// Write `nuclearqc_obs.csv` for AnnData.
+    pub emit_obs_csv: bool,
+    /// Write `program_shares.tsv` (`--emit-program-shares`).
+    pub emit_program_shares: bool,
+    /// Write `axis_correlations.tsv` (`--emit-axis-correlations`).
+    pub emit_axis_correlations: bool,
+    /// Write `metrics_long.tsv` (`--emit-long`).
+    pub emit_long: bool,
+    /// `--cell-stdout`: stream the cell-mode `nuclearqc.tsv` to stdout; the
+    /// other reports still go to `out_dir`.
+    pub cell_stdout: bool,
+    /// Explain `Unclassified` cells in `nuclearqc.tsv` (`--explain-regimes`).
+    pub explain_regimes: bool,
+    /// Panel whose top contributing genes per cell go to
+    /// `panel_<id>_contributions.tsv` (`--explain-panel`).
+    pub explain_panel: Option<String>,
+    /// Report per-cell axis percentile ranks (`--axis-percentiles`).
+    pub axis_percentiles: bool,
+    /// Append per-cell detected-gene counts of the key panels to
+    /// `nuclearqc.tsv` (`--emit-panel-detection`).
+    pub emit_panel_detection: bool,
+    /// Write the normalized matrix Stage 3 scored under `normalized/`.
+    pub emit_normalized_mtx: bool,
+    pub output_prefix: String,
+    /// `--chunk-cells`: keep one chunk of counts in memory through Stages
+    /// 2–4; the per-cell outputs of all cells are still held.
+    pub chunk_cells: Option<usize>,
+    /// Seed for randomized steps; recorded in the reports even when no step
+    /// draws from it.
+    pub seed: u64,
+    /// Write the report files into `out_dir`.
+    pub write_reports: bool,
+}
+
+impl RunConfig {
+    /// CLI defaults: immune-aware scoring on log-normalized counts, cell
+    /// report, standalone mode.
+    pub fn new(input_dir: impl Into<PathBuf>, out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            args: Vec::new(),
+            input_dir: input_dir.into(),
+            input_format: InputFormat::TenX,
+            out_dir: out_dir.into(),
+            cache_path: None,
+            report_mode: ReportMode::Cell,
+            meta_path: None,
+            extra_meta_paths: Vec::new(),
+            meta_by_order: false,
+            clusters_path: None,
+            contrast: None,
+            contrast_reference: None,
+            normalize: true,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_codec: CacheCodec::None,
+            profile: NamedProfile::Immune,
+            confidence_model: None,
+            custom_composite: None,
+            run_mode: RunMode::Standalone,
+            cea_ribo_adjust: false,
+            sum_mode: SumMode::Blocked,
+            relative_within: RelativeWithin::Global,
+            relative_min_group_cells: ThresholdProfile::default_v1().relative_min_group_cells,
+            panel_score: PanelScoreMode::Sum,
+            winsorize_panels: None,
+            mito_max: None,
+            ribo_max: None,
+            control_prefixes: Vec::new(),
+            tail_thresholds: None,
+            legacy_quantiles: false,
+            key_panels: None,
+            panels_path: None,
+            only_panels: None,
+            disable_panels: Vec::new(),
+            min_mappable: MinMappable::default(),
+            qc_gates: QcGates::default(),
+            strict_mappable: false,
+            panel_rule_max_genes: panels::loader::DEFAULT_MAX_RULE_GENES,
+            assume_transposed: false,
+            feature_columns: FeatureColumns::default(),
+            strict_input: false,
+            max_cells: None,
+            threads: 1,
+            fail_on_warn: false,
+            emit_drivers: false,
+            entropy_unit: EntropyUnit::Nats,
+            confidence_breakdown: false,
+            emit_step_json: false,
+            emit_obs_csv: false,
+            emit_program_shares: false,
+            emit_axis_correlations: false,
+            emit_long: false,
+            cell_stdout: false,
+            explain_regimes: false,
+            explain_panel: None,
+            axis_percentiles: false,
+            emit_panel_detection: false,
+            emit_normalized_mtx: false,
+            output_prefix: String::new(),
+            chunk_cells: None,
+            seed: DEFAULT_SEED,
+            write_reports: true,
+        }
+    }
+
+    /// Scoring mode of the selected profile.
+    pub fn scoring_mode(&self) -> NuclearScoringMode {
+        self.profile.scoring_mode()
+    }
+
+    /// How the 10x loaders read `input_dir`; `--chunk-cells` streams the
+    /// matrix later, so only its tables are read up front.
+    pub fn tenx_options(&self) -> TenxOptions {
+        TenxOptions {
+            assume_transposed: self.assume_transposed,
+            stream_matrix: self.chunk_cells.is_some(),
+            feature_columns: self.feature_columns,
+        }
+    }
+
+    /// `--meta` for the loaders to join by barcode; `None` under
+    /// `--meta-by-order`, where rows are attached by position after loading.
+    pub fn keyed_meta_path(&self) -> Option<&Path> {
+        self.meta_path.as_deref().filter(|_| !self.meta_by_order)
+    }
+
+    /// Completes `meta` as joined by the loaders from [`Self::keyed_meta_path`]:
+    /// attaches a positional `--meta-by-order` file and merges any further
+    /// `--meta` files onto `barcodes`.
+    pub fn attach_meta(
+        &self,
+        meta: &mut Option<CellMeta>,
+        barcodes: &[String],
+    ) -> Result<(), InputError> {
+        if let Some(path) = &self.meta_path
+            && self.meta_by_order
+        {
+            *meta = Some(load_meta_by_order(path, barcodes.len())?);
+        }
+        if let Some(meta) = meta {
+            for path in &self.extra_meta_paths {
+                merge_meta(meta, path, barcodes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets `normalize` and `normalize_mode` from `--normalize`/`--raw` and
+    /// `--normalize-mode`, either `None` when not given. Call after the
+    /// profile is set: its scoring mode picks the default.
+    pub fn resolve_normalization(
+        &mut self,
+        normalize: Option<bool>,
+        normalize_mode: Option<NormalizeMode>,
+    ) -> Result<(), String> {
+        if normalize_mode.is_some() && normalize == Some(false) {
+            return Err("--normalize-mode cannot be combined with --raw".to_string());
+        }
+        // Immune-aware thresholds were calibrated on log-normalized data.
+        self.normalize = normalize.unwrap_or(
+            normalize_mode.is_some() || self.scoring_mode() == NuclearScoringMode::ImmuneAware,
+        );
+        self.normalize_mode = normalize_mode.unwrap_or(NormalizeMode::LogCp10k);
+        Ok(())
+    }
+
+    /// Rejects settings that cannot be combined, naming the CLI flags.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.only_panels.is_some() && !self.disable_panels.is_empty() {
+            return Err("--only-panels cannot be combined with --disable-panels".to_string());
+        }
+        if self.chunk_cells.is_some() && self.cache_normalized {
+            return Err("--chunk-cells cannot be combined with --cache-normalized".to_string());
+        }
+        if self.max_cells.is_some() && self.chunk_cells.is_some() {
+            return Err("--max-cells cannot be combined with --chunk-cells".to_string());
+        }
+        if self.max_cells.is_some() && self.cache_normalized {
+            return Err("--max-cells cannot be combined with --cache-normalized".to_string());
+        }
+        if self.cell_stdout && matches!(self.report_mode, ReportMode::Sample) {
+            return Err("--cell-stdout cannot be combined with --mode sample".to_string());
+        }
+        if self.cell_stdout && matches!(self.report_mode, ReportMode::Cluster) {
+            return Err("--cell-stdout cannot be combined with --mode cluster".to_string());
+        }
+        if self.contrast_reference.is_some() && self.contrast.is_none() {
+            return Err("--contrast-reference requires --contrast".to_string());
+        }
+        if self.meta_by_order && self.meta_path.is_none() {
+            return Err("--meta-by-order requires --meta".to_string());
+        }
+        // The shared cache carries its own gene table.
+        if self.cache_path.is_some() && self.feature_columns != FeatureColumns::default() {
+            return Err(
+                "--feature-id-col/--feature-symbol-col cannot be combined with --cache".to_string(),
+            );
+        }
+        if let Some(id) = &self.explain_panel
+            && id.contains(['/', '\\'])
+        {
+            return Err(format!(
+                "--explain-panel {id}: a panel id with a path separator cannot name panel_<id>_contributions.tsv"
+            ));
+        }
+        if self.input_format == InputFormat::Loom {
+            for (set, flag) in [
+                (self.chunk_cells.is_some(), "--chunk-cells"),
+                (self.cache_normalized, "--cache-normalized"),
+                (self.cache_path.is_some(), "--cache"),
+                (self.meta_by_order, "--meta-by-order"),
+                (self.assume_transposed, "--assume-transposed"),
+                (
+                    self.feature_columns != FeatureColumns::default(),
+                    "--feature-id-col/--feature-symbol-col",
+                ),
+            ] {
+                if set {
+                    return Err(format!(
+                        "{flag} cannot be combined with --input-format loom"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// In-memory results of [`run_pipeline`], one entry per cell in barcode order.
+#[derive(Debug, Clone)]
+pub struct RunOutputs {
+    pub barcodes: Vec<String>,
+    pub axes: Axes,
+    pub scores: CompositeScores,
+    pub classifications: Vec<Classification>,
+    /// The data behind `summary.json`.
+    pub summary: SummaryData,
+    /// Directory the reports were written to, if any.
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Input(InputError),
+    Stage2(Stage2Error),
+    Io(std::io::Error),
+    /// Invalid settings or panel selection.
+    Config(String),
+    /// The run warned under `--fail-on-warn`; its reports are written.
+    Warnings(WarningLog),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Input(e) => write!(f, "{e}"),
+            RunError::Stage2(e) => write!(f, "{e}"),
+            RunError::Io(e) => write!(f, "IO error: {e}"),
+            RunError::Config(msg) => write!(f, "{msg}"),
+            RunError::Warnings(log) => {
+                write!(f, "--fail-on-warn: {} warnings (", log.total())?;
+                for (i, (source, count)) in log.by_source().iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{sep}{source}: {count}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+impl From<InputError> for RunError {
+    fn from(value: InputError) -> Self {
+        RunError::Input(value)
+    }
+}
+
+impl From<Stage2Error> for RunError {
+    fn from(value: Stage2Error) -> Self {
+        RunError::Stage2(value)
+    }
+}
+
+impl From<std::io::Error> for RunError {
+    fn from(value: std::io::Error) -> Self {
+        RunError::Io(value)
+    }
+}
+
+/// Runs Stages 1-7 on one dataset and returns the per-cell results.
+///
+/// Reports are written under `config.out_dir` only when
+/// `config.write_reports` is set; `--chunk-cells` spill files go there
+/// either way.
+pub fn run_pipeline(config: RunConfig) -> Result<RunOutputs, RunError> {
+    let mut timer = StageTimer::start();
+    let panel_defs = resolve_panels(&config)?;
+    let (mut bundle, loaded_counts, input_source, shared_bin) = match config.input_format {
+        InputFormat::TenX => {
+            let (bundle, input_source, shared_bin) = load_bundle(&config)?;
+            (bundle, None, input_source, shared_bin)
+        }
+        InputFormat::Loom => {
+            let (bundle, csc) = load_loom(&config)?;
+            (bundle, Some(csc), "loom".to_string(), None)
+        }
+    };
+    let prefixes = control_prefixes(&config.control_prefixes);
+    // Stage 2 reads a plain MTX whole anyway: reading it here splits the
+    // controls off in memory rather than in a pass of their own. Chunked
+    // runs sum them while spilling; a normalized-cache hit never parses the
+    // matrix, so only then do they take their own pass.
+    let csc = match loaded_counts {
+        Some(csc) => Some(csc),
+        None => match bundle.source == InputSourceKind::TenX
+            && config.chunk_cells.is_none()
+            && has_controls(&bundle.gene_index, &prefixes)
+        {
+            true if !config.cache_normalized => Some(read_bundle_counts(&bundle)?),
+            _ => None,
+        },
+    };
+    let csc = exclude_controls(&mut bundle, csc, &prefixes)?;
+    if config.chunk_cells.is_none() {
+        read_control_counts(&mut bundle)?;
+    }
+    let csc = match config.max_cells {
+        Some(max_cells) if max_cells < bundle.n_cells => {
+            Some(subsample_cells(&mut bundle, csc, max_cells)?)
+        }
+        _ => csc,
+    };
+    let accessor =
+        csc.map(|csc| counts_accessor(csc, bundle.n_genes_indexed, &stage2_params(&config)));
+    timer.lap("input");
+    run_bundle(
+        config,
+        &panel_defs,
+        bundle,
+        accessor,
+        input_source,
+        shared_bin,
+        timer,
+    )
+}
+
+/// Cells × features counts in CSR layout, as scipy and AnnData hold them.
+#[derive(Debug, Clone, Copy)]
+pub struct CsrCounts<'a> {
+    /// `n_cells + 1` offsets into `indices` and `data`.
+    pub indptr: &'a [i64],
+    /// Feature index of each stored value.
+    pub indices: &'a [i64],
+    /// Raw counts; fractional values are truncated and negative ones clamped
+    /// (or rejected under `strict_input`) as in an MTX.
+    pub data: &'a [f64],
+    pub gene_symbols: &'a [String],
+    pub barcodes: &'a [String],
+}
+
+/// [`run_pipeline`] over counts already in memory. Gives the same results as
+/// an MTX of the same counts; the input, metadata, cache and chunking
+/// settings of `config` do not apply, apart from `strict_input`.
+pub fn run_counts(config: RunConfig, counts: CsrCounts<'_>) -> Result<RunOutputs, RunError> {
+    if counts.indptr.len() != counts.barcodes.len() + 1 {
+        return Err(RunError::Config(format!(
+            "CSR indptr has {} entries for {} barcodes",
+            counts.indptr.len(),
+            counts.barcodes.len()
+        )));
+    }
+    let mut timer = StageTimer::start();
+    let panel_defs = resolve_panels(&config)?;
+    let mut bundle = bundle_from_symbols(counts.gene_symbols, counts.barcodes.to_vec());
+    let csc = csc_from_cell_rows(
+        counts.indptr,
+        counts.indices,
+        counts.data,
+        &bundle.gene_index,
+        config.strict_input,
+    )?;
+    let csc = exclude_controls(
+        &mut bundle,
+        Some(csc),
+        &control_prefixes(&config.control_prefixes),
+    )?
+    .expect("counts kept in memory");
+    let accessor = counts_accessor(csc, bundle.n_genes_indexed, &stage2_params(&config));
+    timer.lap("input");
+    run_bundle(
+        config,
+        &panel_defs,
+        bundle,
+        Some(accessor),
+        "in-memory".to_string(),
+        None,
+        timer,
+    )
+}
+
+const MIB: f64 = (1u64 << 20) as f64;
+
+// Wall-clock laps between stages and the run's own warnings, for the
+// `performance.json` and `pipeline_step.json`. Laps are taken around
+// stages, never inside the per-cell loops.
+struct StageTimer {
+    last: Instant,
+    laps: Vec<(&'static str, f64)>,
+    warnings: WarningScope,
+}
+
+impl StageTimer {
+    fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            laps: Vec::new(),
+            warnings: WarningScope::begin(),
+        }
+    }
+
+    fn lap(&mut self, stage: &'static str) {
+        let now = Instant::now();
+        let seconds = now.duration_since(self.last).as_secs_f64();
+        crate::info!("{stage} finished in {seconds:.3}s");
+        self.laps.push((stage, seconds));
+        self.last = now;
+    }
+
+    fn warnings(&self) -> WarningLog {
+        self.warnings.log()
+    }
+}
+
+// Files the bundle was read from, with their FNV-1a 64 hashes.
+fn input_hashes<'a>(
+    bundle: &'a InputBundle,
+    meta_paths: impl Iterator<Item = &'a Path>,
+) -> Result<Vec<(String, String)>, RunError> {
+    let mut paths: Vec<&Path> = match bundle.source {
+        InputSourceKind::TenX => vec![
+            &bundle.mtx_path,
+            &bundle.features_path,
+            &bundle.barcodes_path,
+        ],
+        InputSourceKind::OrganelleBin => bundle.shared_bin_path.as_deref().into_iter().collect(),
+        InputSourceKind::InMemory => Vec::new(),
+        InputSourceKind::Loom => vec![&bundle.mtx_path],
+    };
+    paths.extend(meta_paths);
+    paths
+        .into_iter()
+        .map(|path| {
+            let hash = input::cache::hash_file(path)?;
+            Ok((path.display().to_string(), format!("{hash:016x}")))
+        })
+        .collect()
+}
+
+fn resolve_panels(config: &RunConfig) -> Result<Vec<PanelDef>, RunError> {
+    Ok(panels::loader::select_panel_defs(
+        panels::loader::resolve_panel_defs(config.panels_path.as_deref())?,
+        config.only_panels.as_deref(),
+        &config.disable_panels,
+    )?)
+}
+
+fn stage2_params(config: &RunConfig) -> Stage2Params {
+    Stage2Params {
+        normalize: config.normalize,
+        normalize_mode: config.normalize_mode,
+        pearson_theta: config.pearson_theta,
+        cache_normalized: config.cache_normalized,
+        cache_path: None,
+        cache_codec: config.cache_codec,
+    }
+}
+
+/// Reads the `.loom` file named by `config` with its counts.
+#[cfg(feature = "h5")]
+fn load_loom(config: &RunConfig) -> Result<(InputBundle, CscMatrix), RunError> {
+    let (mut bundle, csc) = input::load_input_loom(
+        &config.input_dir,
+        config.keyed_meta_path(),
+        config.strict_input,
+    )?;
+    config.attach_meta(&mut bundle.meta, &bundle.barcodes)?;
+    Ok((bundle, csc))
+}
+
+#[cfg(not(feature = "h5"))]
+fn load_loom(_config: &RunConfig) -> Result<(InputBundle, CscMatrix), RunError> {
+    Err(RunError::Config(
+        "--input-format loom needs kira-nuclearqc built with the h5 feature".to_string(),
+    ))
+}
+
+/// Reads the input named by `config`, returning the bundle, a label for its
+/// source and the shared bin name, if one was used.
+fn load_bundle(config: &RunConfig) -> Result<(InputBundle, String, Option<String>), RunError> {
+    let meta_path = config.keyed_meta_path();
+    let loaded = if let Some(cache_path) = config.cache_path.as_ref() {
+        if !cache_path.exists() {
+            return Err(RunError::Config(format!(
+                "shared cache path does not exist: {}",
+                cache_path.display()
+            )));
+        }
+        match load_input_organelle(&config.input_dir, meta_path, cache_path) {
+            Ok(bundle) => (
+                bundle,
+                cache_path.display().to_string(),
+                Some(cache_path.display().to_string()),
+            ),
+            Err(err) => {
+                crate::warn!(
+                    "failed reading shared cache {}: {}; falling back to 10x MTX reading",
+                    cache_path.display(),
+                    err
+                );
+                (
+                    load_input_tenx(&config.input_dir, meta_path, config.tenx_options())?,
+                    "10x".to_string(),
+                    None,
+                )
+            }
+        }
+    } else {
+        match config.run_mode {
+            RunMode::Standalone => (
+                load_input_tenx(&config.input_dir, meta_path, config.tenx_options())?,
+                "10x".to_string(),
+                None,
+            ),
+            RunMode::Pipeline => {
+                let resolution = resolve_shared_bin(&config.input_dir)?;
+                if resolution.exists && config.feature_columns != FeatureColumns::default() {
+                    crate::info!(
+                        "--feature-id-col/--feature-symbol-col: reading 10x MTX instead of shared cache {}",
+                        resolution.name
+                    );
+                    (
+                        load_input_tenx(&config.input_dir, meta_path, config.tenx_options())?,
+                        "10x".to_string(),
+                        None,
+                    )
+                } else if resolution.exists {
+                    match load_input_organelle(&config.input_dir, meta_path, &resolution.path) {
+                        Ok(bundle) => (
+                            bundle,
+                            "kira-organelle.bin".to_string(),
+                            Some(resolution.name),
+                        ),
+                        Err(err) => {
+                            crate::warn!(
+                                "failed reading shared cache {}: {}; falling back to 10x MTX reading (slower).",
+                                resolution.path.display(),
+                                err
+                            );
+                            (
+                                load_input_tenx(
+                                    &config.input_dir,
+                                    meta_path,
+                                    config.tenx_options(),
+                                )?,
+                                "10x".to_string(),
+                                None,
+                            )
+                        }
+                    }
+                } else {
+                    crate::warn!(
+                        "--run-mode pipeline requested but shared cache file {} was not found; falling back to 10x MTX reading (slower).",
+                        resolution.name
+                    );
+                    (
+                        load_input_tenx(&config.input_dir, meta_path, config.tenx_options())?,
+                        "10x".to_string(),
+                        None,
+                    )
+                }
+            }
+        }
+    };
+    let (mut bundle, input_source, shared_bin) = loaded;
+    bundle.strict_input = config.strict_input;
+    bundle.parse_threads = config.threads;
+    config.attach_meta(&mut bundle.meta, &bundle.barcodes)?;
+    Ok((bundle, input_source, shared_bin))
+}
+
+/// `--max-cells`: keeps `max_cells` cells evenly strided over the input, in
+/// input order, and returns their raw counts; the bundle's barcodes and
+/// metadata are trimmed to match.
+fn subsample_cells(
+    bundle: &mut InputBundle,
+    csc: Option<CscMatrix>,
+    max_cells: usize,
+) -> Result<CscMatrix, InputError> {
+    let n_cells = bundle.n_cells;
+    let kept = (0..max_cells)
+        .map(|i| i * n_cells / max_cells)
+        .collect::<Vec<_>>();
+    let counts = match csc {
+        Some(csc) => csc.select_columns(&kept),
+        None => read_cell_counts(bundle, &kept)?,
+    };
+    bundle.barcodes = kept.iter().map(|&c| bundle.barcodes[c].clone()).collect();
+    if let Some(meta) = bundle.meta.as_mut() {
+        meta.rows = kept.iter().map(|&c| meta.rows[c].clone()).collect();
+    }
+    if let Some(control_counts) = bundle.control_counts.as_mut() {
+        *control_counts = kept.iter().map(|&c| control_counts[c]).collect();
+    }
+    bundle.n_cells = max_cells;
+    bundle.sampled_from = Some(n_cells);
+    crate::info!("--max-cells: scoring {max_cells} of {n_cells} cells");
+    Ok(counts)
+}
+
+// Stages 2-7 on a loaded bundle; `accessor` replaces Stage 2's own.
+fn run_bundle(
+    config: RunConfig,
+    panel_defs: &[PanelDef],
+    bundle: InputBundle,
+    accessor: Option<Box<dyn ExprAccessor>>,
+    input_source: String,
+    shared_bin: Option<String>,
+    mut timer: StageTimer,
+) -> Result<RunOutputs, RunError> {
+    let out_dir = resolve_output_dir(&config.out_dir, config.run_mode);
+
+    if config.scoring_mode() == NuclearScoringMode::StrictBulk && !config.normalize {
+        crate::warn!(
+            "strict nuclear scoring on raw counts; pass --normalize for log-normalized input"
+        );
+    }
+    let excluded_panels = check_panel_mappability(&config, &bundle, panel_defs)?;
+    let kept_defs;
+    let panel_defs = if excluded_panels.is_empty() {
+        panel_defs
+    } else {
+        kept_defs = panel_defs
+            .iter()
+            .filter(|d| !excluded_panels.iter().any(|p| p.panel_id == d.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        kept_defs.as_slice()
+    };
+    // Before Stage 2, so a bad id fails before any output is written.
+    if let Some(id) = &config.explain_panel {
+        if excluded_panels.iter().any(|p| p.panel_id == *id) {
+            return Err(RunError::Config(format!(
+                "--explain-panel {id}: panel was excluded for low mappability"
+            )));
+        }
+        if !panel_defs.iter().any(|d| d.id == *id) {
+            return Err(RunError::Config(format!(
+                "unknown --explain-panel panel: {id}"
+            )));
+        }
+    }
+    let stage2 = stage2_params(&config);
+    let confidence_model = config
+        .confidence_model
+        .unwrap_or(ConfidenceModel::for_scoring_mode(config.scoring_mode()));
+    let mut thresholds = config.profile.thresholds();
+    thresholds.cea_ribo_adjust = config.cea_ribo_adjust;
+    thresholds.sum_mode = config.sum_mode;
+    if config.legacy_quantiles {
+        thresholds.quantile_method = QuantileMethod::LegacyCeil;
+    }
+    thresholds.relative_within = config.relative_within;
+    thresholds.relative_min_group_cells = config.relative_min_group_cells;
+    thresholds.panel_score = config.panel_score;
+    thresholds.winsorize_panels = config.winsorize_panels;
+    if let Some(max) = config.mito_max {
+        thresholds.mito_frac_max = max;
+    }
+    if let Some(max) = config.ribo_max {
+        thresholds.ribo_frac_max = max;
+    }
+    if let Some([trs, nps, rls]) = config.tail_thresholds {
+        thresholds.tail_trs_min = trs;
+        thresholds.tail_nps_min = nps;
+        thresholds.tail_rls_max = rls;
+    }
+    match &config.key_panels {
+        Some(key_panels) => thresholds.key_panels = key_panels.clone(),
+        // Default key panels that were deselected are simply not checked.
+        None => thresholds
+            .key_panels
+            .retain(|id| panel_defs.iter().any(|d| d.id == *id)),
+    }
+    thresholds
+        .key_panels
+        .retain(|id| !excluded_panels.iter().any(|p| p.panel_id == *id));
+    thresholds.validate().map_err(RunError::Config)?;
+
+    let mut export = if config.emit_normalized_mtx {
+        let dir = out_dir.join(format!("{}{NORMALIZED_EXPORT_DIR}", config.output_prefix));
+        Some(NormalizedExport::create(
+            &dir,
+            &bundle.gene_index,
+            &bundle.barcodes,
+        )?)
+    } else {
+        None
+    };
+    let (stage3, cells) = match (accessor, config.chunk_cells) {
+        (None, Some(chunk_cells)) => {
+            // Chunks normalize and score together; all of it counts as stage3.
+            timer.lap("stage2");
+            run_chunked(
+                &bundle,
+                &stage2,
+                chunk_cells,
+                &out_dir.join(CHUNK_SPILL_DIR),
+                panels::loader::load_panels(
+                    bundle.species,
+                    &bundle.gene_index,
+                    panel_defs,
+                    config.panel_rule_max_genes,
+                )?,
+                config.explain_panel.as_deref(),
+                &thresholds,
+                export.as_mut(),
+            )?
+        }
+        (accessor, _) => {
+            let accessor = match accessor {
+                Some(accessor) => accessor,
+                None => build_expr_accessor(&bundle, &stage2)?,
+            };
+            timer.lap("stage2");
+            if let Some(mut export) = export.take() {
+                export.append(accessor.as_ref())?;
+                export.finish()?;
+                timer.lap("export");
+            }
+            let stage3 = run_stage3(
+                &bundle,
+                accessor.as_ref(),
+                panel_defs,
+                config.panel_rule_max_genes,
+                config.explain_panel.as_deref(),
+            )?;
+            (
+                stage3,
+                CellScan::scan(accessor.as_ref(), &bundle, &thresholds),
+            )
+        }
+    };
+    // The chunked scan appends as it goes; only its export is still open.
+    if let Some(export) = export {
+        export.finish()?;
+    }
+    timer.lap("stage3");
+    if let Some(unknown) = thresholds
+        .key_panels
+        .iter()
+        .find(|id| !stage3.panels.panels.iter().any(|p| p.id == id.as_str()))
+    {
+        return Err(RunError::Config(format!("unknown key panel: {unknown}")));
+    }
+    let (sample, condition, species_per_cell, cluster_labels) = extract_meta(&bundle);
+    let cluster_labels = match &config.clusters_path {
+        Some(path) => Some(load_clusters(path, &bundle.barcodes)?),
+        None => cluster_labels,
+    };
+    if matches!(config.report_mode, ReportMode::Cluster) && cluster_labels.is_none() {
+        return Err(RunError::Config(
+            "--mode cluster needs --clusters or a cluster column in --meta".to_string(),
+        ));
+    }
+    let contrast = match &config.contrast {
+        Some(column) => {
+            let meta = bundle
+                .meta
+                .as_ref()
+                .ok_or_else(|| RunError::Config("--contrast needs --meta".to_string()))?;
+            Some(
+                ContrastDesign::new(meta, column, config.contrast_reference.as_deref())
+                    .map_err(RunError::Config)?,
+            )
+        }
+        None => None,
+    };
+    if bundle.species == Species::Mixed {
+        if species_per_cell.is_some() {
+            crate::info!(
+                "mixed human/mouse reference; reporting per-cell species from the metadata species column"
+            );
+        } else {
+            crate::warn!(
+                "mixed human/mouse reference detected (human_score={:.3}, mouse_score={:.3}); panels map to human genes first, then mouse. Add a species column to --meta to label cells",
+                bundle.species_call.human_score,
+                bundle.species_call.mouse_score
+            );
+        }
+    }
+    if thresholds.relative_within == RelativeWithin::Sample && sample.is_none() {
+        crate::warn!(
+            "--relative-within sample requested but metadata has no sample column; using global anchors"
+        );
+        thresholds.relative_within = RelativeWithin::Global;
+    }
+    let metadata_columns = bundle
+        .meta
+        .as_ref()
+        .map(|meta| summarize_meta(meta, thresholds.quantile_method))
+        .unwrap_or_default();
+    let groups_by_sample = matches!(config.report_mode, ReportMode::Sample)
+        || thresholds.relative_within == RelativeWithin::Sample;
+    if groups_by_sample
+        && let Some(column) = metadata_columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case("sample"))
+        && column.cardinality > MAX_GROUPS
+    {
+        crate::warn!(
+            "metadata sample column has {} distinct values; grouping by sample expects at most {MAX_GROUPS}, check that --meta names the right column",
+            column.cardinality
+        );
+    }
+    let CellScan {
+        stage4: stage4_cells,
+        pct_mito,
+        pct_ribo,
+        libsize: libsize_vec,
+        nnz: nnz_vec,
+        control_counts,
+    } = cells;
+    let control_counts = bundle
+        .control_counts
+        .as_deref()
+        .or(control_counts.as_deref());
+    let pct_control = control_fraction(control_counts, &libsize_vec);
+    let stage4 = run_stage4(
+        stage4_cells,
+        &bundle.gene_index,
+        bundle.species,
+        &stage3.panels,
+        &stage3.scores,
+        &thresholds,
+        &Stage4Covariates {
+            ribo_fraction: Some(&pct_ribo),
+            sample_labels: sample.as_deref(),
+        },
+        config.threads,
+    );
+    log_scoring_mode(
+        config.scoring_mode(),
+        &stage3,
+        &stage4,
+        thresholds.quantile_method,
+    );
+    timer.lap("stage4");
+
+    let key_panel_coverage_median = panels::compute_key_panel_coverage(
+        &stage3.panels,
+        &stage3.scores,
+        thresholds.quantile_method,
+    );
+    let ambient_rna_risk = vec![false; bundle.n_cells];
+    let axis_p90 = [
+        p90(&stage4.axes.iaa, thresholds.quantile_method),
+        p90(&stage4.axes.dfa, thresholds.quantile_method),
+        p90(&stage4.axes.nsai, thresholds.quantile_method),
+    ];
+
+    let (program_sum, sum_tf, proliferation_share, panel_nonzero_fraction) =
+        compute_panel_signals(&stage3.panels, &stage3.scores);
+    let cell_cycle_phase = panels::compute_cell_cycle_phase(&stage3.panels, &stage3.scores);
+    let key_panels_missing =
+        panels::compute_key_panels_missing(&stage3.panels, &stage3.scores, &thresholds.key_panels);
+
+    let stage5 = run_stage5(&Stage5Inputs {
+        axes: &stage4.axes,
+        drivers: &stage4.drivers,
+        thresholds: &thresholds,
+        n_genes_mappable: Some(bundle.n_genes_indexed as u32),
+        key_panel_coverage_median: Some(&key_panel_coverage_median),
+        ambient_rna_risk: Some(&ambient_rna_risk),
+        key_panels_missing: Some(&key_panels_missing),
+        panel_nonzero_fraction: Some(&panel_nonzero_fraction),
+        mito_fraction: Some(&pct_mito),
+        axis_p90: Some(axis_p90),
+        scoring_mode: config.scoring_mode(),
+        confidence_model,
+        include_ddr: true,
+        custom_composite: config.custom_composite.as_ref(),
+    });
+
+    timer.lap("stage5");
+    let stage6 = run_stage6(&Stage6Inputs {
+        tbi: &stage4.axes.tbi,
+        rci: &stage4.axes.rci,
+        pds: &stage4.axes.pds,
+        trs: &stage4.axes.trs,
+        nsai: &stage4.axes.nsai,
+        iaa: &stage4.axes.iaa,
+        dfa: &stage4.axes.dfa,
+        cea: &stage4.axes.cea,
+        rss: &stage4.axes.rss,
+        drbi: &stage4.axes.drbi,
+        cci: &stage4.axes.cci,
+        trci: &stage4.axes.trci,
+        scores: &stage5.scores,
+        drivers: &stage4.drivers,
+        thresholds: &thresholds,
+        scoring_mode: config.scoring_mode(),
+        key_panel_coverage_median: Some(&key_panel_coverage_median),
+        key_panels_missing: Some(&key_panels_missing),
+        sum_tf_panels: Some(&sum_tf),
+        ambient_rna_risk: Some(&ambient_rna_risk),
+        mito_fraction: Some(&pct_mito),
+        ribo_fraction: Some(&pct_ribo),
+        proliferation_program_share: Some(&proliferation_share),
+        cell_cycle_phase: Some(&cell_cycle_phase),
+        program_sum: Some(&program_sum),
+    });
+
+    let expressed_vec = stage4
+        .drivers
+        .iter()
+        .map(|d| d.expressed_genes)
+        .collect::<Vec<_>>();
+    let min_expr_vec = stage4
+        .drivers
+        .iter()
+        .map(|d| d.min_nonzero_expr)
+        .collect::<Vec<_>>();
+
+    timer.lap("stage6");
+
+    let input = Stage7Input {
+        barcodes: &bundle.barcodes,
+        sample: sample.as_deref(),
+        condition: condition.as_deref(),
+        species_per_cell: species_per_cell.as_deref(),
+        cluster_labels: cluster_labels.as_deref(),
+        contrast: contrast.as_ref(),
+        species_global: format!("{:?}", bundle.species),
+        species_call: bundle.species_call,
+        sampled_from: bundle.sampled_from,
+
+        libsize: &libsize_vec,
+        nnz: &nnz_vec,
+        expressed_genes: &expressed_vec,
+        pct_mito: &pct_mito,
+        pct_ribo: &pct_ribo,
+        pct_control: &pct_control,
+        cell_cycle_phase: &cell_cycle_phase,
+        min_nonzero_expr: &min_expr_vec,
+
+        axes_tbi: &stage4.axes.tbi,
+        axes_rci: &stage4.axes.rci,
+        axes_pds: &stage4.axes.pds,
+        axes_trs: &stage4.axes.trs,
+        axes_nsai: &stage4.axes.nsai,
+        axes_iaa: &stage4.axes.iaa,
+        axes_dfa: &stage4.axes.dfa,
+        axes_cea: &stage4.axes.cea,
+        ddr_rss: &stage4.axes.rss,
+        ddr_drbi: &stage4.axes.drbi,
+        ddr_cci: &stage4.axes.cci,
+        ddr_trci: &stage4.axes.trci,
+        genome_stability: &stage4.genome_stability,
+        genome_stability_norm: &stage4.genome_stability_norm,
+        genome_stability_panel_version: stage4.genome_stability_panel_version,
+        genome_stability_panel_audits: &stage4.genome_stability_panel_audits,
+
+        scores: &stage5.scores,
+        drivers: &stage5.drivers,
+        thresholds: &thresholds,
+        cea_ribo_slope: stage4.cea_ribo_slope,
+        panel_caps: stage4.panel_caps.as_deref(),
+        activation_mode: format!("{:?}", thresholds.activation_mode),
+
+        classifications: &stage6,
+
+        panel_set: &stage3.panels,
+        panel_audits: &stage3.audits,
+        panel_scores: &stage3.scores,
+        excluded_panels: &excluded_panels,
+        metadata_columns: &metadata_columns,
+        qc_gates: config.qc_gates,
+
+        tool_name: "kira-nuclearqc".to_string(),
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: git_hash(&PathBuf::from(".")),
+        simd_backend: simd::backend_name().to_string(),
+        seed: config.seed,
+        stage_seconds: timer.laps.clone(),
+        peak_rss_bytes: crate::memory::peak_rss_bytes(),
+
+        n_genes_raw: bundle.n_features_raw,
+        n_genes_mappable: bundle.n_genes_indexed,
+        n_control_features: bundle.n_control_features,
+
+        normalize: config.normalize,
+        scale: 10_000.0,
+        log1p: config.normalize && config.normalize_mode == NormalizeMode::LogCp10k,
+        confidence_breakdown: Some(&stage5.scores.confidence_breakdown),
+        confidence_breakdown_columns: config
+            .confidence_breakdown
+            .then(|| confidence_model.breakdown_columns()),
+        custom_composite: config
+            .custom_composite
+            .as_ref()
+            .zip(stage5.custom.as_deref())
+            .map(|(composite, values)| (composite.expr(), values)),
+        axis_drivers: config.emit_drivers.then_some(stage4.drivers.as_slice()),
+        entropy_unit: config.entropy_unit,
+        output_prefix: config.output_prefix.clone(),
+        emit_obs_csv: config.emit_obs_csv,
+        emit_program_shares: config.emit_program_shares,
+        emit_axis_correlations: config.emit_axis_correlations,
+        emit_long: config.emit_long,
+        cell_stdout: config.cell_stdout,
+        explain_regimes: config.explain_regimes,
+        panel_contributions: stage3
+            .contributions
+            .as_ref()
+            .map(|contributions| (contributions, &bundle.gene_index)),
+        axis_percentiles: config.axis_percentiles,
+        emit_panel_detection: config.emit_panel_detection,
+        scoring_mode: match config.scoring_mode() {
+            NuclearScoringMode::ImmuneAware => "immune-aware (default)".to_string(),
+            NuclearScoringMode::StrictBulk => "strict (bulk-oriented)".to_string(),
+        },
+        confidence_model: confidence_model.label().to_string(),
+        profile: config.profile.as_str().to_string(),
+        provenance: Some(RunProvenance {
+            args: config.args.clone(),
+            input_dir: config.input_dir.display().to_string(),
+            out_dir: out_dir.display().to_string(),
+            meta_path: config.meta_path.as_ref().map(|p| p.display().to_string()),
+            cache_path: config.cache_path.as_ref().map(|p| p.display().to_string()),
+            panels_path: config.panels_path.as_ref().map(|p| p.display().to_string()),
+            run_mode: config.run_mode.as_str().to_string(),
+            report_mode: match config.report_mode {
+                ReportMode::Cell => "cell",
+                ReportMode::Sample => "sample",
+                ReportMode::Cluster => "cluster",
+            }
+            .to_string(),
+            scoring_mode: match config.scoring_mode() {
+                NuclearScoringMode::ImmuneAware => "immune-aware",
+                NuclearScoringMode::StrictBulk => "strict",
+            }
+            .to_string(),
+            confidence_model: confidence_model.as_str().to_string(),
+            thresholds_profile: config.profile.versioned_name().to_string(),
+            normalize_mode: if config.normalize {
+                config.normalize_mode.as_str()
+            } else {
+                "raw"
+            }
+            .to_string(),
+            cache_normalized: config.cache_normalized,
+            cache_codec: config.cache_codec.as_str().to_string(),
+            assume_transposed: config.assume_transposed,
+        }),
+        pipeline_context: if config.run_mode == RunMode::Pipeline || config.emit_step_json {
+            Some(PipelineContext {
+                input_dir: config.input_dir.display().to_string(),
+                input_source,
+                shared_bin,
+                run_mode: config.run_mode.as_str().to_string(),
+                emit_step_json: config.emit_step_json,
+                // Rereads every input, so only on request.
+                input_hashes: if config.emit_step_json {
+                    input_hashes(
+                        &bundle,
+                        config
+                            .meta_path
+                            .iter()
+                            .chain(&config.extra_meta_paths)
+                            .chain(&config.clusters_path)
+                            .map(PathBuf::as_path),
+                    )?
+                } else {
+                    Vec::new()
+                },
+            })
+        } else {
+            None
+        },
+    };
+
+    let summary = build_summary(&input, config.report_mode);
+    let written = if config.write_reports {
+        write_reports(&input, &out_dir, config.report_mode)?;
+        Some(out_dir)
+    } else {
+        None
+    };
+    timer.lap("stage7");
+    match crate::memory::peak_rss_bytes() {
+        Some(bytes) => crate::info!(
+            "peak RSS {:.1} MiB (estimated {:.1} MiB)",
+            bytes as f64 / MIB,
+            summary.estimated_peak_rss_bytes as f64 / MIB
+        ),
+        None => crate::info!(
+            "peak RSS unavailable (estimated {:.1} MiB)",
+            summary.estimated_peak_rss_bytes as f64 / MIB
+        ),
+    }
+
+    let warnings = timer.warnings();
+    let failed = config.fail_on_warn && warnings.total() > 0;
+    if let Some(out_dir) = &written {
+        write_pipeline_step(&input, &summary, &warnings, failed, out_dir)?;
+    }
+    if failed {
+        return Err(RunError::Warnings(warnings));
+    }
+
+    Ok(RunOutputs {
+        barcodes: bundle.barcodes,
+        axes: stage4.axes,
+        scores: stage5.scores,
+        classifications: stage6,
+        summary,
+        out_dir: written,
+    })
+}
+
+/// Panels mapping less than `--min-mappable-fraction` of their genes, each
+/// warned about; under `--strict-mappable` any such panel fails the run instead.
+fn check_panel_mappability(
+    config: &RunConfig,
+    bundle: &InputBundle,
+    panel_defs: &[PanelDef],
+) -> Result<Vec<LowMappabilityPanel>, RunError> {
+    let audits = panels::loader::audit_panels(
+        bundle.species,
+        &bundle.gene_index,
+        panel_defs,
+        config.panel_rule_max_genes,
+    )?;
+    let low = panels::loader::low_mappability_panels(panel_defs, &audits, &config.min_mappable);
+    let describe = |p: &LowMappabilityPanel| {
+        format!(
+            "{} maps {:.2} of its genes (minimum {:.2}; missing: {})",
+            p.panel_id,
+            p.mappable_fraction,
+            p.min_fraction,
+            p.missing_genes.join(",")
+        )
+    };
+    if config.strict_mappable && !low.is_empty() {
+        return Err(RunError::Config(format!(
+            "--strict-mappable: panels below --min-mappable-fraction: {}",
+            low.iter().map(describe).collect::<Vec<_>>().join("; ")
+        )));
+    }
+    for panel in &low {
+        crate::warn!("excluding panel from scoring: {}", describe(panel));
+    }
+    Ok(low)
+}
+
+fn resolve_output_dir(base: &Path, run_mode: RunMode) -> PathBuf {
+    match run_mode {
+        RunMode::Standalone => base.to_path_buf(),
+        RunMode::Pipeline => base.join("kira-nuclearqc"),
+    }
+}
+
+fn extract_meta(
+    bundle: &input::InputBundle,
+) -> (
+    Option<Vec<String>>,
+    Option<Vec<String>>,
+    Option<Vec<String>>,
+    Option<Vec<String>>,
+) {
+    let mut sample: Option<Vec<String>> = None;
+    let mut condition: Option<Vec<String>> = None;
+    let mut species: Option<Vec<String>> = None;
+    let mut cluster: Option<Vec<String>> = None;
+    if let Some(meta) = &bundle.meta {
+        let mut sample_idx = None;
+        let mut condition_idx = None;
+        let mut species_idx = None;
+        let mut cluster_idx = None;
+        for (i, name) in meta.columns.iter().enumerate() {
+            let lower = name.to_ascii_lowercase();
+            if lower == "sample" {
+                sample_idx = Some(i);
+            } else if lower == "condition" {
+                condition_idx = Some(i);
+            } else if lower == "species" {
+                species_idx = Some(i);
+            } else if lower == "cluster"
+                || lower == "clusters"
+                || lower == "seurat_clusters"
+                || lower == "leiden"
+                || lower == "louvain"
+            {
+                cluster_idx = Some(i);
+            }
+        }
+        if let Some(idx) = sample_idx {
+            sample = Some(
+                meta.rows
+                    .iter()
+                    .map(|r| r.get(idx).cloned().unwrap_or_default())
+                    .collect(),
+            );
+        }
+        if let Some(idx) = condition_idx {
+            condition = Some(
+                meta.rows
+                    .iter()
+                    .map(|r| r.get(idx).cloned().unwrap_or_default())
+                    .collect(),
+            );
+        }
+        if let Some(idx) = species_idx {
+            species = Some(
+                meta.rows
+                    .iter()
+                    .map(|r| r.get(idx).cloned().unwrap_or_default())
+                    .collect(),
+            );
+        }
+        if let Some(idx) = cluster_idx {
+            cluster = Some(
+                meta.rows
+                    .iter()
+                    .map(|r| r.get(idx).cloned().unwrap_or_default())
+                    .collect(),
+            );
+        }
+    }
+    (sample, condition, species, cluster)
+}
+
+fn compute_panel_signals(
+    panel_set: &panels::PanelSet,
+    scores: &panels::PanelScores,
+) -> (Vec<f32>, Vec<f32>, Vec<f32>, Vec<f32>) {
+    let n_cells = scores.panel_sum.len();
+    let mut program_sum = vec![0.0f32; n_cells];
+    let mut tf_sum = vec![0.0f32; n_cells];
+    let mut proliferation_sum = vec![0.0f32; n_cells];
+    let mut nonzero_frac = vec![0.0f32; n_cells];
+
+    for (idx, panel) in panel_set.panels.iter().enumerate() {
+        for cell in 0..n_cells {
+            let v = scores.panel_sum[cell][idx];
+            match panel.group {
+                panels::defs::PanelGroup::Program => program_sum[cell] += v,
+                panels::defs::PanelGroup::Tf | panels::defs::PanelGroup::Chromatin => {
+                    tf_sum[cell] += v
+                }
+                panels::defs::PanelGroup::Proliferation => proliferation_sum[cell] += v,
+                _ => {}
+            }
+        }
+    }
+
+    for cell in 0..n_cells {
+        let mut detected = 0u32;
+        let mut total = 0u32;
+        for (idx, panel) in panel_set.panels.iter().enumerate() {
+            detected += scores.panel_detected[cell][idx];
+            total += panel.genes.len() as u32;
+        }
+        if total > 0 {
+            nonzero_frac[cell] = detected as f32 / total as f32;
+        }
+    }
+
+    let mut proliferation_share = vec![0.0f32; n_cells];
+    for cell in 0..n_cells {
+        let denom = program_sum[cell];
+        if denom > 0.0 {
+            proliferation_share[cell] = proliferation_sum[cell] / denom;
+        }
+    }
+
+    (program_sum, tf_sum, proliferation_share, nonzero_frac)
+}
+
+// `KIRA_GIT_HASH` wins so builds shipped without the repository (containers)
+// can still record provenance.
+fn git_hash(repo_root: &Path) -> Option<String> {
+    std::env::var("KIRA_GIT_HASH")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| read_git_hash(repo_root))
+}
+
+fn read_git_hash(repo_root: &Path) -> Option<String> {
+    let dot_git = repo_root.join(".git");
+    // A worktree or submodule has a `.git` file holding `gitdir: <path>`;
+    // its HEAD lives there, shared refs under the `commondir` it names.
+    let git_dir = if dot_git.is_file() {
+        let content = std::fs::read_to_string(&dot_git).ok()?;
+        let target = Path::new(content.strip_prefix("gitdir:")?.trim());
+        repo_root.join(target)
+    } else {
+        dot_git
+    };
+    let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+        .map(|s| git_dir.join(s.trim()))
+        .unwrap_or_else(|_| git_dir.clone());
+
+    let content = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let Some(ref_name) = content.strip_prefix("ref: ") else {
+        return Some(content.trim().to_string());
+    };
+    let ref_name = ref_name.trim();
+    for dir in [&git_dir, &common_dir] {
+        if let Ok(hash) = std::fs::read_to_string(dir.join(ref_name)) {
+            return Some(hash.trim().to_string());
+        }
+    }
+    // After `git gc` the ref only exists as a `<hash> <ref>` line.
+    let packed = std::fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed.lines().find_map(|line| {
+        let (hash, name) = line.split_once(' ')?;
+        (name.trim() == ref_name && !hash.starts_with('#') && !hash.starts_with('^'))
+            .then(|| hash.to_string())
+    })
+}
+
+fn log_scoring_mode(
+    mode: NuclearScoringMode,
+    stage3: &pipeline::stage3_panels::Stage3Output,
+    stage4: &pipeline::stage4_axes::Stage4Output,
+    method: QuantileMethod,
+) {
+    match mode {
+        NuclearScoringMode::ImmuneAware => {
+            eprintln!("INFO  Immune-aware nuclear scoring enabled (default)");
+            warn_unmapped_immune_panels(&stage3.audits);
+            if immune_like_detected(stage3, stage4, method) {
+                eprintln!("INFO  Immune-like scRNA detected; relative nuclear scoring in effect");
+            }
+        }
+        NuclearScoringMode::StrictBulk => {
+            eprintln!(
+                "WARN  Strict nuclear mode enabled (--strict-nuclear); immune dynamics may be underdetected"
+            );
+        }
+    }
+}
+
+const IMMUNE_PANELS: [&str; 2] = ["immune_activation", "clonal_engagement"];
+
+// Immune-aware anchors are relative to immune panel activity; with no immune
+// gene mapped they rank cells on empty panels. Returns whether it warned.
+fn warn_unmapped_immune_panels(audits: &[PanelAudit]) -> bool {
+    let immune = audits
+        .iter()
+        .filter(|a| IMMUNE_PANELS.contains(&a.panel_id.as_str()))
+        .collect::<Vec<_>>();
+    let unmapped = !immune.is_empty() && immune.iter().all(|a| a.panel_size_mappable == 0);
+    if unmapped {
+        crate::warn!(
+            "immune-aware scoring is active but no gene of {} maps to the reference; \
+             check the detected species and symbol aliases, or use --strict-nuclear",
+            immune
+                .iter()
+                .map(|a| a.panel_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    unmapped
+}
+
+fn immune_like_detected(
+    stage3: &pipeline::stage3_panels::Stage3Output,
+    stage4: &pipeline::stage4_axes::Stage4Output,
+    method: QuantileMethod,
+) -> bool {
+    let has_immune_panels = stage3
+        .panels
+        .panels
+        .iter()
+        .any(|p| IMMUNE_PANELS.contains(&p.id.as_str()));
+    let p90_iaa = p90(&stage4.axes.iaa, method);
+    let p90_dfa = p90(&stage4.axes.dfa, method);
+    let p90_cea = p90(&stage4.axes.cea, method);
+    has_immune_panels && (p90_iaa > 0.5 || p90_dfa > 0.5 || p90_cea > 0.5)
+}
+
+#[cfg(test)]
+#[path = "../tests/src_inline/run.rs"]
+mod tests;
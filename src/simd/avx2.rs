@@ -1,80 +1,229 @@
-#[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
-pub fn sum_f32_f64(values: &[f32]) -> f64 {
-    // Deterministic order: process chunks, but accumulate each lane in order.
-    let mut sum = 0f64;
-    let mut i = 0usize;
-    let n = values.len();
-    unsafe {
-        while i + 8 <= n {
-            let ptr = values.as_ptr().add(i) as *const __m256;
-            let v = _mm256_loadu_ps(ptr);
-            let mut lanes = [0f32; 8];
-            _mm256_storeu_ps(lanes.as_mut_ptr(), v);
-            for lane in &lanes {
-                sum += *lane as f64;
-            }
-            i += 8;
-        }
+use crate::simd::Kernels;
+use crate::simd::scalar::{
+    LG, LN2_HI, LN2_LO, MANTISSA_MASK, ONE_BITS, SUM_LANES, fold_lanes, plogp, stats_tail,
+};
+
+/// AVX2 kernels, or `None` when the running CPU lacks AVX2. The wrappers
+/// below are only reachable through this table.
+pub fn kernels() -> Option<Kernels> {
+    if !is_x86_feature_detected!("avx2") {
+        return None;
     }
-    while i < n {
-        sum += values[i] as f64;
-        i += 1;
+    Some(Kernels {
+        name: "avx2",
+        sum_f32_f64,
+        min_f32,
+        entropy_f64,
+        stats_f32,
+    })
+}
+
+fn sum_f32_f64(values: &[f32]) -> f64 {
+    // SAFETY: installed by `kernels()` only after AVX2 was detected.
+    unsafe { sum_f32_f64_avx2(values) }
+}
+
+fn min_f32(values: &[f32]) -> f32 {
+    // SAFETY: installed by `kernels()` only after AVX2 was detected.
+    unsafe { min_f32_avx2(values) }
+}
+
+fn stats_f32(values: &[f32]) -> (f64, f32, usize) {
+    if values.len() < SUM_LANES {
+        return stats_tail((0.0, f32::NEG_INFINITY, 0), values);
+    }
+    // SAFETY: installed by `kernels()` only after AVX2 was detected.
+    unsafe { stats_f32_avx2(values) }
+}
+
+fn entropy_f64(values: &[f32], sum: f64) -> f64 {
+    // SAFETY: installed by `kernels()` only after AVX2 was detected.
+    unsafe { entropy_f64_avx2(values, sum) }
+}
+
+/// Same blocked order as `scalar::sum_f32_f64`: four f64x4 accumulators hold
+/// the 16 lanes, widened from two f32x8 loads per block.
+#[target_feature(enable = "avx2")]
+fn sum_f32_f64_avx2(values: &[f32]) -> f64 {
+    let mut acc = [_mm256_setzero_pd(); 4];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        // SAFETY: each block holds exactly 16 floats.
+        let (lo, hi) = unsafe {
+            let ptr = block.as_ptr();
+            (_mm256_loadu_ps(ptr), _mm256_loadu_ps(ptr.add(8)))
+        };
+        acc[0] = _mm256_add_pd(acc[0], _mm256_cvtps_pd(_mm256_castps256_ps128(lo)));
+        acc[1] = _mm256_add_pd(acc[1], _mm256_cvtps_pd(_mm256_extractf128_ps::<1>(lo)));
+        acc[2] = _mm256_add_pd(acc[2], _mm256_cvtps_pd(_mm256_castps256_ps128(hi)));
+        acc[3] = _mm256_add_pd(acc[3], _mm256_cvtps_pd(_mm256_extractf128_ps::<1>(hi)));
+    }
+    let mut lanes = [0f64; SUM_LANES];
+    for (k, v) in acc.iter().enumerate() {
+        // SAFETY: `lanes[4 * k..4 * k + 4]` is in bounds for k < 4.
+        unsafe { _mm256_storeu_pd(lanes.as_mut_ptr().add(4 * k), *v) };
+    }
+    let mut sum = fold_lanes(lanes);
+    for &v in blocks.remainder() {
+        sum += v as f64;
     }
     sum
 }
 
-pub fn max_f32(values: &[f32]) -> f32 {
-    let mut max = f32::NEG_INFINITY;
+/// Same sum lanes as `scalar::stats_f32`. `max_ps(v, acc)` is
+/// `v > acc ? v : acc`, so NaN never enters a lane maximum; positive lanes
+/// subtract their all-ones mask (-1) from the counts.
+#[target_feature(enable = "avx2")]
+fn stats_f32_avx2(values: &[f32]) -> (f64, f32, usize) {
+    let zero = _mm256_setzero_ps();
+    let mut sums = [_mm256_setzero_pd(); 4];
+    let mut maxes = [_mm256_set1_ps(f32::NEG_INFINITY); 2];
+    let mut counts = [_mm256_setzero_si256(); 2];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        // SAFETY: each block holds exactly 16 floats.
+        let halves = unsafe {
+            let ptr = block.as_ptr();
+            [_mm256_loadu_ps(ptr), _mm256_loadu_ps(ptr.add(8))]
+        };
+        for (h, v) in halves.into_iter().enumerate() {
+            let lo = _mm256_cvtps_pd(_mm256_castps256_ps128(v));
+            let hi = _mm256_cvtps_pd(_mm256_extractf128_ps::<1>(v));
+            sums[2 * h] = _mm256_add_pd(sums[2 * h], lo);
+            sums[2 * h + 1] = _mm256_add_pd(sums[2 * h + 1], hi);
+            maxes[h] = _mm256_max_ps(v, maxes[h]);
+            let positive = _mm256_castps_si256(_mm256_cmp_ps::<_CMP_GT_OQ>(v, zero));
+            counts[h] = _mm256_sub_epi32(counts[h], positive);
+        }
+    }
+    let mut sum_lanes = [0f64; SUM_LANES];
+    // SAFETY: `sum_lanes[4 * k..4 * k + 4]` is in bounds for k < 4.
+    unsafe {
+        for (k, v) in sums.iter().enumerate() {
+            _mm256_storeu_pd(sum_lanes.as_mut_ptr().add(4 * k), *v);
+        }
+    }
+    // Max and count are order-independent (zero signs are canonicalized in
+    // `stats_tail`), so they reduce in registers.
+    let m = _mm256_max_ps(maxes[0], maxes[1]);
+    let m = _mm_max_ps(_mm256_castps256_ps128(m), _mm256_extractf128_ps::<1>(m));
+    let m = _mm_max_ps(m, _mm_movehl_ps(m, m));
+    let max = _mm_cvtss_f32(_mm_max_ss(m, _mm_shuffle_ps::<0b01>(m, m)));
+    let c = _mm256_add_epi32(counts[0], counts[1]);
+    let c = _mm_add_epi32(_mm256_castsi256_si128(c), _mm256_extracti128_si256::<1>(c));
+    let c = _mm_add_epi32(c, _mm_unpackhi_epi64(c, c));
+    let c = _mm_add_epi32(c, _mm_shuffle_epi32::<0b01>(c));
+    let positive = _mm_cvtsi128_si32(c) as u32 as usize;
+    stats_tail((fold_lanes(sum_lanes), max, positive), blocks.remainder())
+}
+
+#[target_feature(enable = "avx2")]
+fn min_f32_avx2(values: &[f32]) -> f32 {
+    let mut min = f32::INFINITY;
     let mut i = 0usize;
     let n = values.len();
-    unsafe {
-        while i + 8 <= n {
-            let ptr = values.as_ptr().add(i) as *const __m256;
-            let v = _mm256_loadu_ps(ptr);
-            let mut lanes = [0f32; 8];
-            _mm256_storeu_ps(lanes.as_mut_ptr(), v);
-            for lane in &lanes {
-                if *lane > max {
-                    max = *lane;
-                }
+    while i + 8 <= n {
+        // SAFETY: `i + 8 <= n`, so the unaligned load stays in bounds.
+        let v = unsafe { _mm256_loadu_ps(values.as_ptr().add(i)) };
+        let mut lanes = [0f32; 8];
+        // SAFETY: `lanes` holds exactly 8 floats.
+        unsafe { _mm256_storeu_ps(lanes.as_mut_ptr(), v) };
+        for lane in &lanes {
+            if *lane < min {
+                min = *lane;
             }
-            i += 8;
         }
+        i += 8;
     }
     while i < n {
         let v = values[i];
-        if v > max {
-            max = v;
+        if v < min {
+            min = v;
         }
         i += 1;
     }
-    if max.is_finite() { max } else { 0.0 }
+    if min.is_finite() { min } else { 0.0 }
+}
+
+#[cfg(test)]
+#[path = "../../tests/src_inline/simd/avx2.rs"]
+mod tests;
+
+/// `scalar::ln_f64` on four lanes, operation for operation. The biased
+/// exponent is turned into a double with the 2^52 bit trick, which is exact.
+#[target_feature(enable = "avx2")]
+fn ln_pd(x: __m256d) -> __m256d {
+    let bits = _mm256_castpd_si256(x);
+    let two52 = _mm256_set1_epi64x(0x4330_0000_0000_0000);
+    let exp = _mm256_or_si256(_mm256_srli_epi64::<52>(bits), two52);
+    let exp = _mm256_sub_pd(
+        _mm256_castsi256_pd(exp),
+        _mm256_set1_pd(4_503_599_627_370_496.0),
+    );
+    let k = _mm256_sub_pd(exp, _mm256_set1_pd(1023.0));
+    let m = _mm256_or_si256(
+        _mm256_and_si256(bits, _mm256_set1_epi64x(MANTISSA_MASK as i64)),
+        _mm256_set1_epi64x(ONE_BITS as i64),
+    );
+    let m = _mm256_castsi256_pd(m);
+    let big = _mm256_cmp_pd::<_CMP_GT_OQ>(m, _mm256_set1_pd(std::f64::consts::SQRT_2));
+    let m = _mm256_blendv_pd(m, _mm256_mul_pd(m, _mm256_set1_pd(0.5)), big);
+    let k = _mm256_add_pd(k, _mm256_and_pd(big, _mm256_set1_pd(1.0)));
+
+    let f = _mm256_sub_pd(m, _mm256_set1_pd(1.0));
+    let hfsq = _mm256_mul_pd(_mm256_mul_pd(_mm256_set1_pd(0.5), f), f);
+    let s = _mm256_div_pd(f, _mm256_add_pd(_mm256_set1_pd(2.0), f));
+    let z = _mm256_mul_pd(s, s);
+    let w = _mm256_mul_pd(z, z);
+    let lg = |i: usize| _mm256_set1_pd(LG[i]);
+    let t1 = _mm256_mul_pd(w, lg(5));
+    let t1 = _mm256_mul_pd(w, _mm256_add_pd(lg(3), t1));
+    let t1 = _mm256_mul_pd(w, _mm256_add_pd(lg(1), t1));
+    let t2 = _mm256_mul_pd(w, lg(6));
+    let t2 = _mm256_mul_pd(w, _mm256_add_pd(lg(4), t2));
+    let t2 = _mm256_mul_pd(w, _mm256_add_pd(lg(2), t2));
+    let t2 = _mm256_mul_pd(z, _mm256_add_pd(lg(0), t2));
+    let r = _mm256_add_pd(t2, t1);
+
+    let inner = _mm256_mul_pd(s, _mm256_add_pd(hfsq, r));
+    let inner = _mm256_add_pd(inner, _mm256_mul_pd(k, _mm256_set1_pd(LN2_LO)));
+    let inner = _mm256_sub_pd(_mm256_sub_pd(hfsq, inner), f);
+    _mm256_sub_pd(_mm256_mul_pd(k, _mm256_set1_pd(LN2_HI)), inner)
 }
 
-pub fn entropy_f32(values: &[f32]) -> f32 {
-    if values.is_empty() {
-        return 0.0;
+/// Same terms and blocked order as `scalar::entropy_f64`; lanes with
+/// `p <= 0` (or NaN) contribute `+0.0` just like the scalar branch.
+#[target_feature(enable = "avx2")]
+fn entropy_f64_avx2(values: &[f32], sum: f64) -> f64 {
+    let sum_v = _mm256_set1_pd(sum);
+    let plogp_pd = |v: __m128| {
+        let p = _mm256_div_pd(_mm256_cvtps_pd(v), sum_v);
+        let positive = _mm256_cmp_pd::<_CMP_GT_OQ>(p, _mm256_setzero_pd());
+        _mm256_and_pd(positive, _mm256_mul_pd(p, ln_pd(p)))
+    };
+    let mut acc = [_mm256_setzero_pd(); 4];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        // SAFETY: each block holds exactly 16 floats.
+        let (lo, hi) = unsafe {
+            let ptr = block.as_ptr();
+            (_mm256_loadu_ps(ptr), _mm256_loadu_ps(ptr.add(8)))
+        };
+        acc[0] = _mm256_add_pd(acc[0], plogp_pd(_mm256_castps256_ps128(lo)));
+        acc[1] = _mm256_add_pd(acc[1], plogp_pd(_mm256_extractf128_ps::<1>(lo)));
+        acc[2] = _mm256_add_pd(acc[2], plogp_pd(_mm256_castps256_ps128(hi)));
+        acc[3] = _mm256_add_pd(acc[3], plogp_pd(_mm256_extractf128_ps::<1>(hi)));
     }
-    let sum = sum_f32_f64(values);
-    if sum <= 0.0 {
-        return 0.0;
+    let mut lanes = [0f64; SUM_LANES];
+    for (k, v) in acc.iter().enumerate() {
+        // SAFETY: `lanes[4 * k..4 * k + 4]` is in bounds for k < 4.
+        unsafe { _mm256_storeu_pd(lanes.as_mut_ptr().add(4 * k), *v) };
     }
-    let mut h = 0f64;
-    for &v in values {
-        let p = (v as f64) / sum;
-        if p > 0.0 {
-            h -= p * p.ln();
-        }
+    let mut total = fold_lanes(lanes);
+    for &v in blocks.remainder() {
+        total += plogp(v, sum);
     }
-    h as f32
+    0.0 - total
 }
-
-pub fn backend_name() -> &'static str {
-    "avx2"
-}
-
-#[cfg(test)]
-#[path = "../../tests/src_inline/simd/avx2.rs"]
-mod tests;
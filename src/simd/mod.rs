@@ -1,6 +1,149 @@
+use std::sync::OnceLock;
+
+/// Kernel set of one backend. Selected once per process by CPU feature
+/// detection, so a baseline build still uses AVX2 where the CPU has it.
+#[derive(Clone, Copy)]
+pub struct Kernels {
+    pub name: &'static str,
+    pub sum_f32_f64: fn(&[f32]) -> f64,
+    pub min_f32: fn(&[f32]) -> f32,
+    /// Entropy of `values / sum` for a precomputed positive `sum`.
+    pub entropy_f64: fn(&[f32], f64) -> f64,
+    /// Fused `(sum, raw max, count of values > 0)` in one pass.
+    pub stats_f32: fn(&[f32]) -> (f64, f32, usize),
+}
+
+static KERNELS: OnceLock<Kernels> = OnceLock::new();
+
+fn detect() -> Kernels {
+    #[cfg(target_arch = "x86_64")]
+    if let Some(kernels) = avx2::kernels() {
+        return kernels;
+    }
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    if let Some(kernels) = neon::kernels() {
+        return kernels;
+    }
+    scalar::KERNELS
+}
+
+#[inline]
+pub fn kernels() -> &'static Kernels {
+    KERNELS.get_or_init(detect)
+}
+
+/// Canonical summation: the backend kernel sums [`SUM_BLOCK`]-element blocks
+/// and the block sums are combined pairwise. Bitwise identical on every
+/// backend.
 #[inline]
 pub fn sum_f32_f64(values: &[f32]) -> f64 {
-    backend::sum_f32_f64(values)
+    sum_f32_f64_tree(values, SumMode::Blocked)
+}
+
+/// Summation order used by axis metrics. `Blocked` is the canonical sum:
+/// [`SUM_BLOCK`]-element blocks whose sums are combined pairwise, as a tree.
+/// `Pairwise` keeps halving down to [`PAIRWISE_BLOCK`]-element leaves, for a
+/// little less rounding error at some cost in speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SumMode {
+    Blocked,
+    Pairwise,
+}
+
+impl SumMode {
+    /// Also accepts `sequential`, the earlier name of `blocked`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "blocked" | "sequential" => Some(SumMode::Blocked),
+            "pairwise" => Some(SumMode::Pairwise),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SumMode::Blocked => "blocked",
+            SumMode::Pairwise => "pairwise",
+        }
+    }
+}
+
+/// Block length of the canonical sum. Part of the determinism contract:
+/// changing it changes results in the last bits.
+pub const SUM_BLOCK: usize = 256;
+
+/// Leaf length of `SumMode::Pairwise`.
+pub const PAIRWISE_BLOCK: usize = 64;
+
+// Where a sum over `len` values splits, or `None` for a single kernel call.
+// Depends only on the length, so the tree is deterministic. The canonical
+// sum splits on block boundaries, giving the left half `n_blocks / 2` blocks.
+fn split_point(len: usize, mode: SumMode) -> Option<usize> {
+    match mode {
+        SumMode::Blocked => {
+            let n_blocks = len.div_ceil(SUM_BLOCK);
+            (n_blocks > 1).then_some(n_blocks / 2 * SUM_BLOCK)
+        }
+        SumMode::Pairwise => (len > PAIRWISE_BLOCK).then_some(len / 2),
+    }
+}
+
+pub fn sum_f32_f64_pairwise(values: &[f32]) -> f64 {
+    sum_f32_f64_tree(values, SumMode::Pairwise)
+}
+
+#[inline]
+pub fn sum_f32_f64_mode(values: &[f32], mode: SumMode) -> f64 {
+    match mode {
+        SumMode::Blocked => sum_f32_f64(values),
+        SumMode::Pairwise => sum_f32_f64_pairwise(values),
+    }
+}
+
+fn sum_f32_f64_tree(values: &[f32], mode: SumMode) -> f64 {
+    match split_point(values.len(), mode) {
+        None => (kernels().sum_f32_f64)(values),
+        Some(mid) => {
+            sum_f32_f64_tree(&values[..mid], mode) + sum_f32_f64_tree(&values[mid..], mode)
+        }
+    }
+}
+
+/// Sum, maximum and count of values `> 0` in one pass. The sum is bitwise
+/// equal to [`sum_f32_f64_mode`]; the maximum ignores NaN, reports a zero
+/// maximum as `+0.0`, and is `0.0` when it is not finite.
+pub fn stats_f32(values: &[f32], mode: SumMode) -> (f64, f32, usize) {
+    let (sum, max, positive) = stats_f32_tree(values, mode);
+    (sum, if max.is_finite() { max } else { 0.0 }, positive)
+}
+
+// Same split points as `sum_f32_f64_tree`; leaves run the fused kernel, and
+// max/count merge exactly.
+fn stats_f32_tree(values: &[f32], mode: SumMode) -> (f64, f32, usize) {
+    let Some(mid) = split_point(values.len(), mode) else {
+        return (kernels().stats_f32)(values);
+    };
+    let (sum_a, max_a, pos_a) = stats_f32_tree(&values[..mid], mode);
+    let (sum_b, max_b, pos_b) = stats_f32_tree(&values[mid..], mode);
+    let max = if max_b > max_a { max_b } else { max_a };
+    (sum_a + sum_b, max, pos_a + pos_b)
+}
+
+/// Mean and population variance. The mean uses the same sum as
+/// [`sum_f32_f64_mode`]; the variance is a second pass over the squared
+/// deviations, accumulated in `f64`. Both are `0.0` for an empty slice.
+pub fn mean_var_f32(values: &[f32], mode: SumMode) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = sum_f32_f64_mode(values, mode) / n;
+    let mut ss = 0.0f64;
+    for &v in values {
+        let d = v as f64 - mean;
+        ss += d * d;
+    }
+    (mean, ss / n)
 }
 
 #[inline]
@@ -9,53 +152,28 @@ pub fn sum_f32(values: &[f32]) -> f32 {
 }
 
 #[inline]
-pub fn max_f32(values: &[f32]) -> f32 {
-    backend::max_f32(values)
+pub fn min_f32(values: &[f32]) -> f32 {
+    (kernels().min_f32)(values)
 }
 
 #[inline]
-pub fn entropy_f32(values: &[f32]) -> f32 {
+pub fn entropy_f32(values: &[f32], mode: SumMode) -> f32 {
     if values.is_empty() {
         return 0.0;
     }
-    let sum = sum_f32_f64(values);
+    let sum = sum_f32_f64_mode(values, mode);
     if sum <= 0.0 {
         return 0.0;
     }
-    let mut h = 0f64;
-    for &v in values {
-        let p = (v as f64) / sum;
-        if p > 0.0 {
-            h -= p * p.ln();
-        }
-    }
-    h as f32
+    (kernels().entropy_f64)(values, sum) as f32
 }
 
 #[inline]
 pub fn backend_name() -> &'static str {
-    backend::backend_name()
-}
-
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-mod backend {
-    pub use crate::simd::avx2::*;
-}
-
-#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
-mod backend {
-    pub use crate::simd::neon::*;
-}
-
-#[cfg(not(any(
-    all(target_arch = "x86_64", target_feature = "avx2"),
-    all(target_arch = "aarch64", target_feature = "neon"),
-)))]
-mod backend {
-    pub use crate::simd::scalar::*;
+    kernels().name
 }
 
-#[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
+#[cfg(target_arch = "x86_64")]
 pub mod avx2;
 #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
 pub mod neon;
@@ -1,31 +1,84 @@
 #[cfg(target_arch = "aarch64")]
 use std::arch::aarch64::*;
 
+use crate::simd::Kernels;
+use crate::simd::scalar::{
+    LG, LN2_HI, LN2_LO, MANTISSA_MASK, ONE_BITS, SUM_LANES, fold_lanes, plogp, stats_tail,
+};
+
+/// Same blocked order as `scalar::sum_f32_f64`: eight f64x2 accumulators hold
+/// the 16 lanes, widened from four f32x4 loads per block.
 pub fn sum_f32_f64(values: &[f32]) -> f64 {
-    let mut sum = 0f64;
-    let mut i = 0usize;
-    let n = values.len();
+    let mut lanes = [0f64; SUM_LANES];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    // SAFETY: NEON is enabled for this build, each block holds exactly 16
+    // floats and `lanes[2 * k..2 * k + 2]` is in bounds for k < 8.
     unsafe {
-        while i + 4 <= n {
-            let ptr = values.as_ptr().add(i);
-            let v = vld1q_f32(ptr);
-            let mut lanes = [0f32; 4];
-            vst1q_f32(lanes.as_mut_ptr(), v);
-            for lane in &lanes {
-                sum += *lane as f64;
+        let mut acc = [vdupq_n_f64(0.0); 8];
+        for block in &mut blocks {
+            let ptr = block.as_ptr();
+            for q in 0..4 {
+                let v = vld1q_f32(ptr.add(4 * q));
+                acc[2 * q] = vaddq_f64(acc[2 * q], vcvt_f64_f32(vget_low_f32(v)));
+                acc[2 * q + 1] = vaddq_f64(acc[2 * q + 1], vcvt_high_f64_f32(v));
             }
-            i += 4;
+        }
+        for (k, v) in acc.iter().enumerate() {
+            vst1q_f64(lanes.as_mut_ptr().add(2 * k), *v);
         }
     }
-    while i < n {
-        sum += values[i] as f64;
-        i += 1;
+    let mut sum = fold_lanes(lanes);
+    for &v in blocks.remainder() {
+        sum += v as f64;
     }
     sum
 }
 
-pub fn max_f32(values: &[f32]) -> f32 {
-    let mut max = f32::NEG_INFINITY;
+/// Same lanes as `scalar::stats_f32`. The max uses a `v > acc` select rather
+/// than `vmaxq_f32`, which would propagate NaN.
+pub fn stats_f32(values: &[f32]) -> (f64, f32, usize) {
+    if values.len() < SUM_LANES {
+        return stats_tail((0.0, f32::NEG_INFINITY, 0), values);
+    }
+    let mut sum_lanes = [0f64; SUM_LANES];
+    let max;
+    let positive;
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    // SAFETY: NEON is enabled for this build, each block holds exactly 16
+    // floats and `sum_lanes[2 * k..2 * k + 2]` is in bounds for k < 8.
+    unsafe {
+        let zero = vdupq_n_f32(0.0);
+        let mut sums = [vdupq_n_f64(0.0); 8];
+        let mut maxes = [vdupq_n_f32(f32::NEG_INFINITY); 4];
+        let mut counts = [vdupq_n_u32(0); 4];
+        for block in &mut blocks {
+            let ptr = block.as_ptr();
+            for q in 0..4 {
+                let v = vld1q_f32(ptr.add(4 * q));
+                sums[2 * q] = vaddq_f64(sums[2 * q], vcvt_f64_f32(vget_low_f32(v)));
+                sums[2 * q + 1] = vaddq_f64(sums[2 * q + 1], vcvt_high_f64_f32(v));
+                maxes[q] = vbslq_f32(vcgtq_f32(v, maxes[q]), v, maxes[q]);
+                counts[q] = vsubq_u32(counts[q], vcgtq_f32(v, zero));
+            }
+        }
+        for (k, v) in sums.iter().enumerate() {
+            vst1q_f64(sum_lanes.as_mut_ptr().add(2 * k), *v);
+        }
+        // Lane maxima are never NaN and zero signs are canonicalized in
+        // `stats_tail`, so max and count reduce in registers.
+        let m = vmaxq_f32(vmaxq_f32(maxes[0], maxes[1]), vmaxq_f32(maxes[2], maxes[3]));
+        let c = vaddq_u32(
+            vaddq_u32(counts[0], counts[1]),
+            vaddq_u32(counts[2], counts[3]),
+        );
+        max = vmaxvq_f32(m);
+        positive = vaddvq_u32(c) as usize;
+    }
+    stats_tail((fold_lanes(sum_lanes), max, positive), blocks.remainder())
+}
+
+pub fn min_f32(values: &[f32]) -> f32 {
+    let mut min = f32::INFINITY;
     let mut i = 0usize;
     let n = values.len();
     unsafe {
@@ -35,8 +88,8 @@ pub fn max_f32(values: &[f32]) -> f32 {
             let mut lanes = [0f32; 4];
             vst1q_f32(lanes.as_mut_ptr(), v);
             for lane in &lanes {
-                if *lane > max {
-                    max = *lane;
+                if *lane < min {
+                    min = *lane;
                 }
             }
             i += 4;
@@ -44,34 +97,101 @@ pub fn max_f32(values: &[f32]) -> f32 {
     }
     while i < n {
         let v = values[i];
-        if v > max {
-            max = v;
+        if v < min {
+            min = v;
         }
         i += 1;
     }
-    if max.is_finite() { max } else { 0.0 }
+    if min.is_finite() { min } else { 0.0 }
 }
 
-pub fn entropy_f32(values: &[f32]) -> f32 {
-    if values.is_empty() {
-        return 0.0;
-    }
-    let sum = sum_f32_f64(values);
-    if sum <= 0.0 {
-        return 0.0;
+/// `scalar::ln_f64` on two lanes, operation for operation.
+///
+/// # Safety
+/// Requires NEON, which every build of this module has.
+unsafe fn ln_f64x2(x: float64x2_t) -> float64x2_t {
+    unsafe {
+        let bits = vreinterpretq_u64_f64(x);
+        let k = vsubq_f64(vcvtq_f64_u64(vshrq_n_u64::<52>(bits)), vdupq_n_f64(1023.0));
+        let m = vorrq_u64(
+            vandq_u64(bits, vdupq_n_u64(MANTISSA_MASK)),
+            vdupq_n_u64(ONE_BITS),
+        );
+        let m = vreinterpretq_f64_u64(m);
+        let big = vcgtq_f64(m, vdupq_n_f64(std::f64::consts::SQRT_2));
+        let m = vbslq_f64(big, vmulq_f64(m, vdupq_n_f64(0.5)), m);
+        let one = vreinterpretq_u64_f64(vdupq_n_f64(1.0));
+        let k = vaddq_f64(k, vreinterpretq_f64_u64(vandq_u64(big, one)));
+
+        let f = vsubq_f64(m, vdupq_n_f64(1.0));
+        let hfsq = vmulq_f64(vmulq_f64(vdupq_n_f64(0.5), f), f);
+        let s = vdivq_f64(f, vaddq_f64(vdupq_n_f64(2.0), f));
+        let z = vmulq_f64(s, s);
+        let w = vmulq_f64(z, z);
+        let lg = |i: usize| vdupq_n_f64(LG[i]);
+        let t1 = vmulq_f64(w, lg(5));
+        let t1 = vmulq_f64(w, vaddq_f64(lg(3), t1));
+        let t1 = vmulq_f64(w, vaddq_f64(lg(1), t1));
+        let t2 = vmulq_f64(w, lg(6));
+        let t2 = vmulq_f64(w, vaddq_f64(lg(4), t2));
+        let t2 = vmulq_f64(w, vaddq_f64(lg(2), t2));
+        let t2 = vmulq_f64(z, vaddq_f64(lg(0), t2));
+        let r = vaddq_f64(t2, t1);
+
+        let inner = vmulq_f64(s, vaddq_f64(hfsq, r));
+        let inner = vaddq_f64(inner, vmulq_f64(k, vdupq_n_f64(LN2_LO)));
+        let inner = vsubq_f64(vsubq_f64(hfsq, inner), f);
+        vsubq_f64(vmulq_f64(k, vdupq_n_f64(LN2_HI)), inner)
     }
-    let mut h = 0f64;
-    for &v in values {
-        let p = (v as f64) / sum;
-        if p > 0.0 {
-            h -= p * p.ln();
+}
+
+/// Same terms and blocked order as `scalar::entropy_f64`; lanes with
+/// `p <= 0` (or NaN) contribute `+0.0` just like the scalar branch.
+pub fn entropy_f64(values: &[f32], sum: f64) -> f64 {
+    let mut lanes = [0f64; SUM_LANES];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    // SAFETY: NEON is enabled for this build, each block holds exactly 16
+    // floats and `lanes[2 * k..2 * k + 2]` is in bounds for k < 8.
+    unsafe {
+        let sum_v = vdupq_n_f64(sum);
+        let plogp_f64x2 = |v: float64x2_t| {
+            let p = vdivq_f64(v, sum_v);
+            let positive = vcgtq_f64(p, vdupq_n_f64(0.0));
+            let term = vreinterpretq_u64_f64(vmulq_f64(p, ln_f64x2(p)));
+            vreinterpretq_f64_u64(vandq_u64(positive, term))
+        };
+        let mut acc = [vdupq_n_f64(0.0); 8];
+        for block in &mut blocks {
+            let ptr = block.as_ptr();
+            for q in 0..4 {
+                let v = vld1q_f32(ptr.add(4 * q));
+                let lo = plogp_f64x2(vcvt_f64_f32(vget_low_f32(v)));
+                let hi = plogp_f64x2(vcvt_high_f64_f32(v));
+                acc[2 * q] = vaddq_f64(acc[2 * q], lo);
+                acc[2 * q + 1] = vaddq_f64(acc[2 * q + 1], hi);
+            }
+        }
+        for (k, v) in acc.iter().enumerate() {
+            vst1q_f64(lanes.as_mut_ptr().add(2 * k), *v);
         }
     }
-    h as f32
+    let mut total = fold_lanes(lanes);
+    for &v in blocks.remainder() {
+        total += plogp(v, sum);
+    }
+    0.0 - total
 }
 
-pub fn backend_name() -> &'static str {
-    "neon"
+/// NEON is part of the aarch64 baseline, so this backend is always available
+/// when compiled in.
+pub fn kernels() -> Option<Kernels> {
+    Some(Kernels {
+        name: "neon",
+        sum_f32_f64,
+        min_f32,
+        entropy_f64,
+        stats_f32,
+    })
 }
 
 #[cfg(test)]
@@ -1,41 +1,164 @@
+use crate::simd::Kernels;
+
+pub const KERNELS: Kernels = Kernels {
+    name: "scalar",
+    sum_f32_f64,
+    min_f32,
+    entropy_f64,
+    stats_f32,
+};
+
+/// Lanes of the blocked sum. Every backend adds `values[i]` of each full
+/// block into lane `i % SUM_LANES`, folds the lanes with [`fold_lanes`], then
+/// adds the tail in order, so all backends agree bitwise.
+pub const SUM_LANES: usize = 16;
+
 pub fn sum_f32_f64(values: &[f32]) -> f64 {
-    let mut sum = 0f64;
-    for &v in values {
+    let mut lanes = [0f64; SUM_LANES];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        for (acc, &v) in lanes.iter_mut().zip(block) {
+            *acc += v as f64;
+        }
+    }
+    let mut sum = fold_lanes(lanes);
+    for &v in blocks.remainder() {
         sum += v as f64;
     }
     sum
 }
 
-pub fn max_f32(values: &[f32]) -> f32 {
-    let mut max = f32::NEG_INFINITY;
+/// Halving tree: lane `i` absorbs lane `i + w` for `w` = 8, 4, 2, 1.
+pub fn fold_lanes(mut lanes: [f64; SUM_LANES]) -> f64 {
+    let mut width = SUM_LANES / 2;
+    while width > 0 {
+        for i in 0..width {
+            lanes[i] += lanes[i + width];
+        }
+        width /= 2;
+    }
+    lanes[0]
+}
+
+/// Sum, raw maximum (`-inf` when empty) and count of values `> 0` in one
+/// pass. Sum lanes follow [`sum_f32_f64`]; NaN never becomes the maximum and
+/// a zero maximum is reported as `+0.0`.
+pub fn stats_f32(values: &[f32]) -> (f64, f32, usize) {
+    if values.len() < SUM_LANES {
+        return stats_tail((0.0, f32::NEG_INFINITY, 0), values);
+    }
+    let mut sums = [0f64; SUM_LANES];
+    let mut maxes = [f32::NEG_INFINITY; SUM_LANES];
+    let mut positive = 0usize;
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        for ((sum, max), &v) in sums.iter_mut().zip(maxes.iter_mut()).zip(block) {
+            *sum += v as f64;
+            if v > *max {
+                *max = v;
+            }
+            positive += (v > 0.0) as usize;
+        }
+    }
+    stats_tail(
+        (fold_lanes(sums), fold_max_lanes(maxes), positive),
+        blocks.remainder(),
+    )
+}
+
+/// Sequential continuation of `stats_f32` over the values after the last
+/// full block. Inputs shorter than a block go straight here, which is exact:
+/// folding empty lanes yields `0.0` and `-inf`.
+#[inline]
+pub fn stats_tail(acc: (f64, f32, usize), values: &[f32]) -> (f64, f32, usize) {
+    let (mut sum, mut max, mut positive) = acc;
     for &v in values {
+        sum += v as f64;
         if v > max {
             max = v;
         }
+        positive += (v > 0.0) as usize;
     }
-    if max.is_finite() { max } else { 0.0 }
+    // `0.0` and `-0.0` tie under `>`, so which one wins depends on lane
+    // order; reporting `+0.0` lets the backends reduce in any order.
+    (sum, max + 0.0, positive)
 }
 
-pub fn entropy_f32(values: &[f32]) -> f32 {
-    if values.is_empty() {
-        return 0.0;
-    }
-    let sum = sum_f32_f64(values);
-    if sum <= 0.0 {
-        return 0.0;
+fn fold_max_lanes(lanes: [f32; SUM_LANES]) -> f32 {
+    lanes
+        .iter()
+        .fold(f32::NEG_INFINITY, |max, &v| if v > max { v } else { max })
+}
+
+// fdlibm `__ieee754_log` split of ln 2 and its log(1+f) polynomial.
+pub const LN2_HI: f64 = f64::from_bits(0x3fe6_2e42_fee0_0000);
+pub const LN2_LO: f64 = f64::from_bits(0x3dea_39ef_3579_3c76);
+pub const LG: [f64; 7] = [
+    f64::from_bits(0x3fe5_5555_5555_5593),
+    f64::from_bits(0x3fd9_9999_9997_fa04),
+    f64::from_bits(0x3fd2_4924_9422_9359),
+    f64::from_bits(0x3fcc_71c5_1d8e_78af),
+    f64::from_bits(0x3fc7_4664_96cb_03de),
+    f64::from_bits(0x3fc3_9a09_d078_c69f),
+    f64::from_bits(0x3fc2_f112_df3e_5244),
+];
+pub const MANTISSA_MASK: u64 = 0x000f_ffff_ffff_ffff;
+pub const ONE_BITS: u64 = 0x3ff0_0000_0000_0000;
+
+/// Natural log for positive normal `x`, built only from IEEE add, mul and div
+/// so the vector backends can repeat it operation for operation. Within 1 ulp
+/// of `f64::ln`, but not bitwise equal to it.
+pub fn ln_f64(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let mut k = (bits >> 52) as f64 - 1023.0;
+    let mut m = f64::from_bits(bits & MANTISSA_MASK | ONE_BITS);
+    if m > std::f64::consts::SQRT_2 {
+        m *= 0.5;
+        k += 1.0;
     }
-    let mut h = 0f64;
-    for &v in values {
-        let p = (v as f64) / sum;
-        if p > 0.0 {
-            h -= p * p.ln();
+    let f = m - 1.0;
+    let hfsq = 0.5 * f * f;
+    let s = f / (2.0 + f);
+    let z = s * s;
+    let w = z * z;
+    let t1 = w * (LG[1] + w * (LG[3] + w * LG[5]));
+    let t2 = z * (LG[0] + w * (LG[2] + w * (LG[4] + w * LG[6])));
+    let r = t2 + t1;
+    k * LN2_HI - ((hfsq - (s * (hfsq + r) + k * LN2_LO)) - f)
+}
+
+/// `p * ln(p)` for `p = value / sum`, or `0.0` where `p` is not positive.
+#[inline]
+pub fn plogp(value: f32, sum: f64) -> f64 {
+    let p = value as f64 / sum;
+    if p > 0.0 { p * ln_f64(p) } else { 0.0 }
+}
+
+/// Shannon entropy of `values / sum`; the terms follow the blocked order of
+/// [`sum_f32_f64`].
+pub fn entropy_f64(values: &[f32], sum: f64) -> f64 {
+    let mut lanes = [0f64; SUM_LANES];
+    let mut blocks = values.chunks_exact(SUM_LANES);
+    for block in &mut blocks {
+        for (acc, &v) in lanes.iter_mut().zip(block) {
+            *acc += plogp(v, sum);
         }
     }
-    h as f32
+    let mut total = fold_lanes(lanes);
+    for &v in blocks.remainder() {
+        total += plogp(v, sum);
+    }
+    0.0 - total
 }
 
-pub fn backend_name() -> &'static str {
-    "scalar"
+pub fn min_f32(values: &[f32]) -> f32 {
+    let mut min = f32::INFINITY;
+    for &v in values {
+        if v < min {
+            min = v;
+        }
+    }
+    if min.is_finite() { min } else { 0.0 }
 }
 
 #[cfg(test)]
@@ -1,14 +1,128 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+// Set once from `--log-level` before any stage runs.
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// The `warn!`s of one run, shown or not, counted by the module that warned
+/// (`module_path!()` without the crate name).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarningLog {
+    by_source: BTreeMap<&'static str, usize>,
+}
+
+impl WarningLog {
+    pub fn record(&mut self, source: &'static str) {
+        let source = source.split_once("::").map_or(source, |(_, rest)| rest);
+        *self.by_source.entry(source).or_default() += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.by_source.values().sum()
+    }
+
+    /// Counts per source module, in module order.
+    pub fn by_source(&self) -> &BTreeMap<&'static str, usize> {
+        &self.by_source
+    }
+}
+
+thread_local! {
+    // Log of the run in progress on this thread, if any.
+    static ACTIVE_LOG: RefCell<Option<WarningLog>> = const { RefCell::new(None) };
+}
+
+/// Collects the `warn!`s made on the current thread while it is alive, so
+/// concurrent runs on other threads never count into it. A run holds one for
+/// its whole length; warnings must be raised on the run's own thread, not in
+/// the workers it spawns.
+pub struct WarningScope {
+    outer: Option<WarningLog>,
+}
+
+impl WarningScope {
+    pub fn begin() -> Self {
+        Self {
+            outer: ACTIVE_LOG.replace(Some(WarningLog::default())),
+        }
+    }
+
+    /// The warnings so far.
+    pub fn log(&self) -> WarningLog {
+        ACTIVE_LOG.with_borrow(|log| log.clone().unwrap_or_default())
+    }
+}
+
+impl Drop for WarningScope {
+    fn drop(&mut self) {
+        ACTIVE_LOG.set(self.outer.take());
+    }
+}
+
+/// Called by `warn!` with the `module_path!()` of the call site; a no-op
+/// outside a [`WarningScope`].
+pub fn count_warning(source: &'static str) {
+    ACTIVE_LOG.with_borrow_mut(|log| {
+        if let Some(log) = log {
+            log.record(source);
+        }
+    });
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {{
+        if $crate::tracing::enabled($crate::tracing::LogLevel::Debug) {
+            eprintln!("[DEBUG] {}", format_args!($($arg)*));
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)*) => {{
-        eprintln!("[INFO] {}", format_args!($($arg)*));
+        if $crate::tracing::enabled($crate::tracing::LogLevel::Info) {
+            eprintln!("[INFO] {}", format_args!($($arg)*));
+        }
     }};
 }
 
 #[macro_export]
 macro_rules! warn {
     ($($arg:tt)*) => {{
-        eprintln!("[WARN] {}", format_args!($($arg)*));
+        $crate::tracing::count_warning(module_path!());
+        if $crate::tracing::enabled($crate::tracing::LogLevel::Warn) {
+            eprintln!("[WARN] {}", format_args!($($arg)*));
+        }
     }};
 }
 
@@ -0,0 +1,616 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use kira_nuclearqc::panels::loader::MinMappable;
+use kira_nuclearqc::pipeline::stage2_normalize::NormalizeMode;
+use kira_nuclearqc::pipeline::stage7_report::ReportMode;
+use kira_nuclearqc::report::{QuantileMethod, median};
+use kira_nuclearqc::{CsrCounts, RunConfig, RunError, run_counts, run_pipeline};
+
+const GENES: &[&str] = &[
+    "ACTB", "GAPDH", "MKI67", "TOP2A", "PCNA", "MCM2", "TYMS", "CDK1", "BIRC5", "CCNB2", "JUN",
+    "FOS", "MYC", "EGR1", "TP53", "CDKN1A", "MT-CO1", "MT-ND1", "RPL3", "RPS6",
+];
+const N_CELLS: usize = 6;
+
+fn count(gene: usize, cell: usize) -> usize {
+    (gene * 7 + cell * 3) % 5 * (cell + 1)
+}
+
+fn write_fixture(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    let mut features = String::new();
+    for (i, gene) in GENES.iter().enumerate() {
+        writeln!(features, "G{i}\t{gene}\tGene Expression").unwrap();
+    }
+    std::fs::write(dir.join("features.tsv"), features).unwrap();
+    let barcodes = (0..N_CELLS)
+        .map(|c| format!("CELL{c}-1\n"))
+        .collect::<String>();
+    std::fs::write(dir.join("barcodes.tsv"), barcodes).unwrap();
+
+    let mut entries = Vec::new();
+    for cell in 0..N_CELLS {
+        for gene in 0..GENES.len() {
+            let count = count(gene, cell);
+            if count > 0 {
+                entries.push(format!("{} {} {}", gene + 1, cell + 1, count));
+            }
+        }
+    }
+    let mut mtx = format!(
+        "%%MatrixMarket matrix coordinate integer general\n{} {} {}\n",
+        GENES.len(),
+        N_CELLS,
+        entries.len()
+    );
+    for entry in entries {
+        mtx.push_str(&entry);
+        mtx.push('\n');
+    }
+    std::fs::write(dir.join("matrix.mtx"), mtx).unwrap();
+}
+
+#[test]
+fn test_run_pipeline_returns_scores_without_writing() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_lib_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+    let out = root.join("out");
+
+    let mut config = RunConfig::new(root.join("input"), &out);
+    config.write_reports = false;
+    let outputs = run_pipeline(config).unwrap();
+
+    assert_eq!(outputs.barcodes.len(), N_CELLS);
+    assert_eq!(outputs.barcodes[0], "CELL0-1");
+    assert_eq!(outputs.classifications.len(), N_CELLS);
+    assert_eq!(outputs.summary.n_cells, N_CELLS);
+    assert_eq!(outputs.summary.n_genes_raw, GENES.len());
+    for scores in [
+        &outputs.scores.nps,
+        &outputs.scores.ci,
+        &outputs.scores.rls,
+        &outputs.scores.confidence,
+    ] {
+        assert_eq!(scores.len(), N_CELLS);
+        assert!(
+            scores
+                .iter()
+                .all(|v| v.is_finite() && (0.0..=1.0).contains(v))
+        );
+    }
+    assert!(outputs.out_dir.is_none());
+    assert!(!out.exists());
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_run_counts_matches_run_pipeline() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_csr_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+
+    let mut indptr = vec![0i64];
+    let mut indices = Vec::new();
+    let mut data = Vec::new();
+    for cell in 0..N_CELLS {
+        for gene in 0..GENES.len() {
+            let count = count(gene, cell);
+            if count > 0 {
+                indices.push(gene as i64);
+                data.push(count as f64);
+            }
+        }
+        indptr.push(indices.len() as i64);
+    }
+    let barcodes = (0..N_CELLS)
+        .map(|c| format!("CELL{c}-1"))
+        .collect::<Vec<_>>();
+    let gene_symbols = GENES.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+    let counts = CsrCounts {
+        indptr: &indptr,
+        indices: &indices,
+        data: &data,
+        gene_symbols: &gene_symbols,
+        barcodes: &barcodes,
+    };
+
+    for normalize_mode in [NormalizeMode::LogCp10k, NormalizeMode::PearsonResiduals] {
+        let mut config = RunConfig::new(root.join("input"), root.join("out"));
+        config.write_reports = false;
+        config.normalize_mode = normalize_mode;
+        let expected = run_pipeline(config.clone()).unwrap();
+        let outputs = run_counts(config, counts).unwrap();
+
+        assert_eq!(outputs.barcodes, expected.barcodes);
+        assert_eq!(outputs.axes.drbi, expected.axes.drbi);
+        assert_eq!(outputs.axes.rss, expected.axes.rss);
+        assert_eq!(outputs.scores.nps, expected.scores.nps);
+        assert_eq!(outputs.scores.ci, expected.scores.ci);
+        assert_eq!(outputs.scores.rls, expected.scores.rls);
+        assert_eq!(outputs.scores.confidence, expected.scores.confidence);
+        assert_eq!(outputs.summary.n_cells, N_CELLS);
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_min_mappable_fraction_excludes_or_fails() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_map_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+    let mut config = RunConfig::new(root.join("input"), root.join("out"));
+    config.write_reports = false;
+
+    // Default: nothing is excluded, however few genes map.
+    let baseline = run_pipeline(config.clone()).unwrap();
+    assert!(baseline.summary.excluded_panels.is_empty());
+
+    // Every built-in panel is only partly covered by the fixture genes.
+    config.min_mappable = MinMappable::parse("1.0").unwrap();
+    let outputs = run_pipeline(config.clone()).unwrap();
+    let excluded = &outputs.summary.excluded_panels;
+    assert!(excluded.iter().any(|p| p.panel_id == "housekeeping_core"));
+    assert!(excluded.iter().all(|p| p.mappable_fraction < 1.0));
+    assert!(excluded.iter().all(|p| !p.missing_genes.is_empty()));
+    assert!(outputs.summary.key_panels.is_empty());
+    assert!(outputs.scores.nps.iter().all(|v| v.is_finite()));
+
+    config.strict_mappable = true;
+    match run_pipeline(config) {
+        Err(RunError::Config(msg)) => {
+            assert!(msg.contains("--strict-mappable"), "{msg}");
+            assert!(msg.contains("housekeeping_core"), "{msg}");
+            assert!(msg.contains("missing: "), "{msg}");
+        }
+        other => panic!(
+            "expected a config error, got {:?}",
+            other.map(|o| o.barcodes)
+        ),
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_libsize_is_exact_above_f32_precision() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_lib64_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let input = root.join("input");
+    write_fixture(&input);
+
+    // A pseudobulk-like first cell of ~50M counts; each entry is exact in
+    // f32, but an f32 total would be rounded to a multiple of 4.
+    let big = |gene: usize| 2_500_001 + gene;
+    let mut entries = (0..GENES.len())
+        .map(|gene| format!("{} 1 {}", gene + 1, big(gene)))
+        .collect::<Vec<_>>();
+    for cell in 1..N_CELLS {
+        for gene in 0..GENES.len() {
+            if count(gene, cell) > 0 {
+                entries.push(format!("{} {} {}", gene + 1, cell + 1, count(gene, cell)));
+            }
+        }
+    }
+    let mtx = format!(
+        "%%MatrixMarket matrix coordinate integer general\n{} {} {}\n{}\n",
+        GENES.len(),
+        N_CELLS,
+        entries.len(),
+        entries.join("\n")
+    );
+    std::fs::write(input.join("matrix.mtx"), mtx).unwrap();
+
+    run_pipeline(RunConfig::new(&input, root.join("out"))).unwrap();
+
+    let tsv = std::fs::read_to_string(root.join("out").join("nuclearqc.tsv")).unwrap();
+    let mut rows = tsv.lines().map(|l| l.split('\t').collect::<Vec<_>>());
+    let header = rows.next().unwrap();
+    let col = header.iter().position(|c| *c == "libsize").unwrap();
+    let row = rows.find(|r| r[0] == "CELL0-1").unwrap();
+    let expected = (0..GENES.len()).map(big).sum::<usize>();
+    assert_eq!(expected, 50_000_210);
+    assert_ne!(expected as f32 as usize, expected);
+    assert_eq!(row[col], format!("{expected}.000000"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_max_cells_subsamples_before_scoring() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_sample_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let input = root.join("input");
+    write_fixture(&input);
+    let tsv_rows = |out: &Path| {
+        std::fs::read_to_string(out.join("nuclearqc.tsv"))
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    let full = run_pipeline(RunConfig::new(&input, root.join("full"))).unwrap();
+    assert_eq!(full.summary.sampled_from, None);
+
+    let mut config = RunConfig::new(&input, root.join("one"));
+    config.max_cells = Some(1);
+    let one = run_pipeline(config).unwrap();
+    assert_eq!(one.barcodes, vec!["CELL0-1".to_string()]);
+    assert_eq!(tsv_rows(&root.join("one")).len(), 1);
+    let summary = std::fs::read_to_string(root.join("one").join("summary.json")).unwrap();
+    assert!(summary.contains("\"sampled\":true"), "{summary}");
+    assert!(summary.contains("\"n_cells_total\":6"), "{summary}");
+
+    // Evenly strided cells keep their own counts.
+    let mut config = RunConfig::new(&input, root.join("two"));
+    config.max_cells = Some(2);
+    let two = run_pipeline(config).unwrap();
+    assert_eq!(
+        two.barcodes,
+        vec!["CELL0-1".to_string(), "CELL3-1".to_string()]
+    );
+    assert_eq!(two.scores.confidence.len(), 2);
+    let libsize = |row: &str| row.split('\t').nth(4).unwrap().to_string();
+    let full_rows = tsv_rows(&root.join("full"));
+    for row in tsv_rows(&root.join("two")) {
+        let barcode = row.split('\t').next().unwrap();
+        let full_row = full_rows.iter().find(|r| r.starts_with(barcode)).unwrap();
+        assert_eq!(libsize(&row), libsize(full_row));
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_cell_stdout_streams_clean_tsv() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_stdout_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_kira-nuclearqc"))
+        .args(["run", "--cell-stdout", "--input"])
+        .arg(root.join("input"))
+        .arg("--out")
+        .arg(root.join("out"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(
+        lines[0].starts_with("barcode\tsample\tcondition\t"),
+        "{}",
+        lines[0]
+    );
+    assert_eq!(lines.len(), N_CELLS + 1);
+    let n_columns = lines[0].split('\t').count();
+    assert!(lines.iter().all(|l| l.split('\t').count() == n_columns));
+    assert!(lines[1].starts_with("CELL0-1\t"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SIMD backend"));
+
+    // The other reports still land in --out.
+    assert!(root.join("out/summary.json").exists());
+    assert!(!root.join("out/nuclearqc.tsv").exists());
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_cluster_mode_aggregates_by_cluster() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_cluster_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let input = root.join("input");
+    write_fixture(&input);
+    // CELL5 is left unassigned.
+    let clusters = root.join("clusters.tsv");
+    std::fs::write(
+        &clusters,
+        "barcode\tleiden\nCELL0-1\tA\nCELL1-1\tA\nCELL2-1\tA\nCELL3-1\tB\nCELL4-1\tB\n",
+    )
+    .unwrap();
+
+    let mut config = RunConfig::new(&input, root.join("out"));
+    config.report_mode = ReportMode::Cluster;
+    assert!(matches!(
+        run_pipeline(config.clone()),
+        Err(RunError::Config(msg)) if msg.contains("--clusters")
+    ));
+
+    config.clusters_path = Some(clusters);
+    let outputs = run_pipeline(config).unwrap();
+    let groups: [(&str, &[usize]); 3] = [("(none)", &[5]), ("A", &[0, 1, 2]), ("B", &[3, 4])];
+    assert_eq!(outputs.summary.clusters.len(), groups.len());
+    for (stat, (name, cells)) in outputs.summary.clusters.iter().zip(groups) {
+        let group_median = |values: &[f32]| {
+            median(
+                &cells.iter().map(|&c| values[c]).collect::<Vec<_>>(),
+                QuantileMethod::Linear,
+            )
+        };
+        assert_eq!(stat.cluster, name);
+        assert_eq!(stat.n_cells, cells.len());
+        assert_eq!(stat.fraction, cells.len() as f32 / N_CELLS as f32);
+        assert_eq!(stat.nps_median, group_median(&outputs.scores.nps));
+        assert_eq!(stat.ci_median, group_median(&outputs.scores.ci));
+        assert_eq!(stat.rls_median, group_median(&outputs.scores.rls));
+    }
+
+    let tsv = std::fs::read_to_string(root.join("out/nuclearqc.tsv")).unwrap();
+    let lines = tsv.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("cluster\tn_cells\ta1_tbi_median\t"));
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].starts_with("(none)\t1\t"));
+    assert!(lines[2].starts_with("A\t3\t"));
+    assert!(lines[3].starts_with("B\t2\t"));
+    let summary = std::fs::read_to_string(root.join("out/summary.json")).unwrap();
+    assert!(summary.contains("\"resolution\":\"cluster\""), "{summary}");
+    assert!(summary.contains("\"clusters\":[{\"cluster\":\"(none)\",\"n_cells\":1.000000,"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_explain_panel_lists_top_genes() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_explain_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let input = root.join("input");
+    write_fixture(&input);
+    let contributions = |out: &Path| {
+        std::fs::read_to_string(out.join("panel_housekeeping_core_contributions.tsv")).unwrap()
+    };
+
+    let mut config = RunConfig::new(&input, root.join("out"));
+    config.explain_panel = Some("housekeeping_core".to_string());
+    run_pipeline(config.clone()).unwrap();
+    let text = contributions(&root.join("out"));
+    let mut lines = text.lines();
+    assert_eq!(lines.next(), Some("barcode\tgene_symbol\tvalue"));
+    let rows = lines
+        .map(|l| l.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    // ACTB and GAPDH are the fixture's housekeeping genes; normalization
+    // keeps their order within a cell.
+    for cell in 0..N_CELLS {
+        let barcode = format!("CELL{cell}-1");
+        let top = rows.iter().find(|r| r[0] == barcode);
+        let (actb, gapdh) = (count(0, cell), count(1, cell));
+        let expected = match (actb, gapdh) {
+            (0, 0) => None,
+            _ if gapdh > actb => Some("GAPDH"),
+            _ => Some("ACTB"),
+        };
+        assert_eq!(top.map(|r| r[1]), expected, "{barcode}");
+    }
+
+    config.out_dir = root.join("chunked");
+    config.chunk_cells = Some(4);
+    run_pipeline(config.clone()).unwrap();
+    assert_eq!(contributions(&root.join("chunked")), text);
+
+    // An unknown id fails before the chunked scan writes the normalized export.
+    config.out_dir = root.join("unknown");
+    config.emit_normalized_mtx = true;
+    config.explain_panel = Some("no_such_panel".to_string());
+    assert!(matches!(
+        run_pipeline(config.clone()),
+        Err(RunError::Config(msg)) if msg == "unknown --explain-panel panel: no_such_panel"
+    ));
+    assert!(!root.join("unknown/normalized").exists());
+
+    config.explain_panel = Some("../housekeeping_core".to_string());
+    assert!(config.validate().unwrap_err().contains("path separator"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_control_features_excluded_from_libsize() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_ercc_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let plain = root.join("plain");
+    write_fixture(&plain);
+    let input = root.join("input");
+    write_fixture(&input);
+
+    // Two spike-ins and a multiplexing oligo after the genes, with counts in
+    // every cell.
+    let controls = ["ERCC-00002", "ERCC-00003", "CMO301"];
+    let control_count = |control: usize, cell: usize| 10 * (control + 1) + cell;
+    let mut features = std::fs::read_to_string(input.join("features.tsv")).unwrap();
+    for (i, control) in controls.iter().enumerate() {
+        writeln!(features, "C{i}\t{control}\tGene Expression").unwrap();
+    }
+    std::fs::write(input.join("features.tsv"), features).unwrap();
+    let mtx = std::fs::read_to_string(input.join("matrix.mtx")).unwrap();
+    let mut lines = mtx.lines().map(str::to_string).collect::<Vec<_>>();
+    let nnz = lines[1]
+        .split(' ')
+        .nth(2)
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+    lines[1] = format!(
+        "{} {} {}",
+        GENES.len() + controls.len(),
+        N_CELLS,
+        nnz + controls.len() * N_CELLS
+    );
+    for control in 0..controls.len() {
+        for cell in 0..N_CELLS {
+            let count = control_count(control, cell);
+            lines.push(format!(
+                "{} {} {count}",
+                GENES.len() + control + 1,
+                cell + 1
+            ));
+        }
+    }
+    std::fs::write(input.join("matrix.mtx"), lines.join("\n") + "\n").unwrap();
+
+    let expected = run_pipeline(RunConfig::new(&plain, root.join("plain_out"))).unwrap();
+    // Controls are split from counts read whole, summed while spilling
+    // chunks, or streamed on their own next to the normalized cache.
+    for (name, chunk_cells, cache_normalized) in [
+        ("out", None, false),
+        ("chunked", Some(2), false),
+        ("cached", None, true),
+    ] {
+        let mut config = RunConfig::new(&input, root.join(name));
+        config.control_prefixes = vec!["CMO".to_string()];
+        config.chunk_cells = chunk_cells;
+        config.cache_normalized = cache_normalized;
+        let outputs = run_pipeline(config).unwrap();
+        assert_eq!(outputs.scores.nps, expected.scores.nps);
+        assert_eq!(outputs.axes.tbi, expected.axes.tbi);
+        assert_eq!(outputs.summary.n_genes_mappable, GENES.len());
+        assert_eq!(outputs.summary.n_control_features, 3);
+
+        let tsv = std::fs::read_to_string(root.join(name).join("nuclearqc.tsv")).unwrap();
+        let mut rows = tsv.lines().map(|l| l.split('\t').collect::<Vec<_>>());
+        let header = rows.next().unwrap();
+        let column = |name: &str| header.iter().position(|c| *c == name).unwrap();
+        let (libsize_col, control_col) = (column("libsize"), column("pct_control"));
+        for (cell, row) in rows.enumerate() {
+            let libsize = (0..GENES.len()).map(|g| count(g, cell)).sum::<usize>() as f64;
+            let control = (0..controls.len())
+                .map(|c| control_count(c, cell))
+                .sum::<usize>() as f64;
+            assert_eq!(row[libsize_col].parse::<f64>().unwrap(), libsize);
+            let pct_control = row[control_col].parse::<f64>().unwrap();
+            assert!((pct_control - control / (control + libsize)).abs() < 1e-6);
+        }
+    }
+
+    // Without the extra prefix the oligo is scored as an ordinary gene.
+    let ercc_only = run_pipeline(RunConfig::new(&input, root.join("ercc"))).unwrap();
+    assert_eq!(ercc_only.summary.n_control_features, 2);
+    assert_eq!(ercc_only.summary.n_genes_mappable, GENES.len() + 1);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_fail_on_warn_rejects_duplicate_symbols() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_warn_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let input = root.join("input");
+    write_fixture(&input);
+    let features = std::fs::read_to_string(input.join("features.tsv")).unwrap();
+    std::fs::write(
+        input.join("features.tsv"),
+        features.replace("G1\tGAPDH", "G1\tACTB"),
+    )
+    .unwrap();
+
+    let mut config = RunConfig::new(&input, root.join("out"));
+    run_pipeline(config.clone()).unwrap();
+
+    config.fail_on_warn = true;
+    config.emit_step_json = true;
+    match run_pipeline(config) {
+        Err(err @ RunError::Warnings(_)) => {
+            let msg = err.to_string();
+            assert!(msg.starts_with("--fail-on-warn: "), "{msg}");
+            assert!(msg.contains("input: "), "{msg}");
+        }
+        other => panic!(
+            "expected a warnings error, got {:?}",
+            other.map(|o| o.barcodes)
+        ),
+    }
+    // Reports are written before the run fails, and the step records it.
+    assert!(root.join("out").join("summary.json").exists());
+    let step = std::fs::read_to_string(root.join("out").join("pipeline_step.json")).unwrap();
+    assert!(step.contains("\"status\":\"failed_on_warnings\""), "{step}");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_step_status_counts_only_own_warnings() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_status_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("clean"));
+    write_fixture(&root.join("dup"));
+    let features = std::fs::read_to_string(root.join("dup").join("features.tsv")).unwrap();
+    std::fs::write(
+        root.join("dup").join("features.tsv"),
+        features.replace("G1\tGAPDH", "G1\tACTB"),
+    )
+    .unwrap();
+    let config = |name: &str| {
+        let mut config = RunConfig::new(root.join(name), root.join(format!("out_{name}")));
+        config.emit_step_json = true;
+        config
+    };
+    // `"warnings":N` of each run's `pipeline_step.json`.
+    let warnings = |name: &str| {
+        let step =
+            std::fs::read_to_string(root.join(format!("out_{name}")).join("pipeline_step.json"))
+                .unwrap();
+        assert!(step.contains("\"fnv1a64\":"));
+        let count = step.split_once("\"warnings\":").unwrap().1;
+        count[..count.find(',').unwrap()].parse::<usize>().unwrap()
+    };
+
+    run_pipeline(config("clean")).unwrap();
+    run_pipeline(config("dup")).unwrap();
+    let (clean, dup) = (warnings("clean"), warnings("dup"));
+    assert_eq!(dup, clean + 1);
+
+    std::thread::scope(|s| {
+        let dup = s.spawn(|| run_pipeline(config("dup")).unwrap());
+        let clean = s.spawn(|| run_pipeline(config("clean")).unwrap());
+        dup.join().unwrap();
+        clean.join().unwrap();
+    });
+    assert_eq!((warnings("clean"), warnings("dup")), (clean, dup));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_quantile_method_is_per_run() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_quant_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+    let mut linear = RunConfig::new(root.join("input"), root.join("out"));
+    linear.write_reports = false;
+    let mut legacy = linear.clone();
+    legacy.legacy_quantiles = true;
+
+    let expected = run_pipeline(linear.clone()).unwrap().summary;
+    let (linear, legacy) = std::thread::scope(|s| {
+        let legacy = s.spawn(|| run_pipeline(legacy).unwrap().summary);
+        let linear = s.spawn(|| run_pipeline(linear).unwrap().summary);
+        (linear.join().unwrap(), legacy.join().unwrap())
+    });
+    assert_eq!(linear.quantile_method, QuantileMethod::Linear);
+    assert_eq!(legacy.quantile_method, QuantileMethod::LegacyCeil);
+    assert_eq!(linear.composites[0].median, expected.composites[0].median);
+    assert_eq!(linear.confidence_median, expected.confidence_median);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_summary_json_is_byte_stable() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_stable_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_fixture(&root.join("input"));
+    let summary = |out: &str| {
+        run_pipeline(RunConfig::new(root.join("input"), root.join(out))).unwrap();
+        assert!(root.join(out).join("performance.json").exists());
+        std::fs::read(root.join(out).join("summary.json")).unwrap()
+    };
+    assert_eq!(summary("a"), summary("b"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
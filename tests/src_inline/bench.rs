@@ -0,0 +1,74 @@
+use super::*;
+use crate::{RunConfig, run_counts, run_pipeline};
+
+fn small_spec(seed: u64) -> SyntheticSpec {
+    SyntheticSpec {
+        n_cells: 120,
+        n_genes: 800,
+        nnz_per_cell: 150,
+        seed,
+    }
+}
+
+#[test]
+fn test_generate_is_deterministic_per_seed() {
+    let a = SyntheticData::generate(&small_spec(7));
+    assert_eq!(a, SyntheticData::generate(&small_spec(7)));
+    assert_ne!(a.data, SyntheticData::generate(&small_spec(8)).data);
+}
+
+#[test]
+fn test_generate_shape() {
+    let spec = small_spec(0);
+    let data = SyntheticData::generate(&spec);
+    assert_eq!(data.gene_symbols.len(), spec.n_genes);
+    assert_eq!(data.gene_ids.len(), spec.n_genes);
+    assert_eq!(data.barcodes.len(), spec.n_cells);
+    assert_eq!(data.barcodes[1], "AAAAAAAAAAAAAAAC-1");
+    assert_eq!(data.indptr.len(), spec.n_cells + 1);
+    assert_eq!(data.gene_symbols[0], builtin_panels()[0].genes[0]);
+    assert!(data.gene_symbols.iter().any(|s| s == "MT-CO1"));
+    assert!(data.gene_symbols.iter().any(|s| s == "RPS6"));
+    let unique = data.gene_symbols.iter().collect::<BTreeSet<_>>();
+    assert_eq!(unique.len(), spec.n_genes);
+
+    for cell in 0..spec.n_cells {
+        let genes = &data.indices[data.indptr[cell] as usize..data.indptr[cell + 1] as usize];
+        assert!((75..=225).contains(&genes.len()));
+        assert!(genes.windows(2).all(|w| w[0] < w[1]));
+    }
+    assert!(data.data.iter().all(|&v| v >= 1.0 && v.fract() == 0.0));
+    let mean_nnz = data.indices.len() as f64 / spec.n_cells as f64;
+    assert!((mean_nnz - 150.0).abs() < 15.0);
+}
+
+#[test]
+fn test_generate_caps_nnz_at_gene_count() {
+    let data = SyntheticData::generate(&SyntheticSpec {
+        n_cells: 3,
+        n_genes: 10,
+        nnz_per_cell: 50,
+        seed: 1,
+    });
+    assert_eq!(data.indptr, vec![0, 10, 20, 30]);
+}
+
+#[test]
+fn test_write_tenx_matches_in_memory_run() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_synth_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let data = SyntheticData::generate(&small_spec(3));
+    data.write_tenx(&root.join("input")).unwrap();
+
+    let mut config = RunConfig::new(root.join("input"), root.join("out"));
+    config.write_reports = false;
+    let expected = run_pipeline(config.clone()).unwrap();
+    let outputs = run_counts(config, data.counts()).unwrap();
+    assert_eq!(outputs.barcodes, expected.barcodes);
+    assert_eq!(outputs.axes.tbi, expected.axes.tbi);
+    assert_eq!(outputs.scores.nps, expected.scores.nps);
+    assert_eq!(outputs.scores.confidence, expected.scores.confidence);
+    assert!(outputs.summary.n_genes_mappable > 0);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,65 @@
+use super::*;
+use crate::input::build_gene_index;
+use crate::input::features::{Feature, normalize_symbol};
+use crate::input::mtx::collect_columns;
+
+fn gene_index(symbols: &[&str]) -> GeneIndex {
+    let features = symbols
+        .iter()
+        .map(|s| Feature {
+            id: s.to_string(),
+            symbol_raw: s.to_string(),
+            symbol_norm: normalize_symbol(s),
+            feature_type: None,
+        })
+        .collect::<Vec<_>>();
+    build_gene_index(&features)
+}
+
+#[test]
+fn test_is_control_symbol() {
+    let prefixes = control_prefixes(&["CMO".to_string()]);
+    assert_eq!(prefixes, ["ERCC-", "CMO"]);
+    assert!(is_control_symbol("ERCC-00002", &prefixes));
+    assert!(is_control_symbol("CMO301", &prefixes));
+    assert!(is_control_symbol("ercc-00002", &prefixes));
+    assert!(!is_control_symbol("ERCC1", &prefixes));
+    assert!(!is_control_symbol("ERC", &prefixes));
+}
+
+#[test]
+fn test_split_controls_renumbers_genes() {
+    let index = gene_index(&["ACTB", "ERCC-00002", "GAPDH", "ercc-00003", "GAPDH"]);
+    let prefixes = control_prefixes(&[]);
+    let split = split_controls(&index, &prefixes).unwrap();
+    assert_eq!(split.gene_index.symbols_by_gene_id, ["ACTB", "GAPDH"]);
+    assert_eq!(
+        split.gene_index.gene_id_by_feature,
+        [Some(0), None, Some(1), None, Some(1)]
+    );
+    assert_eq!(split.kept_gene_id, [Some(0), None, Some(1), None]);
+    assert_eq!(split.control_by_feature, [false, true, false, true, false]);
+
+    assert!(split_controls(&gene_index(&["ACTB", "GAPDH"]), &prefixes).is_none());
+}
+
+#[test]
+fn test_split_genes_sums_dropped_counts() {
+    // Genes 0..3 with gene 1 a control, over two cells.
+    let per_col = vec![
+        [(0u32, 4i64), (1, 6), (2, 1)].into_iter().collect(),
+        [(1u32, 3i64)].into_iter().collect(),
+    ];
+    let csc = collect_columns(3, per_col);
+    let (kept, dropped) = csc.split_genes(&[Some(0), None, Some(1)]);
+    assert_eq!(dropped, [6.0, 3.0]);
+    assert_eq!(kept.column(0), (&[0u32, 1][..], &[4i64, 1][..]));
+    assert_eq!(kept.column(1), (&[][..], &[][..]));
+}
+
+#[test]
+fn test_control_fraction() {
+    let fraction = control_fraction(Some(&[5.0, 0.0, 0.0]), &[15.0, 10.0, 0.0]);
+    assert_eq!(fraction, [0.25, 0.0, 0.0]);
+    assert_eq!(control_fraction(None, &[3.0, 4.0]), [0.0, 0.0]);
+}
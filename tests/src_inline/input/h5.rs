@@ -0,0 +1,460 @@
+use super::*;
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+
+static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn make_temp_dir() -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    let id = DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+    dir.push(format!("kira_h5_test_{}_{}", std::process::id(), id));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Element values of a synthetic dataset, in row-major order.
+pub(crate) enum Values<'a> {
+    Int32(&'a [i32]),
+    Float32(&'a [f32]),
+    /// Null-padded strings of the given size.
+    FixedString(&'a [&'a str], usize),
+    VarString(&'a [&'a str]),
+}
+
+/// Writes small HDF5 files the way libhdf5 lays them out: with
+/// `new_format`, a v2 superblock, v2 object headers and link messages;
+/// otherwise a v0 superblock, v1 object headers and symbol-table groups.
+/// Chunked datasets are shuffled and deflated, with edge chunks padded.
+pub(crate) struct H5Writer {
+    buf: Vec<u8>,
+    new_format: bool,
+}
+
+const UNDEF: u64 = u64::MAX;
+
+impl H5Writer {
+    pub(crate) fn new(new_format: bool) -> Self {
+        let superblock = if new_format { 48 } else { 96 };
+        H5Writer {
+            buf: vec![0; superblock],
+            new_format,
+        }
+    }
+
+    fn alloc(&mut self, bytes: &[u8]) -> u64 {
+        self.buf.resize(self.buf.len().next_multiple_of(8), 0);
+        let at = self.buf.len() as u64;
+        self.buf.extend_from_slice(bytes);
+        at
+    }
+
+    /// A dataset of `shape`, chunked by `chunk` when given.
+    pub(crate) fn dataset(&mut self, shape: &[u64], values: Values, chunk: Option<&[u64]>) -> u64 {
+        let (dtype, size, elements) = self.encode(values);
+        let n = shape.iter().product::<u64>() as usize;
+        assert_eq!(elements.len(), n * size);
+
+        let mut dataspace = Vec::new();
+        if self.new_format {
+            dataspace.extend([2, shape.len() as u8, 0, 1]);
+        } else {
+            dataspace.extend([1, shape.len() as u8, 0, 0, 0, 0, 0, 0]);
+        }
+        for &d in shape {
+            dataspace.extend(d.to_le_bytes());
+        }
+
+        let mut messages = vec![(0x01, dataspace), (0x03, dtype)];
+        match chunk {
+            None => {
+                let address = self.alloc(&elements);
+                let mut layout = vec![3, 1];
+                layout.extend(address.to_le_bytes());
+                layout.extend((elements.len() as u64).to_le_bytes());
+                messages.push((0x08, layout));
+            }
+            Some(chunk) => {
+                let btree = self.chunks(shape, chunk, size, &elements);
+                let mut layout = vec![3, 2, chunk.len() as u8 + 1];
+                layout.extend(btree.to_le_bytes());
+                for &d in chunk {
+                    layout.extend((d as u32).to_le_bytes());
+                }
+                layout.extend((size as u32).to_le_bytes());
+                messages.push((0x08, layout));
+                // Shuffle, then deflate at level 4.
+                let mut filters = vec![1, 2, 0, 0, 0, 0, 0, 0];
+                for (id, value) in [(2u16, size as u32), (1, 4)] {
+                    filters.extend(id.to_le_bytes());
+                    filters.extend([0, 0, 0, 0]);
+                    filters.extend(1u16.to_le_bytes());
+                    filters.extend(value.to_le_bytes());
+                    filters.extend([0, 0, 0, 0]);
+                }
+                messages.push((0x0b, filters));
+            }
+        }
+        self.object_header(&messages)
+    }
+
+    /// A group linking `members` by name.
+    pub(crate) fn group(&mut self, members: &[(&str, u64)]) -> u64 {
+        if self.new_format {
+            let messages = members
+                .iter()
+                .map(|(name, address)| {
+                    let mut link = vec![1, 0, name.len() as u8];
+                    link.extend(name.as_bytes());
+                    link.extend(address.to_le_bytes());
+                    (0x06, link)
+                })
+                .collect::<Vec<_>>();
+            return self.object_header(&messages);
+        }
+
+        // Local heap of names, the first one empty as libhdf5 writes it.
+        let mut names = vec![0u8; 8];
+        let mut offsets = Vec::new();
+        for (name, _) in members {
+            offsets.push(names.len() as u64);
+            names.extend(name.as_bytes());
+            names.push(0);
+            names.resize(names.len().next_multiple_of(8), 0);
+        }
+        let data = self.alloc(&names);
+        let mut heap = b"HEAP\0\0\0\0".to_vec();
+        heap.extend((names.len() as u64).to_le_bytes());
+        heap.extend(UNDEF.to_le_bytes());
+        heap.extend(data.to_le_bytes());
+        let heap = self.alloc(&heap);
+
+        let mut node = b"SNOD\x01\0".to_vec();
+        node.extend((members.len() as u16).to_le_bytes());
+        for ((_, address), offset) in members.iter().zip(&offsets) {
+            node.extend(offset.to_le_bytes());
+            node.extend(address.to_le_bytes());
+            node.extend([0; 24]);
+        }
+        let node = self.alloc(&node);
+
+        let mut tree = b"TREE\0\0".to_vec();
+        tree.extend(1u16.to_le_bytes());
+        tree.extend(UNDEF.to_le_bytes());
+        tree.extend(UNDEF.to_le_bytes());
+        tree.extend(0u64.to_le_bytes());
+        tree.extend(node.to_le_bytes());
+        tree.extend(offsets.last().copied().unwrap_or(0).to_le_bytes());
+        let tree = self.alloc(&tree);
+
+        let mut symbol_table = tree.to_le_bytes().to_vec();
+        symbol_table.extend(heap.to_le_bytes());
+        self.object_header(&[(0x11, symbol_table)])
+    }
+
+    /// The file bytes, with `root` as the root group.
+    pub(crate) fn finish(mut self, root: u64) -> Vec<u8> {
+        let eof = self.buf.len() as u64;
+        let mut sb = b"\x89HDF\r\n\x1a\n".to_vec();
+        if self.new_format {
+            sb.extend([2, 8, 8, 0]);
+            sb.extend(0u64.to_le_bytes());
+            sb.extend(UNDEF.to_le_bytes());
+            sb.extend(eof.to_le_bytes());
+            sb.extend(root.to_le_bytes());
+            sb.extend([0; 4]);
+        } else {
+            sb.extend([0, 0, 0, 0, 0, 8, 8, 0]);
+            sb.extend(4u16.to_le_bytes());
+            sb.extend(16u16.to_le_bytes());
+            sb.extend([0; 4]);
+            sb.extend(0u64.to_le_bytes());
+            sb.extend(UNDEF.to_le_bytes());
+            sb.extend(eof.to_le_bytes());
+            sb.extend(UNDEF.to_le_bytes());
+            sb.extend(0u64.to_le_bytes());
+            sb.extend(root.to_le_bytes());
+            sb.extend([0; 24]);
+        }
+        self.buf[..sb.len()].copy_from_slice(&sb);
+        self.buf
+    }
+
+    fn object_header(&mut self, messages: &[(u16, Vec<u8>)]) -> u64 {
+        let mut body = Vec::new();
+        for (kind, data) in messages {
+            if self.new_format {
+                body.push(*kind as u8);
+                body.extend((data.len() as u16).to_le_bytes());
+                body.push(0);
+                body.extend(data);
+            } else {
+                let padded = data.len().next_multiple_of(8);
+                body.extend(kind.to_le_bytes());
+                body.extend((padded as u16).to_le_bytes());
+                body.extend([0; 4]);
+                body.extend(data);
+                body.resize(body.len() + padded - data.len(), 0);
+            }
+        }
+        let mut header = Vec::new();
+        if self.new_format {
+            header.extend(b"OHDR\x02\x02");
+            header.extend((body.len() as u32).to_le_bytes());
+            header.extend(body);
+            header.extend([0; 4]);
+        } else {
+            header.extend([1, 0]);
+            header.extend((messages.len() as u16).to_le_bytes());
+            header.extend(1u32.to_le_bytes());
+            header.extend((body.len() as u32).to_le_bytes());
+            header.extend([0; 4]);
+            header.extend(body);
+        }
+        self.alloc(&header)
+    }
+
+    // Datatype message, element size and element bytes.
+    fn encode(&mut self, values: Values) -> (Vec<u8>, usize, Vec<u8>) {
+        let header = |class: u8, bits: u8, size: u32| {
+            let mut dtype = vec![0x10 | class, bits, 0, 0];
+            dtype.extend(size.to_le_bytes());
+            dtype
+        };
+        match values {
+            Values::Int32(values) => {
+                let mut dtype = header(0, 0x08, 4);
+                dtype.extend([0, 0, 32, 0]);
+                (
+                    dtype,
+                    4,
+                    values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                )
+            }
+            Values::Float32(values) => {
+                let mut dtype = header(1, 0x20, 4);
+                dtype.extend([0, 0, 32, 0, 23, 8, 0, 23, 127, 0, 0, 0]);
+                (
+                    dtype,
+                    4,
+                    values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                )
+            }
+            Values::FixedString(values, size) => {
+                let elements = values
+                    .iter()
+                    .flat_map(|v| {
+                        let mut bytes = v.as_bytes().to_vec();
+                        bytes.resize(size, 0);
+                        bytes
+                    })
+                    .collect();
+                (header(3, 0x01, size as u32), size, elements)
+            }
+            Values::VarString(values) => {
+                // One global heap collection holding every string.
+                let mut objects = Vec::new();
+                for (i, v) in values.iter().enumerate() {
+                    objects.extend((i as u16 + 1).to_le_bytes());
+                    objects.extend([1, 0, 0, 0, 0, 0]);
+                    objects.extend((v.len() as u64).to_le_bytes());
+                    objects.extend(v.as_bytes());
+                    objects.resize(objects.len().next_multiple_of(8), 0);
+                }
+                objects.extend([0; 16]);
+                let mut collection = b"GCOL\x01\0\0\0".to_vec();
+                collection.extend((16 + objects.len() as u64).to_le_bytes());
+                collection.extend(objects);
+                let collection = self.alloc(&collection);
+
+                let elements = values
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, v)| {
+                        let mut bytes = (v.len() as u32).to_le_bytes().to_vec();
+                        bytes.extend(collection.to_le_bytes());
+                        bytes.extend((i as u32 + 1).to_le_bytes());
+                        bytes
+                    })
+                    .collect();
+                let mut dtype = header(9, 0x01, 16);
+                dtype.extend(header(3, 0x00, 1));
+                (dtype, 16, elements)
+            }
+        }
+    }
+
+    // Writes the chunks of a row-major dataset and returns the B-tree over them.
+    fn chunks(&mut self, shape: &[u64], chunk: &[u64], size: usize, elements: &[u8]) -> u64 {
+        let grid = shape
+            .iter()
+            .zip(chunk)
+            .map(|(&d, &c)| d.div_ceil(c))
+            .collect::<Vec<_>>();
+        let n_chunks = grid.iter().product::<u64>();
+        let chunk_elements = chunk.iter().product::<u64>() as usize;
+        let mut keys = Vec::new();
+        for k in 0..n_chunks {
+            // Chunk coordinates, last dimension fastest.
+            let mut rest = k;
+            let mut origin = vec![0u64; shape.len()];
+            for d in (0..shape.len()).rev() {
+                origin[d] = (rest % grid[d]) * chunk[d];
+                rest /= grid[d];
+            }
+            let mut data = vec![0u8; chunk_elements * size];
+            for i in 0..chunk_elements as u64 {
+                let mut rest = i;
+                let mut index = 0u64;
+                let mut inside = true;
+                let mut local = vec![0u64; shape.len()];
+                for d in (0..shape.len()).rev() {
+                    local[d] = rest % chunk[d];
+                    rest /= chunk[d];
+                }
+                for d in 0..shape.len() {
+                    let coord = origin[d] + local[d];
+                    inside &= coord < shape[d];
+                    index = index * shape[d] + coord;
+                }
+                if inside {
+                    let (at, from) = (i as usize * size, index as usize * size);
+                    data[at..at + size].copy_from_slice(&elements[from..from + size]);
+                }
+            }
+            let mut shuffled = vec![0u8; data.len()];
+            for i in 0..chunk_elements {
+                for byte in 0..size {
+                    shuffled[byte * chunk_elements + i] = data[i * size + byte];
+                }
+            }
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(4));
+            encoder.write_all(&shuffled).unwrap();
+            let stored = encoder.finish().unwrap();
+            let address = self.alloc(&stored);
+            keys.push((stored.len() as u32, origin, address));
+        }
+
+        let mut tree = b"TREE\x01\0".to_vec();
+        tree.extend((keys.len() as u16).to_le_bytes());
+        tree.extend(UNDEF.to_le_bytes());
+        tree.extend(UNDEF.to_le_bytes());
+        for (stored, origin, address) in &keys {
+            tree.extend(stored.to_le_bytes());
+            tree.extend(0u32.to_le_bytes());
+            for o in origin.iter().chain([&0]) {
+                tree.extend(o.to_le_bytes());
+            }
+            tree.extend(address.to_le_bytes());
+        }
+        tree.extend([0; 8]);
+        for d in shape.iter().chain([&0]) {
+            tree.extend(d.to_le_bytes());
+        }
+        self.alloc(&tree)
+    }
+}
+
+fn write_h5(bytes: Vec<u8>) -> PathBuf {
+    let path = make_temp_dir().join("test.h5");
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn nonzeros(dataset: &Dataset<'_>) -> Vec<(u64, u64, f64)> {
+    let mut out = Vec::new();
+    dataset
+        .for_each_nonzero(|row, col, value| out.push((row, col, value)))
+        .unwrap();
+    out.sort_by_key(|&(row, col, _)| (row, col));
+    out
+}
+
+// 5 × 3, so 2 × 2 chunks leave partial chunks on both edges.
+const MATRIX: [i32; 15] = [1, 0, 2, 0, 0, 3, 4, 0, 0, 0, 5, 0, -6, 0, 7];
+
+fn expected_nonzeros() -> Vec<(u64, u64, f64)> {
+    MATRIX
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| **v != 0)
+        .map(|(i, &v)| (i as u64 / 3, i as u64 % 3, f64::from(v)))
+        .collect()
+}
+
+#[test]
+fn test_reads_v0_file_with_symbol_table_groups() {
+    let mut w = H5Writer::new(false);
+    let matrix = w.dataset(&[5, 3], Values::Int32(&MATRIX), None);
+    let names = w.dataset(
+        &[3],
+        Values::FixedString(&["ACTB", "GAPDH", "CD3E"], 8),
+        None,
+    );
+    let attrs = w.group(&[("Gene", names)]);
+    let root = w.group(&[("matrix", matrix), ("row_attrs", attrs)]);
+    let path = write_h5(w.finish(root));
+
+    let file = H5File::open(&path).unwrap();
+    assert_eq!(file.members("").unwrap(), ["matrix", "row_attrs"]);
+    assert_eq!(file.members("row_attrs").unwrap(), ["Gene"]);
+    assert!(file.members("col_attrs").unwrap().is_empty());
+    assert!(file.dataset("row_attrs/Accession").unwrap().is_none());
+
+    let matrix = file.dataset("matrix").unwrap().unwrap();
+    assert_eq!(matrix.shape(), [5, 3]);
+    assert_eq!(nonzeros(&matrix), expected_nonzeros());
+    let genes = file.dataset("/row_attrs/Gene").unwrap().unwrap();
+    assert_eq!(genes.read_strings().unwrap(), ["ACTB", "GAPDH", "CD3E"]);
+}
+
+#[test]
+fn test_reads_v2_file_with_chunked_deflated_datasets() {
+    let values = MATRIX.map(|v| v as f32 * 0.5);
+    let mut w = H5Writer::new(true);
+    let matrix = w.dataset(&[5, 3], Values::Float32(&values), Some(&[2, 2]));
+    let ids = w.dataset(&[3], Values::VarString(&["AAAC-1", "", "TTTG-1"]), None);
+    let clusters = w.dataset(&[3], Values::Int32(&[2, -1, 0]), Some(&[2]));
+    let attrs = w.group(&[("CellID", ids), ("Cluster", clusters)]);
+    let root = w.group(&[("matrix", matrix), ("col_attrs", attrs)]);
+    let path = write_h5(w.finish(root));
+
+    let file = H5File::open(&path).unwrap();
+    assert_eq!(file.members("col_attrs").unwrap(), ["CellID", "Cluster"]);
+    let matrix = file.dataset("matrix").unwrap().unwrap();
+    let expected = expected_nonzeros()
+        .into_iter()
+        .map(|(row, col, value)| (row, col, value * 0.5))
+        .collect::<Vec<_>>();
+    assert_eq!(nonzeros(&matrix), expected);
+    let ids = file.dataset("col_attrs/CellID").unwrap().unwrap();
+    assert_eq!(ids.read_strings().unwrap(), ["AAAC-1", "", "TTTG-1"]);
+    let clusters = file.dataset("col_attrs/Cluster").unwrap().unwrap();
+    assert_eq!(clusters.read_strings().unwrap(), ["2", "-1", "0"]);
+}
+
+#[test]
+fn test_rejects_non_hdf5_and_wrong_rank() {
+    let path = make_temp_dir().join("plain.loom");
+    fs::write(&path, b"%%MatrixMarket matrix coordinate integer general\n").unwrap();
+    let err = H5File::open(&path).err().unwrap().to_string();
+    assert!(err.contains("not an HDF5 file"), "{err}");
+
+    let mut w = H5Writer::new(false);
+    let genes = w.dataset(&[2], Values::FixedString(&["A", "B"], 4), None);
+    let root = w.group(&[("genes", genes)]);
+    let path = write_h5(w.finish(root));
+    let file = H5File::open(&path).unwrap();
+    let genes = file.dataset("genes").unwrap().unwrap();
+    let err = genes
+        .for_each_nonzero(|_, _, _| {})
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("expected a 2-D numeric dataset"), "{err}");
+    // A group is not a dataset.
+    let err = file.dataset("").err().unwrap().to_string();
+    assert!(err.contains("not a dataset"), "{err}");
+}
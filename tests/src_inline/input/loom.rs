@@ -0,0 +1,163 @@
+use super::*;
+use std::path::PathBuf;
+
+use crate::input::InputFormat;
+use crate::input::h5::tests::{H5Writer, Values, make_temp_dir};
+use crate::run::{CsrCounts, RunConfig, run_counts, run_pipeline};
+
+const GENES: [&str; 4] = ["ACTB", "GAPDH", "MKI67", "CD3E"];
+const CELLS: [&str; 3] = ["AAAC-1", "AAAG-1", "AAAT-1"];
+// Genes × cells.
+const COUNTS: [i32; 12] = [5, 0, 2, 0, 3, 0, 1, 0, 4, 0, 7, 1];
+
+fn write_loom(transposed: bool, new_format: bool) -> PathBuf {
+    let mut w = H5Writer::new(new_format);
+    let matrix = match transposed {
+        false => w.dataset(&[4, 3], Values::Int32(&COUNTS), Some(&[2, 2])),
+        true => {
+            let counts = (0..12)
+                .map(|i| COUNTS[(i % 4) * 3 + i / 4])
+                .collect::<Vec<_>>();
+            w.dataset(&[3, 4], Values::Int32(&counts), None)
+        }
+    };
+    let genes = w.dataset(&[4], Values::VarString(&GENES), None);
+    let row_attrs = w.group(&[("Gene", genes)]);
+    let ids = w.dataset(&[3], Values::FixedString(&CELLS, 8), None);
+    let clusters = w.dataset(&[3], Values::Int32(&[1, 0, 1]), None);
+    let samples = w.dataset(&[3], Values::VarString(&["s1", "s1", "s2"]), None);
+    let col_attrs = w.group(&[
+        ("CellID", ids),
+        ("ClusterID", clusters),
+        ("sample", samples),
+    ]);
+    let root = w.group(&[
+        ("matrix", matrix),
+        ("row_attrs", row_attrs),
+        ("col_attrs", col_attrs),
+    ]);
+    let path = make_temp_dir().join("sample.loom");
+    std::fs::write(&path, w.finish(root)).unwrap();
+    path
+}
+
+// Counts per cell as (symbol, count), sorted.
+fn cell_counts(bundle: &InputBundle, csc: &CscMatrix) -> Vec<Vec<(String, i64)>> {
+    (0..bundle.n_cells)
+        .map(|cell| {
+            let (genes, counts) = csc.column(cell);
+            let mut column = genes
+                .iter()
+                .zip(counts)
+                .map(|(&g, &c)| (bundle.gene_index.symbols_by_gene_id[g as usize].clone(), c))
+                .collect::<Vec<_>>();
+            column.sort();
+            column
+        })
+        .collect()
+}
+
+fn expected_counts() -> Vec<Vec<(String, i64)>> {
+    (0..3)
+        .map(|cell| {
+            let mut column = (0..4)
+                .filter(|&gene| COUNTS[gene * 3 + cell] != 0)
+                .map(|gene| (GENES[gene].to_string(), i64::from(COUNTS[gene * 3 + cell])))
+                .collect::<Vec<_>>();
+            column.sort();
+            column
+        })
+        .collect()
+}
+
+#[test]
+fn test_load_input_loom_reads_both_orientations() {
+    for (transposed, new_format) in [(false, false), (true, true)] {
+        let path = write_loom(transposed, new_format);
+        let (bundle, csc) = load_input_loom(&path, None, false).unwrap();
+        assert_eq!(bundle.source, InputSourceKind::Loom);
+        assert_eq!(bundle.barcodes, CELLS);
+        assert_eq!(bundle.n_features_raw, 4);
+        assert_eq!(bundle.mtx_path, path);
+        assert_eq!(cell_counts(&bundle, &csc), expected_counts());
+
+        let meta = bundle.meta.unwrap();
+        assert_eq!(meta.columns, ["ClusterID", "sample"]);
+        assert_eq!(meta.rows, [["1", "s1"], ["0", "s1"], ["1", "s2"]]);
+    }
+}
+
+#[test]
+fn test_load_input_loom_joins_meta_onto_col_attrs() {
+    let path = write_loom(false, false);
+    let meta_path = path.with_file_name("meta.tsv");
+    std::fs::write(&meta_path, "barcode\tdonor\nAAAT-1\td2\nAAAC-1\td1\n").unwrap();
+    let (bundle, _) = load_input_loom(&path, Some(&meta_path), false).unwrap();
+    let meta = bundle.meta.unwrap();
+    assert_eq!(meta.columns, ["ClusterID", "sample", "donor"]);
+    assert_eq!(meta.rows[0], ["1", "s1", "d1"]);
+    assert_eq!(meta.rows[1], ["0", "s1", ""]);
+    assert_eq!(meta.rows[2], ["1", "s2", "d2"]);
+}
+
+#[test]
+fn test_load_input_loom_rejects_mismatched_matrix() {
+    let mut w = H5Writer::new(false);
+    let matrix = w.dataset(&[2, 2], Values::Int32(&[1, 0, 0, 1]), None);
+    let genes = w.dataset(&[4], Values::VarString(&GENES), None);
+    let row_attrs = w.group(&[("Gene", genes)]);
+    let ids = w.dataset(&[3], Values::VarString(&CELLS), None);
+    let col_attrs = w.group(&[("CellID", ids)]);
+    let root = w.group(&[
+        ("matrix", matrix),
+        ("row_attrs", row_attrs),
+        ("col_attrs", col_attrs),
+    ]);
+    let path = make_temp_dir().join("bad.loom");
+    std::fs::write(&path, w.finish(root)).unwrap();
+    let err = load_input_loom(&path, None, false).unwrap_err().to_string();
+    assert!(err.contains("matrix shape [2, 2]"), "{err}");
+
+    let mut w = H5Writer::new(true);
+    let root = w.group(&[]);
+    let path = make_temp_dir().join("empty.loom");
+    std::fs::write(&path, w.finish(root)).unwrap();
+    let err = load_input_loom(&path, None, false).unwrap_err().to_string();
+    assert!(err.contains("no row_attrs/Gene"), "{err}");
+}
+
+#[test]
+fn test_loom_run_matches_in_memory_counts() {
+    let path = write_loom(true, false);
+    let mut config = RunConfig::new(&path, "");
+    config.input_format = InputFormat::Loom;
+    config.write_reports = false;
+    let loom = run_pipeline(config).unwrap();
+
+    // The same counts as cells × genes CSR.
+    let (mut indptr, mut indices, mut data) = (vec![0i64], Vec::new(), Vec::new());
+    for cell in 0..3 {
+        for gene in 0..4 {
+            if COUNTS[gene * 3 + cell] != 0 {
+                indices.push(gene as i64);
+                data.push(f64::from(COUNTS[gene * 3 + cell]));
+            }
+        }
+        indptr.push(indices.len() as i64);
+    }
+    let symbols = GENES.map(String::from);
+    let barcodes = CELLS.map(String::from);
+    let mut config = RunConfig::new("", "");
+    config.write_reports = false;
+    let counts = CsrCounts {
+        indptr: &indptr,
+        indices: &indices,
+        data: &data,
+        gene_symbols: &symbols,
+        barcodes: &barcodes,
+    };
+    let memory = run_counts(config, counts).unwrap();
+    assert_eq!(loom.barcodes, memory.barcodes);
+    assert_eq!(format!("{:?}", loom.axes), format!("{:?}", memory.axes));
+    assert_eq!(format!("{:?}", loom.scores), format!("{:?}", memory.scores));
+}
@@ -27,16 +27,101 @@ fn test_reader_minimal_bin() {
     let bin = read_organelle_bin(&path).unwrap();
     assert_eq!(bin.genes, vec!["GENEA", "GENEB", "GENEC"]);
     assert_eq!(bin.barcodes, vec!["BC1", "BC2"]);
-    assert_eq!(bin.csc.col_ptr, vec![0, 2, 3]);
-    assert_eq!(bin.csc.row_idx, vec![0, 2, 1]);
-    assert_eq!(bin.csc.values, vec![5, 1, 7]);
+    assert_eq!(bin.csc.col_ptr(), [0, 2, 3]);
+    assert_eq!(bin.csc.row_idx(), [0, 2, 1]);
+    assert_eq!(bin.csc.values(), [5, 1, 7]);
+}
+
+#[test]
+fn test_mapped_columns_match_owned_copy() {
+    let dir = make_temp_dir();
+    let path = dir.join("kira-organelle.bin");
+    fs::write(&path, build_test_bin()).unwrap();
+
+    let bin = read_organelle_bin(&path).unwrap();
+    let owned = kira_shared_sc_cache::read_shared_cache_owned(&path).unwrap();
+    assert_eq!(bin.csc.n_cells as u64, owned.n_cells);
+    assert_eq!(bin.csc.nnz as u64, owned.nnz);
+    for cell in 0..bin.csc.n_cells {
+        let start = owned.col_ptr[cell] as usize;
+        let end = owned.col_ptr[cell + 1] as usize;
+        let (rows, counts) = bin.csc.column(cell);
+        assert_eq!(rows, &owned.row_idx[start..end]);
+        assert_eq!(counts, &owned.values_u32[start..end]);
+    }
+
+    // Clones share the mapping rather than copying the arrays.
+    let clone = bin.clone();
+    assert_eq!(clone.csc.values().as_ptr(), bin.csc.values().as_ptr());
+}
+
+#[test]
+fn test_overflowing_section_offsets_are_rejected() {
+    let dir = make_temp_dir();
+    let path = dir.join("kira-organelle.bin");
+    for (field, value) in [
+        (72usize, u64::MAX - 7),
+        (32, u64::MAX / 2),
+        (24, u64::MAX),
+        (88, 1 << 40),
+    ] {
+        let mut bytes = build_test_bin();
+        bytes[field..field + 8].copy_from_slice(&value.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        match read_organelle_bin(&path) {
+            Err(InputError::InvalidInput(msg)) => {
+                assert!(msg.contains("kira-organelle.bin"), "{msg}")
+            }
+            other => panic!("header field {field}: expected InvalidInput, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_table_lengths_must_match_matrix() {
+    let dir = make_temp_dir();
+    let path = dir.join("kira-organelle.bin");
+    for (genes, barcodes) in [
+        (&["GENEA", "GENEB"][..], &["BC1", "BC2"][..]),
+        (&["GENEA", "GENEB", "GENEC"][..], &["BC1", "BC2", "BC3"][..]),
+    ] {
+        fs::write(&path, build_test_bin_from(genes, barcodes, [0, 2, 1])).unwrap();
+        match crate::input::load_input_organelle(&dir, None, &path) {
+            Err(InputError::InvalidInput(msg)) => {
+                assert!(msg.contains("kira-organelle.bin"), "{msg}")
+            }
+            other => panic!("{genes:?} x {barcodes:?}: expected InvalidInput, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_row_index_past_gene_count_is_rejected() {
+    let dir = make_temp_dir();
+    let path = dir.join("kira-organelle.bin");
+    let genes = ["GENEA", "GENEB", "GENEC"];
+    fs::write(
+        &path,
+        build_test_bin_from(&genes, &["BC1", "BC2"], [0, 3, 1]),
+    )
+    .unwrap();
+    match crate::input::load_input_organelle(&dir, None, &path) {
+        Err(InputError::InvalidInput(msg)) => {
+            assert!(msg.contains("row_idx out of bounds"), "{msg}")
+        }
+        other => panic!("expected InvalidInput, got {other:?}"),
+    }
 }
 
 fn build_test_bin() -> Vec<u8> {
-    let genes = build_string_table(&["GENEA", "GENEB", "GENEC"]);
-    let barcodes = build_string_table(&["BC1", "BC2"]);
+    build_test_bin_from(&["GENEA", "GENEB", "GENEC"], &["BC1", "BC2"], [0, 2, 1])
+}
+
+// The header describes 3 genes x 2 cells with 3 stored counts.
+fn build_test_bin_from(genes: &[&str], barcodes: &[&str], row_idx: [u32; 3]) -> Vec<u8> {
+    let genes = build_string_table(genes);
+    let barcodes = build_string_table(barcodes);
     let col_ptr = [0u64, 2, 3];
-    let row_idx = [0u32, 2, 1];
     let values = [5u32, 1, 7];
 
     let mut offset = 256usize;
@@ -5,9 +5,16 @@ use std::process::Command;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::barcodes::parse_barcodes;
-use super::features::{Feature, normalize_symbol, parse_features};
-use super::meta::load_meta;
-use super::{Species, build_gene_index, detect_prefix, detect_species, resolve_shared_bin};
+use super::features::{
+    Feature, FeatureColumns, has_bom_or_comments, normalize_symbol, parse_features,
+    parse_features_tsv,
+};
+use super::meta::{load_clusters, load_meta, load_meta_by_order, merge_meta};
+use super::mtx::read_mtx_csc;
+use super::{
+    InputSourceKind, Species, TenxOptions, build_gene_index, call_species, detect_prefix,
+    detect_species, load_gene_index, load_input_tenx, resolve_shared_bin, validate_input,
+};
 
 static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
@@ -177,6 +184,147 @@ fn test_species_detection() {
     assert_eq!(detect_species(&unknown_features), Species::Unknown);
 }
 
+fn features_from(rows: &[(&str, &str)]) -> Vec<Feature> {
+    rows.iter()
+        .map(|(id, symbol)| Feature {
+            id: id.to_string(),
+            symbol_raw: symbol.to_string(),
+            symbol_norm: normalize_symbol(symbol),
+            feature_type: None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_species_call_without_mhc_genes() {
+    // Casing alone, with the HLA/H2 genes filtered out.
+    let human = features_from(&[
+        ("g1", "GAPDH"),
+        ("g2", "ACTB"),
+        ("g3", "XIST"),
+        ("g4", "C1orf112"),
+    ]);
+    let call = call_species(&human);
+    assert_eq!(call.species, Species::Human);
+    assert_eq!(call.human_score, 0.75);
+    assert_eq!(call.mouse_score, 0.0);
+    assert_eq!(call.confidence, 1.0);
+
+    let mouse = features_from(&[
+        ("g1", "Gapdh"),
+        ("g2", "Actb"),
+        ("g3", "Xist"),
+        ("g4", "mt-Co1"),
+    ]);
+    assert_eq!(call_species(&mouse).species, Species::Mouse);
+
+    // Upper-cased symbols carry no casing signal; ids and names still do.
+    let ids = features_from(&[
+        ("ENSMUSG00000057666", "GAPDH"),
+        ("ENSMUSG00000029580", "ACTB"),
+        ("ENSMUSG00000086503", "XIST"),
+    ]);
+    assert_eq!(call_species(&ids).species, Species::Mouse);
+    let names = features_from(&[
+        ("g1", "GM12345"),
+        ("g2", "1110004F10RIK"),
+        ("g3", "RPL13-PS3"),
+        ("g4", "GAPDH"),
+    ]);
+    let call = call_species(&names);
+    assert_eq!(call.species, Species::Mouse);
+    assert_eq!(call.human_score, 0.0);
+
+    let unknown = features_from(&[("g1", "GENE1"), ("g2", "GENE2"), ("g3", "GENE3")]);
+    let call = call_species(&unknown);
+    assert_eq!(call.species, Species::Unknown);
+    assert_eq!(call.confidence, 0.0);
+}
+
+#[test]
+fn test_species_call_mixed_reference() {
+    let mut rows = Vec::new();
+    for i in 0..6 {
+        rows.push((format!("ENSG{i:011}"), format!("GRCh38_GENE{i}")));
+        rows.push((format!("ENSMUSG{i:011}"), format!("mm10___Gene{i}")));
+    }
+    let rows = rows
+        .iter()
+        .map(|(id, symbol)| (id.as_str(), symbol.as_str()))
+        .collect::<Vec<_>>();
+    let call = call_species(&features_from(&rows));
+    assert_eq!(call.species, Species::Mixed);
+    assert_eq!(call.human_score, 0.5);
+    assert_eq!(call.mouse_score, 0.5);
+    assert_eq!(call.confidence, 1.0);
+
+    // A handful of human-looking genes in a mouse reference is not a mix.
+    let mut rows = (0..20)
+        .map(|i| (format!("g{i}"), format!("Gene{i}")))
+        .collect::<Vec<_>>();
+    rows.extend((0..3).map(|i| (format!("c{i}"), format!("AC{i}0001.1"))));
+    let rows = rows
+        .iter()
+        .map(|(id, symbol)| (id.as_str(), symbol.as_str()))
+        .collect::<Vec<_>>();
+    let call = call_species(&features_from(&rows));
+    assert_eq!(call.species, Species::Mouse);
+    assert!(call.confidence > 0.7 && call.confidence < 1.0);
+}
+
+#[test]
+fn test_combined_reference_prefixes_stripped_for_panels() {
+    let mut rows = Vec::new();
+    for (i, (human, mouse)) in [
+        ("MKI67", "Mki67"),
+        ("TOP2A", "Top2a"),
+        ("PCNA", "Pcna"),
+        ("MCM2", "Mcm2"),
+        ("MT-CO1", "mt-Co1"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        rows.push((format!("ENSG{i:011}"), format!("GRCh38_{human}")));
+        rows.push((format!("ENSMUSG{i:011}"), format!("mm10___{mouse}")));
+    }
+    let rows = rows
+        .iter()
+        .map(|(id, symbol)| (id.as_str(), symbol.as_str()))
+        .collect::<Vec<_>>();
+    let features = features_from(&rows);
+    assert_eq!(call_species(&features).species, Species::Mixed);
+
+    let index = build_gene_index(&features);
+    assert_eq!(
+        index.symbols_by_gene_id,
+        ["MKI67", "TOP2A", "PCNA", "MCM2", "MT-CO1"]
+    );
+    // The mouse ortholog shares the human gene's id.
+    assert_eq!(index.gene_id_by_feature[0], index.gene_id_by_feature[1]);
+
+    let defs = crate::panels::defs::builtin_panels();
+    let (_, audits) = crate::panels::loader::load_panels(
+        Species::Mixed,
+        &index,
+        &defs,
+        crate::panels::loader::DEFAULT_MAX_RULE_GENES,
+    )
+    .unwrap();
+    let proliferation = audits
+        .iter()
+        .find(|a| a.panel_id == "proliferation_core")
+        .unwrap();
+    assert_eq!(proliferation.panel_size_mappable, 4);
+
+    // A single prefixed reference is left as written.
+    let human_only = features_from(&[("g1", "GRCh38_MKI67"), ("g2", "GRCh38_TOP2A")]);
+    assert_eq!(
+        build_gene_index(&human_only).symbols_by_gene_id,
+        ["GRCH38_MKI67", "GRCH38_TOP2A"]
+    );
+}
+
 #[test]
 fn test_metadata_join() {
     let dir = make_temp_dir();
@@ -200,6 +348,200 @@ fn test_metadata_join() {
     assert_eq!(meta.rows[2], vec!["S2".to_string(), "C2".to_string()]);
 }
 
+#[test]
+fn test_metadata_skips_bom_and_comments() {
+    let dir = make_temp_dir();
+    let meta_path = dir.join("meta.tsv");
+    write_file(
+        &meta_path,
+        "\u{feff}barcode\tsample\n# exported from Excel\nAA-1\tS1\n\n#CC-1\tS9\nCC-1\tS2\n",
+    );
+
+    let barcodes = vec!["AA-1".to_string(), "CC-1".to_string()];
+    let meta = load_meta(&meta_path, &barcodes).unwrap();
+    assert_eq!(meta.columns, vec!["sample".to_string()]);
+    assert_eq!(
+        meta.rows,
+        vec![vec!["S1".to_string()], vec!["S2".to_string()]]
+    );
+
+    write_file(
+        &meta_path,
+        "\u{feff}# cells in barcodes.tsv order\nsample\tcondition\nS1\tC1\n# dropped\nS2\tC2\n",
+    );
+    let meta = load_meta_by_order(&meta_path, 2).unwrap();
+    assert_eq!(
+        meta.columns,
+        vec!["sample".to_string(), "condition".to_string()]
+    );
+    assert_eq!(meta.rows[1], vec!["S2".to_string(), "C2".to_string()]);
+}
+
+#[test]
+fn test_metadata_commented_header() {
+    let dir = make_temp_dir();
+    let meta_path = dir.join("meta.tsv");
+    write_file(
+        &meta_path,
+        "# exported from R\n#barcode\tsample\nAA-1\tS1\n#CC-1\tS9\nCC-1\tS2\n",
+    );
+
+    let barcodes = vec!["AA-1".to_string(), "CC-1".to_string()];
+    let meta = load_meta(&meta_path, &barcodes).unwrap();
+    assert_eq!(meta.columns, vec!["sample".to_string()]);
+    assert_eq!(
+        meta.rows,
+        vec![vec!["S1".to_string()], vec!["S2".to_string()]]
+    );
+}
+
+#[test]
+fn test_metadata_join_by_order() {
+    let dir = make_temp_dir();
+    let meta_path = dir.join("meta.tsv");
+
+    write_file(
+        &meta_path,
+        "barcode\tsample\nAA-1_S1\tS1\nBB-1_S1\tS1\nCC-1_S2\tS2\n",
+    );
+
+    // Keyed join finds none of the renamed barcodes; positional join does.
+    let barcodes = vec!["AA-1".to_string(), "BB-1".to_string(), "CC-1".to_string()];
+    let keyed = load_meta(&meta_path, &barcodes).unwrap();
+    assert!(keyed.rows.iter().all(|row| row[0].is_empty()));
+
+    let meta = load_meta_by_order(&meta_path, barcodes.len()).unwrap();
+    assert_eq!(meta.columns, vec!["sample".to_string()]);
+    assert_eq!(
+        meta.rows,
+        vec![
+            vec!["S1".to_string()],
+            vec!["S1".to_string()],
+            vec!["S2".to_string()]
+        ]
+    );
+
+    let err = load_meta_by_order(&meta_path, 4).unwrap_err();
+    assert!(err.to_string().contains("3 rows"), "{err}");
+
+    // Without a barcode column every column is kept.
+    write_file(&meta_path, "sample\tcondition\nS1\tC1\n");
+    let meta = load_meta_by_order(&meta_path, 1).unwrap();
+    assert_eq!(
+        meta.columns,
+        vec!["sample".to_string(), "condition".to_string()]
+    );
+    assert_eq!(meta.rows, vec![vec!["S1".to_string(), "C1".to_string()]]);
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn test_merge_meta_two_barcode_files() {
+    let dir = make_temp_dir();
+    let clinical = dir.join("clinical.tsv");
+    let wetlab = dir.join("wetlab.tsv");
+    write_file(&clinical, "barcode\tsample\nAA-1\tS1\nBB-1\tS2\n");
+    write_file(&wetlab, "batch\tBarcode\nL2\tBB-1\nL3\tCC-1\n");
+
+    let barcodes = strings(&["AA-1", "BB-1", "CC-1"]);
+    let mut meta = load_meta(&clinical, &barcodes).unwrap();
+    merge_meta(&mut meta, &wetlab, &barcodes).unwrap();
+
+    assert_eq!(meta.columns, strings(&["sample", "batch"]));
+    assert_eq!(
+        meta.rows,
+        vec![
+            strings(&["S1", ""]),
+            strings(&["S2", "L2"]),
+            strings(&["", "L3"])
+        ]
+    );
+}
+
+#[test]
+fn test_merge_meta_sample_keyed_file() {
+    let dir = make_temp_dir();
+    let cells = dir.join("cells.tsv");
+    let samples = dir.join("samples.tsv");
+    write_file(
+        &cells,
+        "barcode\tsample\nAA-1\tS1\nBB-1\tS2\nCC-1\tS1\nDD-1\tS3\n",
+    );
+    write_file(
+        &samples,
+        "donor\tSample\tcondition\nD1\tS1\ttreated\nD2\tS2\tcontrol\n",
+    );
+
+    let barcodes = strings(&["AA-1", "BB-1", "CC-1", "DD-1", "EE-1"]);
+    let mut meta = load_meta(&cells, &barcodes).unwrap();
+    merge_meta(&mut meta, &samples, &barcodes).unwrap();
+
+    assert_eq!(meta.columns, strings(&["sample", "donor", "condition"]));
+    assert_eq!(
+        meta.rows,
+        vec![
+            strings(&["S1", "D1", "treated"]),
+            strings(&["S2", "D2", "control"]),
+            strings(&["S1", "D1", "treated"]),
+            strings(&["S3", "", ""]),
+            strings(&["", "", ""])
+        ]
+    );
+
+    // A sample-keyed file needs a sample column from an earlier file.
+    write_file(&cells, "barcode\tcluster\nAA-1\t0\n");
+    let mut meta = load_meta(&cells, &barcodes).unwrap();
+    let err = merge_meta(&mut meta, &samples, &barcodes).unwrap_err();
+    assert!(err.to_string().contains("keyed by sample"), "{err}");
+}
+
+#[test]
+fn test_merge_meta_rejects_column_collisions() {
+    let dir = make_temp_dir();
+    let first = dir.join("first.tsv");
+    let second = dir.join("second.tsv");
+    write_file(&first, "barcode\tsample\tcondition\nAA-1\tS1\tC1\n");
+    write_file(
+        &second,
+        "barcode\tCondition\tcluster\tsample\nAA-1\tC2\t0\tS1\n",
+    );
+
+    let barcodes = strings(&["AA-1"]);
+    let mut meta = load_meta(&first, &barcodes).unwrap();
+    let err = merge_meta(&mut meta, &second, &barcodes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "invalid input: --meta {}: columns already set by an earlier --meta: Condition, sample",
+            second.display()
+        )
+    );
+    assert_eq!(meta.columns, strings(&["sample", "condition"]));
+}
+
+#[test]
+fn test_load_clusters() {
+    let dir = make_temp_dir();
+    let clusters = dir.join("clusters.tsv");
+    write_file(&clusters, "\tleiden\nBB-1\t3\nAA-1\t0\n");
+
+    let barcodes = strings(&["AA-1", "BB-1", "CC-1"]);
+    assert_eq!(
+        load_clusters(&clusters, &barcodes).unwrap(),
+        strings(&["0", "3", ""])
+    );
+
+    write_file(&clusters, "barcode\nAA-1\n");
+    let err = load_clusters(&clusters, &barcodes).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("expected a barcode column and a cluster column")
+    );
+}
+
 #[test]
 fn test_barcodes_parse_order() {
     let dir = make_temp_dir();
@@ -243,3 +585,468 @@ fn test_resolve_shared_bin_filename_default() {
     let res = resolve_shared_bin(&dir).unwrap();
     assert_eq!(res.name, "kira-organelle.bin");
 }
+
+fn write_tenx_fixture(dir: &Path) {
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n%\n3 2 3\n1 1 5\n2 1 1\n3 2 2\n",
+    );
+    write_file(
+        &dir.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tGAPDH\tGene Expression\nG3\tMT-CO1\tGene Expression\n",
+    );
+    write_file(&dir.join("barcodes.tsv"), "AA-1\nBB-1\n");
+}
+
+#[test]
+fn test_validate_input_good_fixture() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    let report = validate_input(&dir, None, None, TenxOptions::default()).unwrap();
+    assert_eq!(report.source, InputSourceKind::TenX);
+    assert_eq!(report.n_cells, 2);
+    assert_eq!(report.n_features, 3);
+    assert_eq!(report.nnz, 3);
+}
+
+#[test]
+fn test_validate_input_checks_extra_meta() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    let cells = dir.join("cells.tsv");
+    let samples = dir.join("samples.tsv");
+    write_file(&cells, "barcode\tcluster\nAA-1\t0\nBB-1\t1\n");
+    write_file(&samples, "sample\tdonor\nS1\tD1\n");
+    let mut config = crate::RunConfig::new(&dir, dir.join("out"));
+    config.meta_path = Some(cells.clone());
+    config.extra_meta_paths = vec![samples];
+
+    let mut report = validate_input(&dir, Some(&cells), None, TenxOptions::default()).unwrap();
+    assert_eq!(report.barcodes, strings(&["AA-1", "BB-1"]));
+    let err = config
+        .attach_meta(&mut report.meta, &report.barcodes)
+        .unwrap_err();
+    assert!(err.to_string().contains("keyed by sample"), "{err}");
+}
+
+#[test]
+fn test_features_symbol_first_columns() {
+    let dir = make_temp_dir();
+    let path = dir.join("genes.tsv");
+    write_file(
+        &path,
+        "Actb\tENSMUSG00000029580.14\tGene Expression\nhla-a\tENSG00000206503\tGene Expression\n",
+    );
+    let columns = FeatureColumns { id: 1, symbol: 0 };
+    let features = parse_features_tsv(&path, columns).unwrap();
+    assert_eq!(features[0].id, "ENSMUSG00000029580.14");
+    assert_eq!(features[0].symbol_raw, "Actb");
+    assert_eq!(features[0].symbol_norm, "ACTB");
+    assert_eq!(features[1].symbol_norm, "HLA-A");
+    assert_eq!(features[1].feature_type.as_deref(), Some("Gene Expression"));
+
+    // The default layout would index genes by their Ensembl ids.
+    let default = parse_features_tsv(&path, FeatureColumns::default()).unwrap();
+    assert_eq!(default[0].symbol_norm, "ENSMUSG00000029580");
+
+    let short = FeatureColumns { id: 0, symbol: 3 };
+    let err = parse_features_tsv(&path, short).unwrap_err().to_string();
+    assert!(err.contains("genes.tsv:1: expected the feature id in column 0 and the symbol in column 3, found 3 columns"), "{err}");
+}
+
+#[test]
+fn test_features_single_column() {
+    let dir = make_temp_dir();
+    let path = dir.join("genes.tsv");
+    write_file(&path, "Actb\nGapdh\n");
+    for columns in [
+        FeatureColumns::default(),
+        FeatureColumns { id: 1, symbol: 0 },
+    ] {
+        let features = parse_features_tsv(&path, columns).unwrap();
+        assert_eq!(features[0].id, "Actb");
+        assert_eq!(features[0].symbol_norm, "ACTB");
+        assert_eq!(features[1].symbol_norm, "GAPDH");
+        assert_eq!(features[1].feature_type, None);
+    }
+}
+
+#[test]
+fn test_load_tenx_with_symbol_first_features() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    write_file(
+        &dir.join("features.tsv"),
+        "ACTB\tG1\tGene Expression\nGAPDH\tG2\tGene Expression\nMT-CO1\tG3\tGene Expression\n",
+    );
+    let options = TenxOptions {
+        feature_columns: FeatureColumns { id: 1, symbol: 0 },
+        ..TenxOptions::default()
+    };
+    let bundle = load_input_tenx(&dir, None, options).unwrap();
+    assert_eq!(
+        bundle.gene_index.symbols_by_gene_id,
+        ["ACTB", "GAPDH", "MT-CO1"]
+    );
+    let report = validate_input(&dir, None, None, options).unwrap();
+    assert_eq!(report.n_features, 3);
+    assert_eq!(report.nnz, 3);
+
+    write_file(&dir.join("features.tsv"), "ACTB\nGAPDH\nMT-CO1\n");
+    let bundle = load_input_tenx(&dir, None, TenxOptions::default()).unwrap();
+    assert_eq!(
+        bundle.gene_index.symbols_by_gene_id,
+        ["ACTB", "GAPDH", "MT-CO1"]
+    );
+}
+
+#[test]
+fn test_features_skip_bom_and_comments() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    write_file(
+        &dir.join("features.tsv"),
+        "\u{feff}G1\tACTB\tGene Expression\n# GRCh38\nG2\tGAPDH\tGene Expression\nG3\tMT-CO1\tGene Expression\n",
+    );
+    assert!(has_bom_or_comments(&dir.join("features.tsv")).unwrap());
+    let features =
+        parse_features_tsv(&dir.join("features.tsv"), FeatureColumns::default()).unwrap();
+    assert_eq!(features.len(), 3);
+    assert_eq!(features[0].id, "G1");
+    assert_eq!(features[1].symbol_norm, "GAPDH");
+
+    let bundle = load_input_tenx(&dir, None, TenxOptions::default()).unwrap();
+    assert_eq!(
+        bundle.gene_index.symbols_by_gene_id,
+        ["ACTB", "GAPDH", "MT-CO1"]
+    );
+    let columns = FeatureColumns { id: 1, symbol: 0 };
+    let features = parse_features_tsv(&dir.join("features.tsv"), columns).unwrap();
+    assert_eq!(features[0].id, "ACTB");
+    assert_eq!(features[0].symbol_raw, "G1");
+    let (gene_index, _) = load_gene_index(&dir, columns).unwrap();
+    assert_eq!(gene_index.symbols_by_gene_id, ["G1", "G2", "G3"]);
+}
+
+#[test]
+fn test_validate_input_missing_matrix() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    fs::remove_file(dir.join("matrix.mtx")).unwrap();
+    assert!(validate_input(&dir, None, None, TenxOptions::default()).is_err());
+}
+
+#[test]
+fn test_validate_input_dimension_mismatch() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    write_file(&dir.join("barcodes.tsv"), "AA-1\nBB-1\nCC-1\n");
+    assert!(validate_input(&dir, None, None, TenxOptions::default()).is_err());
+}
+
+fn write_transposed_fixture(dir: &Path) {
+    // Two cells as rows, three features as columns.
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n2 3 3\n1 1 5\n1 2 1\n2 3 2\n",
+    );
+    write_file(
+        &dir.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tGAPDH\tGene Expression\nG3\tMYC\tGene Expression\n",
+    );
+    write_file(&dir.join("barcodes.tsv"), "AA-1\nBB-1\n");
+}
+
+#[test]
+fn test_transposed_matrix_detected() {
+    let dir = make_temp_dir();
+    write_transposed_fixture(&dir);
+    let err = load_input_tenx(&dir, None, TenxOptions::default()).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("appears transposed"), "{msg}");
+    assert!(msg.contains("--assume-transposed"), "{msg}");
+}
+
+#[test]
+fn test_assume_transposed_reads_cells_from_rows() {
+    let dir = make_temp_dir();
+    write_transposed_fixture(&dir);
+    let bundle = load_input_tenx(
+        &dir,
+        None,
+        TenxOptions {
+            assume_transposed: true,
+            ..TenxOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(bundle.transposed);
+    assert_eq!(bundle.n_cells, 2);
+    assert_eq!(bundle.n_features_raw, 3);
+
+    let csc = read_mtx_csc(
+        &bundle.mtx_path,
+        bundle.n_features_raw,
+        bundle.n_cells,
+        &bundle.gene_index,
+        bundle.transposed,
+        false,
+        1,
+    )
+    .unwrap();
+    assert_eq!(csc.col_ptr, vec![0, 2, 3]);
+    assert_eq!(csc.column(0), (&[0, 1][..], &[5, 1][..]));
+    assert_eq!(csc.column(1), (&[2][..], &[2][..]));
+}
+
+#[test]
+fn test_dense_array_matches_sparse_equivalent() {
+    let sparse_dir = make_temp_dir();
+    write_tenx_fixture(&sparse_dir);
+    let dense_dir = make_temp_dir();
+    write_tenx_fixture(&dense_dir);
+    // Same 3x2 matrix, column-major.
+    write_file(
+        &dense_dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix array integer general\n%\n3 2\n5\n1\n0\n0\n0\n2\n",
+    );
+
+    let read = |dir: &Path| {
+        let bundle = load_input_tenx(dir, None, TenxOptions::default()).unwrap();
+        read_mtx_csc(
+            &bundle.mtx_path,
+            bundle.n_features_raw,
+            bundle.n_cells,
+            &bundle.gene_index,
+            bundle.transposed,
+            false,
+            1,
+        )
+        .unwrap()
+    };
+    let sparse = read(&sparse_dir);
+    let dense = read(&dense_dir);
+    assert_eq!(dense.n_rows, sparse.n_rows);
+    assert_eq!(dense.n_cols, sparse.n_cols);
+    assert_eq!(dense.col_ptr, sparse.col_ptr);
+    assert_eq!(dense.gene_ids, sparse.gene_ids);
+    assert_eq!(dense.counts, sparse.counts);
+    assert_eq!(dense.col_ptr, vec![0, 2, 3]);
+    assert_eq!(dense.gene_ids, vec![0, 1, 2]);
+    assert_eq!(dense.counts, vec![5, 1, 2]);
+
+    write_file(
+        &dense_dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix array integer general\n3 2\n5\n1\n0\n0\n0\n",
+    );
+    let bundle = load_input_tenx(&dense_dir, None, TenxOptions::default()).unwrap();
+    assert!(
+        read_mtx_csc(
+            &bundle.mtx_path,
+            bundle.n_features_raw,
+            bundle.n_cells,
+            &bundle.gene_index,
+            false,
+            false,
+            1,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_negative_counts_clamped_or_rejected() {
+    let coordinate = make_temp_dir();
+    write_tenx_fixture(&coordinate);
+    write_file(
+        &coordinate.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate real general\n3 2 4\n1 1 5\n2 1 -1.5\n3 2 2\n1 2 -3\n",
+    );
+    let dense = make_temp_dir();
+    write_tenx_fixture(&dense);
+    write_file(
+        &dense.join("matrix.mtx"),
+        "%%MatrixMarket matrix array real general\n3 2\n5\n-1.5\n0\n-3\n0\n2\n",
+    );
+
+    for dir in [&coordinate, &dense] {
+        let bundle = load_input_tenx(dir, None, TenxOptions::default()).unwrap();
+        for threads in [1, 3] {
+            let read = |strict_input| {
+                read_mtx_csc(
+                    &bundle.mtx_path,
+                    bundle.n_features_raw,
+                    bundle.n_cells,
+                    &bundle.gene_index,
+                    bundle.transposed,
+                    strict_input,
+                    threads,
+                )
+            };
+            let csc = read(false).unwrap();
+            assert_eq!(csc.col_ptr, vec![0, 1, 2]);
+            assert_eq!(csc.gene_ids, vec![0, 2]);
+            assert_eq!(csc.counts, vec![5, 2]);
+
+            match read(true) {
+                Err(super::InputError::InvalidInput(msg)) => {
+                    assert!(msg.contains("negative count in MTX entry"), "{msg}");
+                    assert!(msg.contains("-1.5"), "{msg}");
+                }
+                other => panic!("expected InvalidInput, got {other:?}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parallel_mtx_parse_matches_serial() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    // Every entry appears three times, spread over the file, so duplicates
+    // land in different chunks whatever the thread count.
+    let mut entries = Vec::new();
+    for round in 0..3 {
+        for cell in 1..=2 {
+            for gene in 1..=3 {
+                if (gene + cell + round) % 4 != 0 {
+                    entries.push(format!("{gene} {cell} {}", gene * 10 + cell + round));
+                }
+            }
+        }
+        entries.push("% comment between rounds".to_string());
+    }
+    let n_entries = entries.iter().filter(|e| !e.starts_with('%')).count();
+    write_file(
+        &dir.join("matrix.mtx"),
+        &format!(
+            "%%MatrixMarket matrix coordinate integer general\n3 2 {n_entries}\n{}\n",
+            entries.join("\n")
+        ),
+    );
+
+    let bundle = load_input_tenx(&dir, None, TenxOptions::default()).unwrap();
+    let read = |threads| {
+        read_mtx_csc(
+            &bundle.mtx_path,
+            bundle.n_features_raw,
+            bundle.n_cells,
+            &bundle.gene_index,
+            false,
+            false,
+            threads,
+        )
+        .unwrap()
+    };
+    let serial = read(1);
+    assert_eq!(serial.col_ptr, vec![0, 3, 6]);
+    assert_eq!(serial.counts, vec![23, 44, 65, 26, 47, 99]);
+    for threads in 2..=entries.len() + 1 {
+        let parallel = read(threads);
+        assert_eq!(parallel.col_ptr, serial.col_ptr, "threads={threads}");
+        assert_eq!(parallel.gene_ids, serial.gene_ids, "threads={threads}");
+        assert_eq!(parallel.counts, serial.counts, "threads={threads}");
+    }
+}
+
+#[test]
+fn test_serial_and_parallel_mtx_agree_on_pattern_and_nnz() {
+    let dir = make_temp_dir();
+    write_tenx_fixture(&dir);
+    let bundle = load_input_tenx(&dir, None, TenxOptions::default()).unwrap();
+    let read = |threads| {
+        read_mtx_csc(
+            &bundle.mtx_path,
+            bundle.n_features_raw,
+            bundle.n_cells,
+            &bundle.gene_index,
+            false,
+            false,
+            threads,
+        )
+    };
+
+    // `pattern` entries have no value and count as 1.
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate pattern general\n3 2 4\n1 1\n3 1\n2 2\n2 2\n",
+    );
+    for threads in [1, 2, 3] {
+        let csc = read(threads).unwrap();
+        assert_eq!(csc.col_ptr, vec![0, 2, 3], "threads={threads}");
+        assert_eq!(csc.gene_ids, vec![0, 2, 1], "threads={threads}");
+        assert_eq!(csc.counts, vec![1, 1, 2], "threads={threads}");
+    }
+
+    // One entry short of the size line fails the same way on every path.
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n3 2 4\n1 1 5\n3 1 2\n2 2 7\n",
+    );
+    for threads in [1, 2, 3] {
+        match read(threads) {
+            Err(super::InputError::Parse(msg)) => {
+                assert!(
+                    msg.ends_with("has 3 entries, its size line declares 4"),
+                    "{msg}"
+                )
+            }
+            other => panic!("expected Parse, got {other:?}"),
+        }
+    }
+
+    // Only `pattern` entries may omit the value; none may carry extra
+    // fields, and NaN is not a count.
+    for (body, bad) in [
+        ("integer general\n3 2 2\n1 1 5\n2 2\n", "2 2"),
+        ("real general\n3 2 2\n1 1 5 9\n2 2 1\n", "1 1 5 9"),
+        ("pattern general\n3 2 2\n1 1 5\n2 2\n", "1 1 5"),
+        ("real general\n3 2 2\n1 1 nan\n2 2 1\n", "1 1 nan"),
+        ("real general\n3 2 2\n1 1 5\n2 2 inf\n", "2 2 inf"),
+    ] {
+        write_file(
+            &dir.join("matrix.mtx"),
+            &format!("%%MatrixMarket matrix coordinate {body}"),
+        );
+        for threads in [1, 2] {
+            match read(threads) {
+                Err(super::InputError::Parse(msg)) => {
+                    assert_eq!(msg, format!("invalid MTX entry: {bad}"))
+                }
+                other => panic!("expected Parse for {body:?}, got {other:?}"),
+            }
+        }
+    }
+
+    write_file(
+        &dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate complex general\n3 2 1\n1 1 5 0\n",
+    );
+    assert!(
+        read(1)
+            .unwrap_err()
+            .to_string()
+            .contains("unsupported MTX field type")
+    );
+}
+
+#[test]
+fn test_load_tenx_rejects_table_length_mismatch() {
+    let extra_barcode = make_temp_dir();
+    write_tenx_fixture(&extra_barcode);
+    write_file(&extra_barcode.join("barcodes.tsv"), "AA-1\nBB-1\nCC-1\n");
+    let missing_feature = make_temp_dir();
+    write_tenx_fixture(&missing_feature);
+    write_file(
+        &missing_feature.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tGAPDH\tGene Expression\n",
+    );
+    for dir in [&extra_barcode, &missing_feature] {
+        match load_input_tenx(dir, None, TenxOptions::default()) {
+            Err(super::InputError::InvalidInput(msg)) => {
+                assert!(msg.contains("matrix is 3x2"), "{msg}")
+            }
+            other => panic!("expected InvalidInput, got {:?}", other.map(|b| b.n_cells)),
+        }
+    }
+}
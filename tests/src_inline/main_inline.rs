@@ -1,5 +1,9 @@
 use super::*;
 
+fn parse_config(args: &[String]) -> Result<RunConfig, String> {
+    parse_args(args).map(|cli| cli.config)
+}
+
 #[test]
 fn test_parse_args_default_run_mode_standalone() {
     let args = vec![
@@ -9,7 +13,7 @@ fn test_parse_args_default_run_mode_standalone() {
         "--out".to_string(),
         "out".to_string(),
     ];
-    let parsed = parse_args(&args).unwrap();
+    let parsed = parse_config(&args).unwrap();
     assert_eq!(parsed.run_mode, RunMode::Standalone);
 }
 
@@ -24,18 +28,864 @@ fn test_parse_args_pipeline_run_mode() {
         "--run-mode".to_string(),
         "pipeline".to_string(),
     ];
-    let parsed = parse_args(&args).unwrap();
+    let parsed = parse_config(&args).unwrap();
     assert_eq!(parsed.run_mode, RunMode::Pipeline);
 }
 
 #[test]
-fn test_resolve_output_dir_pipeline() {
-    let out = resolve_output_dir(Path::new("/tmp/out"), RunMode::Pipeline);
-    assert_eq!(out, PathBuf::from("/tmp/out/kira-nuclearqc"));
+fn test_parse_args_sum_mode() {
+    let args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+        "--sum-mode".to_string(),
+        "pairwise".to_string(),
+    ];
+    let parsed = parse_config(&args).unwrap();
+    assert_eq!(parsed.sum_mode, SumMode::Pairwise);
+
+    for blocked in ["blocked", "sequential"] {
+        let mut args = args.clone();
+        args[6] = blocked.to_string();
+        assert_eq!(parse_config(&args).unwrap().sum_mode, SumMode::Blocked);
+    }
+
+    let mut bad = args.clone();
+    bad[6] = "kahan".to_string();
+    assert!(parse_config(&bad).is_err());
+}
+
+#[test]
+fn test_parse_args_panel_rule_max_genes() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    let parsed = parse_config(&args).unwrap();
+    assert_eq!(parsed.panel_rule_max_genes, DEFAULT_MAX_RULE_GENES);
+
+    args.extend(["--panel-rule-max-genes".to_string(), "40".to_string()]);
+    assert_eq!(parse_config(&args).unwrap().panel_rule_max_genes, 40);
+
+    args[6] = "0".to_string();
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_threads() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    assert_eq!(parse_config(&args).unwrap().threads, 1);
+
+    args.extend(["--threads".to_string(), "8".to_string()]);
+    assert_eq!(parse_config(&args).unwrap().threads, 8);
+
+    args[6] = "0".to_string();
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_max_cells() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    assert_eq!(parse_config(&args).unwrap().max_cells, None);
+
+    args.extend(["--max-cells".to_string(), "500".to_string()]);
+    assert_eq!(parse_config(&args).unwrap().max_cells, Some(500));
+
+    let mut zero = args.clone();
+    *zero.last_mut().unwrap() = "0".to_string();
+    assert!(parse_config(&zero).is_err());
+
+    args.extend(["--chunk-cells".to_string(), "100".to_string()]);
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_winsorize_panels() {
+    let with = |value: &str| {
+        parse_config(&[
+            "run".to_string(),
+            "--input".to_string(),
+            "in".to_string(),
+            "--out".to_string(),
+            "out".to_string(),
+            "--winsorize-panels".to_string(),
+            value.to_string(),
+        ])
+    };
+    assert_eq!(with("0.99").unwrap().winsorize_panels, Some(0.99));
+    assert_eq!(with("1").unwrap().winsorize_panels, Some(1.0));
+    for bad in ["0", "1.5", "-0.1", "NaN", "p99"] {
+        assert!(with(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn test_parse_args_mito_ribo_max() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    let config = with(&[]).unwrap();
+    assert_eq!((config.mito_max, config.ribo_max), (None, None));
+    let config = with(&["--mito-max", "0.1", "--ribo-max", "0.6"]).unwrap();
+    assert_eq!(config.mito_max, Some(0.1));
+    assert_eq!(config.ribo_max, Some(0.6));
+    assert_eq!(
+        with(&["--ribo-max", "1.5"]).unwrap_err(),
+        "invalid --ribo-max (use a fraction in [0, 1])"
+    );
+    assert_eq!(
+        with(&["--mito-max"]).unwrap_err(),
+        "missing value for --mito-max"
+    );
+}
+
+#[test]
+fn test_parse_args_control_prefixes() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    assert!(with(&[]).unwrap().control_prefixes.is_empty());
+    assert_eq!(
+        with(&["--control-prefixes", "CMO, HTO-,"])
+            .unwrap()
+            .control_prefixes,
+        ["CMO", "HTO-"]
+    );
+    assert_eq!(
+        with(&["--control-prefixes"]).unwrap_err(),
+        "missing value for --control-prefixes"
+    );
+}
+
+#[test]
+fn test_parse_args_fail_on_warn() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    assert!(!with(&[]).unwrap().fail_on_warn);
+    assert!(with(&["--fail-on-warn"]).unwrap().fail_on_warn);
+}
+
+#[test]
+fn test_parse_args_entropy_unit() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().entropy_unit, EntropyUnit::Nats);
+    assert_eq!(
+        with(&["--entropy-unit", "bits"]).unwrap().entropy_unit,
+        EntropyUnit::Bits
+    );
+    assert!(with(&["--entropy-unit", "log2"]).is_err());
+    assert!(with(&["--entropy-unit"]).is_err());
+}
+
+#[test]
+fn test_parse_args_tail_thresholds() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().tail_thresholds, None);
+    assert_eq!(
+        with(&["--tail-thresholds", "0.8,0.5,0.3"])
+            .unwrap()
+            .tail_thresholds,
+        Some([0.8, 0.5, 0.3])
+    );
+    for bad in ["0.8,0.5", "0.8,0.5,0.3,0.1", "0.8,x,0.3", "1.2,0.5,0.3", ""] {
+        assert!(with(&["--tail-thresholds", bad]).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn test_parse_args_feature_columns() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(
+        with(&[]).unwrap().feature_columns,
+        FeatureColumns::default()
+    );
+    let config = with(&["--feature-symbol-col", "0", "--feature-id-col", "1"]).unwrap();
+    assert_eq!(config.feature_columns, FeatureColumns { id: 1, symbol: 0 });
+    assert!(with(&["--feature-id-col", "-1"]).is_err());
+    assert!(with(&["--feature-symbol-col"]).is_err());
+    let err = with(&["--feature-id-col", "1", "--cache", "x.kira-organelle.bin"]).unwrap_err();
+    assert!(err.contains("--cache"), "{err}");
+}
+
+#[test]
+fn test_parse_args_meta_by_order() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    let mut without_meta = args.clone();
+    without_meta.push("--meta-by-order".to_string());
+    assert!(parse_config(&without_meta).is_err());
+
+    args.extend(["--meta".to_string(), "meta.tsv".to_string()]);
+    let config = parse_config(&args).unwrap();
+    assert!(!config.meta_by_order);
+    assert_eq!(config.keyed_meta_path(), Some(Path::new("meta.tsv")));
+    assert!(config.extra_meta_paths.is_empty());
+
+    args.extend(["--meta".to_string(), "samples.tsv".to_string()]);
+    let config = parse_config(&args).unwrap();
+    assert_eq!(config.keyed_meta_path(), Some(Path::new("meta.tsv")));
+    assert_eq!(config.extra_meta_paths, vec![PathBuf::from("samples.tsv")]);
+
+    args.push("--meta-by-order".to_string());
+    let config = parse_config(&args).unwrap();
+    assert!(config.meta_by_order);
+    assert_eq!(config.keyed_meta_path(), None);
+}
+
+#[test]
+fn test_parse_args_qc_gates() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    assert_eq!(parse_config(&args).unwrap().qc_gates, QcGates::default());
+
+    args.extend([
+        "--qc-max-low-confidence".to_string(),
+        "0.35".to_string(),
+        "--qc-min-cells".to_string(),
+        "10".to_string(),
+    ]);
+    let parsed = parse_config(&args).unwrap();
+    assert_eq!(parsed.qc_gates.max_low_confidence_fraction, 0.35);
+    assert_eq!(parsed.qc_gates.min_cells, 10);
+
+    args[6] = "1.5".to_string();
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_relative_within_sample() {
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+        "--relative-within".to_string(),
+        "sample".to_string(),
+        "--relative-min-cells".to_string(),
+        "50".to_string(),
+    ];
+    let parsed = parse_config(&args).unwrap();
+    assert_eq!(parsed.relative_within, RelativeWithin::Sample);
+    assert_eq!(parsed.relative_min_group_cells, 50);
+
+    args[8] = "0".to_string();
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_strict_mappable() {
+    let mut args = ["run", "--input", "in", "--out", "out", "--strict-mappable"]
+        .map(String::from)
+        .to_vec();
+    assert!(parse_config(&args).unwrap().strict_mappable);
+    args[5] = "--strict".to_string();
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_input_format() {
+    let mut args = ["run", "--input", "a.loom", "--out", "out"]
+        .map(String::from)
+        .to_vec();
+    assert_eq!(parse_config(&args).unwrap().input_format, InputFormat::TenX);
+    args.extend(["--input-format", "loom", "--input", "b.loom"].map(String::from));
+    let cli = parse_args(&args).unwrap();
+    assert_eq!(cli.config.input_format, InputFormat::Loom);
+    let names = dataset_configs(&cli)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.out_dir)
+        .collect::<Vec<_>>();
+    assert_eq!(names, [Path::new("out/a"), Path::new("out/b")]);
+
+    for extra in ["--validate-only", "--print-panels"] {
+        let mut args = args.clone();
+        args.push(extra.to_string());
+        let err = parse_args(&args).err().unwrap();
+        assert!(err.contains(extra) && err.contains("loom"), "{err}");
+    }
+    args[6] = "h5ad".to_string();
+    assert!(parse_args(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_validate_only_without_out() {
+    let args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--validate-only".to_string(),
+    ];
+    let parsed = parse_args(&args).unwrap();
+    assert!(parsed.validate_only);
+    assert!(parse_config(&args[..3]).is_err());
+}
+
+fn write_tenx_fixture(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    std::fs::write(
+        dir.join("matrix.mtx"),
+        "%%MatrixMarket matrix coordinate integer general\n3 2 4\n1 1 5\n2 1 1\n2 2 3\n3 2 2\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tGAPDH\tGene Expression\nG3\tMT-CO1\tGene Expression\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("barcodes.tsv"), "AA-1\nBB-1\n").unwrap();
+}
+
+#[test]
+fn test_batch_runs_each_input_into_subfolder() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_batch_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_tenx_fixture(&root.join("donor_a"));
+    write_tenx_fixture(&root.join("donor_b"));
+    let out = root.join("out");
+
+    let args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        root.join("donor_a").display().to_string(),
+        "--input".to_string(),
+        root.join("donor_b").display().to_string(),
+        "--out".to_string(),
+        out.display().to_string(),
+    ];
+    let cli = parse_args(&args).unwrap();
+    let datasets = dataset_configs(&cli).unwrap();
+    assert_eq!(datasets.len(), 2);
+    for dataset in &datasets {
+        run_pipeline(dataset.clone()).unwrap();
+    }
+    assert!(out.join("donor_a").join("nuclearqc.tsv").exists());
+    assert!(out.join("donor_b").join("nuclearqc.tsv").exists());
+}
+
+#[test]
+fn test_batch_dir_isolates_failures() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_batchdir_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_tenx_fixture(&root.join("in").join("donor_a"));
+    write_tenx_fixture(&root.join("in").join("donor_b"));
+    std::fs::write(
+        root.join("in").join("donor_b").join("matrix.mtx"),
+        "garbage\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("in").join("notes")).unwrap();
+    let out = root.join("out");
+
+    let args = vec![
+        "run".to_string(),
+        "--batch".to_string(),
+        root.join("in").display().to_string(),
+        "--out".to_string(),
+        out.display().to_string(),
+    ];
+    let cli = parse_args(&args).unwrap();
+    assert_eq!(cli.input_dirs.len(), 2);
+    assert!(run_datasets(&cli).is_err());
+    assert!(out.join("donor_a").join("nuclearqc.tsv").exists());
+    let tsv = std::fs::read_to_string(out.join("batch_summary.tsv")).unwrap();
+    let rows = tsv.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].starts_with("donor_a\t"));
+    assert_eq!(rows[1].split('\t').nth(2), Some("ok"));
+    assert_eq!(rows[2].split('\t').nth(2), Some("error"));
+    assert_eq!(rows[0].split('\t').count(), rows[2].split('\t').count());
+    let json = std::fs::read_to_string(out.join("batch_summary.json")).unwrap();
+    assert!(json.contains("\"n_failed\":1"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_missing_matrix_exit_code() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_exit_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    write_tenx_fixture(&root.join("in"));
+    std::fs::remove_file(root.join("in").join("matrix.mtx")).unwrap();
+
+    let mut args = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        root.join("in").display().to_string(),
+        "--out".to_string(),
+        root.join("out").display().to_string(),
+    ];
+    let err = run_datasets(&parse_args(&args).unwrap()).unwrap_err();
+    assert_eq!(err.code, 2, "{}", err.message);
+    assert!(err.message.contains("matrix.mtx"), "{}", err.message);
+
+    args.push("--validate-only".to_string());
+    let err = run_datasets(&parse_args(&args).unwrap()).unwrap_err();
+    assert_eq!(err.code, 2, "{}", err.message);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_input_error_exit_codes() {
+    let codes = [
+        InputError::MissingInput(String::new()),
+        InputError::InvalidInput(String::new()),
+        InputError::Parse(String::new()),
+        InputError::Io(std::io::Error::other("disk")),
+    ]
+    .map(|err| CliError::from(RunError::Input(err)).code);
+    assert_eq!(codes, [2, 3, 4, 5]);
+    assert_eq!(CliError::from("bad flag".to_string()).code, 1);
+    assert_eq!(CliError::from(RunError::Config(String::new())).code, 1);
+}
+
+#[test]
+fn test_inputs_file_lists_directories() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_inputs_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let list = root.join("inputs.txt");
+    std::fs::write(&list, "# datasets\n/data/a\n\n/data/b  # second\n").unwrap();
+    assert_eq!(
+        read_inputs_file(&list).unwrap(),
+        vec![PathBuf::from("/data/a"), PathBuf::from("/data/b")]
+    );
+}
+
+#[test]
+fn test_print_panels_lists_missing_housekeeping_genes() {
+    let dir = std::env::temp_dir().join(format!(
+        "kira_nuclearqc_print_panels_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tGAPDH\tGene Expression\nG3\tMYC\tGene Expression\n",
+    )
+    .unwrap();
+
+    let tsv = panel_audit_tsv(
+        &dir,
+        None,
+        DEFAULT_MAX_RULE_GENES,
+        FeatureColumns::default(),
+    )
+    .unwrap();
+    let mut lines = tsv.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "panel_id\tpanel_size_defined\tpanel_size_mappable\tmissing_genes"
+    );
+    let housekeeping = lines
+        .find(|l| l.starts_with("housekeeping_core\t"))
+        .unwrap();
+    assert_eq!(housekeeping, "housekeeping_core\t4\t2\tRPLP0,B2M");
+}
+
+#[test]
+fn test_parse_args_panel_score() {
+    let base = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    assert_eq!(
+        parse_config(&base).unwrap().panel_score,
+        PanelScoreMode::Sum
+    );
+
+    let mut args = base.clone();
+    args.push("--panel-score".to_string());
+    args.push("mean".to_string());
+    assert_eq!(
+        parse_config(&args).unwrap().panel_score,
+        PanelScoreMode::Mean
+    );
+
+    let mut args = base;
+    args.push("--panel-score".to_string());
+    args.push("median".to_string());
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_parse_args_log_level() {
+    let base = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    assert_eq!(parse_args(&base).unwrap().log_level, LogLevel::Info);
+
+    let mut args = base.clone();
+    args.push("--log-level".to_string());
+    args.push("debug".to_string());
+    assert_eq!(parse_args(&args).unwrap().log_level, LogLevel::Debug);
+
+    let mut args = base;
+    args.push("--log-level".to_string());
+    args.push("trace".to_string());
+    assert!(parse_config(&args).is_err());
+}
+
+#[test]
+fn test_print_panels_applies_custom_panel_file() {
+    let dir = std::env::temp_dir().join(format!(
+        "kira_nuclearqc_print_panels_custom_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("features.tsv"),
+        "G1\tACTB\tGene Expression\nG2\tFOS\tGene Expression\n",
+    )
+    .unwrap();
+    let panels_path = dir.join("panels.tsv");
+    std::fs::write(
+        &panels_path,
+        "housekeeping_core\thousekeeping\tACTB:2,NOPE\ncustom_stress\tstress\tFOS\n",
+    )
+    .unwrap();
+
+    let tsv = panel_audit_tsv(
+        &dir,
+        Some(&panels_path),
+        DEFAULT_MAX_RULE_GENES,
+        FeatureColumns::default(),
+    )
+    .unwrap();
+    assert!(tsv.contains("housekeeping_core\t2\t1\tNOPE\n"));
+    assert!(tsv.ends_with("custom_stress\t1\t1\t\n"));
+}
+
+#[test]
+fn test_parse_args_normalize_follows_scoring_mode() {
+    let base = vec![
+        "run".to_string(),
+        "--input".to_string(),
+        "in".to_string(),
+        "--out".to_string(),
+        "out".to_string(),
+    ];
+    let with = |extra: &[&str]| {
+        let mut args = base.clone();
+        args.extend(extra.iter().map(|s| s.to_string()));
+        parse_config(&args)
+    };
+
+    assert!(with(&[]).unwrap().normalize);
+    assert!(!with(&["--raw"]).unwrap().normalize);
+    assert!(!with(&["--strict-nuclear"]).unwrap().normalize);
+    assert!(
+        with(&["--strict-nuclear", "--normalize"])
+            .unwrap()
+            .normalize
+    );
+    assert!(with(&["--normalize", "--raw"]).is_err());
+
+    let pearson = with(&["--strict-nuclear", "--normalize-mode", "pearson"]).unwrap();
+    assert!(pearson.normalize);
+    assert_eq!(pearson.normalize_mode, NormalizeMode::PearsonResiduals);
+    assert_eq!(pearson.pearson_theta, DEFAULT_PEARSON_THETA);
+    assert_eq!(with(&[]).unwrap().normalize_mode, NormalizeMode::LogCp10k);
+    assert!(with(&["--raw", "--normalize-mode", "pearson"]).is_err());
+    assert!(with(&["--normalize-mode", "sct"]).is_err());
+    assert!(with(&["--pearson-theta", "0"]).is_err());
+}
+
+#[test]
+fn test_parse_args_output_prefix() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().output_prefix, "");
+    assert_eq!(
+        with(&["--output-prefix", "s1."]).unwrap().output_prefix,
+        "s1."
+    );
+    assert!(with(&["--output-prefix", "a/b_"]).is_err());
+    assert!(with(&["--output-prefix"]).is_err());
+}
+
+#[test]
+fn test_parse_args_chunk_cells() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().chunk_cells, None);
+    assert_eq!(
+        with(&["--chunk-cells", "50000"]).unwrap().chunk_cells,
+        Some(50_000)
+    );
+    assert!(with(&["--chunk-cells", "0"]).is_err());
+    assert!(with(&["--chunk-cells", "100", "--cache-normalized"]).is_err());
+}
+
+#[test]
+fn test_parse_args_panel_selection() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    let parsed = with(&[]).unwrap();
+    assert_eq!(parsed.only_panels, None);
+    assert!(parsed.disable_panels.is_empty());
+    assert_eq!(
+        with(&["--disable-panels", "dna_repair_hr, dna_repair_nhej"])
+            .unwrap()
+            .disable_panels,
+        vec!["dna_repair_hr", "dna_repair_nhej"]
+    );
+    assert_eq!(
+        with(&["--only-panels", "tf_basic"]).unwrap().only_panels,
+        Some(vec!["tf_basic".to_string()])
+    );
+    assert!(with(&["--only-panels", "a", "--disable-panels", "b"]).is_err());
+}
+
+#[test]
+fn test_parse_args_seed() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().seed, DEFAULT_SEED);
+    assert_eq!(with(&["--seed", "42"]).unwrap().seed, 42);
+    assert!(with(&["--seed", "-1"]).is_err());
+}
+
+#[test]
+fn test_parse_args_profile() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().profile, NamedProfile::Immune);
+    assert_eq!(
+        with(&["--profile", "snrna"]).unwrap().profile,
+        NamedProfile::Snrna
+    );
+    let strict = with(&["--strict-nuclear"]).unwrap();
+    assert_eq!(strict.profile, NamedProfile::Default);
+    assert_eq!(
+        strict.scoring_mode(),
+        kira_nuclearqc::model::thresholds::NuclearScoringMode::StrictBulk
+    );
+    assert_eq!(
+        with(&["--profile", "tumor", "--strict-nuclear"])
+            .unwrap()
+            .profile,
+        NamedProfile::Default
+    );
+    assert!(with(&["--profile", "bulk"]).is_err());
+    assert!(with(&["--profile"]).is_err());
+}
+
+#[test]
+fn test_parse_args_composite() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert!(with(&[]).unwrap().custom_composite.is_none());
+    let composite = with(&["--composite", "0.4*tbi + 0.3*rci - 0.2*pds"])
+        .unwrap()
+        .custom_composite
+        .unwrap();
+    assert_eq!(composite.expr(), "0.4*tbi+0.3*rci-0.2*pds");
+    let err = with(&["--composite", "0.4*tbx"]).unwrap_err();
+    assert!(
+        err.contains("invalid --composite") && err.contains("tbx"),
+        "{err}"
+    );
+    assert!(with(&["--composite"]).is_err());
+}
+
+#[test]
+fn test_parse_bench_args() {
+    let with =
+        |extra: &[&str]| parse_bench_args(&extra.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    let defaults = with(&[]).unwrap();
+    assert_eq!(defaults.spec, SyntheticSpec::default());
+    assert_eq!(defaults.threads, 1);
+    assert!(defaults.emit.is_none() && defaults.out_dir.is_none());
+
+    let bench = with(&[
+        "--cells",
+        "200",
+        "--genes",
+        "3000",
+        "--nnz-per-cell",
+        "400",
+        "--seed",
+        "9",
+        "--threads",
+        "4",
+        "--emit",
+        "fixture",
+    ])
+    .unwrap();
+    assert_eq!(
+        bench.spec,
+        SyntheticSpec {
+            n_cells: 200,
+            n_genes: 3000,
+            nnz_per_cell: 400,
+            seed: 9,
+        }
+    );
+    assert_eq!(bench.threads, 4);
+    assert_eq!(bench.emit, Some(PathBuf::from("fixture")));
+
+    assert_eq!(
+        with(&["--cells", "0"]).unwrap_err(),
+        "invalid --cells (use a positive integer)"
+    );
+    assert_eq!(with(&["--seed"]).unwrap_err(), "missing value for --seed");
+    assert!(with(&["--input", "in"]).is_err());
+}
+
+#[test]
+fn test_parse_args_explain_panel() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().explain_panel, None);
+    assert_eq!(
+        with(&["--explain-panel", "stress_response"])
+            .unwrap()
+            .explain_panel
+            .as_deref(),
+        Some("stress_response")
+    );
+    assert_eq!(
+        with(&["--explain-panel"]).unwrap_err(),
+        "missing value for --explain-panel"
+    );
+}
+
+#[test]
+fn test_parse_args_contrast() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    let config = with(&[]).unwrap();
+    assert_eq!((config.contrast, config.contrast_reference), (None, None));
+    let config = with(&["--contrast", "condition", "--contrast-reference", "ctrl"]).unwrap();
+    assert_eq!(config.contrast.as_deref(), Some("condition"));
+    assert_eq!(config.contrast_reference.as_deref(), Some("ctrl"));
+    assert_eq!(
+        with(&["--contrast-reference", "ctrl"]).unwrap_err(),
+        "--contrast-reference requires --contrast"
+    );
+    assert_eq!(
+        with(&["--contrast"]).unwrap_err(),
+        "missing value for --contrast"
+    );
+}
+
+#[test]
+fn test_parse_args_clusters() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(with(&[]).unwrap().clusters_path, None);
+    let config = with(&["--mode", "cluster", "--clusters", "clusters.tsv"]).unwrap();
+    assert!(matches!(config.report_mode, ReportMode::Cluster));
+    assert_eq!(config.clusters_path, Some(PathBuf::from("clusters.tsv")));
+    assert_eq!(
+        with(&["--clusters"]).unwrap_err(),
+        "missing value for --clusters"
+    );
+    assert_eq!(
+        with(&["--mode", "clusters"]).unwrap_err(),
+        "invalid --mode (use cell|sample|cluster)"
+    );
 }
 
 #[test]
-fn test_resolve_output_dir_standalone() {
-    let out = resolve_output_dir(Path::new("/tmp/out"), RunMode::Standalone);
-    assert_eq!(out, PathBuf::from("/tmp/out"));
+fn test_parse_args_cell_stdout() {
+    let with = |extra: &[&str]| {
+        let mut args = vec!["run", "--input", "in", "--out", "out"];
+        args.extend_from_slice(extra);
+        parse_config(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    };
+    assert!(!with(&[]).unwrap().cell_stdout);
+    assert!(with(&["--cell-stdout"]).unwrap().cell_stdout);
+    assert_eq!(
+        with(&["--cell-stdout", "--mode", "sample"]).unwrap_err(),
+        "--cell-stdout cannot be combined with --mode sample"
+    );
+    assert_eq!(
+        with(&["--cell-stdout", "--mode", "cluster"]).unwrap_err(),
+        "--cell-stdout cannot be combined with --mode cluster"
+    );
+    assert_eq!(
+        with(&["--cell-stdout", "--input", "in2"]).unwrap_err(),
+        "--cell-stdout cannot be combined with multiple inputs"
+    );
 }
@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn test_parse_vm_hwm_reads_kilobytes() {
+    let status =
+        "Name:\tkira-nuclearqc\nVmPeak:\t  204800 kB\nVmHWM:\t   51200 kB\nVmRSS:\t   40960 kB\n";
+    assert_eq!(parse_vm_hwm(status), Some(51200 * 1024));
+}
+
+#[test]
+fn test_parse_vm_hwm_rejects_missing_or_malformed_lines() {
+    assert_eq!(parse_vm_hwm("Name:\tkira\nVmRSS:\t 40960 kB\n"), None);
+    assert_eq!(parse_vm_hwm("VmHWM:\t 51200\n"), None);
+    assert_eq!(parse_vm_hwm("VmHWM:\t 51200 MB\n"), None);
+    assert_eq!(parse_vm_hwm("VmHWM:\t lots kB\n"), None);
+    assert_eq!(parse_vm_hwm(""), None);
+}
+
+#[test]
+fn test_parse_vm_hwm_uses_first_match_only() {
+    let status = "VmHWM:\t 8 kB\nVmHWM:\t 16 kB\n";
+    assert_eq!(parse_vm_hwm(status), Some(8 * 1024));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_peak_rss_is_reported_on_linux() {
+    assert!(peak_rss_bytes().is_some_and(|bytes| bytes > 0));
+}
+
+#[test]
+fn test_estimate_grows_with_input() {
+    let small = estimate_peak_rss_bytes(1_000, 20_000, 1_000_000);
+    assert!(estimate_peak_rss_bytes(2_000, 20_000, 1_000_000) > small);
+    assert!(estimate_peak_rss_bytes(1_000, 20_000, 2_000_000) > small);
+    assert!(estimate_peak_rss_bytes(1_000, 40_000, 1_000_000) > small);
+}
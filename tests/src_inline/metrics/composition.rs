@@ -0,0 +1,100 @@
+use super::*;
+
+struct CountsAccessor {
+    cols: Vec<Vec<(u32, f32)>>,
+    n_genes: usize,
+}
+
+impl ExprAccessor for CountsAccessor {
+    fn n_cells(&self) -> usize {
+        self.cols.len()
+    }
+    fn n_genes(&self) -> usize {
+        self.n_genes
+    }
+    fn for_cell(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        // Deliberately transformed so tests catch use of the wrong callback.
+        for &(g, v) in &self.cols[cell] {
+            f(g, v.ln_1p());
+        }
+    }
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        for &(g, v) in &self.cols[cell] {
+            f(g, v);
+        }
+    }
+    fn libsize(&self, cell: usize) -> f64 {
+        self.cols[cell].iter().map(|&(_, v)| f64::from(v)).sum()
+    }
+    fn nnz(&self, cell: usize) -> u32 {
+        self.cols[cell].len() as u32
+    }
+}
+
+fn gene_index(symbols: &[&str]) -> GeneIndex {
+    GeneIndex {
+        gene_id_by_feature: (0..symbols.len()).map(Some).collect(),
+        symbols_by_gene_id: symbols.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_mito_symbol_prefix() {
+    assert!(is_mito_symbol("MT-CO1"));
+    assert!(is_mito_symbol(&crate::input::features::normalize_symbol(
+        "mt-Nd1"
+    )));
+    assert!(!is_mito_symbol("MTOR"));
+    assert!(!is_mito_symbol("MRPL12"));
+}
+
+#[test]
+fn test_mito_fraction_from_counts() {
+    let index = gene_index(&["MT-CO1", "ACTB", "MT-ND1"]);
+    let accessor = CountsAccessor {
+        cols: vec![vec![(0, 2.0), (1, 6.0), (2, 2.0)], vec![(1, 5.0)], vec![]],
+        n_genes: 3,
+    };
+    let frac = mito_fraction(&accessor, &index);
+    assert_eq!(frac.len(), 3);
+    assert!((frac[0] - 0.4).abs() < 1e-6);
+    assert_eq!(frac[1], 0.0);
+    assert_eq!(frac[2], 0.0);
+}
+
+#[test]
+fn test_mito_fraction_without_mito_genes() {
+    let index = gene_index(&["ACTB", "GAPDH"]);
+    let accessor = CountsAccessor {
+        cols: vec![vec![(0, 3.0), (1, 1.0)]],
+        n_genes: 2,
+    };
+    assert_eq!(mito_fraction(&accessor, &index), vec![0.0]);
+}
+
+#[test]
+fn test_ribo_symbol_prefix() {
+    assert!(is_ribo_symbol("RPL13A"));
+    assert!(is_ribo_symbol("RPS27"));
+    assert!(is_ribo_symbol(&crate::input::features::normalize_symbol(
+        "Rpl7"
+    )));
+    assert!(!is_ribo_symbol("MRPL12"));
+    assert!(!is_ribo_symbol("MRPS5"));
+    assert!(!is_ribo_symbol("RPS6KA1"));
+}
+
+#[test]
+fn test_ribo_fraction_ribo_heavy_cell() {
+    let index = gene_index(&["RPL13A", "RPS27", "ACTB", "MRPL12"]);
+    let accessor = CountsAccessor {
+        cols: vec![
+            vec![(0, 40.0), (1, 40.0), (2, 20.0)],
+            vec![(2, 9.0), (3, 1.0)],
+        ],
+        n_genes: 4,
+    };
+    let frac = ribo_fraction(&accessor, &index);
+    assert!((frac[0] - 0.8).abs() < 1e-6);
+    assert_eq!(frac[1], 0.0);
+}
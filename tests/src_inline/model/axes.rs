@@ -0,0 +1,16 @@
+use super::*;
+
+#[test]
+fn test_clip01_range() {
+    assert_eq!(clip01(-0.5), 0.0);
+    assert_eq!(clip01(0.25), 0.25);
+    assert_eq!(clip01(1.5), 1.0);
+}
+
+#[test]
+fn test_clip01_non_finite() {
+    assert_eq!(clip01(f32::NAN), 0.0);
+    assert_eq!(clip01(-f32::NAN), 0.0);
+    assert_eq!(clip01(f32::INFINITY), 1.0);
+    assert_eq!(clip01(f32::NEG_INFINITY), 0.0);
+}
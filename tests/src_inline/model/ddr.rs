@@ -42,6 +42,9 @@ fn test_drbi_rescale_boundaries() {
     assert_eq!(rescale_to_0_1(1.0), 1.0);
     assert_eq!(rescale_to_0_1(-2.0), 0.0);
     assert_eq!(rescale_to_0_1(2.0), 1.0);
+    assert_eq!(rescale_to_0_1(f32::NAN), 0.0);
+    assert_eq!(rescale_to_0_1(f32::INFINITY), 1.0);
+    assert_eq!(rescale_to_0_1(f32::NEG_INFINITY), 0.0);
 }
 
 #[test]
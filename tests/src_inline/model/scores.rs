@@ -0,0 +1,75 @@
+use super::*;
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_composite_scores_serde_json() {
+    let scores = CompositeScores {
+        nps: vec![0.5],
+        ci: vec![0.25],
+        rls: vec![0.0],
+        confidence: vec![1.0],
+        confidence_breakdown: vec![[0.5, 0.5, 0.0, 1.0]],
+    };
+    let json = serde_json::to_string(&scores).unwrap();
+    assert_eq!(
+        json,
+        "{\"nps\":[0.5],\"ci\":[0.25],\"rls\":[0.0],\"confidence\":[1.0],\
+         \"confidence_breakdown\":[[0.5,0.5,0.0,1.0]]}"
+    );
+}
+
+#[test]
+fn test_custom_composite_parse() {
+    let composite = CustomComposite::parse("0.4*tbi+0.3*rci-0.2*pds").unwrap();
+    assert_eq!(
+        composite.terms,
+        vec![(0.4, "tbi"), (0.3, "rci"), (-0.2, "pds")]
+    );
+    let composite = CustomComposite::parse(" -nsai + 2 * trci ").unwrap();
+    assert_eq!(composite.terms, vec![(-1.0, "nsai"), (2.0, "trci")]);
+    assert_eq!(composite.expr(), "-1*nsai+2*trci");
+}
+
+#[test]
+fn test_custom_composite_parse_errors() {
+    for expr in [
+        "",
+        "  ",
+        "0.4*",
+        "0.4*tbi+",
+        "0.4*tbi++rci",
+        "x*tbi",
+        "inf*tbi",
+        "0.4*TBI",
+    ] {
+        assert!(CustomComposite::parse(expr).is_err(), "{expr:?}");
+    }
+    let err = CustomComposite::parse("0.5*foo").unwrap_err();
+    assert!(err.contains("unknown axis 'foo'"), "{err}");
+}
+
+#[test]
+fn test_custom_composite_evaluate() {
+    let axes = Axes {
+        tbi: vec![0.5, 1.0, 0.0],
+        rci: vec![0.2, 1.0, 0.0],
+        pds: vec![0.3, 0.0, 1.0],
+        trs: vec![0.0; 3],
+        nsai: vec![0.0; 3],
+        iaa: vec![0.0; 3],
+        dfa: vec![0.0; 3],
+        cea: vec![0.0; 3],
+        rss: vec![0.0; 3],
+        drbi: vec![0.0; 3],
+        cci: vec![0.0; 3],
+        trci: vec![0.0; 3],
+    };
+    let composite = CustomComposite::parse("0.4*tbi+0.3*rci-0.2*pds").unwrap();
+    let values = composite.evaluate(&axes);
+    assert!((values[0] - (0.4 * 0.5 + 0.3 * 0.2 - 0.2 * 0.3)).abs() < 1e-6);
+    // Clipped to [0, 1] like the built-in composites.
+    assert!((values[1] - 0.7).abs() < 1e-6);
+    assert_eq!(values[2], 0.0);
+    let doubled = CustomComposite::parse("tbi+tbi").unwrap().evaluate(&axes);
+    assert_eq!(doubled, vec![1.0, 1.0, 0.0]);
+}
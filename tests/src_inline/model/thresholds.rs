@@ -0,0 +1,59 @@
+use super::*;
+
+const PROFILES: [NamedProfile; 4] = [
+    NamedProfile::Default,
+    NamedProfile::Immune,
+    NamedProfile::Snrna,
+    NamedProfile::Tumor,
+];
+
+#[test]
+fn test_profile_names_round_trip() {
+    for profile in PROFILES {
+        assert_eq!(NamedProfile::parse(profile.as_str()), Some(profile));
+        assert!(profile.versioned_name().starts_with(profile.as_str()));
+    }
+    assert_eq!(NamedProfile::parse("strict"), None);
+}
+
+#[test]
+fn test_profiles_keep_scoring_and_activation_modes_coherent() {
+    for profile in PROFILES {
+        let t = profile.thresholds();
+        assert!(t.validate().is_ok(), "{}", profile.as_str());
+        assert_eq!(profile.scoring_mode(), t.scoring_mode);
+        let expected = match t.scoring_mode {
+            NuclearScoringMode::StrictBulk => AxisActivationMode::Absolute,
+            NuclearScoringMode::ImmuneAware => AxisActivationMode::Hybrid,
+        };
+        assert_eq!(t.activation_mode, expected, "{}", profile.as_str());
+    }
+    assert_eq!(
+        NamedProfile::Default.scoring_mode(),
+        NuclearScoringMode::StrictBulk
+    );
+}
+
+#[test]
+fn test_snrna_profile_values() {
+    let t = ThresholdProfile::snrna_v1();
+    assert_eq!(t.min_expr_genes, 3);
+    assert_eq!(t.frac_rescale_max, 0.40);
+    assert_eq!(t.confidence_w_coverage, 0.35);
+    assert_eq!(t.confidence_w_expr_support, 0.15);
+    assert_eq!(t.confidence_w_axis_structure, 0.30);
+    assert_eq!(t.confidence_w_consistency, 0.20);
+    assert_eq!(t.cell_cycle_share_min, 0.5);
+    assert_eq!(t.scoring_mode, NuclearScoringMode::ImmuneAware);
+}
+
+#[test]
+fn test_tumor_profile_values() {
+    let t = ThresholdProfile::tumor_v1();
+    let immune = ThresholdProfile::immune_v1();
+    assert_eq!(t.cell_cycle_share_min, 0.7);
+    assert_eq!(immune.cell_cycle_share_min, 0.5);
+    assert_eq!(t.min_expr_genes, immune.min_expr_genes);
+    assert_eq!(t.confidence_weights(), immune.confidence_weights());
+    assert_eq!(t.scoring_mode, NuclearScoringMode::ImmuneAware);
+}
@@ -1,8 +1,15 @@
-use super::PanelSet;
 use super::defs::{PanelGroup, builtin_panels};
-use super::loader::load_panels;
+use super::loader::{
+    DEFAULT_MAX_RULE_GENES, MinMappable, PANEL_OVERLAP_WARN_FRACTION, audit_panels, group_overlaps,
+    load_panels, low_mappability_panels, parse_panel_file, select_panel_defs,
+};
 use super::mapping::{build_symbol_map, map_symbol};
+use super::{
+    CellCyclePhase, Panel, PanelScores, PanelSet, compute_cell_cycle_phase,
+    compute_key_panel_coverage, compute_key_panels_missing,
+};
 use crate::input::{GeneIndex, Species};
+use crate::report::QuantileMethod;
 
 fn fake_gene_index(symbols: &[&str]) -> GeneIndex {
     let symbols_by_gene_id: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
@@ -13,6 +20,27 @@ fn fake_gene_index(symbols: &[&str]) -> GeneIndex {
     }
 }
 
+// Mouse reference symbols (MGI casing) covering the built-in panels.
+const MOUSE_SYMBOLS: &[&str] = &[
+    "Actb", "Anln", "Anp32e", "Arid1a", "Arid1b", "Atad2", "Atf3", "Atm", "Atr", "Aurka", "Aurkb",
+    "B2m", "Bard1", "Bcl6", "Birc5", "Blm", "Brca1", "Brca2", "Brip1", "Bub1", "Casp8ap2", "Cbx1",
+    "Cbx3", "Cbx5", "Ccnb2", "Ccne2", "Cd69", "Cd74", "Cd83", "Cdc20", "Cdc25c", "Cdc45", "Cdc6",
+    "Cdca2", "Cdca3", "Cdca7", "Cdca8", "Cdk1", "Cdkn1a", "Cenpa", "Cenpe", "Cenpf", "Cenpu",
+    "Chaf1b", "Chek1", "Chek2", "Ckap2", "Ckap2l", "Ckap5", "Cks1b", "Cks2", "Clspn", "Ctcf",
+    "Dlgap5", "Dscc1", "Dtl", "E2f8", "Ect2", "Ehmt2", "Ep300", "Exo1", "Ezh2", "Fen1", "Fos",
+    "G2e3", "Gadd45a", "Gapdh", "Gas2l3", "Gata3", "Gins1", "Gins2", "Gmnn", "Gtse1", "H2-Aa",
+    "H2-Ab1", "H2-D1", "H2-K1", "Hells", "Hjurp", "Hmgb2", "Hmmr", "Hnrnpa1", "Hnrnpc", "Hsp90aa1",
+    "Irf4", "Jpt1", "Jun", "Kat2b", "Kdm6a", "Kif11", "Kif20b", "Kif23", "Kif2c", "Lbr", "Lig4",
+    "Malat1", "Mcm2", "Mcm3", "Mcm4", "Mcm5", "Mcm6", "Mcm7", "Mdm2", "Mki67", "Mre11", "Msh2",
+    "mt-Co1", "mt-Nd1", "Myc", "Nanog", "Nasp", "Ncapd2", "Ndc80", "Nek2", "Nhej1", "Nuf2",
+    "Nusap1", "Palb2", "Pax6", "Pcna", "Pimreg", "Pnkp", "Pola1", "Pold3", "Pou5f1", "Prim1",
+    "Prkdc", "Psrc1", "Rad17", "Rad51", "Rad51ap1", "Rad51b", "Rad51c", "Rad51d", "Rad52",
+    "Rangap1", "Rfc2", "Rpa1", "Rpa2", "Rpa3", "Rpl13a", "Rplp0", "Rrm1", "Rrm2", "Setdb1", "Slbp",
+    "Smarca4", "Smarcb1", "Smc4", "Sox2", "Sox9", "Srsf1", "Suv39h1", "Suv39h2", "Tacc3", "Tbx5",
+    "Timeless", "Tipin", "Tmpo", "Top2a", "Tpx2", "Trp53", "Ttk", "Tubb4b", "Tyms", "Ube2c",
+    "Ubr7", "Uhrf1", "Ung", "Usp1", "Wdr76", "Xist", "Xrcc4", "Xrcc5", "Xrcc6",
+];
+
 #[test]
 fn test_panel_defs_loaded() {
     let defs = builtin_panels();
@@ -40,10 +68,74 @@ fn test_species_mapping_human_vs_mouse() {
     assert_eq!(map_symbol(Species::Mouse, "HLA-DRB1", &map), Some(3));
 }
 
+#[test]
+fn test_builtin_panels_map_to_mouse() {
+    // Loaded features are upper-cased; hand-built indexes keep MGI casing.
+    let upper = MOUSE_SYMBOLS
+        .iter()
+        .map(|s| s.to_ascii_uppercase())
+        .collect::<Vec<_>>();
+    let upper = upper.iter().map(String::as_str).collect::<Vec<_>>();
+    for symbols in [MOUSE_SYMBOLS, &upper[..]] {
+        let gene_index = fake_gene_index(symbols);
+        let (_, audits) = load_panels(
+            Species::Mouse,
+            &gene_index,
+            &builtin_panels(),
+            DEFAULT_MAX_RULE_GENES,
+        )
+        .unwrap();
+        for audit in &audits {
+            let fraction = audit.panel_size_mappable as f32 / audit.panel_size_defined as f32;
+            assert!(
+                fraction >= 0.9,
+                "{}: {fraction} mappable, missing {:?}",
+                audit.panel_id,
+                audit.missing_genes
+            );
+        }
+    }
+}
+
+#[test]
+fn test_mouse_features_file_maps_human_panel_symbols() {
+    let dir = std::env::temp_dir().join(format!(
+        "kira_nuclearqc_mouse_features_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("features.tsv"),
+        "ENSMUSG00000029580\tActb\tGene Expression\n\
+         ENSMUSG00000021250\tFos\tGene Expression\n\
+         ENSMUSG00000059552\tTrp53\tGene Expression\n\
+         ENSMUSG00000031928\tMre11\tGene Expression\n\
+         ENSMUSG00000036594\tH2-Aa\tGene Expression\n",
+    )
+    .unwrap();
+    let (gene_index, species) = crate::input::load_gene_index(&dir, Default::default()).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(species, Species::Mouse);
+
+    let map = build_symbol_map(&gene_index);
+    assert_eq!(map_symbol(species, "FOS", &map), Some(1));
+    assert_eq!(map_symbol(species, "TP53", &map), Some(2));
+    assert_eq!(map_symbol(species, "MRE11", &map), Some(3));
+    assert_eq!(map_symbol(species, "HLA-DRA", &map), Some(4));
+    assert_eq!(map_symbol(Species::Human, "TP53", &map), None);
+    assert_eq!(map_symbol(Species::Mixed, "TP53", &map), Some(2));
+}
+
 #[test]
 fn test_missing_genes_reported() {
     let gene_index = fake_gene_index(&["ACTB"]);
-    let (panels, audits) = load_panels(Species::Human, &gene_index);
+    let (panels, audits) = load_panels(
+        Species::Human,
+        &gene_index,
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+    )
+    .unwrap();
     assert!(!panels.panels.is_empty());
 
     let hk = audits
@@ -54,14 +146,414 @@ fn test_missing_genes_reported() {
     assert!(hk.missing_genes.len() >= 1);
 }
 
+#[test]
+fn test_panel_file_parses_weights() {
+    let text = "# id\tgroup\tgenes\nimmune_activation\tprogram\tCD69:2,CD83, CD74:0.5\nflat\tstress\tFOS,JUN\n";
+    let defs = parse_panel_file(text).unwrap();
+    assert_eq!(defs.len(), 2);
+    assert_eq!(defs[0].id, "immune_activation");
+    assert_eq!(defs[0].group, PanelGroup::Program);
+    assert_eq!(defs[0].genes, vec!["CD69", "CD83", "CD74"]);
+    assert_eq!(defs[0].weights, vec![2.0, 1.0, 0.5]);
+    assert!(defs[0].is_weighted());
+    assert!(!defs[1].is_weighted());
+
+    assert!(parse_panel_file("p\tprogram\tCD69:-1\n").is_err());
+    assert!(parse_panel_file("p\tnot_a_group\tCD69\n").is_err());
+    assert!(parse_panel_file("p\tprogram\tCD69\np\tprogram\tCD83\n").is_err());
+}
+
+#[test]
+fn test_weights_follow_mapped_genes() {
+    let gene_index = fake_gene_index(&["CD69", "CD74"]);
+    let defs = parse_panel_file("immune_activation\tprogram\tCD69:2,CD83:3,CD74:0.5\n").unwrap();
+    let (panels, audits) =
+        load_panels(Species::Human, &gene_index, &defs, DEFAULT_MAX_RULE_GENES).unwrap();
+    let panel = &panels.panels[0];
+    assert_eq!(panel.genes, vec![0, 1]);
+    assert_eq!(panel.weights, vec![2.0, 0.5]);
+    assert_eq!(panel.weight(1), 0.5);
+    assert_eq!(audits[0].missing_genes, vec!["CD83"]);
+}
+
+#[test]
+fn test_panel_file_parses_rules() {
+    let defs = parse_panel_file("mito\tstress\tprefix:mt-, regex:^MT-(CO|ND)\\d,FOS\n").unwrap();
+    assert_eq!(defs[0].genes, vec!["FOS"]);
+    let rules = defs[0]
+        .rules
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(rules, ["prefix:MT-", "regex:^MT-(CO|ND)\\d"]);
+    assert!(defs[0].rules[0].matches("MT-CO1"));
+    assert!(!defs[0].rules[1].matches("MT-ATP6"));
+    assert!(defs[0].rules[1].matches("mt-Co1"));
+
+    let defs = parse_panel_file("hist\tprogram\tregex:^H[34]C\\d{1,2}$,FOS\n").unwrap();
+    assert_eq!(defs[0].genes, ["FOS"]);
+    assert!(defs[0].rules[0].matches("H3C12"));
+
+    assert!(parse_panel_file("rules_only\tstress\tprefix:HIST1H\n").is_ok());
+    assert!(parse_panel_file("p\tstress\tprefix:\n").is_err());
+    let err = parse_panel_file("p\tstress\tregex:(\n").unwrap_err();
+    assert!(err.to_string().contains("invalid regex"));
+}
+
+#[test]
+fn test_rules_expand_in_gene_id_order() {
+    let gene_index = fake_gene_index(&["MT-ND1", "ACTB", "MT-CO1", "HIST1H4C", "MT-ATP6"]);
+    let defs = parse_panel_file(
+        "mito\tstress\tMT-CO1:2,regex:^MT-(CO|ND),prefix:MT-\nhist\tprogram\tregex:^HIST1H\n",
+    )
+    .unwrap();
+    let (panels, audits) =
+        load_panels(Species::Human, &gene_index, &defs, DEFAULT_MAX_RULE_GENES).unwrap();
+
+    let mito = &panels.panels[0];
+    assert_eq!(mito.genes, vec![2, 0, 4]);
+    assert_eq!(mito.weights, vec![2.0, 1.0, 1.0]);
+    assert_eq!(audits[0].panel_size_defined, 3);
+    assert_eq!(audits[0].panel_size_mappable, 3);
+    assert_eq!(audits[0].rules, ["regex:^MT-(CO|ND)", "prefix:MT-"]);
+    assert_eq!(panels.panels[1].genes, vec![3]);
+    assert!(panels.panels[1].weights.is_empty());
+}
+
+#[test]
+fn test_rule_expansion_does_not_inflate_panel_size() {
+    let defs = parse_panel_file("mito\tstress\tFOS,prefix:MT-,regex:^HIST\n").unwrap();
+    for symbols in [&["FOS", "MT-ND1", "MT-CO1"][..], &["FOS"][..]] {
+        let (_, audits) = load_panels(
+            Species::Human,
+            &fake_gene_index(symbols),
+            &defs,
+            DEFAULT_MAX_RULE_GENES,
+        )
+        .unwrap();
+        assert_eq!(audits[0].panel_size_defined, 3);
+    }
+    let (panels, audits) = load_panels(
+        Species::Human,
+        &fake_gene_index(&["FOS", "MT-ND1", "MT-CO1"]),
+        &defs,
+        DEFAULT_MAX_RULE_GENES,
+    )
+    .unwrap();
+    assert_eq!(panels.panels[0].genes.len(), 3);
+    assert_eq!(audits[0].panel_size_mappable, 2);
+    assert_eq!(audits[0].missing_genes, ["regex:^HIST"]);
+}
+
+#[test]
+fn test_rule_match_cap() {
+    let gene_index = fake_gene_index(&["MT-ND1", "MT-CO1", "MT-ATP6"]);
+    let defs = parse_panel_file("mito\tstress\tprefix:MT-\n").unwrap();
+    assert!(load_panels(Species::Human, &gene_index, &defs, 3).is_ok());
+    let err = load_panels(Species::Human, &gene_index, &defs, 2).unwrap_err();
+    assert!(err.to_string().contains("rule prefix:MT- matches 3 genes"));
+}
+
+#[test]
+fn test_overlapping_custom_panels_warn() {
+    let gene_index = fake_gene_index(&["CD69", "CD83", "CD74", "FOS", "JUN", "IRF4"]);
+    let mut defs = builtin_panels();
+    defs.extend(
+        parse_panel_file(
+            "act_a\tprogram\tCD69,CD83,CD74\nact_b\tprogram\tCD69,CD83,IRF4\nsolo\tstress\tFOS,JUN\n",
+        )
+        .unwrap(),
+    );
+    let (panels, _) =
+        load_panels(Species::Human, &gene_index, &defs, DEFAULT_MAX_RULE_GENES).unwrap();
+
+    let overlaps = group_overlaps(&panels);
+    let program = overlaps
+        .iter()
+        .find(|o| o.group == PanelGroup::Program)
+        .unwrap();
+    // CD69/CD74/CD83 are already in immune_activation, IRF4 in differentiation_flux.
+    assert_eq!(program.shared, vec![0, 1, 2, 5]);
+    assert_eq!(program.n_genes, 4);
+    assert_eq!(
+        program.panel_ids,
+        vec![
+            "immune_activation",
+            "differentiation_flux",
+            "act_a",
+            "act_b"
+        ]
+    );
+    assert!(program.fraction() > PANEL_OVERLAP_WARN_FRACTION);
+    // solo shares FOS/JUN with the built-in stress_response panel.
+    assert!(overlaps.iter().any(|o| o.group == PanelGroup::Stress));
+}
+
+#[test]
+fn test_builtin_panels_have_no_group_overlap() {
+    let defs = builtin_panels();
+    let mut symbols = defs
+        .iter()
+        .flat_map(|d| d.genes.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    symbols.sort_unstable();
+    symbols.dedup();
+    let gene_index = fake_gene_index(&symbols);
+    let (panels, _) =
+        load_panels(Species::Human, &gene_index, &defs, DEFAULT_MAX_RULE_GENES).unwrap();
+    assert!(group_overlaps(&panels).is_empty());
+}
+
 #[test]
 fn test_panel_set_order_stable() {
     let gene_index = fake_gene_index(&["ACTB", "GAPDH", "RPLP0", "B2M"]);
-    let (panels_a, _) = load_panels(Species::Human, &gene_index);
-    let (panels_b, _) = load_panels(Species::Human, &gene_index);
+    let (panels_a, _) = load_panels(
+        Species::Human,
+        &gene_index,
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+    )
+    .unwrap();
+    let (panels_b, _) = load_panels(
+        Species::Human,
+        &gene_index,
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+    )
+    .unwrap();
 
     assert_eq!(panels_a.panels.len(), panels_b.panels.len());
     for (a, b) in panels_a.panels.iter().zip(panels_b.panels.iter()) {
         assert_eq!(a.id, b.id);
     }
 }
+
+fn coverage_fixture(coverage: Vec<Vec<f32>>) -> (PanelSet, PanelScores) {
+    let n_panels = coverage[0].len();
+    let panels = (0..n_panels)
+        .map(|i| Panel {
+            id: "p".to_string(),
+            name: "P".to_string(),
+            group: PanelGroup::Program,
+            genes: vec![i as u32],
+            weights: Vec::new(),
+            missing: Vec::new(),
+        })
+        .collect();
+    let zeros = vec![vec![0.0; n_panels]; coverage.len()];
+    let scores = PanelScores {
+        panel_sum: zeros.clone(),
+        panel_mean: zeros.clone(),
+        panel_raw_sum: zeros,
+        panel_detected: vec![vec![0; n_panels]; coverage.len()],
+        panel_coverage: coverage,
+    };
+    (PanelSet { panels }, scores)
+}
+
+#[test]
+fn test_key_panel_coverage_median_odd() {
+    let (set, scores) = coverage_fixture(vec![vec![0.9, 0.1, 0.5]]);
+    assert_eq!(
+        compute_key_panel_coverage(&set, &scores, QuantileMethod::Linear),
+        vec![0.5]
+    );
+}
+
+#[test]
+fn test_key_panel_coverage_median_even() {
+    // The upper-middle element (0.8) used to be reported here; the median is 0.45,
+    // which drops the confidence coverage score from 1.0 to 0.75 at the default scale.
+    let (set, scores) = coverage_fixture(vec![vec![1.0, 0.0, 0.8, 0.1], vec![0.5, 0.5, 0.5, 0.5]]);
+    let median = compute_key_panel_coverage(&set, &scores, QuantileMethod::Linear);
+    assert!((median[0] - 0.45).abs() < 1e-6);
+    assert_eq!(median[1], 0.5);
+}
+
+#[test]
+fn test_key_panel_coverage_no_panels() {
+    let set = PanelSet { panels: Vec::new() };
+    let scores = PanelScores {
+        panel_sum: vec![Vec::new()],
+        panel_mean: vec![Vec::new()],
+        panel_raw_sum: vec![Vec::new()],
+        panel_detected: vec![Vec::new()],
+        panel_coverage: vec![Vec::new()],
+    };
+    assert_eq!(
+        compute_key_panel_coverage(&set, &scores, QuantileMethod::Linear),
+        vec![0.0]
+    );
+}
+
+fn key_panel_fixture(detected: Vec<Vec<u32>>, tf_genes: Vec<u32>) -> (PanelSet, PanelScores) {
+    let panel = |id: &str, group, genes| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group,
+        genes,
+        weights: Vec::new(),
+        missing: Vec::new(),
+    };
+    let set = PanelSet {
+        panels: vec![
+            panel("housekeeping_core", PanelGroup::Housekeeping, vec![0, 1]),
+            panel("tf_basic", PanelGroup::Tf, tf_genes),
+            panel("stress_response", PanelGroup::Stress, vec![3]),
+        ],
+    };
+    let n_cells = detected.len();
+    let scores = PanelScores {
+        panel_sum: vec![vec![0.0; 3]; n_cells],
+        panel_mean: vec![vec![0.0; 3]; n_cells],
+        panel_raw_sum: vec![vec![0.0; 3]; n_cells],
+        panel_detected: detected,
+        panel_coverage: vec![vec![0.0; 3]; n_cells],
+    };
+    (set, scores)
+}
+
+fn keys(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_key_panels_missing_per_cell() {
+    // Cell 1 does not express tf_basic; the non-key stress panel is ignored.
+    let (set, scores) = key_panel_fixture(vec![vec![2, 1, 0], vec![1, 0, 1]], vec![2]);
+    let missing =
+        compute_key_panels_missing(&set, &scores, &keys(&["housekeeping_core", "tf_basic"]));
+    assert_eq!(missing, vec![false, true]);
+}
+
+#[test]
+fn test_key_panels_missing_global_gap() {
+    // tf_basic has no mappable genes, so every cell is marked.
+    let (set, scores) = key_panel_fixture(vec![vec![2, 0, 1], vec![1, 0, 1]], Vec::new());
+    let missing =
+        compute_key_panels_missing(&set, &scores, &keys(&["housekeeping_core", "tf_basic"]));
+    assert_eq!(missing, vec![true, true]);
+
+    // The same gap is irrelevant when tf_basic is not a key panel.
+    let missing = compute_key_panels_missing(&set, &scores, &keys(&["housekeeping_core"]));
+    assert_eq!(missing, vec![false, false]);
+}
+
+#[test]
+fn test_cell_cycle_phase_follows_dominant_panel() {
+    let panel = |id: &str, genes: Vec<u32>| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group: PanelGroup::Confounder,
+        genes,
+        weights: Vec::new(),
+        missing: Vec::new(),
+    };
+    let set = PanelSet {
+        panels: vec![
+            panel("cell_cycle_s", vec![0, 1, 2]),
+            panel("cell_cycle_g2m", vec![3, 4, 5, 6]),
+        ],
+    };
+    // Cells: G2M-dominated, S-dominated, a lone G2M gene, no phase signal.
+    let scores = PanelScores {
+        panel_sum: vec![
+            vec![1.0, 8.0],
+            vec![6.0, 2.0],
+            vec![0.0, 3.0],
+            vec![0.0, 0.0],
+        ],
+        panel_mean: vec![
+            vec![0.33, 2.0],
+            vec![2.0, 0.5],
+            vec![0.0, 0.75],
+            vec![0.0, 0.0],
+        ],
+        panel_raw_sum: vec![vec![0.0; 2]; 4],
+        panel_detected: vec![vec![1, 4], vec![3, 1], vec![0, 1], vec![0, 0]],
+        panel_coverage: vec![vec![0.0; 2]; 4],
+    };
+    assert_eq!(
+        compute_cell_cycle_phase(&set, &scores),
+        vec![
+            CellCyclePhase::G2M,
+            CellCyclePhase::S,
+            CellCyclePhase::G1,
+            CellCyclePhase::G1
+        ]
+    );
+
+    let without_g2m = PanelSet {
+        panels: vec![set.panels[0].clone()],
+    };
+    assert!(
+        compute_cell_cycle_phase(&without_g2m, &scores)
+            .iter()
+            .all(|&p| p == CellCyclePhase::G1)
+    );
+}
+
+#[test]
+fn test_select_panel_defs() {
+    let ids = |defs: &[crate::panels::defs::PanelDef]| {
+        defs.iter().map(|d| d.id.clone()).collect::<Vec<_>>()
+    };
+    let all = builtin_panels();
+    let only = vec!["tf_basic".to_string(), "housekeeping_core".to_string()];
+    let kept = select_panel_defs(all.clone(), Some(&only), &[]).unwrap();
+    // Definition order, not the order given.
+    assert_eq!(
+        ids(&kept),
+        ids(&all)
+            .into_iter()
+            .filter(|id| only.contains(id))
+            .collect::<Vec<_>>()
+    );
+
+    let disable = vec!["dna_repair_hr".to_string()];
+    let kept = select_panel_defs(all.clone(), None, &disable).unwrap();
+    assert_eq!(kept.len(), all.len() - 1);
+    assert!(!ids(&kept).contains(&disable[0]));
+
+    assert!(select_panel_defs(all.clone(), None, &["nope".to_string()]).is_err());
+    assert!(select_panel_defs(all, Some(&["nope".to_string()]), &[]).is_err());
+}
+
+#[test]
+fn test_min_mappable_parse() {
+    assert_eq!(MinMappable::parse("0").unwrap(), MinMappable::default());
+    let min = MinMappable::parse("0.5, program=0.25,tf=0.75,program=0.3").unwrap();
+    assert_eq!(min.global, 0.5);
+    assert_eq!(min.for_group(PanelGroup::Program), 0.3);
+    assert_eq!(min.for_group(PanelGroup::Tf), 0.75);
+    assert_eq!(min.for_group(PanelGroup::Stress), 0.5);
+    for bad in ["", "1.5", "-0.1", "nope", "bogus=0.5", "tf="] {
+        assert!(MinMappable::parse(bad).is_err(), "{bad:?}");
+    }
+}
+
+#[test]
+fn test_low_mappability_panels() {
+    let defs =
+        parse_panel_file("sparse\tprogram\tA,X1,X2,X3\nhalf\ttf\tB,C,Y1,Y2\nfull\ttf\tA,B\n")
+            .unwrap();
+    let index = fake_gene_index(&["A", "B", "C"]);
+    let audits = audit_panels(Species::Human, &index, &defs, DEFAULT_MAX_RULE_GENES).unwrap();
+    let ids = |min: &MinMappable| {
+        low_mappability_panels(&defs, &audits, min)
+            .into_iter()
+            .map(|p| p.panel_id)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(ids(&MinMappable::default()).is_empty());
+    assert_eq!(ids(&MinMappable::parse("0.5").unwrap()), ["sparse"]);
+    assert_eq!(ids(&MinMappable::parse("0.6").unwrap()), ["sparse", "half"]);
+    assert_eq!(ids(&MinMappable::parse("0.6,tf=0.5").unwrap()), ["sparse"]);
+
+    let low = low_mappability_panels(&defs, &audits, &MinMappable::parse("0.5").unwrap());
+    assert_eq!(low[0].mappable_fraction, 0.25);
+    assert_eq!(low[0].min_fraction, 0.5);
+    assert_eq!(low[0].group, PanelGroup::Program);
+    assert_eq!(low[0].missing_genes, ["X1", "X2", "X3"]);
+}
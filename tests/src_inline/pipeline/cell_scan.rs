@@ -0,0 +1,274 @@
+use super::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::input::cache::CacheCodec;
+use crate::input::load_input;
+use crate::panels::defs::builtin_panels;
+use crate::panels::loader::{DEFAULT_MAX_RULE_GENES, load_panels};
+use crate::pipeline::stage2_normalize::{
+    DEFAULT_PEARSON_THETA, NORMALIZED_EXPORT_DIR, NormalizeMode, build_expr_accessor,
+};
+use crate::pipeline::stage3_panels::run_stage3;
+
+static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn make_temp_dir() -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    let id = DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+    dir.push(format!(
+        "kira_nuclearqc_cell_scan_{}_{}",
+        std::process::id(),
+        id
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const SYMBOLS: [&str; 12] = [
+    "ACTB", "GAPDH", "SOX2", "FOS", "MKI67", "TOP2A", "PCNA", "MCM2", "RAD51", "MT-CO1", "RPL13",
+    "JUN",
+];
+
+/// 11 cells with an empty cell, and a duplicated entry summed on read.
+fn write_fixture(dir: &Path) -> InputBundle {
+    let mut feats = String::new();
+    for (i, symbol) in SYMBOLS.iter().enumerate() {
+        feats.push_str(&format!("G{}\t{}\tGene Expression\n", i + 1, symbol));
+    }
+    fs::write(dir.join("features.tsv"), feats).unwrap();
+    let barcodes = (1..=11).map(|i| format!("CELL-{i}\n")).collect::<String>();
+    fs::write(dir.join("barcodes.tsv"), barcodes).unwrap();
+
+    let mut entries = Vec::new();
+    let mut state = 0x9e37_79b9u32;
+    for cell in 1..=11 {
+        if cell == 6 {
+            continue;
+        }
+        for gene in 1..=SYMBOLS.len() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if !state.is_multiple_of(3) {
+                entries.push((gene, cell, 1 + (state >> 8) % 40));
+            }
+        }
+    }
+    entries.push((3, 2, 7));
+    let mut out = BufWriter::new(File::create(dir.join("matrix.mtx")).unwrap());
+    writeln!(out, "%%MatrixMarket matrix coordinate integer general").unwrap();
+    writeln!(out, "{} 11 {}", SYMBOLS.len(), entries.len()).unwrap();
+    for (gene, cell, value) in entries {
+        writeln!(out, "{gene} {cell} {value}").unwrap();
+    }
+    drop(out);
+    load_input(dir, None).unwrap()
+}
+
+fn bits(values: &[f32]) -> Vec<u32> {
+    values.iter().map(|v| v.to_bits()).collect()
+}
+
+fn nested_bits(values: &[Vec<f32>]) -> Vec<Vec<u32>> {
+    values.iter().map(|v| bits(v)).collect()
+}
+
+fn assert_same(a: &(Stage3Output, CellScan), b: &(Stage3Output, CellScan)) {
+    let (sa, ca) = a;
+    let (sb, cb) = b;
+    assert_eq!(
+        nested_bits(&sa.scores.panel_sum),
+        nested_bits(&sb.scores.panel_sum)
+    );
+    assert_eq!(
+        nested_bits(&sa.scores.panel_mean),
+        nested_bits(&sb.scores.panel_mean)
+    );
+    assert_eq!(
+        nested_bits(&sa.scores.panel_raw_sum),
+        nested_bits(&sb.scores.panel_raw_sum)
+    );
+    assert_eq!(sa.scores.panel_detected, sb.scores.panel_detected);
+    assert_eq!(sa.contributions, sb.contributions);
+
+    let expression = |c: &CellScan| {
+        c.stage4
+            .expression
+            .iter()
+            .map(|e| {
+                (
+                    e.expressed_genes,
+                    e.frac_norm.to_bits(),
+                    e.gene_entropy.to_bits(),
+                    e.gene_entropy_norm.to_bits(),
+                    e.min_nonzero_expr.to_bits(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(expression(ca), expression(cb));
+    let cores = |c: &CellScan| {
+        let g = &c.stage4.genome_cores;
+        [
+            bits(&g.replication_core),
+            bits(&g.ddr_core),
+            bits(&g.hr_core),
+            bits(&g.nhej_core),
+            bits(&g.sphase_core),
+            bits(&g.senescence_core),
+        ]
+    };
+    assert_eq!(cores(ca), cores(cb));
+    assert_eq!(bits(&ca.pct_mito), bits(&cb.pct_mito));
+    assert_eq!(bits(&ca.pct_ribo), bits(&cb.pct_ribo));
+    let bits64 = |v: &[f64]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+    assert_eq!(bits64(&ca.libsize), bits64(&cb.libsize));
+    assert_eq!(ca.nnz, cb.nnz);
+}
+
+#[test]
+fn test_chunked_scan_matches_in_memory_bitwise() {
+    let dir = make_temp_dir();
+    let bundle = write_fixture(&dir);
+    let thresholds = ThresholdProfile::immune_v1();
+    let panel_defs = builtin_panels();
+
+    for normalize_mode in [NormalizeMode::LogCp10k, NormalizeMode::PearsonResiduals] {
+        let params = Stage2Params {
+            normalize: true,
+            normalize_mode,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        };
+        let accessor = build_expr_accessor(&bundle, &params).unwrap();
+        let in_memory = (
+            run_stage3(
+                &bundle,
+                accessor.as_ref(),
+                &panel_defs,
+                DEFAULT_MAX_RULE_GENES,
+                Some("housekeeping_core"),
+            )
+            .unwrap(),
+            CellScan::scan(accessor.as_ref(), &bundle, &thresholds),
+        );
+        assert_eq!(in_memory.1.libsize.len(), 11);
+        assert!(in_memory.0.contributions.is_some());
+
+        let spill_dir = dir.join(CHUNK_SPILL_DIR);
+        for chunk_cells in [1, 3, 11, 64] {
+            let chunked = run_chunked(
+                &bundle,
+                &params,
+                chunk_cells,
+                &spill_dir,
+                load_panels(
+                    bundle.species,
+                    &bundle.gene_index,
+                    &panel_defs,
+                    DEFAULT_MAX_RULE_GENES,
+                )
+                .unwrap(),
+                Some("housekeeping_core"),
+                &thresholds,
+                None,
+            )
+            .unwrap();
+            assert_same(&in_memory, &chunked);
+            assert!(!spill_dir.exists());
+        }
+    }
+}
+
+#[test]
+fn test_normalized_export_round_trips_accessor_values() {
+    let dir = make_temp_dir();
+    let bundle = write_fixture(&dir);
+    let thresholds = ThresholdProfile::immune_v1();
+    let panel_defs = builtin_panels();
+    let params = Stage2Params {
+        normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: false,
+        cache_path: None,
+        cache_codec: CacheCodec::None,
+    };
+    let accessor = build_expr_accessor(&bundle, &params).unwrap();
+
+    let in_memory_dir = dir.join(NORMALIZED_EXPORT_DIR);
+    let mut export =
+        NormalizedExport::create(&in_memory_dir, &bundle.gene_index, &bundle.barcodes).unwrap();
+    export.append(accessor.as_ref()).unwrap();
+    export.finish().unwrap();
+
+    let chunked_dir = dir.join("normalized_chunked");
+    let mut export =
+        NormalizedExport::create(&chunked_dir, &bundle.gene_index, &bundle.barcodes).unwrap();
+    run_chunked(
+        &bundle,
+        &params,
+        3,
+        &dir.join(CHUNK_SPILL_DIR),
+        load_panels(
+            bundle.species,
+            &bundle.gene_index,
+            &panel_defs,
+            DEFAULT_MAX_RULE_GENES,
+        )
+        .unwrap(),
+        None,
+        &thresholds,
+        Some(&mut export),
+    )
+    .unwrap();
+    export.finish().unwrap();
+
+    let mtx = fs::read_to_string(in_memory_dir.join("matrix.mtx")).unwrap();
+    assert_eq!(
+        mtx,
+        fs::read_to_string(chunked_dir.join("matrix.mtx")).unwrap()
+    );
+    assert!(!in_memory_dir.join("matrix.mtx.body").exists());
+    let features = fs::read_to_string(in_memory_dir.join("features.tsv")).unwrap();
+    assert_eq!(
+        features.lines().count(),
+        bundle.gene_index.symbols_by_gene_id.len()
+    );
+
+    let mut lines = mtx.lines();
+    assert_eq!(
+        lines.next(),
+        Some("%%MatrixMarket matrix coordinate real general")
+    );
+    let size = lines
+        .next()
+        .unwrap()
+        .split(' ')
+        .map(|v| v.parse::<usize>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        &size[..2],
+        &[bundle.gene_index.symbols_by_gene_id.len(), 11]
+    );
+    let mut exported = vec![Vec::new(); 11];
+    for line in lines {
+        let parts = line.split(' ').collect::<Vec<_>>();
+        let gene = parts[0].parse::<u32>().unwrap() - 1;
+        let cell = parts[1].parse::<usize>().unwrap() - 1;
+        exported[cell].push((gene, parts[2].parse::<f32>().unwrap().to_bits()));
+    }
+    assert_eq!(exported.iter().map(Vec::len).sum::<usize>(), size[2]);
+    for (cell, entries) in exported.iter().enumerate() {
+        let mut expected = Vec::new();
+        accessor.for_cell(cell, &mut |gene, value| {
+            expected.push((gene, value.to_bits()))
+        });
+        assert_eq!(entries, &expected);
+    }
+}
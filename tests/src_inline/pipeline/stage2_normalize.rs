@@ -70,8 +70,11 @@ fn test_small_mtx_stats_and_values() {
 
     let params = Stage2Params {
         normalize: false,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
         cache_normalized: false,
         cache_path: None,
+        cache_codec: CacheCodec::None,
     };
     let accessor = build_expr_accessor(&bundle, &params).unwrap();
 
@@ -100,8 +103,11 @@ fn test_normalized_vs_raw() {
         &bundle,
         &Stage2Params {
             normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
             cache_normalized: false,
             cache_path: None,
+            cache_codec: CacheCodec::None,
         },
     )
     .unwrap();
@@ -110,8 +116,11 @@ fn test_normalized_vs_raw() {
         &bundle,
         &Stage2Params {
             normalize: true,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
             cache_normalized: false,
             cache_path: None,
+            cache_codec: CacheCodec::None,
         },
     )
     .unwrap();
@@ -134,8 +143,11 @@ fn test_cache_round_trip() {
     let cache_path = dir.join("cache.bin");
     let params = Stage2Params {
         normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
         cache_normalized: true,
         cache_path: Some(cache_path.clone()),
+        cache_codec: CacheCodec::None,
     };
     let accessor_a = build_expr_accessor(&bundle, &params).unwrap();
     let accessor_b = build_expr_accessor(&bundle, &params).unwrap();
@@ -156,8 +168,11 @@ fn test_determinism_bitwise() {
 
     let params = Stage2Params {
         normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
         cache_normalized: false,
         cache_path: None,
+        cache_codec: CacheCodec::None,
     };
     let a = build_expr_accessor(&bundle, &params).unwrap();
     let b = build_expr_accessor(&bundle, &params).unwrap();
@@ -170,3 +185,322 @@ fn test_determinism_bitwise() {
         assert_eq!(av, bv);
     }
 }
+
+#[test]
+fn test_counts_recovered_from_normalized_cache() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 3, 2, &[(1, 1, 1), (2, 1, 7), (3, 2, 4)]);
+
+    let params = Stage2Params {
+        normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: true,
+        cache_path: Some(dir.join("cache.bin")),
+        cache_codec: CacheCodec::None,
+    };
+    let accessor = build_expr_accessor(&bundle, &params).unwrap();
+
+    let mut counts = Vec::new();
+    accessor.for_cell_counts(0, &mut |g, v| counts.push((g, v)));
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[0].0, 0);
+    assert!((counts[0].1 - 1.0).abs() < 1e-3);
+    assert_eq!(counts[1].0, 1);
+    assert!((counts[1].1 - 7.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_cache_not_honored_after_mtx_changes() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 2, 2, &[(1, 1, 1), (2, 1, 2), (2, 2, 3)]);
+
+    let cache_path = dir.join("cache.bin");
+    let params = Stage2Params {
+        normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: true,
+        cache_path: Some(cache_path.clone()),
+        cache_codec: CacheCodec::None,
+    };
+    let cell0_counts = |accessor: &dyn ExprAccessor| {
+        let mut counts = Vec::new();
+        accessor.for_cell_counts(0, &mut |g, v| counts.push((g, v.round())));
+        counts
+    };
+
+    let first = build_expr_accessor(&bundle, &params).unwrap();
+    let cache_bytes = fs::read(&cache_path).unwrap();
+    let hit = build_expr_accessor(&bundle, &params).unwrap();
+    assert_eq!(fs::read(&cache_path).unwrap(), cache_bytes);
+    assert_eq!(cell0_counts(hit.as_ref()), cell0_counts(first.as_ref()));
+
+    // Same shape, different counts: the stale cache must be re-read and rewritten.
+    write_mtx(
+        &dir.join("matrix.mtx"),
+        2,
+        2,
+        &[(1, 1, 5), (2, 1, 2), (2, 2, 3)],
+    );
+    let miss = build_expr_accessor(&bundle, &params).unwrap();
+    assert_eq!(cell0_counts(miss.as_ref()), vec![(0, 5.0), (1, 2.0)]);
+    assert_ne!(fs::read(&cache_path).unwrap(), cache_bytes);
+
+    // Garbage must surface as a parse error, not be masked by the cache.
+    write_file(&dir.join("matrix.mtx"), "not a matrix\n");
+    assert!(build_expr_accessor(&bundle, &params).is_err());
+}
+
+#[test]
+fn test_damaged_cache_is_recomputed() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 2, 2, &[(1, 1, 1), (2, 1, 2), (2, 2, 3)]);
+
+    let cache_path = dir.join("cache.bin");
+    let params = Stage2Params {
+        normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: true,
+        cache_path: Some(cache_path.clone()),
+        cache_codec: CacheCodec::None,
+    };
+    let cell_values = |accessor: &dyn ExprAccessor| {
+        let mut values = Vec::new();
+        for cell in 0..accessor.n_cells() {
+            accessor.for_cell(cell, &mut |g, v| values.push((g, v.to_bits())));
+        }
+        values
+    };
+
+    let fresh = build_expr_accessor(&bundle, &params).unwrap();
+    let expected = cell_values(fresh.as_ref());
+    let good = fs::read(&cache_path).unwrap();
+    assert!(!dir.join("cache.bin.tmp").exists());
+
+    // Truncated mid-payload, as after a crash during a non-atomic write.
+    fs::write(&cache_path, &good[..good.len() - 6]).unwrap();
+    let recomputed = build_expr_accessor(&bundle, &params).unwrap();
+    assert_eq!(cell_values(recomputed.as_ref()), expected);
+    assert_eq!(fs::read(&cache_path).unwrap(), good);
+
+    // One flipped payload bit must fail the checksum rather than be served.
+    let mut flipped = good.clone();
+    let last = flipped.len() - 1;
+    flipped[last] ^= 0x01;
+    fs::write(&cache_path, &flipped).unwrap();
+    let recomputed = build_expr_accessor(&bundle, &params).unwrap();
+    assert_eq!(cell_values(recomputed.as_ref()), expected);
+    assert_eq!(fs::read(&cache_path).unwrap(), good);
+}
+
+#[test]
+fn test_cache_codecs_round_trip_bitwise() {
+    let dir = make_temp_dir();
+    let (rows, cols) = (60, 40);
+    let mut entries = Vec::new();
+    for c in 1..=cols {
+        for r in (1..=rows).filter(|r| (r + c) % 3 != 0) {
+            entries.push((r, c, ((r * c) % 7 + 1) as i64));
+        }
+    }
+    let bundle = setup_bundle(&dir, rows, cols, &entries);
+    let cell_values = |accessor: &dyn ExprAccessor| {
+        let mut values = Vec::new();
+        for cell in 0..accessor.n_cells() {
+            accessor.for_cell(cell, &mut |g, v| values.push((g, v.to_bits())));
+        }
+        values
+    };
+
+    let mut sizes = Vec::new();
+    let mut expected = None;
+    for codec in [CacheCodec::None, CacheCodec::Deflate] {
+        let cache_path = dir.join(format!("cache_{}.bin", codec.as_str()));
+        let params = Stage2Params {
+            normalize: true,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: true,
+            cache_path: Some(cache_path.clone()),
+            cache_codec: codec,
+        };
+        let fresh = cell_values(build_expr_accessor(&bundle, &params).unwrap().as_ref());
+        let written = fs::read(&cache_path).unwrap();
+        // Reads detect the codec from the header, whatever the caller would write.
+        let reader = Stage2Params {
+            cache_codec: CacheCodec::None,
+            ..params
+        };
+        let hit = cell_values(build_expr_accessor(&bundle, &reader).unwrap().as_ref());
+        assert_eq!(fs::read(&cache_path).unwrap(), written);
+        assert_eq!(hit, fresh);
+        assert_eq!(expected.get_or_insert(fresh), &hit);
+        sizes.push(written.len());
+    }
+    assert!(sizes[1] < sizes[0]);
+}
+
+/// v1 cache: per-cell records and no payload hash.
+fn v1_cache_bytes(meta: &CacheMeta, libsizes: &[f32], columns: &[Vec<(u32, f32)>]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for lib in libsizes {
+        payload.extend_from_slice(&lib.to_le_bytes());
+    }
+    for col in columns {
+        payload.extend_from_slice(&(col.len() as u32).to_le_bytes());
+    }
+    for (gene_id, value) in columns.iter().flatten() {
+        payload.extend_from_slice(&gene_id.to_le_bytes());
+        payload.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let mut out = b"KIRAQC2\0".to_vec();
+    out.extend_from_slice(&1u32.to_le_bytes());
+    out.extend_from_slice(&meta.scale.to_le_bytes());
+    out.extend_from_slice(&[meta.log1p as u8, 0, 0, 0]);
+    out.extend_from_slice(&meta.n_cells.to_le_bytes());
+    out.extend_from_slice(&meta.n_genes.to_le_bytes());
+    for hash in [
+        meta.hash_mtx,
+        meta.hash_features,
+        meta.hash_barcodes,
+        meta.hash_gene_index,
+    ] {
+        out.extend_from_slice(&hash.to_le_bytes());
+    }
+    out.extend_from_slice(&payload);
+    out
+}
+
+#[test]
+fn test_mapped_cache_and_legacy_layouts_match() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(
+        &dir,
+        4,
+        3,
+        &[(1, 1, 1), (3, 1, 2), (2, 2, 5), (1, 3, 4), (4, 3, 1)],
+    );
+    let cache_path = dir.join("cache.bin");
+    let params = Stage2Params {
+        normalize: true,
+        normalize_mode: NormalizeMode::LogCp10k,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: true,
+        cache_path: Some(cache_path.clone()),
+        cache_codec: CacheCodec::None,
+    };
+    let snapshot = |accessor: &dyn ExprAccessor| {
+        (0..accessor.n_cells())
+            .map(|cell| {
+                let mut col = Vec::new();
+                accessor.for_cell(cell, &mut |g, v| col.push((g, v)));
+                (accessor.libsize(cell), accessor.nnz(cell), col)
+            })
+            .collect::<Vec<_>>()
+    };
+    let bits = |cells: &[(f64, u32, Vec<(u32, f32)>)]| {
+        cells
+            .iter()
+            .map(|(lib, nnz, col)| {
+                let col = col.iter().map(|(g, v)| (*g, v.to_bits())).collect();
+                (lib.to_bits(), *nnz, col)
+            })
+            .collect::<Vec<(u64, u32, Vec<(u32, u32)>)>>()
+    };
+
+    let fresh = snapshot(build_expr_accessor(&bundle, &params).unwrap().as_ref());
+    let written = fs::read(&cache_path).unwrap();
+    assert_eq!(&written[8..12], &2u32.to_le_bytes());
+    let mapped = snapshot(build_expr_accessor(&bundle, &params).unwrap().as_ref());
+    assert_eq!(bits(&mapped), bits(&fresh));
+
+    let meta = build_cache_meta(&bundle, 10_000.0, true).unwrap();
+    let libsizes = fresh.iter().map(|c| c.0 as f32).collect::<Vec<_>>();
+    let columns = fresh.iter().map(|c| c.2.clone()).collect::<Vec<_>>();
+    let legacy = v1_cache_bytes(&meta, &libsizes, &columns);
+    fs::write(&cache_path, &legacy).unwrap();
+    let read = snapshot(build_expr_accessor(&bundle, &params).unwrap().as_ref());
+    assert_eq!(bits(&read), bits(&fresh));
+    // A legacy hit is served as-is, not rewritten.
+    assert_eq!(fs::read(&cache_path).unwrap(), legacy);
+}
+
+#[test]
+fn test_pearson_residual_ranges_vs_log() {
+    let dir = make_temp_dir();
+    // G1 dominates every library (halved in cell 2), G2 is flat, and G3 is
+    // a marker detected only in cell 1.
+    let mut entries = Vec::new();
+    for cell in 1..=6 {
+        entries.push((1, cell, if cell == 2 { 50 } else { 100 }));
+        entries.push((2, cell, 10));
+    }
+    entries.push((3, 1, 5));
+    let bundle = setup_bundle(&dir, 3, 6, &entries);
+
+    let params = |normalize_mode| Stage2Params {
+        normalize: true,
+        normalize_mode,
+        pearson_theta: DEFAULT_PEARSON_THETA,
+        cache_normalized: false,
+        cache_path: None,
+        cache_codec: CacheCodec::None,
+    };
+    let log = build_expr_accessor(&bundle, &params(NormalizeMode::LogCp10k)).unwrap();
+    let pearson = build_expr_accessor(&bundle, &params(NormalizeMode::PearsonResiduals)).unwrap();
+
+    let collect = |accessor: &dyn ExprAccessor, cell: usize| {
+        let mut values = Vec::new();
+        accessor.for_cell(cell, &mut |g, v| values.push((g, v)));
+        values
+    };
+    let clip = (6f64).sqrt() as f32;
+    let mut log_range = (f32::INFINITY, f32::NEG_INFINITY);
+    let mut pearson_range = (f32::INFINITY, f32::NEG_INFINITY);
+    for cell in 0..6 {
+        let log_values = collect(log.as_ref(), cell);
+        let pearson_values = collect(pearson.as_ref(), cell);
+        let genes = |v: &[(u32, f32)]| v.iter().map(|(g, _)| *g).collect::<Vec<_>>();
+        assert_eq!(genes(&log_values), genes(&pearson_values));
+        assert_eq!(log.libsize(cell), pearson.libsize(cell));
+        for (_, v) in &log_values {
+            log_range = (log_range.0.min(*v), log_range.1.max(*v));
+        }
+        for (_, v) in &pearson_values {
+            assert!(v.is_finite() && v.abs() <= clip);
+            pearson_range = (pearson_range.0.min(*v), pearson_range.1.max(*v));
+        }
+    }
+
+    // Log-normalized values are non-negative and led by the dominant gene;
+    // residuals are signed, and the marker hits the clip.
+    assert!(log_range.0 > 0.0 && log_range.1 <= (1.0f32 + 10_000.0).ln());
+    assert!(pearson_range.0 < 0.0);
+    assert_eq!(pearson_range.1, clip);
+    let cell0_log = collect(log.as_ref(), 0);
+    let cell0_pearson = collect(pearson.as_ref(), 0);
+    assert!(cell0_log[0].1 > cell0_log[2].1);
+    assert!(cell0_pearson[2].1 > cell0_pearson[0].1);
+
+    // G2 in cell 1: mu = 115 * 60 / 615.
+    let mu = 115.0 * 60.0 / 615.0;
+    let expected = (10.0 - mu) / (mu + mu * mu / 100.0f64).sqrt();
+    assert!((cell0_pearson[1].1 as f64 - expected).abs() < 1e-6);
+
+    let mut counts = Vec::new();
+    pearson.for_cell_counts(0, &mut |g, v| counts.push((g, v)));
+    assert_eq!(counts, vec![(0, 100.0), (1, 10.0), (2, 5.0)]);
+}
+
+#[test]
+fn test_spill_chunk_cells_raised_to_file_limit() {
+    assert_eq!(spill_chunk_cells(11, 3), 3);
+    assert_eq!(spill_chunk_cells(MAX_SPILL_CHUNKS, 1), 1);
+    // One cell per chunk would need 513 files; two cells fit in 257.
+    assert_eq!(spill_chunk_cells(MAX_SPILL_CHUNKS + 1, 1), 2);
+    let chunk_cells = spill_chunk_cells(1_000_000, 100);
+    assert!(1_000_000usize.div_ceil(chunk_cells) <= MAX_SPILL_CHUNKS);
+}
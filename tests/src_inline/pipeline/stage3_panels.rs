@@ -4,8 +4,14 @@ use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use crate::input::cache::CacheCodec;
 use crate::input::load_input;
-use crate::pipeline::stage2_normalize::{Stage2Params, build_expr_accessor};
+use crate::panels::defs::builtin_panels;
+use crate::panels::loader::DEFAULT_MAX_RULE_GENES;
+use crate::panels::{Panel, PanelGroup, PanelScoreMode};
+use crate::pipeline::stage2_normalize::{
+    DEFAULT_PEARSON_THETA, NormalizeMode, Stage2Params, build_expr_accessor,
+};
 
 static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
@@ -74,13 +80,23 @@ fn test_panel_scoring_simple() {
         &bundle,
         &Stage2Params {
             normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
             cache_normalized: false,
             cache_path: None,
+            cache_codec: CacheCodec::None,
         },
     )
     .unwrap();
 
-    let output = run_stage3(&bundle, accessor.as_ref()).unwrap();
+    let output = run_stage3(
+        &bundle,
+        accessor.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        None,
+    )
+    .unwrap();
     let panels = &output.panels.panels;
 
     let hk_idx = panels
@@ -107,16 +123,246 @@ fn test_determinism() {
         &bundle,
         &Stage2Params {
             normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
             cache_normalized: false,
             cache_path: None,
+            cache_codec: CacheCodec::None,
         },
     )
     .unwrap();
 
-    let a = run_stage3(&bundle, accessor.as_ref()).unwrap();
-    let b = run_stage3(&bundle, accessor.as_ref()).unwrap();
+    let a = run_stage3(
+        &bundle,
+        accessor.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        None,
+    )
+    .unwrap();
+    let b = run_stage3(
+        &bundle,
+        accessor.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        None,
+    )
+    .unwrap();
 
     assert_eq!(a.scores.panel_sum, b.scores.panel_sum);
     assert_eq!(a.scores.panel_detected, b.scores.panel_detected);
     assert_eq!(a.scores.panel_coverage, b.scores.panel_coverage);
 }
+
+#[test]
+fn test_raw_sum_independent_of_normalization() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 5, 2, &[(1, 1, 2), (2, 1, 1), (3, 2, 3), (4, 2, 4)]);
+
+    let raw = build_expr_accessor(
+        &bundle,
+        &Stage2Params {
+            normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        },
+    )
+    .unwrap();
+    let norm = build_expr_accessor(
+        &bundle,
+        &Stage2Params {
+            normalize: true,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        },
+    )
+    .unwrap();
+
+    let raw_out = run_stage3(
+        &bundle,
+        raw.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        None,
+    )
+    .unwrap();
+    let norm_out = run_stage3(
+        &bundle,
+        norm.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        None,
+    )
+    .unwrap();
+    let hk_idx = raw_out
+        .panels
+        .panels
+        .iter()
+        .position(|p| p.id == "housekeeping_core")
+        .unwrap();
+
+    assert_eq!(raw_out.scores.panel_raw_sum[0][hk_idx], 3.0);
+    assert_eq!(norm_out.scores.panel_raw_sum, raw_out.scores.panel_raw_sum);
+    assert_eq!(raw_out.scores.panel_sum, raw_out.scores.panel_raw_sum);
+    assert_ne!(norm_out.scores.panel_sum[0][hk_idx], 3.0);
+}
+
+#[test]
+fn test_panel_mean_normalizes_by_size() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 5, 1, &[(1, 1, 2), (2, 1, 2), (3, 1, 2)]);
+    let accessor = build_expr_accessor(
+        &bundle,
+        &Stage2Params {
+            normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        },
+    )
+    .unwrap();
+
+    let panel = |id: &str, genes| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group: PanelGroup::Program,
+        genes,
+        weights: Vec::new(),
+        missing: Vec::new(),
+    };
+    let panel_set = PanelSet {
+        panels: vec![
+            panel("small", vec![0]),
+            panel("large", vec![1, 2]),
+            panel("empty", Vec::new()),
+        ],
+    };
+    let scores = score_panels(accessor.as_ref(), &panel_set);
+
+    assert_eq!(scores.panel_sum[0], vec![2.0, 4.0, 0.0]);
+    assert_eq!(scores.panel_mean[0], vec![2.0, 2.0, 0.0]);
+    assert_eq!(scores.values(PanelScoreMode::Sum), &scores.panel_sum[..]);
+    assert_eq!(scores.values(PanelScoreMode::Mean), &scores.panel_mean[..]);
+}
+
+#[test]
+fn test_weighted_gene_contributes_proportionally() {
+    let dir = make_temp_dir();
+    let bundle = setup_bundle(&dir, 5, 1, &[(1, 1, 3), (2, 1, 3)]);
+    let accessor = build_expr_accessor(
+        &bundle,
+        &Stage2Params {
+            normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        },
+    )
+    .unwrap();
+
+    let panel = |id: &str, genes, weights| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group: PanelGroup::Program,
+        genes,
+        weights,
+        missing: Vec::new(),
+    };
+    let panel_set = PanelSet {
+        panels: vec![
+            panel("light", vec![0], vec![1.0]),
+            panel("heavy", vec![1], vec![2.0]),
+            panel("mixed", vec![0, 1], vec![1.0, 2.0]),
+        ],
+    };
+    let scores = score_panels(accessor.as_ref(), &panel_set);
+
+    assert_eq!(scores.panel_sum[0], vec![3.0, 6.0, 9.0]);
+    assert_eq!(scores.panel_sum[0][1], 2.0 * scores.panel_sum[0][0]);
+    assert_eq!(scores.panel_raw_sum[0], vec![3.0, 3.0, 6.0]);
+    assert_eq!(scores.panel_detected[0], vec![1, 1, 2]);
+    assert_eq!(scores.panel_coverage[0], vec![1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn test_explain_panel_ranks_genes_by_value() {
+    let dir = make_temp_dir();
+    // housekeeping_core maps ACTB and GAPDH here; SOX2 is in another panel.
+    let bundle = setup_bundle(
+        &dir,
+        5,
+        3,
+        &[
+            (1, 1, 2),
+            (2, 1, 5),
+            (3, 1, 9),
+            (1, 2, 7),
+            (2, 2, 1),
+            (3, 3, 4),
+        ],
+    );
+    let accessor = build_expr_accessor(
+        &bundle,
+        &Stage2Params {
+            normalize: false,
+            normalize_mode: NormalizeMode::LogCp10k,
+            pearson_theta: DEFAULT_PEARSON_THETA,
+            cache_normalized: false,
+            cache_path: None,
+            cache_codec: CacheCodec::None,
+        },
+    )
+    .unwrap();
+
+    let output = run_stage3(
+        &bundle,
+        accessor.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        Some("housekeeping_core"),
+    )
+    .unwrap();
+    let contributions = output.contributions.unwrap();
+    assert_eq!(contributions.panel_id, "housekeeping_core");
+    let symbols = contributions
+        .top_genes
+        .iter()
+        .map(|genes| {
+            genes
+                .iter()
+                .map(|&(gene_id, value)| {
+                    let symbol = &bundle.gene_index.symbols_by_gene_id[gene_id as usize];
+                    (symbol.as_str(), value)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symbols,
+        vec![
+            vec![("GAPDH", 5.0), ("ACTB", 2.0)],
+            vec![("ACTB", 7.0), ("GAPDH", 1.0)],
+            vec![],
+        ]
+    );
+
+    let output = run_stage3(
+        &bundle,
+        accessor.as_ref(),
+        &builtin_panels(),
+        DEFAULT_MAX_RULE_GENES,
+        Some("no_such_panel"),
+    )
+    .unwrap();
+    assert!(output.contributions.is_none());
+}
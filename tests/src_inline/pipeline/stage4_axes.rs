@@ -6,7 +6,7 @@ use crate::panels::{Panel, PanelScores, PanelSet};
 struct DummyAccessor {
     cols: Vec<Vec<(u32, f32)>>,
     n_genes: usize,
-    libsizes: Vec<f32>,
+    libsizes: Vec<f64>,
     nnz: Vec<u32>,
 }
 
@@ -22,7 +22,10 @@ impl ExprAccessor for DummyAccessor {
             f(g, v);
         }
     }
-    fn libsize(&self, cell: usize) -> f32 {
+    fn for_cell_counts(&self, cell: usize, f: &mut dyn FnMut(u32, f32)) {
+        self.for_cell(cell, f);
+    }
+    fn libsize(&self, cell: usize) -> f64 {
         self.libsizes[cell]
     }
     fn nnz(&self, cell: usize) -> u32 {
@@ -30,48 +33,75 @@ impl ExprAccessor for DummyAccessor {
     }
 }
 
+fn run_stage4_on(
+    accessor: &dyn ExprAccessor,
+    gene_index: &GeneIndex,
+    species: Species,
+    panel_set: &PanelSet,
+    panel_scores: &PanelScores,
+    thresholds: &ThresholdProfile,
+    covariates: &Stage4Covariates,
+) -> Stage4Output {
+    run_stage4(
+        Stage4Cells::scan(accessor, gene_index, species, thresholds),
+        gene_index,
+        species,
+        panel_set,
+        panel_scores,
+        thresholds,
+        covariates,
+        1,
+    )
+}
+
 fn simple_panel_set() -> PanelSet {
     let panels = vec![
         Panel {
-            id: "p1",
-            name: "P1",
+            id: "p1".to_string(),
+            name: "P1".to_string(),
             group: PanelGroup::Program,
             genes: vec![0, 1],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
         Panel {
-            id: "p2",
-            name: "P2",
+            id: "p2".to_string(),
+            name: "P2".to_string(),
             group: PanelGroup::Program,
             genes: vec![2],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
         Panel {
-            id: "tf1",
-            name: "TF",
+            id: "tf1".to_string(),
+            name: "TF".to_string(),
             group: PanelGroup::Tf,
             genes: vec![0],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
         Panel {
-            id: "ch1",
-            name: "CH",
+            id: "ch1".to_string(),
+            name: "CH".to_string(),
             group: PanelGroup::Chromatin,
             genes: vec![1],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
         Panel {
-            id: "stress",
-            name: "Stress",
+            id: "stress".to_string(),
+            name: "Stress".to_string(),
             group: PanelGroup::Stress,
             genes: vec![2],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
         Panel {
-            id: "dev",
-            name: "Dev",
+            id: "dev".to_string(),
+            name: "Dev".to_string(),
             group: PanelGroup::Developmental,
             genes: vec![1],
+            weights: Vec::new(),
             missing: Vec::new(),
         },
     ];
@@ -91,6 +121,14 @@ fn simple_scores() -> PanelScores {
             vec![3.0, 1.0, 2.0, 1.0, 1.0, 0.5],
             vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0],
         ],
+        panel_mean: vec![
+            vec![3.0, 1.0, 2.0, 1.0, 1.0, 0.5],
+            vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+        ],
+        panel_raw_sum: vec![
+            vec![3.0, 1.0, 2.0, 1.0, 1.0, 0.5],
+            vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+        ],
         panel_detected: vec![vec![2, 1, 1, 1, 1, 1], vec![1, 1, 0, 0, 0, 0]],
         panel_coverage: vec![
             vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
@@ -102,7 +140,7 @@ fn simple_scores() -> PanelScores {
 #[test]
 fn test_entropy_uniform() {
     let values = vec![1.0f32, 1.0, 1.0, 1.0];
-    let (_h, h_norm) = entropy_norm_from_values(&values);
+    let (_h, h_norm) = entropy_norm_from_values(&values, SumMode::Blocked);
     assert!((h_norm - 1.0).abs() < 1e-6);
 }
 
@@ -121,13 +159,14 @@ fn test_tbi_extremes() {
     thresholds.frac_rescale_min = 0.0;
     thresholds.frac_rescale_max = 1.0;
 
-    let out = run_stage4(
+    let out = run_stage4_on(
         &accessor,
         &simple_gene_index(),
         Species::Human,
         &panel_set,
         &panel_scores,
         &thresholds,
+        &Stage4Covariates::default(),
     );
     assert!(out.axes.tbi[0] >= 0.0 && out.axes.tbi[0] <= 1.0);
 }
@@ -143,13 +182,14 @@ fn test_pds_dominance() {
         nnz: vec![3],
     };
     let thresholds = ThresholdProfile::default_v1();
-    let out = run_stage4(
+    let out = run_stage4_on(
         &accessor,
         &simple_gene_index(),
         Species::Human,
         &panel_set,
         &panel_scores,
         &thresholds,
+        &Stage4Covariates::default(),
     );
     assert!(out.axes.pds[0] > 0.0);
 }
@@ -168,18 +208,74 @@ fn test_rci_guard() {
     };
     let mut thresholds = ThresholdProfile::default_v1();
     thresholds.tf_min_sum = 5.0;
-    let out = run_stage4(
+    let out = run_stage4_on(
         &accessor,
         &simple_gene_index(),
         Species::Human,
         &panel_set,
         &panel_scores,
         &thresholds,
+        &Stage4Covariates::default(),
     );
     assert_eq!(out.axes.rci[0], 0.0);
     assert!(out.flags[0].low_tf_signal);
 }
 
+#[test]
+fn test_pearson_residual_shares_stay_in_unit_range() {
+    use crate::pipeline::stage2_normalize::{DEFAULT_PEARSON_THETA, PearsonResidualAccessor};
+    use crate::pipeline::stage3_panels::score_panels;
+
+    // Cell 0 is ATR-heavy: its negative CHEK1 residual leaves the TF and
+    // chromatin panels summing below their maximum. In the other cells the
+    // negative ATR residual does the same to the program panels.
+    let mut cols = vec![vec![(0, 30.0), (1, 8.0), (2, 9.0)]];
+    cols.extend((0..5).map(|_| vec![(0, 10.0), (1, 10.0), (2, 10.0)]));
+    let counts = DummyAccessor {
+        libsizes: cols
+            .iter()
+            .map(|c| c.iter().map(|&(_, v)| v as f64).sum())
+            .collect(),
+        nnz: vec![3; cols.len()],
+        cols,
+        n_genes: 3,
+    };
+    let accessor = PearsonResidualAccessor::new(Box::new(counts), DEFAULT_PEARSON_THETA);
+    let panel_set = simple_panel_set();
+    let panel_scores = score_panels(&accessor, &panel_set);
+    assert!(panel_scores.panel_mean[0].iter().any(|&v| v < 0.0));
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    thresholds.tf_min_sum = 0.0;
+    thresholds.program_min_sum = 0.0;
+    let out = run_stage4_on(
+        &accessor,
+        &simple_gene_index(),
+        Species::Human,
+        &panel_set,
+        &panel_scores,
+        &thresholds,
+        &Stage4Covariates::default(),
+    );
+    let unit = |v: f32| (0.0..=1.0).contains(&v);
+    for cell in 0..accessor.n_cells() {
+        let drivers = &out.drivers[cell];
+        assert!(unit(out.axes.rci[cell]), "rci {}", out.axes.rci[cell]);
+        assert!(unit(out.axes.pds[cell]), "pds {}", out.axes.pds[cell]);
+        assert!(unit(out.axes.tbi[cell]), "tbi {}", out.axes.tbi[cell]);
+        assert!(
+            unit(drivers.tf_entropy),
+            "tf_entropy {}",
+            drivers.tf_entropy
+        );
+        assert!(
+            unit(drivers.max_program_share),
+            "{}",
+            drivers.max_program_share
+        );
+    }
+}
+
 #[test]
 fn test_determinism() {
     let panel_set = simple_panel_set();
@@ -191,21 +287,23 @@ fn test_determinism() {
         nnz: vec![3],
     };
     let thresholds = ThresholdProfile::default_v1();
-    let a = run_stage4(
+    let a = run_stage4_on(
         &accessor,
         &simple_gene_index(),
         Species::Human,
         &panel_set,
         &panel_scores,
         &thresholds,
+        &Stage4Covariates::default(),
     );
-    let b = run_stage4(
+    let b = run_stage4_on(
         &accessor,
         &simple_gene_index(),
         Species::Human,
         &panel_set,
         &panel_scores,
         &thresholds,
+        &Stage4Covariates::default(),
     );
 
     assert_eq!(a.axes.tbi[0].to_bits(), b.axes.tbi[0].to_bits());
@@ -218,3 +316,360 @@ fn test_determinism() {
     assert_eq!(a.axes.cci[0].to_bits(), b.axes.cci[0].to_bits());
     assert_eq!(a.axes.trci[0].to_bits(), b.axes.trci[0].to_bits());
 }
+
+fn axes_bits(axes: &Axes) -> Vec<u32> {
+    [
+        &axes.tbi, &axes.rci, &axes.pds, &axes.trs, &axes.nsai, &axes.iaa, &axes.dfa, &axes.cea,
+        &axes.rss, &axes.drbi, &axes.cci, &axes.trci,
+    ]
+    .into_iter()
+    .flat_map(|values| values.iter().map(|v| v.to_bits()))
+    .collect()
+}
+
+#[test]
+fn test_parallel_axes_match_serial_bitwise() {
+    let n_cells = 5_000;
+    let mut state = 0x2545_f491_u32;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state % 1000) as f32 / 100.0
+    };
+    let cols: Vec<Vec<(u32, f32)>> = (0..n_cells)
+        .map(|_| (0..3).map(|g| (g, next() + 0.01)).collect())
+        .collect();
+    let libsizes = cols
+        .iter()
+        .map(|col| col.iter().map(|&(_, v)| v as f64).sum())
+        .collect();
+    let accessor = DummyAccessor {
+        cols,
+        n_genes: 3,
+        libsizes,
+        nnz: vec![3; n_cells],
+    };
+    let panel_sum: Vec<Vec<f32>> = (0..n_cells)
+        .map(|_| (0..6).map(|_| next()).collect())
+        .collect();
+    let panel_scores = PanelScores {
+        panel_mean: panel_sum.clone(),
+        panel_raw_sum: panel_sum.clone(),
+        panel_detected: vec![vec![1; 6]; n_cells],
+        panel_coverage: vec![vec![1.0; 6]; n_cells],
+        panel_sum,
+    };
+    let panel_set = simple_panel_set();
+    let gene_index = simple_gene_index();
+    let thresholds = ThresholdProfile::default_v1();
+    let run = |threads: usize| {
+        run_stage4(
+            Stage4Cells::scan(&accessor, &gene_index, Species::Human, &thresholds),
+            &gene_index,
+            Species::Human,
+            &panel_set,
+            &panel_scores,
+            &thresholds,
+            &Stage4Covariates::default(),
+            threads,
+        )
+    };
+
+    let serial = run(1);
+    let expected = axes_bits(&serial.axes);
+    for round in 0..10 {
+        let threads = [2, 3, 4, 7, 8][round % 5];
+        let out = run(threads);
+        assert_eq!(axes_bits(&out.axes), expected, "threads={threads}");
+        for (a, b) in out.drivers.iter().zip(&serial.drivers) {
+            assert_eq!(a.panel_entropy.to_bits(), b.panel_entropy.to_bits());
+            assert_eq!(a.axis_variance.to_bits(), b.axis_variance.to_bits());
+        }
+    }
+}
+
+#[test]
+fn test_cea_ribo_adjust_lowers_ribo_heavy_cell() {
+    let accessor = DummyAccessor {
+        cols: vec![vec![(0, 1.0)], vec![(0, 1.0)], vec![(0, 1.0)]],
+        n_genes: 3,
+        libsizes: vec![1.0; 3],
+        nnz: vec![1; 3],
+    };
+    let panel_set = PanelSet {
+        panels: vec![Panel {
+            id: "clonal_engagement".to_string(),
+            name: "Clonal".to_string(),
+            group: PanelGroup::Program,
+            genes: vec![0],
+            weights: Vec::new(),
+            missing: Vec::new(),
+        }],
+    };
+    let panel_scores = PanelScores {
+        panel_sum: vec![vec![0.2], vec![0.4], vec![0.9]],
+        panel_mean: vec![vec![0.2], vec![0.4], vec![0.9]],
+        panel_raw_sum: vec![vec![0.2], vec![0.4], vec![0.9]],
+        panel_detected: vec![vec![1], vec![1], vec![1]],
+        panel_coverage: vec![vec![1.0], vec![1.0], vec![1.0]],
+    };
+    let ribo = vec![0.05, 0.15, 0.60];
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    let plain = run_stage4_on(
+        &accessor,
+        &simple_gene_index(),
+        Species::Human,
+        &panel_set,
+        &panel_scores,
+        &thresholds,
+        &Stage4Covariates {
+            ribo_fraction: Some(&ribo),
+            ..Default::default()
+        },
+    );
+    assert!(plain.cea_ribo_slope.is_none());
+
+    thresholds.cea_ribo_adjust = true;
+    let adjusted = run_stage4_on(
+        &accessor,
+        &simple_gene_index(),
+        Species::Human,
+        &panel_set,
+        &panel_scores,
+        &thresholds,
+        &Stage4Covariates {
+            ribo_fraction: Some(&ribo),
+            ..Default::default()
+        },
+    );
+    assert!(adjusted.cea_ribo_slope.unwrap() > 0.0);
+    assert!(adjusted.axes.cea[2] < plain.axes.cea[2]);
+}
+
+#[test]
+fn test_min_nonzero_expr_driver() {
+    let accessor = DummyAccessor {
+        cols: vec![vec![(0, 3.0), (1, 0.0), (2, 0.25)], vec![]],
+        n_genes: 3,
+        libsizes: vec![3.25, 0.0],
+        nnz: vec![3, 0],
+    };
+    let out = run_stage4_on(
+        &accessor,
+        &simple_gene_index(),
+        Species::Human,
+        &simple_panel_set(),
+        &simple_scores(),
+        &ThresholdProfile::default_v1(),
+        &Stage4Covariates::default(),
+    );
+    assert_eq!(out.drivers[0].min_nonzero_expr, 0.25);
+    assert_eq!(out.drivers[1].min_nonzero_expr, 0.0);
+}
+
+#[test]
+fn test_relative_within_sample_differs_from_pooled() {
+    // Sample "a" is immune-hot; pooled anchors leave sample "b" inactive.
+    let iaa = (0..20)
+        .map(|i| {
+            if i < 10 {
+                5.0 + i as f32
+            } else {
+                0.1 * (i - 9) as f32
+            }
+        })
+        .collect::<Vec<_>>();
+    let n = iaa.len();
+    let accessor = DummyAccessor {
+        cols: vec![vec![(0, 1.0)]; n],
+        n_genes: 3,
+        libsizes: vec![1.0; n],
+        nnz: vec![1; n],
+    };
+    let panel_set = PanelSet {
+        panels: vec![Panel {
+            id: "immune_activation".to_string(),
+            name: "Immune".to_string(),
+            group: PanelGroup::Program,
+            genes: vec![0],
+            weights: Vec::new(),
+            missing: Vec::new(),
+        }],
+    };
+    let panel_scores = PanelScores {
+        panel_sum: iaa.iter().map(|&v| vec![v]).collect(),
+        panel_mean: iaa.iter().map(|&v| vec![v]).collect(),
+        panel_raw_sum: iaa.iter().map(|&v| vec![v]).collect(),
+        panel_detected: vec![vec![1]; n],
+        panel_coverage: vec![vec![1.0]; n],
+    };
+    let labels = (0..n)
+        .map(|i| if i < 10 { "a" } else { "b" }.to_string())
+        .collect::<Vec<_>>();
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    thresholds.activation_mode = AxisActivationMode::Relative;
+    thresholds.relative_min_group_cells = 10;
+    let run = |thresholds: &ThresholdProfile| {
+        run_stage4_on(
+            &accessor,
+            &simple_gene_index(),
+            Species::Human,
+            &panel_set,
+            &panel_scores,
+            thresholds,
+            &Stage4Covariates {
+                sample_labels: Some(&labels),
+                ..Default::default()
+            },
+        )
+    };
+
+    let pooled = run(&thresholds);
+    assert_eq!(pooled.axes.iaa[19], 0.0);
+
+    thresholds.relative_within = RelativeWithin::Sample;
+    let stratified = run(&thresholds);
+    assert!(stratified.axes.iaa[19] > 0.0);
+    assert_eq!(stratified.axes.iaa[19], stratified.axes.iaa[9]);
+
+    // Groups below the minimum fall back to pooled anchors.
+    thresholds.relative_min_group_cells = 11;
+    let fallback = run(&thresholds);
+    assert_eq!(fallback.axes.iaa, pooled.axes.iaa);
+}
+
+#[test]
+fn test_winsorize_panels_clamps_extreme_cell() {
+    // IAA sums 1..=19 plus one ambient burst in the last cell.
+    let run = |last: f32, winsorize_panels: Option<f32>| {
+        let iaa = (1..20).map(|v| v as f32).chain([last]).collect::<Vec<_>>();
+        let n = iaa.len();
+        let accessor = DummyAccessor {
+            cols: vec![vec![(0, 1.0)]; n],
+            n_genes: 3,
+            libsizes: vec![1.0; n],
+            nnz: vec![1; n],
+        };
+        let panel_set = PanelSet {
+            panels: vec![Panel {
+                id: "immune_activation".to_string(),
+                name: "Immune".to_string(),
+                group: PanelGroup::Program,
+                genes: vec![0],
+                weights: Vec::new(),
+                missing: Vec::new(),
+            }],
+        };
+        let panel_scores = PanelScores {
+            panel_sum: iaa.iter().map(|&v| vec![v]).collect(),
+            panel_mean: iaa.iter().map(|&v| vec![v]).collect(),
+            panel_raw_sum: iaa.iter().map(|&v| vec![v]).collect(),
+            panel_detected: vec![vec![1]; n],
+            panel_coverage: vec![vec![1.0]; n],
+        };
+        let mut thresholds = ThresholdProfile::default_v1();
+        thresholds.activation_mode = AxisActivationMode::Relative;
+        thresholds.winsorize_panels = winsorize_panels;
+        run_stage4_on(
+            &accessor,
+            &simple_gene_index(),
+            Species::Human,
+            &panel_set,
+            &panel_scores,
+            &thresholds,
+            &Stage4Covariates::default(),
+        )
+    };
+
+    // Clamped at p90, the burst cell looks like an ordinary top cell, so the
+    // relative anchors and every axis match a run without the burst.
+    let burst = run(1.0e6, Some(0.9));
+    let ordinary = run(20.0, Some(0.9));
+    assert_eq!(burst.panel_caps, ordinary.panel_caps);
+    assert!((burst.panel_caps.as_ref().unwrap()[0] - 18.1).abs() < 1e-4);
+    assert_eq!(burst.axes.iaa, ordinary.axes.iaa);
+    assert_eq!(burst.axes.tbi, ordinary.axes.tbi);
+
+    // Without the flag nothing is clamped; a cap at the maximum is a no-op.
+    let raw = run(1.0e6, None);
+    assert!(raw.panel_caps.is_none());
+    let capped_at_max = run(1.0e6, Some(1.0));
+    assert_eq!(capped_at_max.panel_caps, Some(vec![1.0e6]));
+    assert_eq!(raw.axes.iaa, capped_at_max.axes.iaa);
+    assert_eq!(raw.axes.tbi, capped_at_max.axes.tbi);
+    assert_eq!(raw.axes.pds, capped_at_max.axes.pds);
+}
+
+#[test]
+fn test_disabled_hr_panel_zeroes_drbi() {
+    use crate::panels::loader::{
+        DEFAULT_MAX_RULE_GENES, load_panels, resolve_panel_defs, select_panel_defs,
+    };
+    use crate::pipeline::stage3_panels::score_panels;
+
+    let defs = resolve_panel_defs(None).unwrap();
+    let symbols = defs
+        .iter()
+        .filter(|d| d.id == "dna_repair_hr" || d.id == "dna_repair_nhej")
+        .flat_map(|d| d.genes.clone())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let gene_index = GeneIndex {
+        gene_id_by_feature: (0..symbols.len()).map(Some).collect(),
+        symbols_by_gene_id: symbols,
+    };
+    let n_genes = gene_index.symbols_by_gene_id.len();
+    let cols = (0..4)
+        .map(|cell| {
+            (0..n_genes as u32)
+                .map(|g| (g, (1 + (g as usize * 3 + cell * 5) % 7) as f32))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let accessor = DummyAccessor {
+        libsizes: cols
+            .iter()
+            .map(|c| c.iter().map(|&(_, v)| f64::from(v)).sum())
+            .collect(),
+        nnz: vec![n_genes as u32; 4],
+        cols,
+        n_genes,
+    };
+    let thresholds = ThresholdProfile::default_v1();
+    let drbi = |defs: &[PanelDef]| {
+        let (panel_set, _) =
+            load_panels(Species::Human, &gene_index, defs, DEFAULT_MAX_RULE_GENES).unwrap();
+        let scores = score_panels(&accessor, &panel_set);
+        run_stage4_on(
+            &accessor,
+            &gene_index,
+            Species::Human,
+            &panel_set,
+            &scores,
+            &thresholds,
+            &Stage4Covariates::default(),
+        )
+        .axes
+        .drbi
+    };
+
+    assert!(drbi(&defs).iter().any(|&v| v != 0.0));
+    let disabled = select_panel_defs(defs, None, &["dna_repair_hr".to_string()]).unwrap();
+    assert!(disabled.iter().all(|d| d.id != "dna_repair_hr"));
+    assert_eq!(drbi(&disabled), vec![0.0; 4]);
+}
+
+#[test]
+fn test_rescale01_non_finite() {
+    assert_eq!(rescale01(0.5, 0.0, 2.0), 0.25);
+    assert_eq!(rescale01(f32::NAN, 0.0, 2.0), 0.0);
+    assert_eq!(rescale01(f32::INFINITY, 0.0, 2.0), 1.0);
+    assert_eq!(rescale01(f32::NEG_INFINITY, 0.0, 2.0), 0.0);
+    assert_eq!(rescale01(0.5, f32::NAN, 2.0), 0.0);
+    assert_eq!(rescale01(0.5, 0.0, f32::INFINITY), 0.0);
+    assert_eq!(rescale01(0.5, 1.0, 1.0), 0.0);
+}
@@ -17,6 +17,7 @@ fn dummy_inputs() -> Stage5Inputs<'static> {
     };
     let drivers = vec![AxisDrivers {
         expressed_genes: 50,
+        min_nonzero_expr: 0.0,
         gene_entropy: 0.0,
         panel_entropy: 0.0,
         max_program_share: 0.0,
@@ -38,9 +39,12 @@ fn dummy_inputs() -> Stage5Inputs<'static> {
         ambient_rna_risk: Some(Box::leak(Box::new(vec![false]))),
         key_panels_missing: Some(Box::leak(Box::new(vec![false]))),
         panel_nonzero_fraction: Some(Box::leak(Box::new(vec![0.5]))),
+        mito_fraction: None,
         axis_p90: Some([0.9, 0.1, 0.1]),
         scoring_mode: NuclearScoringMode::ImmuneAware,
+        confidence_model: ConfidenceModel::AdditiveImmune,
         include_ddr: true,
+        custom_composite: None,
     }
 }
 
@@ -88,9 +92,12 @@ fn test_confidence_degradation() {
         ambient_rna_risk: None,
         key_panels_missing: None,
         panel_nonzero_fraction: None,
+        mito_fraction: None,
         axis_p90: None,
         scoring_mode: NuclearScoringMode::ImmuneAware,
+        confidence_model: ConfidenceModel::AdditiveImmune,
         include_ddr: false,
+        custom_composite: None,
     };
     let out = run_stage5(&inputs);
     assert_eq!(out.scores.confidence[0], 0.0);
@@ -120,14 +127,83 @@ fn test_confidence_not_low_when_structure_high() {
         ambient_rna_risk: Some(Box::leak(Box::new(vec![false]))),
         key_panels_missing: Some(Box::leak(Box::new(vec![false]))),
         panel_nonzero_fraction: Some(Box::leak(Box::new(vec![0.5]))),
+        mito_fraction: None,
         axis_p90: Some([0.9, 0.2, 0.2]),
         scoring_mode: NuclearScoringMode::ImmuneAware,
+        confidence_model: ConfidenceModel::AdditiveImmune,
         include_ddr: true,
+        custom_composite: None,
     };
     let out = run_stage5(&inputs);
     assert!(out.scores.confidence[0] >= 0.2);
 }
 
+#[test]
+fn test_raising_confidence_floor_raises_median_confidence() {
+    // No key panel coverage or expression support, but clear axis structure:
+    // the additive sum stays under 0.5, so the floor decides.
+    let n = 3;
+    let axes = Axes {
+        tbi: vec![0.5, 0.4, 0.3],
+        rci: vec![0.2; n],
+        pds: vec![0.3; n],
+        trs: vec![0.4; n],
+        nsai: vec![0.1; n],
+        iaa: vec![0.0; n],
+        dfa: vec![0.0; n],
+        cea: vec![0.0; n],
+        rss: vec![0.0; n],
+        drbi: vec![0.0; n],
+        cci: vec![0.0; n],
+        trci: vec![0.0; n],
+    };
+    let drivers = vec![
+        AxisDrivers {
+            axis_variance: 0.02,
+            ..AxisDrivers::default()
+        };
+        n
+    ];
+    let confidence = |thresholds: &ThresholdProfile| {
+        let inputs = Stage5Inputs {
+            axes: &axes,
+            drivers: &drivers,
+            thresholds,
+            n_genes_mappable: Some(100),
+            key_panel_coverage_median: Some(&[0.0; 3]),
+            ambient_rna_risk: Some(&[false; 3]),
+            key_panels_missing: Some(&[false; 3]),
+            panel_nonzero_fraction: Some(&[0.0; 3]),
+            mito_fraction: None,
+            axis_p90: None,
+            scoring_mode: NuclearScoringMode::ImmuneAware,
+            confidence_model: ConfidenceModel::AdditiveImmune,
+            include_ddr: false,
+            custom_composite: None,
+        };
+        run_stage5(&inputs).scores.confidence
+    };
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    assert_eq!(thresholds.confidence_floor, 0.2);
+    assert_eq!(thresholds.confidence_structure_min, 0.2);
+    let default = confidence(&thresholds);
+    assert!(
+        default.iter().all(|&c| (0.2..0.5).contains(&c)),
+        "{default:?}"
+    );
+
+    thresholds.confidence_floor = 0.5;
+    let raised = confidence(&thresholds);
+    assert!(raised.iter().all(|&c| c == 0.5), "{raised:?}");
+    let method = thresholds.quantile_method;
+    assert!(crate::report::median(&raised, method) > crate::report::median(&default, method));
+
+    // An axis structure score of 0.4 is now below the minimum: no floor.
+    thresholds.confidence_structure_min = 0.5;
+    assert!(confidence(&thresholds).iter().all(|&c| c < 0.5));
+}
+
 #[test]
 fn test_driver_ordering() {
     let inputs = dummy_inputs();
@@ -149,3 +225,137 @@ fn test_determinism_bits() {
     assert_eq!(out_a.scores.ci[0].to_bits(), out_b.scores.ci[0].to_bits());
     assert_eq!(out_a.scores.rls[0].to_bits(), out_b.scores.rls[0].to_bits());
 }
+
+#[test]
+fn test_high_mito_reduces_expression_support() {
+    let base = dummy_inputs();
+    let baseline = run_stage5(&base);
+
+    let mut inputs = dummy_inputs();
+    inputs.mito_fraction = Some(Box::leak(Box::new(vec![0.1])));
+    let below = run_stage5(&inputs);
+    assert_eq!(
+        below.scores.confidence_breakdown[0],
+        baseline.scores.confidence_breakdown[0]
+    );
+
+    inputs.mito_fraction = Some(Box::leak(Box::new(vec![0.5])));
+    let above = run_stage5(&inputs);
+    let support_base = baseline.scores.confidence_breakdown[0][1];
+    let support_mito = above.scores.confidence_breakdown[0][1];
+    assert!((support_mito - 0.9 * support_base).abs() < 1e-6);
+    assert!(above.scores.confidence[0] < baseline.scores.confidence[0]);
+}
+
+#[test]
+fn test_zero_weight_removes_component() {
+    let mut thresholds = ThresholdProfile::default_v1();
+    thresholds.confidence_w_coverage = 0.0;
+    thresholds.validate().unwrap();
+
+    let mut inputs = dummy_inputs();
+    inputs.thresholds = Box::leak(Box::new(thresholds));
+    let high_cov = run_stage5(&inputs);
+    inputs.key_panel_coverage_median = Some(Box::leak(Box::new(vec![0.05])));
+    let low_cov = run_stage5(&inputs);
+
+    assert_ne!(
+        high_cov.scores.confidence_breakdown[0][0],
+        low_cov.scores.confidence_breakdown[0][0]
+    );
+    assert_eq!(high_cov.scores.confidence[0], low_cov.scores.confidence[0]);
+
+    // With default weights the same coverage drop does lower confidence.
+    let baseline = run_stage5(&dummy_inputs());
+    let mut inputs = dummy_inputs();
+    inputs.key_panel_coverage_median = Some(Box::leak(Box::new(vec![0.05])));
+    let low_cov_default = run_stage5(&inputs);
+    assert!(low_cov_default.scores.confidence[0] < baseline.scores.confidence[0]);
+}
+
+#[test]
+fn test_confidence_weight_validation() {
+    let mut thresholds = ThresholdProfile::default_v1();
+    assert_eq!(thresholds.confidence_weights(), [0.30, 0.25, 0.25, 0.20]);
+    thresholds.confidence_w_consistency = -0.1;
+    assert!(thresholds.validate().is_err());
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    thresholds.confidence_w_coverage = 0.0;
+    thresholds.confidence_w_expr_support = 0.0;
+    thresholds.confidence_w_axis_structure = 0.0;
+    thresholds.confidence_w_consistency = 0.0;
+    assert!(thresholds.validate().is_err());
+
+    let mut thresholds = ThresholdProfile::default_v1();
+    thresholds.confidence_w_coverage = 2.0;
+    thresholds.confidence_w_expr_support = 2.0;
+    thresholds.confidence_w_axis_structure = 0.0;
+    thresholds.confidence_w_consistency = 0.0;
+    assert_eq!(thresholds.confidence_weights(), [0.5, 0.5, 0.0, 0.0]);
+}
+
+#[test]
+fn test_missing_key_panels_zeroes_coverage_component() {
+    let present = run_stage5(&dummy_inputs());
+    assert!(present.scores.confidence_breakdown[0][0] > 0.0);
+
+    let mut inputs = dummy_inputs();
+    inputs.key_panels_missing = Some(Box::leak(Box::new(vec![true])));
+    let missing = run_stage5(&inputs);
+    assert_eq!(missing.scores.confidence_breakdown[0][0], 0.0);
+    assert!(missing.scores.confidence[0] < present.scores.confidence[0]);
+}
+
+#[test]
+fn test_confidence_models_differ_on_same_axes() {
+    let mut inputs = dummy_inputs();
+    let mut drivers = (*inputs.drivers).to_vec();
+    drivers[0].axis_variance = 0.03;
+    inputs.drivers = Box::leak(Box::new(drivers));
+
+    let mut confidence = Vec::new();
+    for model in [
+        ConfidenceModel::AdditiveImmune,
+        ConfidenceModel::LegacyMultiplicative,
+        ConfidenceModel::Logistic,
+    ] {
+        inputs.confidence_model = model;
+        let out = run_stage5(&inputs);
+        confidence.push(out.scores.confidence[0]);
+    }
+    let [additive, multiplicative, logistic] = confidence[..] else {
+        unreachable!()
+    };
+    let breakdown = run_stage5(&inputs).scores.confidence_breakdown[0];
+    let w = inputs.thresholds.confidence_weights();
+    let sum = w[0] * breakdown[0] + w[1] * breakdown[1] + w[2] * breakdown[2] + w[3] * breakdown[3];
+
+    assert!((additive - sum.max(0.2)).abs() < 1e-6);
+    assert!((multiplicative - (0.5 * 0.8) * (0.3 * 0.5) * 0.2).abs() < 1e-6);
+    assert!((logistic - 1.0 / (1.0 + (-10.0 * (sum - 0.5)).exp())).abs() < 1e-6);
+    assert_ne!(additive, multiplicative);
+    assert_ne!(additive, logistic);
+    assert_ne!(multiplicative, logistic);
+}
+
+#[test]
+fn test_logistic_confidence_is_zero_without_evidence() {
+    let mut inputs = dummy_inputs();
+    inputs.key_panel_coverage_median = None;
+    inputs.panel_nonzero_fraction = None;
+    inputs.confidence_model = ConfidenceModel::Logistic;
+    let out = run_stage5(&inputs);
+    assert_eq!(out.scores.confidence[0], 0.0);
+}
+
+#[test]
+fn test_custom_composite_output() {
+    let mut inputs = dummy_inputs();
+    assert!(run_stage5(&inputs).custom.is_none());
+    let composite = CustomComposite::parse("0.4*tbi+0.3*rci-0.2*pds").unwrap();
+    inputs.custom_composite = Some(Box::leak(Box::new(composite)));
+    let out = run_stage5(&inputs);
+    let expected = clip01(0.4 * 0.5 + 0.3 * 0.2 - 0.2 * 0.3);
+    assert_eq!(out.custom, Some(vec![expected]));
+}
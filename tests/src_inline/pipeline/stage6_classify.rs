@@ -20,7 +20,10 @@ struct TestInputs {
     key_panels_missing: Option<Vec<bool>>,
     sum_tf_panels: Option<Vec<f32>>,
     ambient_rna_risk: Option<Vec<bool>>,
+    mito_fraction: Option<Vec<f32>>,
+    ribo_fraction: Option<Vec<f32>>,
     proliferation_program_share: Option<Vec<f32>>,
+    cell_cycle_phase: Option<Vec<CellCyclePhase>>,
     program_sum: Option<Vec<f32>>,
 }
 
@@ -47,7 +50,10 @@ impl TestInputs {
             key_panels_missing: self.key_panels_missing.as_deref(),
             sum_tf_panels: self.sum_tf_panels.as_deref(),
             ambient_rna_risk: self.ambient_rna_risk.as_deref(),
+            mito_fraction: self.mito_fraction.as_deref(),
+            ribo_fraction: self.ribo_fraction.as_deref(),
             proliferation_program_share: self.proliferation_program_share.as_deref(),
+            cell_cycle_phase: self.cell_cycle_phase.as_deref(),
             program_sum: self.program_sum.as_deref(),
         }
     }
@@ -76,6 +82,7 @@ fn base_inputs() -> TestInputs {
         },
         drivers: vec![AxisDrivers {
             expressed_genes: 50,
+            min_nonzero_expr: 0.0,
             gene_entropy: 0.2,
             panel_entropy: 0.2,
             max_program_share: 0.2,
@@ -92,7 +99,10 @@ fn base_inputs() -> TestInputs {
         key_panels_missing: None,
         sum_tf_panels: None,
         ambient_rna_risk: None,
+        mito_fraction: None,
+        ribo_fraction: None,
         proliferation_program_share: None,
+        cell_cycle_phase: None,
         program_sum: None,
     }
 }
@@ -115,6 +125,20 @@ fn test_rigid_deg() {
     assert_eq!(out[0].regime, NuclearRegime::RigidDegenerative);
 }
 
+#[test]
+fn test_rigid_trs_cutoff_from_profile() {
+    let mut inputs = base_inputs();
+    inputs.trs[0] = 0.72;
+    inputs.nsai[0] = 0.6;
+    inputs.rci[0] = 0.3;
+    let out = run_stage6(&inputs.as_inputs());
+    assert_ne!(out[0].regime, NuclearRegime::RigidDegenerative);
+
+    inputs.thresholds.rigid_trs_min = 0.70;
+    let out = run_stage6(&inputs.as_inputs());
+    assert_eq!(out[0].regime, NuclearRegime::RigidDegenerative);
+}
+
 #[test]
 fn test_committed_state() {
     let mut inputs = base_inputs();
@@ -164,12 +188,54 @@ fn test_unclassified() {
     assert_eq!(out[0].regime, NuclearRegime::Unclassified);
 }
 
+#[test]
+fn test_unclassified_near_miss() {
+    let mut inputs = base_inputs();
+    inputs.nsai[0] = 0.70;
+    inputs.rci[0] = 0.30;
+    inputs.tbi[0] = 0.36;
+    let out = run_stage6(&inputs.as_inputs());
+    assert_eq!(out[0].regime, NuclearRegime::Unclassified);
+    let near_miss = out[0].near_miss.as_ref().unwrap();
+    assert_eq!(near_miss.regime, NuclearRegime::StressAdaptive);
+    assert_eq!(near_miss.failed, 1);
+    assert_eq!(near_miss.blocked_by.rule(), "rci>=0.35");
+    assert_eq!(near_miss.blocked_by.failure(), "rci=0.30<0.35");
+
+    inputs.rci[0] = 0.40;
+    let out = run_stage6(&inputs.as_inputs());
+    assert_eq!(out[0].regime, NuclearRegime::StressAdaptive);
+    assert!(out[0].near_miss.is_none());
+}
+
+#[test]
+fn test_near_miss_prefers_fewest_failed_conditions() {
+    // Every rule fails; Transient and Plastic fail one condition each and
+    // Transient is closer through its nearest either-or alternative.
+    let inputs = base_inputs();
+    let miss = near_miss(&inputs.as_inputs(), 0).unwrap();
+    assert_eq!(miss.regime, NuclearRegime::TransientAdaptive);
+    assert_eq!(miss.blocked_by.failure(), "iaa=0.20<0.35");
+
+    // Plastic misses on both nps and trs, Transient only on trs.
+    let mut inputs = base_inputs();
+    inputs.scores.nps[0] = 0.55;
+    inputs.trs[0] = 0.58;
+    inputs.iaa[0] = 0.0;
+    inputs.dfa[0] = 0.0;
+    inputs.nsai[0] = 0.0;
+    let miss = near_miss(&inputs.as_inputs(), 0).unwrap();
+    assert_eq!(miss.regime, NuclearRegime::TransientAdaptive);
+    assert_eq!(miss.failed, 1);
+    assert_eq!(miss.blocked_by.failure(), "trs=0.58>0.55");
+}
+
 #[test]
 fn test_flags() {
     let mut inputs = base_inputs();
     inputs.drivers[0].expressed_genes = 1;
     inputs.scores.confidence[0] = 0.2;
-    inputs.key_panel_coverage_median = Some(vec![0.2]);
+    inputs.key_panel_coverage_median = Some(vec![inputs.thresholds.panel_coverage_min - 0.2]);
     inputs.key_panels_missing = Some(vec![true]);
     inputs.sum_tf_panels = Some(vec![0.0]);
     inputs.ambient_rna_risk = Some(vec![true]);
@@ -198,6 +264,48 @@ fn test_flags() {
     assert!(flags.contains(&Flag::HighTrConflict));
 }
 
+#[test]
+fn test_low_panel_coverage_follows_profile_cutoff() {
+    let mut inputs = base_inputs();
+    inputs.key_panel_coverage_median = Some(vec![0.2]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(out[0].flags.contains(&Flag::LowPanelCoverage));
+
+    inputs.thresholds = ThresholdProfile::snrna_v1();
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::LowPanelCoverage));
+
+    inputs.thresholds.panel_coverage_min = 0.25;
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(out[0].flags.contains(&Flag::LowPanelCoverage));
+}
+
+#[test]
+fn test_cell_cycle_confounder_needs_cycling_phase() {
+    let mut inputs = base_inputs();
+    inputs.proliferation_program_share = Some(vec![0.9]);
+    inputs.cell_cycle_phase = Some(vec![CellCyclePhase::G1]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::CellCycleConfounder));
+
+    inputs.cell_cycle_phase = Some(vec![CellCyclePhase::G2M]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(out[0].flags.contains(&Flag::CellCycleConfounder));
+}
+
+#[test]
+fn test_tumor_profile_tolerates_higher_proliferation_share() {
+    let mut inputs = base_inputs();
+    inputs.proliferation_program_share = Some(vec![0.6]);
+    inputs.cell_cycle_phase = Some(vec![CellCyclePhase::S]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(out[0].flags.contains(&Flag::CellCycleConfounder));
+
+    inputs.thresholds = ThresholdProfile::tumor_v1();
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::CellCycleConfounder));
+}
+
 #[test]
 fn test_ddr_repair_bias_flags() {
     let mut inputs = base_inputs();
@@ -219,3 +327,78 @@ fn test_determinism() {
     assert_eq!(a[0].regime, b[0].regime);
     assert_eq!(a[0].flags, b[0].flags);
 }
+
+#[test]
+fn test_high_mito_flag() {
+    let mut inputs = base_inputs();
+    inputs.mito_fraction = Some(vec![0.15]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::HighMitoFraction));
+
+    inputs.mito_fraction = Some(vec![0.35]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(out[0].flags.contains(&Flag::HighMitoFraction));
+    assert!(!out[0].flags.contains(&Flag::HighRiboFraction));
+
+    inputs.thresholds.mito_frac_max = 0.4;
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::HighMitoFraction));
+}
+
+#[test]
+fn test_high_ribo_flag_follows_mito_flag() {
+    let mut inputs = base_inputs();
+    inputs.ribo_fraction = Some(vec![0.45]);
+    let out = run_stage6(&inputs.as_inputs());
+    assert!(!out[0].flags.contains(&Flag::HighRiboFraction));
+
+    inputs.ribo_fraction = Some(vec![0.6]);
+    inputs.mito_fraction = Some(vec![0.3]);
+    let out = run_stage6(&inputs.as_inputs());
+    let high = out[0]
+        .flags
+        .iter()
+        .filter(|f| matches!(f, Flag::HighMitoFraction | Flag::HighRiboFraction))
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(high, [Flag::HighMitoFraction, Flag::HighRiboFraction]);
+}
+
+#[test]
+fn test_silence_reason_branches() {
+    let mut data = base_inputs();
+    data.iaa = vec![0.0];
+    data.dfa = vec![0.0];
+    data.cea = vec![0.0];
+    let out = run_stage6(&data.as_inputs());
+    assert_eq!(
+        silence_reason(&data.as_inputs(), 0),
+        Some(SilenceReason::BiologicalSilence)
+    );
+    assert_eq!(
+        out[0].silence_reason(),
+        Some(SilenceReason::BiologicalSilence)
+    );
+    assert!(!out[0].flags.contains(&Flag::ModelLimitation));
+
+    data.iaa = vec![0.3];
+    let out = run_stage6(&data.as_inputs());
+    assert_eq!(
+        out[0].silence_reason(),
+        Some(SilenceReason::ModelLimitation)
+    );
+    assert!(!out[0].flags.contains(&Flag::BiologicalSilence));
+
+    data.iaa = vec![0.0];
+    data.thresholds.activation_mode = AxisActivationMode::Hybrid;
+    let out = run_stage6(&data.as_inputs());
+    assert_eq!(
+        out[0].silence_reason(),
+        Some(SilenceReason::ModelLimitation)
+    );
+
+    data.thresholds.activation_mode = AxisActivationMode::Absolute;
+    data.scores.confidence = vec![0.1];
+    let out = run_stage6(&data.as_inputs());
+    assert_eq!(out[0].silence_reason(), None);
+}
@@ -1,12 +1,19 @@
 use super::*;
+use crate::input::Species;
+use crate::input::meta::CellMeta;
 use crate::metrics::genome_stability::scores::{
     GenomePanelAudit, GenomeStabilityCellScores, RobustNormStat,
 };
+use crate::model::axes::AxisDrivers;
 use crate::model::drivers::ScoreDrivers;
 use crate::model::flags::Flag;
 use crate::model::regimes::NuclearRegime;
 use crate::model::scores::CompositeScores;
-use crate::panels::{Panel, PanelAudit, PanelScores, PanelSet};
+use crate::model::thresholds::ConfidenceModel;
+use crate::panels::{
+    CellCyclePhase, Panel, PanelAudit, PanelGroup, PanelScoreMode, PanelScores, PanelSet,
+};
+use crate::report::metadata::summarize_meta;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 static DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -28,6 +35,9 @@ fn build_input() -> Stage7Input<'static> {
     let libsize = vec![10.0, 20.0];
     let nnz = vec![1u32, 2u32];
     let expr = vec![5u32, 6u32];
+    let pct_mito = vec![0.05, 0.3];
+    let pct_ribo = vec![0.2, 0.1];
+    let min_nonzero_expr = vec![0.5, 1.0];
 
     let axes_tbi = vec![0.1, 0.2];
     let axes_rci = vec![0.2, 0.3];
@@ -104,19 +114,22 @@ fn build_input() -> Stage7Input<'static> {
         crate::pipeline::stage6_classify::Classification {
             regime: NuclearRegime::PlasticAdaptive,
             flags: vec![Flag::LowConfidence],
+            near_miss: None,
         },
         crate::pipeline::stage6_classify::Classification {
             regime: NuclearRegime::Unclassified,
             flags: vec![],
+            near_miss: None,
         },
     ];
 
     let panels = PanelSet {
         panels: vec![Panel {
-            id: "p1",
-            name: "P1",
+            id: "p1".to_string(),
+            name: "P1".to_string(),
             group: crate::panels::defs::PanelGroup::Program,
             genes: vec![0],
+            weights: Vec::new(),
             missing: vec![],
         }],
     };
@@ -125,9 +138,12 @@ fn build_input() -> Stage7Input<'static> {
         panel_size_defined: 1,
         panel_size_mappable: 1,
         missing_genes: vec![],
+        rules: vec![],
     }];
     let panel_scores = PanelScores {
         panel_sum: vec![vec![1.0], vec![2.0]],
+        panel_mean: vec![vec![3.0], vec![5.0]],
+        panel_raw_sum: vec![vec![3.0], vec![5.0]],
         panel_detected: vec![vec![1], vec![1]],
         panel_coverage: vec![vec![1.0], vec![1.0]],
     };
@@ -139,10 +155,22 @@ fn build_input() -> Stage7Input<'static> {
         species_per_cell: Some(Box::leak(Box::new(species))),
         cluster_labels: None,
         species_global: "Human".to_string(),
+        species_call: SpeciesCall {
+            species: Species::Human,
+            human_score: 0.9,
+            mouse_score: 0.0,
+            confidence: 1.0,
+        },
+        sampled_from: None,
 
         libsize: Box::leak(Box::new(libsize)),
         nnz: Box::leak(Box::new(nnz)),
         expressed_genes: Box::leak(Box::new(expr)),
+        pct_mito: Box::leak(Box::new(pct_mito)),
+        pct_ribo: Box::leak(Box::new(pct_ribo)),
+        pct_control: Box::leak(Box::new(vec![0.0, 0.25])),
+        cell_cycle_phase: Box::leak(Box::new(vec![CellCyclePhase::G1, CellCyclePhase::S])),
+        min_nonzero_expr: Box::leak(Box::new(min_nonzero_expr)),
 
         axes_tbi: Box::leak(Box::new(axes_tbi)),
         axes_rci: Box::leak(Box::new(axes_rci)),
@@ -163,28 +191,56 @@ fn build_input() -> Stage7Input<'static> {
 
         scores: Box::leak(Box::new(scores)),
         drivers: Box::leak(Box::new(drivers)),
+        thresholds: Box::leak(Box::new(ThresholdProfile::immune_v1())),
+        cea_ribo_slope: None,
+        panel_caps: None,
 
         classifications: Box::leak(Box::new(classifications)),
 
         panel_set: Box::leak(Box::new(panels)),
         panel_audits: Box::leak(Box::new(panel_audits)),
         panel_scores: Box::leak(Box::new(panel_scores)),
+        excluded_panels: &[],
+        metadata_columns: &[],
+        qc_gates: QcGates::default(),
 
         tool_name: "kira-nuclearqc".to_string(),
         tool_version: "0.1.0".to_string(),
         git_hash: None,
         simd_backend: "scalar".to_string(),
+        seed: 0,
+        stage_seconds: Vec::new(),
+        peak_rss_bytes: None,
 
         n_genes_raw: 10,
         n_genes_mappable: 8,
+        n_control_features: 0,
 
         normalize: true,
         scale: 10000.0,
         log1p: true,
         activation_mode: "Hybrid".to_string(),
         confidence_breakdown: None,
+        confidence_breakdown_columns: None,
+        custom_composite: None,
+        axis_drivers: None,
+        entropy_unit: EntropyUnit::Nats,
+        output_prefix: String::new(),
+        emit_obs_csv: false,
+        emit_program_shares: false,
+        emit_axis_correlations: false,
+        emit_long: false,
+        cell_stdout: false,
+        explain_regimes: false,
+        contrast: None,
+        panel_contributions: None,
+        axis_percentiles: false,
+        emit_panel_detection: false,
         scoring_mode: "immune-aware (default)".to_string(),
+        confidence_model: "immune-calibrated additive".to_string(),
+        profile: "immune".to_string(),
         pipeline_context: None,
+        provenance: None,
     }
 }
 
@@ -212,6 +268,274 @@ fn test_json_schema() {
     assert!(text.contains("\"genome_stability\""));
 }
 
+#[test]
+fn test_profile_recorded_in_summary_and_report() {
+    let mut input = build_input();
+    input.profile = "snrna".to_string();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let summary = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(summary.contains("\"profile\":\"snrna\""));
+    let report = std::fs::read_to_string(dir.join("report.txt")).unwrap();
+    assert!(report.contains("Threshold profile: snrna\n"));
+}
+
+#[test]
+fn test_summary_json_thresholds_block() {
+    let input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    let immune = ThresholdProfile::immune_v1();
+    assert_eq!(immune.min_expr_genes, 5);
+    assert!(text.contains(
+        "\"thresholds\":{\"expr_min\":0.000000,\"min_expr_genes\":5.000000,\
+         \"tf_min_sum\":0.500000,\"program_min_sum\":0.500000,\"rel_p70\":0.700000,\
+         \"rel_p85\":0.850000,\"confidence_low\":0.400000,\
+         \"trs_weights\":{\"a\":0.400000,\"b\":0.300000,\"c\":0.300000},"
+    ));
+    assert!(text.contains(
+        "\"mito_frac_max\":0.200000,\"ribo_frac_max\":0.500000,\"panel_coverage_min\":0.400000,"
+    ));
+    assert!(text.contains(
+        ",\"high_ribo_fraction\":0.000000,\"pct_control_median\":0.125000,\"pct_control_p90\":0.225000},\"thresholds\":"
+    ));
+}
+
+#[test]
+fn test_summary_records_panel_winsorization() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(text.contains("\"winsorize_panels\":null,"));
+
+    let mut thresholds = ThresholdProfile::immune_v1();
+    thresholds.winsorize_panels = Some(0.99);
+    input.thresholds = Box::leak(Box::new(thresholds));
+    input.panel_caps = Some(&[1.5]);
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(
+        text.contains("\"winsorize_panels\":{\"quantile\":0.990000,\"caps\":{\"p1\":1.500000}},")
+    );
+}
+
+#[test]
+fn test_unclassified_blockers_and_explain_columns() {
+    use crate::pipeline::stage6_classify::{Classification, Cmp, Condition, NearMiss};
+    let stress_rci = NearMiss {
+        regime: NuclearRegime::StressAdaptive,
+        failed: 1,
+        blocked_by: Condition {
+            metric: "rci",
+            cmp: Cmp::Ge,
+            value: 0.3,
+            cutoff: 0.35,
+        },
+    };
+    let mut input = build_input();
+    input.classifications = Box::leak(Box::new(vec![
+        Classification {
+            regime: NuclearRegime::Unclassified,
+            flags: vec![],
+            near_miss: Some(stress_rci.clone()),
+        },
+        Classification {
+            regime: NuclearRegime::PlasticAdaptive,
+            flags: vec![],
+            near_miss: None,
+        },
+    ]));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let summary = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(summary.contains(
+        "\"unclassified_blockers\":[{\"regime\":\"StressAdaptive\",\"condition\":\"rci>=0.35\",\
+         \"count\":1.000000,\"fraction\":1.000000}]}"
+    ));
+    let report = std::fs::read_to_string(dir.join("report.txt")).unwrap();
+    assert!(report.contains(
+        "Unclassified cells: 1 (0.500000); nearest regime and its most common unmet condition:\n  \
+         StressAdaptive needs rci>=0.35: 1 cells (1.000000)\n"
+    ));
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    assert!(!text.contains("nearest_regime"));
+
+    input.explain_regimes = true;
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    assert_eq!(
+        &header[header.len() - 3..],
+        ["nearest_regime", "unmet_conditions", "blocked_by"]
+    );
+    let rows = lines
+        .map(|l| l.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let unclassified = rows.iter().find(|r| r.contains(&"Unclassified")).unwrap();
+    assert_eq!(
+        &unclassified[header.len() - 3..],
+        ["StressAdaptive", "1", "rci=0.30<0.35"]
+    );
+    let classified = rows
+        .iter()
+        .find(|r| r.contains(&"PlasticAdaptive"))
+        .unwrap();
+    assert_eq!(&classified[header.len() - 3..], ["", "", ""]);
+}
+
+#[test]
+fn test_unclassified_blockers_rank_by_count() {
+    use crate::pipeline::stage6_classify::{Classification, Cmp, Condition, NearMiss};
+    let miss = |regime, metric, cutoff| Classification {
+        regime: NuclearRegime::Unclassified,
+        flags: vec![],
+        near_miss: Some(NearMiss {
+            regime,
+            failed: 1,
+            blocked_by: Condition {
+                metric,
+                cmp: Cmp::Ge,
+                value: 0.0,
+                cutoff,
+            },
+        }),
+    };
+    let classifications = vec![
+        miss(NuclearRegime::PlasticAdaptive, "nps", 0.6),
+        miss(NuclearRegime::StressAdaptive, "rci", 0.35),
+        miss(NuclearRegime::StressAdaptive, "rci", 0.35),
+        miss(NuclearRegime::StressAdaptive, "nsai", 0.65),
+    ];
+    let blockers = unclassified_blockers(&classifications);
+    let keys = blockers
+        .iter()
+        .map(|b| (b.regime, b.condition.as_str(), b.count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            ("StressAdaptive", "rci>=0.35", 2),
+            ("PlasticAdaptive", "nps>=0.6", 1),
+            ("StressAdaptive", "nsai>=0.65", 1),
+        ]
+    );
+    assert_eq!(blockers[0].fraction, 0.5);
+    assert!(unclassified_blockers(&[]).is_empty());
+}
+
+#[test]
+fn test_metrics_long_rows() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    assert!(!dir.join("metrics_long.tsv").exists());
+
+    input.emit_long = true;
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("metrics_long.tsv")).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(lines.next(), Some("barcode\tmetric_name\tvalue"));
+    let rows = lines
+        .map(|l| {
+            let fields = l.split('\t').collect::<Vec<_>>();
+            assert_eq!(fields.len(), 3);
+            (fields[0], fields[1], fields[2].parse::<f32>().unwrap())
+        })
+        .collect::<Vec<_>>();
+    let n_metrics = long_metrics(&input).len();
+    assert_eq!(n_metrics, 15);
+    assert_eq!(rows.len(), input.barcodes.len() * n_metrics);
+    assert_eq!(rows[0].0, input.barcodes[0]);
+    assert_eq!(rows[0].1, "a1_tbi");
+    assert_eq!(rows[n_metrics].0, input.barcodes[1]);
+    let trs = rows
+        .iter()
+        .filter(|(_, name, _)| *name == "a4_trs")
+        .map(|(_, _, v)| *v)
+        .collect::<Vec<_>>();
+    assert_eq!(trs, input.axes_trs);
+
+    input.custom_composite = Some(("1*tbi".to_string(), input.axes_tbi));
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("metrics_long.tsv")).unwrap();
+    assert_eq!(text.lines().count(), 1 + input.barcodes.len() * 16);
+    let first_custom = format!("{}\tc4_custom\t0.100000", input.barcodes[0]);
+    assert_eq!(text.lines().nth(16), Some(first_custom.as_str()));
+}
+
+#[test]
+fn test_tail_thresholds_default_keys() {
+    let input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(text.contains(
+        ",\"trs_ge_threshold\":0.750000,\"trs_tail_fraction\":0.000000,\
+         \"nps_ge_threshold\":0.600000,\"nps_tail_fraction\":0.000000,\
+         \"rls_le_threshold\":0.350000,\"rls_tail_fraction\":0.500000,\
+         \"trs_ge_0_75\":0.000000,\"nps_ge_0_60\":0.000000,\"rls_le_0_35\":0.500000}"
+    ));
+    assert!(!text.contains(",,"));
+
+    write_reports(&input, &dir, ReportMode::Sample).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    assert!(
+        text.lines()
+            .next()
+            .unwrap()
+            .contains("\ttrs_ge_0_75\tnps_ge_0_60\trls_le_0_35\tlow_confidence_fraction\t")
+    );
+}
+
+#[test]
+fn test_tail_thresholds_custom_cutoffs() {
+    let mut input = build_input();
+    let mut thresholds = ThresholdProfile::immune_v1();
+    thresholds.tail_trs_min = 0.5;
+    thresholds.tail_nps_min = 0.15;
+    thresholds.tail_rls_max = 0.4;
+    input.thresholds = Box::leak(Box::new(thresholds));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(text.contains(
+        ",\"trs_ge_threshold\":0.500000,\"trs_tail_fraction\":0.500000,\
+         \"nps_ge_threshold\":0.150000,\"nps_tail_fraction\":0.500000,\
+         \"rls_le_threshold\":0.400000,\"rls_tail_fraction\":1.000000},"
+    ));
+    assert!(!text.contains("trs_ge_0_75"));
+    assert!(!text.contains("rls_le_0_35"));
+
+    write_reports(&input, &dir, ReportMode::Sample).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let row = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    assert_eq!(row[col("trs_ge_0_50")], "0.500000");
+    assert_eq!(row[col("nps_ge_0_15")], "0.500000");
+    assert_eq!(row[col("rls_le_0_40")], "1.000000");
+    assert!(!header.contains(&"trs_ge_0_75"));
+
+    assert_eq!(cutoff_label(0.725), "0_725");
+}
+
+#[test]
+fn test_summary_and_provenance_record_seed() {
+    let mut input = build_input();
+    input.seed = 18_446_744_073_709_551_615;
+    input.provenance = Some(test_provenance());
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let summary = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(summary.contains("\"seed\":18446744073709551615}"));
+    let provenance = std::fs::read_to_string(dir.join("provenance.json")).unwrap();
+    assert!(provenance.contains("\"seed\":18446744073709551615,"));
+}
+
 #[test]
 fn test_deterministic_output() {
     let input = build_input();
@@ -223,18 +547,31 @@ fn test_deterministic_output() {
     assert_eq!(a, b);
 }
 
+// Writes the reports, then `pipeline_step.json` as `run_bundle` does.
+fn write_reports_with_step(input: &Stage7Input<'_>, dir: &Path) {
+    write_reports(input, dir, ReportMode::Cell).unwrap();
+    let summary = build_summary(input, ReportMode::Cell);
+    write_pipeline_step(input, &summary, &WarningLog::default(), false, dir).unwrap();
+}
+
 #[test]
 fn test_pipeline_step_json_schema_and_determinism() {
     let mut input = build_input();
+    input.stage_seconds = vec![("input", 0.25), ("stage3", 1.5)];
     input.pipeline_context = Some(PipelineContext {
         input_dir: "/tmp/input".to_string(),
-        input_source: "10x".to_string(),
-        shared_bin: None,
+        input_source: "kira-organelle.bin".to_string(),
+        shared_bin: Some("kira-organelle.bin".to_string()),
         run_mode: "pipeline".to_string(),
+        emit_step_json: false,
+        input_hashes: vec![(
+            "/tmp/input/kira-organelle.bin".to_string(),
+            "00000000deadbeef".to_string(),
+        )],
     });
 
     let dir = make_temp_dir();
-    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    write_reports_with_step(&input, &dir);
     let first = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
     assert!(first.contains("\"tool\""));
     assert!(first.contains("\"artifacts\""));
@@ -245,8 +582,863 @@ fn test_pipeline_step_json_schema_and_determinism() {
     assert!(first.contains("\"trci\""));
     assert!(first.contains("\"key_metrics\""));
     assert!(first.contains("\"mode\":\"pipeline\""));
+    assert!(first.contains("\"schema_version\":3,\"summary_schema_version\":8,"));
+    assert!(first.contains("\"status\":\"ok\""));
+    assert!(first.contains("\"tool_version\""));
+    assert!(first.contains("\"git_hash\""));
+    assert!(first.contains(
+        "\"input\":{\"dir\":\"/tmp/input\",\"source\":\"kira-organelle.bin\",\"shared_bin\":\"kira-organelle.bin\",\"hashes\":[{\"path\":\"/tmp/input/kira-organelle.bin\",\"fnv1a64\":\"00000000deadbeef\"}]}"
+    ));
+    assert!(first.contains("\"timings_seconds\":{\"input\":0.250000,\"stage3\":1.500000}"));
 
-    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    write_reports_with_step(&input, &dir);
     let second = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
     assert_eq!(first, second);
 }
+
+#[test]
+fn test_performance_json() {
+    let mut input = build_input();
+    let n_cells = input.barcodes.len() as f64;
+    let nnz = input.nnz.iter().map(|&n| n as u64).sum();
+    let estimate =
+        crate::memory::estimate_peak_rss_bytes(input.barcodes.len(), input.n_genes_raw, nnz);
+    input.stage_seconds = vec![("input", 0.5), ("stage2", 0.25), ("stage3", 1.25)];
+    let summary = build_summary(&input, ReportMode::Cell);
+    assert_eq!(
+        render_performance_json(&summary),
+        format!(
+            "{{\"tool\":\"kira-nuclearqc\",\"stage_seconds\":{{\"input\":0.500000,\"stage2\":0.250000,\"stage3\":1.250000}},\"total_seconds\":2.000000,\"cells_per_second\":{},\"peak_rss_bytes\":null,\"estimated_peak_rss_bytes\":{estimate}}}",
+            format_f32_6((n_cells / 2.0) as f32)
+        )
+    );
+    assert!(!render_summary_json(&summary).contains("seconds"));
+
+    // Timings too short to measure report no rate.
+    input.stage_seconds = vec![("input", 0.0), ("stage2", 0.0)];
+    let json = render_performance_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains("\"total_seconds\":0.000000,\"cells_per_second\":0.000000,"));
+}
+
+#[test]
+fn test_summary_metadata_columns() {
+    let mut input = build_input();
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains("\"metadata_columns\":[],"));
+
+    let meta = CellMeta {
+        columns: vec!["donor".to_string(), "viability".to_string()],
+        rows: vec![
+            vec!["D1".to_string(), "0.91".to_string()],
+            vec!["D1".to_string(), String::new()],
+        ],
+    };
+    let columns = summarize_meta(&meta, QuantileMethod::Linear);
+    input.metadata_columns = &columns;
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains(
+        "\"metadata_columns\":[{\"name\":\"donor\",\"type\":\"categorical\",\"missing_fraction\":0.000000,\"cardinality\":1.000000,\"top\":[{\"value\":\"D1\",\"count\":2.000000}]},\
+         {\"name\":\"viability\",\"type\":\"numeric\",\"missing_fraction\":0.500000,\"cardinality\":1.000000,\"min\":0.910000,\"median\":0.910000,\"max\":0.910000}],"
+    ));
+}
+
+#[test]
+fn test_cluster_mode_groups_unassigned_cells() {
+    let mut input = build_input();
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains("\"clusters\":[],"));
+
+    let labels = vec![String::new(), "k1".to_string()];
+    input.cluster_labels = Some(&labels);
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cluster));
+    assert!(json.contains("\"resolution\":\"cluster\""));
+    assert!(json.contains(
+        "\"clusters\":[{\"cluster\":\"(none)\",\"n_cells\":1.000000,\"fraction\":0.500000,\"dominant_regime\":\"PlasticAdaptive\",\"c1_nps_median\":0.100000,\"c2_ci_median\":0.200000,\"c3_rls_median\":0.300000},\
+         {\"cluster\":\"k1\",\"n_cells\":1.000000,\"fraction\":0.500000,\"dominant_regime\":\"Unclassified\",\"c1_nps_median\":0.200000,\"c2_ci_median\":0.300000,\"c3_rls_median\":0.400000}],"
+    ));
+
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cluster).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let rows = text
+        .lines()
+        .map(|l| l.split('\t').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, ["cluster", "(none)", "k1"]);
+}
+
+#[test]
+fn test_summary_and_report_contrasts() {
+    let mut input = build_input();
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains("\"contrasts\":null,"));
+
+    let meta = CellMeta {
+        columns: vec!["condition".to_string()],
+        rows: vec![vec!["treated".to_string()], vec!["control".to_string()]],
+    };
+    let design = ContrastDesign::new(&meta, "condition", None).unwrap();
+    input.contrast = Some(&design);
+    let summary = build_summary(&input, ReportMode::Cell);
+    let json = render_summary_json(&summary);
+    // One cell per level: U = 0 of 1 pair for a lower treated NPS.
+    assert!(json.contains(
+        "\"contrasts\":{\"column\":\"condition\",\"reference\":\"control\",\"results\":[\
+         {\"level\":\"treated\",\"metric\":\"a1_tbi\",\"n_cells\":1.000000,\"n_reference\":1.000000,\
+         \"median_diff\":-0.100000,\"cliffs_delta\":-1.000000,\"u\":0.000000,\"p_value\":1.000000,\"p_method\":\"exact\"},"
+    ));
+    let results = &summary.contrasts.as_ref().unwrap().results;
+    assert_eq!(results.len(), 11);
+    assert_eq!(results[10].metric, "c3_rls");
+
+    let report = render_report_text(&build_report_context(&input, &summary));
+    assert!(report.contains(
+        "6. Contrasts by condition (reference control)\nlevel\tmetric\tmedian_diff\tcliffs_delta\tp_value\ntreated\ta1_tbi\t-0.100000\t-1.000000\t1.000000\n"
+    ));
+}
+
+#[test]
+fn test_summary_schema_version_key() {
+    let input = build_input();
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.starts_with(&format!(
+        "{{\"tool\":\"kira-nuclearqc\",\"schema_version\":{SUMMARY_SCHEMA_VERSION},\"input\":{{"
+    )));
+    assert_eq!(SUMMARY_SCHEMA_VERSION, 8);
+}
+
+#[test]
+fn test_emit_step_json_in_standalone_mode() {
+    let mut input = build_input();
+    input.pipeline_context = Some(PipelineContext {
+        input_dir: "/tmp/input".to_string(),
+        input_source: "10x".to_string(),
+        shared_bin: None,
+        run_mode: "standalone".to_string(),
+        emit_step_json: false,
+        input_hashes: Vec::new(),
+    });
+    let dir = make_temp_dir();
+    write_reports_with_step(&input, &dir);
+    assert!(!dir.join("pipeline_step.json").exists());
+
+    input.pipeline_context.as_mut().unwrap().emit_step_json = true;
+    write_reports_with_step(&input, &dir);
+    let step = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
+    assert!(step.contains("\"mode\":\"standalone\""));
+}
+
+#[test]
+fn test_pipeline_step_status_reports_warnings() {
+    let mut input = build_input();
+    input.pipeline_context = Some(PipelineContext {
+        input_dir: "/tmp/input".to_string(),
+        input_source: "10x".to_string(),
+        shared_bin: None,
+        run_mode: "pipeline".to_string(),
+        emit_step_json: false,
+        input_hashes: Vec::new(),
+    });
+    let mut warnings = WarningLog::default();
+    warnings.record("kira_nuclearqc::input::meta");
+    warnings.record("kira_nuclearqc::run");
+    let dir = make_temp_dir();
+    let summary = build_summary(&input, ReportMode::Cell);
+    write_pipeline_step(&input, &summary, &warnings, false, &dir).unwrap();
+    let step = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
+    assert!(step.contains("\"status\":\"ok_with_warnings\",\"warnings\":2"));
+
+    write_pipeline_step(&input, &summary, &warnings, true, &dir).unwrap();
+    let step = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
+    assert!(step.contains("\"status\":\"failed_on_warnings\",\"warnings\":2"));
+    assert!(step.contains("\"shared_bin\":null,\"hashes\":[]"));
+}
+
+#[test]
+fn test_pipeline_step_marks_cell_stdout() {
+    let mut input = build_input();
+    input.pipeline_context = Some(PipelineContext {
+        input_dir: "/tmp/input".to_string(),
+        input_source: "10x".to_string(),
+        shared_bin: None,
+        run_mode: "pipeline".to_string(),
+        emit_step_json: false,
+        input_hashes: Vec::new(),
+    });
+    input.cell_stdout = true;
+    let dir = make_temp_dir();
+    let summary = build_summary(&input, ReportMode::Cell);
+    write_pipeline_step(&input, &summary, &WarningLog::default(), false, &dir).unwrap();
+    let step = std::fs::read_to_string(dir.join("pipeline_step.json")).unwrap();
+    assert!(step.contains("\"primary_metrics\":\"stdout\""));
+    assert!(step.contains("\"cell_metrics\":{\"file\":\"stdout\""));
+    assert!(!step.contains("nuclearqc.tsv"));
+}
+
+#[test]
+fn test_obs_csv_keeps_input_barcode_order() {
+    let mut input = build_input();
+    input.barcodes = Box::leak(Box::new(vec!["c2,x".to_string(), "c1".to_string()]));
+    input.emit_obs_csv = true;
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc_obs.csv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split(',').collect::<Vec<_>>();
+    assert_eq!(header[0], "index");
+    assert!(!header.iter().any(|c| c.starts_with("drivers")));
+    let regime_col = header.iter().position(|c| *c == "regime").unwrap();
+    let nps_col = header.iter().position(|c| *c == "c1_nps").unwrap();
+
+    let rows = lines.collect::<Vec<_>>();
+    assert_eq!(rows.len(), input.barcodes.len());
+    for (cell, row) in rows.iter().enumerate() {
+        let quoted = format!("\"{}\",", input.barcodes[cell]);
+        let rest = row
+            .strip_prefix(&quoted)
+            .or_else(|| row.strip_prefix(&format!("{},", input.barcodes[cell])))
+            .unwrap();
+        let fields = rest.split(',').collect::<Vec<_>>();
+        assert_eq!(fields[nps_col - 1], format_f32_6(input.scores.nps[cell]),);
+        assert_eq!(
+            fields[regime_col - 1],
+            regime_name(input.classifications[cell].regime)
+        );
+    }
+}
+
+#[test]
+fn test_output_prefix_renames_artifacts() {
+    let mut input = build_input();
+    input.output_prefix = "run1_".to_string();
+    input.pipeline_context = Some(PipelineContext {
+        input_dir: "/tmp/input".to_string(),
+        input_source: "10x".to_string(),
+        shared_bin: None,
+        run_mode: "pipeline".to_string(),
+        emit_step_json: false,
+        input_hashes: Vec::new(),
+    });
+
+    let dir = make_temp_dir();
+    write_reports_with_step(&input, &dir);
+    let mut names = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "run1_nuclearqc.tsv",
+            "run1_panels_report.tsv",
+            "run1_performance.json",
+            "run1_pipeline_step.json",
+            "run1_report.txt",
+            "run1_summary.json",
+        ]
+    );
+
+    let step = std::fs::read_to_string(dir.join("run1_pipeline_step.json")).unwrap();
+    assert!(step.contains("\"summary\":\"run1_summary.json\""));
+    assert!(step.contains("\"primary_metrics\":\"run1_nuclearqc.tsv\""));
+    assert!(step.contains("\"file\":\"run1_nuclearqc.tsv\""));
+}
+
+#[test]
+fn test_panels_report_species_mappability() {
+    let input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("panels_report.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap();
+    assert!(header.ends_with("\tweighted\trules\tspecies\tmappable_fraction"));
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .ends_with("\tfalse\t\tHuman\t1.000000")
+    );
+}
+
+#[test]
+fn test_pct_mito_column_and_summary() {
+    let input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = header.iter().position(|h| *h == "pct_mito").unwrap();
+    let row = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    assert_eq!(row[col], "0.050000");
+
+    let json = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(json.contains("\"pct_mito_median\":"));
+    assert!(json.contains("\"pct_mito_p90\":0.275000"));
+}
+
+fn test_provenance() -> RunProvenance {
+    RunProvenance {
+        args: vec![
+            "run".to_string(),
+            "--input".to_string(),
+            "in".to_string(),
+            "--strict-nuclear".to_string(),
+        ],
+        input_dir: "in".to_string(),
+        out_dir: "out".to_string(),
+        meta_path: None,
+        cache_path: None,
+        panels_path: None,
+        run_mode: "standalone".to_string(),
+        report_mode: "cell".to_string(),
+        scoring_mode: "strict".to_string(),
+        confidence_model: "multiplicative".to_string(),
+        thresholds_profile: "default_v1".to_string(),
+        normalize_mode: "log".to_string(),
+        cache_normalized: false,
+        cache_codec: "deflate".to_string(),
+        assume_transposed: false,
+    }
+}
+
+#[test]
+fn test_provenance_json_records_modes() {
+    let mut input = build_input();
+    input.provenance = Some(test_provenance());
+
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let first = std::fs::read_to_string(dir.join("provenance.json")).unwrap();
+    assert!(first.contains("\"run_mode\":\"standalone\""));
+    assert!(first.contains("\"scoring_mode\":\"strict\""));
+    assert!(first.contains("\"confidence_model\":\"multiplicative\""));
+    assert!(first.contains("\"args\":[\"run\",\"--input\",\"in\",\"--strict-nuclear\"]"));
+    assert!(first.contains("\"meta\":null"));
+    assert!(first.contains("\"cache_codec\":\"deflate\""));
+
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let second = std::fs::read_to_string(dir.join("provenance.json")).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_silence_reason_column() {
+    let mut input = build_input();
+    input.classifications = Box::leak(Box::new(vec![
+        crate::pipeline::stage6_classify::Classification {
+            regime: NuclearRegime::Unclassified,
+            flags: vec![Flag::ModelLimitation],
+            near_miss: None,
+        },
+        crate::pipeline::stage6_classify::Classification {
+            regime: NuclearRegime::Unclassified,
+            flags: vec![Flag::BiologicalSilence],
+            near_miss: None,
+        },
+    ]));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = header.iter().position(|h| *h == "silence_reason").unwrap();
+    let values = lines
+        .map(|l| l.split('\t').nth(col).unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec!["model_limitation", "biological_silence"]);
+
+    let plain = build_input();
+    write_reports(&plain, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let row = text.lines().nth(1).unwrap().split('\t').collect::<Vec<_>>();
+    assert_eq!(row[col], "");
+}
+
+#[test]
+fn test_axis_driver_columns() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    assert!(text.lines().next().unwrap().ends_with("\tcc_phase"));
+
+    let drivers = (0..2)
+        .map(|i| AxisDrivers {
+            axis_variance: 0.01 + i as f32,
+            gene_entropy: 2.5 + i as f32,
+            panel_entropy: 0.75,
+            tf_entropy: 1.25,
+            max_program_share: 0.4 + 0.1 * i as f32,
+            ..AxisDrivers::default()
+        })
+        .collect::<Vec<_>>();
+    input.axis_drivers = Some(Box::leak(Box::new(drivers.clone())));
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    for (line, d) in lines.zip(&drivers) {
+        let row = line.split('\t').collect::<Vec<_>>();
+        assert_eq!(row.len(), header.len());
+        assert_eq!(row[col("axis_variance")], format_f32_6(d.axis_variance));
+        assert_eq!(row[col("gene_entropy")], format_f32_6(d.gene_entropy));
+        assert_eq!(row[col("panel_entropy")], format_f32_6(d.panel_entropy));
+        assert_eq!(row[col("tf_entropy")], format_f32_6(d.tf_entropy));
+        assert_eq!(
+            row[col("max_program_share")],
+            format_f32_6(d.max_program_share)
+        );
+    }
+}
+
+#[test]
+fn test_confidence_breakdown_columns() {
+    let mut input = build_input();
+    let breakdown = vec![[0.9, 0.4, 0.7, 0.6], [0.2, 0.1, 0.3, 0.5]];
+    input.confidence_breakdown = Some(Box::leak(Box::new(breakdown.clone())));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    assert!(!text.lines().next().unwrap().contains("conf_"));
+
+    input.confidence_breakdown_columns = Some(ConfidenceModel::AdditiveImmune.breakdown_columns());
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    let names = [
+        "conf_panel_coverage",
+        "conf_expr_support",
+        "conf_axis_structure",
+        "conf_consistency",
+    ];
+    for (line, values) in lines.zip(&breakdown) {
+        let row = line.split('\t').collect::<Vec<_>>();
+        assert_eq!(row.len(), header.len());
+        for (name, value) in names.iter().zip(values) {
+            assert_eq!(row[col(name)], format_f32_6(*value));
+        }
+    }
+
+    // The multiplicative model's terms get their own names and no fourth column.
+    input.confidence_breakdown_columns =
+        Some(ConfidenceModel::LegacyMultiplicative.breakdown_columns());
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let header = text.lines().next().unwrap();
+    assert!(
+        header.ends_with("\tconf_key_coverage_term\tconf_expr_fraction_term\tconf_ambient_term")
+    );
+    assert!(!header.contains("conf_consistency"));
+}
+
+#[test]
+fn test_axis_percentile_columns() {
+    let mut input = build_input();
+    input.axis_percentiles = true;
+    input.axes_trs = Box::leak(Box::new(vec![0.5, 0.5]));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    assert_eq!(col("a1_tbi_pct"), col("a8_cea") + 1);
+    assert_eq!(col("c1_nps"), col("a8_cea_pct") + 1);
+
+    let rows = lines
+        .map(|l| l.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(rows[0][col("a1_tbi_pct")], "0.500000");
+    assert_eq!(rows[1][col("a1_tbi_pct")], "1.000000");
+    // Tied cells share the mean of ranks 1 and 2.
+    assert_eq!(rows[0][col("a4_trs_pct")], "0.750000");
+    assert_eq!(rows[1][col("a4_trs_pct")], "0.750000");
+    assert!(rows.iter().all(|row| row.len() == header.len()));
+
+    write_reports(&input, &dir, ReportMode::Sample).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let row = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    assert_eq!(header.last(), Some(&"a8_cea_pct_median"));
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    assert_eq!(row[col("a1_tbi_pct_median")], "0.750000");
+    assert_eq!(row[col("a4_trs_pct_median")], "0.750000");
+}
+
+#[test]
+fn test_entropy_unit_bits() {
+    let mut input = build_input();
+    let drivers = (0..2)
+        .map(|i| AxisDrivers {
+            gene_entropy: (4.0f32 * (i + 1) as f32).ln(),
+            panel_entropy: 0.5,
+            tf_entropy: std::f32::consts::LN_2,
+            ..AxisDrivers::default()
+        })
+        .collect::<Vec<_>>();
+    input.axis_drivers = Some(Box::leak(Box::new(drivers.clone())));
+    input.entropy_unit = EntropyUnit::Bits;
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    let value = |row: &[&str], name: &str| row[col(name)].parse::<f32>().unwrap();
+    for (line, d) in lines.zip(&drivers) {
+        let row = line.split('\t').collect::<Vec<_>>();
+        for (name, nats) in [
+            ("gene_entropy", d.gene_entropy),
+            ("panel_entropy", d.panel_entropy),
+            ("tf_entropy", d.tf_entropy),
+        ] {
+            assert!((value(&row, name) - nats / std::f32::consts::LN_2).abs() < 1e-5);
+        }
+        assert_eq!(value(&row, "tf_entropy"), 1.0);
+    }
+    let summary = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(summary.contains("\"entropy_unit\":\"bits\""));
+
+    assert_eq!(EntropyUnit::Nats.from_nats(1.5), 1.5);
+    assert_eq!(EntropyUnit::Bits.from_nats(4.0f32.ln()), 2.0);
+}
+
+#[test]
+fn test_custom_composite_column_and_summary() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    input.custom_composite = Some((
+        "0.4*tbi+0.3*rci".to_string(),
+        Box::leak(Box::new(vec![0.25, 0.75])),
+    ));
+    input.axis_drivers = Some(Box::leak(Box::new(vec![AxisDrivers::default(); 2])));
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let col = header.iter().position(|h| *h == "c4_custom").unwrap();
+    assert_eq!(header[col - 1], "cc_phase");
+    assert_eq!(header[col + 1], "axis_variance");
+    let values = lines
+        .map(|line| line.split('\t').nth(col).unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(values, ["0.250000", "0.750000"]);
+
+    let json = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(json.contains("\"custom_expr\":\"0.4*tbi+0.3*rci\""));
+    assert!(json.contains("\"custom_median\":"));
+    assert!(json.contains("{\"name\":\"c4_custom\""));
+}
+
+#[test]
+fn test_summary_diagnostics_excluded_panels() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let json = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(json.contains("\"diagnostics\":{\"excluded_panels\":[],\"unclassified_blockers\":[]}"));
+
+    input.excluded_panels = Box::leak(Box::new(vec![LowMappabilityPanel {
+        panel_id: "tf_basic".to_string(),
+        group: PanelGroup::Tf,
+        mappable_fraction: 0.25,
+        min_fraction: 0.5,
+        missing_genes: vec!["SP1".to_string(), "YY1".to_string()],
+    }]));
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let json = std::fs::read_to_string(dir.join("summary.json")).unwrap();
+    assert!(json.contains(
+        "\"excluded_panels\":[{\"panel_id\":\"tf_basic\",\"group\":\"tf\",\
+         \"mappable_fraction\":0.250000,\"min_mappable_fraction\":0.500000,\
+         \"missing_genes\":[\"SP1\",\"YY1\"]}]"
+    ));
+}
+
+#[test]
+fn test_panel_detection_columns() {
+    let mut input = build_input();
+    let dir = make_temp_dir();
+    let panel = |id: &str, group, genes: Vec<u32>| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group,
+        genes,
+        weights: Vec::new(),
+        missing: vec![],
+    };
+    let panels = PanelSet {
+        panels: vec![
+            panel("tf_basic", PanelGroup::Tf, vec![4, 5]),
+            panel("p1", PanelGroup::Program, vec![6]),
+            panel(
+                "housekeeping_core",
+                PanelGroup::Housekeeping,
+                vec![0, 1, 2, 3],
+            ),
+        ],
+    };
+    // c1 expresses two of the four housekeeping genes, c2 all of them.
+    let panel_scores = PanelScores {
+        panel_sum: vec![vec![1.0, 1.0, 2.0], vec![0.0, 2.0, 4.0]],
+        panel_mean: vec![vec![0.5, 1.0, 0.5], vec![0.0, 2.0, 1.0]],
+        panel_raw_sum: vec![vec![1.0, 1.0, 2.0], vec![0.0, 2.0, 4.0]],
+        panel_detected: vec![vec![1, 1, 2], vec![0, 1, 4]],
+        panel_coverage: vec![vec![0.5, 1.0, 0.5], vec![0.0, 1.0, 1.0]],
+    };
+    let mut thresholds = ThresholdProfile::immune_v1();
+    thresholds.key_panels = vec![
+        "tf_basic".to_string(),
+        "housekeeping_core".to_string(),
+        "not_a_panel".to_string(),
+    ];
+    input.panel_set = Box::leak(Box::new(panels));
+    input.panel_scores = Box::leak(Box::new(panel_scores));
+    input.thresholds = Box::leak(Box::new(thresholds));
+    input.emit_panel_detection = true;
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap();
+    assert!(header.ends_with("\tcc_phase\tdet_housekeeping_core\tdet_tf_basic"));
+    let rows = lines
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(rows[0][0], "c1");
+    assert_eq!(rows[0][rows[0].len() - 2..], ["2", "1"]);
+    assert_eq!(rows[1][rows[1].len() - 2..], ["4", "0"]);
+}
+
+#[test]
+fn test_sample_tsv_dispersion_columns() {
+    let mut input = build_input();
+    input.axes_tbi = Box::leak(Box::new(vec![0.2, 0.6]));
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Sample).unwrap();
+    let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+    let mut lines = text.lines();
+    let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    let row = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+    assert_eq!(row.len(), header.len());
+    let tbi = header.iter().position(|h| *h == "a1_tbi_median").unwrap();
+    assert_eq!(
+        &header[tbi..tbi + 8],
+        [
+            "a1_tbi_median",
+            "a1_tbi_p90",
+            "a1_tbi_p99",
+            "a1_tbi_mean",
+            "a1_tbi_std",
+            "a1_tbi_p25",
+            "a1_tbi_p75",
+            "a1_tbi_iqr",
+        ]
+    );
+    assert_eq!(header[tbi + 8], "a2_rci_median");
+    let value = |name: &str| {
+        let idx = header.iter().position(|h| *h == name).unwrap();
+        row[idx].parse::<f32>().unwrap()
+    };
+    assert!((value("a1_tbi_mean") - 0.4).abs() < 1e-6);
+    assert!((value("a1_tbi_std") - 0.2).abs() < 1e-6);
+    assert!((value("a1_tbi_p25") - 0.3).abs() < 1e-6);
+    assert!((value("a1_tbi_p75") - 0.5).abs() < 1e-6);
+    assert!((value("a1_tbi_iqr") - 0.2).abs() < 1e-6);
+    assert!((value("a2_rci_std") - 0.05).abs() < 1e-6);
+}
+
+#[test]
+fn test_top_program_panel_ties_break_by_id() {
+    let panel = |id: &str| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group: crate::panels::defs::PanelGroup::Program,
+        genes: vec![0],
+        weights: Vec::new(),
+        missing: vec![],
+    };
+    let scores = PanelScores {
+        panel_sum: vec![vec![2.0, 2.0, 1.0]],
+        ..PanelScores::default()
+    };
+    let forward = PanelSet {
+        panels: vec![panel("beta"), panel("alpha"), panel("gamma")],
+    };
+    let (id, share) = top_program_panel(0, &[0, 1, 2], &forward, &scores, PanelScoreMode::Sum);
+    assert_eq!(id, "alpha");
+    assert!((share - 0.4).abs() < 1e-6);
+
+    let reversed = PanelSet {
+        panels: vec![panel("alpha"), panel("beta"), panel("gamma")],
+    };
+    assert_eq!(
+        top_program_panel(0, &[0, 1, 2], &reversed, &scores, PanelScoreMode::Sum).0,
+        "alpha"
+    );
+    assert_eq!(
+        top_program_panel(0, &[1, 0, 2], &reversed, &scores, PanelScoreMode::Sum).0,
+        "alpha"
+    );
+
+    let counts = BTreeMap::from([("PlasticAdaptive", 3), ("Unclassified", 3)]);
+    assert_eq!(
+        majority_regime(&counts, &["Unclassified", "PlasticAdaptive"]),
+        "Unclassified"
+    );
+}
+
+// A 10-gene panel outsums a 2-gene one at a lower per-gene level.
+fn size_imbalanced_programs() -> (PanelSet, PanelScores) {
+    let panel = |id: &str, genes: u32| Panel {
+        id: id.to_string(),
+        name: id.to_string(),
+        group: crate::panels::defs::PanelGroup::Program,
+        genes: (0..genes).collect(),
+        weights: Vec::new(),
+        missing: vec![],
+    };
+    let panels = PanelSet {
+        panels: vec![panel("large", 10), panel("small", 2)],
+    };
+    let scores = PanelScores {
+        panel_sum: vec![vec![5.0, 3.0]],
+        panel_mean: vec![vec![0.5, 1.5]],
+        ..PanelScores::default()
+    };
+    (panels, scores)
+}
+
+#[test]
+fn test_top_program_panel_mean_mode_corrects_size_bias() {
+    let (panels, scores) = size_imbalanced_programs();
+    let (id, share) = top_program_panel(0, &[0, 1], &panels, &scores, PanelScoreMode::Sum);
+    assert_eq!(id, "large");
+    assert!((share - 5.0 / 8.0).abs() < 1e-6);
+
+    let (id, share) = top_program_panel(0, &[0, 1], &panels, &scores, PanelScoreMode::Mean);
+    assert_eq!(id, "small");
+    assert!((share - 0.75).abs() < 1e-6);
+}
+
+#[test]
+fn test_program_shares_tsv() {
+    let (panels, scores) = size_imbalanced_programs();
+    let panels: &'static PanelSet = Box::leak(Box::new(panels));
+    let scores = PanelScores {
+        panel_sum: vec![vec![5.0, 3.0], vec![0.0, 0.0]],
+        panel_mean: vec![vec![0.5, 1.5], vec![0.0, 0.0]],
+        ..scores
+    };
+    let mut input = build_input();
+    input.panel_set = panels;
+    input.panel_scores = Box::leak(Box::new(scores));
+
+    let path = make_temp_dir().join("program_shares.tsv");
+    write_program_shares(&input, &path).unwrap();
+    let tsv = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        tsv,
+        "barcode\tlarge\tsmall\nc1\t0.625000\t0.375000\nc2\t0.000000\t0.000000\n"
+    );
+}
+
+#[test]
+fn test_sample_qc_pass() {
+    let mut input = build_input();
+    let qc = |input: &Stage7Input<'_>| {
+        let dir = make_temp_dir();
+        write_reports(input, &dir, ReportMode::Sample).unwrap();
+        let text = std::fs::read_to_string(dir.join("nuclearqc.tsv")).unwrap();
+        let mut lines = text.lines();
+        let header = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+        let row = lines.next().unwrap().split('\t').collect::<Vec<_>>();
+        assert_eq!(
+            header[header.len() - 2..],
+            ["low_confidence_fraction", "qc_pass"]
+        );
+        assert_eq!(row[row.len() - 2], "0.500000");
+        row[row.len() - 1].to_string()
+    };
+
+    // One of the two cells is LOW_CONFIDENCE, well above the default 0.2.
+    input.qc_gates = QcGates {
+        min_cells: 2,
+        ..QcGates::default()
+    };
+    assert_eq!(qc(&input), "false");
+
+    input.qc_gates.max_low_confidence_fraction = 0.6;
+    assert_eq!(qc(&input), "true");
+
+    input.qc_gates.min_cells = 3;
+    assert_eq!(qc(&input), "false");
+
+    let summary = render_summary_json(&build_summary(&input, ReportMode::Sample));
+    assert!(summary.contains(
+        "\"qc_gates\":{\"max_low_confidence_fraction\":0.600000,\"min_cells\":3.000000}"
+    ));
+}
+
+#[test]
+fn test_summary_reports_species_call() {
+    let input = build_input();
+    let json = render_summary_json(&build_summary(&input, ReportMode::Cell));
+    assert!(json.contains(
+        "\"species\":\"Human\",\"species_call\":{\"human_score\":0.900000,\"mouse_score\":0.000000,\"confidence\":1.000000},"
+    ));
+}
+
+#[test]
+fn test_axis_correlations() {
+    let mut input = build_input();
+    let tbi: &'static [f32] = &[0.1, 0.2, 0.3, 0.4];
+    let rci: &'static [f32] = &[0.3, 0.5, 0.7, 0.9];
+    let pds: &'static [f32] = &[0.4, 0.1, 0.3, 0.2];
+    let flat: &'static [f32] = &[0.5; 4];
+    input.axes_tbi = tbi;
+    input.axes_rci = rci;
+    input.axes_pds = pds;
+    for axis in [
+        &mut input.axes_trs,
+        &mut input.axes_nsai,
+        &mut input.axes_iaa,
+        &mut input.axes_dfa,
+        &mut input.axes_cea,
+        &mut input.ddr_rss,
+        &mut input.ddr_drbi,
+        &mut input.ddr_cci,
+        &mut input.ddr_trci,
+    ] {
+        *axis = flat;
+    }
+
+    let corr = axis_correlations(&input);
+    assert_eq!(corr.len(), 12);
+    assert!((corr[0][1] - 1.0).abs() < 1e-6);
+    assert_eq!(corr[0][1], corr[1][0]);
+    assert!((corr[0][0] - 1.0).abs() < 1e-6);
+    assert!((corr[0][2] + 0.4).abs() < 1e-6);
+    assert!(corr[3].iter().all(|&r| r == 0.0));
+
+    let dir = make_temp_dir();
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    assert!(!dir.join("axis_correlations.tsv").exists());
+    input.emit_axis_correlations = true;
+    write_reports(&input, &dir, ReportMode::Cell).unwrap();
+    let text = std::fs::read_to_string(dir.join("axis_correlations.tsv")).unwrap();
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 13);
+    assert_eq!(
+        lines[0],
+        "axis\ttbi\trci\tpds\ttrs\tnsai\tiaa\tdfa\tcea\trss\tdrbi\tcci\ttrci"
+    );
+    assert!(lines[1].starts_with("tbi\t1.000000\t1.000000\t-0.400000\t0.000000"));
+}
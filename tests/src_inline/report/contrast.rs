@@ -0,0 +1,147 @@
+use super::*;
+
+fn meta(values: &[&str]) -> CellMeta {
+    CellMeta {
+        columns: vec!["barcode_rank".to_string(), "Condition".to_string()],
+        rows: values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| vec![i.to_string(), v.to_string()])
+            .collect(),
+    }
+}
+
+#[test]
+fn test_mann_whitney_exact_without_ties() {
+    // Complete separation: U = 0, and 2 of the C(6, 3) = 20 orderings are
+    // as extreme.
+    let test = mann_whitney(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+    assert_eq!(test.u, 0.0);
+    assert_eq!(test.cliffs_delta, -1.0);
+    assert!(test.exact);
+    assert!((test.p_value - 0.1).abs() < 1e-12);
+
+    // U = 2 for 5 vs 5: P(U <= 2) = (1 + 1 + 2) / 252 on each side.
+    let test = mann_whitney(&[1.0, 2.0, 3.0, 4.0, 7.0], &[5.0, 6.0, 8.0, 9.0, 10.0]);
+    assert_eq!(test.u, 2.0);
+    assert!((test.cliffs_delta - (2.0 * 2.0 / 25.0 - 1.0)).abs() < 1e-12);
+    assert!((test.p_value - 8.0 / 252.0).abs() < 1e-12);
+
+    let swapped = mann_whitney(&[5.0, 6.0, 8.0, 9.0, 10.0], &[1.0, 2.0, 3.0, 4.0, 7.0]);
+    assert_eq!(swapped.u, 23.0);
+    assert_eq!(swapped.p_value, test.p_value);
+    assert!((swapped.cliffs_delta + test.cliffs_delta).abs() < 1e-12);
+}
+
+#[test]
+fn test_mann_whitney_ties_use_midranks() {
+    // Ranks 1, 3, 3 for a (the three 2s share ranks 2-4): U = 7 - 6 = 1,
+    // the same as counting pairs with ties as one half.
+    let test = mann_whitney(&[1.0, 2.0, 2.0], &[2.0, 3.0, 4.0]);
+    assert_eq!(test.u, 1.0);
+    assert!((test.cliffs_delta - (2.0 / 9.0 - 1.0)).abs() < 1e-12);
+    assert!(!test.exact);
+    // var = 9/12 * (7 - 24/30) = 4.65, z = (8 - 4.5 - 0.5) / sqrt(4.65).
+    assert!((test.p_value - 0.164_159_73).abs() < 1e-6);
+
+    let all_tied = mann_whitney(&[0.5, 0.5], &[0.5, 0.5, 0.5]);
+    assert_eq!(all_tied.u, 3.0);
+    assert_eq!(all_tied.cliffs_delta, 0.0);
+    assert_eq!(all_tied.p_value, 1.0);
+
+    assert_eq!(mann_whitney(&[], &[1.0]).p_value, 1.0);
+}
+
+#[test]
+fn test_mann_whitney_normal_approximation_for_large_groups() {
+    let a = (0..30).map(|i| i as f32).collect::<Vec<_>>();
+    let b = (0..30).map(|i| i as f32 + 10.5).collect::<Vec<_>>();
+    let test = mann_whitney(&a, &b);
+    assert!(!test.exact);
+    // a[i] > b[j] when i >= j + 11: 19 + 18 + ... + 1 pairs.
+    assert_eq!(test.u, 190.0);
+    let z = (710.0f64 - 450.0 - 0.5) / (900.0f64 * 61.0 / 12.0).sqrt();
+    let expected = erfc(z / std::f64::consts::SQRT_2);
+    assert!((test.p_value - expected).abs() < 1e-12);
+    assert!(test.p_value < 0.001);
+}
+
+#[test]
+fn test_u_distribution_counts_orderings() {
+    assert_eq!(u_distribution(2, 2), vec![1, 1, 2, 1, 1]);
+    assert_eq!(u_distribution(1, 3), vec![1, 1, 1, 1]);
+    assert_eq!(u_distribution(20, 20).iter().sum::<u64>(), 137_846_528_820);
+}
+
+#[test]
+fn test_contrast_design_levels_and_reference() {
+    let design = ContrastDesign::new(
+        &meta(&["treated", "control", "NA", "treated", ""]),
+        "condition",
+        None,
+    )
+    .unwrap();
+    assert_eq!(design.column, "Condition");
+    assert_eq!(design.reference, "control");
+    assert_eq!(design.levels, vec!["treated".to_string()]);
+    assert_eq!(
+        design.cell_level,
+        vec![Some(0), Some(1), None, Some(0), None]
+    );
+
+    let three = meta(&["a", "b", "c", "b"]);
+    assert_eq!(
+        ContrastDesign::new(&three, "condition", None).unwrap_err(),
+        "--contrast condition has 3 levels; choose one with --contrast-reference"
+    );
+    let design = ContrastDesign::new(&three, "condition", Some("b")).unwrap();
+    assert_eq!(design.levels, vec!["a".to_string(), "c".to_string()]);
+    assert_eq!(design.cell_level, vec![Some(0), Some(2), Some(1), Some(2)]);
+    assert_eq!(
+        ContrastDesign::new(&three, "condition", Some("d")).unwrap_err(),
+        "--contrast-reference d is not a level of condition (levels: a, b, c)"
+    );
+    assert_eq!(
+        ContrastDesign::new(&meta(&["a", "a"]), "condition", None).unwrap_err(),
+        "--contrast condition: needs at least two levels, found 1"
+    );
+    assert_eq!(
+        ContrastDesign::new(&three, "batch", None).unwrap_err(),
+        "--contrast batch: no such metadata column"
+    );
+}
+
+#[test]
+fn test_contrast_metrics_per_level() {
+    let design = ContrastDesign::new(
+        &meta(&["a", "ref", "b", "ref", "a"]),
+        "condition",
+        Some("ref"),
+    )
+    .unwrap();
+    let nps = [0.9, 0.1, 0.5, 0.3, 0.7];
+    let ci = [0.0, 0.0, 0.0, 0.0, 0.0];
+    let contrasts = contrast_metrics(
+        &design,
+        &[("c1_nps", &nps), ("c2_ci", &ci)],
+        QuantileMethod::Linear,
+    );
+    let keys = contrasts
+        .iter()
+        .map(|c| (c.level.as_str(), c.metric, c.n_level, c.n_reference))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            ("a", "c1_nps", 2, 2),
+            ("a", "c2_ci", 2, 2),
+            ("b", "c1_nps", 1, 2),
+            ("b", "c2_ci", 1, 2)
+        ]
+    );
+    assert!((contrasts[0].median_diff - 0.6).abs() < 1e-6);
+    assert_eq!(contrasts[0].test.cliffs_delta, 1.0);
+    assert!((contrasts[2].median_diff - 0.3).abs() < 1e-6);
+    assert_eq!(contrasts[2].test.u, 2.0);
+    assert_eq!(contrasts[1].test.cliffs_delta, 0.0);
+}
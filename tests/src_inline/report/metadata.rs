@@ -0,0 +1,85 @@
+use super::*;
+
+fn column(values: &[&str]) -> MetaColumnSummary {
+    summarize_column("col", values.iter().copied(), QuantileMethod::Linear)
+}
+
+#[test]
+fn test_numeric_column() {
+    let summary = column(&["34", "51.5", "", "NA", "29", "34"]);
+    assert_eq!(summary.missing_fraction, 2.0 / 6.0);
+    assert_eq!(summary.cardinality, 3);
+    assert_eq!(
+        summary.kind,
+        MetaColumnKind::Numeric {
+            min: 29.0,
+            median: 34.0,
+            max: 51.5
+        }
+    );
+}
+
+#[test]
+fn test_one_text_value_makes_column_categorical() {
+    let summary = column(&["1", "2", "2", "two", "2", "1", "3"]);
+    assert_eq!(summary.cardinality, 4);
+    assert_eq!(summary.missing_fraction, 0.0);
+    assert_eq!(
+        summary.kind,
+        MetaColumnKind::Categorical {
+            top: vec![
+                ("2".to_string(), 3),
+                ("1".to_string(), 2),
+                ("3".to_string(), 1),
+                ("two".to_string(), 1),
+            ]
+        }
+    );
+
+    // Non-finite numbers are text too.
+    assert_eq!(column(&["1", "inf"]).kind.as_str(), "categorical");
+}
+
+#[test]
+fn test_empty_column_is_categorical_with_no_values() {
+    let summary = column(&["", " ", "NA"]);
+    assert_eq!(summary.missing_fraction, 1.0);
+    assert_eq!(summary.cardinality, 0);
+    assert_eq!(
+        summary.kind,
+        MetaColumnKind::Categorical { top: Vec::new() }
+    );
+
+    let summary = column(&[]);
+    assert_eq!(summary.missing_fraction, 0.0);
+    assert_eq!(summary.kind.as_str(), "categorical");
+}
+
+#[test]
+fn test_top_categories_are_capped() {
+    let values = ["a", "b", "b", "c", "d", "e", "f", "f", "f"];
+    let MetaColumnKind::Categorical { top } = column(&values).kind else {
+        panic!("expected categorical");
+    };
+    assert_eq!(top.len(), TOP_CATEGORIES);
+    assert_eq!(top[0], ("f".to_string(), 3));
+    assert_eq!(top[1], ("b".to_string(), 2));
+    assert_eq!(top[4].0, "d");
+}
+
+#[test]
+fn test_summarize_meta_keeps_column_order() {
+    let meta = CellMeta {
+        columns: vec!["sample".to_string(), "age".to_string()],
+        rows: vec![
+            vec!["S1".to_string(), "40".to_string()],
+            vec!["S2".to_string()],
+        ],
+    };
+    let summaries = summarize_meta(&meta, QuantileMethod::Linear);
+    assert_eq!(summaries[0].name, "sample");
+    assert_eq!(summaries[0].kind.as_str(), "categorical");
+    assert_eq!(summaries[1].name, "age");
+    assert_eq!(summaries[1].missing_fraction, 0.5);
+    assert_eq!(summaries[1].kind.as_str(), "numeric");
+}
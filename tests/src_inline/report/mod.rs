@@ -1,9 +1,68 @@
 use super::*;
 
+const LINEAR: QuantileMethod = QuantileMethod::Linear;
+
 #[test]
 fn test_quantiles() {
     let v = vec![1.0f32, 2.0, 3.0, 4.0, 5.0];
-    assert_eq!(median(&v), 3.0);
-    assert_eq!(p90(&v), 5.0);
-    assert_eq!(p99(&v), 5.0);
+    assert_eq!(median(&v, LINEAR), 3.0);
+    assert!((p10(&v, LINEAR) - 1.4).abs() < 1e-6);
+    assert!((p90(&v, LINEAR) - 4.6).abs() < 1e-6);
+    assert!((p99(&v, LINEAR) - 4.96).abs() < 1e-6);
+}
+
+#[test]
+fn test_quantiles_even_median_interpolates() {
+    let v = vec![4.0f32, 1.0, 3.0, 2.0];
+    assert_eq!(median(&v, LINEAR), 2.5);
+}
+
+#[test]
+fn test_batched_quantiles_match_individual_calls() {
+    let v = [0.7f32, 0.1, 0.35, 0.9, 0.35, 0.0, 0.55, 1.0, 0.2, 0.8, 0.45];
+    let probs = [0.1, 0.5, 0.9, 0.99];
+    let batched = quantiles(&v, &probs, LINEAR);
+    assert_eq!(batched, probs.map(|p| quantile_indexed(&v, p, LINEAR)));
+    assert_eq!(
+        batched,
+        [
+            p10(&v, LINEAR),
+            median(&v, LINEAR),
+            p90(&v, LINEAR),
+            p99(&v, LINEAR)
+        ]
+    );
+    assert_eq!(quantiles(&[], &probs, LINEAR), [0.0; 4]);
+    assert_eq!(quantiles(&v, &[], LINEAR), [0.0f32; 0]);
+}
+
+#[test]
+fn test_percentile_ranks_average_ties() {
+    let ranks = percentile_ranks(&[0.4, 0.1, 0.4, 0.9, 0.4]);
+    // 0.1 ranks 1st, the three 0.4s share ranks 2-4, 0.9 ranks 5th.
+    assert_eq!(ranks, vec![0.6, 0.2, 0.6, 1.0, 0.6]);
+    assert_eq!(percentile_ranks(&[0.5, 0.5]), vec![0.75, 0.75]);
+    assert_eq!(percentile_ranks(&[0.0]), vec![1.0]);
+    assert!(percentile_ranks(&[]).is_empty());
+}
+
+#[test]
+fn test_legacy_quantiles() {
+    let sorted = [1.0f32, 2.0, 3.0, 4.0, 5.0];
+    assert_eq!(
+        quantile_sorted(&sorted, 0.5, QuantileMethod::LegacyCeil),
+        3.0
+    );
+    assert_eq!(
+        quantile_sorted(&sorted, 0.1, QuantileMethod::LegacyCeil),
+        2.0
+    );
+    assert_eq!(
+        quantile_sorted(&sorted, 0.9, QuantileMethod::LegacyCeil),
+        5.0
+    );
+    assert_eq!(
+        quantile_sorted(&sorted, 0.99, QuantileMethod::LegacyCeil),
+        5.0
+    );
 }
@@ -0,0 +1,145 @@
+use super::*;
+
+#[test]
+fn test_resolve_output_dir_pipeline() {
+    let out = resolve_output_dir(Path::new("/tmp/out"), RunMode::Pipeline);
+    assert_eq!(out, PathBuf::from("/tmp/out/kira-nuclearqc"));
+}
+
+#[test]
+fn test_resolve_output_dir_standalone() {
+    let out = resolve_output_dir(Path::new("/tmp/out"), RunMode::Standalone);
+    assert_eq!(out, PathBuf::from("/tmp/out"));
+}
+
+#[test]
+fn test_read_git_hash_layouts() {
+    let root = std::env::temp_dir().join(format!("kira_nuclearqc_git_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let loose_hash = "1111111111111111111111111111111111111111";
+    let packed_hash = "2222222222222222222222222222222222222222";
+
+    let loose = root.join("loose");
+    std::fs::create_dir_all(loose.join(".git/refs/heads")).unwrap();
+    std::fs::write(loose.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    std::fs::write(
+        loose.join(".git/refs/heads/main"),
+        format!("{loose_hash}\n"),
+    )
+    .unwrap();
+    assert_eq!(read_git_hash(&loose).as_deref(), Some(loose_hash));
+
+    let packed = root.join("packed");
+    std::fs::create_dir_all(packed.join(".git")).unwrap();
+    std::fs::write(packed.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    std::fs::write(
+        packed.join(".git/packed-refs"),
+        format!(
+            "# pack-refs with: peeled fully-peeled sorted\n\
+             {loose_hash} refs/heads/other\n\
+             {packed_hash} refs/heads/main\n\
+             ^3333333333333333333333333333333333333333\n"
+        ),
+    )
+    .unwrap();
+    assert_eq!(read_git_hash(&packed).as_deref(), Some(packed_hash));
+
+    // Worktree of `packed`: own HEAD, refs resolved through `commondir`.
+    let worktree = root.join("worktree");
+    let wt_git = packed.join(".git/worktrees/wt");
+    std::fs::create_dir_all(&wt_git).unwrap();
+    std::fs::create_dir_all(&worktree).unwrap();
+    std::fs::write(wt_git.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    std::fs::write(wt_git.join("commondir"), "../..\n").unwrap();
+    std::fs::write(
+        worktree.join(".git"),
+        format!("gitdir: {}\n", wt_git.display()),
+    )
+    .unwrap();
+    assert_eq!(read_git_hash(&worktree).as_deref(), Some(packed_hash));
+
+    std::fs::write(wt_git.join("HEAD"), format!("{loose_hash}\n")).unwrap();
+    assert_eq!(read_git_hash(&worktree).as_deref(), Some(loose_hash));
+
+    assert_eq!(read_git_hash(&root.join("missing")), None);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_warns_when_no_immune_gene_maps() {
+    let audit = |panel_id: &str, mappable: usize| PanelAudit {
+        panel_id: panel_id.to_string(),
+        panel_size_defined: 5,
+        panel_size_mappable: mappable,
+        missing_genes: Vec::new(),
+        rules: Vec::new(),
+    };
+    let warnings = crate::tracing::WarningScope::begin();
+    assert!(warn_unmapped_immune_panels(&[
+        audit("proliferation", 4),
+        audit("immune_activation", 0),
+        audit("clonal_engagement", 0),
+    ]));
+    assert_eq!(warnings.log().total(), 1);
+
+    // One mapped immune panel, or none selected at all, is not flagged.
+    assert!(!warn_unmapped_immune_panels(&[
+        audit("immune_activation", 0),
+        audit("clonal_engagement", 2),
+    ]));
+    assert!(!warn_unmapped_immune_panels(&[audit("proliferation", 0)]));
+}
+
+#[test]
+fn test_resolve_normalization_defaults_and_conflicts() {
+    let mut config = RunConfig::new("in", "out");
+    config.resolve_normalization(None, None).unwrap();
+    assert!(config.normalize);
+    assert_eq!(config.normalize_mode, NormalizeMode::LogCp10k);
+
+    config.profile = NamedProfile::Default;
+    config.resolve_normalization(None, None).unwrap();
+    assert!(!config.normalize);
+    config
+        .resolve_normalization(None, Some(NormalizeMode::PearsonResiduals))
+        .unwrap();
+    assert!(config.normalize);
+    assert_eq!(config.normalize_mode, NormalizeMode::PearsonResiduals);
+
+    let err = config
+        .resolve_normalization(Some(false), Some(NormalizeMode::PearsonResiduals))
+        .unwrap_err();
+    assert!(err.contains("--raw"), "{err}");
+}
+
+#[test]
+fn test_validate_rejects_conflicting_settings() {
+    let mut config = RunConfig::new("in", "out");
+    assert!(config.validate().is_ok());
+    config.only_panels = Some(vec!["proliferation_core".to_string()]);
+    config.disable_panels = vec!["stress_core".to_string()];
+    assert!(config.validate().unwrap_err().contains("--only-panels"));
+
+    let mut config = RunConfig::new("in", "out");
+    config.max_cells = Some(10);
+    config.chunk_cells = Some(10);
+    assert!(config.validate().unwrap_err().contains("--max-cells"));
+
+    let mut config = RunConfig::new("in", "out");
+    config.meta_by_order = true;
+    assert!(config.validate().unwrap_err().contains("--meta-by-order"));
+
+    let mut config = RunConfig::new("in", "out");
+    config.explain_panel = Some("a/b".to_string());
+    assert!(config.validate().unwrap_err().contains("path separator"));
+
+    let mut config = RunConfig::new("in.loom", "out");
+    config.input_format = InputFormat::Loom;
+    assert!(config.validate().is_ok());
+    config.chunk_cells = Some(10);
+    let err = config.validate().unwrap_err();
+    assert!(
+        err.contains("--chunk-cells") && err.contains("loom"),
+        "{err}"
+    );
+}
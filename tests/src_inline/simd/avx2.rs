@@ -1,20 +1,55 @@
 use super::*;
 use crate::simd::scalar;
 
+// Each test compares against scalar only where the CPU supports AVX2.
+
+#[test]
+fn test_kernels_follow_detection() {
+    assert_eq!(kernels().is_some(), is_x86_feature_detected!("avx2"));
+}
+
 #[test]
 fn test_sum_equiv() {
+    let Some(k) = kernels() else { return };
     let v = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 1.1, 2.2, 3.3];
-    assert_eq!(sum_f32_f64(&v), scalar::sum_f32_f64(&v));
+    assert_eq!((k.sum_f32_f64)(&v), scalar::sum_f32_f64(&v));
 }
 
 #[test]
-fn test_max_equiv() {
-    let v = [0.1f32, 2.0, 0.3, -4.0];
-    assert_eq!(max_f32(&v), scalar::max_f32(&v));
+fn test_stats_short_equiv() {
+    let Some(k) = kernels() else { return };
+    let v = [0.1f32, 2.0, 0.3, -4.0, 0.5, 0.6, 0.7, 0.8, 9.5, -0.2];
+    assert_eq!((k.stats_f32)(&v), (scalar::KERNELS.stats_f32)(&v));
+    assert_eq!((k.stats_f32)(&v).1, 9.5);
 }
 
 #[test]
 fn test_entropy_equiv() {
-    let v = [0.1f32, 0.2, 0.3, 0.4];
-    assert_eq!(entropy_f32(&v), scalar::entropy_f32(&v));
+    let Some(k) = kernels() else { return };
+    let v: Vec<f32> = (0..53).map(|i| (i % 7) as f32 * 0.37).collect();
+    let sum = scalar::sum_f32_f64(&v);
+    let expected = (scalar::KERNELS.entropy_f64)(&v, sum);
+    assert_eq!((k.entropy_f64)(&v, sum).to_bits(), expected.to_bits());
+}
+
+#[test]
+fn test_stats_equiv() {
+    let Some(k) = kernels() else { return };
+    let mut v: Vec<f32> = (0..45).map(|i| (i % 9) as f32 - 3.5).collect();
+    v[17] = f32::NAN;
+    v[40] = 12.0;
+    let expected = (scalar::KERNELS.stats_f32)(&v);
+    let got = (k.stats_f32)(&v);
+    assert_eq!(got.0.to_bits(), expected.0.to_bits());
+    assert_eq!(got.1.to_bits(), expected.1.to_bits());
+    assert_eq!(got.2, expected.2);
+    assert_eq!(got.1, 12.0);
+}
+
+#[test]
+fn test_min_equiv() {
+    let Some(k) = kernels() else { return };
+    let v = [0.1f32, 2.0, 0.3, -4.0, 0.5, 0.6, 0.7, 0.8, -0.2, 9.0];
+    assert_eq!((k.min_f32)(&v), scalar::min_f32(&v));
+    assert_eq!((k.min_f32)(&[]), 0.0);
 }
@@ -3,12 +3,19 @@ use super::*;
 #[test]
 fn test_backend_name() {
     let name = backend_name();
-    #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))]
-    assert_eq!(name, "avx2");
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(
+        name,
+        if is_x86_feature_detected!("avx2") {
+            "avx2"
+        } else {
+            "scalar"
+        }
+    );
     #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
     assert_eq!(name, "neon");
     #[cfg(not(any(
-        all(target_arch = "x86_64", target_feature = "avx2"),
+        target_arch = "x86_64",
         all(target_arch = "aarch64", target_feature = "neon"),
     )))]
     assert_eq!(name, "scalar");
@@ -26,6 +33,289 @@ fn test_sum_determinism() {
 fn test_backend_equiv_scalar() {
     let values = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 1.5];
     assert_eq!(sum_f32_f64(&values), scalar::sum_f32_f64(&values));
-    assert_eq!(max_f32(&values), scalar::max_f32(&values));
-    assert_eq!(entropy_f32(&values), scalar::entropy_f32(&values));
+    assert_eq!(
+        stats_f32(&values, SumMode::Blocked),
+        (sum_f32_f64(&values), 1.5, 7)
+    );
+    assert_eq!(min_f32(&values), scalar::min_f32(&values));
+    let sum = scalar::sum_f32_f64(&values);
+    let reference = values
+        .iter()
+        .map(|&v| v as f64 / sum)
+        .fold(0f64, |h, p| h - p * p.ln()) as f32;
+    let h = entropy_f32(&values, SumMode::Blocked);
+    assert_eq!(h, (scalar::KERNELS.entropy_f64)(&values, sum) as f32);
+    assert!((h - reference).abs() <= f32::EPSILON * reference);
+}
+
+#[test]
+fn test_min_empty_is_zero() {
+    assert_eq!(min_f32(&[]), 0.0);
+    assert_eq!(min_f32(&[f32::INFINITY]), 0.0);
+}
+
+#[test]
+fn test_mean_var() {
+    let values = [2.0f32, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    assert_eq!(mean_var_f32(&values, SumMode::Blocked), (5.0, 4.0));
+    assert_eq!(mean_var_f32(&[], SumMode::Pairwise), (0.0, 0.0));
+}
+
+#[test]
+fn test_pairwise_closer_to_reference() {
+    // 2^53 absorbs every following 1.0 when accumulated in order into f64.
+    let big = 9_007_199_254_740_992f32;
+    let n_small = 4096usize;
+    let mut values = vec![big];
+    values.extend(std::iter::repeat_n(1.0f32, n_small));
+    let reference = big as f64 + n_small as f64;
+
+    let blocked = sum_f32_f64_mode(&values, SumMode::Blocked);
+    let pairwise = sum_f32_f64_mode(&values, SumMode::Pairwise);
+    let err_blocked = (blocked - reference).abs();
+    let err_pair = (pairwise - reference).abs();
+    assert!(
+        err_pair < err_blocked,
+        "pairwise {err_pair} vs blocked {err_blocked}"
+    );
+
+    let again = sum_f32_f64_pairwise(&values);
+    assert_eq!(pairwise.to_bits(), again.to_bits());
+}
+
+#[test]
+fn test_blocked_sum_error_on_deep_cell() {
+    // One huge count followed by many tiny values: a running f64 total rounds
+    // every tiny term against 1e8, while the blocked tree adds small block
+    // sums to the large one only a handful of times.
+    let small = 1e-3f32;
+    let n_small = 1_000_000usize;
+    let mut values = vec![1e8f32];
+    values.extend(std::iter::repeat_n(small, n_small));
+    let reference = 1e8f64 + n_small as f64 * small as f64;
+
+    let running = values.iter().fold(0f64, |acc, &v| acc + v as f64);
+    let blocked = sum_f32_f64(&values);
+    let err_running = (running - reference).abs();
+    let err_blocked = (blocked - reference).abs();
+    assert!(err_blocked < 1e-6, "blocked error {err_blocked}");
+    assert!(
+        err_blocked * 1000.0 < err_running,
+        "blocked {err_blocked} vs running {err_running}"
+    );
+}
+
+#[test]
+fn test_blocked_sum_splits_on_block_boundaries() {
+    let values = random_values(5, 5 * SUM_BLOCK + 17);
+    let kernel = scalar::KERNELS.sum_f32_f64;
+    let blocks = values.chunks(SUM_BLOCK).map(kernel).collect::<Vec<_>>();
+    // Six blocks: ((b0 + (b1 + b2)) + (b3 + (b4 + b5))).
+    let left = blocks[0] + (blocks[1] + blocks[2]);
+    let right = blocks[3] + (blocks[4] + blocks[5]);
+    assert_eq!(sum_f32_f64(&values).to_bits(), (left + right).to_bits());
+    assert_eq!(
+        sum_f32_f64(&values[..SUM_BLOCK]).to_bits(),
+        kernel(&values[..SUM_BLOCK]).to_bits()
+    );
+}
+
+#[test]
+fn test_pairwise_short_input_matches_blocked() {
+    let values = [0.1f32, 0.2, 0.3, 0.4, 0.5, 0.6, 1.5];
+    assert_eq!(sum_f32_f64_pairwise(&values), sum_f32_f64(&values));
+}
+
+// xorshift64*: deterministic inputs without pulling in a rand dependency.
+fn random_values(seed: u64, len: usize) -> Vec<f32> {
+    let mut state = seed.max(1);
+    (0..len)
+        .map(|_| {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            let bits = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+            // Mix magnitudes so cancellation and absorption both show up.
+            let unit = (bits >> 40) as f32 / (1u64 << 24) as f32 - 0.5;
+            let scale = [1e-3f32, 1.0, 1e4, 1e7][(bits & 3) as usize];
+            unit * scale
+        })
+        .collect()
+}
+
+#[test]
+fn test_sum_backends_bitwise_random() {
+    let backend = kernels();
+    for case in 0..200u64 {
+        let len = (case as usize * 37) % 1500;
+        let values = random_values(case + 1, len);
+        let expected = (scalar::KERNELS.sum_f32_f64)(&values);
+        let got = (backend.sum_f32_f64)(&values);
+        assert_eq!(
+            got.to_bits(),
+            expected.to_bits(),
+            "{} len {len}: {got} vs {expected}",
+            backend.name
+        );
+    }
+}
+
+#[test]
+fn test_entropy_backends_bitwise_random() {
+    let backend = kernels();
+    for case in 0..200u64 {
+        let len = (case as usize * 41) % 1500;
+        let mut values = random_values(case + 1000, len);
+        // Entropy inputs are mostly non-negative; keep a few zeros and
+        // negatives so the masked lanes are exercised too.
+        for (i, v) in values.iter_mut().enumerate() {
+            if i % 5 != 0 {
+                *v = v.abs();
+            }
+        }
+        let sum = values.iter().map(|v| v.abs() as f64).sum::<f64>().max(1.0);
+        let expected = (scalar::KERNELS.entropy_f64)(&values, sum);
+        let got = (backend.entropy_f64)(&values, sum);
+        assert_eq!(
+            got.to_bits(),
+            expected.to_bits(),
+            "{} len {len}: {got} vs {expected}",
+            backend.name
+        );
+    }
+}
+
+#[test]
+fn test_stats_backends_bitwise_random() {
+    let backend = kernels();
+    for case in 0..200u64 {
+        let len = (case as usize * 29) % 1500;
+        let mut values = random_values(case + 2000, len);
+        if len > 10 {
+            values[len / 3] = f32::NAN;
+            values[len / 2] = -0.0;
+        }
+        let expected = (scalar::KERNELS.stats_f32)(&values);
+        let got = (backend.stats_f32)(&values);
+        assert_eq!(
+            got.0.to_bits(),
+            expected.0.to_bits(),
+            "{} sum",
+            backend.name
+        );
+        assert_eq!(
+            got.1.to_bits(),
+            expected.1.to_bits(),
+            "{} max",
+            backend.name
+        );
+        assert_eq!(got.2, expected.2, "{} count", backend.name);
+
+        for mode in [SumMode::Blocked, SumMode::Pairwise] {
+            let (sum, max, positive) = stats_f32(&values, mode);
+            let separate = sum_f32_f64_mode(&values, mode);
+            assert_eq!(sum.to_bits(), separate.to_bits(), "{mode:?} len {len}");
+            let reference = values
+                .iter()
+                .fold(f32::NEG_INFINITY, |m, &v| if v > m { v } else { m });
+            assert_eq!(
+                max,
+                if reference.is_finite() {
+                    reference
+                } else {
+                    0.0
+                }
+            );
+            assert_eq!(positive, values.iter().filter(|&&v| v > 0.0).count());
+        }
+    }
+}
+
+#[test]
+#[ignore = "benchmark; run with --release -- --ignored --nocapture"]
+fn bench_sum_1m() {
+    let values = random_values(7, 1 << 20);
+    let rounds = 200;
+    let time = |f: fn(&[f32]) -> f64| {
+        let start = std::time::Instant::now();
+        let mut acc = 0f64;
+        for _ in 0..rounds {
+            acc += f(std::hint::black_box(&values));
+        }
+        (start.elapsed().as_secs_f64() * 1e6 / rounds as f64, acc)
+    };
+    let (scalar_us, scalar_acc) = time(scalar::KERNELS.sum_f32_f64);
+    let (backend_us, backend_acc) = time(kernels().sum_f32_f64);
+    assert_eq!(scalar_acc.to_bits(), backend_acc.to_bits());
+    println!(
+        "sum 1M f32: scalar {scalar_us:.1} us, {} {backend_us:.1} us",
+        kernels().name
+    );
+}
+
+#[test]
+#[ignore = "benchmark; run with --release -- --ignored --nocapture"]
+fn bench_entropy_5k_genes() {
+    // 2000 cells with 5000 expressed genes each, as Stage 4 sees them.
+    let cells: Vec<Vec<f32>> = (0..2000u64)
+        .map(|c| random_values(c + 1, 5000).iter().map(|v| v.abs()).collect())
+        .collect();
+    let time = |f: fn(&[f32], f64) -> f64| {
+        let start = std::time::Instant::now();
+        let mut acc = 0f64;
+        for cell in &cells {
+            let sum = sum_f32_f64(cell);
+            acc += f(std::hint::black_box(cell), sum);
+        }
+        (start.elapsed().as_secs_f64() * 1e3, acc)
+    };
+    let (scalar_ms, scalar_acc) = time(scalar::KERNELS.entropy_f64);
+    let (backend_ms, backend_acc) = time(kernels().entropy_f64);
+    assert_eq!(scalar_acc.to_bits(), backend_acc.to_bits());
+    println!(
+        "entropy 2000 x 5000: scalar {scalar_ms:.1} ms, {} {backend_ms:.1} ms",
+        kernels().name
+    );
+}
+
+#[test]
+#[ignore = "benchmark; run with --release -- --ignored --nocapture"]
+fn bench_stats_panel_slices() {
+    fn separate(v: &[f32]) -> (f64, f32, usize) {
+        let max = v
+            .iter()
+            .fold(f32::NEG_INFINITY, |m, &x| if x > m { x } else { m });
+        let positive = v.iter().filter(|&&x| x > 0.0).count();
+        (sum_f32_f64(v), max, positive)
+    }
+    fn fused(v: &[f32]) -> (f64, f32, usize) {
+        stats_f32(v, SumMode::Blocked)
+    }
+    // Stage 4 slices are per-cell panel values: a handful of tf/program
+    // panels, up to a few hundred with custom panel files.
+    for width in [3usize, 24, 64, 256] {
+        let values = random_values(11, width * (4_000_000 / width));
+        // Best of five runs; single runs are noisy on shared hosts.
+        let time = |f: fn(&[f32]) -> (f64, f32, usize)| {
+            let mut best = f64::INFINITY;
+            let mut acc = 0f64;
+            for _ in 0..5 {
+                let start = std::time::Instant::now();
+                acc = 0.0;
+                for slice in values.chunks_exact(width) {
+                    let (sum, _, positive) = f(std::hint::black_box(slice));
+                    acc += sum + positive as f64;
+                }
+                best = best.min(start.elapsed().as_secs_f64() * 1e3);
+            }
+            (best, acc)
+        };
+        let (separate_ms, separate_acc) = time(separate);
+        let (fused_ms, fused_acc) = time(fused);
+        assert_eq!(separate_acc.to_bits(), fused_acc.to_bits());
+        println!(
+            "stats 4M values in slices of {width}: separate {separate_ms:.1} ms, fused {} {fused_ms:.1} ms",
+            kernels().name
+        );
+    }
 }
@@ -7,7 +7,50 @@ fn test_sum() {
 }
 
 #[test]
-fn test_max() {
+fn test_stats_short() {
     let v = [1.0f32, -1.0, 3.0];
-    assert_eq!(max_f32(&v), 3.0);
+    assert_eq!(stats_f32(&v), (3.0, 3.0, 2));
+}
+
+#[test]
+fn test_min() {
+    let v = [1.0f32, -1.0, 3.0];
+    assert_eq!(min_f32(&v), -1.0);
+    assert_eq!(min_f32(&[]), 0.0);
+}
+
+#[test]
+fn test_ln_f64_within_ulp_of_std() {
+    let mut x = 1e-90f64;
+    while x < 1e90 {
+        for scale in [1.0, 1.001, 1.414, 1.415, 1.9999] {
+            let v = x * scale;
+            let expected = v.ln();
+            let got = ln_f64(v);
+            assert!(
+                (got - expected).abs() <= f64::EPSILON * expected.abs(),
+                "ln({v}) = {got} vs {expected}"
+            );
+        }
+        x *= 7.3;
+    }
+    assert_eq!(ln_f64(1.0), 0.0);
+}
+
+#[test]
+fn test_entropy_uniform() {
+    let v = [2.0f32; 37];
+    let h = entropy_f64(&v, 74.0);
+    assert!((h - 37f64.ln()).abs() < 1e-12);
+    assert_eq!(entropy_f64(&[0.0, 3.0], 3.0).to_bits(), 0f64.to_bits());
+}
+
+#[test]
+fn test_stats_matches_separate_passes() {
+    let v: Vec<f32> = (0..50).map(|i| ((i * 7) % 11) as f32 - 4.0).collect();
+    let (sum, max, positive) = stats_f32(&v);
+    assert_eq!(sum.to_bits(), sum_f32_f64(&v).to_bits());
+    assert_eq!(max, 6.0);
+    assert_eq!(positive, v.iter().filter(|&&x| x > 0.0).count());
+    assert_eq!(stats_f32(&[]), (0.0, f32::NEG_INFINITY, 0));
 }